@@ -140,17 +140,95 @@ pub enum BlockEntityKind {
         #[serde(default)]
         record_item: InventorySlot,
     },
+    #[serde(rename = "minecraft:mob_spawner")]
+    #[serde(rename_all = "PascalCase")]
+    MobSpawner {
+        spawn_data: Option<SpawnerEntity>,
+        #[serde(default)]
+        spawn_potentials: Vec<SpawnPotential>,
+        #[serde(default = "default_spawner_delay")]
+        delay: i16,
+        #[serde(default = "default_min_spawn_delay")]
+        min_spawn_delay: i16,
+        #[serde(default = "default_max_spawn_delay")]
+        max_spawn_delay: i16,
+        #[serde(default = "default_spawn_count")]
+        spawn_count: i16,
+        #[serde(default = "default_max_nearby_entities")]
+        max_nearby_entities: i16,
+        #[serde(default = "default_required_player_range")]
+        required_player_range: i16,
+        #[serde(default = "default_spawn_range")]
+        spawn_range: i16,
+    },
     // TODO: a few more
     /// Fallback type for unknown block entities
     #[serde(other, serialize_with = "BlockEntityKind::serialize_unknown")]
     Unknown,
 }
 
+/// An entity spawned by a mob spawner.
+///
+/// Only the entity type is retained; other entity data is ignored.
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct SpawnerEntity {
+    pub id: String,
+}
+
+// Vanilla's defaults for mob spawners which omit their settings.
+fn default_spawner_delay() -> i16 {
+    20
+}
+fn default_min_spawn_delay() -> i16 {
+    200
+}
+fn default_max_spawn_delay() -> i16 {
+    800
+}
+fn default_spawn_count() -> i16 {
+    4
+}
+fn default_max_nearby_entities() -> i16 {
+    6
+}
+fn default_required_player_range() -> i16 {
+    16
+}
+fn default_spawn_range() -> i16 {
+    4
+}
+
+/// A weighted entry in a mob spawner's `SpawnPotentials`.
+#[derive(Debug, Clone, Serialize, Deserialize)]
+#[serde(rename_all = "PascalCase")]
+pub struct SpawnPotential {
+    pub entity: SpawnerEntity,
+    pub weight: i32,
+}
+
 impl BlockEntityKind {
     pub(crate) fn serialize_unknown<S: Serializer>(_serializer: S) -> Result<S::Ok, S::Error> {
         Err(S::Error::custom("cannot serialize unknown block entities"))
     }
 
+    /// Creates a mob spawner for the entity with the given
+    /// namespaced ID, using vanilla's default settings.
+    pub fn mob_spawner(entity_id: impl Into<String>) -> Self {
+        BlockEntityKind::MobSpawner {
+            spawn_data: Some(SpawnerEntity {
+                id: entity_id.into(),
+            }),
+            spawn_potentials: Vec::new(),
+            delay: default_spawner_delay(),
+            min_spawn_delay: default_min_spawn_delay(),
+            max_spawn_delay: default_max_spawn_delay(),
+            spawn_count: default_spawn_count(),
+            max_nearby_entities: default_max_nearby_entities(),
+            required_player_range: default_required_player_range(),
+            spawn_range: default_spawn_range(),
+        }
+    }
+
     pub fn variant(&self) -> BlockEntityVariant {
         match self {
             BlockEntityKind::Beacon { .. } => BlockEntityVariant::Beacon,
@@ -171,6 +249,7 @@ impl BlockEntityKind {
             BlockEntityKind::Hopper { .. } => BlockEntityVariant::Hopper,
             BlockEntityKind::Jigsaw { .. } => BlockEntityVariant::Jigsaw,
             BlockEntityKind::Jukebox { .. } => BlockEntityVariant::Jukebox,
+            BlockEntityKind::MobSpawner { .. } => BlockEntityVariant::MobSpawner,
             BlockEntityKind::Unknown { .. } => BlockEntityVariant::Unknown,
         }
     }
@@ -197,5 +276,6 @@ pub enum BlockEntityVariant {
     Hopper,
     Jigsaw,
     Jukebox,
+    MobSpawner,
     Unknown,
 }
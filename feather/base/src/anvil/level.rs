@@ -75,6 +75,11 @@ pub struct LevelData {
     pub generator_name: String,
     #[serde(rename = "generatorOptions")]
     pub generator_options: Option<SuperflatGeneratorOptions>,
+
+    /// Game rules, with their values stored as strings.
+    #[serde(default)]
+    #[serde(rename = "GameRules")]
+    pub game_rules: HashMap<String, String>,
 }
 
 impl LevelData {
@@ -203,5 +208,6 @@ mod tests {
         assert_eq!(level.thunder_time, 5252);
         assert_eq!(level.generator_name, "default");
         assert!(level.generator_options.is_none());
+        assert_eq!(level.game_rules["doMobSpawning"], "true");
     }
 }
@@ -11,11 +11,12 @@ use serde::{Deserialize, Serialize};
 use uuid::Uuid;
 
 use quill_common::components::{
-    CanBuild, CanCreativeFly, CreativeFlying, CreativeFlyingSpeed, Instabreak, Invulnerable,
-    WalkSpeed,
+    CanBuild, CanCreativeFly, CreativeFlying, CreativeFlyingSpeed, EffectInstance, Instabreak,
+    Invulnerable, WalkSpeed,
 };
 
 use crate::inventory::*;
+use crate::{Dimension, StatusEffect};
 
 use super::entity::{AnimalData, ItemNbt};
 
@@ -35,6 +36,25 @@ pub struct PlayerData {
     #[serde(rename = "SelectedItemSlot")]
     pub held_item: i32,
     pub abilities: PlayerAbilities,
+    #[serde(rename = "ActiveEffects")]
+    #[serde(default)]
+    pub active_effects: Vec<ActiveEffectData>,
+    #[serde(rename = "XpLevel")]
+    #[serde(default)]
+    pub xp_level: i32,
+    #[serde(rename = "XpP")]
+    #[serde(default)]
+    pub xp_progress: f32,
+    #[serde(rename = "XpTotal")]
+    #[serde(default)]
+    pub xp_total: i32,
+    #[serde(rename = "Dimension")]
+    #[serde(default = "default_dimension")]
+    pub dimension: Dimension,
+}
+
+fn default_dimension() -> Dimension {
+    Dimension::Overworld
 }
 
 /// Represents player's abilities (flying, invulnerability, speed, etc.)
@@ -55,6 +75,46 @@ pub struct PlayerAbilities {
     pub invulnerable: Invulnerable,
 }
 
+/// Represents a status effect applied to a player.
+#[derive(Debug, Clone, Copy, Serialize, Deserialize, PartialEq, Eq)]
+#[serde(rename_all = "PascalCase")]
+pub struct ActiveEffectData {
+    pub id: i8,
+    pub amplifier: i8,
+    pub duration: i32,
+    pub ambient: bool,
+    pub show_particles: bool,
+    pub show_icon: bool,
+}
+
+impl ActiveEffectData {
+    /// Converts this data into an [`EffectInstance`].
+    /// Returns `None` if the effect ID is unknown.
+    pub fn to_effect_instance(&self) -> Option<EffectInstance> {
+        Some(EffectInstance {
+            effect: StatusEffect::from_id(self.id as u8)?,
+            amplifier: self.amplifier as u8,
+            duration: self.duration.max(0) as u32,
+            ambient: self.ambient,
+            show_particles: self.show_particles,
+            show_icon: self.show_icon,
+        })
+    }
+}
+
+impl From<&EffectInstance> for ActiveEffectData {
+    fn from(instance: &EffectInstance) -> Self {
+        Self {
+            id: instance.effect.id() as i8,
+            amplifier: instance.amplifier as i8,
+            duration: instance.duration as i32,
+            ambient: instance.ambient,
+            show_particles: instance.show_particles,
+            show_icon: instance.show_icon,
+        }
+    }
+}
+
 /// Represents a single inventory slot (including position index).
 #[derive(Default, Debug, Clone, Serialize, Deserialize, PartialEq, Eq)]
 pub struct InventorySlot {
@@ -209,6 +269,19 @@ mod tests {
         );
         assert_eq!(player.inventory[0].item, "minecraft:diamond_shovel");
         assert_eq!(player.inventory[0].nbt, Some(ItemNbt { damage: Some(3) }));
+        assert_eq!(player.dimension, Dimension::Overworld);
+    }
+
+    #[test]
+    fn test_convert_active_effect() {
+        let instance = EffectInstance::new(StatusEffect::Regeneration, 2, 400);
+        let data = ActiveEffectData::from(&instance);
+        assert_eq!(data.id, 10);
+        assert_eq!(data.amplifier, 2);
+        assert_eq!(data.to_effect_instance(), Some(instance));
+
+        let unknown = ActiveEffectData { id: 100, ..data };
+        assert_eq!(unknown.to_effect_instance(), None);
     }
 
     #[test]
@@ -2,7 +2,10 @@
 //! of Anvil region files.
 
 use crate::{
-    chunk::{BlockStore, LightStore, PackedArray, Palette},
+    chunk::{
+        BlockStore, ChunkStructures, LightStore, PackedArray, Palette, StructureBox,
+        StructurePiece, StructureStart,
+    },
     Chunk, ChunkPosition, ChunkSection,
 };
 
@@ -77,6 +80,58 @@ pub struct ChunkLevel {
     #[serde(rename = "Status")]
     #[serde(default)]
     worldgen_status: Cow<'static, str>,
+    #[serde(default)]
+    structures: LevelStructures,
+}
+
+/// Represents the structures starting in or
+/// referenced by a chunk in a region file.
+#[derive(Serialize, Deserialize, Debug, Default)]
+#[serde(rename_all = "PascalCase")]
+pub struct LevelStructures {
+    #[serde(default)]
+    starts: BTreeMap<String, LevelStructureStart>,
+    /// The start chunks of each structure with pieces in this
+    /// chunk, packed as in `ChunkPos.asLong` of vanilla.
+    #[serde(default)]
+    references: BTreeMap<String, LevelStructureReferences>,
+}
+
+/// Represents the packed start chunks of a structure.
+#[derive(Serialize, Deserialize, Debug)]
+pub struct LevelStructureReferences(#[serde(serialize_with = "nbt::i64_array")] Vec<i64>);
+
+/// Represents a structure start in a region file.
+#[derive(Serialize, Deserialize, Debug)]
+pub struct LevelStructureStart {
+    /// The structure ID, or `INVALID` if no
+    /// structure of this type starts in the chunk.
+    id: String,
+    #[serde(rename = "ChunkX")]
+    #[serde(default)]
+    chunk_x: i32,
+    #[serde(rename = "ChunkZ")]
+    #[serde(default)]
+    chunk_z: i32,
+    #[serde(default)]
+    references: i32,
+    #[serde(rename = "BB", serialize_with = "nbt::i32_array")]
+    #[serde(default)]
+    bounding_box: Vec<i32>,
+    #[serde(rename = "Children")]
+    #[serde(default)]
+    pieces: Vec<LevelStructurePiece>,
+}
+
+/// Represents a structure piece in a region file.
+#[derive(Serialize, Deserialize, Debug)]
+pub struct LevelStructurePiece {
+    id: String,
+    #[serde(rename = "BB", serialize_with = "nbt::i32_array")]
+    bounding_box: Vec<i32>,
+    /// Data specific to the type of piece.
+    #[serde(flatten)]
+    data: BTreeMap<String, nbt::Value>,
 }
 
 /// Represents a chunk section in a region file.
@@ -238,6 +293,8 @@ impl RegionHandle {
                 Biome::from_id(id as u32).ok_or(Error::InvalidBiomeId(id))?;
         }
 
+        *chunk.structures_mut() = read_structures(&level.structures)?;
+
         // chunk.recalculate_heightmap();
 
         Ok((chunk, level.entities.clone(), level.block_entities.clone()))
@@ -449,11 +506,99 @@ fn chunk_to_chunk_root(
             scheduled_liquid_updates: vec![],
             post_processing: vec![vec![]; 16],
             worldgen_status: "postprocessed".into(),
+            structures: structures_to_level_structures(chunk.structures()),
         },
         data_version: DATA_VERSION,
     }
 }
 
+/// Packs a chunk position into a long, as vanilla does.
+fn pack_chunk_position(pos: ChunkPosition) -> i64 {
+    (pos.x as u32 as i64) | ((pos.z as u32 as i64) << 32)
+}
+
+fn unpack_chunk_position(packed: i64) -> ChunkPosition {
+    ChunkPosition::new(packed as i32, (packed >> 32) as i32)
+}
+
+fn read_structures(structures: &LevelStructures) -> Result<ChunkStructures, Error> {
+    let mut chunk_structures = ChunkStructures::new();
+    for (name, start) in &structures.starts {
+        if start.id == "INVALID" {
+            continue;
+        }
+        let pieces = start
+            .pieces
+            .iter()
+            .map(|piece| {
+                Ok(StructurePiece {
+                    id: piece.id.clone(),
+                    bounding_box: StructureBox::from_array(&piece.bounding_box)
+                        .ok_or(Error::InvalidBoundingBox)?,
+                    data: piece.data.clone(),
+                })
+            })
+            .collect::<Result<_, Error>>()?;
+        chunk_structures.set_start(
+            name.clone(),
+            StructureStart {
+                id: start.id.clone(),
+                chunk: ChunkPosition::new(start.chunk_x, start.chunk_z),
+                bounding_box: StructureBox::from_array(&start.bounding_box)
+                    .ok_or(Error::InvalidBoundingBox)?,
+                pieces,
+                references: start.references,
+            },
+        );
+    }
+    for (name, references) in &structures.references {
+        for packed in &references.0 {
+            chunk_structures.add_reference(name.clone(), unpack_chunk_position(*packed));
+        }
+    }
+    Ok(chunk_structures)
+}
+
+fn structures_to_level_structures(structures: &ChunkStructures) -> LevelStructures {
+    LevelStructures {
+        starts: structures
+            .starts()
+            .map(|(name, start)| {
+                (
+                    name.to_owned(),
+                    LevelStructureStart {
+                        id: start.id.clone(),
+                        chunk_x: start.chunk.x,
+                        chunk_z: start.chunk.z,
+                        references: start.references,
+                        bounding_box: start.bounding_box.to_array().to_vec(),
+                        pieces: start
+                            .pieces
+                            .iter()
+                            .map(|piece| LevelStructurePiece {
+                                id: piece.id.clone(),
+                                bounding_box: piece.bounding_box.to_array().to_vec(),
+                                data: piece.data.clone(),
+                            })
+                            .collect(),
+                    },
+                )
+            })
+            .collect(),
+        references: structures
+            .all_references()
+            .map(|(name, starts)| {
+                (
+                    name.to_owned(),
+                    LevelStructureReferences(
+                        starts.iter().copied().map(pack_chunk_position).collect(),
+                    ),
+                )
+            })
+            .collect(),
+    }
+}
+
 fn convert_palette(section: &mut ChunkSection) -> Vec<LevelPaletteEntry> {
     raw_palette_to_palette_entries(section.blocks().palette().unwrap().as_slice())
 }
@@ -611,6 +756,8 @@ pub enum Error {
     IndexOutOfBounds,
     /// Invalid biome ID
     InvalidBiomeId(i32),
+    /// A structure bounding box did not have six coordinates
+    InvalidBoundingBox,
 }
 
 impl Display for Error {
@@ -632,6 +779,7 @@ impl Display for Error {
             Error::MissingRootTag => f.write_str("Chunk is missing a root NBT tag")?,
             Error::IndexOutOfBounds => f.write_str("Section index out of bounds")?,
             Error::InvalidBiomeId(id) => write!(f, "Invalid biome ID {}", id)?,
+            Error::InvalidBoundingBox => f.write_str("Invalid structure bounding box")?,
         }
 
         Ok(())
@@ -921,4 +1069,30 @@ mod tests {
             }
         );
     }
+
+    #[test]
+    fn structures_roundtrip() {
+        let mut structures = ChunkStructures::new();
+        let bounding_box = StructureBox::from_array(&[-40, 60, 20, -20, 80, 45]).unwrap();
+        structures.set_start(
+            "desert_pyramid",
+            StructureStart {
+                id: "minecraft:desert_pyramid".to_owned(),
+                chunk: ChunkPosition::new(-3, 1),
+                bounding_box,
+                pieces: vec![StructurePiece {
+                    id: "TeDP".to_owned(),
+                    bounding_box,
+                    data: iter::once(("O".to_owned(), nbt::Value::Int(2))).collect(),
+                }],
+                references: 0,
+            },
+        );
+        structures.add_reference("desert_pyramid", ChunkPosition::new(-3, 1));
+        structures.add_reference("village", ChunkPosition::new(7, -12));
+
+        let level = structures_to_level_structures(&structures);
+        assert_eq!(level.references["village"].0, vec![7 | (-12i64 << 32)]);
+        assert_eq!(read_structures(&level).unwrap(), structures);
+    }
 }
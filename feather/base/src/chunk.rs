@@ -44,6 +44,7 @@ mod heightmap;
 mod light;
 mod packed_array;
 mod palette;
+mod structures;
 
 pub use self::blocks::BlockStore;
 pub use biome_store::BiomeStore;
@@ -51,6 +52,7 @@ pub use heightmap::{Heightmap, HeightmapFunction, HeightmapStore};
 pub use light::LightStore;
 pub use packed_array::PackedArray;
 pub use palette::Palette;
+pub use structures::{ChunkStructures, StructureBox, StructurePiece, StructureStart};
 
 /// A 16x256x16 chunk of blocks plus associated
 /// light, biome, and heightmap data.
@@ -63,6 +65,8 @@ pub struct Chunk {
 
     heightmaps: HeightmapStore,
 
+    structures: ChunkStructures,
+
     position: ChunkPosition,
 }
 
@@ -77,6 +81,7 @@ impl Default for Chunk {
             biomes: BiomeStore::default(),
             position: ChunkPosition::new(0, 0),
             heightmaps: HeightmapStore::new(),
+            structures: ChunkStructures::new(),
         }
     }
 }
@@ -253,6 +258,16 @@ impl Chunk {
         &mut self.heightmaps
     }
 
+    /// Gets the [`ChunkStructures`] for this chunk.
+    pub fn structures(&self) -> &ChunkStructures {
+        &self.structures
+    }
+
+    /// Mutably gets the [`ChunkStructures`] for this chunk.
+    pub fn structures_mut(&mut self) -> &mut ChunkStructures {
+        &mut self.structures
+    }
+
     /// Gets the chunk section at index `y`.
     pub fn section(&self, y: isize) -> Option<&ChunkSection> {
         self.sections.get((y + 1) as usize)?.as_ref()
@@ -0,0 +1,218 @@
+use std::collections::BTreeMap;
+
+use crate::{ChunkPosition, CHUNK_HEIGHT, CHUNK_WIDTH};
+
+/// Stores the structures which start in a chunk
+/// and the structures whose pieces reach into it.
+///
+/// Structures are keyed by their name, e.g. `village`.
+#[derive(Debug, Clone, Default, PartialEq)]
+pub struct ChunkStructures {
+    starts: BTreeMap<String, StructureStart>,
+    references: BTreeMap<String, Vec<ChunkPosition>>,
+}
+
+impl ChunkStructures {
+    /// Creates an empty `ChunkStructures`.
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Gets the structure starting in this chunk with the given name.
+    pub fn start(&self, name: &str) -> Option<&StructureStart> {
+        self.starts.get(name)
+    }
+
+    /// Returns an iterator over the structures starting
+    /// in this chunk, along with their names.
+    pub fn starts(&self) -> impl Iterator<Item = (&str, &StructureStart)> + '_ {
+        self.starts
+            .iter()
+            .map(|(name, start)| (name.as_str(), start))
+    }
+
+    /// Sets the structure starting in this chunk with the given name.
+    pub fn set_start(&mut self, name: impl Into<String>, start: StructureStart) {
+        self.starts.insert(name.into(), start);
+    }
+
+    /// Gets the start chunks of the structures with the
+    /// given name which have pieces in this chunk.
+    pub fn references(&self, name: &str) -> &[ChunkPosition] {
+        self.references.get(name).map_or(&[], Vec::as_slice)
+    }
+
+    /// Returns an iterator over the referenced start
+    /// chunks of each structure name.
+    pub fn all_references(&self) -> impl Iterator<Item = (&str, &[ChunkPosition])> + '_ {
+        self.references
+            .iter()
+            .map(|(name, starts)| (name.as_str(), starts.as_slice()))
+    }
+
+    /// Records that the structure with the given name starting
+    /// at `start` has pieces in this chunk.
+    pub fn add_reference(&mut self, name: impl Into<String>, start: ChunkPosition) {
+        let starts = self.references.entry(name.into()).or_default();
+        if !starts.contains(&start) {
+            starts.push(start);
+        }
+    }
+
+    /// Returns whether the chunk has no structure starts or references.
+    pub fn is_empty(&self) -> bool {
+        self.starts.is_empty() && self.references.is_empty()
+    }
+}
+
+/// A structure which starts in a chunk, made up of pieces
+/// which may extend into neighboring chunks.
+#[derive(Debug, Clone, PartialEq)]
+pub struct StructureStart {
+    /// The ID of the structure, e.g. `minecraft:village`.
+    pub id: String,
+    pub chunk: ChunkPosition,
+    /// The bounding box enclosing all pieces.
+    pub bounding_box: StructureBox,
+    pub pieces: Vec<StructurePiece>,
+    /// The number of chunks referencing this start.
+    pub references: i32,
+}
+
+/// A piece of a structure.
+#[derive(Debug, Clone, PartialEq)]
+pub struct StructurePiece {
+    /// The type of the piece, as in vanilla, e.g. `TeDP`
+    /// for a desert pyramid or `minecraft:jigsaw`.
+    pub id: String,
+    pub bounding_box: StructureBox,
+    /// Any other data of the piece, such as its orientation,
+    /// stored as in the vanilla piece NBT.
+    pub data: BTreeMap<String, nbt::Value>,
+}
+
+/// An inclusive box of block positions.
+#[derive(Debug, Copy, Clone, PartialEq, Eq, Hash)]
+pub struct StructureBox {
+    pub min_x: i32,
+    pub min_y: i32,
+    pub min_z: i32,
+    pub max_x: i32,
+    pub max_y: i32,
+    pub max_z: i32,
+}
+
+impl StructureBox {
+    /// Creates a box from the vanilla representation
+    /// `[min_x, min_y, min_z, max_x, max_y, max_z]`.
+    ///
+    /// Returns `None` if `array` does not have six elements.
+    pub fn from_array(array: &[i32]) -> Option<Self> {
+        match *array {
+            [min_x, min_y, min_z, max_x, max_y, max_z] => Some(Self {
+                min_x,
+                min_y,
+                min_z,
+                max_x,
+                max_y,
+                max_z,
+            }),
+            _ => None,
+        }
+    }
+
+    /// Converts the box to the vanilla representation.
+    pub fn to_array(self) -> [i32; 6] {
+        [
+            self.min_x, self.min_y, self.min_z, self.max_x, self.max_y, self.max_z,
+        ]
+    }
+
+    /// Returns the box covering the whole column of the given chunk.
+    pub fn for_chunk(chunk: ChunkPosition) -> Self {
+        Self {
+            min_x: chunk.x * CHUNK_WIDTH as i32,
+            min_y: 0,
+            min_z: chunk.z * CHUNK_WIDTH as i32,
+            max_x: chunk.x * CHUNK_WIDTH as i32 + CHUNK_WIDTH as i32 - 1,
+            max_y: CHUNK_HEIGHT as i32 - 1,
+            max_z: chunk.z * CHUNK_WIDTH as i32 + CHUNK_WIDTH as i32 - 1,
+        }
+    }
+
+    /// Returns whether the two boxes share any position.
+    pub fn intersects(&self, other: &StructureBox) -> bool {
+        self.max_x >= other.min_x
+            && self.min_x <= other.max_x
+            && self.max_y >= other.min_y
+            && self.min_y <= other.max_y
+            && self.max_z >= other.min_z
+            && self.min_z <= other.max_z
+    }
+
+    /// Returns whether the box contains the given position.
+    pub fn contains(&self, x: i32, y: i32, z: i32) -> bool {
+        (self.min_x..=self.max_x).contains(&x)
+            && (self.min_y..=self.max_y).contains(&y)
+            && (self.min_z..=self.max_z).contains(&z)
+    }
+
+    /// Grows the box to also enclose `other`.
+    pub fn expand(&mut self, other: &StructureBox) {
+        self.min_x = self.min_x.min(other.min_x);
+        self.min_y = self.min_y.min(other.min_y);
+        self.min_z = self.min_z.min(other.min_z);
+        self.max_x = self.max_x.max(other.max_x);
+        self.max_y = self.max_y.max(other.max_y);
+        self.max_z = self.max_z.max(other.max_z);
+    }
+
+    /// Moves the box by the given offset.
+    pub fn offset(&mut self, x: i32, y: i32, z: i32) {
+        self.min_x += x;
+        self.min_y += y;
+        self.min_z += z;
+        self.max_x += x;
+        self.max_y += y;
+        self.max_z += z;
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn references_are_deduplicated() {
+        let mut structures = ChunkStructures::new();
+        assert!(structures.is_empty());
+        structures.add_reference("village", ChunkPosition::new(3, -2));
+        structures.add_reference("village", ChunkPosition::new(3, -2));
+        structures.add_reference("village", ChunkPosition::new(4, -2));
+        assert_eq!(
+            structures.references("village"),
+            &[ChunkPosition::new(3, -2), ChunkPosition::new(4, -2)]
+        );
+        assert!(structures.references("mineshaft").is_empty());
+    }
+
+    #[test]
+    fn box_array_roundtrip() {
+        let array = [1, 2, 3, 4, 5, 6];
+        let bounding_box = StructureBox::from_array(&array).unwrap();
+        assert_eq!(bounding_box.to_array(), array);
+        assert_eq!(StructureBox::from_array(&[1, 2]), None);
+    }
+
+    #[test]
+    fn box_intersection() {
+        let chunk = StructureBox::for_chunk(ChunkPosition::new(-1, 2));
+        assert_eq!(chunk.to_array(), [-16, 0, 32, -1, 255, 47]);
+
+        let mut piece = StructureBox::from_array(&[-20, 60, 40, -17, 70, 50]).unwrap();
+        assert!(!piece.intersects(&chunk));
+        piece.offset(4, 0, 0);
+        assert!(piece.intersects(&chunk));
+        assert!(piece.contains(-16, 60, 50));
+    }
+}
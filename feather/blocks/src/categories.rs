@@ -1,4 +1,4 @@
-use crate::{BlockId, BlockKind};
+use crate::{BlockId, BlockKind, HalfTopBottom, SlabKind};
 use libcraft_blocks::SimplifiedBlockKind;
 
 #[derive(Copy, Clone, Debug, Eq, PartialEq)]
@@ -211,4 +211,57 @@ impl BlockId {
     pub fn is_full_block(self) -> bool {
         self.kind().solid()
     }
+
+    /// Returns the height of the top of this block's collision box,
+    /// measured from the bottom of the block. Blocks without a
+    /// collision box have a height of 0.
+    ///
+    /// Fences and walls are 1.5 blocks high, so mobs can't jump over them.
+    pub fn collision_height(self) -> f64 {
+        if !self.is_solid() {
+            return 0.0;
+        }
+
+        // Walls are the only blocks with this property
+        if self.east_nlt().is_some() {
+            return 1.5;
+        }
+
+        match self.simplified_kind() {
+            SimplifiedBlockKind::Fence => 1.5,
+            SimplifiedBlockKind::FenceGate => {
+                if self.open() == Some(true) {
+                    0.0
+                } else {
+                    1.5
+                }
+            }
+            SimplifiedBlockKind::Slab => match self.slab_kind() {
+                Some(SlabKind::Bottom) => 0.5,
+                _ => 1.0,
+            },
+            SimplifiedBlockKind::Snow => (self.layers().unwrap_or(1) - 1) as f64 / 8.0,
+            SimplifiedBlockKind::Carpet => 1.0 / 16.0,
+            SimplifiedBlockKind::LilyPad => 1.5 / 16.0,
+            SimplifiedBlockKind::WoodenTrapdoor
+            | SimplifiedBlockKind::IronTrapdoor
+            | SimplifiedBlockKind::CrimsonTrapdoor
+            | SimplifiedBlockKind::WarpedTrapdoor => {
+                if self.open() == Some(false)
+                    && self.half_top_bottom() == Some(HalfTopBottom::Bottom)
+                {
+                    3.0 / 16.0
+                } else {
+                    1.0
+                }
+            }
+            SimplifiedBlockKind::Cake => 0.5,
+            SimplifiedBlockKind::Bed => 9.0 / 16.0,
+            SimplifiedBlockKind::SoulSand => 14.0 / 16.0,
+            SimplifiedBlockKind::Farmland
+            | SimplifiedBlockKind::GrassPath
+            | SimplifiedBlockKind::HoneyBlock => 15.0 / 16.0,
+            _ => 1.0,
+        }
+    }
 }
@@ -170,6 +170,29 @@ mod tests {
         assert_eq!(block.instrument(), Some(Instrument::Basedrum));
     }
 
+    #[test]
+    fn collision_heights() {
+        assert_eq!(BlockId::air().collision_height(), 0.0);
+        assert_eq!(BlockId::stone().collision_height(), 1.0);
+        assert_eq!(BlockId::oak_fence().collision_height(), 1.5);
+        assert_eq!(BlockId::cobblestone_wall().collision_height(), 1.5);
+        assert_eq!(
+            BlockId::oak_slab()
+                .with_slab_kind(SlabKind::Bottom)
+                .collision_height(),
+            0.5
+        );
+        assert_eq!(
+            BlockId::oak_slab()
+                .with_slab_kind(SlabKind::Top)
+                .collision_height(),
+            1.0
+        );
+        assert_eq!(BlockId::snow().with_layers(1).collision_height(), 0.0);
+        assert_eq!(BlockId::snow().with_layers(3).collision_height(), 0.25);
+        assert_eq!(BlockId::white_carpet().collision_height(), 1.0 / 16.0);
+    }
+
     #[test]
     fn highest_id() {
         assert_eq!(
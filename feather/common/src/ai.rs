@@ -0,0 +1,694 @@
+//! Mob AI based on prioritized goals.
+//!
+//! Mobs with a [`MobGoals`] component pursue the highest-priority
+//! goal that is currently able to run. Goals that move the
+//! mob compute a [`Path`](pathfinding::Path) through loaded chunks
+//! and walk along it one waypoint per step. Mobs fall when there
+//! is no ground below them and attack by dealing damage through
+//! [`damage`](crate::damage).
+
+use ahash::AHashMap;
+use base::{BlockPosition, EntityKind, Position, Vec3d};
+use ecs::{Entity, SysResult, SystemExecutor};
+use quill_common::{
+    components::{ActiveEffects, Goal, Invulnerable, MobGoals},
+    entities::Player,
+};
+use rand::Rng;
+
+use crate::{damage, damage::Dead, effects, Game, World, WorldId};
+
+pub mod pathfinding;
+
+use pathfinding::Path;
+
+/// Number of ticks between path recomputations
+/// when following a moving target.
+const REPATH_INTERVAL: u32 = 20;
+
+/// Maximum number of blocks explored when computing a path.
+const MAX_PATH_NODES: usize = 512;
+
+/// Horizontal radius in which wandering mobs pick their destination.
+const WANDER_RADIUS: i32 = 10;
+
+/// Distance a fleeing mob tries to put between itself and the player.
+const FLEE_DISTANCE: f64 = 8.0;
+
+/// Chance per tick that an idle mob starts wandering.
+const WANDER_CHANCE: f64 = 1.0 / 120.0;
+
+/// Distance at which a following mob stops approaching its target.
+const FOLLOW_STOP_DISTANCE: f64 = 2.5;
+
+/// Blocks a mob falls per tick when there is no ground below it.
+const FALL_SPEED: f64 = 0.5;
+
+/// Height of the eyes of players above their feet.
+const PLAYER_EYE_HEIGHT: f64 = 1.62;
+
+/// Distance between the points checked along a line of sight.
+const LINE_OF_SIGHT_STEP: f64 = 0.25;
+
+pub fn register(systems: &mut SystemExecutor<Game>) {
+    systems
+        .add_system(add_ai_state)
+        .add_system(select_goals)
+        .add_system(run_goals);
+}
+
+/// Default goals of hostile mobs that attack in melee.
+pub fn melee_mob_goals() -> MobGoals {
+    MobGoals::new()
+        .with(
+            0,
+            Goal::MeleeAttack {
+                speed: 0.23,
+                range: 35.0,
+                reach: 2.0,
+                damage: 3.0,
+                cooldown_ticks: 20,
+            },
+        )
+        .with(1, Goal::LookAtPlayer { range: 8.0 })
+        .with(2, Goal::Wander { speed: 0.15 })
+}
+
+/// Default goals of hostile mobs that attack from a distance.
+pub fn ranged_mob_goals() -> MobGoals {
+    MobGoals::new()
+        .with(
+            0,
+            Goal::RangedAttack {
+                range: 15.0,
+                damage: 2.0,
+                cooldown_ticks: 40,
+            },
+        )
+        .with(1, Goal::LookAtPlayer { range: 8.0 })
+        .with(2, Goal::Wander { speed: 0.15 })
+}
+
+/// Default goals of passive animals.
+pub fn passive_mob_goals() -> MobGoals {
+    MobGoals::new()
+        .with(0, Goal::LookAtPlayer { range: 6.0 })
+        .with(1, Goal::Wander { speed: 0.1 })
+}
+
+/// Default goals of animals that run away from players.
+pub fn skittish_mob_goals() -> MobGoals {
+    MobGoals::new()
+        .with(
+            0,
+            Goal::FleePlayer {
+                speed: 0.25,
+                range: 6.0,
+            },
+        )
+        .with(1, Goal::Wander { speed: 0.1 })
+}
+
+/// Runtime state of a mob's AI.
+///
+/// Added automatically to entities with a [`MobGoals`] component.
+#[derive(Debug, Default)]
+pub struct AiState {
+    /// Index of the running goal within `MobGoals`.
+    active_goal: Option<usize>,
+    /// The player targeted by the running goal.
+    target: Option<Entity>,
+    path: Option<Path>,
+    ticks_until_repath: u32,
+    attack_cooldown: u32,
+}
+
+impl AiState {
+    /// Returns the goal the mob is currently pursuing.
+    pub fn active_goal(&self, goals: &MobGoals) -> Option<Goal> {
+        self.active_goal.and_then(|index| goals.get(index))
+    }
+
+    /// Returns the player targeted by the current goal.
+    pub fn target(&self) -> Option<Entity> {
+        self.target
+    }
+
+    /// Returns the path the mob is currently walking along.
+    pub fn path(&self) -> Option<&Path> {
+        self.path.as_ref()
+    }
+
+    fn switch_goal(&mut self, goal: Option<usize>, target: Option<Entity>) {
+        if self.active_goal != goal || self.target != target {
+            self.path = None;
+            self.ticks_until_repath = 0;
+        }
+        self.active_goal = goal;
+        self.target = target;
+    }
+}
+
+/// A player that mobs can observe.
+#[derive(Copy, Clone, Debug)]
+struct PlayerInfo {
+    entity: Entity,
+    position: Position,
+    attackable: bool,
+}
+
+/// Returns the players mobs can observe, grouped by world.
+fn nearby_players(game: &Game) -> AHashMap<WorldId, Vec<PlayerInfo>> {
+    let mut players: AHashMap<WorldId, Vec<PlayerInfo>> = AHashMap::new();
+    for (entity, (&world, &position, _, invulnerable, dead)) in game
+        .ecs
+        .query::<(
+            &WorldId,
+            &Position,
+            &Player,
+            Option<&Invulnerable>,
+            Option<&Dead>,
+        )>()
+        .iter()
+    {
+        players.entry(world).or_default().push(PlayerInfo {
+            entity,
+            position,
+            attackable: !invulnerable.map(|i| i.0).unwrap_or(false) && dead.is_none(),
+        });
+    }
+    players
+}
+
+/// Returns the players in `world`.
+fn players_in(players: &AHashMap<WorldId, Vec<PlayerInfo>>, world: WorldId) -> &[PlayerInfo] {
+    players.get(&world).map_or(&[], Vec::as_slice)
+}
+
+fn nearest_player(
+    players: &[PlayerInfo],
+    position: Position,
+    range: f64,
+    require_attackable: bool,
+) -> Option<PlayerInfo> {
+    players
+        .iter()
+        .filter(|player| player.attackable || !require_attackable)
+        .map(|player| (player, player.position.distance_squared_to(position)))
+        .filter(|(_, distance)| *distance <= range * range)
+        .min_by(|(_, a), (_, b)| a.partial_cmp(b).unwrap())
+        .map(|(player, _)| *player)
+}
+
+/// Adds an `AiState` to entities that have been given goals.
+fn add_ai_state(game: &mut Game) -> SysResult {
+    let mut missing = Vec::new();
+    for (entity, _) in game.ecs.query::<&MobGoals>().iter() {
+        if game.ecs.get::<AiState>(entity).is_err() {
+            missing.push(entity);
+        }
+    }
+    for entity in missing {
+        game.ecs.insert(entity, AiState::default())?;
+    }
+    Ok(())
+}
+
+/// Picks the goal each mob should pursue this tick.
+fn select_goals(game: &mut Game) -> SysResult {
+    let players = nearby_players(game);
+    for (_, (goals, state, &world, &position)) in game
+        .ecs
+        .query::<(&MobGoals, &mut AiState, &WorldId, &Position)>()
+        .iter()
+    {
+        let players = players_in(&players, world);
+        let mut selected = None;
+        for (index, goal) in goals.iter().enumerate() {
+            if let Some(target) = can_run(goal, state, position, players) {
+                selected = Some((index, target));
+                break;
+            }
+        }
+
+        match selected {
+            Some((index, target)) => state.switch_goal(Some(index), target),
+            None => state.switch_goal(None, None),
+        }
+    }
+    Ok(())
+}
+
+/// Determines whether `goal` can run. Returns the
+/// player the goal would target, if any.
+fn can_run(
+    goal: Goal,
+    state: &AiState,
+    position: Position,
+    players: &[PlayerInfo],
+) -> Option<Option<Entity>> {
+    match goal {
+        Goal::Wander { .. } => Some(None),
+        Goal::LookAtPlayer { range } => {
+            nearest_player(players, position, range, false).map(|p| Some(p.entity))
+        }
+        Goal::FollowPlayer { range, .. } => nearest_player(players, position, range, false)
+            .filter(|p| {
+                // Keep following an already targeted player
+                // so the mob doesn't stop and go at the boundary.
+                state.target == Some(p.entity)
+                    || p.position.distance_to(position) > FOLLOW_STOP_DISTANCE
+            })
+            .map(|p| Some(p.entity)),
+        Goal::FleePlayer { range, .. } => {
+            nearest_player(players, position, range, false).map(|p| Some(p.entity))
+        }
+        Goal::MeleeAttack { range, .. } | Goal::RangedAttack { range, .. } => {
+            nearest_player(players, position, range, true).map(|p| Some(p.entity))
+        }
+    }
+}
+
+/// An attack performed by a mob on a player.
+struct Attack {
+    target: Entity,
+    damage: f32,
+}
+
+/// Executes the active goal of each mob.
+fn run_goals(game: &mut Game) -> SysResult {
+    let players = nearby_players(game);
+    let mut attacks = Vec::new();
+
+    for (_, (goals, state, &world_id, position, kind, effects)) in game
+        .ecs
+        .query::<(
+            &MobGoals,
+            &mut AiState,
+            &WorldId,
+            &mut Position,
+            Option<&EntityKind>,
+            Option<&ActiveEffects>,
+        )>()
+        .iter()
+    {
+        let world = &game.worlds[world_id];
+        let players = players_in(&players, world_id);
+        state.attack_cooldown = state.attack_cooldown.saturating_sub(1);
+        state.ticks_until_repath = state.ticks_until_repath.saturating_sub(1);
+
+        let goal = match state.active_goal(goals) {
+            Some(goal) => goal,
+            None => continue,
+        };
+        let target = state
+            .target
+            .and_then(|target| players.iter().find(|p| p.entity == target))
+            .copied();
+        let height = kind.map(|kind| mob_height(*kind)).unwrap_or(2);
+        let eye_height = kind.map_or(PLAYER_EYE_HEIGHT, |kind| mob_eye_height(*kind));
+        let speed_multiplier = effects.map_or(1.0, effects::movement_speed_multiplier);
+
+        match goal {
+            Goal::Wander { speed } => {
+                if state.path.is_none() && rand::thread_rng().gen_bool(WANDER_CHANCE) {
+                    let destination = random_nearby_block(*position);
+                    state.path = pathfinding::find_path(
+                        world,
+                        position.block(),
+                        destination,
+                        height,
+                        MAX_PATH_NODES,
+                    );
+                }
+                walk_along_path(state, position, speed * speed_multiplier);
+            }
+            Goal::LookAtPlayer { .. } => {
+                if let Some(target) = target {
+                    look_at(position, target.position);
+                }
+            }
+            Goal::FollowPlayer { speed, .. } => {
+                if let Some(target) = target {
+                    if position.distance_to(target.position) > FOLLOW_STOP_DISTANCE {
+                        repath_if_needed(world, state, *position, target.position.block(), height);
+                        walk_along_path(state, position, speed * speed_multiplier);
+                    } else {
+                        state.path = None;
+                    }
+                    look_at(position, target.position);
+                }
+            }
+            Goal::FleePlayer { speed, .. } => {
+                if let Some(target) = target {
+                    if state.path.is_none() {
+                        let destination = flee_destination(*position, target.position);
+                        state.path = pathfinding::find_path(
+                            world,
+                            position.block(),
+                            destination,
+                            height,
+                            MAX_PATH_NODES,
+                        );
+                    }
+                    walk_along_path(state, position, speed * speed_multiplier);
+                }
+            }
+            Goal::MeleeAttack {
+                speed,
+                reach,
+                damage,
+                cooldown_ticks,
+                ..
+            } => {
+                if let Some(target) = target {
+                    if position.distance_to(target.position) <= reach {
+                        state.path = None;
+                        if state.attack_cooldown == 0 {
+                            attacks.push(Attack {
+                                target: target.entity,
+                                damage: effects.map_or(damage, |effects| {
+                                    effects::attack_damage(effects, damage)
+                                }),
+                            });
+                            state.attack_cooldown = cooldown_ticks;
+                        }
+                    } else {
+                        repath_if_needed(world, state, *position, target.position.block(), height);
+                        walk_along_path(state, position, speed * speed_multiplier);
+                    }
+                    look_at(position, target.position);
+                }
+            }
+            Goal::RangedAttack {
+                damage,
+                cooldown_ticks,
+                ..
+            } => {
+                if let Some(target) = target {
+                    look_at(position, target.position);
+                    let eyes = position.vec() + Vec3d::new(0.0, eye_height, 0.0);
+                    let target_eyes =
+                        target.position.vec() + Vec3d::new(0.0, PLAYER_EYE_HEIGHT, 0.0);
+                    if state.attack_cooldown == 0 && has_line_of_sight(world, eyes, target_eyes) {
+                        attacks.push(Attack {
+                            target: target.entity,
+                            damage,
+                        });
+                        state.attack_cooldown = cooldown_ticks;
+                    }
+                }
+            }
+        }
+
+        apply_gravity(world, position);
+    }
+
+    for attack in attacks {
+        damage::damage(game, attack.target, attack.damage)?;
+    }
+
+    Ok(())
+}
+
+/// Returns the number of blocks of headroom a mob needs.
+fn mob_height(kind: EntityKind) -> u32 {
+    kind.bounding_box().max.y.ceil().max(1.0) as u32
+}
+
+/// Returns the height of the eyes of a mob above its feet.
+fn mob_eye_height(kind: EntityKind) -> f64 {
+    kind.bounding_box().max.y * 0.85
+}
+
+/// Returns whether no solid or unloaded block lies between `from` and `to`.
+fn has_line_of_sight(world: &World, from: Vec3d, to: Vec3d) -> bool {
+    let delta = to - from;
+    let steps = (delta.magnitude() / LINE_OF_SIGHT_STEP).ceil() as usize;
+    (1..steps).all(|step| {
+        let point = from + delta * (step as f64 / steps as f64);
+        matches!(
+            pathfinding::block_at(world, Position::from(point).block()),
+            Some(block) if !block.is_solid()
+        )
+    })
+}
+
+/// Lets a mob climb onto a block it walked into and
+/// otherwise fall until it stands on the ground.
+fn apply_gravity(world: &World, position: &mut Position) {
+    let feet = position.block();
+    let collision_top =
+        |pos| pathfinding::block_at(world, pos).map(|block| block.collision_height());
+    // Unloaded blocks are treated as ground so mobs
+    // don't fall through chunks which aren't loaded yet.
+    let floor = match (collision_top(feet), collision_top(feet.down())) {
+        (Some(top), _) if top > 0.0 => feet.y as f64 + top,
+        (_, None) => feet.y as f64,
+        (_, Some(top)) if top > 0.0 => feet.y as f64 - 1.0 + top,
+        _ => f64::NEG_INFINITY,
+    };
+    if position.y < floor {
+        position.y = floor;
+    } else {
+        position.y = (position.y - FALL_SPEED).max(floor);
+    }
+}
+
+fn repath_if_needed(
+    world: &World,
+    state: &mut AiState,
+    position: Position,
+    destination: BlockPosition,
+    height: u32,
+) {
+    let stale = state
+        .path
+        .as_ref()
+        .map_or(true, |path| path.destination() != Some(destination));
+    if state.ticks_until_repath == 0 && stale {
+        state.path =
+            pathfinding::find_path(world, position.block(), destination, height, MAX_PATH_NODES);
+        state.ticks_until_repath = REPATH_INTERVAL;
+    }
+}
+
+/// Moves `position` towards the next waypoint of the mob's path
+/// by at most `speed` blocks.
+fn walk_along_path(state: &mut AiState, position: &mut Position, speed: f64) {
+    let path = match &mut state.path {
+        Some(path) => path,
+        None => return,
+    };
+    let waypoint = match path.current() {
+        Some(waypoint) => waypoint,
+        None => {
+            state.path = None;
+            return;
+        }
+    };
+
+    let target = Vec3d::new(
+        waypoint.x as f64 + 0.5,
+        waypoint.y as f64,
+        waypoint.z as f64 + 0.5,
+    );
+    let delta = target - position.vec();
+    let horizontal_distance = (delta.x * delta.x + delta.z * delta.z).sqrt();
+
+    if horizontal_distance <= speed {
+        position.x = target.x;
+        position.z = target.z;
+        path.advance();
+        if path.is_finished() {
+            state.path = None;
+        }
+    } else {
+        position.x += delta.x / horizontal_distance * speed;
+        position.z += delta.z / horizontal_distance * speed;
+    }
+
+    if horizontal_distance > 0.0 {
+        position.yaw = yaw_towards(delta);
+    }
+}
+
+fn look_at(position: &mut Position, target: Position) {
+    let delta = target.vec() - position.vec();
+    let horizontal_distance = (delta.x * delta.x + delta.z * delta.z).sqrt();
+    if horizontal_distance > 0.0 {
+        position.yaw = yaw_towards(delta);
+    }
+    position.pitch = (-delta.y.atan2(horizontal_distance).to_degrees()) as f32;
+}
+
+fn yaw_towards(delta: Vec3d) -> f32 {
+    (-delta.x).atan2(delta.z).to_degrees() as f32
+}
+
+fn random_nearby_block(position: Position) -> BlockPosition {
+    let mut rng = rand::thread_rng();
+    let block = position.block();
+    BlockPosition::new(
+        block.x + rng.gen_range(-WANDER_RADIUS..=WANDER_RADIUS),
+        block.y + rng.gen_range(-1..=1),
+        block.z + rng.gen_range(-WANDER_RADIUS..=WANDER_RADIUS),
+    )
+}
+
+fn flee_destination(position: Position, threat: Position) -> BlockPosition {
+    let mut away = position.vec() - threat.vec();
+    away.y = 0.0;
+    let length = (away.x * away.x + away.z * away.z).sqrt();
+    let away = if length > 0.0 {
+        away / length
+    } else {
+        Vec3d::new(1.0, 0.0, 0.0)
+    };
+    (position + away * FLEE_DISTANCE).block()
+}
+
+#[cfg(test)]
+mod tests {
+    use std::convert::TryInto;
+
+    use super::*;
+
+    #[test]
+    fn yaw_matches_direction() {
+        let mut position = Position::default();
+        look_at(&mut position, base::position!(0.0, 64.0, 10.0));
+        assert!(position.yaw.abs() < 0.001);
+
+        let direction = position.direction();
+        assert!((direction.z - 1.0).abs() < 0.001);
+
+        look_at(&mut position, base::position!(-10.0, 64.0, 0.0));
+        let direction = position.direction();
+        assert!((direction.x + 1.0).abs() < 0.001);
+    }
+
+    #[test]
+    fn flee_destination_points_away() {
+        let position = base::position!(0.5, 64.0, 0.5);
+        let threat = base::position!(5.5, 64.0, 0.5);
+        let destination = flee_destination(position, threat);
+        assert_eq!(destination, BlockPosition::new(-8, 64, 0));
+    }
+
+    #[test]
+    fn goals_are_ordered_by_priority() {
+        let goals = MobGoals::new()
+            .with(2, Goal::Wander { speed: 0.1 })
+            .with(0, Goal::LookAtPlayer { range: 8.0 })
+            .with(
+                1,
+                Goal::FleePlayer {
+                    speed: 0.2,
+                    range: 6.0,
+                },
+            );
+        let order: Vec<Goal> = goals.iter().collect();
+        assert_eq!(
+            order,
+            vec![
+                Goal::LookAtPlayer { range: 8.0 },
+                Goal::FleePlayer {
+                    speed: 0.2,
+                    range: 6.0
+                },
+                Goal::Wander { speed: 0.1 },
+            ]
+        );
+    }
+
+    #[test]
+    fn walk_reaches_waypoint() {
+        let mut state = AiState::default();
+        let mut position = base::position!(0.5, 64.0, 0.5);
+        let world = flat_world();
+        state.path = pathfinding::find_path(
+            &world,
+            position.block(),
+            BlockPosition::new(2, 64, 0),
+            2,
+            64,
+        );
+        assert!(state.path.is_some());
+
+        for _ in 0..40 {
+            walk_along_path(&mut state, &mut position, 0.25);
+        }
+        assert!(state.path.is_none());
+        assert_eq!(position.block(), BlockPosition::new(2, 64, 0));
+    }
+
+    /// Creates a world with a stone floor up to y=63 in the chunk at the origin.
+    fn flat_world() -> World {
+        let mut world = World::new();
+        let mut chunk = base::Chunk::new(base::ChunkPosition::new(0, 0));
+        chunk.fill_section(4, base::BlockId::stone());
+        world.chunk_map_mut().insert_chunk(chunk);
+        world
+    }
+
+    #[test]
+    fn mobs_fall_and_climb_steps() {
+        let world = flat_world();
+        let mut position = base::position!(0.5, 66.0, 0.5);
+        for _ in 0..10 {
+            apply_gravity(&world, &mut position);
+        }
+        assert_eq!(position.y, 64.0);
+
+        world.set_block_at(
+            BlockPosition::new(1, 64, 0).try_into().unwrap(),
+            base::BlockId::stone(),
+        );
+        position.x = 1.1;
+        apply_gravity(&world, &mut position);
+        assert_eq!(position.y, 65.0);
+    }
+
+    #[test]
+    fn walls_block_line_of_sight() {
+        let world = flat_world();
+        let from = Vec3d::new(0.5, 65.5, 0.5);
+        let to = Vec3d::new(8.5, 65.5, 0.5);
+        assert!(has_line_of_sight(&world, from, to));
+
+        world.set_block_at(
+            BlockPosition::new(4, 65, 0).try_into().unwrap(),
+            base::BlockId::stone(),
+        );
+        assert!(!has_line_of_sight(&world, from, to));
+    }
+
+    #[test]
+    fn melee_attacks_trigger_damage_events() {
+        use quill_common::{components::Health, events::EntityDamageEvent};
+
+        let mut game = Game::new();
+        game.worlds = crate::Worlds::new(flat_world());
+        let player = game.ecs.spawn((
+            Player,
+            WorldId::MAIN,
+            base::position!(1.5, 64.0, 0.5),
+            Health(20.0),
+        ));
+        let zombie = game.ecs.spawn((
+            EntityKind::Zombie,
+            WorldId::MAIN,
+            base::position!(0.5, 64.0, 0.5),
+            Health(20.0),
+            melee_mob_goals(),
+        ));
+        add_ai_state(&mut game).unwrap();
+        select_goals(&mut game).unwrap();
+        run_goals(&mut game).unwrap();
+
+        assert!(game.ecs.get::<EntityDamageEvent>(player).is_ok());
+        assert!(game.ecs.get::<EntityDamageEvent>(zombie).is_err());
+        assert_eq!(game.ecs.get::<Health>(player).unwrap().0, 20.0);
+    }
+}
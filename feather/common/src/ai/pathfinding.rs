@@ -0,0 +1,384 @@
+//! A* pathfinding over the blocks of loaded chunks.
+
+use std::{cmp::Reverse, collections::BinaryHeap, convert::TryInto};
+
+use ahash::AHashMap;
+use base::{BlockId, BlockPosition, SimplifiedBlockKind};
+
+use crate::World;
+
+/// Maximum number of blocks a mob is willing to drop down in one step.
+const MAX_DROP: i32 = 3;
+/// Maximum height a mob walks up without jumping.
+const MAX_STEP: f64 = 0.6;
+/// Maximum height a mob can jump up.
+const MAX_JUMP: f64 = 1.25;
+
+/// Cost of moving one block horizontally.
+const STEP_COST: u32 = 10;
+/// Additional cost of jumping up one block.
+const JUMP_COST: u32 = 5;
+/// Additional cost per block of dropping down.
+const DROP_COST: u32 = 2;
+
+/// A path computed by [`find_path`].
+///
+/// Consists of a sequence of block positions
+/// that the mob's feet should pass through.
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct Path {
+    waypoints: Vec<BlockPosition>,
+    index: usize,
+}
+
+impl Path {
+    fn new(waypoints: Vec<BlockPosition>) -> Self {
+        Self {
+            waypoints,
+            index: 0,
+        }
+    }
+
+    /// Returns the waypoint the mob is currently walking towards.
+    pub fn current(&self) -> Option<BlockPosition> {
+        self.waypoints.get(self.index).copied()
+    }
+
+    /// Moves on to the next waypoint.
+    pub fn advance(&mut self) {
+        self.index += 1;
+    }
+
+    /// Returns whether the mob has walked past the last waypoint.
+    pub fn is_finished(&self) -> bool {
+        self.index >= self.waypoints.len()
+    }
+
+    /// Returns the final waypoint of the path.
+    pub fn destination(&self) -> Option<BlockPosition> {
+        self.waypoints.last().copied()
+    }
+
+    /// Returns all waypoints in this path.
+    pub fn waypoints(&self) -> &[BlockPosition] {
+        &self.waypoints
+    }
+}
+
+/// Finds a walkable path from `start` to `goal` for a mob
+/// `height` blocks tall.
+///
+/// Only blocks in loaded chunks are considered. At most
+/// `max_nodes` positions are explored; if `goal` cannot be
+/// reached within that budget, the path leads to the explored
+/// position closest to `goal` instead. Returns `None` if no
+/// progress towards `goal` is possible.
+pub fn find_path(
+    world: &World,
+    start: BlockPosition,
+    goal: BlockPosition,
+    height: u32,
+    max_nodes: usize,
+) -> Option<Path> {
+    let mut open = BinaryHeap::new();
+    let mut came_from: AHashMap<BlockPosition, BlockPosition> = AHashMap::new();
+    let mut costs: AHashMap<BlockPosition, u32> = AHashMap::new();
+
+    costs.insert(start, 0);
+    open.push(Reverse((heuristic(start, goal), start)));
+
+    let mut closest = (heuristic(start, goal), start);
+    let mut explored = 0;
+
+    while let Some(Reverse((_, current))) = open.pop() {
+        if current == goal {
+            closest = (0, current);
+            break;
+        }
+
+        explored += 1;
+        if explored > max_nodes {
+            break;
+        }
+
+        let current_cost = costs[&current];
+        for (neighbor, step_cost) in neighbors(world, current, height) {
+            let cost = current_cost + step_cost;
+            if costs.get(&neighbor).map_or(true, |&old| cost < old) {
+                costs.insert(neighbor, cost);
+                came_from.insert(neighbor, current);
+
+                let h = heuristic(neighbor, goal);
+                if h < closest.0 {
+                    closest = (h, neighbor);
+                }
+                open.push(Reverse((cost + h, neighbor)));
+            }
+        }
+    }
+
+    let end = closest.1;
+    if end == start {
+        return None;
+    }
+
+    let mut waypoints = vec![end];
+    let mut current = end;
+    while let Some(&previous) = came_from.get(&current) {
+        if previous == start {
+            break;
+        }
+        waypoints.push(previous);
+        current = previous;
+    }
+    waypoints.reverse();
+
+    Some(Path::new(waypoints))
+}
+
+/// Determines whether a mob `height` blocks tall can stand
+/// with its feet at `pos`.
+pub fn can_stand_at(world: &World, pos: BlockPosition, height: u32) -> bool {
+    floor_height(world, pos, height).is_some()
+}
+
+/// Returns the height at which the feet of a mob `height` blocks
+/// tall rest when it stands in the block at `pos`, or `None`
+/// if it can't stand there.
+///
+/// Mobs stand on top of the collision boxes of the blocks below,
+/// which can be lower than a full block (slabs, carpets, snow layers)
+/// or reach into `pos` from below (fences and walls).
+pub fn floor_height(world: &World, pos: BlockPosition, height: u32) -> Option<f64> {
+    let block = block_at(world, pos)?;
+    if is_harmful(block) {
+        return None;
+    }
+
+    let floor = match block.collision_height() {
+        top if top >= 1.0 => return None,
+        top if top > 0.0 => top,
+        _ => match block_at(world, pos.down())?.collision_height() {
+            top if top >= 1.0 => top - 1.0,
+            _ => return None,
+        },
+    };
+
+    // The blocks above need to be free wherever the mob's body is
+    let blocks = (floor + height as f64).ceil() as u32;
+    if has_clearance(world, pos.up(), blocks.saturating_sub(1)) {
+        Some(pos.y as f64 + floor)
+    } else {
+        None
+    }
+}
+
+/// Determines whether the `height` blocks starting at `pos` and
+/// going upwards have no collision box.
+pub fn has_clearance(world: &World, pos: BlockPosition, height: u32) -> bool {
+    (0..height as i32).all(|dy| {
+        let pos = BlockPosition::new(pos.x, pos.y + dy, pos.z);
+        matches!(
+            block_at(world, pos),
+            Some(block) if block.collision_height() == 0.0 && !is_harmful(block)
+        )
+    })
+}
+
+fn neighbors(world: &World, pos: BlockPosition, height: u32) -> Vec<(BlockPosition, u32)> {
+    let mut neighbors = Vec::with_capacity(4);
+    let feet = floor_height(world, pos, height).unwrap_or(pos.y as f64);
+    for &next in &[pos.north(), pos.south(), pos.east(), pos.west()] {
+        // Walk on the same level, stepping or jumping
+        // onto lower blocks like slabs
+        if let Some(floor) = floor_height(world, next, height) {
+            if floor - feet <= MAX_STEP {
+                neighbors.push((next, STEP_COST));
+            } else if floor - feet <= MAX_JUMP && has_clearance(world, pos.up(), height) {
+                neighbors.push((next, STEP_COST + JUMP_COST));
+            }
+            continue;
+        }
+
+        // Jump up one block, which requires headroom
+        // above the current position
+        let up = next.up();
+        if let Some(floor) = floor_height(world, up, height) {
+            if floor - feet <= MAX_JUMP && has_clearance(world, pos.up(), height) {
+                neighbors.push((up, STEP_COST + JUMP_COST));
+            }
+            continue;
+        }
+
+        // Drop down, landing on the first block to stand on
+        if !has_clearance(world, next, height) {
+            continue;
+        }
+        let landing = (1..=MAX_DROP)
+            .map(|drop| BlockPosition::new(next.x, next.y - drop, next.z))
+            .find(|&below| !has_clearance(world, below, 1) || can_stand_at(world, below, height))
+            .filter(|&below| can_stand_at(world, below, height));
+        if let Some(below) = landing {
+            let drop = (next.y - below.y) as u32;
+            neighbors.push((below, STEP_COST + drop * DROP_COST));
+        }
+    }
+    neighbors
+}
+
+fn heuristic(a: BlockPosition, b: BlockPosition) -> u32 {
+    a.manhattan_distance(b) as u32 * STEP_COST
+}
+
+fn is_harmful(block: BlockId) -> bool {
+    block.simplified_kind() == SimplifiedBlockKind::Lava
+}
+
+/// Returns the block at `pos`, or `None` if it is not loaded.
+pub fn block_at(world: &World, pos: BlockPosition) -> Option<BlockId> {
+    world.block_at(pos.try_into().ok()?)
+}
+
+#[cfg(test)]
+mod tests {
+    use base::{Chunk, ChunkPosition, SlabKind};
+
+    use super::*;
+
+    /// Creates a world with a stone floor up to y=63
+    /// in the chunks around the origin.
+    fn flat_world() -> World {
+        let mut world = World::new();
+        for x in -1..=1 {
+            for z in -1..=1 {
+                let mut chunk = Chunk::new(ChunkPosition::new(x, z));
+                for section in 1..=4 {
+                    chunk.fill_section(section, BlockId::stone());
+                }
+                world.chunk_map_mut().insert_chunk(chunk);
+            }
+        }
+        world
+    }
+
+    #[test]
+    fn straight_path_on_flat_ground() {
+        let world = flat_world();
+        let start = BlockPosition::new(0, 64, 0);
+        let goal = BlockPosition::new(5, 64, 0);
+
+        let path = find_path(&world, start, goal, 2, 256).unwrap();
+        assert_eq!(path.destination(), Some(goal));
+        assert_eq!(path.waypoints().len(), 5);
+        assert!(!path.waypoints().contains(&start));
+    }
+
+    #[test]
+    fn path_goes_around_wall() {
+        let world = flat_world();
+        for z in -3..=3 {
+            for y in 64..67 {
+                assert!(world.set_block_at(
+                    BlockPosition::new(2, y, z).try_into().unwrap(),
+                    BlockId::stone()
+                ));
+            }
+        }
+
+        let start = BlockPosition::new(0, 64, 0);
+        let goal = BlockPosition::new(4, 64, 0);
+        let path = find_path(&world, start, goal, 2, 1024).unwrap();
+
+        assert_eq!(path.destination(), Some(goal));
+        assert!(path
+            .waypoints()
+            .iter()
+            .all(|pos| pos.x != 2 || pos.z.abs() > 3));
+    }
+
+    #[test]
+    fn path_climbs_single_step() {
+        let world = flat_world();
+        for z in -8..=8 {
+            for x in 2..8 {
+                world.set_block_at(
+                    BlockPosition::new(x, 64, z).try_into().unwrap(),
+                    BlockId::stone(),
+                );
+            }
+        }
+
+        let start = BlockPosition::new(0, 64, 0);
+        let goal = BlockPosition::new(4, 65, 0);
+        let path = find_path(&world, start, goal, 2, 256).unwrap();
+        assert_eq!(path.destination(), Some(goal));
+    }
+
+    #[test]
+    fn fences_cannot_be_jumped_over() {
+        let world = flat_world();
+        for z in -3..=3 {
+            world.set_block_at(
+                BlockPosition::new(2, 64, z).try_into().unwrap(),
+                BlockId::oak_fence(),
+            );
+        }
+
+        // Mobs can stand on a fence, but it is too high to jump onto
+        let on_fence = BlockPosition::new(2, 65, 0);
+        assert_eq!(floor_height(&world, on_fence, 2), Some(65.5));
+        let start = BlockPosition::new(1, 64, 0);
+        assert!(neighbors(&world, start, 2)
+            .iter()
+            .all(|&(pos, _)| pos != on_fence));
+
+        let goal = BlockPosition::new(4, 64, 0);
+        let path = find_path(&world, BlockPosition::new(0, 64, 0), goal, 2, 1024).unwrap();
+        assert_eq!(path.destination(), Some(goal));
+        assert!(path
+            .waypoints()
+            .iter()
+            .all(|pos| pos.x != 2 || pos.z.abs() > 3));
+    }
+
+    #[test]
+    fn slabs_and_carpets_are_stepped_onto() {
+        let world = flat_world();
+        let slab = BlockPosition::new(1, 64, 0);
+        let carpet = BlockPosition::new(0, 64, 1);
+        world.set_block_at(
+            slab.try_into().unwrap(),
+            BlockId::stone_slab().with_slab_kind(SlabKind::Bottom),
+        );
+        world.set_block_at(carpet.try_into().unwrap(), BlockId::white_carpet());
+
+        assert_eq!(floor_height(&world, slab, 2), Some(64.5));
+        assert_eq!(floor_height(&world, carpet, 2), Some(64.0625));
+
+        let neighbors = neighbors(&world, BlockPosition::new(0, 64, 0), 2);
+        assert!(neighbors.contains(&(slab, STEP_COST)));
+        assert!(neighbors.contains(&(carpet, STEP_COST)));
+    }
+
+    #[test]
+    fn no_path_outside_loaded_chunks() {
+        let world = flat_world();
+        let start = BlockPosition::new(0, 64, 0);
+        let goal = BlockPosition::new(100, 64, 0);
+
+        // The path stops at the edge of the loaded area.
+        let path = find_path(&world, start, goal, 2, 4096).unwrap();
+        assert_eq!(path.destination().unwrap().x, 31);
+
+        let unloaded = BlockPosition::new(100, 64, 100);
+        assert!(find_path(&world, unloaded, goal, 2, 256).is_none());
+    }
+
+    #[test]
+    fn cannot_stand_in_solid_blocks() {
+        let world = flat_world();
+        assert!(can_stand_at(&world, BlockPosition::new(0, 64, 0), 2));
+        assert!(!can_stand_at(&world, BlockPosition::new(0, 63, 0), 2));
+        assert!(!can_stand_at(&world, BlockPosition::new(0, 65, 0), 2));
+    }
+}
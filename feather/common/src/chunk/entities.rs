@@ -4,7 +4,10 @@ use ecs::{Entity, SysResult, SystemExecutor};
 use quill_common::events::{EntityCreateEvent, EntityRemoveEvent};
 use utils::vec_remove_item;
 
-use crate::{events::ChunkCrossEvent, Game};
+use crate::{
+    events::{ChunkCrossEvent, WorldChangeEvent},
+    Game, WorldId,
+};
 
 pub fn register(systems: &mut SystemExecutor<Game>) {
     systems.add_system(update_chunk_entities);
@@ -13,14 +16,14 @@ pub fn register(systems: &mut SystemExecutor<Game>) {
 /// A spatial index to look up entities within a given chunk.
 #[derive(Default)]
 pub struct ChunkEntities {
-    entities: AHashMap<ChunkPosition, Vec<Entity>>,
+    entities: AHashMap<(WorldId, ChunkPosition), Vec<Entity>>,
 }
 
 impl ChunkEntities {
-    /// Returns the entities in the given chunk.
-    pub fn entities_in_chunk(&self, chunk: ChunkPosition) -> &[Entity] {
+    /// Returns the entities in the given chunk of `world`.
+    pub fn entities_in_chunk(&self, world: WorldId, chunk: ChunkPosition) -> &[Entity] {
         self.entities
-            .get(&chunk)
+            .get(&(world, chunk))
             .map(Vec::as_slice)
             .unwrap_or_default()
     }
@@ -28,8 +31,8 @@ impl ChunkEntities {
     fn update(
         &mut self,
         entity: Entity,
-        old_chunk: Option<ChunkPosition>,
-        new_chunk: ChunkPosition,
+        old_chunk: Option<(WorldId, ChunkPosition)>,
+        new_chunk: (WorldId, ChunkPosition),
     ) {
         if let Some(old_chunk) = old_chunk {
             if let Some(vec) = self.entities.get_mut(&old_chunk) {
@@ -40,7 +43,7 @@ impl ChunkEntities {
         self.entities.entry(new_chunk).or_default().push(entity);
     }
 
-    fn remove_entity(&mut self, entity: Entity, chunk: ChunkPosition) {
+    fn remove_entity(&mut self, entity: Entity, chunk: (WorldId, ChunkPosition)) {
         if let Some(vec) = self.entities.get_mut(&chunk) {
             vec_remove_item(vec, &entity);
         }
@@ -48,19 +51,32 @@ impl ChunkEntities {
 }
 
 fn update_chunk_entities(game: &mut Game) -> SysResult {
-    // Entities that have crossed chunks
+    // Entities that have crossed chunks or changed worlds
     let mut events = Vec::new();
-    for (entity, (old_chunk, &position)) in
-        game.ecs.query::<(&mut ChunkPosition, &Position)>().iter()
+    for (entity, (old_chunk, &position, &new_world, world_change)) in game
+        .ecs
+        .query::<(
+            &mut ChunkPosition,
+            &Position,
+            &WorldId,
+            Option<&WorldChangeEvent>,
+        )>()
+        .iter()
     {
+        let old_world = world_change.map_or(new_world, |event| event.old_world);
         let new_chunk = position.chunk();
-        if position.chunk() != *old_chunk {
-            game.chunk_entities
-                .update(entity, Some(*old_chunk), new_chunk);
+        if new_chunk != *old_chunk || new_world != old_world {
+            game.chunk_entities.update(
+                entity,
+                Some((old_world, *old_chunk)),
+                (new_world, new_chunk),
+            );
             events.push((
                 entity,
                 ChunkCrossEvent {
+                    old_world,
                     old_chunk: *old_chunk,
+                    new_world,
                     new_chunk,
                 },
             ));
@@ -74,10 +90,13 @@ fn update_chunk_entities(game: &mut Game) -> SysResult {
 
     // Entities that have been created
     let mut insertions = Vec::new();
-    for (entity, (_event, &position)) in game.ecs.query::<(&EntityCreateEvent, &Position)>().iter()
+    for (entity, (_event, &position, &world)) in game
+        .ecs
+        .query::<(&EntityCreateEvent, &Position, &WorldId)>()
+        .iter()
     {
         let chunk = position.chunk();
-        game.chunk_entities.update(entity, None, chunk);
+        game.chunk_entities.update(entity, None, (world, chunk));
         insertions.push((entity, chunk));
     }
     // Add ChunkPosition component to new entities
@@ -86,12 +105,12 @@ fn update_chunk_entities(game: &mut Game) -> SysResult {
     }
 
     // Entities that have been destroyed
-    for (entity, (_event, &chunk)) in game
+    for (entity, (_event, &chunk, &world)) in game
         .ecs
-        .query::<(&EntityRemoveEvent, &ChunkPosition)>()
+        .query::<(&EntityRemoveEvent, &ChunkPosition, &WorldId)>()
         .iter()
     {
-        game.chunk_entities.remove_entity(entity, chunk);
+        game.chunk_entities.remove_entity(entity, (world, chunk));
     }
 
     Ok(())
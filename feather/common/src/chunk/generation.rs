@@ -0,0 +1,273 @@
+//! A pool of threads generating chunks which don't exist in the world save.
+
+use std::{
+    sync::Arc,
+    thread,
+    time::{Duration, Instant},
+};
+
+use ahash::AHashMap;
+use anyhow::bail;
+use base::ChunkPosition;
+use flume::{Receiver, Sender};
+use parking_lot::{Condvar, Mutex};
+use worldgen::WorldGenerator;
+
+use super::worker::LoadedChunk;
+
+/// Statistics of chunk generation, for monitoring.
+#[derive(Copy, Clone, Debug, Default, PartialEq)]
+pub struct GenerationStats {
+    /// Chunks waiting for a worker thread.
+    pub queued: usize,
+    /// Chunks being generated.
+    pub in_progress: usize,
+    /// Chunks generated since the pool was created.
+    pub generated: u64,
+    /// Chunks whose generation was cancelled before it started.
+    pub cancelled: u64,
+    /// Total time spent generating chunks.
+    pub total_generation_time: Duration,
+    /// Total time between queueing chunks and their generation
+    /// finishing, including the time spent in the queue.
+    pub total_latency: Duration,
+}
+
+impl GenerationStats {
+    /// Average time between queueing a chunk and
+    /// its generation finishing.
+    pub fn average_latency(&self) -> Duration {
+        if self.generated == 0 {
+            Duration::default()
+        } else {
+            self.total_latency / self.generated as u32
+        }
+    }
+}
+
+/// Generates chunks on a pool of worker threads.
+///
+/// Chunks closest to the players waiting on them, as set
+/// through [`GenerationPool::set_focus`], are generated first.
+pub struct GenerationPool {
+    shared: Arc<Shared>,
+    recv_generated: Receiver<LoadedChunk>,
+}
+
+struct Shared {
+    state: Mutex<State>,
+    /// Notified when chunks are queued or the pool shuts down.
+    queued: Condvar,
+}
+
+#[derive(Default)]
+struct State {
+    /// Chunks waiting for a worker, with the time they were queued.
+    queue: AHashMap<ChunkPosition, Instant>,
+    /// Chunks players are in.
+    focus: Vec<ChunkPosition>,
+    stats: GenerationStats,
+    shutdown: bool,
+}
+
+impl State {
+    /// Takes the queued chunk closest to a player,
+    /// or the oldest one if no player is waiting.
+    fn next(&mut self) -> Option<(ChunkPosition, Instant)> {
+        let focus = &self.focus;
+        let pos = *self
+            .queue
+            .iter()
+            .min_by_key(|&(&pos, &queued_at)| (distance_to_focus(focus, pos), queued_at))?
+            .0;
+        let queued_at = self.queue.remove(&pos)?;
+        self.stats.in_progress += 1;
+        Some((pos, queued_at))
+    }
+}
+
+/// Gets the distance, in chunks, from `pos` to the closest chunk of `focus`.
+fn distance_to_focus(focus: &[ChunkPosition], pos: ChunkPosition) -> i32 {
+    focus
+        .iter()
+        .map(|center| (center.x - pos.x).abs().max((center.z - pos.z).abs()))
+        .min()
+        .unwrap_or(0)
+}
+
+impl GenerationPool {
+    /// Creates a pool of `threads` worker threads generating chunks
+    /// with `generator`. If `threads` is 0, one thread per CPU is used.
+    pub fn new(generator: Arc<dyn WorldGenerator>, threads: usize) -> Self {
+        let threads = match threads {
+            0 => thread::available_parallelism().map_or(1, |threads| threads.get()),
+            threads => threads,
+        };
+        let (send_generated, recv_generated) = flume::unbounded();
+        let shared = Arc::new(Shared {
+            state: Mutex::new(State::default()),
+            queued: Condvar::new(),
+        });
+
+        for index in 0..threads {
+            let shared = Arc::clone(&shared);
+            let generator = Arc::clone(&generator);
+            let send_generated = send_generated.clone();
+            thread::Builder::new()
+                .name(format!("chunk-generator-{}", index))
+                .spawn(move || run_worker(&shared, &*generator, &send_generated))
+                .expect("failed to spawn chunk generation thread");
+        }
+
+        Self {
+            shared,
+            recv_generated,
+        }
+    }
+
+    /// Queues the given chunk to be generated.
+    pub fn queue(&self, pos: ChunkPosition) {
+        self.shared
+            .state
+            .lock()
+            .queue
+            .entry(pos)
+            .or_insert_with(Instant::now);
+        self.shared.queued.notify_one();
+    }
+
+    /// Cancels generation of the given chunk. Returns `false`
+    /// if it was not queued or its generation already started.
+    pub fn cancel(&self, pos: ChunkPosition) -> bool {
+        let mut state = self.shared.state.lock();
+        let cancelled = state.queue.remove(&pos).is_some();
+        if cancelled {
+            state.stats.cancelled += 1;
+        }
+        cancelled
+    }
+
+    /// Sets the chunks players are in. Queued chunks
+    /// closest to them are generated first.
+    pub fn set_focus(&self, focus: Vec<ChunkPosition>) {
+        self.shared.state.lock().focus = focus;
+    }
+
+    /// Attempts to receive a generated chunk.
+    pub fn try_recv(&self) -> anyhow::Result<Option<LoadedChunk>> {
+        match self.recv_generated.try_recv() {
+            Ok(loaded) => Ok(Some(loaded)),
+            Err(flume::TryRecvError::Empty) => Ok(None),
+            Err(flume::TryRecvError::Disconnected) => bail!("chunk generation threads died"),
+        }
+    }
+
+    pub fn stats(&self) -> GenerationStats {
+        let state = self.shared.state.lock();
+        GenerationStats {
+            queued: state.queue.len(),
+            ..state.stats
+        }
+    }
+}
+
+impl Drop for GenerationPool {
+    fn drop(&mut self) {
+        self.shared.state.lock().shutdown = true;
+        self.shared.queued.notify_all();
+    }
+}
+
+fn run_worker(shared: &Shared, generator: &dyn WorldGenerator, send: &Sender<LoadedChunk>) {
+    loop {
+        let (pos, queued_at) = {
+            let mut state = shared.state.lock();
+            loop {
+                if state.shutdown {
+                    return;
+                }
+                if let Some(next) = state.next() {
+                    break next;
+                }
+                shared.queued.wait(&mut state);
+            }
+        };
+
+        let started_at = Instant::now();
+        let (chunk, block_entities) = generator.generate_chunk_with_block_entities(pos);
+        let finished_at = Instant::now();
+
+        {
+            let mut state = shared.state.lock();
+            let stats = &mut state.stats;
+            stats.in_progress -= 1;
+            stats.generated += 1;
+            stats.total_generation_time += finished_at - started_at;
+            stats.total_latency += finished_at - queued_at;
+        }
+
+        let loaded = LoadedChunk {
+            pos,
+            chunk,
+            block_entities,
+            generated: true,
+        };
+        if send.send(loaded).is_err() {
+            return;
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use worldgen::VoidWorldGenerator;
+
+    #[test]
+    fn closest_chunks_first() {
+        let mut state = State {
+            focus: vec![ChunkPosition::new(10, 10)],
+            ..Default::default()
+        };
+        let now = Instant::now();
+        state.queue.insert(ChunkPosition::new(0, 0), now);
+        state.queue.insert(ChunkPosition::new(12, 9), now);
+        state.queue.insert(ChunkPosition::new(10, 11), now);
+
+        let order: Vec<_> = std::iter::from_fn(|| state.next().map(|(pos, _)| pos)).collect();
+        assert_eq!(
+            order,
+            vec![
+                ChunkPosition::new(10, 11),
+                ChunkPosition::new(12, 9),
+                ChunkPosition::new(0, 0),
+            ]
+        );
+        assert_eq!(state.stats.in_progress, 3);
+    }
+
+    #[test]
+    fn generate_queued_chunks() {
+        let pool = GenerationPool::new(Arc::new(VoidWorldGenerator), 2);
+        let positions = [ChunkPosition::new(0, 0), ChunkPosition::new(5, -3)];
+        for &pos in &positions {
+            pool.queue(pos);
+        }
+
+        let mut generated: Vec<_> = (0..positions.len())
+            .map(|_| {
+                pool.recv_generated
+                    .recv_timeout(Duration::from_secs(10))
+                    .unwrap()
+                    .pos
+            })
+            .collect();
+        generated.sort_by_key(|pos| pos.x);
+        assert_eq!(generated, positions);
+
+        let stats = pool.stats();
+        assert_eq!(stats.generated, 2);
+        assert_eq!(stats.queued, 0);
+        assert!(!pool.cancel(ChunkPosition::new(0, 0)));
+    }
+}
@@ -7,12 +7,14 @@ use std::{
 };
 
 use ahash::AHashMap;
-use base::ChunkPosition;
+use base::{ChunkPosition, TICK_DURATION};
 use ecs::{Entity, SysResult, SystemExecutor};
 use quill_common::events::EntityRemoveEvent;
 use utils::vec_remove_item;
 
-use crate::{chunk::worker::LoadRequest, events::ViewUpdateEvent, Game};
+use crate::{
+    chunk::worker::LoadRequest, events::ViewUpdateEvent, spawner, view::View, Game, WorldId,
+};
 
 pub fn register(game: &mut Game, systems: &mut SystemExecutor<Game>) {
     game.insert_resource(ChunkLoadState::default());
@@ -21,23 +23,33 @@ pub fn register(game: &mut Game, systems: &mut SystemExecutor<Game>) {
         .add_system(remove_dead_entities)
         .add_system(update_tickets_for_players)
         .add_system(unload_chunks)
-        .add_system(load_chunks);
+        .add_system(load_chunks)
+        .add_system(pregenerate_chunks);
 }
 
 /// Amount of time to wait after a chunk has
 /// no tickets until it is unloaded.
 const UNLOAD_DELAY: Duration = Duration::from_secs(10);
 
+/// Time each tick may spend queueing chunks to pregenerate.
+const PREGENERATION_BUDGET: Duration = Duration::from_millis(5);
+
+/// A chunk of a particular world.
+type WorldChunk = (WorldId, ChunkPosition);
+
 #[derive(Default)]
 struct ChunkLoadState {
     /// Chunks that have been queued for unloading.
     chunk_unload_queue: VecDeque<QueuedChunkUnload>,
 
     chunk_tickets: ChunkTickets,
+
+    /// When chunks were last queued for pregeneration.
+    last_pregeneration_update: Option<Instant>,
 }
 
 impl ChunkLoadState {
-    pub fn remove_ticket(&mut self, chunk: ChunkPosition, ticket: Ticket) {
+    pub fn remove_ticket(&mut self, chunk: WorldChunk, ticket: Ticket) {
         self.chunk_tickets.remove_ticket(chunk, ticket);
 
         // If this was the last ticket, then queue the chunk to be
@@ -52,14 +64,16 @@ impl ChunkLoadState {
 
 #[derive(Copy, Clone, Debug)]
 struct QueuedChunkUnload {
+    world: WorldId,
     pos: ChunkPosition,
     /// Time after which the chunk should be unloaded.
     unload_at_time: Instant,
 }
 
 impl QueuedChunkUnload {
-    pub fn new(pos: ChunkPosition) -> Self {
+    pub fn new((world, pos): WorldChunk) -> Self {
         Self {
+            world,
             pos,
             unload_at_time: Instant::now() + UNLOAD_DELAY,
         }
@@ -70,39 +84,39 @@ impl QueuedChunkUnload {
 /// A chunk is queued for unloading when it has no more tickets.
 #[derive(Default)]
 struct ChunkTickets {
-    tickets: AHashMap<ChunkPosition, Vec<Ticket>>,
-    by_entity: AHashMap<Ticket, Vec<ChunkPosition>>,
+    tickets: AHashMap<WorldChunk, Vec<Ticket>>,
+    by_entity: AHashMap<Ticket, Vec<WorldChunk>>,
 }
 
 impl ChunkTickets {
-    pub fn insert_ticket(&mut self, chunk: ChunkPosition, ticket: Ticket) {
+    pub fn insert_ticket(&mut self, chunk: WorldChunk, ticket: Ticket) {
         self.tickets.entry(chunk).or_default().push(ticket);
         self.by_entity.entry(ticket).or_default().push(chunk);
     }
 
-    pub fn remove_ticket(&mut self, chunk: ChunkPosition, ticket: Ticket) {
+    pub fn remove_ticket(&mut self, chunk: WorldChunk, ticket: Ticket) {
         if let Some(vec) = self.tickets.get_mut(&chunk) {
             vec_remove_item(vec, &ticket);
         }
         vec_remove_item(self.by_entity.get_mut(&ticket).unwrap(), &chunk);
     }
 
-    pub fn num_tickets(&self, chunk: ChunkPosition) -> usize {
+    pub fn num_tickets(&self, chunk: WorldChunk) -> usize {
         match self.tickets.get(&chunk) {
             Some(vec) => vec.len(),
             None => 0,
         }
     }
 
-    pub fn take_entity_tickets(&mut self, ticket: Ticket) -> Vec<ChunkPosition> {
+    pub fn take_entity_tickets(&mut self, ticket: Ticket) -> Vec<WorldChunk> {
         self.by_entity
             .get_mut(&ticket)
             .map(mem::take)
             .unwrap_or_default()
     }
 
-    pub fn remove_chunk(&mut self, pos: ChunkPosition) {
-        self.tickets.remove(&pos);
+    pub fn remove_chunk(&mut self, chunk: WorldChunk) {
+        self.tickets.remove(&chunk);
     }
 }
 
@@ -113,23 +127,50 @@ impl ChunkTickets {
 #[derive(Copy, Clone, Debug, PartialEq, Eq, Hash)]
 struct Ticket(Entity);
 
+/// Loads chunks of `world` which no player needs to have loaded,
+/// for example to search for a portal. Unless a player's view
+/// includes them by then, they are unloaded after the usual delay.
+pub fn load_chunks_temporarily(game: &mut Game, world: WorldId, chunks: &[ChunkPosition]) {
+    let mut state = game
+        .resources
+        .get_mut::<ChunkLoadState>()
+        .expect("chunk loading is not registered");
+    let world_ref = &mut game.worlds[world];
+    for &pos in chunks {
+        if world_ref.is_chunk_loaded(pos) || world_ref.is_chunk_loading(pos) {
+            continue;
+        }
+        world_ref.queue_chunk_load(LoadRequest { pos });
+        if state.chunk_tickets.num_tickets((world, pos)) == 0 {
+            state
+                .chunk_unload_queue
+                .push_back(QueuedChunkUnload::new((world, pos)));
+        }
+    }
+}
+
 /// System to populate chunk tickets based on players' views.
 fn update_tickets_for_players(game: &mut Game, state: &mut ChunkLoadState) -> SysResult {
     for (player, event) in game.ecs.query::<&ViewUpdateEvent>().iter() {
         let player_ticket = Ticket(player);
 
         // Remove old tickets
+        let old_world = event.old_view.world();
         for &old_chunk in &event.old_chunks {
-            state.remove_ticket(old_chunk, player_ticket);
+            state.remove_ticket((old_world, old_chunk), player_ticket);
         }
 
         // Create new tickets
+        let new_world = event.new_view.world();
+        let world = &mut game.worlds[new_world];
         for &new_chunk in &event.new_chunks {
-            state.chunk_tickets.insert_ticket(new_chunk, player_ticket);
+            state
+                .chunk_tickets
+                .insert_ticket((new_world, new_chunk), player_ticket);
 
             // Load if needed
-            if !game.world.is_chunk_loaded(new_chunk) && !game.world.is_chunk_loading(new_chunk) {
-                game.world.queue_chunk_load(LoadRequest { pos: new_chunk });
+            if !world.is_chunk_loaded(new_chunk) && !world.is_chunk_loading(new_chunk) {
+                world.queue_chunk_load(LoadRequest { pos: new_chunk });
             }
         }
     }
@@ -149,13 +190,16 @@ fn unload_chunks(game: &mut Game, state: &mut ChunkLoadState) -> SysResult {
         state.chunk_unload_queue.pop_front();
 
         // If the chunk has acquired new tickets, then abort unloading it.
-        if state.chunk_tickets.num_tickets(unload.pos) > 0 {
+        if state.chunk_tickets.num_tickets((unload.world, unload.pos)) > 0 {
             continue;
         }
 
-        game.world.unload_chunk(unload.pos)?;
+        let block_entities = spawner::unload_spawner_blocks(game, unload.world, unload.pos);
+        game.worlds[unload.world].unload_chunk(unload.pos, block_entities)?;
+    }
+    for world in game.worlds.iter_mut() {
+        world.cache.purge_unused();
     }
-    game.world.cache.purge_unused();
     Ok(())
 }
 
@@ -169,7 +213,35 @@ fn remove_dead_entities(game: &mut Game, state: &mut ChunkLoadState) -> SysResul
     Ok(())
 }
 
-/// System to call `World::load_chunks` each tick
+/// System to call `World::load_chunks` on each world each tick
 fn load_chunks(game: &mut Game, _state: &mut ChunkLoadState) -> SysResult {
-    game.world.load_chunks(&mut game.ecs)
+    // Generate the chunks closest to players first.
+    let mut players: AHashMap<WorldId, Vec<ChunkPosition>> = AHashMap::new();
+    for (_, view) in game.ecs.query::<&View>().iter() {
+        players.entry(view.world()).or_default().push(view.center());
+    }
+    for world in game.worlds.iter_mut() {
+        let focus = players.remove(&world.id()).unwrap_or_default();
+        world.set_generation_focus(focus);
+        world.load_chunks(&mut game.ecs)?;
+    }
+    Ok(())
+}
+
+/// Queues chunks for pregeneration, skipping ticks
+/// which follow a tick that ran over its duration.
+fn pregenerate_chunks(game: &mut Game, state: &mut ChunkLoadState) -> SysResult {
+    let now = Instant::now();
+    let lagging = state
+        .last_pregeneration_update
+        .map_or(false, |last| now - last > TICK_DURATION * 3 / 2);
+    state.last_pregeneration_update = Some(now);
+    if lagging {
+        return Ok(());
+    }
+    let deadline = now + PREGENERATION_BUDGET;
+    for world in game.worlds.iter_mut() {
+        world.update_pregeneration(deadline)?;
+    }
+    Ok(())
 }
@@ -0,0 +1,409 @@
+//! Generating an area of the world ahead of time,
+//! so players don't wait for terrain to generate.
+//!
+//! Chunks are loaded through the `ChunkWorker`, which generates
+//! those missing from the world save, and generated chunks are
+//! saved right away instead of being kept loaded. Progress is
+//! written to a file in the world directory so pregeneration
+//! resumes after a restart.
+
+use std::{
+    fmt, fs,
+    path::{Path, PathBuf},
+    time::{Duration, Instant},
+};
+
+use ahash::AHashMap;
+use anyhow::{bail, Context};
+use base::ChunkPosition;
+
+/// Name of the file in the world directory storing
+/// the progress of a running pregeneration.
+pub const PROGRESS_FILE: &str = "pregen_progress.txt";
+
+/// Interval between progress reports, which
+/// also save progress to the progress file.
+const REPORT_INTERVAL: Duration = Duration::from_secs(10);
+
+/// The shape of a pregenerated area.
+#[derive(Copy, Clone, Debug, PartialEq, Eq)]
+pub enum PregenShape {
+    Square,
+    Circle,
+}
+
+impl PregenShape {
+    pub fn from_name(name: &str) -> Option<Self> {
+        match name {
+            "square" => Some(PregenShape::Square),
+            "circle" => Some(PregenShape::Circle),
+            _ => None,
+        }
+    }
+
+    pub fn name(self) -> &'static str {
+        match self {
+            PregenShape::Square => "square",
+            PregenShape::Circle => "circle",
+        }
+    }
+}
+
+/// An area of chunks to pregenerate.
+#[derive(Copy, Clone, Debug, PartialEq, Eq)]
+pub struct PregenArea {
+    pub center: ChunkPosition,
+    /// The radius in chunks.
+    pub radius: u32,
+    pub shape: PregenShape,
+}
+
+impl PregenArea {
+    pub fn contains(&self, pos: ChunkPosition) -> bool {
+        let dx = i64::from(pos.x - self.center.x);
+        let dz = i64::from(pos.z - self.center.z);
+        let radius = i64::from(self.radius);
+        match self.shape {
+            PregenShape::Square => dx.abs() <= radius && dz.abs() <= radius,
+            PregenShape::Circle => dx * dx + dz * dz <= radius * radius,
+        }
+    }
+
+    /// Returns the chunks of the area, ring by ring
+    /// outwards from the center.
+    pub fn chunks(&self) -> impl Iterator<Item = ChunkPosition> {
+        let area = *self;
+        Spiral {
+            center: self.center,
+            radius: self.radius as i32,
+            ring: 0,
+            index: 0,
+        }
+        .filter(move |&pos| area.contains(pos))
+    }
+}
+
+/// Iterates over the square rings around a center chunk.
+struct Spiral {
+    center: ChunkPosition,
+    radius: i32,
+    ring: i32,
+    /// Index in the current ring, clockwise from its north-west corner.
+    index: i32,
+}
+
+impl Iterator for Spiral {
+    type Item = ChunkPosition;
+
+    fn next(&mut self) -> Option<ChunkPosition> {
+        let ring = self.ring;
+        if ring > self.radius {
+            return None;
+        }
+
+        let side = 2 * ring;
+        let (x, z) = if ring == 0 {
+            (0, 0)
+        } else {
+            let offset = self.index % side;
+            match self.index / side {
+                0 => (-ring + offset, -ring),
+                1 => (ring, -ring + offset),
+                2 => (ring - offset, ring),
+                _ => (-ring, ring - offset),
+            }
+        };
+
+        self.index += 1;
+        if self.index >= (4 * side).max(1) {
+            self.ring += 1;
+            self.index = 0;
+        }
+        Some(ChunkPosition::new(self.center.x + x, self.center.z + z))
+    }
+}
+
+/// Progress of a pregeneration.
+#[derive(Copy, Clone, Debug, PartialEq)]
+pub struct PregenProgress {
+    pub completed: u64,
+    pub total: u64,
+    /// Chunks completed per second since the pregeneration
+    /// started or resumed.
+    pub chunks_per_second: f64,
+    /// Estimated time until the pregeneration finishes.
+    pub eta: Option<Duration>,
+}
+
+impl fmt::Display for PregenProgress {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        let percent = if self.total == 0 {
+            100.0
+        } else {
+            self.completed as f64 / self.total as f64 * 100.0
+        };
+        write!(
+            f,
+            "{}/{} chunks ({:.1}%), {:.1} chunks/s",
+            self.completed, self.total, percent, self.chunks_per_second
+        )?;
+        if let Some(eta) = self.eta {
+            let seconds = eta.as_secs();
+            write!(
+                f,
+                ", ETA {}h {:02}m {:02}s",
+                seconds / 3600,
+                seconds / 60 % 60,
+                seconds % 60
+            )?;
+        }
+        Ok(())
+    }
+}
+
+/// A running pregeneration.
+pub struct Pregeneration {
+    area: PregenArea,
+    chunks: Box<dyn Iterator<Item = ChunkPosition> + Send>,
+    /// Index of the next chunk to queue.
+    next_index: u64,
+    /// Queued chunks, with their index.
+    in_flight: AHashMap<ChunkPosition, u64>,
+    completed: u64,
+    total: u64,
+    started_at: Instant,
+    completed_at_start: u64,
+    last_report: Instant,
+    progress_file: PathBuf,
+}
+
+impl Pregeneration {
+    /// Starts pregenerating `area`, writing progress to `progress_file`.
+    pub fn new(area: PregenArea, progress_file: PathBuf) -> Self {
+        Self::resume(area, 0, progress_file)
+    }
+
+    /// Resumes pregenerating `area`, skipping the first `completed` chunks.
+    fn resume(area: PregenArea, completed: u64, progress_file: PathBuf) -> Self {
+        let total = area.chunks().count() as u64;
+        let completed = completed.min(total);
+        Self {
+            area,
+            chunks: Box::new(area.chunks().skip(completed as usize)),
+            next_index: completed,
+            in_flight: AHashMap::new(),
+            completed,
+            total,
+            started_at: Instant::now(),
+            completed_at_start: completed,
+            last_report: Instant::now(),
+            progress_file,
+        }
+    }
+
+    /// Loads a pregeneration from its progress file,
+    /// or returns `None` if the file doesn't exist.
+    pub fn load(progress_file: &Path) -> anyhow::Result<Option<Self>> {
+        if !progress_file.exists() {
+            return Ok(None);
+        }
+        let contents = fs::read_to_string(progress_file)?;
+        let mut values = AHashMap::new();
+        for line in contents.lines() {
+            let mut parts = line.splitn(2, '=');
+            if let (Some(key), Some(value)) = (parts.next(), parts.next()) {
+                values.insert(key.trim(), value.trim());
+            }
+        }
+        let value = |key: &str| {
+            values
+                .get(key)
+                .copied()
+                .with_context(|| format!("missing `{}`", key))
+        };
+        let integer = |key: &str| -> anyhow::Result<i64> {
+            value(key)?
+                .parse()
+                .with_context(|| format!("invalid `{}`", key))
+        };
+
+        let shape = match PregenShape::from_name(value("shape")?) {
+            Some(shape) => shape,
+            None => bail!("invalid `shape`"),
+        };
+        let area = PregenArea {
+            center: ChunkPosition::new(integer("center_x")? as i32, integer("center_z")? as i32),
+            radius: integer("radius")? as u32,
+            shape,
+        };
+        Ok(Some(Self::resume(
+            area,
+            integer("completed")? as u64,
+            progress_file.to_owned(),
+        )))
+    }
+
+    /// Writes the progress to the progress file. Chunks
+    /// still in flight are generated again after a restart.
+    pub fn save(&self) -> anyhow::Result<()> {
+        let completed = self
+            .in_flight
+            .values()
+            .min()
+            .copied()
+            .unwrap_or(self.next_index);
+        let contents = format!(
+            "center_x={}\ncenter_z={}\nradius={}\nshape={}\ncompleted={}\n",
+            self.area.center.x,
+            self.area.center.z,
+            self.area.radius,
+            self.area.shape.name(),
+            completed
+        );
+        fs::write(&self.progress_file, contents)?;
+        Ok(())
+    }
+
+    /// Deletes the progress file, once finished or cancelled.
+    pub fn remove_progress_file(&self) -> anyhow::Result<()> {
+        if self.progress_file.exists() {
+            fs::remove_file(&self.progress_file)?;
+        }
+        Ok(())
+    }
+
+    pub fn area(&self) -> PregenArea {
+        self.area
+    }
+
+    /// Takes the next chunk to pregenerate.
+    pub fn next_chunk(&mut self) -> Option<ChunkPosition> {
+        let pos = self.chunks.next()?;
+        self.in_flight.insert(pos, self.next_index);
+        self.next_index += 1;
+        Some(pos)
+    }
+
+    /// Marks a chunk as pregenerated. Returns `false`
+    /// if the chunk was not taken through `next_chunk`.
+    pub fn complete(&mut self, pos: ChunkPosition) -> bool {
+        let completed = self.in_flight.remove(&pos).is_some();
+        if completed {
+            self.completed += 1;
+        }
+        completed
+    }
+
+    /// Returns whether the given chunk was taken, but not yet completed.
+    pub fn is_in_flight(&self, pos: ChunkPosition) -> bool {
+        self.in_flight.contains_key(&pos)
+    }
+
+    pub fn num_in_flight(&self) -> usize {
+        self.in_flight.len()
+    }
+
+    pub fn is_finished(&self) -> bool {
+        self.next_index >= self.total && self.in_flight.is_empty()
+    }
+
+    /// Returns whether progress should be reported,
+    /// which is the case every few seconds.
+    pub fn report_due(&mut self) -> bool {
+        let due = self.last_report.elapsed() >= REPORT_INTERVAL;
+        if due {
+            self.last_report = Instant::now();
+        }
+        due
+    }
+
+    pub fn progress(&self) -> PregenProgress {
+        let elapsed = self.started_at.elapsed().as_secs_f64();
+        let completed_since_start = self.completed - self.completed_at_start;
+        let chunks_per_second = if elapsed > 0.0 {
+            completed_since_start as f64 / elapsed
+        } else {
+            0.0
+        };
+        let eta = if chunks_per_second > 0.0 {
+            let remaining = self.total.saturating_sub(self.completed);
+            Some(Duration::from_secs_f64(
+                remaining as f64 / chunks_per_second,
+            ))
+        } else {
+            None
+        };
+        PregenProgress {
+            completed: self.completed,
+            total: self.total,
+            chunks_per_second,
+            eta,
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use ahash::AHashSet;
+
+    #[test]
+    fn square_area_spirals_outwards() {
+        let area = PregenArea {
+            center: ChunkPosition::new(10, -4),
+            radius: 2,
+            shape: PregenShape::Square,
+        };
+        let chunks: Vec<_> = area.chunks().collect();
+        assert_eq!(chunks.len(), 25);
+        assert_eq!(chunks[0], area.center);
+        let unique: AHashSet<_> = chunks.iter().copied().collect();
+        assert_eq!(unique.len(), 25);
+        // The first ring is generated before the second.
+        for pos in &chunks[1..9] {
+            assert!((pos.x - 10).abs() <= 1 && (pos.z + 4).abs() <= 1);
+        }
+    }
+
+    #[test]
+    fn circle_area() {
+        let area = PregenArea {
+            center: ChunkPosition::new(0, 0),
+            radius: 3,
+            shape: PregenShape::Circle,
+        };
+        assert!(area.chunks().all(|pos| pos.x * pos.x + pos.z * pos.z <= 9));
+        assert!(!area.contains(ChunkPosition::new(3, 3)));
+        assert_eq!(area.chunks().count(), 29);
+    }
+
+    #[test]
+    fn resume_from_progress_file() {
+        let dir = std::env::temp_dir().join(format!("feather-pregen-{}", std::process::id()));
+        fs::create_dir_all(&dir).unwrap();
+        let file = dir.join(PROGRESS_FILE);
+        let area = PregenArea {
+            center: ChunkPosition::new(0, 0),
+            radius: 4,
+            shape: PregenShape::Square,
+        };
+
+        let mut pregeneration = Pregeneration::new(area, file.clone());
+        let first = pregeneration.next_chunk().unwrap();
+        let second = pregeneration.next_chunk().unwrap();
+        pregeneration.next_chunk().unwrap();
+        assert!(pregeneration.complete(first));
+        assert!(pregeneration.complete(second));
+        pregeneration.save().unwrap();
+
+        let resumed = Pregeneration::load(&file).unwrap().unwrap();
+        assert_eq!(resumed.area(), area);
+        // The third chunk was in flight, so it's generated again.
+        assert_eq!(resumed.progress().completed, 2);
+        assert_eq!(resumed.progress().total, 81);
+
+        resumed.remove_progress_file().unwrap();
+        assert!(Pregeneration::load(&file).unwrap().is_none());
+        fs::remove_dir_all(&dir).unwrap();
+    }
+}
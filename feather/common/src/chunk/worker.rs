@@ -8,7 +8,10 @@ use base::{
 use flume::{Receiver, Sender};
 use worldgen::WorldGenerator;
 
-use crate::region_worker::RegionWorker;
+use crate::{
+    chunk::generation::{GenerationPool, GenerationStats},
+    region_worker::RegionWorker,
+};
 
 #[derive(Debug)]
 pub struct LoadRequest {
@@ -18,6 +21,9 @@ pub struct LoadRequest {
 pub struct LoadedChunk {
     pub pos: ChunkPosition,
     pub chunk: Chunk,
+    pub block_entities: Vec<BlockEntityData>,
+    /// Whether the chunk was generated rather than loaded from the world save.
+    pub generated: bool,
 }
 
 #[derive(Debug)]
@@ -46,24 +52,25 @@ pub enum WorkerRequest {
     Save(SaveRequest),
 }
 pub struct ChunkWorker {
-    generator: Arc<dyn WorldGenerator>,
     send_req: Sender<WorkerRequest>,
-    send_gen: Sender<LoadedChunk>,
-    recv_gen: Receiver<LoadedChunk>, // Chunk generation should be infallible.
+    generation: GenerationPool,
     recv_load: Receiver<ChunkLoadResult>,
 }
 
 impl ChunkWorker {
-    pub fn new(world_dir: impl Into<PathBuf>, generator: Arc<dyn WorldGenerator>) -> Self {
+    /// Creates a worker which loads chunks from `world_dir`, and
+    /// generates missing chunks on `generation_threads` threads.
+    pub fn new(
+        world_dir: impl Into<PathBuf>,
+        generator: Arc<dyn WorldGenerator>,
+        generation_threads: usize,
+    ) -> Self {
         let (send_req, recv_req) = flume::unbounded();
-        let (send_gen, recv_gen) = flume::unbounded();
         let (region_worker, recv_load) = RegionWorker::new(world_dir.into(), recv_req);
         region_worker.start();
         Self {
-            generator,
             send_req,
-            send_gen,
-            recv_gen,
+            generation: GenerationPool::new(generator, generation_threads),
             recv_load,
         }
     }
@@ -71,16 +78,20 @@ impl ChunkWorker {
         self.send_req.send(WorkerRequest::Load(request)).unwrap()
     }
 
-    /// Helper function for poll_loaded_chunk. Attemts to receive a freshly generated chunk.
-    /// Function signature identical to that of poll_loaded_chunk for ease of use.
-    fn try_recv_gen(&mut self) -> Result<Option<LoadedChunk>, anyhow::Error> {
-        match self.recv_gen.try_recv() {
-            Ok(l) => Ok(Some(l)),
-            Err(e) => match e {
-                flume::TryRecvError::Empty => Ok(None),
-                flume::TryRecvError::Disconnected => bail!("chunkgen channel died"),
-            },
-        }
+    /// Cancels generation of the given chunk. Returns `false` if
+    /// it isn't queued for generation or its generation already started.
+    pub fn cancel_generation(&mut self, pos: ChunkPosition) -> bool {
+        self.generation.cancel(pos)
+    }
+
+    /// Sets the chunks players are in, so chunks
+    /// closest to them are generated first.
+    pub fn set_generation_focus(&mut self, focus: Vec<ChunkPosition>) {
+        self.generation.set_focus(focus);
+    }
+
+    pub fn generation_stats(&self) -> GenerationStats {
+        self.generation.stats()
     }
     pub fn poll_loaded_chunk(&mut self) -> Result<Option<LoadedChunk>, anyhow::Error> {
         match self.recv_load.try_recv() {
@@ -89,21 +100,15 @@ impl ChunkWorker {
                     // RegionWorker answered
                     ChunkLoadResult::Missing(pos) => {
                         // chunk does not exist, queue it for generation
-                        let send_gen = self.send_gen.clone();
-                        let gen = self.generator.clone();
-                        rayon::spawn(move || {
-                            // spawn task to generate chunk
-                            let chunk = gen.generate_chunk(pos);
-                            send_gen.send(LoadedChunk { pos, chunk }).unwrap()
-                        });
-                        self.try_recv_gen() // check for generated chunks
+                        self.generation.queue(pos);
+                        self.generation.try_recv() // check for generated chunks
                     }
                     ChunkLoadResult::Error(e) => Err(e),
                     ChunkLoadResult::Loaded(l) => Ok(Some(l)),
                 }
             }
             Err(e) => match e {
-                flume::TryRecvError::Empty => self.try_recv_gen(), // check for generated chunks
+                flume::TryRecvError::Empty => self.generation.try_recv(), // check for generated chunks
                 flume::TryRecvError::Disconnected => bail!("RegionWorker died"),
             },
         }
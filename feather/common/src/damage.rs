@@ -0,0 +1,175 @@
+//! Damage and death of players and mobs.
+//!
+//! Damage is dealt by triggering an [`EntityDamageEvent`], which
+//! is applied to the entity's [`Health`] on the next tick unless a
+//! plugin cancels it. An entity whose health drops to zero triggers
+//! an [`EntityDeathEvent`]. Dead mobs are removed, while dead players
+//! stay [`Dead`] until they ask to respawn.
+
+use base::Position;
+use ecs::{Entity, SysResult, SystemExecutor};
+use quill_common::{
+    components::{ActiveEffects, Health, Invulnerable},
+    entities::Player,
+    events::{EntityDamageEvent, EntityDeathEvent},
+};
+
+use crate::{effects, Game, WorldId};
+
+/// Health of players after they respawn.
+const PLAYER_MAX_HEALTH: f32 = 20.0;
+
+/// Marks players who died and have not respawned yet.
+#[derive(Copy, Clone, Debug)]
+pub struct Dead;
+
+pub fn register(systems: &mut SystemExecutor<Game>) {
+    systems.add_system(apply_damage).add_system(handle_deaths);
+}
+
+/// Deals `amount` damage to `entity` on the next tick.
+pub fn damage(game: &mut Game, entity: Entity, amount: f32) -> SysResult {
+    game.ecs
+        .insert_entity_event(entity, EntityDamageEvent::new(amount))?;
+    Ok(())
+}
+
+/// Brings a dead player back to life at the spawn point of the main world.
+pub fn respawn(game: &mut Game, player: Entity) -> SysResult {
+    if game.ecs.remove::<Dead>(player).is_err() {
+        return Ok(());
+    }
+    *game.ecs.get_mut::<Health>(player)? = Health(PLAYER_MAX_HEALTH);
+    if let Ok(mut effects) = game.ecs.get_mut::<ActiveEffects>(player) {
+        effects.clear();
+    }
+    game.change_world(player, WorldId::MAIN, Position::default())
+}
+
+/// Subtracts damage which was not cancelled from entities' health.
+fn apply_damage(game: &mut Game) -> SysResult {
+    for (_, (event, health, invulnerable, effects, dead)) in game
+        .ecs
+        .query::<(
+            &EntityDamageEvent,
+            &mut Health,
+            Option<&Invulnerable>,
+            Option<&ActiveEffects>,
+            Option<&Dead>,
+        )>()
+        .iter()
+    {
+        if event.cancelled || dead.is_some() || invulnerable.map_or(false, |i| i.0) {
+            continue;
+        }
+        let damage = effects.map_or(event.damage, |effects| {
+            effects::damage_taken(effects, event.damage)
+        });
+        health.0 = (health.0 - damage).max(0.0);
+    }
+    Ok(())
+}
+
+/// Triggers an `EntityDeathEvent` for entities whose health
+/// dropped to zero and removes dead mobs.
+fn handle_deaths(game: &mut Game) -> SysResult {
+    let mut died = Vec::new();
+    for (entity, (health, player, dead)) in game
+        .ecs
+        .query::<(&Health, Option<&Player>, Option<&Dead>)>()
+        .iter()
+    {
+        if health.0 <= 0.0 && dead.is_none() {
+            died.push((entity, player.is_some()));
+        }
+    }
+
+    for (entity, is_player) in died {
+        game.ecs.insert_entity_event(entity, EntityDeathEvent)?;
+        if is_player {
+            game.ecs.insert(entity, Dead)?;
+        } else {
+            game.remove_entity(entity)?;
+        }
+    }
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use base::{EntityKind, StatusEffect};
+    use quill_common::{components::EffectInstance, events::EntityRemoveEvent};
+
+    use super::*;
+
+    #[test]
+    fn damage_is_applied_next_tick() {
+        let mut game = Game::new();
+        let mob = game.ecs.spawn((EntityKind::Zombie, Health(20.0)));
+        damage(&mut game, mob, 6.0).unwrap();
+        assert_eq!(game.ecs.get::<Health>(mob).unwrap().0, 20.0);
+
+        apply_damage(&mut game).unwrap();
+        assert_eq!(game.ecs.get::<Health>(mob).unwrap().0, 14.0);
+    }
+
+    #[test]
+    fn cancelled_damage_is_ignored() {
+        let mut game = Game::new();
+        let mob = game.ecs.spawn((EntityKind::Zombie, Health(20.0)));
+        game.ecs
+            .insert_entity_event(
+                mob,
+                EntityDamageEvent {
+                    damage: 6.0,
+                    cancelled: true,
+                },
+            )
+            .unwrap();
+        apply_damage(&mut game).unwrap();
+        assert_eq!(game.ecs.get::<Health>(mob).unwrap().0, 20.0);
+    }
+
+    #[test]
+    fn resistance_and_invulnerability_reduce_damage() {
+        let mut game = Game::new();
+        let mut effects = ActiveEffects::new();
+        effects.add(EffectInstance::new(StatusEffect::Resistance, 0, 100));
+        let resistant = game.ecs.spawn((Health(20.0), effects));
+        let invulnerable = game.ecs.spawn((Health(20.0), Invulnerable(true)));
+        damage(&mut game, resistant, 10.0).unwrap();
+        damage(&mut game, invulnerable, 10.0).unwrap();
+        apply_damage(&mut game).unwrap();
+
+        assert_eq!(game.ecs.get::<Health>(resistant).unwrap().0, 12.0);
+        assert_eq!(game.ecs.get::<Health>(invulnerable).unwrap().0, 20.0);
+    }
+
+    #[test]
+    fn dead_mobs_are_removed() {
+        let mut game = Game::new();
+        let mob = game.ecs.spawn((EntityKind::Zombie, Health(4.0)));
+        damage(&mut game, mob, 10.0).unwrap();
+        apply_damage(&mut game).unwrap();
+        handle_deaths(&mut game).unwrap();
+
+        assert!(game.ecs.get::<EntityDeathEvent>(mob).is_ok());
+        assert!(game.ecs.get::<EntityRemoveEvent>(mob).is_ok());
+    }
+
+    #[test]
+    fn dead_players_stay_until_they_respawn() {
+        let mut game = Game::new();
+        let player = game
+            .ecs
+            .spawn((Player, Health(0.0), WorldId::MAIN, Position::default()));
+        handle_deaths(&mut game).unwrap();
+        assert!(game.ecs.get::<EntityDeathEvent>(player).is_ok());
+        assert!(game.ecs.get::<EntityRemoveEvent>(player).is_err());
+        assert!(game.ecs.get::<Dead>(player).is_ok());
+
+        damage(&mut game, player, 1.0).unwrap();
+        apply_damage(&mut game).unwrap();
+        assert_eq!(game.ecs.get::<Health>(player).unwrap().0, 0.0);
+    }
+}
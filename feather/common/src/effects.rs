@@ -0,0 +1,174 @@
+//! Ticking of status effects.
+//!
+//! Effects which only change client-side behavior, like
+//! jump boost or night vision, are applied by the client
+//! once it is notified of the effect. Effects which change
+//! server-side state are implemented here.
+
+use base::StatusEffect;
+use ecs::{SysResult, SystemExecutor};
+use quill_common::components::{ActiveEffects, EffectInstance, Health};
+
+use crate::Game;
+
+/// Maximum health of players and mobs.
+const MAX_HEALTH: f32 = 20.0;
+
+pub fn register(systems: &mut SystemExecutor<Game>) {
+    systems.add_system(tick_effects);
+}
+
+/// Applies effects to entities and removes expired effects.
+fn tick_effects(game: &mut Game) -> SysResult {
+    for (_, (effects, health)) in game
+        .ecs
+        .query::<(&mut ActiveEffects, Option<&mut Health>)>()
+        .iter()
+    {
+        let mut health = health;
+        for instance in effects.iter_mut() {
+            if let Some(health) = health.as_deref_mut() {
+                apply_effect(instance, health);
+            }
+            instance.duration = instance.duration.saturating_sub(1);
+        }
+        effects.retain(|instance| instance.duration > 0 && !instance.effect.is_instant());
+    }
+    Ok(())
+}
+
+/// Applies one tick of an effect to an entity's health.
+fn apply_effect(instance: &EffectInstance, health: &mut Health) {
+    match instance.effect {
+        StatusEffect::Regeneration => {
+            if is_effect_tick(instance, 50) {
+                heal(health, 1.0);
+            }
+        }
+        StatusEffect::Poison => {
+            // Poison can't kill.
+            if is_effect_tick(instance, 25) && health.0 > 1.0 {
+                health.0 = (health.0 - 1.0).max(1.0);
+            }
+        }
+        StatusEffect::Wither => {
+            if is_effect_tick(instance, 40) {
+                damage(health, 1.0);
+            }
+        }
+        StatusEffect::InstantHealth => heal(health, (4 << instance.amplifier.min(8)) as f32),
+        StatusEffect::InstantDamage => damage(health, (6 << instance.amplifier.min(8)) as f32),
+        _ => {}
+    }
+}
+
+/// Returns whether an effect which applies every `interval`
+/// ticks at level I should apply this tick. Higher levels
+/// halve the interval.
+fn is_effect_tick(instance: &EffectInstance, interval: u32) -> bool {
+    let interval = interval.checked_shr(instance.amplifier as u32).unwrap_or(0);
+    interval == 0 || instance.duration % interval == 0
+}
+
+fn heal(health: &mut Health, amount: f32) {
+    health.0 = (health.0 + amount).min(MAX_HEALTH);
+}
+
+fn damage(health: &mut Health, amount: f32) {
+    health.0 = (health.0 - amount).max(0.0);
+}
+
+/// Returns the factor by which effects change
+/// the movement speed of a mob.
+pub fn movement_speed_multiplier(effects: &ActiveEffects) -> f64 {
+    let speed = effects.level(StatusEffect::Speed) as f64;
+    let slowness = effects.level(StatusEffect::Slowness) as f64;
+    ((1.0 + 0.2 * speed) * (1.0 - 0.15 * slowness)).max(0.0)
+}
+
+/// Modifies the damage of a melee attack
+/// according to the attacker's effects.
+pub fn attack_damage(effects: &ActiveEffects, damage: f32) -> f32 {
+    let strength = effects.level(StatusEffect::Strength) as f32;
+    let weakness = effects.level(StatusEffect::Weakness) as f32;
+    (damage + 3.0 * strength - 4.0 * weakness).max(0.0)
+}
+
+/// Modifies the damage taken by an entity
+/// according to its effects.
+pub fn damage_taken(effects: &ActiveEffects, damage: f32) -> f32 {
+    let resistance = effects.level(StatusEffect::Resistance) as f32;
+    (damage * (1.0 - 0.2 * resistance)).max(0.0)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn tick(effects: &mut ActiveEffects, health: &mut Health, ticks: u32) {
+        let mut game = Game::new();
+        let entity = game.ecs.spawn((effects.clone(), *health));
+        for _ in 0..ticks {
+            tick_effects(&mut game).unwrap();
+        }
+        *effects = game.ecs.get::<ActiveEffects>(entity).unwrap().clone();
+        *health = *game.ecs.get::<Health>(entity).unwrap();
+    }
+
+    #[test]
+    fn effects_expire() {
+        let mut effects = ActiveEffects::new();
+        effects.add(EffectInstance::new(StatusEffect::Speed, 0, 10));
+        effects.add(EffectInstance::new(StatusEffect::NightVision, 0, 20));
+        let mut health = Health(20.0);
+
+        tick(&mut effects, &mut health, 10);
+        assert!(!effects.contains(StatusEffect::Speed));
+        assert_eq!(effects.get(StatusEffect::NightVision).unwrap().duration, 10);
+
+        tick(&mut effects, &mut health, 10);
+        assert!(effects.is_empty());
+    }
+
+    #[test]
+    fn poison_does_not_kill() {
+        let mut effects = ActiveEffects::new();
+        effects.add(EffectInstance::new(StatusEffect::Poison, 3, 1000));
+        let mut health = Health(5.0);
+
+        tick(&mut effects, &mut health, 200);
+        assert_eq!(health.0, 1.0);
+    }
+
+    #[test]
+    fn regeneration_heals() {
+        let mut effects = ActiveEffects::new();
+        effects.add(EffectInstance::new(StatusEffect::Regeneration, 0, 100));
+        let mut health = Health(10.0);
+
+        tick(&mut effects, &mut health, 100);
+        assert_eq!(health.0, 12.0);
+    }
+
+    #[test]
+    fn instant_effects_apply_once() {
+        let mut effects = ActiveEffects::new();
+        effects.add(EffectInstance::new(StatusEffect::InstantDamage, 0, 1));
+        let mut health = Health(20.0);
+
+        tick(&mut effects, &mut health, 5);
+        assert_eq!(health.0, 14.0);
+        assert!(effects.is_empty());
+    }
+
+    #[test]
+    fn stronger_effects_replace_weaker_ones() {
+        let mut effects = ActiveEffects::new();
+        assert!(effects.add(EffectInstance::new(StatusEffect::Speed, 0, 100)));
+        assert!(!effects.add(EffectInstance::new(StatusEffect::Speed, 0, 50)));
+        assert!(effects.add(EffectInstance::new(StatusEffect::Speed, 1, 20)));
+        assert_eq!(effects.level(StatusEffect::Speed), 2);
+
+        assert!((movement_speed_multiplier(&effects) - 1.4).abs() < 1e-9);
+    }
+}
@@ -5,7 +5,10 @@
 //! add default components for that entity.
 
 use ecs::EntityBuilder;
-use quill_common::{components::OnGround, entity_init::EntityInit};
+use quill_common::{
+    components::{Health, OnGround},
+    entity_init::EntityInit,
+};
 use uuid::Uuid;
 
 /// Adds default components shared between all entities.
@@ -13,6 +16,13 @@ fn build_default(builder: &mut EntityBuilder) {
     builder.add(Uuid::new_v4()).add(OnGround(true));
 }
 
+/// Adds default components shared between all mobs,
+/// which spawn with `max_health`.
+fn build_mob(builder: &mut EntityBuilder, max_health: f32) {
+    build_default(builder);
+    builder.add(Health(max_health));
+}
+
 pub mod area_effect_cloud;
 pub mod armor_stand;
 pub mod arrow;
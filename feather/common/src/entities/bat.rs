@@ -3,6 +3,6 @@ use ecs::EntityBuilder;
 use quill_common::entities::Bat;
 
 pub fn build_default(builder: &mut EntityBuilder) {
-    super::build_default(builder);
+    super::build_mob(builder, 6.0);
     builder.add(Bat).add(EntityKind::Bat);
 }
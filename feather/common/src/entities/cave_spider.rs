@@ -3,6 +3,6 @@ use ecs::EntityBuilder;
 use quill_common::entities::CaveSpider;
 
 pub fn build_default(builder: &mut EntityBuilder) {
-    super::build_default(builder);
+    super::build_mob(builder, 12.0);
     builder.add(CaveSpider).add(EntityKind::CaveSpider);
 }
@@ -2,7 +2,12 @@ use base::EntityKind;
 use ecs::EntityBuilder;
 use quill_common::entities::Chicken;
 
+use crate::ai;
+
 pub fn build_default(builder: &mut EntityBuilder) {
-    super::build_default(builder);
-    builder.add(Chicken).add(EntityKind::Chicken);
+    super::build_mob(builder, 4.0);
+    builder
+        .add(Chicken)
+        .add(EntityKind::Chicken)
+        .add(ai::passive_mob_goals());
 }
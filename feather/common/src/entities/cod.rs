@@ -3,6 +3,6 @@ use ecs::EntityBuilder;
 use quill_common::entities::Cod;
 
 pub fn build_default(builder: &mut EntityBuilder) {
-    super::build_default(builder);
+    super::build_mob(builder, 3.0);
     builder.add(Cod).add(EntityKind::Cod);
 }
@@ -2,7 +2,12 @@ use base::EntityKind;
 use ecs::EntityBuilder;
 use quill_common::entities::Cow;
 
+use crate::ai;
+
 pub fn build_default(builder: &mut EntityBuilder) {
-    super::build_default(builder);
-    builder.add(Cow).add(EntityKind::Cow);
+    super::build_mob(builder, 10.0);
+    builder
+        .add(Cow)
+        .add(EntityKind::Cow)
+        .add(ai::passive_mob_goals());
 }
@@ -3,6 +3,6 @@ use ecs::EntityBuilder;
 use quill_common::entities::Donkey;
 
 pub fn build_default(builder: &mut EntityBuilder) {
-    super::build_default(builder);
+    super::build_mob(builder, 15.0);
     builder.add(Donkey).add(EntityKind::Donkey);
 }
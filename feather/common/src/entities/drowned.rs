@@ -2,7 +2,12 @@ use base::EntityKind;
 use ecs::EntityBuilder;
 use quill_common::entities::Drowned;
 
+use crate::ai;
+
 pub fn build_default(builder: &mut EntityBuilder) {
-    super::build_default(builder);
-    builder.add(Drowned).add(EntityKind::Drowned);
+    super::build_mob(builder, 20.0);
+    builder
+        .add(Drowned)
+        .add(EntityKind::Drowned)
+        .add(ai::melee_mob_goals());
 }
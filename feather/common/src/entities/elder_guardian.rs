@@ -3,6 +3,6 @@ use ecs::EntityBuilder;
 use quill_common::entities::ElderGuardian;
 
 pub fn build_default(builder: &mut EntityBuilder) {
-    super::build_default(builder);
+    super::build_mob(builder, 80.0);
     builder.add(ElderGuardian).add(EntityKind::ElderGuardian);
 }
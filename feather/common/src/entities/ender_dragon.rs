@@ -3,6 +3,6 @@ use ecs::EntityBuilder;
 use quill_common::entities::EnderDragon;
 
 pub fn build_default(builder: &mut EntityBuilder) {
-    super::build_default(builder);
+    super::build_mob(builder, 200.0);
     builder.add(EnderDragon).add(EntityKind::EnderDragon);
 }
@@ -3,6 +3,6 @@ use ecs::EntityBuilder;
 use quill_common::entities::Endermite;
 
 pub fn build_default(builder: &mut EntityBuilder) {
-    super::build_default(builder);
+    super::build_mob(builder, 8.0);
     builder.add(Endermite).add(EntityKind::Endermite);
 }
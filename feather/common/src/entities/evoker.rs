@@ -3,6 +3,6 @@ use ecs::EntityBuilder;
 use quill_common::entities::Evoker;
 
 pub fn build_default(builder: &mut EntityBuilder) {
-    super::build_default(builder);
+    super::build_mob(builder, 24.0);
     builder.add(Evoker).add(EntityKind::Evoker);
 }
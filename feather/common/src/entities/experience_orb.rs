@@ -2,7 +2,13 @@ use base::EntityKind;
 use ecs::EntityBuilder;
 use quill_common::entities::ExperienceOrb;
 
+use crate::experience::{ExperienceOrbValue, OrbAge};
+
 pub fn build_default(builder: &mut EntityBuilder) {
     super::build_default(builder);
-    builder.add(ExperienceOrb).add(EntityKind::ExperienceOrb);
+    builder
+        .add(ExperienceOrb)
+        .add(EntityKind::ExperienceOrb)
+        .add(ExperienceOrbValue(1))
+        .add(OrbAge::default());
 }
@@ -3,6 +3,6 @@ use ecs::EntityBuilder;
 use quill_common::entities::Giant;
 
 pub fn build_default(builder: &mut EntityBuilder) {
-    super::build_default(builder);
+    super::build_mob(builder, 100.0);
     builder.add(Giant).add(EntityKind::Giant);
 }
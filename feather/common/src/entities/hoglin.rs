@@ -3,6 +3,6 @@ use ecs::EntityBuilder;
 use quill_common::entities::Hoglin;
 
 pub fn build_default(builder: &mut EntityBuilder) {
-    super::build_default(builder);
+    super::build_mob(builder, 40.0);
     builder.add(Hoglin).add(EntityKind::Hoglin);
 }
@@ -2,7 +2,12 @@ use base::EntityKind;
 use ecs::EntityBuilder;
 use quill_common::entities::Husk;
 
+use crate::ai;
+
 pub fn build_default(builder: &mut EntityBuilder) {
-    super::build_default(builder);
-    builder.add(Husk).add(EntityKind::Husk);
+    super::build_mob(builder, 20.0);
+    builder
+        .add(Husk)
+        .add(EntityKind::Husk)
+        .add(ai::melee_mob_goals());
 }
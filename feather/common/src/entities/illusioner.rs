@@ -3,6 +3,6 @@ use ecs::EntityBuilder;
 use quill_common::entities::Illusioner;
 
 pub fn build_default(builder: &mut EntityBuilder) {
-    super::build_default(builder);
+    super::build_mob(builder, 32.0);
     builder.add(Illusioner).add(EntityKind::Illusioner);
 }
@@ -3,6 +3,6 @@ use ecs::EntityBuilder;
 use quill_common::entities::Ocelot;
 
 pub fn build_default(builder: &mut EntityBuilder) {
-    super::build_default(builder);
+    super::build_mob(builder, 10.0);
     builder.add(Ocelot).add(EntityKind::Ocelot);
 }
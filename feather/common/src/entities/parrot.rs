@@ -3,6 +3,6 @@ use ecs::EntityBuilder;
 use quill_common::entities::Parrot;
 
 pub fn build_default(builder: &mut EntityBuilder) {
-    super::build_default(builder);
+    super::build_mob(builder, 6.0);
     builder.add(Parrot).add(EntityKind::Parrot);
 }
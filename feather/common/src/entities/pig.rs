@@ -2,7 +2,12 @@ use base::EntityKind;
 use ecs::EntityBuilder;
 use quill_common::entities::Pig;
 
+use crate::ai;
+
 pub fn build_default(builder: &mut EntityBuilder) {
-    super::build_default(builder);
-    builder.add(Pig).add(EntityKind::Pig);
+    super::build_mob(builder, 10.0);
+    builder
+        .add(Pig)
+        .add(EntityKind::Pig)
+        .add(ai::passive_mob_goals());
 }
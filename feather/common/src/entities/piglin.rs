@@ -3,6 +3,6 @@ use ecs::EntityBuilder;
 use quill_common::entities::Piglin;
 
 pub fn build_default(builder: &mut EntityBuilder) {
-    super::build_default(builder);
+    super::build_mob(builder, 16.0);
     builder.add(Piglin).add(EntityKind::Piglin);
 }
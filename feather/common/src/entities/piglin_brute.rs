@@ -3,6 +3,6 @@ use ecs::EntityBuilder;
 use quill_common::entities::PiglinBrute;
 
 pub fn build_default(builder: &mut EntityBuilder) {
-    super::build_default(builder);
+    super::build_mob(builder, 50.0);
     builder.add(PiglinBrute).add(EntityKind::PiglinBrute);
 }
@@ -3,6 +3,6 @@ use ecs::EntityBuilder;
 use quill_common::entities::Pufferfish;
 
 pub fn build_default(builder: &mut EntityBuilder) {
-    super::build_default(builder);
+    super::build_mob(builder, 3.0);
     builder.add(Pufferfish).add(EntityKind::Pufferfish);
 }
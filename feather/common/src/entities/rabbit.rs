@@ -2,7 +2,12 @@ use base::EntityKind;
 use ecs::EntityBuilder;
 use quill_common::entities::Rabbit;
 
+use crate::ai;
+
 pub fn build_default(builder: &mut EntityBuilder) {
-    super::build_default(builder);
-    builder.add(Rabbit).add(EntityKind::Rabbit);
+    super::build_mob(builder, 3.0);
+    builder
+        .add(Rabbit)
+        .add(EntityKind::Rabbit)
+        .add(ai::skittish_mob_goals());
 }
@@ -3,6 +3,6 @@ use ecs::EntityBuilder;
 use quill_common::entities::Ravager;
 
 pub fn build_default(builder: &mut EntityBuilder) {
-    super::build_default(builder);
+    super::build_mob(builder, 100.0);
     builder.add(Ravager).add(EntityKind::Ravager);
 }
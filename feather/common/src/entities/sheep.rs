@@ -2,7 +2,12 @@ use base::EntityKind;
 use ecs::EntityBuilder;
 use quill_common::entities::Sheep;
 
+use crate::ai;
+
 pub fn build_default(builder: &mut EntityBuilder) {
-    super::build_default(builder);
-    builder.add(Sheep).add(EntityKind::Sheep);
+    super::build_mob(builder, 8.0);
+    builder
+        .add(Sheep)
+        .add(EntityKind::Sheep)
+        .add(ai::passive_mob_goals());
 }
@@ -3,6 +3,6 @@ use ecs::EntityBuilder;
 use quill_common::entities::Shulker;
 
 pub fn build_default(builder: &mut EntityBuilder) {
-    super::build_default(builder);
+    super::build_mob(builder, 30.0);
     builder.add(Shulker).add(EntityKind::Shulker);
 }
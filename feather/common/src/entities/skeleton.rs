@@ -2,7 +2,12 @@ use base::EntityKind;
 use ecs::EntityBuilder;
 use quill_common::entities::Skeleton;
 
+use crate::ai;
+
 pub fn build_default(builder: &mut EntityBuilder) {
-    super::build_default(builder);
-    builder.add(Skeleton).add(EntityKind::Skeleton);
+    super::build_mob(builder, 20.0);
+    builder
+        .add(Skeleton)
+        .add(EntityKind::Skeleton)
+        .add(ai::ranged_mob_goals());
 }
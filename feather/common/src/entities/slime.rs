@@ -3,6 +3,6 @@ use ecs::EntityBuilder;
 use quill_common::entities::Slime;
 
 pub fn build_default(builder: &mut EntityBuilder) {
-    super::build_default(builder);
+    super::build_mob(builder, 1.0);
     builder.add(Slime).add(EntityKind::Slime);
 }
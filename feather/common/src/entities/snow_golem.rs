@@ -3,6 +3,6 @@ use ecs::EntityBuilder;
 use quill_common::entities::SnowGolem;
 
 pub fn build_default(builder: &mut EntityBuilder) {
-    super::build_default(builder);
+    super::build_mob(builder, 4.0);
     builder.add(SnowGolem).add(EntityKind::SnowGolem);
 }
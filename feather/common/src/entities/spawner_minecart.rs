@@ -2,9 +2,12 @@ use base::EntityKind;
 use ecs::EntityBuilder;
 use quill_common::entities::SpawnerMinecart;
 
+use crate::spawner::MobSpawner;
+
 pub fn build_default(builder: &mut EntityBuilder) {
     super::build_default(builder);
     builder
         .add(SpawnerMinecart)
-        .add(EntityKind::SpawnerMinecart);
+        .add(EntityKind::SpawnerMinecart)
+        .add(MobSpawner::default());
 }
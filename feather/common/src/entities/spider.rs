@@ -3,6 +3,6 @@ use ecs::EntityBuilder;
 use quill_common::entities::Spider;
 
 pub fn build_default(builder: &mut EntityBuilder) {
-    super::build_default(builder);
+    super::build_mob(builder, 16.0);
     builder.add(Spider).add(EntityKind::Spider);
 }
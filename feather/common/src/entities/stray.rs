@@ -2,7 +2,12 @@ use base::EntityKind;
 use ecs::EntityBuilder;
 use quill_common::entities::Stray;
 
+use crate::ai;
+
 pub fn build_default(builder: &mut EntityBuilder) {
-    super::build_default(builder);
-    builder.add(Stray).add(EntityKind::Stray);
+    super::build_mob(builder, 20.0);
+    builder
+        .add(Stray)
+        .add(EntityKind::Stray)
+        .add(ai::ranged_mob_goals());
 }
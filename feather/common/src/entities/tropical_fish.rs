@@ -3,6 +3,6 @@ use ecs::EntityBuilder;
 use quill_common::entities::TropicalFish;
 
 pub fn build_default(builder: &mut EntityBuilder) {
-    super::build_default(builder);
+    super::build_mob(builder, 3.0);
     builder.add(TropicalFish).add(EntityKind::TropicalFish);
 }
@@ -3,6 +3,6 @@ use ecs::EntityBuilder;
 use quill_common::entities::Vex;
 
 pub fn build_default(builder: &mut EntityBuilder) {
-    super::build_default(builder);
+    super::build_mob(builder, 14.0);
     builder.add(Vex).add(EntityKind::Vex);
 }
@@ -3,7 +3,7 @@ use ecs::EntityBuilder;
 use quill_common::entities::WanderingTrader;
 
 pub fn build_default(builder: &mut EntityBuilder) {
-    super::build_default(builder);
+    super::build_mob(builder, 20.0);
     builder
         .add(WanderingTrader)
         .add(EntityKind::WanderingTrader);
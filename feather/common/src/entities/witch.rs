@@ -3,6 +3,6 @@ use ecs::EntityBuilder;
 use quill_common::entities::Witch;
 
 pub fn build_default(builder: &mut EntityBuilder) {
-    super::build_default(builder);
+    super::build_mob(builder, 26.0);
     builder.add(Witch).add(EntityKind::Witch);
 }
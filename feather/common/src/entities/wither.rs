@@ -3,6 +3,6 @@ use ecs::EntityBuilder;
 use quill_common::entities::Wither;
 
 pub fn build_default(builder: &mut EntityBuilder) {
-    super::build_default(builder);
+    super::build_mob(builder, 300.0);
     builder.add(Wither).add(EntityKind::Wither);
 }
@@ -3,6 +3,6 @@ use ecs::EntityBuilder;
 use quill_common::entities::Zoglin;
 
 pub fn build_default(builder: &mut EntityBuilder) {
-    super::build_default(builder);
+    super::build_mob(builder, 40.0);
     builder.add(Zoglin).add(EntityKind::Zoglin);
 }
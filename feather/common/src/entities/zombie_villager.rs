@@ -3,6 +3,6 @@ use ecs::EntityBuilder;
 use quill_common::entities::ZombieVillager;
 
 pub fn build_default(builder: &mut EntityBuilder) {
-    super::build_default(builder);
+    super::build_mob(builder, 20.0);
     builder.add(ZombieVillager).add(EntityKind::ZombieVillager);
 }
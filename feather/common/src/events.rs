@@ -1,6 +1,6 @@
-use base::{ChunkHandle, ChunkPosition};
+use base::{anvil::block_entity::BlockEntityData, ChunkHandle, ChunkPosition};
 
-use crate::view::View;
+use crate::{view::View, WorldId};
 
 mod block_change;
 mod plugin_message;
@@ -42,19 +42,39 @@ impl ViewUpdateEvent {
 /// Unlike [`ViewUpdateEvent`], this event triggers for all entities,
 /// not just players.
 pub struct ChunkCrossEvent {
+    pub old_world: WorldId,
     pub old_chunk: ChunkPosition,
+    pub new_world: WorldId,
     pub new_chunk: ChunkPosition,
 }
 
+/// Event triggered when an entity is moved to another world
+/// through [`Game::change_world`](crate::Game::change_world).
+#[derive(Debug)]
+pub struct WorldChangeEvent {
+    pub old_world: WorldId,
+    pub new_world: WorldId,
+}
+
 /// Triggered when a chunk is loaded.
 #[derive(Debug)]
 pub struct ChunkLoadEvent {
+    pub world: WorldId,
     pub position: ChunkPosition,
     pub chunk: ChunkHandle,
+    /// Block entities stored in the chunk.
+    pub block_entities: Vec<BlockEntityData>,
 }
 
 /// Triggered when an error occurs while loading a chunk.
 #[derive(Debug)]
 pub struct ChunkLoadFailEvent {
+    pub world: WorldId,
     pub position: ChunkPosition,
 }
+
+/// Triggered on a player when the server changes
+/// the contents of their inventory, for example when
+/// an item is repaired by Mending.
+#[derive(Debug)]
+pub struct InventoryUpdateEvent;
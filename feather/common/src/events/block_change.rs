@@ -6,6 +6,8 @@ use base::{
 };
 use itertools::Either;
 
+use crate::WorldId;
+
 /// Event triggered when one or more blocks are changed.
 ///
 /// This event can efficiently store bulk block updates
@@ -13,25 +15,33 @@ use itertools::Either;
 /// is cheap as it is, at worst, cloning an `Arc`.
 #[derive(Debug, Clone)]
 pub struct BlockChangeEvent {
+    world: WorldId,
     changes: BlockChanges,
 }
 
 impl BlockChangeEvent {
     /// Creates an event affecting a single block.
-    pub fn single(pos: ValidBlockPosition) -> Self {
+    pub fn single(world: WorldId, pos: ValidBlockPosition) -> Self {
         Self {
+            world,
             changes: BlockChanges::Single { pos },
         }
     }
 
     /// Creates an event corresponding to a block update
     /// that fills an entire chunk section with the same block.
-    pub fn fill_chunk_section(chunk: ChunkPosition, section: u32) -> Self {
+    pub fn fill_chunk_section(world: WorldId, chunk: ChunkPosition, section: u32) -> Self {
         Self {
+            world,
             changes: BlockChanges::FillChunkSection { chunk, section },
         }
     }
 
+    /// Returns the world in which blocks were changed.
+    pub fn world(&self) -> WorldId {
+        self.world
+    }
+
     /// Determines the number of blocks that were
     /// changed in this block change event.
     pub fn count(&self) -> usize {
@@ -104,7 +114,8 @@ mod tests {
     #[test]
     fn create_single() {
         let pos = BlockPosition::new(5, 64, 9).try_into().unwrap();
-        let event = BlockChangeEvent::single(pos);
+        let event = BlockChangeEvent::single(WorldId::MAIN, pos);
+        assert_eq!(event.world(), WorldId::MAIN);
         assert_eq!(event.count(), 1);
         assert_eq!(event.iter_changed_blocks().collect::<Vec<_>>(), vec![pos]);
         assert_eq!(
@@ -117,7 +128,7 @@ mod tests {
     fn create_chunk_section_fill() {
         let chunk = ChunkPosition::new(10, 15);
         let section_y = 5;
-        let event = BlockChangeEvent::fill_chunk_section(chunk, section_y);
+        let event = BlockChangeEvent::fill_chunk_section(WorldId::MAIN, chunk, section_y);
         assert_eq!(event.count(), SECTION_VOLUME);
         assert_eq!(event.iter_changed_blocks().count(), SECTION_VOLUME);
         assert_eq!(
@@ -0,0 +1,545 @@
+//! Experience orbs and player experience.
+//!
+//! Orbs are dropped by dying mobs and mined ores. They are
+//! attracted to nearby players, who collect them to gain
+//! experience or to repair items enchanted with Mending.
+//! Taking smelted items out of a furnace grants experience directly.
+//!
+//! Data sourced from: <https://minecraft.fandom.com/wiki/Experience>
+
+use base::{Area, BlockKind, EntityKind, Gamemode, Inventory, Item, Position, ValidBlockPosition};
+use ecs::{Entity, SysResult, SystemExecutor};
+use libcraft_core::MobCategory;
+use libcraft_items::EnchantmentKind;
+use quill_common::{
+    components::Experience, entities::Player, entity_init::EntityInit, events::EntityDeathEvent,
+};
+use rand::{prelude::SliceRandom, Rng};
+
+use crate::{entities::player::HotbarSlot, events::InventoryUpdateEvent, Game, WorldId};
+
+/// Values of the orbs experience is split into when dropped.
+const ORB_SIZES: [u32; 11] = [2477, 1237, 617, 307, 149, 73, 37, 17, 7, 3, 1];
+
+/// Distance in blocks from which orbs are attracted to players.
+const ATTRACTION_RANGE: f64 = 8.0;
+
+/// Distance in blocks from which players collect orbs.
+const PICKUP_RANGE: f64 = 1.0;
+
+/// Distance in blocks within which orbs merge.
+const MERGE_RANGE: f64 = 0.5;
+
+/// Ticks after which orbs despawn.
+const ORB_LIFETIME: u32 = 6000;
+
+/// The number of experience points an orb is worth.
+#[derive(Copy, Clone, Debug, PartialEq, Eq)]
+pub struct ExperienceOrbValue(pub u32);
+
+/// Number of ticks an orb has existed for.
+#[derive(Copy, Clone, Debug, Default)]
+pub struct OrbAge(pub u32);
+
+pub fn register(systems: &mut SystemExecutor<Game>) {
+    systems
+        .add_system(drop_mob_experience)
+        .add_system(tick_orbs);
+}
+
+/// Splits an amount of experience into orb values.
+pub fn split_into_orbs(mut amount: u32) -> Vec<u32> {
+    let mut orbs = Vec::new();
+    while amount > 0 {
+        let value = ORB_SIZES
+            .iter()
+            .copied()
+            .find(|&size| size <= amount)
+            .unwrap_or(1);
+        orbs.push(value);
+        amount -= value;
+    }
+    orbs
+}
+
+/// Spawns orbs worth `amount` experience points in total.
+pub fn spawn_experience(game: &mut Game, world: WorldId, position: Position, amount: u32) {
+    let mut rng = rand::thread_rng();
+    for value in split_into_orbs(amount) {
+        let position = Position {
+            x: position.x + rng.gen_range(-0.25..=0.25),
+            z: position.z + rng.gen_range(-0.25..=0.25),
+            ..position
+        };
+        let mut builder = game.create_entity_builder(position, EntityInit::ExperienceOrb);
+        builder
+            .add(world)
+            .add(ExperienceOrbValue(value))
+            .add(OrbAge(0));
+        game.spawn_entity(builder);
+    }
+}
+
+/// Returns the experience dropped by a mob when killed.
+pub fn mob_experience(kind: EntityKind, rng: &mut impl Rng) -> u32 {
+    use EntityKind::*;
+    match kind {
+        Blaze | ElderGuardian | Evoker | Guardian => 10,
+        Wither => 50,
+        EnderDragon => 12000,
+        Slime | MagmaCube => 4,
+        Bat | IronGolem | SnowGolem | Villager | WanderingTrader => 0,
+        Cod | Pufferfish | Salmon | TropicalFish | Squid | Dolphin => rng.gen_range(1..=3),
+        kind => match MobCategory::of(kind) {
+            Some(MobCategory::Monster) => 5,
+            Some(_) => rng.gen_range(1..=3),
+            None => 0,
+        },
+    }
+}
+
+/// Returns the experience dropped when a block is mined
+/// without Silk Touch.
+pub fn block_experience(kind: BlockKind, rng: &mut impl Rng) -> u32 {
+    match kind {
+        BlockKind::CoalOre => rng.gen_range(0..=2),
+        BlockKind::NetherGoldOre => rng.gen_range(0..=1),
+        BlockKind::DiamondOre | BlockKind::EmeraldOre => rng.gen_range(3..=7),
+        BlockKind::LapisOre | BlockKind::NetherQuartzOre => rng.gen_range(2..=5),
+        BlockKind::RedstoneOre => rng.gen_range(1..=5),
+        BlockKind::Spawner => rng.gen_range(15..=43),
+        _ => 0,
+    }
+}
+
+/// Returns the experience gained per item when
+/// taking `item` out of a furnace.
+pub fn smelting_experience(item: Item) -> f32 {
+    match item {
+        Item::NetheriteScrap => 2.0,
+        Item::GoldIngot | Item::Diamond | Item::Emerald | Item::GreenDye => 1.0,
+        Item::IronIngot | Item::Redstone => 0.7,
+        Item::CookedBeef
+        | Item::CookedPorkchop
+        | Item::CookedChicken
+        | Item::CookedMutton
+        | Item::CookedRabbit
+        | Item::CookedCod
+        | Item::CookedSalmon
+        | Item::BakedPotato
+        | Item::Terracotta => 0.35,
+        Item::Brick => 0.3,
+        Item::LapisLazuli | Item::Quartz => 0.2,
+        Item::Charcoal | Item::Sponge => 0.15,
+        Item::Coal
+        | Item::Glass
+        | Item::Stone
+        | Item::SmoothStone
+        | Item::NetherBrick
+        | Item::DriedKelp
+        | Item::LimeDye
+        | Item::PoppedChorusFruit
+        | Item::CrackedStoneBricks
+        | Item::IronNugget
+        | Item::GoldNugget
+        | Item::SmoothSandstone
+        | Item::SmoothRedSandstone
+        | Item::SmoothQuartz
+        | Item::CrackedNetherBricks
+        | Item::CrackedPolishedBlackstoneBricks => 0.1,
+        _ => 0.0,
+    }
+}
+
+/// Returns the experience points gained when taking `count`
+/// smelted `item`s out of a furnace. Fractional points are
+/// rounded up at random.
+pub fn smelting_experience_points(item: Item, count: u32, rng: &mut impl Rng) -> u32 {
+    let experience = smelting_experience(item) * count as f32;
+    let points = experience.floor();
+    let fraction = experience - points;
+    if fraction > 0.0 && rng.gen::<f32>() < fraction {
+        points as u32 + 1
+    } else {
+        points as u32
+    }
+}
+
+/// Drops the experience for a block mined by `player`.
+///
+/// Nothing is dropped for players in creative mode
+/// or when mining with a Silk Touch tool.
+pub fn drop_block_experience(
+    game: &mut Game,
+    player: Entity,
+    pos: ValidBlockPosition,
+    kind: BlockKind,
+) -> SysResult {
+    if *game.ecs.get::<Gamemode>(player)? == Gamemode::Creative {
+        return Ok(());
+    }
+    let silk_touch = {
+        let inventory = game.ecs.get::<Inventory>(player)?;
+        let hotbar_slot = game.ecs.get::<HotbarSlot>(player)?;
+        let held_item = inventory.item(Area::Hotbar, hotbar_slot.get());
+        held_item
+            .and_then(|item| {
+                item.map_ref(|stack| stack.enchantment_level(EnchantmentKind::SilkTouch))
+            })
+            .flatten()
+            .is_some()
+    };
+    if silk_touch {
+        return Ok(());
+    }
+
+    let amount = block_experience(kind, &mut rand::thread_rng());
+    if amount > 0 {
+        let position = Position {
+            x: pos.x() as f64 + 0.5,
+            y: pos.y() as f64 + 0.5,
+            z: pos.z() as f64 + 0.5,
+            ..Default::default()
+        };
+        let world = *game.ecs.get::<WorldId>(player)?;
+        spawn_experience(game, world, position, amount);
+    }
+    Ok(())
+}
+
+/// Drops experience orbs for mobs which died.
+fn drop_mob_experience(game: &mut Game) -> SysResult {
+    let mut dead = Vec::new();
+    for (_, (_, &kind, &world, &position, player)) in game
+        .ecs
+        .query::<(
+            &EntityDeathEvent,
+            &EntityKind,
+            &WorldId,
+            &Position,
+            Option<&Player>,
+        )>()
+        .iter()
+    {
+        if player.is_none() {
+            dead.push((kind, world, position));
+        }
+    }
+
+    let mut rng = rand::thread_rng();
+    for (kind, world, position) in dead {
+        spawn_experience(game, world, position, mob_experience(kind, &mut rng));
+    }
+    Ok(())
+}
+
+/// Ages, merges and moves orbs towards players,
+/// then lets players collect orbs in range.
+fn tick_orbs(game: &mut Game) -> SysResult {
+    let players: Vec<(Entity, WorldId, Position)> = game
+        .ecs
+        .query::<(&WorldId, &Position, &Gamemode, &Experience)>()
+        .iter()
+        .filter(|(_, (_, _, gamemode, _))| **gamemode != Gamemode::Spectator)
+        .map(|(entity, (&world, &position, _, _))| (entity, world, position))
+        .collect();
+
+    let merged = if game.tick_count % 20 == 0 {
+        merge_orbs(game)?
+    } else {
+        Vec::new()
+    };
+
+    let mut expired = Vec::new();
+    let mut pickups: Vec<(Entity, Entity, u32)> = Vec::new();
+    for (orb, (&world, position, &ExperienceOrbValue(value), age)) in game
+        .ecs
+        .query::<(&WorldId, &mut Position, &ExperienceOrbValue, &mut OrbAge)>()
+        .iter()
+    {
+        if merged.contains(&orb) {
+            continue;
+        }
+        age.0 += 1;
+        if age.0 >= ORB_LIFETIME {
+            expired.push(orb);
+            continue;
+        }
+
+        let nearest = players
+            .iter()
+            .filter(|&&(_, player_world, _)| player_world == world)
+            .map(|&(player, _, player_pos)| (player, attraction_target(player_pos)))
+            .map(|(player, target)| (player, target, position.distance_to(target)))
+            .filter(|&(_, _, distance)| distance < ATTRACTION_RANGE)
+            .min_by(|a, b| a.2.partial_cmp(&b.2).unwrap());
+        if let Some((player, target, distance)) = nearest {
+            if distance < PICKUP_RANGE {
+                // Like in vanilla, a player collects at most one orb per tick.
+                if !pickups.iter().any(|&(p, _, _)| p == player) {
+                    pickups.push((player, orb, value));
+                }
+            } else {
+                move_towards(position, target, distance);
+            }
+        }
+    }
+
+    for orb in expired {
+        game.remove_entity(orb)?;
+    }
+    for (player, orb, value) in pickups {
+        collect_orb(game, player, orb, value)?;
+    }
+    Ok(())
+}
+
+/// The point orbs are attracted to: roughly the center of the player.
+fn attraction_target(player: Position) -> Position {
+    Position {
+        y: player.y + 0.8,
+        ..player
+    }
+}
+
+/// Moves an orb towards `target`. Orbs accelerate
+/// as they get closer to the player.
+fn move_towards(position: &mut Position, target: Position, distance: f64) {
+    let speed = (1.0 - distance / ATTRACTION_RANGE).powi(2) * 0.8;
+    let step = speed.min(distance) / distance;
+    position.x += (target.x - position.x) * step;
+    position.y += (target.y - position.y) * step;
+    position.z += (target.z - position.z) * step;
+}
+
+/// Merges orbs close to each other into a single orb.
+/// Returns the orbs which were merged into others.
+fn merge_orbs(game: &mut Game) -> anyhow::Result<Vec<Entity>> {
+    let orbs: Vec<(Entity, WorldId, Position)> = game
+        .ecs
+        .query::<(&WorldId, &Position, &ExperienceOrbValue)>()
+        .iter()
+        .map(|(entity, (&world, &position, _))| (entity, world, position))
+        .collect();
+
+    let mut merged = Vec::new();
+    for (i, &(orb, world, position)) in orbs.iter().enumerate() {
+        if merged.contains(&orb) {
+            continue;
+        }
+        let mut value = game.ecs.get::<ExperienceOrbValue>(orb)?.0;
+        for &(other, other_world, other_position) in &orbs[i + 1..] {
+            if merged.contains(&other)
+                || other_world != world
+                || position.distance_to(other_position) > MERGE_RANGE
+            {
+                continue;
+            }
+            value += game.ecs.get::<ExperienceOrbValue>(other)?.0;
+            merged.push(other);
+        }
+        game.ecs.get_mut::<ExperienceOrbValue>(orb)?.0 = value;
+    }
+
+    for &orb in &merged {
+        game.remove_entity(orb)?;
+    }
+    Ok(merged)
+}
+
+/// Gives the experience of an orb to a player, using it
+/// to repair Mending items first.
+fn collect_orb(game: &mut Game, player: Entity, orb: Entity, value: u32) -> SysResult {
+    game.remove_entity(orb)?;
+
+    let (remaining, mended) = match (
+        game.ecs.get::<Inventory>(player),
+        game.ecs.get::<HotbarSlot>(player),
+    ) {
+        (Ok(inventory), Ok(hotbar_slot)) => mend_items(
+            &inventory,
+            hotbar_slot.get(),
+            value,
+            &mut rand::thread_rng(),
+        ),
+        _ => (value, false),
+    };
+
+    if remaining > 0 {
+        game.ecs
+            .get_mut::<Experience>(player)?
+            .add_points(remaining);
+    }
+    if mended {
+        game.ecs.insert_entity_event(player, InventoryUpdateEvent)?;
+    }
+    Ok(())
+}
+
+/// Uses experience to repair a random damaged, equipped item with Mending.
+/// Each point repairs two durability.
+///
+/// Returns the experience left over and whether an item was repaired.
+fn mend_items(
+    inventory: &Inventory,
+    hotbar_slot: usize,
+    experience: u32,
+    rng: &mut impl Rng,
+) -> (u32, bool) {
+    let equipment = [
+        (Area::Hotbar, hotbar_slot),
+        (Area::Offhand, 0),
+        (Area::Helmet, 0),
+        (Area::Chestplate, 0),
+        (Area::Leggings, 0),
+        (Area::Boots, 0),
+    ];
+    let candidates: Vec<(Area, usize)> = equipment
+        .iter()
+        .copied()
+        .filter(|&(area, slot)| {
+            inventory
+                .item(area, slot)
+                .and_then(|item| {
+                    item.map_ref(|stack| {
+                        stack.enchantment_level(EnchantmentKind::Mending).is_some()
+                            && stack.damage_taken().unwrap_or(0) > 0
+                    })
+                })
+                .unwrap_or(false)
+        })
+        .collect();
+
+    let (area, slot) = match candidates.choose(rng) {
+        Some(&candidate) => candidate,
+        None => return (experience, false),
+    };
+    let repaired = inventory
+        .item(area, slot)
+        .and_then(|mut item| {
+            item.map_mut(|stack| stack.repair(experience.saturating_mul(2) as i32))
+        })
+        .unwrap_or(0);
+    (experience - repaired as u32 / 2, repaired > 0)
+}
+
+#[cfg(test)]
+mod tests {
+    use base::ItemStackBuilder;
+    use libcraft_items::{Enchantment, InventorySlot};
+    use quill_common::events::EntityRemoveEvent;
+
+    use super::*;
+
+    #[test]
+    fn experience_is_split_into_orbs() {
+        assert_eq!(split_into_orbs(0), Vec::<u32>::new());
+        assert_eq!(split_into_orbs(5), vec![3, 1, 1]);
+        assert_eq!(split_into_orbs(20), vec![17, 3]);
+        assert_eq!(split_into_orbs(5000).iter().sum::<u32>(), 5000);
+    }
+
+    #[test]
+    fn points_advance_levels() {
+        let mut experience = Experience::default();
+        experience.add_points(7);
+        assert_eq!(experience.level, 1);
+        assert_eq!(experience.progress, 0.0);
+
+        experience.add_points(Experience::points_for_level(30) - 7);
+        assert_eq!(experience.level, 30);
+        assert_eq!(experience.total, 1395);
+
+        experience.add_points(Experience::points_to_next_level(30) / 2);
+        assert_eq!(experience.level, 30);
+        assert!((experience.progress - 0.5).abs() < 0.01);
+    }
+
+    #[test]
+    fn charging_levels() {
+        let mut experience = Experience {
+            level: 5,
+            progress: 0.5,
+            total: 100,
+        };
+        assert!(!experience.charge_levels(Gamemode::Survival, 30, 3));
+        assert!(experience.charge_levels(Gamemode::Survival, 5, 3));
+        assert_eq!(experience.level, 2);
+        assert_eq!(experience.progress, 0.5);
+        assert!(!experience.remove_levels(3));
+        assert_eq!(experience.level, 2);
+        assert!(experience.charge_levels(Gamemode::Creative, 30, 30));
+        assert_eq!(experience.level, 2);
+    }
+
+    #[test]
+    fn mending_repairs_items() {
+        let inventory = Inventory::player();
+        let sword = ItemStackBuilder::with_item(Item::DiamondSword)
+            .damage(10)
+            .enchantments(vec![Enchantment::new(EnchantmentKind::Mending, 1)])
+            .into();
+        *inventory.item(Area::Hotbar, 0).unwrap() = InventorySlot::Filled(sword);
+
+        let mut rng = rand::thread_rng();
+        assert_eq!(mend_items(&inventory, 0, 3, &mut rng), (0, true));
+        assert_eq!(mend_items(&inventory, 0, 3, &mut rng), (1, true));
+        assert_eq!(mend_items(&inventory, 0, 3, &mut rng), (3, false));
+        let damage = inventory
+            .item(Area::Hotbar, 0)
+            .unwrap()
+            .map_ref(|stack| stack.damage_taken())
+            .flatten();
+        assert_eq!(damage, Some(0));
+    }
+
+    #[test]
+    fn dead_mobs_drop_experience() {
+        let mut game = Game::new();
+        game.ecs.spawn((
+            EntityKind::Zombie,
+            WorldId::MAIN,
+            Position::default(),
+            EntityDeathEvent,
+        ));
+        game.ecs
+            .spawn((EntityKind::Zombie, WorldId::MAIN, Position::default()));
+        drop_mob_experience(&mut game).unwrap();
+
+        let total: u32 = game
+            .ecs
+            .query::<&ExperienceOrbValue>()
+            .iter()
+            .map(|(_, value)| value.0)
+            .sum();
+        assert_eq!(total, 5);
+    }
+
+    #[test]
+    fn players_collect_nearby_orbs() {
+        let mut game = Game::new();
+        let player = game.ecs.spawn((
+            WorldId::MAIN,
+            Position::default(),
+            Gamemode::Survival,
+            Experience::default(),
+        ));
+        let orb = game.ecs.spawn((
+            WorldId::MAIN,
+            Position {
+                x: 3.0,
+                ..Default::default()
+            },
+            ExperienceOrbValue(7),
+            OrbAge(0),
+        ));
+        for _ in 0..20 {
+            tick_orbs(&mut game).unwrap();
+            if game.ecs.get::<EntityRemoveEvent>(orb).is_ok() {
+                break;
+            }
+        }
+        assert_eq!(game.ecs.get::<Experience>(player).unwrap().level, 1);
+        assert!(game.ecs.get::<EntityRemoveEvent>(orb).is_ok());
+    }
+}
@@ -11,8 +11,8 @@ use quill_common::{entities::Player, entity_init::EntityInit};
 use crate::{
     chat::{ChatKind, ChatMessage},
     chunk::entities::ChunkEntities,
-    events::BlockChangeEvent,
-    ChatBox, World,
+    events::{BlockChangeEvent, WorldChangeEvent},
+    ChatBox, Metrics, WorldId, Worlds,
 };
 
 type EntitySpawnCallback = Box<dyn FnMut(&mut EntityBuilder, &EntityInit)>;
@@ -20,7 +20,7 @@ type EntitySpawnCallback = Box<dyn FnMut(&mut EntityBuilder, &EntityInit)>;
 /// Stores the entire state of a Minecraft game.
 ///
 /// This contains:
-/// * A [`World`](crate::World) containing chunks and blocks.
+/// * The [`Worlds`](crate::Worlds) containing chunks and blocks.
 /// * An [`Ecs`](ecs::Ecs) containing entities.
 /// * A [`Resources`](ecs::Resources) containing additional, user-defined data.
 /// * A [`SystemExecutor`] to run systems.
@@ -29,13 +29,14 @@ type EntitySpawnCallback = Box<dyn FnMut(&mut EntityBuilder, &EntityInit)>;
 /// as "drop item" or "kill entity." These high-level methods
 /// should be preferred over raw interaction with the ECS.
 pub struct Game {
-    /// Contains chunks and blocks.
+    /// Contains chunks and blocks of each world,
+    /// indexed by [`WorldId`].
     ///
     /// NB: use methods on `Game` to update
     /// blocks, not direct methods on `World`.
     /// The `Game` methods will automatically
     /// trigger the necessary `BlockChangeEvent`s.
-    pub world: World,
+    pub worlds: Worlds,
     /// Contains entities, including players.
     pub ecs: Ecs,
     /// Contains systems.
@@ -52,6 +53,9 @@ pub struct Game {
     /// Total ticks elapsed since the server started.
     pub tick_count: u64,
 
+    /// Metrics exported to monitoring tools.
+    pub metrics: Metrics,
+
     entity_spawn_callbacks: Vec<EntitySpawnCallback>,
 
     entity_builder: EntityBuilder,
@@ -67,12 +71,13 @@ impl Game {
     /// Creates a new, empty `Game`.
     pub fn new() -> Self {
         Self {
-            world: World::new(),
+            worlds: Worlds::default(),
             ecs: Ecs::new(),
             system_executor: Rc::new(RefCell::new(SystemExecutor::new())),
             resources: Arc::new(Resources::new()),
             chunk_entities: ChunkEntities::default(),
             tick_count: 0,
+            metrics: Metrics::new(),
             entity_spawn_callbacks: Vec::new(),
             entity_builder: EntityBuilder::new(),
         }
@@ -113,9 +118,12 @@ impl Game {
 
     /// Creates an entity builder with the default components
     /// for an entity of type `init`.
+    ///
+    /// The entity is in the main world unless
+    /// another `WorldId` is added to the builder.
     pub fn create_entity_builder(&mut self, position: Position, init: EntityInit) -> EntityBuilder {
         let mut builder = mem::take(&mut self.entity_builder);
-        builder.add(position);
+        builder.add(position).add(WorldId::MAIN);
         self.invoke_entity_spawn_callbacks(&mut builder, init);
         builder
     }
@@ -158,6 +166,30 @@ impl Game {
         self.ecs.insert_entity_event(entity, EntityRemoveEvent)
     }
 
+    /// Moves an entity to `position` in another world.
+    ///
+    /// Triggers a `WorldChangeEvent`.
+    pub fn change_world(
+        &mut self,
+        entity: Entity,
+        world: WorldId,
+        position: Position,
+    ) -> SysResult {
+        if self.worlds.get(world).is_none() {
+            anyhow::bail!("no world with ID {:?}", world);
+        }
+        let old_world = mem::replace(&mut *self.ecs.get_mut::<WorldId>(entity)?, world);
+        *self.ecs.get_mut::<Position>(entity)? = position;
+        self.ecs.insert_entity_event(
+            entity,
+            WorldChangeEvent {
+                old_world,
+                new_world: world,
+            },
+        )?;
+        Ok(())
+    }
+
     /// Broadcasts a chat message to all entities with
     /// a `ChatBox` component (usually just players).
     pub fn broadcast_chat(&self, kind: ChatKind, message: impl Into<Text>) {
@@ -181,18 +213,21 @@ impl Game {
         Ok(())
     }
 
-    /// Gets the block at the given position.
-    pub fn block(&self, pos: ValidBlockPosition) -> Option<BlockId> {
-        self.world.block_at(pos)
+    /// Gets the block at the given position in `world`.
+    pub fn block(&self, world: WorldId, pos: ValidBlockPosition) -> Option<BlockId> {
+        self.worlds.get(world)?.block_at(pos)
     }
 
-    /// Sets the block at the given position.
+    /// Sets the block at the given position in `world`.
     ///
     /// Triggers necessary `BlockChangeEvent`s.
-    pub fn set_block(&mut self, pos: ValidBlockPosition, block: BlockId) -> bool {
-        let was_successful = self.world.set_block_at(pos, block);
+    pub fn set_block(&mut self, world: WorldId, pos: ValidBlockPosition, block: BlockId) -> bool {
+        let was_successful = self
+            .worlds
+            .get(world)
+            .map_or(false, |w| w.set_block_at(pos, block));
         if was_successful {
-            self.ecs.insert_event(BlockChangeEvent::single(pos));
+            self.ecs.insert_event(BlockChangeEvent::single(world, pos));
         }
         was_successful
     }
@@ -202,11 +237,16 @@ impl Game {
     /// All blocks in the chunk section are overwritten with `block`.
     pub fn fill_chunk_section(
         &mut self,
+        world: WorldId,
         chunk_pos: ChunkPosition,
         section_y: usize,
         block: BlockId,
     ) -> bool {
-        let mut chunk = match self.world.chunk_map().chunk_at_mut(chunk_pos) {
+        let mut chunk = match self
+            .worlds
+            .get(world)
+            .and_then(|w| w.chunk_map().chunk_at_mut(chunk_pos))
+        {
             Some(chunk) => chunk,
             None => return false,
         };
@@ -218,6 +258,7 @@ impl Game {
         }
 
         self.ecs.insert_event(BlockChangeEvent::fill_chunk_section(
+            world,
             chunk_pos,
             section_y as u32,
         ));
@@ -227,8 +268,8 @@ impl Game {
 
     /// Breaks the block at the given position, propagating any
     /// necessary block updates.
-    pub fn break_block(&mut self, pos: ValidBlockPosition) -> bool {
-        self.set_block(pos, BlockId::air())
+    pub fn break_block(&mut self, world: WorldId, pos: ValidBlockPosition) -> bool {
+        self.set_block(world, pos, BlockId::air())
     }
 }
 
@@ -8,6 +8,7 @@
 mod game;
 use ecs::SystemExecutor;
 pub use game::Game;
+use libcraft_core::GameRules;
 
 mod tick_loop;
 pub use tick_loop::TickLoop;
@@ -23,7 +24,7 @@ pub mod chunk;
 mod region_worker;
 
 pub mod world;
-pub use world::World;
+pub use world::{World, WorldId, Worlds};
 
 pub mod chat;
 pub use chat::ChatBox;
@@ -32,12 +33,39 @@ pub mod entities;
 
 pub mod interactable;
 
+pub mod damage;
+
+pub mod ai;
+
+pub mod mob_spawning;
+
+pub mod spawner;
+
+pub mod effects;
+
+pub mod experience;
+
+pub mod portal;
+
+pub mod metrics;
+pub use metrics::Metrics;
+
 /// Registers gameplay systems with the given `Game` and `SystemExecutor`.
 pub fn register(game: &mut Game, systems: &mut SystemExecutor<Game>) {
+    game.insert_resource(GameRules::default());
+
     view::register(game, systems);
     chunk::loading::register(game, systems);
     chunk::entities::register(systems);
     interactable::register(game);
+    damage::register(systems);
+    ai::register(systems);
+    mob_spawning::register(game, systems);
+    spawner::register(systems);
+    effects::register(systems);
+    experience::register(systems);
+    portal::register(systems);
+    metrics::register(systems);
 
     game.add_entity_spawn_callback(entities::add_entity_components);
 }
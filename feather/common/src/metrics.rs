@@ -0,0 +1,414 @@
+//! A registry of server metrics, which can be rendered in
+//! the [Prometheus text format](https://prometheus.io/docs/instrumenting/exposition_formats/).
+//!
+//! Values which are expensive to compute, like entity counts,
+//! are sampled every [`UPDATE_INTERVAL`] ticks.
+
+use std::{collections::BTreeMap, fmt::Write, iter::once, sync::Arc, time::Duration};
+
+use ahash::AHashMap;
+use base::EntityKind;
+use ecs::{SysResult, SystemExecutor};
+use parking_lot::Mutex;
+
+use crate::Game;
+
+/// The number of ticks between samples of metrics.
+pub const UPDATE_INTERVAL: u64 = 20;
+
+/// Upper bounds, in seconds, of the tick duration histogram buckets.
+const TICK_DURATION_BUCKETS: &[f64] = &[0.005, 0.01, 0.025, 0.05, 0.075, 0.1, 0.25, 0.5, 1.0];
+
+/// The type of a metric.
+#[derive(Copy, Clone, Debug, PartialEq, Eq)]
+pub enum MetricKind {
+    /// A total which only increases, like the number of bytes sent.
+    Counter,
+    /// A value which can go up and down, like the number of loaded chunks.
+    Gauge,
+}
+
+/// A thread-safe handle to a registry of metrics.
+///
+/// Cloning is cheap and yields a handle to the same registry.
+#[derive(Clone, Default)]
+pub struct Metrics {
+    families: Arc<Mutex<BTreeMap<String, Family>>>,
+}
+
+struct Family {
+    help: String,
+    values: Values,
+}
+
+enum Values {
+    /// Values keyed by their formatted labels.
+    Samples(MetricKind, BTreeMap<String, f64>),
+    Histogram(Histogram),
+}
+
+struct Histogram {
+    bounds: Vec<f64>,
+    counts: Vec<u64>,
+    sum: f64,
+    count: u64,
+}
+
+impl Metrics {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Sets the value of a gauge, registering it if needed.
+    pub fn set_gauge(&self, name: &str, help: &str, value: f64) {
+        self.set_labeled(
+            MetricKind::Gauge,
+            name,
+            help,
+            "",
+            once((String::new(), value)),
+        );
+    }
+
+    /// Sets the value of a counter, registering it if needed.
+    ///
+    /// The total is kept by the caller, since metrics are usually
+    /// copied from statistics tracked elsewhere.
+    pub fn set_counter(&self, name: &str, help: &str, value: f64) {
+        self.set_labeled(
+            MetricKind::Counter,
+            name,
+            help,
+            "",
+            once((String::new(), value)),
+        );
+    }
+
+    /// Replaces the values of a metric with one value per
+    /// value of `label`. If `label` is empty, the values
+    /// are unlabeled.
+    pub fn set_labeled(
+        &self,
+        kind: MetricKind,
+        name: &str,
+        help: &str,
+        label: &str,
+        values: impl IntoIterator<Item = (String, f64)>,
+    ) {
+        let samples = values
+            .into_iter()
+            .map(|(label_value, value)| {
+                let labels = if label.is_empty() {
+                    String::new()
+                } else {
+                    format!("{}=\"{}\"", label, escape_label_value(&label_value))
+                };
+                (labels, value)
+            })
+            .collect();
+
+        self.families.lock().insert(
+            name.to_owned(),
+            Family {
+                help: help.to_owned(),
+                values: Values::Samples(kind, samples),
+            },
+        );
+    }
+
+    /// Records `value` in a histogram, registering it with
+    /// the given bucket upper bounds if needed.
+    pub fn observe(&self, name: &str, help: &str, buckets: &[f64], value: f64) {
+        let mut families = self.families.lock();
+        let family = families.entry(name.to_owned()).or_insert_with(|| Family {
+            help: help.to_owned(),
+            values: Values::Histogram(Histogram::new(buckets)),
+        });
+        if !matches!(family.values, Values::Histogram(_)) {
+            family.values = Values::Histogram(Histogram::new(buckets));
+        }
+        if let Values::Histogram(histogram) = &mut family.values {
+            histogram.observe(value);
+        }
+    }
+
+    /// Renders all metrics in the Prometheus text format.
+    pub fn render(&self) -> String {
+        let mut output = String::new();
+        for (name, family) in self.families.lock().iter() {
+            family.render(name, &mut output);
+        }
+        output
+    }
+}
+
+impl Family {
+    fn render(&self, name: &str, output: &mut String) {
+        let kind = match &self.values {
+            Values::Samples(MetricKind::Counter, _) => "counter",
+            Values::Samples(MetricKind::Gauge, _) => "gauge",
+            Values::Histogram(_) => "histogram",
+        };
+        let _ = writeln!(output, "# HELP {} {}", name, escape_help(&self.help));
+        let _ = writeln!(output, "# TYPE {} {}", name, kind);
+
+        match &self.values {
+            Values::Samples(_, samples) => {
+                for (labels, &value) in samples {
+                    render_sample(output, name, labels, value);
+                }
+            }
+            Values::Histogram(histogram) => {
+                let bucket = format!("{}_bucket", name);
+                for (&bound, &count) in histogram.bounds.iter().zip(&histogram.counts) {
+                    let labels = format!("le=\"{}\"", format_value(bound));
+                    render_sample(output, &bucket, &labels, count as f64);
+                }
+                render_sample(output, &bucket, "le=\"+Inf\"", histogram.count as f64);
+                render_sample(output, &format!("{}_sum", name), "", histogram.sum);
+                render_sample(
+                    output,
+                    &format!("{}_count", name),
+                    "",
+                    histogram.count as f64,
+                );
+            }
+        }
+    }
+}
+
+impl Histogram {
+    fn new(bounds: &[f64]) -> Self {
+        Self {
+            bounds: bounds.to_vec(),
+            counts: vec![0; bounds.len()],
+            sum: 0.0,
+            count: 0,
+        }
+    }
+
+    fn observe(&mut self, value: f64) {
+        // Buckets are cumulative.
+        for (bound, count) in self.bounds.iter().zip(&mut self.counts) {
+            if value <= *bound {
+                *count += 1;
+            }
+        }
+        self.sum += value;
+        self.count += 1;
+    }
+}
+
+fn render_sample(output: &mut String, name: &str, labels: &str, value: f64) {
+    if labels.is_empty() {
+        let _ = writeln!(output, "{} {}", name, format_value(value));
+    } else {
+        let _ = writeln!(output, "{}{{{}}} {}", name, labels, format_value(value));
+    }
+}
+
+fn format_value(value: f64) -> String {
+    if value.is_infinite() {
+        if value > 0.0 { "+Inf" } else { "-Inf" }.to_owned()
+    } else {
+        value.to_string()
+    }
+}
+
+fn escape_help(help: &str) -> String {
+    help.replace('\\', "\\\\").replace('\n', "\\n")
+}
+
+fn escape_label_value(value: &str) -> String {
+    escape_help(value).replace('"', "\\\"")
+}
+
+/// Determines whether `name` is a valid metric name.
+pub fn is_valid_name(name: &str) -> bool {
+    let mut chars = name.chars();
+    match chars.next() {
+        Some(c) if c.is_ascii_alphabetic() || c == '_' || c == ':' => {}
+        _ => return false,
+    }
+    chars.all(|c| c.is_ascii_alphanumeric() || c == '_' || c == ':')
+}
+
+pub fn register(systems: &mut SystemExecutor<Game>) {
+    systems.add_system(update_metrics);
+}
+
+/// Records the duration of a tick. Every [`UPDATE_INTERVAL`]
+/// ticks, also records the time spent running each system.
+///
+/// Must be called between ticks, as the system executor
+/// is borrowed while systems run.
+pub fn record_tick(game: &Game, duration: Duration) {
+    game.metrics.observe(
+        "feather_tick_duration_seconds",
+        "Time taken to run a tick.",
+        TICK_DURATION_BUCKETS,
+        duration.as_secs_f64(),
+    );
+
+    if game.tick_count % UPDATE_INTERVAL == 0 {
+        // Systems may share a name.
+        let mut timings: AHashMap<String, Duration> = AHashMap::new();
+        for (name, time) in game.system_executor.borrow().system_timings() {
+            *timings.entry(name.to_owned()).or_default() += time;
+        }
+        game.metrics.set_labeled(
+            MetricKind::Counter,
+            "feather_system_seconds_total",
+            "Time spent running each system.",
+            "system",
+            timings
+                .into_iter()
+                .map(|(name, time)| (name, time.as_secs_f64())),
+        );
+    }
+}
+
+fn update_metrics(game: &mut Game) -> SysResult {
+    if game.tick_count % UPDATE_INTERVAL != 0 {
+        return Ok(());
+    }
+    let metrics = &game.metrics;
+
+    let mut entities: AHashMap<EntityKind, usize> = AHashMap::new();
+    for (_, &kind) in game.ecs.query::<&EntityKind>().iter() {
+        *entities.entry(kind).or_default() += 1;
+    }
+    metrics.set_labeled(
+        MetricKind::Gauge,
+        "feather_entities",
+        "Entities of each kind.",
+        "kind",
+        entities
+            .into_iter()
+            .map(|(kind, count)| (kind.name().to_owned(), count as f64)),
+    );
+
+    let worlds = || game.worlds.iter();
+    metrics.set_gauge(
+        "feather_loaded_chunks",
+        "Chunks loaded in all worlds.",
+        worlds().map(|world| world.chunk_map().len()).sum::<usize>() as f64,
+    );
+    metrics.set_gauge(
+        "feather_cached_chunks",
+        "Unloaded chunks kept in the chunk cache.",
+        worlds().map(|world| world.cache.len()).sum::<usize>() as f64,
+    );
+
+    let load_stats = worlds().map(|world| world.load_stats());
+    metrics.set_counter(
+        "feather_chunks_loaded_total",
+        "Chunks loaded from the world save or generated.",
+        load_stats.clone().map(|stats| stats.loaded).sum::<u64>() as f64,
+    );
+    metrics.set_counter(
+        "feather_chunk_load_latency_seconds_total",
+        "Total time between queueing chunks to be loaded and their loading finishing.",
+        load_stats
+            .map(|stats| stats.total_latency)
+            .sum::<Duration>()
+            .as_secs_f64(),
+    );
+
+    let generation_stats: Vec<_> = worlds().map(|world| world.generation_stats()).collect();
+    let generation = generation_stats.iter();
+    metrics.set_gauge(
+        "feather_chunk_generation_queued",
+        "Chunks waiting to be generated.",
+        generation.clone().map(|stats| stats.queued).sum::<usize>() as f64,
+    );
+    metrics.set_gauge(
+        "feather_chunk_generation_in_progress",
+        "Chunks being generated.",
+        generation
+            .clone()
+            .map(|stats| stats.in_progress)
+            .sum::<usize>() as f64,
+    );
+    metrics.set_counter(
+        "feather_chunks_generated_total",
+        "Chunks generated.",
+        generation.clone().map(|stats| stats.generated).sum::<u64>() as f64,
+    );
+    metrics.set_counter(
+        "feather_chunk_generation_seconds_total",
+        "Total time spent generating chunks.",
+        generation
+            .clone()
+            .map(|stats| stats.total_generation_time)
+            .sum::<Duration>()
+            .as_secs_f64(),
+    );
+    metrics.set_counter(
+        "feather_chunk_generation_latency_seconds_total",
+        "Total time between queueing chunks to be generated and their generation finishing.",
+        generation
+            .map(|stats| stats.total_latency)
+            .sum::<Duration>()
+            .as_secs_f64(),
+    );
+
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn render_samples() {
+        let metrics = Metrics::new();
+        metrics.set_gauge("players", "Online players.", 3.0);
+        metrics.set_labeled(
+            MetricKind::Counter,
+            "sent_total",
+            "Sent \"things\".\nSecond line",
+            "kind",
+            vec![("a\"b".to_owned(), 1.5), ("c".to_owned(), 2.0)],
+        );
+
+        assert_eq!(
+            metrics.render(),
+            "# HELP players Online players.\n\
+             # TYPE players gauge\n\
+             players 3\n\
+             # HELP sent_total Sent \"things\".\\nSecond line\n\
+             # TYPE sent_total counter\n\
+             sent_total{kind=\"a\\\"b\"} 1.5\n\
+             sent_total{kind=\"c\"} 2\n"
+        );
+    }
+
+    #[test]
+    fn render_histogram() {
+        let metrics = Metrics::new();
+        for &value in &[0.25, 0.5, 3.0] {
+            metrics.observe("tick_seconds", "Tick time.", &[0.25, 1.0], value);
+        }
+
+        assert_eq!(
+            metrics.render(),
+            "# HELP tick_seconds Tick time.\n\
+             # TYPE tick_seconds histogram\n\
+             tick_seconds_bucket{le=\"0.25\"} 1\n\
+             tick_seconds_bucket{le=\"1\"} 2\n\
+             tick_seconds_bucket{le=\"+Inf\"} 3\n\
+             tick_seconds_sum 3.75\n\
+             tick_seconds_count 3\n"
+        );
+    }
+
+    #[test]
+    fn valid_names() {
+        assert!(is_valid_name("feather_tick_duration_seconds"));
+        assert!(is_valid_name(":plugin:players"));
+        assert!(!is_valid_name(""));
+        assert!(!is_valid_name("1players"));
+        assert!(!is_valid_name("players online"));
+    }
+}
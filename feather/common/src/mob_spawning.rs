@@ -0,0 +1,551 @@
+//! Natural spawning and despawning of mobs.
+//!
+//! Each tick, mobs are spawned in random positions in the chunks
+//! around players, as long as the mob cap of their category has
+//! not been reached. Hostile mobs far away from all players are despawned.
+
+use std::convert::TryInto;
+
+use ahash::{AHashMap, AHashSet};
+use base::{BlockKind, BlockPosition, ChunkPosition, EntityKind, Position};
+use ecs::{Entity, SysResult, SystemExecutor};
+use libcraft_core::{GameRules, MobCategory, SpawnEntry};
+use quill_common::{
+    components::{CustomName, Persistent},
+    entities::Player,
+};
+use rand::{prelude::SliceRandom, Rng};
+
+use crate::{ai::pathfinding, Game, World, WorldId};
+
+/// Radius, in chunks, around each player in which mobs can spawn.
+const SPAWN_RADIUS: i32 = 8;
+
+/// Number of spawnable chunks around a single player.
+/// Mob caps scale with the number of spawnable chunks
+/// relative to this value.
+const CHUNKS_PER_PLAYER: usize = 289;
+
+/// Mobs never spawn closer than this to a player.
+const MIN_PLAYER_DISTANCE: f64 = 24.0;
+
+/// Hostile mobs farther than this from all players
+/// are despawned immediately.
+const DESPAWN_DISTANCE: f64 = 128.0;
+
+/// Hostile mobs farther than this from all players
+/// may despawn at random.
+const RANDOM_DESPAWN_DISTANCE: f64 = 32.0;
+
+/// Number of ticks a mob must be out of range
+/// before it may despawn at random.
+const RANDOM_DESPAWN_DELAY: u32 = 600;
+
+/// Chance per tick of a mob despawning at random.
+const RANDOM_DESPAWN_CHANCE: f64 = 1.0 / 800.0;
+
+/// Passive animals only attempt to spawn every this many ticks.
+const CREATURE_SPAWN_INTERVAL: u64 = 400;
+
+/// Maximum horizontal distance between mobs in one group.
+const GROUP_SPREAD: i32 = 5;
+
+pub fn register(game: &mut Game, systems: &mut SystemExecutor<Game>) {
+    game.insert_resource(DespawnState::default());
+    systems.add_system(spawn_mobs);
+    systems.group::<DespawnState>().add_system(despawn_mobs);
+}
+
+/// Returns the positions of players, grouped by world.
+fn player_positions(game: &Game) -> AHashMap<WorldId, Vec<Position>> {
+    let mut players: AHashMap<WorldId, Vec<Position>> = AHashMap::new();
+    for (_, (&world, &position, _)) in game.ecs.query::<(&WorldId, &Position, &Player)>().iter() {
+        players.entry(world).or_default().push(position);
+    }
+    players
+}
+
+/// Returns the loaded chunks in which mobs can spawn.
+fn spawnable_chunks(world: &World, players: &[Position]) -> AHashSet<ChunkPosition> {
+    let mut chunks = AHashSet::new();
+    for player in players {
+        let center = player.chunk();
+        for x in -SPAWN_RADIUS..=SPAWN_RADIUS {
+            for z in -SPAWN_RADIUS..=SPAWN_RADIUS {
+                let chunk = ChunkPosition::new(center.x + x, center.z + z);
+                if world.is_chunk_loaded(chunk) {
+                    chunks.insert(chunk);
+                }
+            }
+        }
+    }
+    chunks
+}
+
+/// Returns the mob cap of `category` given the number of spawnable chunks.
+fn mob_cap(category: MobCategory, num_chunks: usize) -> usize {
+    category.mob_cap() as usize * num_chunks / CHUNKS_PER_PLAYER
+}
+
+fn spawn_mobs(game: &mut Game) -> SysResult {
+    if !game.resources.get::<GameRules>()?.do_mob_spawning {
+        return Ok(());
+    }
+
+    let mut to_spawn = Vec::new();
+    for (world_id, players) in player_positions(game) {
+        let world = &game.worlds[world_id];
+        let chunks = spawnable_chunks(world, &players);
+
+        let mut counts: AHashMap<MobCategory, usize> = AHashMap::new();
+        for (_, (&kind, &mob_world, position)) in game
+            .ecs
+            .query::<(&EntityKind, &WorldId, &Position)>()
+            .iter()
+        {
+            if let Some(category) = MobCategory::of(kind) {
+                if mob_world == world_id && chunks.contains(&position.chunk()) {
+                    *counts.entry(category).or_default() += 1;
+                }
+            }
+        }
+
+        let mut rng = rand::thread_rng();
+        for &category in &MobCategory::ALL {
+            if category == MobCategory::Creature && game.tick_count % CREATURE_SPAWN_INTERVAL != 0 {
+                continue;
+            }
+
+            let cap = mob_cap(category, chunks.len());
+            let mut count = counts.get(&category).copied().unwrap_or_default();
+            for &chunk in &chunks {
+                if count >= cap {
+                    break;
+                }
+                let group = find_spawn_group(world, chunk, category, &players, &mut rng);
+                count += group.len();
+                to_spawn.extend(
+                    group
+                        .into_iter()
+                        .map(|(position, kind)| (world_id, position, kind)),
+                );
+            }
+        }
+    }
+
+    for (world, position, kind) in to_spawn {
+        let mut builder = game.create_entity_builder(position, kind.into());
+        builder.add(world);
+        game.spawn_entity(builder);
+    }
+
+    Ok(())
+}
+
+/// Attempts to find positions for a group of mobs
+/// in a random position within `chunk`.
+fn find_spawn_group(
+    world: &World,
+    chunk: ChunkPosition,
+    category: MobCategory,
+    players: &[Position],
+    rng: &mut impl Rng,
+) -> Vec<(Position, EntityKind)> {
+    let x = rng.gen_range(0..16);
+    let z = rng.gen_range(0..16);
+    let (top, biome) = match world.chunk_map().chunk_at(chunk) {
+        Some(chunk) => {
+            let top = chunk.heightmaps().world_surface.height(x, z).unwrap_or(0);
+            (top, chunk.biomes().get_at_block(x, 0, z))
+        }
+        None => return Vec::new(),
+    };
+    if top == 0 {
+        return Vec::new();
+    }
+    let y = rng.gen_range(0..=top) as i32;
+
+    let entry = match pick_entry(biome.spawn_entries(category), rng) {
+        Some(entry) => *entry,
+        None => return Vec::new(),
+    };
+    let group_size = rng.gen_range(entry.min_group..=entry.max_group);
+
+    let origin = BlockPosition::new(chunk.x * 16 + x as i32, y, chunk.z * 16 + z as i32);
+    let mut group = Vec::new();
+    for _ in 0..group_size {
+        let pos = BlockPosition::new(
+            origin.x + rng.gen_range(-GROUP_SPREAD..=GROUP_SPREAD),
+            origin.y,
+            origin.z + rng.gen_range(-GROUP_SPREAD..=GROUP_SPREAD),
+        );
+        if can_spawn_at(world, entry.kind, category, pos, players, rng) {
+            let position = Position {
+                x: pos.x as f64 + 0.5,
+                y: pos.y as f64,
+                z: pos.z as f64 + 0.5,
+                pitch: 0.0,
+                yaw: rng.gen_range(0.0..360.0),
+            };
+            group.push((position, entry.kind));
+        }
+    }
+    group
+}
+
+/// Picks a random spawn entry, weighted by the entries' weights.
+fn pick_entry<'a>(entries: &'a [SpawnEntry], rng: &mut impl Rng) -> Option<&'a SpawnEntry> {
+    entries.choose_weighted(rng, |entry| entry.weight).ok()
+}
+
+/// Determines whether a mob of type `kind` can spawn at `pos`.
+fn can_spawn_at(
+    world: &World,
+    kind: EntityKind,
+    category: MobCategory,
+    pos: BlockPosition,
+    players: &[Position],
+    rng: &mut impl Rng,
+) -> bool {
+    let center = Position {
+        x: pos.x as f64 + 0.5,
+        y: pos.y as f64,
+        z: pos.z as f64 + 0.5,
+        pitch: 0.0,
+        yaw: 0.0,
+    };
+    match nearest_player_distance(players, center) {
+        Some(distance) if (MIN_PLAYER_DISTANCE..=DESPAWN_DISTANCE).contains(&distance) => {}
+        _ => return false,
+    }
+    is_valid_spawn(world, kind, category, pos, rng)
+}
+
+/// Determines whether the blocks and light at `pos` let a mob of
+/// type `kind` spawn there. Mob spawners use the same rules,
+/// but ignore the distance to players.
+pub fn is_valid_spawn(
+    world: &World,
+    kind: EntityKind,
+    category: MobCategory,
+    pos: BlockPosition,
+    rng: &mut impl Rng,
+) -> bool {
+    if category == MobCategory::Water {
+        return is_water(world, pos) && is_water(world, pos.up());
+    }
+
+    let height = kind.bounding_box().max.y.ceil().max(1.0) as u32;
+    if !pathfinding::can_stand_at(world, pos, height) {
+        return false;
+    }
+
+    let light = match light_at(world, pos) {
+        Some(light) => light,
+        None => return false,
+    };
+    match category {
+        MobCategory::Monster => light <= rng.gen_range(0..=7),
+        MobCategory::Ambient => pos.y < 63 && light <= rng.gen_range(0..=3),
+        MobCategory::Creature => {
+            let ground = match block_kind_at(world, pos.down()) {
+                Some(ground) => ground,
+                None => return false,
+            };
+            light >= 9 && is_creature_ground(kind, ground)
+        }
+        MobCategory::Water => unreachable!(),
+    }
+}
+
+/// Determines whether a passive animal can spawn on top of `ground`.
+fn is_creature_ground(kind: EntityKind, ground: BlockKind) -> bool {
+    match kind {
+        EntityKind::Mooshroom => ground == BlockKind::Mycelium,
+        EntityKind::Turtle => ground == BlockKind::Sand,
+        EntityKind::Rabbit | EntityKind::PolarBear => matches!(
+            ground,
+            BlockKind::GrassBlock | BlockKind::Sand | BlockKind::SnowBlock | BlockKind::Snow
+        ),
+        _ => ground == BlockKind::GrassBlock,
+    }
+}
+
+fn nearest_player_distance(players: &[Position], position: Position) -> Option<f64> {
+    players
+        .iter()
+        .map(|player| player.distance_to(position))
+        .min_by(|a, b| a.partial_cmp(b).unwrap())
+}
+
+fn block_kind_at(world: &World, pos: BlockPosition) -> Option<BlockKind> {
+    world
+        .block_at(pos.try_into().ok()?)
+        .map(|block| block.kind())
+}
+
+fn is_water(world: &World, pos: BlockPosition) -> bool {
+    block_kind_at(world, pos) == Some(BlockKind::Water)
+}
+
+/// Returns the combined block and sky light at `pos`.
+///
+/// Sky light isn't calculated yet, so it is derived from
+/// the heightmap: positions at or above the highest
+/// light-blocking block are fully lit, and all others are dark.
+/// The time of day is fixed, so the sky is never darkened.
+fn light_at(world: &World, pos: BlockPosition) -> Option<u8> {
+    if !(0..256).contains(&pos.y) {
+        return None;
+    }
+    let chunk = world.chunk_map().chunk_at(pos.chunk())?;
+    let x = pos.x.rem_euclid(16) as usize;
+    let y = pos.y as usize;
+    let z = pos.z.rem_euclid(16) as usize;
+
+    let sky_light = if y >= chunk.heightmaps().light_blocking.height(x, z)? {
+        15
+    } else {
+        0
+    };
+    // Empty sections have no light data and hold no light sources.
+    let block_light = match chunk.section(pos.y as isize / 16) {
+        Some(section) => section.block_light_at(x, y % 16, z)?,
+        None => 0,
+    };
+    Some(block_light.max(sky_light))
+}
+
+/// Tracks how long each hostile mob has been
+/// out of range of all players.
+#[derive(Default)]
+struct DespawnState {
+    idle_ticks: AHashMap<Entity, u32>,
+}
+
+fn despawn_mobs(game: &mut Game, state: &mut DespawnState) -> SysResult {
+    let players = player_positions(game);
+    let mut rng = rand::thread_rng();
+
+    let mut idle_ticks = AHashMap::new();
+    let mut to_despawn = Vec::new();
+    for (entity, (&kind, world, &position, persistent, name)) in game
+        .ecs
+        .query::<(
+            &EntityKind,
+            &WorldId,
+            &Position,
+            Option<&Persistent>,
+            Option<&CustomName>,
+        )>()
+        .iter()
+    {
+        if MobCategory::of(kind) != Some(MobCategory::Monster)
+            || persistent.is_some()
+            || name.is_some()
+        {
+            continue;
+        }
+
+        let distance = players
+            .get(world)
+            .and_then(|players| nearest_player_distance(players, position))
+            .unwrap_or(f64::INFINITY);
+        if distance > DESPAWN_DISTANCE {
+            to_despawn.push(entity);
+        } else if distance > RANDOM_DESPAWN_DISTANCE {
+            let ticks = state.idle_ticks.get(&entity).copied().unwrap_or_default() + 1;
+            if ticks > RANDOM_DESPAWN_DELAY && rng.gen_bool(RANDOM_DESPAWN_CHANCE) {
+                to_despawn.push(entity);
+            } else {
+                idle_ticks.insert(entity, ticks);
+            }
+        }
+    }
+    state.idle_ticks = idle_ticks;
+
+    for entity in to_despawn {
+        game.remove_entity(entity)?;
+    }
+
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use base::{BlockId, Chunk};
+    use quill_common::events::EntityRemoveEvent;
+    use rand::{rngs::StdRng, SeedableRng};
+
+    use super::*;
+
+    /// Creates a world with a grass floor at y=63 and
+    /// a player standing above the origin.
+    fn grass_world() -> World {
+        let mut world = World::new();
+        for x in -3..=3 {
+            for z in -3..=3 {
+                let mut chunk = Chunk::new(ChunkPosition::new(x, z));
+                chunk.fill_section(4, BlockId::stone());
+                for bx in 0..16 {
+                    for bz in 0..16 {
+                        chunk.set_block_at(bx, 63, bz, BlockId::grass_block());
+                    }
+                }
+                chunk.recalculate_heightmaps();
+                world.chunk_map_mut().insert_chunk(chunk);
+            }
+        }
+        world
+    }
+
+    #[test]
+    fn mob_cap_scales_with_chunks() {
+        assert_eq!(mob_cap(MobCategory::Monster, CHUNKS_PER_PLAYER), 70);
+        assert_eq!(mob_cap(MobCategory::Monster, 2 * CHUNKS_PER_PLAYER), 140);
+        assert_eq!(mob_cap(MobCategory::Creature, 0), 0);
+    }
+
+    #[test]
+    fn creatures_spawn_on_grass_away_from_players() {
+        let world = grass_world();
+        let players = [base::position!(0.5, 64.0, 0.5)];
+        let mut rng = rand::thread_rng();
+
+        let far = BlockPosition::new(30, 64, 0);
+        assert!(can_spawn_at(
+            &world,
+            EntityKind::Cow,
+            MobCategory::Creature,
+            far,
+            &players,
+            &mut rng
+        ));
+
+        let near = BlockPosition::new(5, 64, 0);
+        assert!(!can_spawn_at(
+            &world,
+            EntityKind::Cow,
+            MobCategory::Creature,
+            near,
+            &players,
+            &mut rng
+        ));
+
+        let underground = BlockPosition::new(30, 60, 0);
+        assert!(!can_spawn_at(
+            &world,
+            EntityKind::Cow,
+            MobCategory::Creature,
+            underground,
+            &players,
+            &mut rng
+        ));
+    }
+
+    #[test]
+    fn monsters_do_not_spawn_in_light() {
+        let world = grass_world();
+        let players = [base::position!(0.5, 64.0, 0.5)];
+        let mut rng = rand::thread_rng();
+
+        let pos = BlockPosition::new(30, 64, 0);
+        assert_eq!(light_at(&world, pos), Some(15));
+        assert!(!can_spawn_at(
+            &world,
+            EntityKind::Zombie,
+            MobCategory::Monster,
+            pos,
+            &players,
+            &mut rng
+        ));
+    }
+
+    #[test]
+    fn monsters_spawn_in_dark_caves() {
+        let world = grass_world();
+        let cave = BlockPosition::new(30, 55, 0);
+        for y in cave.y..cave.y + 2 {
+            world.set_block_at(
+                BlockPosition::new(cave.x, y, cave.z).try_into().unwrap(),
+                BlockId::air(),
+            );
+        }
+        let players = [base::position!(0.5, 64.0, 0.5)];
+        let mut rng = rand::thread_rng();
+
+        assert_eq!(light_at(&world, cave), Some(0));
+        assert!(can_spawn_at(
+            &world,
+            EntityKind::Zombie,
+            MobCategory::Monster,
+            cave,
+            &players,
+            &mut rng
+        ));
+
+        // A torch nearby lights up the cave.
+        world
+            .chunk_map()
+            .chunk_at_mut(cave.chunk())
+            .unwrap()
+            .section_mut(3)
+            .unwrap()
+            .set_block_light_at(14, 55 % 16, 0, 14);
+        assert_eq!(light_at(&world, cave), Some(14));
+        assert!(!can_spawn_at(
+            &world,
+            EntityKind::Zombie,
+            MobCategory::Monster,
+            cave,
+            &players,
+            &mut rng
+        ));
+    }
+
+    #[test]
+    fn pick_entry_respects_weights() {
+        let entry = |kind, weight| SpawnEntry {
+            kind,
+            weight,
+            min_group: 1,
+            max_group: 1,
+        };
+        let entries = [entry(EntityKind::Zombie, 1), entry(EntityKind::Skeleton, 3)];
+        let mut rng = StdRng::seed_from_u64(42);
+
+        let samples = 10_000;
+        let skeletons = (0..samples)
+            .filter(|_| pick_entry(&entries, &mut rng).unwrap().kind == EntityKind::Skeleton)
+            .count();
+        let share = skeletons as f64 / samples as f64;
+        assert!((0.72..0.78).contains(&share), "skeleton share {}", share);
+
+        assert!(pick_entry(&[], &mut rng).is_none());
+    }
+
+    #[test]
+    fn persistent_and_named_mobs_are_not_despawned() {
+        let mut game = Game::new();
+        let position = base::position!(0.5, 64.0, 0.5);
+        let zombie = game
+            .ecs
+            .spawn((EntityKind::Zombie, WorldId::MAIN, position));
+        let persistent = game
+            .ecs
+            .spawn((EntityKind::Zombie, WorldId::MAIN, position, Persistent));
+        let named = game.ecs.spawn((
+            EntityKind::Zombie,
+            WorldId::MAIN,
+            position,
+            CustomName::new("Bob"),
+        ));
+
+        // Without players, every other monster is out of range
+        despawn_mobs(&mut game, &mut DespawnState::default()).unwrap();
+
+        assert!(game.ecs.get::<EntityRemoveEvent>(zombie).is_ok());
+        assert!(game.ecs.get::<EntityRemoveEvent>(persistent).is_err());
+        assert!(game.ecs.get::<EntityRemoveEvent>(named).is_err());
+    }
+}
@@ -0,0 +1,1112 @@
+//! Nether and end portals.
+//!
+//! Lighting the inside of an obsidian frame with flint and steel
+//! fills it with nether portal blocks, and inserting the last eye
+//! of ender into a ring of end portal frames opens an end portal.
+//!
+//! Entities standing in a portal trigger a [`PortalEnterEvent`]
+//! once they have been inside for the vanilla delay. Unless a plugin
+//! cancels the event, the chunks around the destination are loaded
+//! and the entity is then moved to the linked world. Nether portals
+//! lead to the closest portal near the position scaled 8:1 between
+//! the overworld and the nether; if there is none, a new portal is built.
+//! The search for that portal covers a few chunks each tick, loading
+//! them as it goes, so large searches do not stall the server.
+
+use std::{cmp::Reverse, convert::TryInto};
+
+use base::{
+    Area, AxisXz, BlockId, BlockKind, BlockPosition, ChunkPosition, Dimension, EntityKind,
+    Gamemode, Inventory, Item, Position,
+};
+use ecs::{Entity, SysResult, SystemExecutor};
+use libcraft_core::{BlockFace, Hand};
+use quill_common::{
+    components::Invulnerable,
+    entities::Player,
+    events::{BlockPlacementEvent, PortalEnterEvent, PortalKind},
+};
+
+use crate::{
+    chunk::loading, entities::player::HotbarSlot, events::InventoryUpdateEvent, Game, World,
+    WorldId, Worlds,
+};
+
+/// Ticks a player has to stand in a nether portal before being teleported.
+const PLAYER_NETHER_PORTAL_DELAY: u32 = 80;
+
+/// Ticks after using a portal before a player can use one again.
+const PLAYER_PORTAL_COOLDOWN: u32 = 10;
+/// Ticks after using a portal before any other entity can use one again.
+const ENTITY_PORTAL_COOLDOWN: u32 = 300;
+
+/// Ratio of horizontal distances in the overworld to those in the nether.
+const NETHER_SCALE: i32 = 8;
+
+/// Horizontal radius searched for an existing portal in the overworld.
+const OVERWORLD_SEARCH_RADIUS: i32 = 128;
+/// Horizontal radius searched for an existing portal in the nether.
+const NETHER_SEARCH_RADIUS: i32 = 16;
+/// Horizontal radius searched for a place to build a new portal.
+const BUILD_RADIUS: i32 = 16;
+/// Chunks searched for an existing portal per tick and teleport,
+/// which spreads searches of large areas over several ticks.
+const SEARCHED_CHUNKS_PER_TICK: usize = 8;
+
+/// Limits of the inside of a nether portal frame.
+const MIN_PORTAL_WIDTH: i32 = 2;
+const MAX_PORTAL_WIDTH: i32 = 21;
+const MIN_PORTAL_HEIGHT: i32 = 3;
+const MAX_PORTAL_HEIGHT: i32 = 21;
+
+/// Lowest y coordinate of a portal built where no space was found.
+const MIN_FORCED_PORTAL_Y: i32 = 70;
+/// Highest y coordinate of a built portal, keeping nether
+/// portals below the bedrock roof.
+const MAX_PORTAL_Y: i32 = 246;
+const MAX_NETHER_PORTAL_Y: i32 = 120;
+
+/// Where entities arrive in the end, on top of an obsidian platform.
+const END_SPAWN: BlockPosition = BlockPosition::new(100, 49, 0);
+
+/// Tracks an entity's use of portals.
+#[derive(Copy, Clone, Debug, Default)]
+pub struct PortalState {
+    /// Ticks spent in the current portal.
+    ticks_inside: u32,
+    /// Ticks until the entity may use a portal again.
+    cooldown: u32,
+}
+
+/// A teleport through a portal, waiting for the search for
+/// a linked portal and for the chunks around its destination.
+#[derive(Clone, Debug)]
+pub struct PendingPortalTeleport {
+    portal: PortalKind,
+    world: WorldId,
+    /// Where to look for a linked portal or to place the entity.
+    target: BlockPosition,
+    /// Chunks which have to be loaded before the entity arrives.
+    chunks: Vec<ChunkPosition>,
+    /// Chunks still to be searched for a linked portal, closest last.
+    search: Vec<ChunkPosition>,
+    search_radius: i32,
+    /// The lowest block of the closest portal found so
+    /// far, along with its squared distance to `target`.
+    linked_portal: Option<(i64, BlockPosition)>,
+}
+
+impl PendingPortalTeleport {
+    /// Searches the next few chunks for the portal closest
+    /// to the target. Returns the chunks to load before
+    /// the search can continue.
+    fn continue_search(&mut self, world: &World) -> Vec<ChunkPosition> {
+        let next = self.search.len().saturating_sub(SEARCHED_CHUNKS_PER_TICK);
+        let unloaded: Vec<_> = self.search[next..]
+            .iter()
+            .copied()
+            .filter(|&chunk| !world.is_chunk_loaded(chunk))
+            .collect();
+        if !unloaded.is_empty() {
+            return unloaded;
+        }
+
+        for chunk in self.search.drain(next..) {
+            let portal = closest_portal_in_chunk(world, chunk, self.target, self.search_radius);
+            if let Some((distance, pos)) = portal {
+                if self
+                    .linked_portal
+                    .map_or(true, |(closest, _)| distance < closest)
+                {
+                    self.linked_portal = Some((distance, lowest_portal_block(world, pos)));
+                }
+            }
+        }
+
+        // The remaining chunks cannot hold a portal closer than the one found.
+        if let (Some((closest, _)), Some(&next)) = (self.linked_portal, self.search.last()) {
+            if horizontal_distance_squared(next, self.target) >= closest {
+                self.search.clear();
+            }
+        }
+        Vec::new()
+    }
+}
+
+pub fn register(systems: &mut SystemExecutor<Game>) {
+    systems
+        .add_system(light_portals)
+        .add_system(start_portal_teleports)
+        .add_system(finish_portal_teleports)
+        .add_system(tick_portals);
+}
+
+/// Lights nether portals with flint and steel and
+/// inserts eyes of ender into end portal frames.
+fn light_portals(game: &mut Game) -> SysResult {
+    let mut uses = Vec::new();
+    for (player, (event, &world)) in game.ecs.query::<(&BlockPlacementEvent, &WorldId)>().iter() {
+        uses.push((player, world, event.hand, event.location, event.face));
+    }
+
+    for (player, world, hand, location, face) in uses {
+        match held_item(game, player, hand)? {
+            Some(Item::FlintAndSteel) => {
+                let frame = find_portal_frame(&game.worlds[world], adjacent(location, face));
+                if let Some(frame) = frame {
+                    let block = BlockId::nether_portal().with_axis_xz(frame.axis);
+                    for pos in frame.inside() {
+                        set_block(game, world, pos, block);
+                    }
+                }
+            }
+            Some(Item::EnderEye) => insert_eye(game, player, world, hand, location)?,
+            _ => {}
+        }
+    }
+    Ok(())
+}
+
+fn held_slot(game: &Game, player: Entity, hand: Hand) -> anyhow::Result<(Area, usize)> {
+    Ok(match hand {
+        Hand::Main => (Area::Hotbar, game.ecs.get::<HotbarSlot>(player)?.get()),
+        Hand::Off => (Area::Offhand, 0),
+    })
+}
+
+fn held_item(game: &Game, player: Entity, hand: Hand) -> anyhow::Result<Option<Item>> {
+    let (area, slot) = held_slot(game, player, hand)?;
+    let inventory = game.ecs.get::<Inventory>(player)?;
+    let item = inventory.item(area, slot).and_then(|item| item.item_kind());
+    Ok(item)
+}
+
+fn insert_eye(
+    game: &mut Game,
+    player: Entity,
+    world: WorldId,
+    hand: Hand,
+    pos: BlockPosition,
+) -> SysResult {
+    let frame = match block_at(&game.worlds[world], pos) {
+        Some(block) if block.kind() == BlockKind::EndPortalFrame && block.eye() == Some(false) => {
+            block
+        }
+        _ => return Ok(()),
+    };
+    set_block(game, world, pos, frame.with_eye(true));
+
+    if *game.ecs.get::<Gamemode>(player)? != Gamemode::Creative {
+        let (area, slot) = held_slot(game, player, hand)?;
+        {
+            let inventory = game.ecs.get::<Inventory>(player)?;
+            if let Some(mut item) = inventory.item(area, slot) {
+                let _ = item.try_take(1);
+            }
+        }
+        game.ecs.insert_entity_event(player, InventoryUpdateEvent)?;
+    }
+
+    if let Some(inside) = find_end_portal(&game.worlds[world], pos) {
+        for pos in inside {
+            set_block(game, world, pos, BlockId::end_portal());
+        }
+    }
+    Ok(())
+}
+
+/// Starts teleporting entities whose `PortalEnterEvent` was not cancelled.
+fn start_portal_teleports(game: &mut Game) -> SysResult {
+    let mut teleports = Vec::new();
+    for (entity, (event, &world, position)) in game
+        .ecs
+        .query::<(&PortalEnterEvent, &WorldId, &Position)>()
+        .iter()
+    {
+        if event.cancelled {
+            continue;
+        }
+        if let Some(teleport) = plan_teleport(&game.worlds, world, position.block(), event.portal) {
+            teleports.push((entity, teleport));
+        }
+    }
+
+    for (entity, teleport) in teleports {
+        loading::load_chunks_temporarily(game, teleport.world, &teleport.chunks);
+        game.ecs.insert(entity, teleport)?;
+    }
+    Ok(())
+}
+
+fn plan_teleport(
+    worlds: &Worlds,
+    world: WorldId,
+    pos: BlockPosition,
+    portal: PortalKind,
+) -> Option<PendingPortalTeleport> {
+    let destination = destination(worlds, world, portal)?;
+    let dimension = worlds[destination].dimension();
+    let (target, radius) = match portal {
+        PortalKind::Nether => (scale_position(pos, dimension), BUILD_RADIUS + 2),
+        PortalKind::End if dimension == Dimension::TheEnd => (END_SPAWN, 2),
+        PortalKind::End => (Position::default().block(), 0),
+    };
+    let search_radius = search_radius(dimension);
+    let search = match portal {
+        PortalKind::Nether => search_order(target, search_radius),
+        PortalKind::End => Vec::new(),
+    };
+    Some(PendingPortalTeleport {
+        portal,
+        world: destination,
+        target,
+        chunks: chunks_around(target, radius),
+        search,
+        search_radius,
+        linked_portal: None,
+    })
+}
+
+/// Gets the world a portal in `world` leads to.
+fn destination(worlds: &Worlds, world: WorldId, portal: PortalKind) -> Option<WorldId> {
+    let dimension = worlds.get(world)?.dimension();
+    match (portal, dimension) {
+        (PortalKind::Nether, Dimension::Overworld) => worlds.by_dimension(Dimension::TheNether),
+        (PortalKind::Nether, Dimension::TheNether) => worlds.by_dimension(Dimension::Overworld),
+        (PortalKind::Nether, Dimension::TheEnd) => None,
+        (PortalKind::End, Dimension::TheEnd) => worlds.by_dimension(Dimension::Overworld),
+        (PortalKind::End, _) => worlds.by_dimension(Dimension::TheEnd),
+    }
+}
+
+/// Scales a position for travel through a nether portal
+/// into a world of the given dimension.
+fn scale_position(pos: BlockPosition, destination: Dimension) -> BlockPosition {
+    if destination == Dimension::TheNether {
+        BlockPosition::new(
+            pos.x.div_euclid(NETHER_SCALE),
+            pos.y,
+            pos.z.div_euclid(NETHER_SCALE),
+        )
+    } else {
+        BlockPosition::new(pos.x * NETHER_SCALE, pos.y, pos.z * NETHER_SCALE)
+    }
+}
+
+fn search_radius(dimension: Dimension) -> i32 {
+    if dimension == Dimension::TheNether {
+        NETHER_SEARCH_RADIUS
+    } else {
+        OVERWORLD_SEARCH_RADIUS
+    }
+}
+
+fn max_portal_y(dimension: Dimension) -> i32 {
+    if dimension == Dimension::TheNether {
+        MAX_NETHER_PORTAL_Y
+    } else {
+        MAX_PORTAL_Y
+    }
+}
+
+fn chunks_around(center: BlockPosition, radius: i32) -> Vec<ChunkPosition> {
+    let min = BlockPosition::new(center.x - radius, 0, center.z - radius).chunk();
+    let max = BlockPosition::new(center.x + radius, 0, center.z + radius).chunk();
+    let mut chunks = Vec::new();
+    for x in min.x..=max.x {
+        for z in min.z..=max.z {
+            chunks.push(ChunkPosition::new(x, z));
+        }
+    }
+    chunks
+}
+
+/// Gets the chunks within `radius` blocks of `center`,
+/// ordered so that the closest chunk comes last.
+fn search_order(center: BlockPosition, radius: i32) -> Vec<ChunkPosition> {
+    let mut chunks = chunks_around(center, radius);
+    chunks.sort_by_key(|&chunk| Reverse(horizontal_distance_squared(chunk, center)));
+    chunks
+}
+
+/// Gets the squared horizontal distance from
+/// `pos` to the closest block column of `chunk`.
+fn horizontal_distance_squared(chunk: ChunkPosition, pos: BlockPosition) -> i64 {
+    let distance = |pos: i32, min: i32| i64::from((min - pos).max(pos - (min + 15)).max(0));
+    let (x, z) = (distance(pos.x, chunk.x * 16), distance(pos.z, chunk.z * 16));
+    x * x + z * z
+}
+
+/// Searches for linked portals and teleports entities
+/// once the chunks around their destination are loaded.
+fn finish_portal_teleports(game: &mut Game) -> SysResult {
+    let mut ready = Vec::new();
+    let mut missing = Vec::new();
+    for (entity, teleport) in game.ecs.query::<&mut PendingPortalTeleport>().iter() {
+        let world = &game.worlds[teleport.world];
+        let mut unloaded = teleport.continue_search(world);
+        if teleport.search.is_empty() {
+            let linked_chunk = teleport.linked_portal.map(|(_, portal)| portal.chunk());
+            unloaded.extend(
+                teleport
+                    .chunks
+                    .iter()
+                    .copied()
+                    .chain(linked_chunk)
+                    .filter(|&chunk| !world.is_chunk_loaded(chunk)),
+            );
+            if unloaded.is_empty() {
+                ready.push((entity, teleport.clone()));
+                continue;
+            }
+        }
+        if !unloaded.is_empty() {
+            missing.push((teleport.world, unloaded));
+        }
+    }
+
+    // Chunks may be unloaded again before slow generation of the others finishes.
+    for (world, chunks) in missing {
+        loading::load_chunks_temporarily(game, world, &chunks);
+    }
+
+    for (entity, teleport) in ready {
+        game.ecs.remove::<PendingPortalTeleport>(entity)?;
+        let arrival = match (teleport.portal, game.worlds[teleport.world].dimension()) {
+            (PortalKind::Nether, _) => match teleport.linked_portal {
+                Some((_, portal)) => portal,
+                None => build_linked_portal(game, teleport.world, teleport.target),
+            },
+            (PortalKind::End, Dimension::TheEnd) => {
+                build_end_platform(game, teleport.world);
+                END_SPAWN
+            }
+            (PortalKind::End, _) => teleport.target,
+        };
+
+        let old_position = *game.ecs.get::<Position>(entity)?;
+        let position = Position {
+            x: arrival.x as f64 + 0.5,
+            y: arrival.y as f64,
+            z: arrival.z as f64 + 0.5,
+            ..old_position
+        };
+        game.change_world(entity, teleport.world, position)?;
+
+        let cooldown = if game.ecs.get::<Player>(entity).is_ok() {
+            PLAYER_PORTAL_COOLDOWN
+        } else {
+            ENTITY_PORTAL_COOLDOWN
+        };
+        if let Ok(mut state) = game.ecs.get_mut::<PortalState>(entity) {
+            state.ticks_inside = 0;
+            state.cooldown = cooldown;
+        }
+    }
+    Ok(())
+}
+
+/// Builds a portal linked to one at `target`, where no existing portal
+/// was found. Returns the lowest portal block to place an entity at.
+fn build_linked_portal(game: &mut Game, world: WorldId, target: BlockPosition) -> BlockPosition {
+    let dimension = game.worlds[world].dimension();
+    match find_portal_site(&game.worlds[world], target) {
+        Some((pos, axis)) => {
+            build_nether_portal(game, world, pos, axis, false);
+            pos
+        }
+        None => {
+            let y = target
+                .y
+                .max(MIN_FORCED_PORTAL_Y)
+                .min(max_portal_y(dimension));
+            let pos = BlockPosition::new(target.x, y, target.z);
+            build_nether_portal(game, world, pos, AxisXz::X, true);
+            pos
+        }
+    }
+}
+
+/// Finds the nether portal block in `chunk_pos` which is closest to
+/// `target` and within `radius` blocks of it horizontally.
+///
+/// Returns that block along with its squared distance to `target`.
+fn closest_portal_in_chunk(
+    world: &World,
+    chunk_pos: ChunkPosition,
+    target: BlockPosition,
+    radius: i32,
+) -> Option<(i64, BlockPosition)> {
+    let chunk = world.chunk_map().chunk_at(chunk_pos)?;
+    let mut closest: Option<(i64, BlockPosition)> = None;
+    for section_y in 0..16 {
+        let section = match chunk.section(section_y) {
+            Some(section) => section,
+            None => continue,
+        };
+        if let Some(palette) = section.blocks().palette() {
+            if !palette
+                .as_slice()
+                .iter()
+                .any(|block| block.kind() == BlockKind::NetherPortal)
+            {
+                continue;
+            }
+        }
+
+        for y in 0..16 {
+            for z in 0..16 {
+                for x in 0..16 {
+                    if section.block_at(x, y, z).map(|block| block.kind())
+                        != Some(BlockKind::NetherPortal)
+                    {
+                        continue;
+                    }
+                    let pos = BlockPosition::new(
+                        chunk_pos.x * 16 + x as i32,
+                        section_y as i32 * 16 + y as i32,
+                        chunk_pos.z * 16 + z as i32,
+                    );
+                    if (pos.x - target.x).abs() > radius || (pos.z - target.z).abs() > radius {
+                        continue;
+                    }
+                    let distance = distance_squared(pos, target);
+                    if closest.map_or(true, |(closest, _)| distance < closest) {
+                        closest = Some((distance, pos));
+                    }
+                }
+            }
+        }
+    }
+    closest
+}
+
+/// Gets the lowest block of the portal column containing `pos`.
+fn lowest_portal_block(world: &World, mut pos: BlockPosition) -> BlockPosition {
+    while block_kind_at(world, pos.down()) == Some(BlockKind::NetherPortal) {
+        pos = pos.down();
+    }
+    pos
+}
+
+/// Finds the place closest to `target` where a portal fits on
+/// solid ground, returning the position of its lower left inside block.
+fn find_portal_site(world: &World, target: BlockPosition) -> Option<(BlockPosition, AxisXz)> {
+    let max_y = max_portal_y(world.dimension());
+    let mut closest: Option<(i64, BlockPosition, AxisXz)> = None;
+    for x in target.x - BUILD_RADIUS..=target.x + BUILD_RADIUS {
+        for z in target.z - BUILD_RADIUS..=target.z + BUILD_RADIUS {
+            for y in (1..=max_y).rev() {
+                let pos = BlockPosition::new(x, y, z);
+                if !is_air(world, pos) || !is_solid(world, pos.down()) {
+                    continue;
+                }
+                let distance = distance_squared(pos, target);
+                if closest.map_or(false, |(closest, _, _)| distance >= closest) {
+                    continue;
+                }
+                for &axis in &[AxisXz::X, AxisXz::Z] {
+                    if portal_fits(world, pos, axis) {
+                        closest = Some((distance, pos, axis));
+                        break;
+                    }
+                }
+            }
+        }
+    }
+    closest.map(|(_, pos, axis)| (pos, axis))
+}
+
+/// Determines whether a portal with its lower left inside block at `pos`
+/// can be built there: its frame needs solid ground and the portal
+/// needs air around it to step out into.
+fn portal_fits(world: &World, pos: BlockPosition, axis: AxisXz) -> bool {
+    (-1..=2).all(|i| {
+        let column = along(pos, axis, i);
+        is_solid(world, column.down())
+            && (0..=3).all(|dy| {
+                (-1..=1).all(|side| {
+                    is_air(
+                        world,
+                        across(column, axis, side) + BlockPosition::new(0, dy, 0),
+                    )
+                })
+            })
+    })
+}
+
+/// Builds a 2x3 nether portal with its lower left inside block at `pos`.
+///
+/// If `forced`, a platform is built below the portal and
+/// the space around it is cleared.
+fn build_nether_portal(
+    game: &mut Game,
+    world: WorldId,
+    pos: BlockPosition,
+    axis: AxisXz,
+    forced: bool,
+) {
+    if forced {
+        for i in -1..=2 {
+            for side in -1..=1 {
+                for dy in -1..=3 {
+                    let block = if dy == -1 {
+                        BlockId::obsidian()
+                    } else {
+                        BlockId::air()
+                    };
+                    let column = across(along(pos, axis, i), axis, side);
+                    set_block(game, world, column + BlockPosition::new(0, dy, 0), block);
+                }
+            }
+        }
+    }
+
+    let portal = BlockId::nether_portal().with_axis_xz(axis);
+    for i in -1..=2 {
+        for dy in -1..=3 {
+            let frame = i == -1 || i == 2 || dy == -1 || dy == 3;
+            let block = if frame { BlockId::obsidian() } else { portal };
+            set_block(
+                game,
+                world,
+                along(pos, axis, i) + BlockPosition::new(0, dy, 0),
+                block,
+            );
+        }
+    }
+}
+
+/// Builds the obsidian platform entities arrive on in the end.
+fn build_end_platform(game: &mut Game, world: WorldId) {
+    for x in -2..=2 {
+        for z in -2..=2 {
+            for dy in -1..=2 {
+                let block = if dy == -1 {
+                    BlockId::obsidian()
+                } else {
+                    BlockId::air()
+                };
+                set_block(game, world, END_SPAWN + BlockPosition::new(x, dy, z), block);
+            }
+        }
+    }
+}
+
+/// Triggers a `PortalEnterEvent` for entities which
+/// have been standing in a portal long enough.
+fn tick_portals(game: &mut Game) -> SysResult {
+    let mut entered = Vec::new();
+    let mut untracked = Vec::new();
+    for (entity, (_, &world, position, state, player, invulnerable, pending)) in game
+        .ecs
+        .query::<(
+            &EntityKind,
+            &WorldId,
+            &Position,
+            Option<&mut PortalState>,
+            Option<&Player>,
+            Option<&Invulnerable>,
+            Option<&PendingPortalTeleport>,
+        )>()
+        .iter()
+    {
+        let portal = portal_at(&game.worlds[world], position.block());
+        let state = match state {
+            Some(state) => state,
+            None => {
+                if portal.is_some() {
+                    untracked.push(entity);
+                }
+                continue;
+            }
+        };
+
+        let portal = match portal {
+            Some(portal) => portal,
+            None => {
+                state.ticks_inside = 0;
+                state.cooldown = state.cooldown.saturating_sub(1);
+                continue;
+            }
+        };
+        if pending.is_some() || destination(&game.worlds, world, portal).is_none() {
+            continue;
+        }
+        if state.cooldown > 0 {
+            // Entities have to step out of the portal they arrived in.
+            state.cooldown = if player.is_some() {
+                PLAYER_PORTAL_COOLDOWN
+            } else {
+                ENTITY_PORTAL_COOLDOWN
+            };
+            continue;
+        }
+
+        let delay = match portal {
+            PortalKind::Nether
+                if player.is_some()
+                    && !invulnerable.map_or(false, |invulnerable| invulnerable.0) =>
+            {
+                PLAYER_NETHER_PORTAL_DELAY
+            }
+            _ => 1,
+        };
+        state.ticks_inside += 1;
+        if state.ticks_inside >= delay {
+            state.ticks_inside = 0;
+            entered.push((entity, portal));
+        }
+    }
+
+    for entity in untracked {
+        game.ecs.insert(entity, PortalState::default())?;
+    }
+    for (entity, portal) in entered {
+        game.ecs.insert_entity_event(
+            entity,
+            PortalEnterEvent {
+                portal,
+                cancelled: false,
+            },
+        )?;
+    }
+    Ok(())
+}
+
+fn portal_at(world: &World, pos: BlockPosition) -> Option<PortalKind> {
+    match block_kind_at(world, pos)? {
+        BlockKind::NetherPortal => Some(PortalKind::Nether),
+        BlockKind::EndPortal => Some(PortalKind::End),
+        _ => None,
+    }
+}
+
+/// The inside of a nether portal frame.
+#[derive(Copy, Clone, Debug, PartialEq, Eq)]
+struct PortalFrame {
+    /// The lowest inside block closest to negative infinity along `axis`.
+    corner: BlockPosition,
+    axis: AxisXz,
+    width: i32,
+    height: i32,
+}
+
+impl PortalFrame {
+    fn inside(self) -> impl Iterator<Item = BlockPosition> {
+        (0..self.width).flat_map(move |i| {
+            (0..self.height)
+                .map(move |dy| along(self.corner, self.axis, i) + BlockPosition::new(0, dy, 0))
+        })
+    }
+}
+
+/// Finds an obsidian frame around `pos` which can hold a nether portal.
+fn find_portal_frame(world: &World, pos: BlockPosition) -> Option<PortalFrame> {
+    [AxisXz::X, AxisXz::Z]
+        .iter()
+        .find_map(|&axis| find_portal_frame_along(world, pos, axis))
+}
+
+fn find_portal_frame_along(world: &World, pos: BlockPosition, axis: AxisXz) -> Option<PortalFrame> {
+    let is_obsidian = |pos| block_kind_at(world, pos) == Some(BlockKind::Obsidian);
+    let is_inside = |pos| block_kind_at(world, pos).map_or(false, can_hold_portal);
+
+    if !is_inside(pos) {
+        return None;
+    }
+    let mut bottom = pos;
+    for _ in 0..MAX_PORTAL_HEIGHT {
+        if !is_inside(bottom.down()) {
+            break;
+        }
+        bottom = bottom.down();
+    }
+    if !is_obsidian(bottom.down()) {
+        return None;
+    }
+
+    let on_floor = |pos: BlockPosition| is_inside(pos) && is_obsidian(pos.down());
+    let mut corner = bottom;
+    for _ in 0..MAX_PORTAL_WIDTH {
+        let next = along(corner, axis, -1);
+        if !on_floor(next) {
+            break;
+        }
+        corner = next;
+    }
+    if !is_obsidian(along(corner, axis, -1)) {
+        return None;
+    }
+    let mut width = 1;
+    while width <= MAX_PORTAL_WIDTH && on_floor(along(corner, axis, width)) {
+        width += 1;
+    }
+    if width < MIN_PORTAL_WIDTH
+        || width > MAX_PORTAL_WIDTH
+        || !is_obsidian(along(corner, axis, width))
+    {
+        return None;
+    }
+
+    let mut height = 0;
+    loop {
+        if height > MAX_PORTAL_HEIGHT {
+            return None;
+        }
+        let row = corner + BlockPosition::new(0, height, 0);
+        if (0..width).all(|i| is_obsidian(along(row, axis, i))) {
+            break;
+        }
+        let enclosed = is_obsidian(along(row, axis, -1)) && is_obsidian(along(row, axis, width));
+        if !enclosed || !(0..width).all(|i| is_inside(along(row, axis, i))) {
+            return None;
+        }
+        height += 1;
+    }
+    if height < MIN_PORTAL_HEIGHT {
+        return None;
+    }
+
+    Some(PortalFrame {
+        corner,
+        axis,
+        width,
+        height,
+    })
+}
+
+fn can_hold_portal(kind: BlockKind) -> bool {
+    matches!(
+        kind,
+        BlockKind::Air
+            | BlockKind::CaveAir
+            | BlockKind::VoidAir
+            | BlockKind::Fire
+            | BlockKind::NetherPortal
+    )
+}
+
+/// Offsets of the frames of an end portal from its center.
+const END_PORTAL_RING: [(i32, i32); 12] = [
+    (-1, -2),
+    (0, -2),
+    (1, -2),
+    (-1, 2),
+    (0, 2),
+    (1, 2),
+    (-2, -1),
+    (-2, 0),
+    (-2, 1),
+    (2, -1),
+    (2, 0),
+    (2, 1),
+];
+
+/// Finds a complete ring of end portal frames with eyes which
+/// includes the frame at `frame`, returning the blocks inside it.
+fn find_end_portal(world: &World, frame: BlockPosition) -> Option<Vec<BlockPosition>> {
+    let has_eye = |pos| {
+        block_at(world, pos).map_or(false, |block| {
+            block.kind() == BlockKind::EndPortalFrame && block.eye() == Some(true)
+        })
+    };
+    END_PORTAL_RING.iter().find_map(|&(dx, dz)| {
+        let center = frame - BlockPosition::new(dx, 0, dz);
+        let complete = END_PORTAL_RING
+            .iter()
+            .all(|&(dx, dz)| has_eye(center + BlockPosition::new(dx, 0, dz)));
+        if !complete {
+            return None;
+        }
+        let mut inside = Vec::new();
+        for dx in -1..=1 {
+            for dz in -1..=1 {
+                inside.push(center + BlockPosition::new(dx, 0, dz));
+            }
+        }
+        Some(inside)
+    })
+}
+
+/// Moves `n` blocks along `axis`.
+fn along(pos: BlockPosition, axis: AxisXz, n: i32) -> BlockPosition {
+    match axis {
+        AxisXz::X => BlockPosition::new(pos.x + n, pos.y, pos.z),
+        AxisXz::Z => BlockPosition::new(pos.x, pos.y, pos.z + n),
+    }
+}
+
+/// Moves `n` blocks horizontally perpendicular to `axis`.
+fn across(pos: BlockPosition, axis: AxisXz, n: i32) -> BlockPosition {
+    match axis {
+        AxisXz::X => along(pos, AxisXz::Z, n),
+        AxisXz::Z => along(pos, AxisXz::X, n),
+    }
+}
+
+fn adjacent(pos: BlockPosition, face: BlockFace) -> BlockPosition {
+    let offset = match face {
+        BlockFace::Bottom => BlockPosition::new(0, -1, 0),
+        BlockFace::Top => BlockPosition::new(0, 1, 0),
+        BlockFace::North => BlockPosition::new(0, 0, -1),
+        BlockFace::South => BlockPosition::new(0, 0, 1),
+        BlockFace::West => BlockPosition::new(-1, 0, 0),
+        BlockFace::East => BlockPosition::new(1, 0, 0),
+    };
+    pos + offset
+}
+
+fn distance_squared(a: BlockPosition, b: BlockPosition) -> i64 {
+    let (dx, dy, dz) = ((a.x - b.x) as i64, (a.y - b.y) as i64, (a.z - b.z) as i64);
+    dx * dx + dy * dy + dz * dz
+}
+
+fn block_at(world: &World, pos: BlockPosition) -> Option<BlockId> {
+    world.block_at(pos.try_into().ok()?)
+}
+
+fn block_kind_at(world: &World, pos: BlockPosition) -> Option<BlockKind> {
+    block_at(world, pos).map(|block| block.kind())
+}
+
+fn is_air(world: &World, pos: BlockPosition) -> bool {
+    block_at(world, pos).map_or(false, BlockId::is_air)
+}
+
+fn is_solid(world: &World, pos: BlockPosition) -> bool {
+    block_at(world, pos).map_or(false, BlockId::is_solid)
+}
+
+fn set_block(game: &mut Game, world: WorldId, pos: BlockPosition, block: BlockId) {
+    if let Ok(pos) = pos.try_into() {
+        game.set_block(world, pos, block);
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use base::Chunk;
+
+    use super::*;
+
+    /// Creates a world with solid stone up to y=63.
+    fn stone_world() -> World {
+        let mut world = World::new();
+        for x in -3..=3 {
+            for z in -3..=3 {
+                let mut chunk = Chunk::new(ChunkPosition::new(x, z));
+                chunk.fill_section(4, BlockId::stone());
+                world.chunk_map_mut().insert_chunk(chunk);
+            }
+        }
+        world
+    }
+
+    fn set(world: &World, pos: BlockPosition, block: BlockId) {
+        world.set_block_at(pos.try_into().unwrap(), block);
+    }
+
+    fn nether_teleport(world: &World, target: BlockPosition, radius: i32) -> PendingPortalTeleport {
+        PendingPortalTeleport {
+            portal: PortalKind::Nether,
+            world: world.id(),
+            target,
+            chunks: Vec::new(),
+            search: search_order(target, radius),
+            search_radius: radius,
+            linked_portal: None,
+        }
+    }
+
+    /// Searches the loaded chunks of `world` for the portal linked to one at `target`.
+    fn search(world: &World, target: BlockPosition, radius: i32) -> Option<BlockPosition> {
+        let mut teleport = nether_teleport(world, target, radius);
+        while !teleport.search.is_empty() {
+            assert!(teleport.continue_search(world).is_empty());
+        }
+        teleport.linked_portal.map(|(_, portal)| portal)
+    }
+
+    /// Builds an obsidian frame around a 2x3 inside at (0, 65, 0) along x.
+    fn build_frame(world: &World) {
+        for x in -1..=2 {
+            for y in 64..=68 {
+                if x == -1 || x == 2 || y == 64 || y == 68 {
+                    set(world, BlockPosition::new(x, y, 0), BlockId::obsidian());
+                }
+            }
+        }
+    }
+
+    #[test]
+    fn finds_obsidian_frame() {
+        let world = stone_world();
+        build_frame(&world);
+
+        let frame = find_portal_frame(&world, BlockPosition::new(1, 67, 0)).unwrap();
+        assert_eq!(
+            frame,
+            PortalFrame {
+                corner: BlockPosition::new(0, 65, 0),
+                axis: AxisXz::X,
+                width: 2,
+                height: 3,
+            }
+        );
+        assert_eq!(frame.inside().count(), 6);
+
+        set(&world, BlockPosition::new(2, 66, 0), BlockId::air());
+        assert_eq!(
+            find_portal_frame(&world, BlockPosition::new(1, 67, 0)),
+            None
+        );
+    }
+
+    #[test]
+    fn finds_complete_end_portal_ring() {
+        let world = stone_world();
+        let center = BlockPosition::new(4, 64, 4);
+        for &(dx, dz) in &END_PORTAL_RING {
+            let frame = BlockId::end_portal_frame().with_eye(true);
+            set(&world, center + BlockPosition::new(dx, 0, dz), frame);
+        }
+        let last = center + BlockPosition::new(2, 0, 1);
+        assert_eq!(
+            find_end_portal(&world, last).map(|inside| inside.len()),
+            Some(9)
+        );
+
+        set(
+            &world,
+            center + BlockPosition::new(-1, 0, -2),
+            BlockId::end_portal_frame().with_eye(false),
+        );
+        assert_eq!(find_end_portal(&world, last), None);
+    }
+
+    #[test]
+    fn scales_positions_between_overworld_and_nether() {
+        let pos = BlockPosition::new(-17, 70, 80);
+        assert_eq!(
+            scale_position(pos, Dimension::TheNether),
+            BlockPosition::new(-3, 70, 10)
+        );
+        assert_eq!(
+            scale_position(BlockPosition::new(-3, 70, 10), Dimension::Overworld),
+            BlockPosition::new(-24, 70, 80)
+        );
+    }
+
+    #[test]
+    fn finds_lowest_block_of_closest_portal() {
+        let world = stone_world();
+        for y in 65..=67 {
+            set(
+                &world,
+                BlockPosition::new(10, y, 3),
+                BlockId::nether_portal(),
+            );
+            set(
+                &world,
+                BlockPosition::new(30, y, 3),
+                BlockId::nether_portal(),
+            );
+        }
+        assert_eq!(
+            search(&world, BlockPosition::new(0, 70, 0), NETHER_SEARCH_RADIUS),
+            Some(BlockPosition::new(10, 65, 3))
+        );
+        assert_eq!(
+            search(&world, BlockPosition::new(-20, 70, 0), NETHER_SEARCH_RADIUS),
+            None
+        );
+    }
+
+    #[test]
+    fn search_stops_once_no_closer_portal_can_exist() {
+        let world = stone_world();
+        for y in 65..=67 {
+            set(
+                &world,
+                BlockPosition::new(10, y, 3),
+                BlockId::nether_portal(),
+            );
+        }
+        // Most of the chunks within the overworld radius are not
+        // loaded, but the portal is found before they are needed.
+        let mut teleport = nether_teleport(
+            &world,
+            BlockPosition::new(0, 70, 0),
+            OVERWORLD_SEARCH_RADIUS,
+        );
+        assert!(teleport.continue_search(&world).is_empty());
+        assert!(teleport.search.is_empty());
+        assert_eq!(
+            teleport.linked_portal.map(|(_, portal)| portal),
+            Some(BlockPosition::new(10, 65, 3))
+        );
+    }
+
+    #[test]
+    fn entering_portals_teleports_unless_cancelled() {
+        let mut game = Game::new();
+        loading::register(&mut game, &mut SystemExecutor::new());
+        game.worlds = Worlds::new(stone_world());
+        let nether = game
+            .worlds
+            .add(stone_world().with_dimension(Dimension::TheNether));
+        for y in 65..=67 {
+            let overworld_portal = BlockPosition::new(0, y, 0);
+            set(
+                &game.worlds[WorldId::MAIN],
+                overworld_portal,
+                BlockId::nether_portal(),
+            );
+            let nether_portal = BlockPosition::new(3, y, 2);
+            set(
+                &game.worlds[nether],
+                nether_portal,
+                BlockId::nether_portal(),
+            );
+        }
+        let zombie = game.ecs.spawn((
+            EntityKind::Zombie,
+            WorldId::MAIN,
+            Position {
+                x: 0.5,
+                y: 65.0,
+                z: 0.5,
+                ..Default::default()
+            },
+            PortalState::default(),
+        ));
+
+        tick_portals(&mut game).unwrap();
+        game.ecs
+            .get_mut::<PortalEnterEvent>(zombie)
+            .unwrap()
+            .cancelled = true;
+        start_portal_teleports(&mut game).unwrap();
+        assert!(game.ecs.get::<PendingPortalTeleport>(zombie).is_err());
+
+        tick_portals(&mut game).unwrap();
+        assert!(!game.ecs.get::<PortalEnterEvent>(zombie).unwrap().cancelled);
+        start_portal_teleports(&mut game).unwrap();
+        assert!(game.ecs.get::<PendingPortalTeleport>(zombie).is_ok());
+        finish_portal_teleports(&mut game).unwrap();
+
+        assert_eq!(*game.ecs.get::<WorldId>(zombie).unwrap(), nether);
+        let position = *game.ecs.get::<Position>(zombie).unwrap();
+        assert_eq!((position.x, position.y, position.z), (3.5, 65.0, 2.5));
+        assert!(game.ecs.get::<PendingPortalTeleport>(zombie).is_err());
+    }
+
+    #[test]
+    fn finds_site_on_the_ground() {
+        let world = stone_world();
+        let (pos, _) = find_portal_site(&world, BlockPosition::new(0, 80, 0)).unwrap();
+        assert_eq!(pos.y, 64);
+        assert!(portal_fits(&world, pos, AxisXz::X));
+    }
+}
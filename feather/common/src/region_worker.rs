@@ -118,8 +118,8 @@ impl RegionWorker {
             None => return ChunkLoadResult::Missing(pos),
         };
 
-        let chunk = match file.handle.load_chunk(pos) {
-            Ok((chunk, _, _)) => chunk,
+        let (chunk, block_entities) = match file.handle.load_chunk(pos) {
+            Ok((chunk, _, block_entities)) => (chunk, block_entities),
             Err(e) => match e {
                 anvil::region::Error::ChunkNotExist => return ChunkLoadResult::Missing(pos),
                 err => return ChunkLoadResult::Error(err.into()),
@@ -128,7 +128,12 @@ impl RegionWorker {
 
         file.last_used = Instant::now();
 
-        ChunkLoadResult::Loaded(LoadedChunk { pos, chunk })
+        ChunkLoadResult::Loaded(LoadedChunk {
+            pos,
+            chunk,
+            block_entities,
+            generated: false,
+        })
     }
 
     fn region_file_handle(&mut self, region: RegionPosition) -> Option<&mut OpenRegionFile> {
@@ -0,0 +1,508 @@
+//! Mob spawners, both as block entities and attached to spawner minecarts.
+//!
+//! Spawner block entities are represented as entities with a
+//! [`SpawnerBlock`] and a [`MobSpawner`] component. They are created
+//! when their chunk is loaded and written back when it is unloaded.
+
+use std::convert::TryInto;
+
+use base::{
+    anvil::block_entity::{
+        BlockEntityBase, BlockEntityData, BlockEntityKind, SpawnPotential, SpawnerEntity,
+    },
+    BlockKind, BlockPosition, ChunkPosition, EntityKind, Position, ValidBlockPosition,
+};
+use ecs::{SysResult, SystemExecutor};
+use libcraft_core::MobCategory;
+use quill_common::entities::Player;
+use rand::{prelude::SliceRandom, Rng};
+
+use crate::{ai::pathfinding, events::ChunkLoadEvent, mob_spawning, Game, World, WorldId};
+
+/// Default number of ticks between spawns.
+const DEFAULT_MIN_SPAWN_DELAY: u32 = 200;
+const DEFAULT_MAX_SPAWN_DELAY: u32 = 800;
+/// Ticks until a newly created spawner first spawns.
+const DEFAULT_INITIAL_DELAY: u32 = 20;
+const DEFAULT_SPAWN_COUNT: u32 = 4;
+const DEFAULT_MAX_NEARBY_ENTITIES: u32 = 6;
+const DEFAULT_REQUIRED_PLAYER_RANGE: u32 = 16;
+const DEFAULT_SPAWN_RANGE: u32 = 4;
+
+/// Vertical distance from a spawner in which entities count
+/// towards `max_nearby_entities`.
+const NEARBY_ENTITIES_HEIGHT: f64 = 4.0;
+
+pub fn register(systems: &mut SystemExecutor<Game>) {
+    systems
+        .add_system(load_spawner_blocks)
+        .add_system(remove_broken_spawner_blocks)
+        .add_system(tick_spawners);
+}
+
+/// A mob spawner.
+///
+/// Attached to spawner minecarts and to the entities
+/// representing spawner block entities.
+#[derive(Clone, Debug, PartialEq)]
+pub struct MobSpawner {
+    /// The entity spawned next.
+    pub spawn_data: EntityKind,
+    /// Weighted entities from which the next `spawn_data` is picked
+    /// after each spawn. If empty, `spawn_data` never changes.
+    pub spawn_potentials: Vec<(EntityKind, u32)>,
+    /// Ticks until the next spawn.
+    pub delay: u32,
+    pub min_spawn_delay: u32,
+    pub max_spawn_delay: u32,
+    /// Number of entities spawned at once.
+    pub spawn_count: u32,
+    /// The spawner stops spawning while this many entities of the spawned
+    /// type are nearby.
+    pub max_nearby_entities: u32,
+    /// The spawner is only active while a player is within this many blocks.
+    pub required_player_range: u32,
+    /// Horizontal radius in which entities are spawned.
+    pub spawn_range: u32,
+}
+
+impl Default for MobSpawner {
+    fn default() -> Self {
+        Self::new(EntityKind::Pig)
+    }
+}
+
+impl MobSpawner {
+    /// Creates a spawner for the given entity
+    /// with vanilla's default settings.
+    pub fn new(spawn_data: EntityKind) -> Self {
+        Self {
+            spawn_data,
+            spawn_potentials: Vec::new(),
+            delay: DEFAULT_INITIAL_DELAY,
+            min_spawn_delay: DEFAULT_MIN_SPAWN_DELAY,
+            max_spawn_delay: DEFAULT_MAX_SPAWN_DELAY,
+            spawn_count: DEFAULT_SPAWN_COUNT,
+            max_nearby_entities: DEFAULT_MAX_NEARBY_ENTITIES,
+            required_player_range: DEFAULT_REQUIRED_PLAYER_RANGE,
+            spawn_range: DEFAULT_SPAWN_RANGE,
+        }
+    }
+
+    /// Creates a spawner from the data of a `minecraft:mob_spawner`
+    /// block entity. Returns `None` for other block entities.
+    pub fn from_block_entity(kind: &BlockEntityKind) -> Option<Self> {
+        match kind {
+            BlockEntityKind::MobSpawner {
+                spawn_data,
+                spawn_potentials,
+                delay,
+                min_spawn_delay,
+                max_spawn_delay,
+                spawn_count,
+                max_nearby_entities,
+                required_player_range,
+                spawn_range,
+            } => {
+                let spawn_potentials: Vec<(EntityKind, u32)> = spawn_potentials
+                    .iter()
+                    .filter_map(|potential| {
+                        Some((
+                            parse_entity_id(&potential.entity.id)?,
+                            potential.weight.max(0) as u32,
+                        ))
+                    })
+                    .collect();
+                let spawn_data = spawn_data
+                    .as_ref()
+                    .and_then(|data| parse_entity_id(&data.id))
+                    .or_else(|| spawn_potentials.first().map(|(kind, _)| *kind))
+                    .unwrap_or(EntityKind::Pig);
+
+                let min_spawn_delay = (*min_spawn_delay).max(0) as u32;
+                Some(Self {
+                    spawn_data,
+                    spawn_potentials,
+                    delay: (*delay).max(0) as u32,
+                    min_spawn_delay,
+                    max_spawn_delay: ((*max_spawn_delay).max(0) as u32).max(min_spawn_delay),
+                    spawn_count: (*spawn_count).max(0) as u32,
+                    max_nearby_entities: (*max_nearby_entities).max(0) as u32,
+                    required_player_range: (*required_player_range).max(0) as u32,
+                    spawn_range: (*spawn_range).max(0) as u32,
+                })
+            }
+            _ => None,
+        }
+    }
+
+    /// Converts this spawner to block entity data.
+    pub fn to_block_entity(&self) -> BlockEntityKind {
+        BlockEntityKind::MobSpawner {
+            spawn_data: Some(spawner_entity(self.spawn_data)),
+            spawn_potentials: self
+                .spawn_potentials
+                .iter()
+                .map(|&(kind, weight)| SpawnPotential {
+                    entity: spawner_entity(kind),
+                    weight: weight as i32,
+                })
+                .collect(),
+            delay: self.delay as i16,
+            min_spawn_delay: self.min_spawn_delay as i16,
+            max_spawn_delay: self.max_spawn_delay as i16,
+            spawn_count: self.spawn_count as i16,
+            max_nearby_entities: self.max_nearby_entities as i16,
+            required_player_range: self.required_player_range as i16,
+            spawn_range: self.spawn_range as i16,
+        }
+    }
+
+    /// Resets the delay and picks the next entity to spawn.
+    fn reset(&mut self, rng: &mut impl Rng) {
+        self.delay = rng.gen_range(self.min_spawn_delay..=self.max_spawn_delay);
+        if let Ok(&(kind, _)) = self
+            .spawn_potentials
+            .choose_weighted(rng, |&(_, weight)| weight)
+        {
+            self.spawn_data = kind;
+        }
+    }
+}
+
+fn parse_entity_id(id: &str) -> Option<EntityKind> {
+    EntityKind::from_name(id.strip_prefix("minecraft:").unwrap_or(id))
+}
+
+fn spawner_entity(kind: EntityKind) -> SpawnerEntity {
+    SpawnerEntity {
+        id: format!("minecraft:{}", kind.name()),
+    }
+}
+
+/// Marks an entity as the block entity of the spawner block
+/// at the given position.
+#[derive(Copy, Clone, Debug, PartialEq, Eq)]
+pub struct SpawnerBlock(pub ValidBlockPosition);
+
+/// Creates entities for the spawner block entities in newly loaded chunks.
+fn load_spawner_blocks(game: &mut Game) -> SysResult {
+    let mut spawners = Vec::new();
+    for (_, event) in game.ecs.query::<&ChunkLoadEvent>().iter() {
+        for block_entity in &event.block_entities {
+            let spawner = match MobSpawner::from_block_entity(&block_entity.kind) {
+                Some(spawner) => spawner,
+                None => continue,
+            };
+            let base = &block_entity.base;
+            if let Ok(pos) = BlockPosition::new(base.x, base.y, base.z).try_into() {
+                spawners.push((SpawnerBlock(pos), event.world, spawner));
+            }
+        }
+    }
+
+    for spawner in spawners {
+        game.ecs.spawn(spawner);
+    }
+    Ok(())
+}
+
+/// Removes the entities of spawner blocks in the given chunk
+/// of `world` and returns their block entity data.
+///
+/// Called when the chunk is unloaded.
+pub fn unload_spawner_blocks(
+    game: &mut Game,
+    world: WorldId,
+    chunk: ChunkPosition,
+) -> Vec<BlockEntityData> {
+    let mut block_entities = Vec::new();
+    let mut entities = Vec::new();
+    for (entity, (block, &block_world, spawner)) in game
+        .ecs
+        .query::<(&SpawnerBlock, &WorldId, &MobSpawner)>()
+        .iter()
+    {
+        let pos = BlockPosition::from(block.0);
+        if pos.chunk() != chunk || block_world != world {
+            continue;
+        }
+        block_entities.push(BlockEntityData {
+            base: BlockEntityBase {
+                x: pos.x,
+                y: pos.y,
+                z: pos.z,
+            },
+            kind: spawner.to_block_entity(),
+        });
+        entities.push(entity);
+    }
+
+    for entity in entities {
+        let _ = game.ecs.despawn(entity);
+    }
+    block_entities
+}
+
+/// Removes spawner block entities whose block is no longer a spawner.
+fn remove_broken_spawner_blocks(game: &mut Game) -> SysResult {
+    let mut broken = Vec::new();
+    for (entity, (block, &world)) in game.ecs.query::<(&SpawnerBlock, &WorldId)>().iter() {
+        if let Some(id) = game.block(world, block.0) {
+            if id.kind() != BlockKind::Spawner {
+                broken.push(entity);
+            }
+        }
+    }
+
+    for entity in broken {
+        game.ecs.despawn(entity)?;
+    }
+    Ok(())
+}
+
+fn tick_spawners(game: &mut Game) -> SysResult {
+    let players: Vec<(WorldId, Position)> = game
+        .ecs
+        .query::<(&WorldId, &Position, &Player)>()
+        .iter()
+        .map(|(_, (&world, &position, _))| (world, position))
+        .collect();
+    let mobs: Vec<(WorldId, EntityKind, Position)> = game
+        .ecs
+        .query::<(&WorldId, &EntityKind, &Position)>()
+        .iter()
+        .map(|(_, (&world, &kind, &position))| (world, kind, position))
+        .collect();
+
+    let mut rng = rand::thread_rng();
+    let mut to_spawn = Vec::new();
+    for (_, (spawner, &world, block, position)) in game
+        .ecs
+        .query::<(
+            &mut MobSpawner,
+            &WorldId,
+            Option<&SpawnerBlock>,
+            Option<&Position>,
+        )>()
+        .iter()
+    {
+        // Spawner blocks spawn around their center;
+        // spawner minecarts spawn around the minecart.
+        let center = match (block, position) {
+            (Some(block), _) => {
+                let pos = BlockPosition::from(block.0);
+                base::position!(pos.x as f64 + 0.5, pos.y as f64, pos.z as f64 + 0.5)
+            }
+            (None, Some(position)) => *position,
+            (None, None) => continue,
+        };
+
+        let range = spawner.required_player_range as f64;
+        if !players.iter().any(|(player_world, player)| {
+            *player_world == world && player.distance_squared_to(center) <= range * range
+        }) {
+            continue;
+        }
+
+        if spawner.delay > 0 {
+            spawner.delay -= 1;
+            continue;
+        }
+
+        let kind = spawner.spawn_data;
+        let spawn_range = spawner.spawn_range as i32;
+        let nearby = mobs
+            .iter()
+            .filter(|(mob_world, mob_kind, mob_position)| {
+                *mob_world == world
+                    && *mob_kind == kind
+                    && (mob_position.x - center.x).abs() <= spawn_range as f64 + 0.5
+                    && (mob_position.y - center.y).abs() <= NEARBY_ENTITIES_HEIGHT
+                    && (mob_position.z - center.z).abs() <= spawn_range as f64 + 0.5
+            })
+            .count() as u32;
+        if nearby >= spawner.max_nearby_entities {
+            spawner.reset(&mut rng);
+            continue;
+        }
+
+        let height = kind.bounding_box().max.y.ceil().max(1.0) as u32;
+        let origin = center.block();
+        let mut spawned = 0;
+        for _ in 0..spawner.spawn_count {
+            if nearby + spawned >= spawner.max_nearby_entities {
+                break;
+            }
+            let pos = BlockPosition::new(
+                origin.x + rng.gen_range(-spawn_range..=spawn_range),
+                origin.y + rng.gen_range(-1..=1),
+                origin.z + rng.gen_range(-spawn_range..=spawn_range),
+            );
+            if !can_spawn_at(&game.worlds[world], kind, pos, height, &mut rng) {
+                continue;
+            }
+            let position = Position {
+                x: pos.x as f64 + 0.5,
+                y: pos.y as f64,
+                z: pos.z as f64 + 0.5,
+                pitch: 0.0,
+                yaw: rng.gen_range(0.0..360.0),
+            };
+            to_spawn.push((world, position, kind));
+            spawned += 1;
+        }
+
+        if spawned > 0 {
+            spawner.reset(&mut rng);
+        }
+    }
+
+    for (world, position, kind) in to_spawn {
+        let mut builder = game.create_entity_builder(position, kind.into());
+        builder.add(world);
+        game.spawn_entity(builder);
+    }
+
+    Ok(())
+}
+
+/// Determines whether a spawner can spawn a mob of type `kind` at `pos`.
+///
+/// Mobs follow the ground and light rules of natural spawning.
+/// Other entities only need ground to stand on.
+fn can_spawn_at(
+    world: &World,
+    kind: EntityKind,
+    pos: BlockPosition,
+    height: u32,
+    rng: &mut impl Rng,
+) -> bool {
+    match MobCategory::of(kind) {
+        Some(category) => mob_spawning::is_valid_spawn(world, kind, category, pos, rng),
+        None => pathfinding::can_stand_at(world, pos, height),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn spawner_block_entity() -> BlockEntityKind {
+        BlockEntityKind::MobSpawner {
+            spawn_data: Some(SpawnerEntity {
+                id: "minecraft:zombie".to_owned(),
+            }),
+            spawn_potentials: vec![
+                SpawnPotential {
+                    entity: SpawnerEntity {
+                        id: "minecraft:zombie".to_owned(),
+                    },
+                    weight: 3,
+                },
+                SpawnPotential {
+                    entity: SpawnerEntity {
+                        id: "minecraft:skeleton".to_owned(),
+                    },
+                    weight: 1,
+                },
+            ],
+            delay: 10,
+            min_spawn_delay: 100,
+            max_spawn_delay: 300,
+            spawn_count: 2,
+            max_nearby_entities: 5,
+            required_player_range: 12,
+            spawn_range: 3,
+        }
+    }
+
+    #[test]
+    fn spawner_from_block_entity() {
+        let spawner = MobSpawner::from_block_entity(&spawner_block_entity()).unwrap();
+        assert_eq!(spawner.spawn_data, EntityKind::Zombie);
+        assert_eq!(
+            spawner.spawn_potentials,
+            vec![(EntityKind::Zombie, 3), (EntityKind::Skeleton, 1)]
+        );
+        assert_eq!(spawner.delay, 10);
+        assert_eq!(spawner.spawn_count, 2);
+        assert_eq!(spawner.required_player_range, 12);
+
+        assert!(MobSpawner::from_block_entity(&BlockEntityKind::Unknown).is_none());
+    }
+
+    #[test]
+    fn spawner_block_entity_roundtrip() {
+        let spawner = MobSpawner::from_block_entity(&spawner_block_entity()).unwrap();
+        let roundtrip = MobSpawner::from_block_entity(&spawner.to_block_entity()).unwrap();
+        assert_eq!(spawner, roundtrip);
+    }
+
+    #[test]
+    fn reset_picks_from_potentials() {
+        let mut spawner = MobSpawner::from_block_entity(&spawner_block_entity()).unwrap();
+        let mut rng = rand::thread_rng();
+        for _ in 0..20 {
+            spawner.reset(&mut rng);
+            assert!((100..=300).contains(&spawner.delay));
+            assert!(matches!(
+                spawner.spawn_data,
+                EntityKind::Zombie | EntityKind::Skeleton
+            ));
+        }
+    }
+
+    #[test]
+    fn generated_spawners_use_vanilla_defaults() {
+        let kind = BlockEntityKind::mob_spawner("minecraft:silverfish");
+        assert_eq!(
+            MobSpawner::from_block_entity(&kind),
+            Some(MobSpawner::new(EntityKind::Silverfish))
+        );
+    }
+
+    #[test]
+    fn spawned_mobs_need_ground_and_darkness() {
+        let mut world = World::new();
+        let mut chunk = base::Chunk::new(ChunkPosition::new(0, 0));
+        for section in 1..=5 {
+            chunk.fill_section(section, base::BlockId::stone());
+        }
+        // A dark room under a stone roof, with a shaft to the sky at x=8.
+        for x in 0..=8 {
+            for y in 64..=65 {
+                chunk.set_block_at(x, y, 0, base::BlockId::air());
+            }
+        }
+        for y in 66..80 {
+            chunk.set_block_at(8, y, 0, base::BlockId::air());
+        }
+        chunk.recalculate_heightmaps();
+        world.chunk_map_mut().insert_chunk(chunk);
+
+        let mut rng = rand::thread_rng();
+        let zombie = EntityKind::Zombie;
+        assert!(can_spawn_at(
+            &world,
+            zombie,
+            BlockPosition::new(1, 64, 0),
+            2,
+            &mut rng
+        ));
+        assert!(!can_spawn_at(
+            &world,
+            zombie,
+            BlockPosition::new(1, 65, 0),
+            2,
+            &mut rng
+        ));
+        assert!(!can_spawn_at(
+            &world,
+            zombie,
+            BlockPosition::new(8, 64, 0),
+            2,
+            &mut rng
+        ));
+    }
+}
@@ -5,7 +5,7 @@ use itertools::Either;
 use quill_common::components::Name;
 use quill_common::events::PlayerJoinEvent;
 
-use crate::{events::ViewUpdateEvent, Game};
+use crate::{events::ViewUpdateEvent, Game, WorldId};
 
 /// Registers systems to update the `View` of a player.
 pub fn register(_game: &mut Game, systems: &mut SystemExecutor<Game>) {
@@ -14,15 +14,21 @@ pub fn register(_game: &mut Game, systems: &mut SystemExecutor<Game>) {
         .add_system(update_view_on_join);
 }
 
-/// Updates players' views when they change chunks.
+/// Updates players' views when they change chunks, worlds
+/// or view distance.
 fn update_player_views(game: &mut Game) -> SysResult {
     let mut events = Vec::new();
-    for (player, (view, &position, name)) in
-        game.ecs.query::<(&mut View, &Position, &Name)>().iter()
+    for (player, (view, &view_distance, &position, &world, name)) in game
+        .ecs
+        .query::<(&mut View, &ViewDistance, &Position, &WorldId, &Name)>()
+        .iter()
     {
-        if position.chunk() != view.center() {
+        if position.chunk() != view.center()
+            || world != view.world()
+            || view_distance.0 != view.view_distance
+        {
             let old_view = *view;
-            let new_view = View::new(position.chunk(), old_view.view_distance);
+            let new_view = View::new(world, position.chunk(), view_distance.0);
 
             let event = ViewUpdateEvent::new(old_view, new_view);
             events.push((player, event));
@@ -52,19 +58,28 @@ fn update_view_on_join(game: &mut Game) -> SysResult {
     Ok(())
 }
 
+/// The view distance a player should have, i.e. the lower of
+/// the server's and the one requested by their client.
+///
+/// Changes are applied to the player's [`View`] at the start of the next tick.
+#[derive(Copy, Clone, Debug, PartialEq, Eq)]
+pub struct ViewDistance(pub u32);
+
 /// The view of a player, representing the set of chunks
-/// within their view distance.
+/// within their view distance in the world they are in.
 #[derive(Copy, Clone, Debug, PartialEq, Eq)]
 pub struct View {
+    world: WorldId,
     center: ChunkPosition,
     view_distance: u32,
 }
 
 impl View {
-    /// Creates a `View` from a center chunk (the position of the player)
-    /// and the view distance.
-    pub fn new(center: ChunkPosition, view_distance: u32) -> Self {
+    /// Creates a `View` from the player's world, a center chunk
+    /// (the position of the player) and the view distance.
+    pub fn new(world: WorldId, center: ChunkPosition, view_distance: u32) -> Self {
         Self {
+            world,
             center,
             view_distance,
         }
@@ -72,7 +87,7 @@ impl View {
 
     /// Gets the empty view, i.e., the view containing no chunks.
     pub fn empty() -> Self {
-        Self::new(ChunkPosition::new(0, 0), 0)
+        Self::new(WorldId::MAIN, ChunkPosition::new(0, 0), 0)
     }
 
     /// Determines whether this is the empty view.
@@ -80,6 +95,10 @@ impl View {
         self.view_distance == 0
     }
 
+    pub fn world(&self) -> WorldId {
+        self.world
+    }
+
     pub fn center(&self) -> ChunkPosition {
         self.center
     }
@@ -111,10 +130,16 @@ impl View {
     }
 
     /// Returns the set of chunks that are in `self` but not in `other`.
+    ///
+    /// If the views are of different worlds, this is all chunks of `self`.
     pub fn difference(self, other: View) -> impl Iterator<Item = ChunkPosition> {
         // PERF: consider analytical approach instead of sets
         let self_chunks: AHashSet<_> = self.iter().collect();
-        let other_chunks: AHashSet<_> = other.iter().collect();
+        let other_chunks: AHashSet<_> = if self.world == other.world {
+            other.iter().collect()
+        } else {
+            AHashSet::new()
+        };
         self_chunks
             .difference(&other_chunks)
             .copied()
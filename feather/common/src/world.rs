@@ -1,12 +1,19 @@
-use std::{path::PathBuf, sync::Arc};
+use std::{
+    ops::{Index, IndexMut},
+    path::PathBuf,
+    sync::Arc,
+    time::{Duration, Instant},
+};
 
 use ahash::{AHashMap, AHashSet};
+use anyhow::bail;
 use parking_lot::{RwLockReadGuard, RwLockWriteGuard};
 use uuid::Uuid;
 
-use base::anvil::player::PlayerData;
+use base::anvil::{block_entity::BlockEntityData, player::PlayerData};
 use base::{
-    BlockPosition, Chunk, ChunkHandle, ChunkLock, ChunkPosition, ValidBlockPosition, CHUNK_HEIGHT,
+    BlockPosition, Chunk, ChunkHandle, ChunkLock, ChunkPosition, Dimension, ValidBlockPosition,
+    CHUNK_HEIGHT,
 };
 use blocks::BlockId;
 use ecs::{Ecs, SysResult};
@@ -14,10 +21,32 @@ use worldgen::{ComposableGenerator, WorldGenerator};
 
 use crate::{
     chunk::cache::ChunkCache,
+    chunk::generation::GenerationStats,
+    chunk::pregen::{PregenArea, PregenProgress, Pregeneration, PROGRESS_FILE},
     chunk::worker::{ChunkWorker, LoadRequest, SaveRequest},
     events::ChunkLoadEvent,
 };
 
+/// The maximum number of chunks a pregeneration
+/// may have queued for loading at once.
+const MAX_PREGEN_IN_FLIGHT: usize = 64;
+
+/// Pregeneration stops queueing chunks while this many
+/// chunks wait to be generated, so chunks needed by
+/// players aren't held up behind it.
+const MAX_PREGEN_GENERATION_QUEUE: usize = 16;
+
+/// Identifies a world of the [`Game`](crate::Game).
+///
+/// Also a component storing the world an entity is in.
+#[derive(Copy, Clone, Debug, Default, PartialEq, Eq, Hash)]
+pub struct WorldId(u32);
+
+impl WorldId {
+    /// The main world, in which players spawn.
+    pub const MAIN: WorldId = WorldId(0);
+}
+
 /// Stores all blocks and chunks in a world,
 /// along with global world data like weather, time,
 /// and the [`WorldSource`](crate::world_source::WorldSource).
@@ -25,25 +54,45 @@ use crate::{
 /// NB: _not_ what most Rust ECSs call "world."
 /// This does not store entities; it only contains blocks.
 pub struct World {
+    id: WorldId,
+    dimension: Dimension,
     chunk_map: ChunkMap,
     pub cache: ChunkCache,
     chunk_worker: ChunkWorker,
-    loading_chunks: AHashSet<ChunkPosition>,
+    /// Chunks queued to be loaded, and when they were queued.
+    loading_chunks: AHashMap<ChunkPosition, Instant>,
     canceled_chunk_loads: AHashSet<ChunkPosition>,
+    load_stats: ChunkLoadStats,
+    pregeneration: Option<Pregeneration>,
     world_dir: PathBuf,
 }
 
+/// Statistics of chunks loaded by a [`World`].
+#[derive(Copy, Clone, Debug, Default)]
+pub struct ChunkLoadStats {
+    /// Chunks loaded from the world save or generated.
+    pub loaded: u64,
+    /// Total time between queueing chunks to be
+    /// loaded and their loading finishing.
+    pub total_latency: Duration,
+}
+
 impl Default for World {
     fn default() -> Self {
         Self {
+            id: WorldId::MAIN,
+            dimension: Dimension::Overworld,
             chunk_map: ChunkMap::new(),
             chunk_worker: ChunkWorker::new(
                 "world",
                 Arc::new(ComposableGenerator::default_with_seed(0)),
+                0,
             ),
             cache: ChunkCache::new(),
-            loading_chunks: AHashSet::new(),
+            loading_chunks: AHashMap::new(),
             canceled_chunk_loads: AHashSet::new(),
+            load_stats: ChunkLoadStats::default(),
+            pregeneration: None,
             world_dir: "world".into(),
         }
     }
@@ -54,20 +103,63 @@ impl World {
         Self::default()
     }
 
+    /// Creates a world saved in `world_dir`, whose missing chunks
+    /// are generated by `generator` on `generation_threads` threads.
+    /// If `generation_threads` is 0, one thread per CPU is used.
+    ///
+    /// A pregeneration interrupted by a restart is resumed.
     pub fn with_gen_and_path(
         generator: Arc<dyn WorldGenerator>,
         world_dir: impl Into<PathBuf> + Clone,
+        generation_threads: usize,
     ) -> Self {
+        let world_dir: PathBuf = world_dir.into();
+        let pregeneration = match Pregeneration::load(&world_dir.join(PROGRESS_FILE)) {
+            Ok(Some(pregeneration)) => {
+                log::info!(
+                    "Resuming pregeneration of {:?}: {}",
+                    pregeneration.area(),
+                    pregeneration.progress()
+                );
+                Some(pregeneration)
+            }
+            Ok(None) => None,
+            Err(e) => {
+                log::error!("Failed to load pregeneration progress: {:?}", e);
+                None
+            }
+        };
         Self {
-            world_dir: world_dir.clone().into(),
-            chunk_worker: ChunkWorker::new(world_dir, generator),
+            chunk_worker: ChunkWorker::new(world_dir.clone(), generator, generation_threads),
+            world_dir,
+            pregeneration,
             ..Default::default()
         }
     }
 
+    /// Sets the dimension of the world, which determines
+    /// how clients render it. Defaults to the overworld.
+    pub fn with_dimension(mut self, dimension: Dimension) -> Self {
+        self.dimension = dimension;
+        self
+    }
+
+    /// Returns the ID of the world within its [`Worlds`].
+    pub fn id(&self) -> WorldId {
+        self.id
+    }
+
+    pub fn dimension(&self) -> Dimension {
+        self.dimension
+    }
+
     /// Queues the given chunk to be loaded. If the chunk was cached, it is loaded immediately.
     pub fn queue_chunk_load(&mut self, req: LoadRequest) {
         let pos = req.pos;
+        // The chunk is still loading; keep it once it's loaded.
+        if self.canceled_chunk_loads.remove(&pos) {
+            return;
+        }
         if self.cache.contains(&pos) {
             // Move the chunk from the cache to the map
             self.chunk_map
@@ -75,7 +167,7 @@ impl World {
                 .insert(pos, self.cache.remove(pos).unwrap());
             self.chunk_map.chunk_handle_at(pos).unwrap().set_loaded();
         } else {
-            self.loading_chunks.insert(req.pos);
+            self.loading_chunks.insert(req.pos, Instant::now());
             self.chunk_worker.queue_load(req);
         }
     }
@@ -84,37 +176,71 @@ impl World {
     /// after a call to [`World::queue_chunk_load`].
     pub fn load_chunks(&mut self, ecs: &mut Ecs) -> SysResult {
         while let Some(loaded) = self.chunk_worker.poll_loaded_chunk()? {
-            self.loading_chunks.remove(&loaded.pos);
-            if self.canceled_chunk_loads.remove(&loaded.pos) {
+            let pregenerated = match &mut self.pregeneration {
+                Some(pregeneration) => pregeneration.complete(loaded.pos),
+                None => false,
+            };
+            let requested = self.loading_chunks.remove(&loaded.pos);
+            if self.canceled_chunk_loads.remove(&loaded.pos) || requested.is_none() {
+                // Nobody needs the chunk loaded, but a
+                // pregenerated chunk still needs saving.
+                if pregenerated && loaded.generated {
+                    self.chunk_worker.queue_chunk_save(SaveRequest {
+                        pos: loaded.pos,
+                        chunk: Arc::new(ChunkLock::new(loaded.chunk, false)),
+                        entities: vec![],
+                        block_entities: loaded.block_entities,
+                    });
+                }
                 continue;
             }
+            if let Some(queued_at) = requested {
+                self.load_stats.loaded += 1;
+                self.load_stats.total_latency += queued_at.elapsed();
+            }
             let chunk = loaded.chunk;
 
             self.chunk_map.insert_chunk(chunk);
             ecs.insert_event(ChunkLoadEvent {
+                world: self.id,
                 chunk: Arc::clone(&self.chunk_map.0[&loaded.pos]),
                 position: loaded.pos,
+                block_entities: loaded.block_entities,
             });
             log::trace!("Loaded chunk {:?}", loaded.pos);
         }
         Ok(())
     }
 
-    /// Unloads the given chunk.
-    pub fn unload_chunk(&mut self, pos: ChunkPosition) -> anyhow::Result<()> {
+    /// Unloads the given chunk, saving it along with
+    /// the given block entities.
+    pub fn unload_chunk(
+        &mut self,
+        pos: ChunkPosition,
+        block_entities: Vec<BlockEntityData>,
+    ) -> anyhow::Result<()> {
         if let Some((pos, handle)) = self.chunk_map.0.remove_entry(&pos) {
             handle.set_unloaded()?;
             self.chunk_worker.queue_chunk_save(SaveRequest {
                 pos,
                 chunk: handle.clone(),
                 entities: vec![],
-                block_entities: vec![],
+                block_entities,
             });
             self.cache.insert(pos, handle);
         }
         self.chunk_map.remove_chunk(pos);
         if self.is_chunk_loading(pos) {
-            self.canceled_chunk_loads.insert(pos);
+            // Chunks being pregenerated are saved once generated.
+            let pregenerating = self
+                .pregeneration
+                .as_ref()
+                .map_or(false, |pregeneration| pregeneration.is_in_flight(pos));
+            if !pregenerating && self.chunk_worker.cancel_generation(pos) {
+                self.loading_chunks.remove(&pos);
+            } else {
+                self.canceled_chunk_loads.insert(pos);
+            }
         }
 
         log::trace!("Unloaded chunk {:?}", pos);
@@ -128,7 +254,101 @@ impl World {
 
     /// Returns whether the given chunk is queued to be loaded.
     pub fn is_chunk_loading(&self, pos: ChunkPosition) -> bool {
-        self.loading_chunks.contains(&pos)
+        self.loading_chunks.contains_key(&pos) && !self.canceled_chunk_loads.contains(&pos)
+    }
+
+    /// Sets the chunks players are in, so missing
+    /// chunks closest to them are generated first.
+    pub fn set_generation_focus(&mut self, players: Vec<ChunkPosition>) {
+        self.chunk_worker.set_generation_focus(players);
+    }
+
+    /// Returns statistics of chunk generation, such
+    /// as the number of chunks waiting to be generated.
+    pub fn generation_stats(&self) -> GenerationStats {
+        self.chunk_worker.generation_stats()
+    }
+
+    /// Returns statistics of chunks loaded by this world.
+    pub fn load_stats(&self) -> ChunkLoadStats {
+        self.load_stats
+    }
+
+    /// Starts generating and saving the chunks of `area`, so they
+    /// needn't be generated once players get there. Progress is
+    /// saved to the world directory, so pregeneration resumes after
+    /// a restart.
+    ///
+    /// Fails if a pregeneration is already running.
+    pub fn pregenerate(&mut self, area: PregenArea) -> anyhow::Result<()> {
+        if let Some(pregeneration) = &self.pregeneration {
+            bail!("already pregenerating {:?}", pregeneration.area());
+        }
+        let pregeneration = Pregeneration::new(area, self.world_dir.join(PROGRESS_FILE));
+        pregeneration.save()?;
+        log::info!(
+            "Pregenerating {} chunks around {:?}",
+            pregeneration.progress().total,
+            area.center
+        );
+        self.pregeneration = Some(pregeneration);
+        Ok(())
+    }
+
+    /// Cancels the running pregeneration. Returns `false`
+    /// if there was none.
+    pub fn cancel_pregeneration(&mut self) -> anyhow::Result<bool> {
+        match self.pregeneration.take() {
+            Some(pregeneration) => {
+                pregeneration.remove_progress_file()?;
+                log::info!("Cancelled pregeneration at {}", pregeneration.progress());
+                Ok(true)
+            }
+            None => Ok(false),
+        }
+    }
+
+    /// Returns the progress of the running pregeneration, if any.
+    pub fn pregeneration_progress(&self) -> Option<PregenProgress> {
+        self.pregeneration
+            .as_ref()
+            .map(|pregeneration| pregeneration.progress())
+    }
+
+    /// Queues chunks of the running pregeneration to be loaded
+    /// until `deadline`, and reports its progress. Chunks are
+    /// only queued while the chunk generation queue is short.
+    pub fn update_pregeneration(&mut self, deadline: Instant) -> anyhow::Result<()> {
+        let pregeneration = match &mut self.pregeneration {
+            Some(pregeneration) => pregeneration,
+            None => return Ok(()),
+        };
+
+        while pregeneration.num_in_flight() < MAX_PREGEN_IN_FLIGHT
+            && self.chunk_worker.generation_stats().queued < MAX_PREGEN_GENERATION_QUEUE
+            && Instant::now() < deadline
+        {
+            let pos = match pregeneration.next_chunk() {
+                Some(pos) => pos,
+                None => break,
+            };
+            if self.chunk_map.0.contains_key(&pos) || self.cache.contains(&pos) {
+                // Loaded chunks are saved when unloaded.
+                pregeneration.complete(pos);
+            } else if !self.loading_chunks.contains_key(&pos) {
+                self.chunk_worker.queue_load(LoadRequest { pos });
+            }
+        }
+
+        if pregeneration.is_finished() {
+            pregeneration.remove_progress_file()?;
+            log::info!("Finished pregeneration: {}", pregeneration.progress());
+            self.pregeneration = None;
+        } else if pregeneration.report_due() {
+            pregeneration.save()?;
+            log::info!("Pregenerating: {}", pregeneration.progress());
+        }
+        Ok(())
     }
 
     /// Sets the block at the given position.
@@ -171,6 +391,83 @@ impl World {
     }
 }
 
+/// The worlds of a [`Game`](crate::Game), such as
+/// the overworld, the nether and the end.
+///
+/// The first world added is the main world.
+pub struct Worlds {
+    worlds: Vec<World>,
+}
+
+impl Default for Worlds {
+    fn default() -> Self {
+        Self::new(World::new())
+    }
+}
+
+impl Worlds {
+    /// Creates a set of worlds containing only the main world.
+    pub fn new(main: World) -> Self {
+        let mut worlds = Self { worlds: Vec::new() };
+        worlds.add(main);
+        worlds
+    }
+
+    /// Adds a world, returning its ID.
+    pub fn add(&mut self, mut world: World) -> WorldId {
+        let id = WorldId(self.worlds.len() as u32);
+        world.id = id;
+        self.worlds.push(world);
+        id
+    }
+
+    pub fn get(&self, id: WorldId) -> Option<&World> {
+        self.worlds.get(id.0 as usize)
+    }
+
+    pub fn get_mut(&mut self, id: WorldId) -> Option<&mut World> {
+        self.worlds.get_mut(id.0 as usize)
+    }
+
+    /// Returns the main world, in which players spawn.
+    pub fn main(&self) -> &World {
+        &self[WorldId::MAIN]
+    }
+
+    pub fn main_mut(&mut self) -> &mut World {
+        &mut self[WorldId::MAIN]
+    }
+
+    /// Finds the world of the given dimension.
+    pub fn by_dimension(&self, dimension: Dimension) -> Option<WorldId> {
+        self.iter()
+            .find(|world| world.dimension() == dimension)
+            .map(World::id)
+    }
+
+    pub fn iter(&self) -> impl Iterator<Item = &World> {
+        self.worlds.iter()
+    }
+
+    pub fn iter_mut(&mut self) -> impl Iterator<Item = &mut World> {
+        self.worlds.iter_mut()
+    }
+}
+
+impl Index<WorldId> for Worlds {
+    type Output = World;
+
+    fn index(&self, id: WorldId) -> &World {
+        self.get(id).expect("no world with the given ID")
+    }
+}
+
+impl IndexMut<WorldId> for Worlds {
+    fn index_mut(&mut self, id: WorldId) -> &mut World {
+        self.get_mut(id).expect("no world with the given ID")
+    }
+}
+
 pub type ChunkMapInner = AHashMap<ChunkPosition, ChunkHandle>;
 
 /// This struct stores all the chunks on the server,
@@ -227,6 +524,15 @@ impl ChunkMap {
             .is_some()
     }
 
+    /// Returns the number of loaded chunks.
+    pub fn len(&self) -> usize {
+        self.0.len()
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.0.is_empty()
+    }
+
     /// Returns an iterator over chunks.
     pub fn iter_chunks(&self) -> impl IntoIterator<Item = &ChunkHandle> {
         self.0.values()
@@ -262,7 +568,11 @@ fn chunk_relative_pos(block_pos: BlockPosition) -> (usize, usize, usize) {
 
 #[cfg(test)]
 mod tests {
-    use std::convert::TryInto;
+    use std::{convert::TryInto, fs};
+
+    use worldgen::VoidWorldGenerator;
+
+    use crate::chunk::pregen::PregenShape;
 
     use super::*;
 
@@ -280,4 +590,67 @@ mod tests {
             .block_at(BlockPosition::new(0, 0, 0).try_into().unwrap())
             .is_some());
     }
+
+    #[test]
+    fn worlds_by_dimension() {
+        let mut worlds = Worlds::default();
+        let nether = worlds.add(World::new().with_dimension(Dimension::TheNether));
+
+        assert_eq!(worlds.main().id(), WorldId::MAIN);
+        assert_eq!(worlds[nether].id(), nether);
+        assert_eq!(
+            worlds.by_dimension(Dimension::Overworld),
+            Some(WorldId::MAIN)
+        );
+        assert_eq!(worlds.by_dimension(Dimension::TheNether), Some(nether));
+        assert_eq!(worlds.by_dimension(Dimension::TheEnd), None);
+    }
+
+    #[test]
+    fn pregenerate_area() {
+        let dir = std::env::temp_dir().join(format!("feather-pregen-world-{}", std::process::id()));
+        let _ = fs::remove_dir_all(&dir);
+        let mut world = World::with_gen_and_path(Arc::new(VoidWorldGenerator), dir.clone(), 1);
+        let mut ecs = Ecs::new();
+
+        let area = PregenArea {
+            center: ChunkPosition::new(0, 0),
+            radius: 1,
+            shape: PregenShape::Square,
+        };
+        world.pregenerate(area).unwrap();
+        assert!(dir.join(PROGRESS_FILE).exists());
+        assert_eq!(world.pregeneration_progress().unwrap().total, 9);
+
+        let start = Instant::now();
+        while world.pregeneration_progress().is_some() {
+            assert!(start.elapsed() < Duration::from_secs(30));
+            world
+                .update_pregeneration(Instant::now() + Duration::from_millis(10))
+                .unwrap();
+            world.load_chunks(&mut ecs).unwrap();
+            std::thread::sleep(Duration::from_millis(1));
+        }
+        assert!(!dir.join(PROGRESS_FILE).exists());
+        // Pregenerated chunks are saved without being loaded.
+        assert!(area.chunks().all(|pos| !world.is_chunk_loaded(pos)));
+
+        // The worker handles requests in order, so
+        // the chunks were saved before they are loaded.
+        for pos in area.chunks() {
+            world.chunk_worker.queue_load(LoadRequest { pos });
+            let loaded = loop {
+                if let Some(loaded) = world.chunk_worker.poll_loaded_chunk().unwrap() {
+                    break loaded;
+                }
+                assert!(start.elapsed() < Duration::from_secs(30));
+                std::thread::sleep(Duration::from_millis(1));
+            };
+            assert_eq!(loaded.pos, pos);
+            assert!(!loaded.generated);
+        }
+
+        drop(world);
+        fs::remove_dir_all(&dir).unwrap();
+    }
 }
@@ -16,6 +16,10 @@ pub use vanilla::download_vanilla_assets;
 mod id;
 pub use id::NamespacedId;
 
+pub mod structure;
+pub mod worldgen;
+pub use worldgen::WorldgenData;
+
 /// The default namespace for resource locations (NamespacedIds).
 pub const DEFAULT_NAMESPACE: &str = "minecraft";
 
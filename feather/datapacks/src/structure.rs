@@ -0,0 +1,166 @@
+//! Structure templates, saved by structure blocks in the `structures`
+//! directory of data packs, and the template pools of jigsaw structures.
+
+use crate::{worldgen::BlockState, NamespacedId};
+use serde::Deserialize;
+use serde_json::Value;
+
+/// A structure template, read from a gzipped NBT file.
+#[derive(Clone, Debug, Deserialize)]
+pub struct StructureTemplate {
+    /// The size of the template along each axis.
+    pub size: Vec<i32>,
+    #[serde(default)]
+    palette: Vec<BlockState>,
+    /// Alternative palettes, one of which is picked
+    /// when placing the template. Used instead of `palette`
+    /// by some templates, such as shipwrecks.
+    #[serde(default)]
+    palettes: Vec<Vec<BlockState>>,
+    pub blocks: Vec<TemplateBlock>,
+}
+
+impl StructureTemplate {
+    /// Returns the block states the blocks of the template
+    /// index into, using the first palette if there are several.
+    pub fn palette(&self) -> &[BlockState] {
+        if self.palette.is_empty() {
+            self.palettes.first().map_or(&[], Vec::as_slice)
+        } else {
+            &self.palette
+        }
+    }
+}
+
+/// A block in a structure template.
+#[derive(Clone, Debug, Deserialize)]
+pub struct TemplateBlock {
+    /// The position relative to the template origin.
+    pub pos: Vec<i32>,
+    /// The index of the block state in the palette.
+    pub state: i32,
+    /// Block entity data. Only the fields of jigsaw blocks are read.
+    #[serde(default)]
+    pub nbt: Option<TemplateBlockNbt>,
+}
+
+/// The block entity data of a jigsaw block.
+#[derive(Clone, Debug, Default, Deserialize)]
+pub struct TemplateBlockNbt {
+    /// The name other jigsaws target to attach to this one.
+    pub name: Option<String>,
+    /// The template pool of pieces attached to this jigsaw.
+    pub pool: Option<String>,
+    /// The name of the jigsaw an attached piece connects with.
+    pub target: Option<String>,
+    /// The block which replaces the jigsaw once placed.
+    pub final_state: Option<String>,
+    /// Either `rollable` or `aligned`.
+    pub joint: Option<String>,
+}
+
+/// A pool of weighted pieces attached to jigsaw blocks.
+#[derive(Clone, Debug, Deserialize)]
+pub struct TemplatePool {
+    /// The pool used when no element of this pool fits.
+    pub fallback: NamespacedId,
+    pub elements: Vec<WeightedPoolElement>,
+}
+
+#[derive(Clone, Debug, Deserialize)]
+pub struct WeightedPoolElement {
+    pub weight: u32,
+    pub element: PoolElement,
+}
+
+/// An element of a template pool.
+#[derive(Clone, Debug, Deserialize)]
+#[serde(tag = "element_type")]
+pub enum PoolElement {
+    #[serde(rename = "minecraft:single_pool_element")]
+    Single {
+        location: NamespacedId,
+        projection: Projection,
+    },
+    /// Like `Single`, but the air blocks of the template are
+    /// not placed, so existing blocks are kept.
+    #[serde(rename = "minecraft:legacy_single_pool_element")]
+    LegacySingle {
+        location: NamespacedId,
+        projection: Projection,
+    },
+    /// Several elements placed at the same position.
+    #[serde(rename = "minecraft:list_pool_element")]
+    List {
+        elements: Vec<PoolElement>,
+        projection: Projection,
+    },
+    /// A configured feature, kept as raw JSON.
+    #[serde(rename = "minecraft:feature_pool_element")]
+    Feature {
+        feature: Value,
+        projection: Projection,
+    },
+    #[serde(rename = "minecraft:empty_pool_element")]
+    Empty,
+}
+
+/// How a piece is placed vertically.
+#[derive(Copy, Clone, Debug, PartialEq, Eq, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum Projection {
+    /// The piece is placed as is.
+    Rigid,
+    /// Each column of the piece follows the terrain surface.
+    TerrainMatching,
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::str::FromStr;
+
+    #[test]
+    fn parse_template_pool() {
+        let pool: TemplatePool = serde_json::from_str(
+            r#"{
+                "name": "minecraft:village/plains/town_centers",
+                "fallback": "minecraft:empty",
+                "elements": [
+                    {
+                        "weight": 50,
+                        "element": {
+                            "location": "minecraft:village/plains/town_centers/plains_fountain_01",
+                            "processors": "minecraft:mossify_20_percent",
+                            "projection": "rigid",
+                            "element_type": "minecraft:legacy_single_pool_element"
+                        }
+                    },
+                    {
+                        "weight": 2,
+                        "element": { "element_type": "minecraft:empty_pool_element" }
+                    }
+                ]
+            }"#,
+        )
+        .unwrap();
+
+        assert_eq!(pool.fallback, NamespacedId::from_str("empty").unwrap());
+        assert_eq!(pool.elements.len(), 2);
+        assert_eq!(pool.elements[0].weight, 50);
+        match &pool.elements[0].element {
+            PoolElement::LegacySingle {
+                location,
+                projection,
+            } => {
+                assert_eq!(
+                    location.name(),
+                    "village/plains/town_centers/plains_fountain_01"
+                );
+                assert_eq!(*projection, Projection::Rigid);
+            }
+            element => panic!("unexpected element {:?}", element),
+        }
+        assert!(matches!(pool.elements[1].element, PoolElement::Empty));
+    }
+}
@@ -0,0 +1,410 @@
+//! World generation data: noise settings, biomes, configured
+//! features and template pools, loaded from the `worldgen` directory
+//! of data packs, and structure templates.
+
+use crate::{
+    structure::{StructureTemplate, TemplatePool},
+    NamespacedId,
+};
+use ahash::AHashMap;
+use anyhow::Context;
+use serde::{de::DeserializeOwned, Deserialize};
+use serde_json::Value;
+use std::{collections::BTreeMap, fs, fs::File, io::BufReader, path::Path, str::FromStr};
+
+/// World generation data from all data packs in a directory.
+///
+/// When several packs define the same ID, the
+/// pack whose directory name sorts last wins.
+#[derive(Debug, Default)]
+pub struct WorldgenData {
+    pub noise_settings: AHashMap<NamespacedId, NoiseSettings>,
+    pub biomes: AHashMap<NamespacedId, Biome>,
+    pub configured_features: AHashMap<NamespacedId, ConfiguredFeature>,
+    pub template_pools: AHashMap<NamespacedId, TemplatePool>,
+    /// Structure templates from the `structures` directory.
+    pub structures: AHashMap<NamespacedId, StructureTemplate>,
+}
+
+impl WorldgenData {
+    /// Loads the world generation data of each data pack
+    /// in `datapacks`. Returns empty data if the directory
+    /// does not exist.
+    pub fn load(datapacks: &Path) -> anyhow::Result<Self> {
+        let mut data = Self::default();
+        if !datapacks.is_dir() {
+            return Ok(data);
+        }
+
+        let mut packs = fs::read_dir(datapacks)?
+            .map(|entry| entry.map(|entry| entry.path()))
+            .collect::<Result<Vec<_>, _>>()?;
+        packs.sort();
+        for pack in packs {
+            let namespaces = pack.join("data");
+            if !namespaces.is_dir() {
+                continue;
+            }
+            for namespace in fs::read_dir(&namespaces)? {
+                let namespace = namespace?;
+                let namespace_name = namespace.file_name().to_string_lossy().into_owned();
+                let worldgen = namespace.path().join("worldgen");
+                load_directory(
+                    &worldgen.join("noise_settings"),
+                    &namespace_name,
+                    "json",
+                    &mut data.noise_settings,
+                    load_json,
+                )?;
+                load_directory(
+                    &worldgen.join("biome"),
+                    &namespace_name,
+                    "json",
+                    &mut data.biomes,
+                    load_json,
+                )?;
+                load_directory(
+                    &worldgen.join("configured_feature"),
+                    &namespace_name,
+                    "json",
+                    &mut data.configured_features,
+                    load_json,
+                )?;
+                load_directory(
+                    &worldgen.join("template_pool"),
+                    &namespace_name,
+                    "json",
+                    &mut data.template_pools,
+                    load_json,
+                )?;
+                load_directory(
+                    &namespace.path().join("structures"),
+                    &namespace_name,
+                    "nbt",
+                    &mut data.structures,
+                    load_nbt,
+                )?;
+            }
+        }
+
+        log::debug!(
+            "Loaded {} noise settings, {} biomes, {} configured features, {} template pools and {} structures from data packs",
+            data.noise_settings.len(),
+            data.biomes.len(),
+            data.configured_features.len(),
+            data.template_pools.len(),
+            data.structures.len()
+        );
+        Ok(data)
+    }
+
+    /// Returns every ore feature, following references to other
+    /// configured features, keyed by the ID of its configured feature.
+    pub fn ore_features(&self) -> AHashMap<NamespacedId, OreFeature> {
+        self.configured_features
+            .iter()
+            .filter_map(|(id, feature)| Some((id.clone(), feature.ore(self)?)))
+            .collect()
+    }
+}
+
+fn load_json<T: DeserializeOwned>(path: &Path) -> anyhow::Result<T> {
+    Ok(serde_json::from_str(&fs::read_to_string(path)?)?)
+}
+
+fn load_nbt<T: DeserializeOwned>(path: &Path) -> anyhow::Result<T> {
+    Ok(nbt::from_gzip_reader(BufReader::new(File::open(path)?))?)
+}
+
+/// Loads each file in `dir` and its subdirectories with the
+/// given extension into `map` using `load`, keyed by its
+/// path relative to `dir`.
+fn load_directory<T>(
+    dir: &Path,
+    namespace: &str,
+    extension: &str,
+    map: &mut AHashMap<NamespacedId, T>,
+    load: fn(&Path) -> anyhow::Result<T>,
+) -> anyhow::Result<()> {
+    if !dir.is_dir() {
+        return Ok(());
+    }
+    let mut pending = vec![dir.to_path_buf()];
+    while let Some(current) = pending.pop() {
+        for entry in fs::read_dir(&current)? {
+            let path = entry?.path();
+            if path.is_dir() {
+                pending.push(path);
+                continue;
+            }
+            if path.extension().map_or(true, |found| found != extension) {
+                continue;
+            }
+
+            let name = path
+                .strip_prefix(dir)?
+                .with_extension("")
+                .to_string_lossy()
+                .replace('\\', "/");
+            let id = NamespacedId::from_str(&format!("{}:{}", namespace, name))?;
+            let value =
+                load(&path).with_context(|| format!("invalid worldgen file {}", path.display()))?;
+            map.insert(id, value);
+        }
+    }
+    Ok(())
+}
+
+/// A block state as written in data packs.
+#[derive(Clone, Debug, Deserialize, PartialEq)]
+pub struct BlockState {
+    #[serde(rename = "Name")]
+    pub name: NamespacedId,
+    #[serde(rename = "Properties", default)]
+    pub properties: BTreeMap<String, String>,
+}
+
+/// The shape of a dimension's terrain.
+#[derive(Clone, Debug, Deserialize)]
+pub struct NoiseSettings {
+    pub sea_level: i32,
+    pub default_block: BlockState,
+    pub default_fluid: BlockState,
+    pub bedrock_floor_position: i32,
+    pub bedrock_roof_position: i32,
+    pub noise: NoiseConfig,
+}
+
+#[derive(Clone, Debug, Deserialize)]
+pub struct NoiseConfig {
+    pub height: i32,
+    pub sampling: NoiseSampling,
+    pub top_slide: Slide,
+    pub bottom_slide: Slide,
+    pub size_horizontal: i32,
+    pub size_vertical: i32,
+    pub density_factor: f64,
+    pub density_offset: f64,
+    #[serde(default)]
+    pub random_density_offset: bool,
+    #[serde(default)]
+    pub amplified: bool,
+}
+
+#[derive(Copy, Clone, Debug, Deserialize)]
+pub struct NoiseSampling {
+    pub xz_scale: f64,
+    pub y_scale: f64,
+    pub xz_factor: f64,
+    pub y_factor: f64,
+}
+
+#[derive(Copy, Clone, Debug, Deserialize)]
+pub struct Slide {
+    pub target: i32,
+    pub size: i32,
+    pub offset: i32,
+}
+
+/// The properties of a biome relevant to terrain generation.
+#[derive(Clone, Debug, Deserialize)]
+pub struct Biome {
+    pub depth: f32,
+    pub scale: f32,
+    pub temperature: f32,
+    pub downfall: f32,
+    /// The configured features placed in each generation step.
+    /// Entries are either IDs or inline features.
+    #[serde(default)]
+    pub features: Vec<Vec<Value>>,
+}
+
+impl Biome {
+    /// Returns the IDs of the configured features the biome places.
+    pub fn feature_ids(&self) -> impl Iterator<Item = NamespacedId> + '_ {
+        self.features
+            .iter()
+            .flatten()
+            .filter_map(|feature| NamespacedId::from_str(feature.as_str()?).ok())
+    }
+}
+
+/// A configured feature. Only ores, possibly wrapped
+/// in decorators, are understood; the configuration of other
+/// features is kept as raw JSON.
+#[derive(Clone, Debug, Deserialize)]
+pub struct ConfiguredFeature {
+    #[serde(rename = "type")]
+    pub kind: NamespacedId,
+    #[serde(default)]
+    pub config: Value,
+}
+
+/// A vein of ore placed by a `minecraft:ore` feature,
+/// along with the decorators which place it.
+#[derive(Clone, Debug, PartialEq)]
+pub struct OreFeature {
+    pub state: BlockState,
+    pub size: u32,
+    /// Number of veins per chunk.
+    pub count: u32,
+    /// A random number of veins, up to this value,
+    /// is added to `count`.
+    pub extra_count: u32,
+    pub height: OreHeight,
+}
+
+/// Heights at which an ore feature is placed.
+#[derive(Copy, Clone, Debug, PartialEq, Eq)]
+pub enum OreHeight {
+    /// Heights in `min..max`, from a `minecraft:range` decorator.
+    Range { min: i32, max: i32 },
+    /// Heights within `spread` of `baseline`, from
+    /// a `minecraft:depth_average` decorator.
+    DepthAverage { baseline: i32, spread: i32 },
+}
+
+/// Maximum depth of nested decorated features, which
+/// guards against features which reference each other.
+const MAX_FEATURE_DEPTH: usize = 16;
+
+impl ConfiguredFeature {
+    /// Returns the ore this feature places, if it is an ore
+    /// feature wrapped in any number of decorators.
+    pub fn ore(&self, data: &WorldgenData) -> Option<OreFeature> {
+        let mut ore = OreFeature {
+            state: BlockState {
+                name: NamespacedId::from_str("air").ok()?,
+                properties: BTreeMap::new(),
+            },
+            size: 0,
+            count: 1,
+            extra_count: 0,
+            height: OreHeight::Range { min: 0, max: 256 },
+        };
+        self.apply_to_ore(data, &mut ore, 0)?;
+        Some(ore)
+    }
+
+    fn apply_to_ore(&self, data: &WorldgenData, ore: &mut OreFeature, depth: usize) -> Option<()> {
+        if depth > MAX_FEATURE_DEPTH {
+            return None;
+        }
+        match (self.kind.namespace(), self.kind.name()) {
+            ("minecraft", "ore") => {
+                ore.state = BlockState::deserialize(self.config.get("state")?).ok()?;
+                ore.size = self.config.get("size")?.as_u64()? as u32;
+                Some(())
+            }
+            ("minecraft", "decorated") => {
+                let decorator = self.config.get("decorator")?;
+                apply_decorator(decorator, ore);
+                match self.config.get("feature")? {
+                    Value::String(id) => data
+                        .configured_features
+                        .get(&NamespacedId::from_str(id).ok()?)?
+                        .apply_to_ore(data, ore, depth + 1),
+                    inline => ConfiguredFeature::deserialize(inline).ok()?.apply_to_ore(
+                        data,
+                        ore,
+                        depth + 1,
+                    ),
+                }
+            }
+            _ => None,
+        }
+    }
+}
+
+/// Applies the count and height decorators understood for ores.
+fn apply_decorator(decorator: &Value, ore: &mut OreFeature) {
+    let kind = decorator.get("type").and_then(Value::as_str).unwrap_or("");
+    let config = decorator.get("config").unwrap_or(&Value::Null);
+    let int = |key: &str| config.get(key).and_then(Value::as_i64).unwrap_or(0) as i32;
+    match kind {
+        "minecraft:count" => match config.get("count") {
+            Some(Value::Object(count)) => {
+                ore.count = count.get("base").and_then(Value::as_u64).unwrap_or(0) as u32;
+                ore.extra_count = count.get("spread").and_then(Value::as_u64).unwrap_or(0) as u32;
+            }
+            Some(count) => ore.count = count.as_u64().unwrap_or(0) as u32,
+            None => {}
+        },
+        "minecraft:range" => {
+            ore.height = OreHeight::Range {
+                min: int("bottom_offset"),
+                max: int("maximum") - int("top_offset"),
+            }
+        }
+        "minecraft:depth_average" => {
+            ore.height = OreHeight::DepthAverage {
+                baseline: int("baseline"),
+                spread: int("spread"),
+            }
+        }
+        _ => {}
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn parse_decorated_ore() {
+        let feature: ConfiguredFeature = serde_json::from_str(
+            r#"{
+                "type": "minecraft:decorated",
+                "config": {
+                    "decorator": { "type": "minecraft:count", "config": { "count": 20 } },
+                    "feature": {
+                        "type": "minecraft:decorated",
+                        "config": {
+                            "decorator": { "type": "minecraft:square", "config": {} },
+                            "feature": {
+                                "type": "minecraft:decorated",
+                                "config": {
+                                    "decorator": {
+                                        "type": "minecraft:range",
+                                        "config": { "bottom_offset": 0, "top_offset": 0, "maximum": 128 }
+                                    },
+                                    "feature": "custom:coal_vein"
+                                }
+                            }
+                        }
+                    }
+                }
+            }"#,
+        )
+        .unwrap();
+        let vein: ConfiguredFeature = serde_json::from_str(
+            r#"{
+                "type": "minecraft:ore",
+                "config": {
+                    "target": { "predicate_type": "minecraft:tag_match", "tag": "minecraft:base_stone_overworld" },
+                    "state": { "Name": "minecraft:coal_ore" },
+                    "size": 17
+                }
+            }"#,
+        )
+        .unwrap();
+
+        let mut data = WorldgenData::default();
+        data.configured_features
+            .insert(NamespacedId::from_str("custom:coal_vein").unwrap(), vein);
+
+        let ore = feature.ore(&data).unwrap();
+        assert_eq!(ore.state.name, NamespacedId::from_str("coal_ore").unwrap());
+        assert_eq!(ore.size, 17);
+        assert_eq!(ore.count, 20);
+        assert_eq!(ore.extra_count, 0);
+        assert_eq!(ore.height, OreHeight::Range { min: 0, max: 128 });
+    }
+
+    #[test]
+    fn non_ore_features_are_ignored() {
+        let feature: ConfiguredFeature =
+            serde_json::from_str(r#"{ "type": "minecraft:tree", "config": {} }"#).unwrap();
+        assert_eq!(feature.ore(&WorldgenData::default()), None);
+    }
+}
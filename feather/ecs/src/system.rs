@@ -1,6 +1,11 @@
 //! System execution, using a simple "systems as functions" model.
 
-use std::{any::type_name, marker::PhantomData, sync::Arc};
+use std::{
+    any::type_name,
+    marker::PhantomData,
+    sync::Arc,
+    time::{Duration, Instant},
+};
 
 use crate::{Ecs, Resources};
 
@@ -25,6 +30,8 @@ type SystemFn<Input> = Box<dyn FnMut(&mut Input) -> SysResult>;
 struct System<Input> {
     function: SystemFn<Input>,
     name: String,
+    /// Total time spent running the system.
+    time: Duration,
 }
 
 impl<Input> System<Input> {
@@ -32,6 +39,7 @@ impl<Input> System<Input> {
         Self {
             function: Box::new(f),
             name: type_name::<F>().to_owned(),
+            time: Duration::default(),
         }
     }
 }
@@ -141,7 +149,9 @@ impl<Input> SystemExecutor<Input> {
                 input.ecs_mut().remove_old_events();
             }
 
+            let start = Instant::now();
             let result = (system.function)(input);
+            system.time += start.elapsed();
             if let Err(e) = result {
                 log::error!(
                     "System {} returned an error; this is a bug: {:?}",
@@ -158,6 +168,14 @@ impl<Input> SystemExecutor<Input> {
     pub fn system_names(&self) -> impl Iterator<Item = &'_ str> + '_ {
         self.systems.iter().map(|system| system.name.as_str())
     }
+
+    /// Gets an iterator over system names and the
+    /// total time spent running each system.
+    pub fn system_timings(&self) -> impl Iterator<Item = (&'_ str, Duration)> + '_ {
+        self.systems
+            .iter()
+            .map(|system| (system.name.as_str(), system.time))
+    }
 }
 
 /// Builder for a group. Created with [`SystemExecutor::group`].
@@ -13,6 +13,7 @@ mod component;
 mod entity;
 mod entity_builder;
 mod event;
+mod metrics;
 mod plugin_message;
 mod query;
 mod system;
@@ -51,6 +52,7 @@ use component::*;
 use entity::*;
 use entity_builder::*;
 use event::*;
+use metrics::*;
 use plugin_message::*;
 use query::*;
 use system::*;
@@ -73,4 +75,5 @@ host_calls! {
     "block_set" => block_set,
     "block_fill_chunk_section" => block_fill_chunk_section,
     "plugin_message_send" => plugin_message_send,
+    "metrics_set_gauge" => metrics_set_gauge,
 }
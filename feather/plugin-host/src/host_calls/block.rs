@@ -1,17 +1,21 @@
 use std::convert::TryInto;
 
 use feather_base::{BlockId, BlockPosition, ChunkPosition};
+use feather_common::WorldId;
 use feather_plugin_host_macros::host_function;
 use quill_common::block::BlockGetResult;
 
 use crate::context::PluginContext;
 
+// The plugin API has no notion of worlds yet,
+// so blocks are always accessed in the main world.
+
 /// NB: `u32` has the same layout as `BlockGetResult`.
 #[host_function]
 pub fn block_get(cx: &PluginContext, x: i32, y: i32, z: i32) -> anyhow::Result<u32> {
     let pos = BlockPosition::new(x, y, z).try_into()?;
 
-    let block = cx.game_mut().block(pos);
+    let block = cx.game_mut().block(WorldId::MAIN, pos);
     let result = BlockGetResult::new(block.map(BlockId::vanilla_id));
     Ok(result.to_u32())
 }
@@ -21,7 +25,7 @@ pub fn block_set(cx: &PluginContext, x: i32, y: i32, z: i32, block_id: u16) -> a
     let pos = BlockPosition::new(x, y, z).try_into()?;
     let block = BlockId::from_vanilla_id(block_id);
 
-    let was_successful = cx.game_mut().set_block(pos, block);
+    let was_successful = cx.game_mut().set_block(WorldId::MAIN, pos, block);
     Ok(was_successful as u32)
 }
 
@@ -35,8 +39,8 @@ pub fn block_fill_chunk_section(
 ) -> anyhow::Result<u32> {
     let chunk_pos = ChunkPosition::new(chunk_x, chunk_z);
     let block = BlockId::from_vanilla_id(block_id);
-    let was_successful = cx
-        .game_mut()
-        .fill_chunk_section(chunk_pos, section_y as usize, block);
+    let was_successful =
+        cx.game_mut()
+            .fill_chunk_section(WorldId::MAIN, chunk_pos, section_y as usize, block);
     Ok(was_successful as u32)
 }
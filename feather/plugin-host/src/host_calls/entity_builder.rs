@@ -1,7 +1,7 @@
 use anyhow::{bail, Context};
 use feather_base::Position;
 use feather_plugin_host_macros::host_function;
-use quill_common::{component::ComponentVisitor, HostComponent};
+use quill_common::{component::ComponentVisitor, components::Persistent, HostComponent};
 
 use crate::context::{PluginContext, PluginPtr};
 
@@ -78,12 +78,14 @@ pub fn entity_builder_add_component(
 
 #[host_function]
 pub fn entity_builder_finish(cx: &PluginContext, builder: u32) -> anyhow::Result<u64> {
-    let builder = cx
+    let mut builder = cx
         .entity_builders
         .borrow_mut()
         .remove(builder as usize)
         .context("invalid entity builder")?;
 
+    // The plugin decides when its entities go away.
+    builder.add(Persistent);
     let entity = cx.game_mut().spawn_entity(builder);
     Ok(entity.to_bits())
 }
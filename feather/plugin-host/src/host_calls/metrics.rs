@@ -0,0 +1,26 @@
+use feather_common::metrics;
+use feather_plugin_host_macros::host_function;
+
+use crate::context::{PluginContext, PluginPtr};
+
+#[host_function]
+pub fn metrics_set_gauge(
+    cx: &PluginContext,
+    name_ptr: PluginPtr<u8>,
+    name_len: u32,
+    help_ptr: PluginPtr<u8>,
+    help_len: u32,
+    value: f64,
+) -> anyhow::Result<()> {
+    let name = cx.read_string(name_ptr, name_len)?;
+    let help = cx.read_string(help_ptr, help_len)?;
+
+    // The `feather_` prefix is reserved for the server's own metrics.
+    if !metrics::is_valid_name(&name) || name.starts_with("feather_") {
+        log::warn!("Ignoring plugin gauge with invalid name {:?}", name);
+        return Ok(());
+    }
+
+    cx.game_mut().metrics.set_gauge(&name, &help, value);
+    Ok(())
+}
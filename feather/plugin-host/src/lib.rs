@@ -130,6 +130,11 @@ impl PluginManager {
     pub fn plugin_mut(&mut self, id: PluginId) -> Option<&mut Plugin> {
         self.plugins.get_mut(id.0)
     }
+
+    /// Gets an iterator over all loaded plugins.
+    pub fn plugins(&self) -> impl Iterator<Item = &Plugin> + '_ {
+        self.plugins.iter().map(|(_, plugin)| plugin)
+    }
 }
 
 #[cfg(all(feature = "cranelift", not(feature = "llvm")))]
@@ -81,6 +81,11 @@ impl Plugin {
         Ok(())
     }
 
+    /// Gets the plugin's name, version and other metadata.
+    pub fn metadata(&self) -> &PluginMetadata {
+        &self.metadata
+    }
+
     /// Runs a plugin system.
     ///
     /// `data` must be the data pointer passed
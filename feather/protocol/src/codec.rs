@@ -10,6 +10,7 @@ use flate2::{
     Compression,
 };
 use std::io::{Cursor, Read};
+use thiserror::Error;
 
 type AesCfb8 = Cfb8<Aes128>;
 pub type CompressionThreshold = usize;
@@ -17,6 +18,15 @@ pub type CompressionThreshold = usize;
 /// An encryption key for use with AES-CFB8.
 pub type CryptKey = [u8; 16];
 
+/// Error when a received packet is longer than
+/// the codec's maximum packet length.
+#[derive(Debug, Error)]
+#[error("packet of {length} bytes exceeds the maximum length of {max} bytes")]
+pub struct PacketTooLong {
+    pub length: usize,
+    pub max: usize,
+}
+
 /// State to serialize and deserialize packets from a byte stream.
 #[derive(Default)]
 pub struct MinecraftCodec {
@@ -32,6 +42,11 @@ pub struct MinecraftCodec {
     staging_buf: Vec<u8>,
     /// Another auxilary buffer.
     compression_target: Vec<u8>,
+
+    /// The protocol version packets are encoded with.
+    version: ProtocolVersion,
+    /// If set, the maximum length of a received packet, before and after decompression.
+    max_packet_length: Option<usize>,
 }
 
 impl MinecraftCodec {
@@ -51,8 +66,29 @@ impl MinecraftCodec {
         self.compression = Some(threshold);
     }
 
-    /// Gets another `MinecraftCodec` with the same compression and encryption
-    /// parameters.
+    /// Gets the compression threshold, if compression is enabled.
+    pub fn compression_threshold(&self) -> Option<CompressionThreshold> {
+        self.compression
+    }
+
+    /// Sets the protocol version to encode and decode packets with.
+    pub fn set_version(&mut self, version: ProtocolVersion) {
+        self.version = version;
+    }
+
+    /// Gets the protocol version packets are encoded with.
+    pub fn version(&self) -> ProtocolVersion {
+        self.version
+    }
+
+    /// Sets the maximum length of received packets. Receiving
+    /// a longer packet results in a [`PacketTooLong`] error.
+    pub fn set_max_packet_length(&mut self, max: Option<usize>) {
+        self.max_packet_length = max;
+    }
+
+    /// Gets another `MinecraftCodec` with the same compression, encryption,
+    /// version and length parameters.
     pub fn clone_with_settings(&self) -> MinecraftCodec {
         MinecraftCodec {
             cryptor: self
@@ -63,12 +99,34 @@ impl MinecraftCodec {
             received_buf: BytesMut::new(),
             staging_buf: Vec::new(),
             compression_target: Vec::new(),
+            version: self.version,
+            max_packet_length: self.max_packet_length,
         }
     }
 
     /// Writes a packet into the provided writer.
     pub fn encode(&mut self, packet: &impl Writeable, output: &mut Vec<u8>) -> anyhow::Result<()> {
-        packet.write(&mut self.staging_buf, ProtocolVersion::V1_16_2)?;
+        let start = output.len();
+        self.encode_frame(packet, output)?;
+
+        if let Some(cryptor) = &mut self.cryptor {
+            cryptor.encrypt(&mut output[start..]);
+        }
+
+        Ok(())
+    }
+
+    /// Writes a packet into the provided writer without encrypting it.
+    ///
+    /// The result can be sent on any connection with the same version
+    /// and compression threshold using [`write_frames`](Self::write_frames),
+    /// so packets sent to many clients only need to be encoded once.
+    pub fn encode_frame(
+        &mut self,
+        packet: &impl Writeable,
+        output: &mut Vec<u8>,
+    ) -> anyhow::Result<()> {
+        packet.write(&mut self.staging_buf, self.version)?;
 
         if let Some(threshold) = self.compression {
             self.encode_compressed(output, threshold)?;
@@ -76,15 +134,22 @@ impl MinecraftCodec {
             self.encode_uncompressed(output)?;
         }
 
-        if let Some(cryptor) = &mut self.cryptor {
-            cryptor.encrypt(output);
-        }
-
         self.staging_buf.clear();
 
         Ok(())
     }
 
+    /// Writes packets encoded with [`encode_frame`](Self::encode_frame)
+    /// into the provided writer.
+    pub fn write_frames(&mut self, frames: &[u8], output: &mut Vec<u8>) {
+        let start = output.len();
+        output.extend_from_slice(frames);
+
+        if let Some(cryptor) = &mut self.cryptor {
+            cryptor.encrypt(&mut output[start..]);
+        }
+    }
+
     fn encode_compressed(
         &mut self,
         output: &mut Vec<u8>,
@@ -104,8 +169,8 @@ impl MinecraftCodec {
             .unwrap();
 
         let packet_length = data_length_bytes.position() as usize + data.len();
-        VarInt(packet_length as i32).write(output, ProtocolVersion::V1_16_2)?;
-        VarInt(data_length as i32).write(output, ProtocolVersion::V1_16_2)?;
+        VarInt(packet_length as i32).write(output, self.version)?;
+        VarInt(data_length as i32).write(output, self.version)?;
         output.extend_from_slice(data);
 
         self.compression_target.clear();
@@ -129,7 +194,7 @@ impl MinecraftCodec {
         // TODO: we should probably be able to determine the length without writing the packet,
         // which could remove an unnecessary copy.
         let length = self.staging_buf.len() as i32;
-        VarInt(length).write(output, ProtocolVersion::V1_16_2)?;
+        VarInt(length).write(output, self.version)?;
         output.extend_from_slice(&self.staging_buf);
 
         Ok(())
@@ -153,8 +218,9 @@ impl MinecraftCodec {
         T: Readable,
     {
         let mut cursor = Cursor::new(&self.received_buf[..]);
-        let packet = if let Ok(length) = VarInt::read(&mut cursor, ProtocolVersion::V1_16_2) {
+        let packet = if let Ok(length) = VarInt::read(&mut cursor, self.version) {
             let length_field_length = cursor.position() as usize;
+            self.check_length(length.0)?;
 
             if self.received_buf.len() - length_field_length >= length.0 as usize {
                 cursor = Cursor::new(
@@ -163,16 +229,19 @@ impl MinecraftCodec {
                 );
 
                 if self.compression.is_some() {
-                    let data_length = VarInt::read(&mut cursor, ProtocolVersion::V1_16_2)?;
+                    let data_length = VarInt::read(&mut cursor, self.version)?;
+                    self.check_length(data_length.0)?;
                     if data_length.0 != 0 {
+                        // Don't trust the data length when decompressing.
                         let mut decoder =
-                            ZlibDecoder::new(&cursor.get_ref()[cursor.position() as usize..]);
+                            ZlibDecoder::new(&cursor.get_ref()[cursor.position() as usize..])
+                                .take(data_length.0 as u64);
                         decoder.read_to_end(&mut self.compression_target)?;
                         cursor = Cursor::new(&self.compression_target);
                     }
                 }
 
-                let packet = T::read(&mut cursor, ProtocolVersion::V1_16_2)?;
+                let packet = T::read(&mut cursor, self.version)?;
 
                 let bytes_read = length.0 as usize + length_field_length;
                 self.received_buf = self.received_buf.split_off(bytes_read);
@@ -188,4 +257,63 @@ impl MinecraftCodec {
 
         Ok(packet)
     }
+
+    fn check_length(&self, length: i32) -> anyhow::Result<()> {
+        if length < 0 {
+            anyhow::bail!("negative packet length {}", length);
+        }
+        match self.max_packet_length {
+            Some(max) if length as usize > max => Err(PacketTooLong {
+                length: length as usize,
+                max,
+            }
+            .into()),
+            _ => Ok(()),
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::{packets::server::KeepAlive, ServerPlayPacket};
+
+    use super::*;
+
+    fn keep_alive(packet: Option<ServerPlayPacket>) -> i64 {
+        match packet {
+            Some(ServerPlayPacket::KeepAlive(packet)) => packet.id,
+            packet => panic!("expected KeepAlive, got {:?}", packet),
+        }
+    }
+
+    #[test]
+    fn shared_frames_are_encrypted_per_connection() {
+        let key = [7; 16];
+        let mut sender = MinecraftCodec::new();
+        sender.enable_compression(16);
+        sender.enable_encryption(key);
+        let mut receiver = sender.clone_with_settings();
+
+        let mut shared = MinecraftCodec::new();
+        shared.enable_compression(16);
+        let mut frame = Vec::new();
+        shared
+            .encode_frame(&ServerPlayPacket::from(KeepAlive { id: 2 }), &mut frame)
+            .unwrap();
+
+        // Both packets go out in a single batch.
+        let mut output = Vec::new();
+        sender
+            .encode(&ServerPlayPacket::from(KeepAlive { id: 1 }), &mut output)
+            .unwrap();
+        sender.write_frames(&frame, &mut output);
+
+        receiver.accept(&output);
+        assert_eq!(keep_alive(receiver.next_packet().unwrap()), 1);
+        assert_eq!(keep_alive(receiver.next_packet().unwrap()), 2);
+        assert!(receiver
+            .next_packet::<ServerPlayPacket>()
+            .unwrap()
+            .is_none());
+    }
 }
@@ -1,6 +1,6 @@
 //! Traits for reading/writing Minecraft-encoded values.
 
-use crate::{ProtocolVersion, Slot};
+use crate::{mapping, ProtocolVersion, Slot};
 use anyhow::{anyhow, bail, Context};
 use base::{
     anvil::entity::ItemNbt, metadata::MetaEntry, BlockId, BlockPosition, Direction, EntityMetadata,
@@ -548,7 +548,7 @@ impl Readable for Slot {
         let present = bool::read(buffer, version)?;
 
         if present {
-            let item_id = VarInt::read(buffer, version)?.0;
+            let item_id = mapping::item_from_wire(version, VarInt::read(buffer, version)?.0 as u32);
             let count = u8::read(buffer, version)? as u32;
 
             // Read NBT, but make sure to reset the buffer position if it's missing.
@@ -558,8 +558,8 @@ impl Readable for Slot {
                 buffer.set_position(position + 1); // account for TAG_End, which is 1 byte
             }
 
-            let item = Item::from_id(item_id.try_into()?)
-                .ok_or_else(|| anyhow!("unknown item ID {}", item_id))?;
+            let item =
+                Item::from_id(item_id).ok_or_else(|| anyhow!("unknown item ID {}", item_id))?;
 
             // Todo fix: Panics if count is zero
             Ok(Filled(
@@ -579,7 +579,8 @@ impl Writeable for Slot {
         self.is_filled().write(buffer, version)?;
 
         if let Filled(stack) = self {
-            VarInt(stack.item().id() as i32).write(buffer, version)?;
+            VarInt(mapping::item_to_wire(version, stack.item().id()) as i32)
+                .write(buffer, version)?;
             (stack.count() as u8).write(buffer, version)?;
 
             let tags: ItemNbt = stack.into();
@@ -594,6 +595,10 @@ impl Writeable for Slot {
     }
 }
 
+/// The metadata index 1.17 inserted for the ticks an entity was frozen
+/// in powder snow, shifting the following indices of every entity.
+const FROZEN_TICKS_INDEX: u8 = 7;
+
 impl Readable for EntityMetadata {
     fn read(buffer: &mut Cursor<&[u8]>, version: ProtocolVersion) -> anyhow::Result<Self>
     where
@@ -609,7 +614,14 @@ impl Readable for EntityMetadata {
             }
 
             let entry = read_meta_entry(buffer, version)?;
-            values.insert(index, entry);
+            match index {
+                // Ticks frozen in powder snow, which doesn't exist internally
+                FROZEN_TICKS_INDEX if version >= ProtocolVersion::V1_17_1 => continue,
+                index if index > FROZEN_TICKS_INDEX && version >= ProtocolVersion::V1_17_1 => {
+                    values.insert(index - 1, entry)
+                }
+                index => values.insert(index, entry),
+            };
         }
 
         Ok(EntityMetadata { values })
@@ -660,7 +672,7 @@ fn read_meta_entry(
             if id == 0 {
                 None
             } else {
-                Some(id)
+                Some(mapping::block_state_from_wire(version, id as u32)?.into())
             }
         }),
         14 => MetaEntry::Nbt(Nbt::read(buffer, version)?.0),
@@ -686,7 +698,11 @@ fn read_meta_entry(
 impl Writeable for EntityMetadata {
     fn write(&self, buffer: &mut Vec<u8>, version: ProtocolVersion) -> anyhow::Result<()> {
         for (index, entry) in self.iter() {
-            index.write(buffer, version)?;
+            if index >= FROZEN_TICKS_INDEX && version >= ProtocolVersion::V1_17_1 {
+                (index + 1).write(buffer, version)?;
+            } else {
+                index.write(buffer, version)?;
+            }
             VarInt(entry.id()).write(buffer, version)?;
             write_meta_entry(entry, buffer, version)?;
         }
@@ -745,7 +761,8 @@ fn write_meta_entry(
         }
         MetaEntry::OptBlockId(ox) => {
             if let Some(x) = ox {
-                VarInt(*x).write(buffer, version)?;
+                let id = mapping::block_state_to_wire(version, (*x).try_into()?);
+                VarInt(id as i32).write(buffer, version)?;
             } else {
                 VarInt(0).write(buffer, version)?; // No value implies air
             }
@@ -859,14 +876,15 @@ impl Readable for BlockId {
     {
         let id = VarInt::read(buffer, version)?.0;
 
-        let block = BlockId::from_vanilla_id(id.try_into()?);
+        let block = BlockId::from_vanilla_id(mapping::block_state_from_wire(version, id as u32)?);
         Ok(block)
     }
 }
 
 impl Writeable for BlockId {
     fn write(&self, buffer: &mut Vec<u8>, version: ProtocolVersion) -> anyhow::Result<()> {
-        VarInt(self.vanilla_id().into()).write(buffer, version)?;
+        let id = mapping::block_state_to_wire(version, self.vanilla_id());
+        VarInt(id as i32).write(buffer, version)?;
         Ok(())
     }
 }
@@ -2,6 +2,7 @@ use anyhow::anyhow;
 
 pub mod codec;
 pub mod io;
+pub mod mapping;
 pub mod packets;
 
 use crate::codec::CompressionThreshold;
@@ -20,9 +21,55 @@ pub use packets::{
 pub type Slot = InventorySlot;
 
 /// A protocol version.
-#[derive(Copy, Clone, Debug, PartialEq, Eq, Hash, PartialOrd, Ord)]
+#[derive(Copy, Clone, Debug, Default, PartialEq, Eq, Hash, PartialOrd, Ord)]
 pub enum ProtocolVersion {
+    #[default]
     V1_16_2,
+    V1_17_1,
+    V1_18_2,
+    V1_19,
+}
+
+impl ProtocolVersion {
+    /// The version packets are modeled after. Other
+    /// versions are translated by the [`mapping`] module.
+    pub const INTERNAL: ProtocolVersion = ProtocolVersion::V1_16_2;
+
+    /// All known versions, in ascending order.
+    pub const ALL: [ProtocolVersion; 4] = [
+        ProtocolVersion::V1_16_2,
+        ProtocolVersion::V1_17_1,
+        ProtocolVersion::V1_18_2,
+        ProtocolVersion::V1_19,
+    ];
+
+    /// Gets the protocol number sent in the handshake.
+    pub fn number(self) -> i32 {
+        match self {
+            ProtocolVersion::V1_16_2 => 754,
+            ProtocolVersion::V1_17_1 => 756,
+            ProtocolVersion::V1_18_2 => 758,
+            ProtocolVersion::V1_19 => 759,
+        }
+    }
+
+    /// Gets the version with the given protocol number.
+    pub fn from_number(number: i32) -> Option<Self> {
+        Self::ALL
+            .iter()
+            .copied()
+            .find(|version| version.number() == number)
+    }
+
+    /// Gets the name of the newest game version using this protocol.
+    pub fn name(self) -> &'static str {
+        match self {
+            ProtocolVersion::V1_16_2 => "1.16.5",
+            ProtocolVersion::V1_17_1 => "1.17.1",
+            ProtocolVersion::V1_18_2 => "1.18.2",
+            ProtocolVersion::V1_19 => "1.19",
+        }
+    }
 }
 
 /// A protocol state.
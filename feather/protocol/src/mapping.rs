@@ -0,0 +1,444 @@
+//! Translation between the internal protocol version and other versions.
+//!
+//! Packets are modeled after [`ProtocolVersion::INTERNAL`]. For other
+//! versions, play packet IDs, block state IDs and item IDs are translated
+//! with mappings loaded from mapping files. The files are generated from
+//! PrismarineJS' minecraft-data by running
+//! `python3 libcraft/generators/python/protocol_mappings.py` with the
+//! `minecraft-data` submodule checked out; the generator writes them to
+//! `feather/server/protocol-mappings`.
+//!
+//! Mappings only translate IDs. The play packets of 1.17 and later also
+//! changed layout; the `Readable` and `Writeable` impls of the packets the
+//! server sends and handles (`JoinGame`, `Respawn`, `ChunkData`,
+//! `UpdateLight`, the entity and window packets, ...) translate those
+//! layouts for 1.17.1 and 1.18.2. Clientbound packets without an
+//! equivalent in a version, such as `WindowConfirmation` from 1.17 on, are
+//! [skipped](has_clientbound_equivalent) by connections of that version.
+//!
+//! 1.19 isn't [supported](is_supported) even once its mappings are loaded:
+//! its clients sign chat messages, send commands in a packet of their own
+//! and expect a chat type registry, none of which is translated yet.
+//!
+//! Connections look up mappings in the [`Registry`] [installed](install)
+//! at startup. Lookups don't lock, and the internal version skips them.
+
+use std::{convert::TryInto, fs, path::Path};
+
+use anyhow::{anyhow, bail, Context};
+use once_cell::sync::OnceCell;
+use serde::Deserialize;
+
+use crate::{
+    ClientHandshakePacket, ClientLoginPacket, ClientPlayPacket, ClientStatusPacket,
+    ProtocolVersion, ServerLoginPacket, ServerPlayPacket, ServerStatusPacket,
+};
+
+/// The registry used by all connections.
+static REGISTRY: OnceCell<Registry> = OnceCell::new();
+
+/// The contents of a mapping file.
+#[derive(Debug, Clone, Deserialize)]
+pub struct VersionMappings {
+    /// Protocol number of the mapped version.
+    pub protocol: i32,
+    /// IDs of clientbound play packets, indexed by the internal ID.
+    /// `None` for packets which don't exist in the mapped version.
+    pub clientbound_play: Vec<Option<u32>>,
+    /// Internal IDs of serverbound play packets, indexed by the mapped ID.
+    /// `None` for packets which don't exist internally.
+    pub serverbound_play: Vec<Option<u32>>,
+    /// Block state IDs, indexed by the internal ID.
+    pub block_states_to_mapped: Vec<u32>,
+    /// Internal block state IDs, indexed by the mapped ID. States which
+    /// don't exist internally map to the closest state of the same block,
+    /// or to air if the block doesn't exist.
+    pub block_states_to_internal: Vec<u32>,
+    /// Item IDs, indexed by the internal ID.
+    pub items_to_mapped: Vec<u32>,
+    /// Internal item IDs, indexed by the mapped ID. Items
+    /// which don't exist internally map to air.
+    pub items_to_internal: Vec<u32>,
+    /// Entity type IDs, indexed by the internal ID.
+    pub entities_to_mapped: Vec<u32>,
+    /// Internal entity type IDs, indexed by the mapped ID. Entity
+    /// types which don't exist internally map to pigs.
+    pub entities_to_internal: Vec<u32>,
+}
+
+impl VersionMappings {
+    /// Gets the version these mappings are for.
+    pub fn version(&self) -> anyhow::Result<ProtocolVersion> {
+        ProtocolVersion::from_number(self.protocol)
+            .ok_or_else(|| anyhow!("unknown protocol version {}", self.protocol))
+    }
+
+    pub fn block_state_to_wire(&self, id: u16) -> u32 {
+        self.block_states_to_mapped
+            .get(id as usize)
+            .copied()
+            .unwrap_or_default()
+    }
+
+    pub fn block_state_from_wire(&self, id: u32) -> anyhow::Result<u16> {
+        let id = self
+            .block_states_to_internal
+            .get(id as usize)
+            .copied()
+            .unwrap_or_default();
+        Ok(id.try_into()?)
+    }
+
+    pub fn item_to_wire(&self, id: u32) -> u32 {
+        self.items_to_mapped
+            .get(id as usize)
+            .copied()
+            .unwrap_or_default()
+    }
+
+    pub fn item_from_wire(&self, id: u32) -> u32 {
+        self.items_to_internal
+            .get(id as usize)
+            .copied()
+            .unwrap_or_default()
+    }
+
+    pub fn entity_to_wire(&self, id: u32) -> u32 {
+        self.entities_to_mapped
+            .get(id as usize)
+            .copied()
+            .unwrap_or_default()
+    }
+
+    pub fn entity_from_wire(&self, id: u32) -> u32 {
+        self.entities_to_internal
+            .get(id as usize)
+            .copied()
+            .unwrap_or_default()
+    }
+}
+
+/// The mappings of a set of versions.
+#[derive(Debug, Default)]
+pub struct Registry {
+    /// Indexed by the position of the version in [`ProtocolVersion::ALL`].
+    mappings: Vec<Option<VersionMappings>>,
+}
+
+impl Registry {
+    /// Creates a registry without mappings.
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Registers the mappings for a version, replacing any
+    /// previous mappings for that version.
+    pub fn register(&mut self, mappings: VersionMappings) -> anyhow::Result<ProtocolVersion> {
+        let version = mappings.version()?;
+        if version == ProtocolVersion::INTERNAL {
+            bail!("the internal version {} can't be mapped", version.name());
+        }
+
+        let index = version as usize;
+        if self.mappings.len() <= index {
+            self.mappings.resize_with(index + 1, || None);
+        }
+        self.mappings[index] = Some(mappings);
+        Ok(version)
+    }
+
+    /// Registers every mapping file (`*.json`) in `directory`,
+    /// returning the versions mappings were loaded for.
+    pub fn load_directory(&mut self, directory: &Path) -> anyhow::Result<Vec<ProtocolVersion>> {
+        let mut versions = Vec::new();
+        for entry in fs::read_dir(directory)? {
+            let path = entry?.path();
+            if path
+                .extension()
+                .map_or(true, |extension| extension != "json")
+            {
+                continue;
+            }
+            let mappings: VersionMappings = serde_json::from_slice(&fs::read(&path)?)
+                .with_context(|| format!("invalid mapping file {}", path.display()))?;
+            versions.push(self.register(mappings)?);
+        }
+        Ok(versions)
+    }
+
+    /// Gets the mappings for `version`, or `None` if it is the
+    /// internal version or no mappings are registered for it.
+    pub fn get(&self, version: ProtocolVersion) -> Option<&VersionMappings> {
+        if version == ProtocolVersion::INTERNAL {
+            return None;
+        }
+        self.mappings.get(version as usize)?.as_ref()
+    }
+
+    /// Determines whether connections can use `version`.
+    pub fn is_supported(&self, version: ProtocolVersion) -> bool {
+        version == ProtocolVersion::INTERNAL
+            || (self.get(version).is_some() && has_translated_layouts(version))
+    }
+
+    /// Gets the versions connections can use, in ascending order.
+    pub fn supported_versions(&self) -> Vec<ProtocolVersion> {
+        ProtocolVersion::ALL
+            .iter()
+            .copied()
+            .filter(|&version| self.is_supported(version))
+            .collect()
+    }
+}
+
+/// Determines whether the packets translate their layouts to those of
+/// `version`, so that translating IDs is all that's left.
+fn has_translated_layouts(version: ProtocolVersion) -> bool {
+    match version {
+        ProtocolVersion::V1_16_2 | ProtocolVersion::V1_17_1 | ProtocolVersion::V1_18_2 => true,
+        ProtocolVersion::V1_19 => false,
+    }
+}
+
+/// Installs the registry used by all connections.
+/// Fails if a registry has already been installed.
+pub fn install(registry: Registry) -> anyhow::Result<()> {
+    REGISTRY
+        .set(registry)
+        .map_err(|_| anyhow!("protocol mappings are already installed"))
+}
+
+/// Gets the installed mappings for `version`, or `None` if it is
+/// the internal version or no mappings are installed for it.
+pub fn get(version: ProtocolVersion) -> Option<&'static VersionMappings> {
+    if version == ProtocolVersion::INTERNAL {
+        return None;
+    }
+    REGISTRY.get()?.get(version)
+}
+
+/// Determines whether connections can use `version`.
+pub fn is_supported(version: ProtocolVersion) -> bool {
+    match REGISTRY.get() {
+        Some(registry) => registry.is_supported(version),
+        None => version == ProtocolVersion::INTERNAL,
+    }
+}
+
+/// Gets the versions connections can use, in ascending order.
+pub fn supported_versions() -> Vec<ProtocolVersion> {
+    match REGISTRY.get() {
+        Some(registry) => registry.supported_versions(),
+        None => vec![ProtocolVersion::INTERNAL],
+    }
+}
+
+pub(crate) fn block_state_to_wire(version: ProtocolVersion, id: u16) -> u32 {
+    match get(version) {
+        Some(mappings) => mappings.block_state_to_wire(id),
+        None => id.into(),
+    }
+}
+
+pub(crate) fn block_state_from_wire(version: ProtocolVersion, id: u32) -> anyhow::Result<u16> {
+    match get(version) {
+        Some(mappings) => mappings.block_state_from_wire(id),
+        None => Ok(id.try_into()?),
+    }
+}
+
+pub(crate) fn item_to_wire(version: ProtocolVersion, id: u32) -> u32 {
+    match get(version) {
+        Some(mappings) => mappings.item_to_wire(id),
+        None => id,
+    }
+}
+
+pub(crate) fn item_from_wire(version: ProtocolVersion, id: u32) -> u32 {
+    match get(version) {
+        Some(mappings) => mappings.item_from_wire(id),
+        None => id,
+    }
+}
+
+pub(crate) fn entity_to_wire(version: ProtocolVersion, id: u32) -> u32 {
+    match get(version) {
+        Some(mappings) => mappings.entity_to_wire(id),
+        None => id,
+    }
+}
+
+pub(crate) fn entity_from_wire(version: ProtocolVersion, id: u32) -> u32 {
+    match get(version) {
+        Some(mappings) => mappings.entity_from_wire(id),
+        None => id,
+    }
+}
+
+/// Determines whether the clientbound play packet with the internal
+/// ID `id` exists in `version`. Packets which don't can't be sent to
+/// connections of that version.
+pub fn has_clientbound_equivalent(version: ProtocolVersion, id: u32) -> bool {
+    ServerPlayPacket::to_wire(version, id).is_ok()
+}
+
+/// Translation of the IDs of a set of packets.
+///
+/// Only play packets are renumbered between the supported versions.
+pub trait PacketIds {
+    /// Translates an internal packet ID to the one sent to `version`.
+    fn to_wire(_version: ProtocolVersion, id: u32) -> anyhow::Result<u32> {
+        Ok(id)
+    }
+
+    /// Translates a packet ID received from `version` to the internal one.
+    fn from_wire(_version: ProtocolVersion, id: u32) -> anyhow::Result<u32> {
+        Ok(id)
+    }
+}
+
+impl PacketIds for ClientHandshakePacket {}
+impl PacketIds for ClientStatusPacket {}
+impl PacketIds for ClientLoginPacket {}
+impl PacketIds for ServerStatusPacket {}
+impl PacketIds for ServerLoginPacket {}
+
+impl PacketIds for ServerPlayPacket {
+    fn to_wire(version: ProtocolVersion, id: u32) -> anyhow::Result<u32> {
+        match get(version) {
+            Some(mappings) => forward(&mappings.clientbound_play, id, version),
+            None => Ok(id),
+        }
+    }
+
+    fn from_wire(version: ProtocolVersion, id: u32) -> anyhow::Result<u32> {
+        match get(version) {
+            Some(mappings) => backward(&mappings.clientbound_play, id, version),
+            None => Ok(id),
+        }
+    }
+}
+
+impl PacketIds for ClientPlayPacket {
+    fn to_wire(version: ProtocolVersion, id: u32) -> anyhow::Result<u32> {
+        match get(version) {
+            Some(mappings) => backward(&mappings.serverbound_play, id, version),
+            None => Ok(id),
+        }
+    }
+
+    fn from_wire(version: ProtocolVersion, id: u32) -> anyhow::Result<u32> {
+        match get(version) {
+            Some(mappings) => forward(&mappings.serverbound_play, id, version),
+            None => Ok(id),
+        }
+    }
+}
+
+/// Looks up `id` in a packet ID table.
+fn forward(table: &[Option<u32>], id: u32, version: ProtocolVersion) -> anyhow::Result<u32> {
+    table
+        .get(id as usize)
+        .copied()
+        .flatten()
+        .ok_or_else(|| anyhow!("packet {:#04x} has no equivalent in {}", id, version.name()))
+}
+
+/// Finds the index in a packet ID table which maps to `id`.
+fn backward(table: &[Option<u32>], id: u32, version: ProtocolVersion) -> anyhow::Result<u32> {
+    table
+        .iter()
+        .position(|&mapped| mapped == Some(id))
+        .map(|index| index as u32)
+        .ok_or_else(|| anyhow!("packet {:#04x} has no equivalent in {}", id, version.name()))
+}
+
+#[cfg(test)]
+mod tests {
+    use base::BlockId;
+
+    use super::*;
+
+    /// Mappings which shift every block state and item by one and
+    /// swap the IDs of the first two play packets in each direction.
+    fn shifted_mappings() -> VersionMappings {
+        let states = BlockId::air().vanilla_id() as u32..=BlockId::stone().vanilla_id() as u32;
+        VersionMappings {
+            protocol: ProtocolVersion::V1_17_1.number(),
+            clientbound_play: (0..0x60)
+                .map(|id| Some(if id < 2 { 1 - id } else { id }))
+                .collect(),
+            serverbound_play: (0..0x30)
+                .map(|id| Some(if id < 2 { 1 - id } else { id }))
+                .collect(),
+            block_states_to_mapped: states.clone().map(|id| id + 1).collect(),
+            block_states_to_internal: std::iter::once(0).chain(states).collect(),
+            items_to_mapped: (0..10).map(|id| id + 1).collect(),
+            items_to_internal: std::iter::once(0).chain(0..10).collect(),
+            entities_to_mapped: (0..10).map(|id| id + 1).collect(),
+            entities_to_internal: std::iter::once(0).chain(0..10).collect(),
+        }
+    }
+
+    #[test]
+    fn translates_ids() {
+        let mut registry = Registry::new();
+        let version = registry.register(shifted_mappings()).unwrap();
+        let mappings = registry.get(version).unwrap();
+
+        let stone = BlockId::stone().vanilla_id();
+        assert_eq!(mappings.block_state_to_wire(stone), stone as u32 + 1);
+        assert_eq!(
+            mappings.block_state_from_wire(stone as u32 + 1).unwrap(),
+            stone
+        );
+        assert_eq!(mappings.item_to_wire(3), 4);
+        assert_eq!(mappings.item_from_wire(4), 3);
+        assert_eq!(mappings.entity_to_wire(3), 4);
+        assert_eq!(mappings.entity_from_wire(4), 3);
+        assert_eq!(forward(&mappings.clientbound_play, 0, version).unwrap(), 1);
+        assert_eq!(backward(&mappings.clientbound_play, 1, version).unwrap(), 0);
+        assert_eq!(forward(&mappings.serverbound_play, 1, version).unwrap(), 0);
+        assert_eq!(backward(&mappings.serverbound_play, 0, version).unwrap(), 1);
+        assert_eq!(ServerLoginPacket::to_wire(version, 0).unwrap(), 0);
+    }
+
+    #[test]
+    fn only_versions_with_translated_layouts_are_supported() {
+        let mut registry = Registry::new();
+        assert!(!registry.is_supported(ProtocolVersion::V1_17_1));
+
+        registry.register(shifted_mappings()).unwrap();
+        registry
+            .register(VersionMappings {
+                protocol: ProtocolVersion::V1_19.number(),
+                ..shifted_mappings()
+            })
+            .unwrap();
+        assert!(registry.get(ProtocolVersion::V1_19).is_some());
+        assert!(registry.is_supported(ProtocolVersion::V1_17_1));
+        assert!(!registry.is_supported(ProtocolVersion::V1_19));
+        assert_eq!(
+            registry.supported_versions(),
+            vec![ProtocolVersion::INTERNAL, ProtocolVersion::V1_17_1]
+        );
+    }
+
+    #[test]
+    fn internal_version_is_not_translated() {
+        let mut registry = Registry::new();
+        assert!(registry
+            .register(VersionMappings {
+                protocol: ProtocolVersion::INTERNAL.number(),
+                ..shifted_mappings()
+            })
+            .is_err());
+        assert!(registry.get(ProtocolVersion::INTERNAL).is_none());
+        assert!(registry.is_supported(ProtocolVersion::INTERNAL));
+        assert_eq!(block_state_to_wire(ProtocolVersion::INTERNAL, 5), 5);
+        assert_eq!(
+            ServerPlayPacket::to_wire(ProtocolVersion::INTERNAL, 0x20).unwrap(),
+            0x20
+        );
+    }
+}
@@ -224,7 +224,8 @@ macro_rules! packet_enum {
             where
                 Self: Sized
             {
-                let packet_id = VarInt::read(buffer, version)?.0;
+                let wire_id = VarInt::read(buffer, version)?.0 as u32;
+                let packet_id = <$ident as crate::mapping::PacketIds>::from_wire(version, wire_id)?;
                 match packet_id {
                     $(
                         id if id == $id => Ok($ident::$packet($packet::read(buffer, version)?)),
@@ -236,7 +237,8 @@ macro_rules! packet_enum {
 
         impl crate::Writeable for $ident {
             fn write(&self, buffer: &mut Vec<u8>, version: crate::ProtocolVersion) -> anyhow::Result<()> {
-                VarInt(self.id() as i32).write(buffer, version)?;
+                let wire_id = <$ident as crate::mapping::PacketIds>::to_wire(version, self.id())?;
+                VarInt(wire_id as i32).write(buffer, version)?;
                 match self {
                     $(
                         $ident::$packet(packet) => {
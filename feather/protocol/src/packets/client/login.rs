@@ -1,10 +1,10 @@
+use std::io::Cursor;
+
+use crate::{ProtocolVersion, Readable, Writeable};
+
 use super::*;
 
 packets! {
-    LoginStart {
-        name String;
-    }
-
     EncryptionResponse {
         shared_secret VarIntPrefixedVec<u8>;
         verify_token VarIntPrefixedVec<u8>;
@@ -16,3 +16,37 @@ packets! {
         data LengthInferredVecU8;
     }
 }
+
+#[derive(Debug, Clone)]
+pub struct LoginStart {
+    pub name: String,
+}
+
+impl Readable for LoginStart {
+    fn read(buffer: &mut Cursor<&[u8]>, version: ProtocolVersion) -> anyhow::Result<Self>
+    where
+        Self: Sized,
+    {
+        let name = String::read(buffer, version)?;
+
+        // Since 1.19, clients send their chat signing key, which
+        // is ignored as chat messages are never signed.
+        if version >= ProtocolVersion::V1_19 && bool::read(buffer, version)? {
+            i64::read(buffer, version)?; // Expiry timestamp
+            VarIntPrefixedVec::<u8>::read(buffer, version)?; // Public key
+            VarIntPrefixedVec::<u8>::read(buffer, version)?; // Signature
+        }
+
+        Ok(Self { name })
+    }
+}
+
+impl Writeable for LoginStart {
+    fn write(&self, buffer: &mut Vec<u8>, version: ProtocolVersion) -> anyhow::Result<()> {
+        self.name.write(buffer, version)?;
+        if version >= ProtocolVersion::V1_19 {
+            false.write(buffer, version)?; // No signing key
+        }
+        Ok(())
+    }
+}
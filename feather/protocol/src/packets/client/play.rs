@@ -1,7 +1,9 @@
+use std::io::Cursor;
+
 use base::ValidBlockPosition;
 
 use super::*;
-use crate::packets::server::Hand;
+use crate::{packets::server::Hand, ProtocolVersion, Readable, Writeable};
 
 packets! {
     TeleportConfirm {
@@ -70,15 +72,6 @@ packets! {
         button_id u8;
     }
 
-    ClickWindow {
-        window_id u8;
-        slot i16;
-        button i8;
-        action_number u16;
-        mode VarInt;
-        clicked_item Slot;
-    }
-
     CloseWindow {
         window_id u8;
     }
@@ -101,6 +94,75 @@ packets! {
     }
 }
 
+#[derive(Debug, Clone)]
+pub struct ClickWindow {
+    pub window_id: u8,
+    pub slot: i16,
+    pub button: i8,
+    /// Always 0 from 1.17 on, where clicks aren't confirmed.
+    pub action_number: u16,
+    pub mode: i32,
+    pub clicked_item: Slot,
+}
+
+impl Readable for ClickWindow {
+    fn read(buffer: &mut Cursor<&[u8]>, version: ProtocolVersion) -> anyhow::Result<Self>
+    where
+        Self: Sized,
+    {
+        let window_id = u8::read(buffer, version)?;
+        if version < ProtocolVersion::V1_17_1 {
+            return Ok(Self {
+                window_id,
+                slot: i16::read(buffer, version)?,
+                button: i8::read(buffer, version)?,
+                action_number: u16::read(buffer, version)?,
+                mode: VarInt::read(buffer, version)?.0,
+                clicked_item: Slot::read(buffer, version)?,
+            });
+        }
+
+        VarInt::read(buffer, version)?; // State ID
+        let slot = i16::read(buffer, version)?;
+        let button = i8::read(buffer, version)?;
+        let mode = VarInt::read(buffer, version)?.0;
+        // The slots the client predicts to change; the server
+        // sends the window's items after every click anyway.
+        for _ in 0..VarInt::read(buffer, version)?.0 {
+            i16::read(buffer, version)?;
+            Slot::read(buffer, version)?;
+        }
+        Ok(Self {
+            window_id,
+            slot,
+            button,
+            action_number: 0,
+            mode,
+            clicked_item: Slot::read(buffer, version)?, // Carried item
+        })
+    }
+}
+
+impl Writeable for ClickWindow {
+    fn write(&self, buffer: &mut Vec<u8>, version: ProtocolVersion) -> anyhow::Result<()> {
+        self.window_id.write(buffer, version)?;
+        if version < ProtocolVersion::V1_17_1 {
+            self.slot.write(buffer, version)?;
+            self.button.write(buffer, version)?;
+            self.action_number.write(buffer, version)?;
+            VarInt(self.mode).write(buffer, version)?;
+            return self.clicked_item.write(buffer, version);
+        }
+
+        VarInt(0).write(buffer, version)?; // State ID
+        self.slot.write(buffer, version)?;
+        self.button.write(buffer, version)?;
+        VarInt(self.mode).write(buffer, version)?;
+        VarInt(0).write(buffer, version)?; // Changed slots
+        self.clicked_item.write(buffer, version)
+    }
+}
+
 def_enum! {
     InteractEntityKind (VarInt) {
         0 = Interact,
@@ -1,3 +1,7 @@
+use std::io::Cursor;
+
+use crate::{ProtocolVersion, Readable, Writeable};
+
 use super::*;
 
 packets! {
@@ -11,11 +15,6 @@ packets! {
         verify_token VarIntPrefixedVec<u8>;
     }
 
-    LoginSuccess {
-        uuid Uuid;
-        username String;
-    }
-
     SetCompression {
         threshold VarInt;
     }
@@ -26,3 +25,41 @@ packets! {
         data LengthInferredVecU8;
     }
 }
+
+#[derive(Debug, Clone)]
+pub struct LoginSuccess {
+    pub uuid: Uuid,
+    pub username: String,
+}
+
+impl Readable for LoginSuccess {
+    fn read(buffer: &mut Cursor<&[u8]>, version: ProtocolVersion) -> anyhow::Result<Self>
+    where
+        Self: Sized,
+    {
+        let uuid = Uuid::read(buffer, version)?;
+        let username = String::read(buffer, version)?;
+        if version >= ProtocolVersion::V1_19 {
+            // Profile properties
+            for _ in 0..VarInt::read(buffer, version)?.0 {
+                String::read(buffer, version)?; // Name
+                String::read(buffer, version)?; // Value
+                if bool::read(buffer, version)? {
+                    String::read(buffer, version)?; // Signature
+                }
+            }
+        }
+        Ok(Self { uuid, username })
+    }
+}
+
+impl Writeable for LoginSuccess {
+    fn write(&self, buffer: &mut Vec<u8>, version: ProtocolVersion) -> anyhow::Result<()> {
+        self.uuid.write(buffer, version)?;
+        self.username.write(buffer, version)?;
+        if version >= ProtocolVersion::V1_19 {
+            VarInt(0).write(buffer, version)?; // Profile properties
+        }
+        Ok(())
+    }
+}
@@ -6,52 +6,138 @@ use base::{
     BlockState, EntityMetadata, Gamemode, ParticleKind, ProfileProperty, ValidBlockPosition,
 };
 pub use chunk_data::{ChunkData, ChunkDataKind};
-use quill_common::components::PreviousGamemode;
+pub use join_game::{JoinGame, Respawn};
 pub use update_light::UpdateLight;
 
-use crate::{io::VarLong, ProtocolVersion, Readable, Writeable};
+use crate::{io::VarLong, mapping, ProtocolVersion, Readable, Writeable};
 
 use super::*;
 
 mod chunk_data;
+mod join_game;
 mod update_light;
-packets! {
-    SpawnEntity {
-        entity_id VarInt;
-        uuid Uuid;
-        kind VarInt;
-        x f64;
-        y f64;
-        z f64;
-        pitch Angle;
-        yaw Angle;
-        data i32;
-        velocity_x i16;
-        velocity_y i16;
-        velocity_z i16;
+
+#[derive(Debug, Clone)]
+pub struct SpawnEntity {
+    pub entity_id: i32,
+    pub uuid: Uuid,
+    pub kind: i32,
+    pub x: f64,
+    pub y: f64,
+    pub z: f64,
+    pub pitch: f32,
+    pub yaw: f32,
+    pub data: i32,
+    pub velocity_x: i16,
+    pub velocity_y: i16,
+    pub velocity_z: i16,
+}
+
+impl Readable for SpawnEntity {
+    fn read(buffer: &mut Cursor<&[u8]>, version: ProtocolVersion) -> anyhow::Result<Self>
+    where
+        Self: Sized,
+    {
+        Ok(Self {
+            entity_id: VarInt::read(buffer, version)?.0,
+            uuid: Uuid::read(buffer, version)?,
+            kind: mapping::entity_from_wire(version, VarInt::read(buffer, version)?.0 as u32)
+                as i32,
+            x: f64::read(buffer, version)?,
+            y: f64::read(buffer, version)?,
+            z: f64::read(buffer, version)?,
+            pitch: Angle::read(buffer, version)?.0,
+            yaw: Angle::read(buffer, version)?.0,
+            data: i32::read(buffer, version)?,
+            velocity_x: i16::read(buffer, version)?,
+            velocity_y: i16::read(buffer, version)?,
+            velocity_z: i16::read(buffer, version)?,
+        })
     }
+}
 
-    SpawnExperienceOrb {
-        entity_id VarInt;
-        x f64;
-        y f64;
-        z f64;
-        count u16;
+impl Writeable for SpawnEntity {
+    fn write(&self, buffer: &mut Vec<u8>, version: ProtocolVersion) -> anyhow::Result<()> {
+        VarInt(self.entity_id).write(buffer, version)?;
+        self.uuid.write(buffer, version)?;
+        VarInt(mapping::entity_to_wire(version, self.kind as u32) as i32).write(buffer, version)?;
+        self.x.write(buffer, version)?;
+        self.y.write(buffer, version)?;
+        self.z.write(buffer, version)?;
+        Angle(self.pitch).write(buffer, version)?;
+        Angle(self.yaw).write(buffer, version)?;
+        self.data.write(buffer, version)?;
+        self.velocity_x.write(buffer, version)?;
+        self.velocity_y.write(buffer, version)?;
+        self.velocity_z.write(buffer, version)?;
+        Ok(())
     }
+}
+
+#[derive(Debug, Clone)]
+pub struct SpawnLivingEntity {
+    pub entity_id: i32,
+    pub entity_uuid: Uuid,
+    pub kind: i32,
+    pub x: f64,
+    pub y: f64,
+    pub z: f64,
+    pub yaw: f32,
+    pub pitch: f32,
+    pub head_pitch: f32,
+    pub velocity_x: i16,
+    pub velocity_y: i16,
+    pub velocity_z: i16,
+}
+
+impl Readable for SpawnLivingEntity {
+    fn read(buffer: &mut Cursor<&[u8]>, version: ProtocolVersion) -> anyhow::Result<Self>
+    where
+        Self: Sized,
+    {
+        Ok(Self {
+            entity_id: VarInt::read(buffer, version)?.0,
+            entity_uuid: Uuid::read(buffer, version)?,
+            kind: mapping::entity_from_wire(version, VarInt::read(buffer, version)?.0 as u32)
+                as i32,
+            x: f64::read(buffer, version)?,
+            y: f64::read(buffer, version)?,
+            z: f64::read(buffer, version)?,
+            yaw: Angle::read(buffer, version)?.0,
+            pitch: Angle::read(buffer, version)?.0,
+            head_pitch: Angle::read(buffer, version)?.0,
+            velocity_x: i16::read(buffer, version)?,
+            velocity_y: i16::read(buffer, version)?,
+            velocity_z: i16::read(buffer, version)?,
+        })
+    }
+}
+
+impl Writeable for SpawnLivingEntity {
+    fn write(&self, buffer: &mut Vec<u8>, version: ProtocolVersion) -> anyhow::Result<()> {
+        VarInt(self.entity_id).write(buffer, version)?;
+        self.entity_uuid.write(buffer, version)?;
+        VarInt(mapping::entity_to_wire(version, self.kind as u32) as i32).write(buffer, version)?;
+        self.x.write(buffer, version)?;
+        self.y.write(buffer, version)?;
+        self.z.write(buffer, version)?;
+        Angle(self.yaw).write(buffer, version)?;
+        Angle(self.pitch).write(buffer, version)?;
+        Angle(self.head_pitch).write(buffer, version)?;
+        self.velocity_x.write(buffer, version)?;
+        self.velocity_y.write(buffer, version)?;
+        self.velocity_z.write(buffer, version)?;
+        Ok(())
+    }
+}
 
-    SpawnLivingEntity {
+packets! {
+    SpawnExperienceOrb {
         entity_id VarInt;
-        entity_uuid Uuid;
-        kind VarInt;
         x f64;
         y f64;
         z f64;
-        yaw Angle;
-        pitch Angle;
-        head_pitch Angle;
-        velocity_x i16;
-        velocity_y i16;
-        velocity_z i16;
+        count u16;
     }
 
     SpawnPainting {
@@ -266,23 +352,12 @@ packets! {
         window_id u8;
     }
 
-    WindowItems {
-        window_id u8;
-        items ShortPrefixedVec<Slot>;
-    }
-
     WindowProperty {
         window_id u8;
         property i16;
         value i16;
     }
 
-    SetSlot {
-        window_id u8;
-        slot i16;
-        slot_data Slot;
-    }
-
     SetCooldown {
         item_id VarInt;
         cooldown_ticks VarInt;
@@ -357,6 +432,88 @@ packets! {
     }
 }
 
+#[derive(Debug, Clone)]
+pub struct WindowItems {
+    pub window_id: u8,
+    pub items: Vec<Slot>,
+    /// The item held by the cursor. Only sent from 1.17 on,
+    /// where the window's items include it.
+    pub carried_item: Slot,
+}
+
+impl Readable for WindowItems {
+    fn read(buffer: &mut Cursor<&[u8]>, version: ProtocolVersion) -> anyhow::Result<Self>
+    where
+        Self: Sized,
+    {
+        let window_id = u8::read(buffer, version)?;
+        if version < ProtocolVersion::V1_17_1 {
+            return Ok(Self {
+                window_id,
+                items: ShortPrefixedVec::<Slot>::read(buffer, version)?.into(),
+                carried_item: Slot::Empty,
+            });
+        }
+
+        VarInt::read(buffer, version)?; // State ID
+        Ok(Self {
+            window_id,
+            items: VarIntPrefixedVec::<Slot>::read(buffer, version)?.into(),
+            carried_item: Slot::read(buffer, version)?,
+        })
+    }
+}
+
+impl Writeable for WindowItems {
+    fn write(&self, buffer: &mut Vec<u8>, version: ProtocolVersion) -> anyhow::Result<()> {
+        self.window_id.write(buffer, version)?;
+        if version < ProtocolVersion::V1_17_1 {
+            return ShortPrefixedVec::from(self.items.as_slice()).write(buffer, version);
+        }
+
+        // The client echoes the state ID in `ClickWindow`,
+        // where the server ignores it.
+        VarInt(0).write(buffer, version)?;
+        VarIntPrefixedVec::from(self.items.as_slice()).write(buffer, version)?;
+        self.carried_item.write(buffer, version)
+    }
+}
+
+#[derive(Debug, Clone)]
+pub struct SetSlot {
+    pub window_id: u8,
+    pub slot: i16,
+    pub slot_data: Slot,
+}
+
+impl Readable for SetSlot {
+    fn read(buffer: &mut Cursor<&[u8]>, version: ProtocolVersion) -> anyhow::Result<Self>
+    where
+        Self: Sized,
+    {
+        let window_id = u8::read(buffer, version)?;
+        if version >= ProtocolVersion::V1_17_1 {
+            VarInt::read(buffer, version)?; // State ID
+        }
+        Ok(Self {
+            window_id,
+            slot: i16::read(buffer, version)?,
+            slot_data: Slot::read(buffer, version)?,
+        })
+    }
+}
+
+impl Writeable for SetSlot {
+    fn write(&self, buffer: &mut Vec<u8>, version: ProtocolVersion) -> anyhow::Result<()> {
+        self.window_id.write(buffer, version)?;
+        if version >= ProtocolVersion::V1_17_1 {
+            VarInt(0).write(buffer, version)?; // State ID
+        }
+        self.slot.write(buffer, version)?;
+        self.slot_data.write(buffer, version)
+    }
+}
+
 #[derive(Debug, Clone)]
 pub enum GameStateChange {
     /// Sends block.minecraft.spawn.not_valid to client
@@ -482,29 +639,6 @@ impl Readable for GameStateChange {
     }
 }
 
-packets! {
-    JoinGame {
-        entity_id i32;
-        is_hardcore bool;
-        gamemode Gamemode;
-        previous_gamemode PreviousGamemode; // can be -1 if "not set", otherwise corresponds to a gamemode ID
-        world_names VarIntPrefixedVec<String>;
-
-        dimension_codec Nbt<Blob>;
-        dimension Nbt<Blob>;
-
-        world_name String;
-        hashed_seed u64;
-        max_players VarInt;
-        view_distance VarInt;
-        reduced_debug_info bool;
-        enable_respawn_screen bool;
-
-        is_debug bool;
-        is_flat bool;
-    }
-}
-
 packets! {
     MapData {
         map_id VarInt;
@@ -931,16 +1065,6 @@ packets! {
         feet_or_eyes VarInt;
     }
 
-    PlayerPositionAndLook {
-        x f64;
-        y f64;
-        z f64;
-        yaw f32;
-        pitch f32;
-        flags u8;
-        teleport_id VarInt;
-    }
-
     UnlockRecipes {
         __todo__ LengthInferredVecU8;
     }
@@ -959,17 +1083,6 @@ packets! {
         hash String;
     }
 
-    Respawn {
-        dimension Nbt<Blob>;
-        world_name String;
-        hashed_seed u64;
-        gamemode Gamemode;
-        previous_gamemode Gamemode;
-        is_debug bool;
-        is_flat bool;
-        copy_metadata bool;
-    }
-
     EntityHeadLook {
         entity_id VarInt;
         head_yaw Angle;
@@ -980,6 +1093,54 @@ packets! {
     }
 }
 
+#[derive(Debug, Clone)]
+pub struct PlayerPositionAndLook {
+    pub x: f64,
+    pub y: f64,
+    pub z: f64,
+    pub yaw: f32,
+    pub pitch: f32,
+    pub flags: u8,
+    pub teleport_id: i32,
+}
+
+impl Readable for PlayerPositionAndLook {
+    fn read(buffer: &mut Cursor<&[u8]>, version: ProtocolVersion) -> anyhow::Result<Self>
+    where
+        Self: Sized,
+    {
+        let packet = Self {
+            x: f64::read(buffer, version)?,
+            y: f64::read(buffer, version)?,
+            z: f64::read(buffer, version)?,
+            yaw: f32::read(buffer, version)?,
+            pitch: f32::read(buffer, version)?,
+            flags: u8::read(buffer, version)?,
+            teleport_id: VarInt::read(buffer, version)?.0,
+        };
+        if version >= ProtocolVersion::V1_17_1 {
+            bool::read(buffer, version)?; // Dismount vehicle
+        }
+        Ok(packet)
+    }
+}
+
+impl Writeable for PlayerPositionAndLook {
+    fn write(&self, buffer: &mut Vec<u8>, version: ProtocolVersion) -> anyhow::Result<()> {
+        self.x.write(buffer, version)?;
+        self.y.write(buffer, version)?;
+        self.z.write(buffer, version)?;
+        self.yaw.write(buffer, version)?;
+        self.pitch.write(buffer, version)?;
+        self.flags.write(buffer, version)?;
+        VarInt(self.teleport_id).write(buffer, version)?;
+        if version >= ProtocolVersion::V1_17_1 {
+            false.write(buffer, version)?; // Dismount vehicle
+        }
+        Ok(())
+    }
+}
+
 def_enum! {
     WorldBorder (VarInt) {
         0 = SetSize {
@@ -4,7 +4,8 @@ use std::{
     sync::Arc,
 };
 
-use base::{Chunk, ChunkHandle, ChunkLock, ChunkPosition, ChunkSection};
+use anyhow::bail;
+use base::{chunk::PackedArray, Chunk, ChunkHandle, ChunkLock, ChunkPosition, ChunkSection};
 use blocks::BlockId;
 use libcraft_core::Biome;
 use serde::{
@@ -13,7 +14,8 @@ use serde::{
     Deserialize, Deserializer, Serialize,
 };
 
-use crate::{io::VarInt, Nbt, ProtocolVersion, Readable, Writeable};
+use super::update_light::{write_bit_set, write_light};
+use crate::{io::VarInt, mapping, Nbt, ProtocolVersion, Readable, Writeable};
 
 #[derive(Serialize, Deserialize)]
 struct Heightmaps {
@@ -68,21 +70,34 @@ impl Writeable for ChunkData {
         chunk.position().x.write(buffer, version)?;
         chunk.position().z.write(buffer, version)?;
 
-        let full_chunk = matches!(self.kind, ChunkDataKind::LoadChunk);
-        full_chunk.write(buffer, version)?;
+        if version >= ProtocolVersion::V1_18_2 {
+            return write_sections_with_biomes(&chunk, buffer, version);
+        }
+
+        // From 1.17 on, chunks are always sent whole
+        let full_chunk =
+            matches!(self.kind, ChunkDataKind::LoadChunk) || version >= ProtocolVersion::V1_17_1;
+        let skip_section = |y| !full_chunk && self.should_skip_section(y);
+        if version < ProtocolVersion::V1_17_1 {
+            full_chunk.write(buffer, version)?;
+        }
 
         // Compute primary bit mask
         let mut bitmask = 0;
         for (y, section) in chunk.sections().iter().enumerate().skip(1).take(16) {
             if section.is_some() {
-                if self.should_skip_section(y) {
+                if skip_section(y) {
                     continue;
                 }
 
                 bitmask |= 1 << (y - 1) as i32;
             }
         }
-        VarInt(bitmask).write(buffer, version)?;
+        if version < ProtocolVersion::V1_17_1 {
+            VarInt(bitmask).write(buffer, version)?;
+        } else {
+            write_bit_set(bitmask as u64, buffer, version)?;
+        }
 
         let heightmaps = build_heightmaps(&chunk);
         Nbt(heightmaps).write(buffer, version)?;
@@ -99,7 +114,7 @@ impl Writeable for ChunkData {
         let mut data = Vec::new();
         for (y, section) in chunk.sections().iter().enumerate().skip(1).take(16) {
             if let Some(section) = section {
-                if self.should_skip_section(y) {
+                if skip_section(y) {
                     continue;
                 }
                 encode_section(section, &mut data, version)?;
@@ -114,6 +129,91 @@ impl Writeable for ChunkData {
     }
 }
 
+/// Writes the rest of a 1.18 `ChunkData` packet, where every section
+/// carries its biomes and the light follows the block entities.
+fn write_sections_with_biomes(
+    chunk: &Chunk,
+    buffer: &mut Vec<u8>,
+    version: ProtocolVersion,
+) -> anyhow::Result<()> {
+    let heightmaps = build_heightmaps(chunk);
+    Nbt(heightmaps).write(buffer, version)?;
+
+    let mut data = Vec::new();
+    for (y, section) in chunk.sections().iter().enumerate().skip(1).take(16) {
+        match section {
+            Some(section) => encode_section(section, &mut data, version)?,
+            None => {
+                // The sections of the whole world height are sent
+                0u16.write(&mut data, version)?; // non-air blocks
+                0u8.write(&mut data, version)?; // a single value...
+                BlockId::air().write(&mut data, version)?; // ...which is air
+                VarInt(0).write(&mut data, version)?; // length of data
+            }
+        }
+        encode_section_biomes(chunk, y - 1, &mut data, version)?;
+    }
+    VarInt(data.len() as i32).write(buffer, version)?;
+    buffer.extend_from_slice(&data);
+
+    VarInt(0).write(buffer, version)?; // number of block entities - always 0 for Feather
+
+    write_light(chunk, buffer, version)
+}
+
+/// The most biomes the palette of a section's biomes can hold. Sections
+/// with more would need the direct format, whose size depends on the
+/// client's biome registry, so their excess biomes are replaced.
+const MAX_SECTION_BIOMES: usize = 8;
+
+/// Writes the biomes of the section at `section_y` as a paletted container.
+fn encode_section_biomes(
+    chunk: &Chunk,
+    section_y: usize,
+    buffer: &mut Vec<u8>,
+    version: ProtocolVersion,
+) -> anyhow::Result<()> {
+    let biomes = chunk.biomes();
+    let mut palette = Vec::new();
+    let mut indices = Vec::with_capacity(64);
+    for y in section_y * 4..section_y * 4 + 4 {
+        for z in 0..4 {
+            for x in 0..4 {
+                let biome = biomes.get(x, y, z).id();
+                let index = match palette.iter().position(|&id| id == biome) {
+                    Some(index) => index,
+                    None if palette.len() < MAX_SECTION_BIOMES => {
+                        palette.push(biome);
+                        palette.len() - 1
+                    }
+                    None => 0,
+                };
+                indices.push(index as u64);
+            }
+        }
+    }
+
+    if palette.len() == 1 {
+        0u8.write(buffer, version)?; // a single value
+        VarInt(palette[0] as i32).write(buffer, version)?;
+        VarInt(0).write(buffer, version)?; // length of data
+        return Ok(());
+    }
+
+    let bits_per_value = (palette.len() as f64).log2().ceil() as usize;
+    (bits_per_value as u8).write(buffer, version)?;
+    VarInt(palette.len() as i32).write(buffer, version)?;
+    for &biome in &palette {
+        VarInt(biome as i32).write(buffer, version)?;
+    }
+    let data = PackedArray::from_iter(indices, bits_per_value);
+    VarInt(data.as_u64_slice().len() as i32).write(buffer, version)?;
+    for &x in data.as_u64_slice() {
+        x.write(buffer, version)?;
+    }
+    Ok(())
+}
+
 fn build_heightmaps(chunk: &Chunk) -> Heightmaps {
     let mut motion_blocking = [0; 37];
     let chunk_motion_blocking = chunk.heightmaps().motion_blocking.as_u64_slice();
@@ -131,12 +231,26 @@ fn encode_section(
 
     if let Some(palette) = section.blocks().palette() {
         VarInt(palette.len() as i32).write(buffer, version)?;
-        for &block in palette.as_slice() {
-            VarInt(block.vanilla_id() as i32).write(buffer, version)?;
+        for block in palette.as_slice() {
+            block.write(buffer, version)?;
         }
     }
 
-    let data = section.blocks().data().as_u64_slice();
+    let data = section.blocks().data();
+    // Without a palette, the data holds block state IDs
+    // which have to be translated one by one.
+    let translated;
+    let data = match mapping::get(version) {
+        Some(mappings) if section.blocks().palette().is_none() => {
+            translated = PackedArray::from_iter(
+                data.iter()
+                    .map(|id| mappings.block_state_to_wire(id as u16) as u64),
+                data.bits_per_value(),
+            );
+            translated.as_u64_slice()
+        }
+        _ => data.as_u64_slice(),
+    };
     VarInt(data.len() as i32).write(buffer, version)?;
     for &x in data {
         x.write(buffer, version)?;
@@ -150,6 +264,10 @@ impl Readable for ChunkData {
     where
         Self: Sized,
     {
+        if version != ProtocolVersion::INTERNAL {
+            bail!("reading chunk data of {} isn't supported", version.name());
+        }
+
         let chunk_x = i32::read(buffer, version)?;
         let chunk_z = i32::read(buffer, version)?;
 
@@ -213,8 +331,7 @@ impl Readable for ChunkData {
                         if let Some(pallete) = section.blocks_mut().palette_mut() {
                             let pallete_length = VarInt::read(buffer, version)?.0 as usize;
                             for _ in 0..pallete_length {
-                                let block_id = VarInt::read(buffer, version)?.0;
-                                pallete.index_or_insert(BlockId::from_vanilla_id(block_id as u16));
+                                pallete.index_or_insert(BlockId::read(buffer, version)?);
                             }
                         }
                     }
@@ -278,3 +395,76 @@ where
     let visitor = MaxVisitor(PhantomData);
     deserializer.deserialize_seq(visitor)
 }
+
+#[cfg(test)]
+mod tests {
+    use std::io::Cursor;
+
+    use super::*;
+
+    /// Reads a paletted container, returning its bits per
+    /// value, palette and number of longs of data.
+    fn read_container(buffer: &mut Cursor<&[u8]>) -> (u8, Vec<i32>, usize) {
+        let version = ProtocolVersion::V1_18_2;
+        let bits = u8::read(buffer, version).unwrap();
+        let palette_length = if bits == 0 {
+            1
+        } else {
+            VarInt::read(buffer, version).unwrap().0
+        };
+        let palette = (0..palette_length)
+            .map(|_| VarInt::read(buffer, version).unwrap().0)
+            .collect();
+        let data_length = VarInt::read(buffer, version).unwrap().0 as usize;
+        for _ in 0..data_length {
+            u64::read(buffer, version).unwrap();
+        }
+        (bits, palette, data_length)
+    }
+
+    #[test]
+    fn writes_every_section_with_its_biomes_from_1_18() {
+        let version = ProtocolVersion::V1_18_2;
+        let mut chunk = Chunk::new(ChunkPosition::new(1, 2));
+        chunk.set_block_at(0, 0, 0, BlockId::stone()).unwrap();
+        chunk.biomes_mut().set(0, 0, 0, Biome::Desert);
+        let packet = ChunkData {
+            chunk: Arc::new(ChunkLock::new(chunk, true)),
+            kind: ChunkDataKind::LoadChunk,
+        };
+
+        let mut bytes = Vec::new();
+        packet.write(&mut bytes, version).unwrap();
+
+        let mut buffer = Cursor::new(bytes.as_slice());
+        assert_eq!(i32::read(&mut buffer, version).unwrap(), 1);
+        assert_eq!(i32::read(&mut buffer, version).unwrap(), 2);
+        Nbt::<Heightmaps>::read(&mut buffer, version).unwrap();
+        VarInt::read(&mut buffer, version).unwrap(); // Size of following array
+
+        for y in 0..16 {
+            let non_air_blocks = u16::read(&mut buffer, version).unwrap();
+            let (_, blocks, _) = read_container(&mut buffer);
+            let biomes = read_container(&mut buffer);
+            if y == 0 {
+                assert_eq!(non_air_blocks, 1);
+                assert!(blocks.contains(&(BlockId::stone().vanilla_id() as i32)));
+                assert_eq!(
+                    biomes,
+                    (
+                        1,
+                        vec![Biome::Desert.id() as i32, Biome::Plains.id() as i32],
+                        1
+                    )
+                );
+            } else {
+                assert_eq!(non_air_blocks, 0);
+                assert_eq!(blocks, vec![BlockId::air().vanilla_id() as i32]);
+                assert_eq!(biomes, (0, vec![Biome::Plains.id() as i32], 0));
+            }
+        }
+
+        assert_eq!(VarInt::read(&mut buffer, version).unwrap().0, 0); // Block entities
+        assert!(bool::read(&mut buffer, version).unwrap()); // Light follows
+    }
+}
@@ -0,0 +1,304 @@
+use std::io::Cursor;
+
+use anyhow::bail;
+use base::Gamemode;
+use nbt::Blob;
+use quill_common::components::PreviousGamemode;
+
+use crate::{
+    io::{Nbt, VarInt, VarIntPrefixedVec},
+    ProtocolVersion, Readable, Writeable,
+};
+
+/// The build height of the internal version.
+const WORLD_HEIGHT: i32 = 256;
+
+#[derive(Debug, Clone)]
+pub struct JoinGame {
+    pub entity_id: i32,
+    pub is_hardcore: bool,
+    pub gamemode: Gamemode,
+    pub previous_gamemode: PreviousGamemode, // can be -1 if "not set", otherwise corresponds to a gamemode ID
+    pub world_names: Vec<String>,
+
+    /// The dimension codec of the internal version. Its dimension
+    /// types get the fields other versions require when written.
+    pub dimension_codec: Nbt<Blob>,
+    pub dimension: Nbt<Blob>,
+
+    pub world_name: String,
+    pub hashed_seed: u64,
+    pub max_players: i32,
+    /// Also sent as the simulation distance from 1.18 on.
+    pub view_distance: i32,
+    pub reduced_debug_info: bool,
+    pub enable_respawn_screen: bool,
+
+    pub is_debug: bool,
+    pub is_flat: bool,
+}
+
+impl Readable for JoinGame {
+    fn read(buffer: &mut Cursor<&[u8]>, version: ProtocolVersion) -> anyhow::Result<Self>
+    where
+        Self: Sized,
+    {
+        let entity_id = i32::read(buffer, version)?;
+        let is_hardcore = bool::read(buffer, version)?;
+        let gamemode = Gamemode::read(buffer, version)?;
+        let previous_gamemode = PreviousGamemode::read(buffer, version)?;
+        let world_names = VarIntPrefixedVec::<String>::read(buffer, version)?.into();
+        let dimension_codec = Nbt::read(buffer, version)?;
+        let dimension = Nbt::read(buffer, version)?;
+        let world_name = String::read(buffer, version)?;
+        let hashed_seed = u64::read(buffer, version)?;
+        let max_players = VarInt::read(buffer, version)?.0;
+        let view_distance = VarInt::read(buffer, version)?.0;
+        if version >= ProtocolVersion::V1_18_2 {
+            VarInt::read(buffer, version)?; // Simulation distance
+        }
+        Ok(Self {
+            entity_id,
+            is_hardcore,
+            gamemode,
+            previous_gamemode,
+            world_names,
+            dimension_codec,
+            dimension,
+            world_name,
+            hashed_seed,
+            max_players,
+            view_distance,
+            reduced_debug_info: bool::read(buffer, version)?,
+            enable_respawn_screen: bool::read(buffer, version)?,
+            is_debug: bool::read(buffer, version)?,
+            is_flat: bool::read(buffer, version)?,
+        })
+    }
+}
+
+impl Writeable for JoinGame {
+    fn write(&self, buffer: &mut Vec<u8>, version: ProtocolVersion) -> anyhow::Result<()> {
+        self.entity_id.write(buffer, version)?;
+        self.is_hardcore.write(buffer, version)?;
+        self.gamemode.write(buffer, version)?;
+        self.previous_gamemode.write(buffer, version)?;
+        VarIntPrefixedVec::from(self.world_names.as_slice()).write(buffer, version)?;
+        Nbt(translate_dimension_codec(&self.dimension_codec.0, version)?).write(buffer, version)?;
+        Nbt(translate_dimension_type(&self.dimension.0, version)?).write(buffer, version)?;
+        self.world_name.write(buffer, version)?;
+        self.hashed_seed.write(buffer, version)?;
+        VarInt(self.max_players).write(buffer, version)?;
+        VarInt(self.view_distance).write(buffer, version)?;
+        if version >= ProtocolVersion::V1_18_2 {
+            VarInt(self.view_distance).write(buffer, version)?; // Simulation distance
+        }
+        self.reduced_debug_info.write(buffer, version)?;
+        self.enable_respawn_screen.write(buffer, version)?;
+        self.is_debug.write(buffer, version)?;
+        self.is_flat.write(buffer, version)?;
+        Ok(())
+    }
+}
+
+#[derive(Debug, Clone)]
+pub struct Respawn {
+    /// A dimension type of the internal version. It gets the
+    /// fields other versions require when written.
+    pub dimension: Nbt<Blob>,
+    pub world_name: String,
+    pub hashed_seed: u64,
+    pub gamemode: Gamemode,
+    pub previous_gamemode: PreviousGamemode,
+    pub is_debug: bool,
+    pub is_flat: bool,
+    pub copy_metadata: bool,
+}
+
+impl Readable for Respawn {
+    fn read(buffer: &mut Cursor<&[u8]>, version: ProtocolVersion) -> anyhow::Result<Self>
+    where
+        Self: Sized,
+    {
+        Ok(Self {
+            dimension: Nbt::read(buffer, version)?,
+            world_name: String::read(buffer, version)?,
+            hashed_seed: u64::read(buffer, version)?,
+            gamemode: Gamemode::read(buffer, version)?,
+            previous_gamemode: PreviousGamemode::read(buffer, version)?,
+            is_debug: bool::read(buffer, version)?,
+            is_flat: bool::read(buffer, version)?,
+            copy_metadata: bool::read(buffer, version)?,
+        })
+    }
+}
+
+impl Writeable for Respawn {
+    fn write(&self, buffer: &mut Vec<u8>, version: ProtocolVersion) -> anyhow::Result<()> {
+        Nbt(translate_dimension_type(&self.dimension.0, version)?).write(buffer, version)?;
+        self.world_name.write(buffer, version)?;
+        self.hashed_seed.write(buffer, version)?;
+        self.gamemode.write(buffer, version)?;
+        self.previous_gamemode.write(buffer, version)?;
+        self.is_debug.write(buffer, version)?;
+        self.is_flat.write(buffer, version)?;
+        self.copy_metadata.write(buffer, version)?;
+        Ok(())
+    }
+}
+
+/// Gets the fields the dimension types of `version` have in addition to,
+/// or instead of, those of the internal version, given the `infiniburn`
+/// field of the internal dimension type.
+fn dimension_type_changes(
+    infiniburn: Option<&nbt::Value>,
+    version: ProtocolVersion,
+) -> Vec<(&'static str, nbt::Value)> {
+    let mut changes = Vec::new();
+    if version >= ProtocolVersion::V1_17_1 {
+        changes.push(("min_y", nbt::Value::Int(0)));
+        changes.push(("height", nbt::Value::Int(WORLD_HEIGHT)));
+    }
+    if version >= ProtocolVersion::V1_18_2 {
+        // The block tag is referenced like any other tag
+        if let Some(nbt::Value::String(tag)) = infiniburn {
+            changes.push(("infiniburn", nbt::Value::String(format!("#{}", tag))));
+        }
+    }
+    changes
+}
+
+/// Translates a dimension type of the internal version to `version`.
+fn translate_dimension_type(dimension: &Blob, version: ProtocolVersion) -> anyhow::Result<Blob> {
+    let mut dimension = dimension.clone();
+    for (name, value) in dimension_type_changes(dimension.get("infiniburn"), version) {
+        dimension.insert(name, value)?;
+    }
+    Ok(dimension)
+}
+
+/// Translates the dimension types of a dimension codec
+/// of the internal version to `version`.
+fn translate_dimension_codec(codec: &Blob, version: ProtocolVersion) -> anyhow::Result<Blob> {
+    if version == ProtocolVersion::INTERNAL {
+        return Ok(codec.clone());
+    }
+
+    let mut registry = match codec.get("minecraft:dimension_type") {
+        Some(nbt::Value::Compound(registry)) => registry.clone(),
+        _ => bail!("dimension codec has no dimension type registry"),
+    };
+    if let Some(nbt::Value::List(types)) = registry.get_mut("value") {
+        for entry in types {
+            if let nbt::Value::Compound(entry) = entry {
+                if let Some(nbt::Value::Compound(element)) = entry.get_mut("element") {
+                    for (name, value) in dimension_type_changes(element.get("infiniburn"), version)
+                    {
+                        element.insert(name.to_owned(), value);
+                    }
+                }
+            }
+        }
+    }
+
+    let mut codec = codec.clone();
+    codec.insert("minecraft:dimension_type", nbt::Value::Compound(registry))?;
+    Ok(codec)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn overworld() -> Blob {
+        let mut dimension = Blob::new();
+        dimension.insert("logical_height", 256).unwrap();
+        dimension
+            .insert("infiniburn", "minecraft:infiniburn_overworld")
+            .unwrap();
+        dimension
+    }
+
+    fn compound(fields: Vec<(&str, nbt::Value)>) -> nbt::Value {
+        nbt::Value::Compound(
+            fields
+                .into_iter()
+                .map(|(name, value)| (name.to_owned(), value))
+                .collect(),
+        )
+    }
+
+    fn codec() -> Blob {
+        let element = compound(vec![
+            ("logical_height", nbt::Value::Int(256)),
+            (
+                "infiniburn",
+                nbt::Value::String("minecraft:infiniburn_overworld".to_owned()),
+            ),
+        ]);
+        let entry = compound(vec![
+            ("name", nbt::Value::String("minecraft:overworld".to_owned())),
+            ("element", element),
+        ]);
+
+        let mut codec = Blob::new();
+        codec
+            .insert(
+                "minecraft:dimension_type",
+                compound(vec![("value", nbt::Value::List(vec![entry]))]),
+            )
+            .unwrap();
+        codec
+    }
+
+    /// Gets the dimension type of a codec built by `codec`.
+    fn codec_element(codec: &Blob) -> Blob {
+        let mut blob = Blob::new();
+        if let Some(nbt::Value::Compound(registry)) = codec.get("minecraft:dimension_type") {
+            if let Some(nbt::Value::List(types)) = registry.get("value") {
+                if let nbt::Value::Compound(entry) = &types[0] {
+                    if let Some(nbt::Value::Compound(element)) = entry.get("element") {
+                        for (name, value) in element {
+                            blob.insert(name.as_str(), value.clone()).unwrap();
+                        }
+                    }
+                }
+            }
+        }
+        blob
+    }
+
+    #[test]
+    fn internal_dimension_types_are_unchanged() {
+        let version = ProtocolVersion::INTERNAL;
+        assert_eq!(
+            translate_dimension_type(&overworld(), version).unwrap(),
+            overworld()
+        );
+        assert_eq!(
+            codec_element(&translate_dimension_codec(&codec(), version).unwrap()),
+            overworld()
+        );
+    }
+
+    #[test]
+    fn dimension_types_get_fields_of_later_versions() {
+        for &version in &[ProtocolVersion::V1_17_1, ProtocolVersion::V1_18_2] {
+            let dimension = translate_dimension_type(&overworld(), version).unwrap();
+            let element = codec_element(&translate_dimension_codec(&codec(), version).unwrap());
+            assert_eq!(dimension, element);
+
+            assert_eq!(dimension.get("min_y"), Some(&nbt::Value::Int(0)));
+            assert_eq!(dimension.get("height"), Some(&nbt::Value::Int(256)));
+            let infiniburn = if version >= ProtocolVersion::V1_18_2 {
+                "#minecraft:infiniburn_overworld"
+            } else {
+                "minecraft:infiniburn_overworld"
+            };
+            assert_eq!(
+                dimension.get("infiniburn"),
+                Some(&nbt::Value::String(infiniburn.to_owned()))
+            );
+        }
+    }
+}
@@ -1,5 +1,6 @@
 use std::{fmt::Debug, sync::Arc};
 
+use anyhow::bail;
 use base::{chunk::PackedArray, Chunk, ChunkHandle, ChunkLock, ChunkPosition, ChunkSection};
 
 use crate::{io::VarInt, ProtocolVersion, Readable, Writeable};
@@ -22,31 +23,71 @@ impl Writeable for UpdateLight {
         let chunk = self.chunk.read();
         VarInt(chunk.position().x).write(buffer, version)?;
         VarInt(chunk.position().z).write(buffer, version)?;
+        write_light(&chunk, buffer, version)
+    }
+}
 
-        true.write(buffer, version)?; // trust edges?
-
-        let mut mask = 0;
-        for (y, section) in chunk.sections().iter().enumerate() {
-            if section.is_some() {
-                mask |= 1 << y;
-            }
+/// Writes the light of `chunk` in the layout shared by `UpdateLight`
+/// and, from 1.18 on, `ChunkData`.
+pub(super) fn write_light(
+    chunk: &Chunk,
+    buffer: &mut Vec<u8>,
+    version: ProtocolVersion,
+) -> anyhow::Result<()> {
+    true.write(buffer, version)?; // trust edges?
+
+    let mut mask = 0;
+    for (y, section) in chunk.sections().iter().enumerate() {
+        if section.is_some() {
+            mask |= 1 << y;
         }
+    }
 
+    if version < ProtocolVersion::V1_17_1 {
         VarInt(mask).write(buffer, version)?; // sky light mask
         VarInt(mask).write(buffer, version)?; // block light mask
 
         VarInt(!mask).write(buffer, version)?; // empty sky light mask
         VarInt(!mask).write(buffer, version)?; // empty block light mask
+    } else {
+        // From 1.17 on, masks are bit sets covering only existing sections
+        let empty_mask = !mask & ((1 << chunk.sections().len()) - 1);
+        write_bit_set(mask as u64, buffer, version)?; // sky light mask
+        write_bit_set(mask as u64, buffer, version)?; // block light mask
+
+        write_bit_set(empty_mask as u64, buffer, version)?; // empty sky light mask
+        write_bit_set(empty_mask as u64, buffer, version)?; // empty block light mask
+    }
 
-        for section in chunk.sections().iter().flatten() {
-            encode_light(section.light().sky_light(), buffer, version);
-        }
+    let sections = chunk.sections().iter().flatten();
+    if version >= ProtocolVersion::V1_17_1 {
+        VarInt(sections.clone().count() as i32).write(buffer, version)?;
+    }
+    for section in sections.clone() {
+        encode_light(section.light().sky_light(), buffer, version);
+    }
 
-        for section in chunk.sections().iter().flatten() {
-            encode_light(section.light().block_light(), buffer, version);
-        }
+    if version >= ProtocolVersion::V1_17_1 {
+        VarInt(sections.clone().count() as i32).write(buffer, version)?;
+    }
+    for section in sections {
+        encode_light(section.light().block_light(), buffer, version);
+    }
 
-        Ok(())
+    Ok(())
+}
+
+/// Writes a bit set of at most 64 bits as an array of longs.
+pub(super) fn write_bit_set(
+    bits: u64,
+    buffer: &mut Vec<u8>,
+    version: ProtocolVersion,
+) -> anyhow::Result<()> {
+    if bits == 0 {
+        VarInt(0).write(buffer, version)
+    } else {
+        VarInt(1).write(buffer, version)?;
+        bits.write(buffer, version)
     }
 }
 
@@ -65,6 +106,10 @@ impl Readable for UpdateLight {
     where
         Self: Sized,
     {
+        if version != ProtocolVersion::INTERNAL {
+            bail!("reading light of {} isn't supported", version.name());
+        }
+
         let mut chunk = Chunk::new(ChunkPosition {
             x: VarInt::read(buffer, version)?.0,
             z: VarInt::read(buffer, version)?.0,
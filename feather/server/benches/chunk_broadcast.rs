@@ -0,0 +1,157 @@
+//! Measures sending packets to 100 clients:
+//! * a chunk, encoded for each client versus encoded once
+//! and shared through a `SharedChunk`;
+//! * a tick's worth of small packets, written one at a time
+//! versus batched into a single write per client.
+//!
+//! Run with `cargo bench -p feather-server --bench chunk_broadcast`.
+
+use std::{
+    io::{self, Write},
+    net::{TcpListener, TcpStream},
+    sync::Arc,
+    thread,
+    time::{Duration, Instant},
+};
+
+use base::{BlockId, Chunk, ChunkHandle, ChunkLock, ChunkPosition, CHUNK_WIDTH};
+use feather_server::chunk_packets::SharedChunk;
+use protocol::{
+    packets::server::{ChunkData, ChunkDataKind, KeepAlive, UpdateLight},
+    MinecraftCodec, ServerPlayPacket,
+};
+
+const CLIENTS: usize = 100;
+const PACKETS_PER_TICK: usize = 50;
+const ITERATIONS: u32 = 20;
+const COMPRESSION_THRESHOLD: usize = 256;
+
+fn main() -> anyhow::Result<()> {
+    let chunk = test_chunk();
+    let mut codecs: Vec<MinecraftCodec> = (0..CLIENTS)
+        .map(|i| {
+            let mut codec = MinecraftCodec::new();
+            codec.enable_compression(COMPRESSION_THRESHOLD);
+            codec.enable_encryption([i as u8; 16]);
+            codec
+        })
+        .collect();
+
+    let per_client = measure(|| encode_per_client(&chunk, &mut codecs))?;
+    let shared = measure(|| encode_shared(&chunk, &mut codecs))?;
+    println!("Sending a chunk to {} clients:", CLIENTS);
+    println!("  encoded per client: {:?}", per_client);
+    println!("  encoded once:       {:?}", shared);
+
+    let mut streams = connect_clients()?;
+    let unbatched = measure(|| write_packets(&mut codecs, &mut streams, false))?;
+    let batched = measure(|| write_packets(&mut codecs, &mut streams, true))?;
+    println!(
+        "Sending {} packets to {} clients:",
+        PACKETS_PER_TICK, CLIENTS
+    );
+    println!(
+        "  unbatched: {:?} ({} writes)",
+        unbatched,
+        CLIENTS * PACKETS_PER_TICK
+    );
+    println!("  batched:   {:?} ({} writes)", batched, CLIENTS);
+
+    Ok(())
+}
+
+/// Runs `f` `ITERATIONS` times and returns the mean duration.
+fn measure(mut f: impl FnMut() -> anyhow::Result<()>) -> anyhow::Result<Duration> {
+    let start = Instant::now();
+    for _ in 0..ITERATIONS {
+        f()?;
+    }
+    Ok(start.elapsed() / ITERATIONS)
+}
+
+fn test_chunk() -> ChunkHandle {
+    let mut chunk = Chunk::new(ChunkPosition::new(0, 0));
+    let blocks = [
+        BlockId::stone(),
+        BlockId::andesite(),
+        BlockId::cobblestone(),
+        BlockId::air(),
+    ];
+    for y in 0..64 {
+        for x in 0..CHUNK_WIDTH {
+            for z in 0..CHUNK_WIDTH {
+                chunk.set_block_at(x, y, z, blocks[(x * 7 + y * 3 + z) % blocks.len()]);
+            }
+        }
+    }
+    Arc::new(ChunkLock::new(chunk, true))
+}
+
+fn encode_per_client(chunk: &ChunkHandle, codecs: &mut [MinecraftCodec]) -> anyhow::Result<()> {
+    let mut buffer = Vec::new();
+    for codec in codecs {
+        codec.encode(
+            &ServerPlayPacket::from(UpdateLight {
+                chunk: Arc::clone(chunk),
+            }),
+            &mut buffer,
+        )?;
+        codec.encode(
+            &ServerPlayPacket::from(ChunkData {
+                chunk: Arc::clone(chunk),
+                kind: ChunkDataKind::LoadChunk,
+            }),
+            &mut buffer,
+        )?;
+        buffer.clear();
+    }
+    Ok(())
+}
+
+fn encode_shared(chunk: &ChunkHandle, codecs: &mut [MinecraftCodec]) -> anyhow::Result<()> {
+    let mut buffer = Vec::new();
+    let shared = SharedChunk::new(ChunkPosition::new(0, 0), Arc::clone(chunk));
+    for codec in codecs {
+        let frames = shared.encoded(codec)?;
+        codec.write_frames(&frames, &mut buffer);
+        buffer.clear();
+    }
+    Ok(())
+}
+
+/// Opens a loopback connection for each client. The
+/// receiving ends discard everything they read.
+fn connect_clients() -> anyhow::Result<Vec<TcpStream>> {
+    let listener = TcpListener::bind("127.0.0.1:0")?;
+    let address = listener.local_addr()?;
+    let mut streams = Vec::with_capacity(CLIENTS);
+    for _ in 0..CLIENTS {
+        streams.push(TcpStream::connect(address)?);
+        let (mut receiver, _) = listener.accept()?;
+        thread::spawn(move || io::copy(&mut receiver, &mut io::sink()));
+    }
+    Ok(streams)
+}
+
+fn write_packets(
+    codecs: &mut [MinecraftCodec],
+    streams: &mut [TcpStream],
+    batch: bool,
+) -> anyhow::Result<()> {
+    let mut buffer = Vec::new();
+    for (codec, stream) in codecs.iter_mut().zip(streams) {
+        for id in 0..PACKETS_PER_TICK {
+            codec.encode(
+                &ServerPlayPacket::from(KeepAlive { id: id as i64 }),
+                &mut buffer,
+            )?;
+            if !batch {
+                stream.write_all(&buffer)?;
+                buffer.clear();
+            }
+        }
+        stream.write_all(&buffer)?;
+        buffer.clear();
+    }
+    Ok(())
+}
@@ -0,0 +1,71 @@
+//! Authentication of players joining an online-mode server.
+
+use std::{fmt::Debug, time::Duration};
+
+use anyhow::bail;
+use base::ProfileProperty;
+use serde::Deserialize;
+use uuid::Uuid;
+
+/// The session server used by the vanilla server.
+pub const MOJANG_SESSION_SERVER: &str = "https://sessionserver.mojang.com";
+
+/// The profile of an authenticated player.
+#[derive(Debug, Clone, Deserialize)]
+pub struct AuthResponse {
+    pub id: Uuid,
+    pub name: String,
+    pub properties: Vec<ProfileProperty>,
+}
+
+/// Verifies that a player logging in has announced
+/// the join to a session server, proving they own the account.
+///
+/// Called on a blocking thread.
+pub trait Authenticator: Debug + Send + Sync {
+    /// Returns the profile of `username` if they joined
+    /// the server identified by `server_hash`.
+    fn has_joined(&self, username: &str, server_hash: &str) -> anyhow::Result<AuthResponse>;
+}
+
+/// An [`Authenticator`] querying the `hasJoined`
+/// endpoint of a Yggdrasil-compatible session server.
+#[derive(Debug, Clone)]
+pub struct SessionServerAuthenticator {
+    base_url: String,
+    timeout: Duration,
+}
+
+impl SessionServerAuthenticator {
+    /// Creates an authenticator for the session server at `base_url`,
+    /// e.g. [`MOJANG_SESSION_SERVER`], giving up on requests after `timeout`.
+    pub fn new(base_url: impl Into<String>, timeout: Duration) -> Self {
+        Self {
+            base_url: base_url.into(),
+            timeout,
+        }
+    }
+}
+
+impl Authenticator for SessionServerAuthenticator {
+    fn has_joined(&self, username: &str, server_hash: &str) -> anyhow::Result<AuthResponse> {
+        let url = format!(
+            "{}/session/minecraft/hasJoined",
+            self.base_url.trim_end_matches('/')
+        );
+        let response = ureq::AgentBuilder::new()
+            .timeout(self.timeout)
+            .build()
+            .get(&url)
+            .query("username", username)
+            .query("serverId", server_hash)
+            .call()?;
+
+        // The session server responds with no content
+        // if the player didn't join.
+        if response.status() == 204 {
+            bail!("{} has not joined this server", username);
+        }
+        Ok(response.into_json()?)
+    }
+}
@@ -0,0 +1,145 @@
+//! Encodes the packets of a chunk once and shares them
+//! between all clients the chunk is sent to.
+
+use std::sync::Arc;
+
+use ahash::AHashMap;
+use base::{ChunkHandle, ChunkPosition};
+use common::{events::BlockChangeEvent, Game, WorldId};
+use ecs::{SysResult, SystemExecutor};
+use parking_lot::Mutex;
+use protocol::{
+    codec::CompressionThreshold,
+    packets::server::{ChunkData, ChunkDataKind, UpdateLight},
+    MinecraftCodec, ProtocolVersion, ServerPlayPacket,
+};
+
+use crate::Server;
+
+/// The codec settings which determine how a packet is encoded.
+type CodecSettings = (ProtocolVersion, Option<CompressionThreshold>);
+
+/// The `UpdateLight` and `ChunkData` packets of a chunk, or only
+/// `ChunkData` for versions where it includes the light.
+///
+/// The packets are encoded by the first connection which sends them
+/// and reused by every connection with the same protocol version
+/// and compression threshold, until the chunk's blocks change.
+pub struct SharedChunk {
+    position: ChunkPosition,
+    chunk: ChunkHandle,
+    frames: Mutex<Vec<(CodecSettings, Arc<[u8]>)>>,
+}
+
+impl SharedChunk {
+    pub fn new(position: ChunkPosition, chunk: ChunkHandle) -> Self {
+        Self {
+            position,
+            chunk,
+            frames: Mutex::new(Vec::new()),
+        }
+    }
+
+    pub fn position(&self) -> ChunkPosition {
+        self.position
+    }
+
+    pub fn chunk(&self) -> &ChunkHandle {
+        &self.chunk
+    }
+
+    /// Gets the packets encoded with `codec`'s settings, encoding
+    /// them if no connection with the same settings has yet.
+    ///
+    /// The returned frames are not encrypted; write them
+    /// with [`MinecraftCodec::write_frames`].
+    pub fn encoded(&self, codec: &mut MinecraftCodec) -> anyhow::Result<Arc<[u8]>> {
+        let settings = (codec.version(), codec.compression_threshold());
+
+        // Connections sending the chunk concurrently wait
+        // here instead of encoding it a second time.
+        let mut frames = self.frames.lock();
+        if let Some((_, encoded)) = frames.iter().find(|(s, _)| *s == settings) {
+            return Ok(Arc::clone(encoded));
+        }
+
+        let mut buffer = Vec::new();
+        // From 1.18 on, the chunk data carries the light as well.
+        if codec.version() < ProtocolVersion::V1_18_2 {
+            codec.encode_frame(
+                &ServerPlayPacket::from(UpdateLight {
+                    chunk: Arc::clone(&self.chunk),
+                }),
+                &mut buffer,
+            )?;
+        }
+        codec.encode_frame(
+            &ServerPlayPacket::from(ChunkData {
+                chunk: Arc::clone(&self.chunk),
+                kind: ChunkDataKind::LoadChunk,
+            }),
+            &mut buffer,
+        )?;
+
+        let encoded: Arc<[u8]> = buffer.into();
+        frames.push((settings, Arc::clone(&encoded)));
+        Ok(encoded)
+    }
+
+    /// Discards the encoded packets after the chunk has changed.
+    pub fn invalidate(&self) {
+        self.frames.lock().clear();
+    }
+}
+
+/// The [`SharedChunk`]s of loaded chunks.
+#[derive(Default)]
+pub struct ChunkPackets {
+    chunks: AHashMap<(WorldId, ChunkPosition), Arc<SharedChunk>>,
+}
+
+impl ChunkPackets {
+    /// Gets the shared packets of the chunk at `pos`.
+    pub fn get(
+        &mut self,
+        world: WorldId,
+        pos: ChunkPosition,
+        chunk: &ChunkHandle,
+    ) -> Arc<SharedChunk> {
+        let shared = self
+            .chunks
+            .entry((world, pos))
+            .or_insert_with(|| Arc::new(SharedChunk::new(pos, Arc::clone(chunk))));
+        // The chunk may have been unloaded and loaded again since.
+        if !Arc::ptr_eq(shared.chunk(), chunk) {
+            *shared = Arc::new(SharedChunk::new(pos, Arc::clone(chunk)));
+        }
+        Arc::clone(shared)
+    }
+}
+
+pub fn register(systems: &mut SystemExecutor<Game>) {
+    systems
+        .group::<Server>()
+        .add_system(invalidate_changed_chunks)
+        .add_system(remove_unloaded_chunks);
+}
+
+fn invalidate_changed_chunks(game: &mut Game, server: &mut Server) -> SysResult {
+    for (_, event) in game.ecs.query::<&BlockChangeEvent>().iter() {
+        for (chunk, _, _) in event.iter_affected_chunk_sections() {
+            if let Some(shared) = server.chunk_packets.chunks.get(&(event.world(), chunk)) {
+                shared.invalidate();
+            }
+        }
+    }
+    Ok(())
+}
+
+fn remove_unloaded_chunks(_game: &mut Game, server: &mut Server) -> SysResult {
+    server
+        .chunk_packets
+        .chunks
+        .retain(|_, shared| shared.chunk().is_loaded());
+    Ok(())
+}
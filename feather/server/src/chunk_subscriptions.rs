@@ -1,6 +1,6 @@
 use ahash::AHashMap;
 use base::ChunkPosition;
-use common::{events::ViewUpdateEvent, view::View, Game};
+use common::{events::ViewUpdateEvent, view::View, Game, WorldId};
 use ecs::{SysResult, SystemExecutor};
 use quill_common::events::EntityRemoveEvent;
 use utils::vec_remove_item;
@@ -11,13 +11,13 @@ use crate::{ClientId, Server};
 /// receive updates from a given chunk, fast.
 #[derive(Default)]
 pub struct ChunkSubscriptions {
-    chunks: AHashMap<ChunkPosition, Vec<ClientId>>,
+    chunks: AHashMap<(WorldId, ChunkPosition), Vec<ClientId>>,
 }
 
 impl ChunkSubscriptions {
-    pub fn subscriptions_for(&self, chunk: ChunkPosition) -> &[ClientId] {
+    pub fn subscriptions_for(&self, world: WorldId, chunk: ChunkPosition) -> &[ClientId] {
         self.chunks
-            .get(&chunk)
+            .get(&(world, chunk))
             .map(Vec::as_slice)
             .unwrap_or_default()
     }
@@ -32,16 +32,18 @@ pub fn register(systems: &mut SystemExecutor<Game>) {
 fn update_chunk_subscriptions(game: &mut Game, server: &mut Server) -> SysResult {
     // Update players whose views have changed
     for (_, (event, &client_id)) in game.ecs.query::<(&ViewUpdateEvent, &ClientId)>().iter() {
+        let new_world = event.new_view.world();
         for new_chunk in event.new_view.difference(event.old_view) {
             server
                 .chunk_subscriptions
                 .chunks
-                .entry(new_chunk)
+                .entry((new_world, new_chunk))
                 .or_default()
                 .push(client_id);
         }
+        let old_world = event.old_view.world();
         for old_chunk in event.old_view.difference(event.new_view) {
-            remove_subscription(server, old_chunk, client_id);
+            remove_subscription(server, old_world, old_chunk, client_id);
         }
     }
 
@@ -52,19 +54,25 @@ fn update_chunk_subscriptions(game: &mut Game, server: &mut Server) -> SysResult
         .iter()
     {
         for chunk in view.iter() {
-            remove_subscription(server, chunk, client_id);
+            remove_subscription(server, view.world(), chunk, client_id);
         }
     }
 
     Ok(())
 }
 
-fn remove_subscription(server: &mut Server, chunk: ChunkPosition, client_id: ClientId) {
-    if let Some(vec) = server.chunk_subscriptions.chunks.get_mut(&chunk) {
+fn remove_subscription(
+    server: &mut Server,
+    world: WorldId,
+    chunk: ChunkPosition,
+    client_id: ClientId,
+) {
+    let key = (world, chunk);
+    if let Some(vec) = server.chunk_subscriptions.chunks.get_mut(&key) {
         vec_remove_item(vec, &client_id);
 
         if vec.is_empty() {
-            server.chunk_subscriptions.chunks.remove(&chunk);
+            server.chunk_subscriptions.chunks.remove(&key);
         }
     }
 }
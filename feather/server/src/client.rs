@@ -3,26 +3,31 @@ use std::{
     collections::VecDeque,
     io::Cursor,
     sync::Arc,
+    time::{Duration, Instant},
 };
 
 use ahash::AHashSet;
 use flume::{Receiver, Sender};
+use once_cell::sync::Lazy;
 use slab::Slab;
 use uuid::Uuid;
 
 use base::{
-    BlockId, ChunkHandle, ChunkPosition, EntityKind, EntityMetadata, Gamemode, Position,
-    ProfileProperty, Text, ValidBlockPosition,
+    BlockId, ChunkHandle, ChunkPosition, Dimension, EntityKind, EntityMetadata, Gamemode, Position,
+    ProfileProperty, StatusEffect, Text, ValidBlockPosition,
 };
 use common::{
     chat::{ChatKind, ChatMessage},
     Window,
 };
 use libcraft_items::InventorySlot;
-use packets::server::{Particle, SetSlot, SpawnLivingEntity, UpdateLight, WindowConfirmation};
+use packets::server::{
+    Particle, SetExperience, SetSlot, SpawnExperienceOrb, SpawnLivingEntity, UpdateHealth,
+    WindowConfirmation,
+};
 use protocol::packets::server::{
-    ChangeGameState, EntityPosition, EntityPositionAndRotation, EntityTeleport, GameStateChange,
-    HeldItemChange, PlayerAbilities,
+    ChangeGameState, EntityEffect, EntityPosition, EntityPositionAndRotation, EntityTeleport,
+    GameStateChange, HeldItemChange, PlayerAbilities, RemoveEntityEffect,
 };
 use protocol::{
     packets::{
@@ -30,23 +35,62 @@ use protocol::{
         server::{
             AddPlayer, Animation, BlockChange, ChatPosition, ChunkData, ChunkDataKind,
             DestroyEntities, Disconnect, EntityAnimation, EntityHeadLook, JoinGame, KeepAlive,
-            PlayerInfo, PlayerPositionAndLook, PluginMessage, SendEntityMetadata, SpawnPlayer,
-            Title, UnloadChunk, UpdateViewPosition, WindowItems,
+            PlayerInfo, PlayerPositionAndLook, PluginMessage, Respawn, SendEntityMetadata,
+            SpawnPlayer, Title, UnloadChunk, UpdateViewDistance, UpdateViewPosition, WindowItems,
         },
     },
     ClientPlayPacket, Nbt, ProtocolVersion, ServerPlayPacket, Writeable,
 };
-use quill_common::components::{OnGround, PreviousGamemode};
+use quill_common::components::{EffectInstance, Experience, Health, OnGround, PreviousGamemode};
 
 use crate::{
+    chunk_packets::SharedChunk,
+    connection_worker::OutgoingPacket,
     entities::{PreviousOnGround, PreviousPosition},
     initial_handler::NewPlayer,
     network_id_registry::NetworkId,
     Options,
 };
 
-/// Max number of chunks to send to a client per tick.
-const MAX_CHUNKS_PER_TICK: usize = 10;
+/// The dimension codec sent by the default vanilla server. (Data acquired via tools/proxy)
+static DIMENSION_CODEC: Lazy<nbt::Blob> = Lazy::new(|| {
+    nbt::Blob::from_reader(&mut Cursor::new(include_bytes!(
+        "../../../assets/dimension_codec.nbt"
+    )))
+    .expect("dimension codec asset is malformed")
+});
+
+/// Returns the entry of the dimension codec describing `dimension`.
+fn dimension_type(dimension: Dimension) -> nbt::Blob {
+    let types = match DIMENSION_CODEC.get("minecraft:dimension_type") {
+        Some(nbt::Value::Compound(registry)) => match registry.get("value") {
+            Some(nbt::Value::List(types)) => types,
+            _ => panic!("dimension codec has no dimension types"),
+        },
+        _ => panic!("dimension codec has no dimension type registry"),
+    };
+    let element = types
+        .iter()
+        .find_map(|entry| match entry {
+            nbt::Value::Compound(entry) => match (entry.get("name"), entry.get("element")) {
+                (Some(nbt::Value::String(name)), Some(nbt::Value::Compound(element)))
+                    if name == dimension.namespaced_id() =>
+                {
+                    Some(element)
+                }
+                _ => None,
+            },
+            _ => None,
+        })
+        .unwrap_or_else(|| panic!("dimension codec is missing {:?}", dimension));
+
+    let mut blob = nbt::Blob::new();
+    for (name, value) in element {
+        blob.insert(name.as_str(), value.clone())
+            .expect("invalid dimension type");
+    }
+    blob
+}
 
 /// ID of a client. Can be reused.
 #[derive(Copy, Clone, Debug, PartialEq, Eq, Hash)]
@@ -89,7 +133,7 @@ impl Clients {
 /// This struct provides methods to send packets
 /// to the client.
 pub struct Client {
-    packets_to_send: Sender<ServerPlayPacket>,
+    packets_to_send: Sender<OutgoingPacket>,
     received_packets: Receiver<ClientPlayPacket>,
     options: Arc<Options>,
     username: String,
@@ -104,13 +148,22 @@ pub struct Client {
     knows_position: Cell<bool>,
     known_chunks: RefCell<AHashSet<ChunkPosition>>,
 
-    chunk_send_queue: RefCell<VecDeque<ChunkData>>,
+    /// Chunks waiting to be sent, nearest first
+    /// unless `chunk_queue_unsorted` is set.
+    chunk_send_queue: RefCell<VecDeque<Arc<SharedChunk>>>,
+    chunk_queue_unsorted: Cell<bool>,
+    /// The chunk the client's view is centered on.
+    own_chunk: Cell<ChunkPosition>,
 
     /// The previous own position sent by the client.
     /// Used to detect when we need to teleport the client.
     client_known_position: Cell<Option<Position>>,
 
     disconnected: Cell<bool>,
+
+    /// The ID of the keepalive awaiting a
+    /// reply, and when it was sent.
+    pending_keepalive: Cell<Option<(i64, Instant)>>,
 }
 
 impl Client {
@@ -128,8 +181,11 @@ impl Client {
             knows_position: Cell::new(false),
             known_chunks: RefCell::new(AHashSet::new()),
             chunk_send_queue: RefCell::new(VecDeque::new()),
+            chunk_queue_unsorted: Cell::new(false),
+            own_chunk: Cell::new(ChunkPosition::new(0, 0)),
             client_known_position: Cell::new(None),
             disconnected: Cell::new(false),
+            pending_keepalive: Cell::new(None),
         }
     }
 
@@ -173,17 +229,25 @@ impl Client {
         self.knows_position.get()
     }
 
+    /// Sends the queued chunks nearest to the
+    /// client, up to the per-tick budget.
     pub fn tick(&self) {
-        let num_to_send = MAX_CHUNKS_PER_TICK.min(self.chunk_send_queue.borrow().len());
-        for packet in self.chunk_send_queue.borrow_mut().drain(0..num_to_send) {
+        let mut queue = self.chunk_send_queue.borrow_mut();
+        if self.chunk_queue_unsorted.replace(false) {
+            let center = self.own_chunk.get();
+            queue
+                .make_contiguous()
+                .sort_unstable_by_key(|chunk| chunk.position().distance_squared_to(center));
+        }
+
+        let num_to_send = self.options.chunks_per_tick.min(queue.len());
+        for chunk in queue.drain(0..num_to_send) {
             log::trace!(
                 "Sending chunk at {:?} to {}",
-                packet.chunk.read().position(),
+                chunk.position(),
                 self.username
             );
-            let chunk = Arc::clone(&packet.chunk);
-            self.send_packet(UpdateLight { chunk });
-            self.send_packet(packet);
+            let _ = self.packets_to_send.try_send(OutgoingPacket::Chunk(chunk));
         }
     }
 
@@ -197,27 +261,29 @@ impl Client {
         self.network_id = Some(network_id);
     }
 
-    pub fn send_join_game(&self, gamemode: Gamemode, previous_gamemode: PreviousGamemode) {
+    /// Sends `JoinGame`. `worlds` are the dimensions of
+    /// all worlds on the server, and `dimension` the one
+    /// the player joins in.
+    pub fn send_join_game(
+        &self,
+        gamemode: Gamemode,
+        previous_gamemode: PreviousGamemode,
+        worlds: &[Dimension],
+        dimension: Dimension,
+    ) {
         log::trace!("Sending Join Game to {}", self.username);
-        // Use the dimension codec sent by the default vanilla server. (Data acquired via tools/proxy)
-        let dimension_codec = nbt::Blob::from_reader(&mut Cursor::new(include_bytes!(
-            "../../../assets/dimension_codec.nbt"
-        )))
-        .expect("dimension codec asset is malformed");
-        let dimension = nbt::Blob::from_reader(&mut Cursor::new(include_bytes!(
-            "../../../assets/dimension.nbt"
-        )))
-        .expect("dimension asset is malformed");
-
         self.send_packet(JoinGame {
             entity_id: self.network_id.expect("No network id! Use client.set_network_id(NetworkId) before calling this method.").0,
             is_hardcore: false,
             gamemode,
             previous_gamemode,
-            world_names: vec!["world".to_owned()],
-            dimension_codec: Nbt(dimension_codec),
-            dimension: Nbt(dimension),
-            world_name: "world".to_owned(),
+            world_names: worlds
+                .iter()
+                .map(|world| world.namespaced_id().to_owned())
+                .collect(),
+            dimension_codec: Nbt(DIMENSION_CODEC.clone()),
+            dimension: Nbt(dimension_type(dimension)),
+            world_name: dimension.namespaced_id().to_owned(),
             hashed_seed: 0,
             max_players: 0,
             view_distance: self.options.view_distance as i32,
@@ -228,6 +294,34 @@ impl Client {
         });
     }
 
+    /// Moves the player to the world of `dimension`.
+    ///
+    /// The client discards its chunks and entities, so they
+    /// are sent again, and the player is respawned once
+    /// enough chunks of the new world arrived.
+    pub fn send_respawn(
+        &self,
+        dimension: Dimension,
+        gamemode: Gamemode,
+        previous_gamemode: PreviousGamemode,
+    ) {
+        log::trace!("Sending Respawn({:?}) to {}", dimension, self.username);
+        self.send_packet(Respawn {
+            dimension: Nbt(dimension_type(dimension)),
+            world_name: dimension.namespaced_id().to_owned(),
+            hashed_seed: 0,
+            gamemode,
+            previous_gamemode,
+            is_debug: false,
+            is_flat: false,
+            copy_metadata: true,
+        });
+        self.known_chunks.borrow_mut().clear();
+        self.chunk_send_queue.borrow_mut().clear();
+        self.sent_entities.borrow_mut().clear();
+        self.knows_position.set(false);
+    }
+
     pub fn send_brand(&self) {
         let mut data = Vec::new();
         "Feather"
@@ -273,16 +367,29 @@ impl Client {
             chunk_x: pos.x,
             chunk_z: pos.z,
         });
+        self.own_chunk.set(pos);
+        self.chunk_queue_unsorted.set(true);
     }
 
-    pub fn send_chunk(&self, chunk: &ChunkHandle) {
-        self.chunk_send_queue.borrow_mut().push_back(ChunkData {
-            chunk: Arc::clone(chunk),
-            kind: ChunkDataKind::LoadChunk,
+    pub fn update_view_distance(&self, view_distance: u32) {
+        log::trace!(
+            "Updating view distance of {} to {}",
+            self.username,
+            view_distance
+        );
+        self.send_packet(UpdateViewDistance {
+            view_distance: view_distance as i32,
         });
-        self.known_chunks
+    }
+
+    /// Queues a chunk to be sent. Chunks are sent in
+    /// [`tick`](Client::tick), nearest to the client first.
+    pub fn send_chunk(&self, chunk: &Arc<SharedChunk>) {
+        self.chunk_send_queue
             .borrow_mut()
-            .insert(chunk.read().position());
+            .push_back(Arc::clone(chunk));
+        self.chunk_queue_unsorted.set(true);
+        self.known_chunks.borrow_mut().insert(chunk.position());
     }
 
     pub fn overwrite_chunk_sections(&self, chunk: &ChunkHandle, sections: Vec<usize>) {
@@ -301,11 +408,18 @@ impl Client {
 
     pub fn unload_chunk(&self, pos: ChunkPosition) {
         log::trace!("Unloading chunk at {:?} on {}", pos, self.username);
-        self.send_packet(UnloadChunk {
-            chunk_x: pos.x,
-            chunk_z: pos.z,
-        });
         self.known_chunks.borrow_mut().remove(&pos);
+
+        // Chunks which are still queued were never sent.
+        let mut queue = self.chunk_send_queue.borrow_mut();
+        let queued = queue.len();
+        queue.retain(|chunk| chunk.position() != pos);
+        if queue.len() == queued {
+            self.send_packet(UnloadChunk {
+                chunk_x: pos.x,
+                chunk_z: pos.z,
+            });
+        }
     }
 
     pub fn add_tablist_player(
@@ -314,6 +428,7 @@ impl Client {
         name: String,
         profile: &[ProfileProperty],
         gamemode: Gamemode,
+        ping: i32,
     ) {
         log::trace!("Sending AddPlayer({}) to {}", name, self.username);
         let action = AddPlayer {
@@ -321,7 +436,7 @@ impl Client {
             name,
             properties: profile.to_vec(),
             gamemode,
-            ping: 0,
+            ping,
             display_name: None,
         };
         self.send_packet(PlayerInfo::AddPlayers(vec![action]));
@@ -332,6 +447,10 @@ impl Client {
         self.send_packet(PlayerInfo::RemovePlayers(vec![uuid]));
     }
 
+    pub fn update_tablist_pings(&self, pings: Vec<(Uuid, i32)>) {
+        self.send_packet(PlayerInfo::UpdatePings(pings));
+    }
+
     pub fn change_player_tablist_gamemode(&self, uuid: Uuid, gamemode: Gamemode) {
         self.send_packet(PlayerInfo::UpdateGamemodes(vec![(uuid, gamemode)]));
     }
@@ -388,6 +507,16 @@ impl Client {
         });
     }
 
+    pub fn send_experience_orb(&self, network_id: NetworkId, pos: Position, value: u32) {
+        self.send_packet(SpawnExperienceOrb {
+            entity_id: network_id.0,
+            x: pos.x,
+            y: pos.y,
+            z: pos.z,
+            count: value.min(u16::MAX as u32) as u16,
+        });
+    }
+
     pub fn update_entity_position(
         &self,
         network_id: NetworkId,
@@ -451,9 +580,37 @@ impl Client {
         }
     }
 
+    /// Sends a keepalive, unless the client
+    /// has yet to reply to the previous one.
     pub fn send_keepalive(&self) {
+        if self.pending_keepalive.get().is_some() {
+            return;
+        }
         log::trace!("Sending keepalive to {}", self.username);
-        self.send_packet(KeepAlive { id: 0 });
+        let id: i64 = rand::random();
+        self.pending_keepalive.set(Some((id, Instant::now())));
+        self.send_packet(KeepAlive { id });
+    }
+
+    /// Handles the client's reply to a keepalive,
+    /// returning the round-trip time if the ID is the expected one.
+    pub fn handle_keepalive(&self, id: u64) -> Option<Duration> {
+        match self.pending_keepalive.get() {
+            Some((expected, sent_at)) if expected == id as i64 => {
+                self.pending_keepalive.set(None);
+                Some(sent_at.elapsed())
+            }
+            _ => None,
+        }
+    }
+
+    /// Determines whether the client failed to reply
+    /// to a keepalive within `timeout`.
+    pub fn is_keepalive_timed_out(&self, timeout: Duration) -> bool {
+        match self.pending_keepalive.get() {
+            Some((_, sent_at)) => sent_at.elapsed() > timeout,
+            None => false,
+        }
     }
 
     pub fn send_entity_animation(&self, network_id: NetworkId, animation: Animation) {
@@ -534,6 +691,7 @@ impl Client {
         let packet = WindowItems {
             window_id: 0,
             items: window.inner().to_vec(),
+            carried_item: window.cursor_item().clone(),
         };
         self.send_packet(packet);
     }
@@ -607,6 +765,51 @@ impl Client {
         });
     }
 
+    pub fn send_entity_effect(&self, network_id: NetworkId, effect: &EffectInstance) {
+        let mut flags = 0;
+        if effect.ambient {
+            flags |= 1 << 0;
+        }
+        if effect.show_particles {
+            flags |= 1 << 1;
+        }
+        if effect.show_icon {
+            flags |= 1 << 2;
+        }
+        self.send_packet(EntityEffect {
+            entity_id: network_id.0,
+            effect_id: effect.effect.id(),
+            amplifier: effect.amplifier as i8,
+            duration: effect.duration.min(i32::MAX as u32) as i32,
+            flags,
+        });
+    }
+
+    pub fn send_remove_entity_effect(&self, network_id: NetworkId, effect: StatusEffect) {
+        self.send_packet(RemoveEntityEffect {
+            entity_id: network_id.0,
+            effect_id: effect.id(),
+        });
+    }
+
+    pub fn send_experience(&self, experience: &Experience) {
+        self.send_packet(SetExperience {
+            experience_bar: experience.progress,
+            level: experience.level as i32,
+            total_experience: experience.total as i32,
+        });
+    }
+
+    /// Sends the player's health. Hunger is not implemented,
+    /// so the food bar is always full.
+    pub fn send_health(&self, health: Health) {
+        self.send_packet(UpdateHealth {
+            health: health.0,
+            food: 20,
+            food_saturation: 5.0,
+        });
+    }
+
     pub fn set_hotbar_slot(&self, slot: u8) {
         self.send_packet(HeldItemChange { slot });
     }
@@ -622,7 +825,9 @@ impl Client {
     }
 
     fn send_packet(&self, packet: impl Into<ServerPlayPacket>) {
-        let _ = self.packets_to_send.try_send(packet.into());
+        let _ = self
+            .packets_to_send
+            .try_send(OutgoingPacket::Packet(packet.into()));
     }
 
     pub fn disconnect(&self, reason: &str) {
@@ -644,3 +849,71 @@ fn chat_packet(message: ChatMessage) -> packets::server::ChatMessage {
         sender: Uuid::default(),
     }
 }
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::config::Config;
+
+    fn client() -> (Client, Receiver<OutgoingPacket>) {
+        let (packets_to_send, sent_packets) = flume::unbounded();
+        let (_, received_packets) = flume::unbounded();
+        let config: Config = toml::from_str(include_str!("../config.toml")).unwrap();
+        let player = NewPlayer {
+            uuid: Uuid::nil(),
+            username: "test".to_owned(),
+            profile: Vec::new(),
+            received_packets,
+            packets_to_send,
+        };
+        (
+            Client::new(player, Arc::new(config.to_options())),
+            sent_packets,
+        )
+    }
+
+    fn sent_keepalive_id(sent_packets: &Receiver<OutgoingPacket>) -> i64 {
+        match sent_packets.try_recv() {
+            Ok(OutgoingPacket::Packet(ServerPlayPacket::KeepAlive(packet))) => packet.id,
+            _ => panic!("expected a keepalive"),
+        }
+    }
+
+    #[test]
+    fn keepalive_round_trip() {
+        let (client, sent_packets) = client();
+        client.send_keepalive();
+        let id = sent_keepalive_id(&sent_packets);
+
+        // No new keepalive is sent until the client replies.
+        client.send_keepalive();
+        assert!(sent_packets.try_recv().is_err());
+
+        assert!(client.handle_keepalive(id as u64).is_some());
+        assert!(!client.is_keepalive_timed_out(Duration::from_secs(0)));
+        client.send_keepalive();
+        assert_ne!(sent_keepalive_id(&sent_packets), id);
+    }
+
+    #[test]
+    fn wrong_keepalive_id_is_rejected() {
+        let (client, sent_packets) = client();
+        client.send_keepalive();
+        let id = sent_keepalive_id(&sent_packets);
+
+        assert!(client.handle_keepalive(id.wrapping_add(1) as u64).is_none());
+        assert!(client.handle_keepalive(id as u64).is_some());
+        assert!(client.handle_keepalive(id as u64).is_none());
+    }
+
+    #[test]
+    fn keepalive_times_out() {
+        let (client, _sent_packets) = client();
+        assert!(!client.is_keepalive_timed_out(Duration::from_secs(0)));
+
+        client.send_keepalive();
+        std::thread::sleep(Duration::from_millis(5));
+        assert!(client.is_keepalive_timed_out(Duration::from_millis(1)));
+        assert!(!client.is_keepalive_timed_out(Duration::from_secs(30)));
+    }
+}
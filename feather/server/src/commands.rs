@@ -0,0 +1,455 @@
+//! Handling of commands sent by players through chat.
+//!
+//! This is a minimal dispatcher until a proper command
+//! framework with argument parsing and completions exists.
+//! Only operators may run commands, which hides
+//! them from other players as if they didn't exist.
+
+use base::{Position, StatusEffect, Text};
+use common::{
+    chat::{ChatKind, ChatMessage},
+    chunk::pregen::{PregenArea, PregenShape},
+    ChatBox, Game, WorldId,
+};
+use ecs::{Entity, SysResult};
+use quill_common::{
+    components::{ActiveEffects, EffectInstance, Name},
+    entities::Player,
+};
+
+/// Longest duration in seconds accepted by `/effect give`.
+const MAX_EFFECT_SECONDS: u32 = 1_000_000;
+
+/// Default duration in seconds of effects given by `/effect give`.
+const DEFAULT_EFFECT_SECONDS: u32 = 30;
+
+/// Largest radius in blocks accepted by `/pregen`.
+const MAX_PREGEN_RADIUS: u32 = 10_000;
+
+/// Marks players who may run commands.
+#[derive(Debug)]
+pub struct Operator;
+
+/// Runs a command sent by `sender`, sending the feedback
+/// to its chat box. `command` excludes the leading slash.
+pub fn handle_command(game: &mut Game, sender: Entity, command: &str) -> SysResult {
+    let message = run_command(game, sender, command);
+    game.ecs
+        .get_mut::<ChatBox>(sender)?
+        .send(ChatMessage::new(ChatKind::System, message));
+    Ok(())
+}
+
+/// Runs a command sent by `sender` and returns the feedback.
+/// `command` excludes the leading slash.
+pub fn run_command(game: &mut Game, sender: Entity, command: &str) -> Text {
+    let args: Vec<&str> = command.split_whitespace().collect();
+    let feedback = if !may_run_commands(game, sender) {
+        Err(Text::translate("command.unknown.command"))
+    } else {
+        match args.split_first() {
+            Some((&"effect", args)) => parse_effect_command(args)
+                .and_then(|command| run_effect_command(game, sender, command)),
+            Some((&"pregen", args)) => parse_pregen_command(args)
+                .and_then(|command| run_pregen_command(game, sender, command)),
+            _ => Err(Text::translate("command.unknown.command")),
+        }
+    };
+    match feedback {
+        Ok(message) | Err(message) => message,
+    }
+}
+
+/// Determines whether `sender` may run commands. Senders
+/// other than players, like the console and RCON, always may.
+fn may_run_commands(game: &Game, sender: Entity) -> bool {
+    game.ecs.get::<Player>(sender).is_err() || game.ecs.get::<Operator>(sender).is_ok()
+}
+
+#[derive(Debug, PartialEq)]
+enum EffectCommand<'a> {
+    Give {
+        target: &'a str,
+        effect: StatusEffect,
+        seconds: u32,
+        amplifier: u8,
+        hide_particles: bool,
+    },
+    Clear {
+        target: &'a str,
+        effect: Option<StatusEffect>,
+    },
+}
+
+/// Parses the arguments of
+/// `/effect give <target> <effect> [seconds] [amplifier] [hideParticles]`
+/// or `/effect clear <target> [effect]`.
+fn parse_effect_command<'a>(args: &[&'a str]) -> Result<EffectCommand<'a>, Text> {
+    match args {
+        ["give", target, effect, rest @ ..] if rest.len() <= 3 => Ok(EffectCommand::Give {
+            target,
+            effect: parse_effect(effect)?,
+            seconds: match rest.get(0) {
+                Some(seconds) => parse_integer(seconds, 1, MAX_EFFECT_SECONDS)?,
+                None => DEFAULT_EFFECT_SECONDS,
+            },
+            amplifier: match rest.get(1) {
+                Some(amplifier) => parse_integer(amplifier, 0, u8::MAX as u32)? as u8,
+                None => 0,
+            },
+            hide_particles: match rest.get(2) {
+                Some(&"true") => true,
+                Some(&"false") | None => false,
+                Some(value) => {
+                    return Err(Text::translate_with(
+                        "parsing.bool.invalid",
+                        vec![value.to_string()],
+                    ))
+                }
+            },
+        }),
+        ["clear", target] => Ok(EffectCommand::Clear {
+            target,
+            effect: None,
+        }),
+        ["clear", target, effect] => Ok(EffectCommand::Clear {
+            target,
+            effect: Some(parse_effect(effect)?),
+        }),
+        _ => Err(Text::translate("command.unknown.command")),
+    }
+}
+
+fn parse_effect(name: &str) -> Result<StatusEffect, Text> {
+    StatusEffect::from_name(name)
+        .ok_or_else(|| Text::translate_with("effect.effectNotFound", vec![name.to_owned()]))
+}
+
+fn parse_integer(value: &str, min: u32, max: u32) -> Result<u32, Text> {
+    let parsed: i64 = value
+        .parse()
+        .map_err(|_| Text::translate_with("parsing.int.invalid", vec![value.to_owned()]))?;
+    if parsed < min as i64 {
+        Err(Text::translate_with(
+            "argument.integer.low",
+            vec![min.to_string(), parsed.to_string()],
+        ))
+    } else if parsed > max as i64 {
+        Err(Text::translate_with(
+            "argument.integer.big",
+            vec![max.to_string(), parsed.to_string()],
+        ))
+    } else {
+        Ok(parsed as u32)
+    }
+}
+
+fn run_effect_command(game: &Game, sender: Entity, command: EffectCommand) -> Result<Text, Text> {
+    match command {
+        EffectCommand::Give {
+            target,
+            effect,
+            seconds,
+            amplifier,
+            hide_particles,
+        } => {
+            let target = find_target(game, sender, target)?;
+            let duration = if effect.is_instant() { 1 } else { seconds * 20 };
+            let mut instance = EffectInstance::new(effect, amplifier, duration);
+            instance.show_particles = !hide_particles;
+
+            let applied = game
+                .ecs
+                .get_mut::<ActiveEffects>(target)
+                .map_or(false, |mut effects| effects.add(instance));
+            if applied {
+                Ok(Text::translate_with(
+                    "commands.effect.give.success.single",
+                    vec![effect_name(effect), target_name(game, target)],
+                ))
+            } else {
+                Err(Text::translate("commands.effect.give.failed"))
+            }
+        }
+        EffectCommand::Clear { target, effect } => {
+            let target = find_target(game, sender, target)?;
+            let mut effects = game
+                .ecs
+                .get_mut::<ActiveEffects>(target)
+                .map_err(|_| Text::translate("commands.effect.clear.everything.failed"))?;
+            match effect {
+                Some(effect) => {
+                    if effects.remove(effect).is_none() {
+                        return Err(Text::translate("commands.effect.clear.specific.failed"));
+                    }
+                    drop(effects);
+                    Ok(Text::translate_with(
+                        "commands.effect.clear.specific.success.single",
+                        vec![effect_name(effect), target_name(game, target)],
+                    ))
+                }
+                None => {
+                    if effects.is_empty() {
+                        return Err(Text::translate("commands.effect.clear.everything.failed"));
+                    }
+                    effects.clear();
+                    drop(effects);
+                    Ok(Text::translate_with(
+                        "commands.effect.clear.everything.success.single",
+                        vec![target_name(game, target)],
+                    ))
+                }
+            }
+        }
+    }
+}
+
+/// Resolves `@s` or a player name to an entity.
+fn find_target(game: &Game, sender: Entity, target: &str) -> Result<Entity, Text> {
+    if target == "@s" {
+        return Ok(sender);
+    }
+    game.ecs
+        .query::<&Name>()
+        .iter()
+        .find(|(_, name)| name.eq_ignore_ascii_case(target))
+        .map(|(entity, _)| entity)
+        .ok_or_else(|| Text::translate("argument.player.unknown"))
+}
+
+fn target_name(game: &Game, target: Entity) -> Text {
+    game.ecs
+        .get::<Name>(target)
+        .map(|name| Text::from(name.to_string()))
+        .unwrap_or_else(|_| Text::empty())
+}
+
+fn effect_name(effect: StatusEffect) -> Text {
+    Text::translate(format!("effect.minecraft.{}", effect.name()))
+}
+
+#[derive(Debug, PartialEq)]
+enum PregenCommand {
+    Start { radius: u32, shape: PregenShape },
+    Cancel,
+    Status,
+}
+
+/// Parses the arguments of `/pregen <radius> [square|circle]`,
+/// `/pregen cancel` or `/pregen status`.
+fn parse_pregen_command(args: &[&str]) -> Result<PregenCommand, Text> {
+    match args {
+        ["cancel"] => Ok(PregenCommand::Cancel),
+        ["status"] => Ok(PregenCommand::Status),
+        [radius, rest @ ..] if rest.len() <= 1 => Ok(PregenCommand::Start {
+            radius: parse_integer(radius, 1, MAX_PREGEN_RADIUS)?,
+            shape: match rest.get(0) {
+                Some(shape) => PregenShape::from_name(shape).ok_or_else(|| {
+                    Text::from(format!(
+                        "Unknown shape {}; expected square or circle",
+                        shape
+                    ))
+                })?,
+                None => PregenShape::Square,
+            },
+        }),
+        _ => Err(Text::translate("command.unknown.command")),
+    }
+}
+
+fn run_pregen_command(
+    game: &mut Game,
+    sender: Entity,
+    command: PregenCommand,
+) -> Result<Text, Text> {
+    // Pregeneration applies to the world the sender is in.
+    let world = game
+        .ecs
+        .get::<WorldId>(sender)
+        .map_or(WorldId::MAIN, |world| *world);
+    let world = &mut game.worlds[world];
+    match command {
+        PregenCommand::Start { radius, shape } => {
+            let center = game
+                .ecs
+                .get::<Position>(sender)
+                .map_err(|_| Text::from("Only players can start a pregeneration"))?
+                .chunk();
+            let area = PregenArea {
+                center,
+                radius: (radius + 15) / 16,
+                shape,
+            };
+            world
+                .pregenerate(area)
+                .map_err(|e| Text::from(format!("Failed to start pregeneration: {}", e)))?;
+            let total = world
+                .pregeneration_progress()
+                .map_or(0, |progress| progress.total);
+            Ok(Text::from(format!(
+                "Pregenerating {} chunks around chunk {}, {}",
+                total, center.x, center.z
+            )))
+        }
+        PregenCommand::Cancel => match world.cancel_pregeneration() {
+            Ok(true) => Ok(Text::from("Cancelled pregeneration")),
+            Ok(false) => Err(Text::from("No pregeneration is running")),
+            Err(e) => Err(Text::from(format!("Failed to cancel pregeneration: {}", e))),
+        },
+        PregenCommand::Status => match world.pregeneration_progress() {
+            Some(progress) => Ok(Text::from(format!("Pregenerating: {}", progress))),
+            None => Err(Text::from("No pregeneration is running")),
+        },
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use common::chat::ChatPreference;
+
+    use super::*;
+
+    fn parse(command: &str) -> Result<EffectCommand, Text> {
+        let args: Vec<&str> = command.split_whitespace().collect();
+        parse_effect_command(&args)
+    }
+
+    #[test]
+    fn parse_give_with_defaults() {
+        assert_eq!(
+            parse("give @s minecraft:speed"),
+            Ok(EffectCommand::Give {
+                target: "@s",
+                effect: StatusEffect::Speed,
+                seconds: DEFAULT_EFFECT_SECONDS,
+                amplifier: 0,
+                hide_particles: false,
+            })
+        );
+    }
+
+    #[test]
+    fn parse_give_with_all_arguments() {
+        assert_eq!(
+            parse("give Steve jump_boost 10 2 true"),
+            Ok(EffectCommand::Give {
+                target: "Steve",
+                effect: StatusEffect::JumpBoost,
+                seconds: 10,
+                amplifier: 2,
+                hide_particles: true,
+            })
+        );
+    }
+
+    #[test]
+    fn parse_clear() {
+        assert_eq!(
+            parse("clear @s"),
+            Ok(EffectCommand::Clear {
+                target: "@s",
+                effect: None
+            })
+        );
+        assert_eq!(
+            parse("clear @s poison"),
+            Ok(EffectCommand::Clear {
+                target: "@s",
+                effect: Some(StatusEffect::Poison)
+            })
+        );
+    }
+
+    #[test]
+    fn parse_invalid() {
+        assert!(parse("give @s not_an_effect").is_err());
+        assert!(parse("give @s speed 0").is_err());
+        assert!(parse("give @s speed 10 256").is_err());
+        assert!(parse("give @s speed 10 0 maybe").is_err());
+        assert!(parse("give @s").is_err());
+        assert!(parse("clear").is_err());
+    }
+
+    #[test]
+    fn parse_pregen() {
+        let parse = |command: &str| {
+            let args: Vec<&str> = command.split_whitespace().collect();
+            parse_pregen_command(&args)
+        };
+        assert_eq!(
+            parse("500"),
+            Ok(PregenCommand::Start {
+                radius: 500,
+                shape: PregenShape::Square
+            })
+        );
+        assert_eq!(
+            parse("64 circle"),
+            Ok(PregenCommand::Start {
+                radius: 64,
+                shape: PregenShape::Circle
+            })
+        );
+        assert_eq!(parse("cancel"), Ok(PregenCommand::Cancel));
+        assert_eq!(parse("status"), Ok(PregenCommand::Status));
+        assert!(parse("0").is_err());
+        assert!(parse("100 triangle").is_err());
+        assert!(parse("").is_err());
+    }
+
+    #[test]
+    fn give_and_clear() {
+        let mut game = Game::new();
+        let player = game.ecs.spawn((Name::new("Steve"), ActiveEffects::new()));
+
+        let give = parse("give steve regeneration 5 1").unwrap();
+        assert!(run_effect_command(&game, player, give).is_ok());
+        let effect = *game
+            .ecs
+            .get::<ActiveEffects>(player)
+            .unwrap()
+            .get(StatusEffect::Regeneration)
+            .unwrap();
+        assert_eq!(effect.duration, 100);
+        assert_eq!(effect.amplifier, 1);
+
+        let clear = parse("clear @s").unwrap();
+        assert!(run_effect_command(&game, player, clear).is_ok());
+        assert!(game.ecs.get::<ActiveEffects>(player).unwrap().is_empty());
+    }
+
+    #[test]
+    fn only_operators_run_commands() {
+        let mut game = Game::new();
+        let player = game.ecs.spawn((
+            Player,
+            Name::new("Steve"),
+            ActiveEffects::new(),
+            ChatBox::new(ChatPreference::All),
+        ));
+
+        handle_command(&mut game, player, "effect give @s speed").unwrap();
+        assert!(game.ecs.get::<ActiveEffects>(player).unwrap().is_empty());
+        handle_command(&mut game, player, "pregen status").unwrap();
+        let feedback: Vec<_> = game
+            .ecs
+            .get_mut::<ChatBox>(player)
+            .unwrap()
+            .drain()
+            .collect();
+        assert_eq!(feedback.len(), 2);
+        assert!(feedback
+            .iter()
+            .all(|message| message.text() == &Text::translate("command.unknown.command")));
+
+        game.ecs.insert(player, Operator).unwrap();
+        handle_command(&mut game, player, "effect give @s speed").unwrap();
+        assert!(!game.ecs.get::<ActiveEffects>(player).unwrap().is_empty());
+
+        let console = game
+            .ecs
+            .spawn((ActiveEffects::new(), ChatBox::new(ChatPreference::All)));
+        handle_command(&mut game, console, "effect give @s speed").unwrap();
+        assert!(!game.ecs.get::<ActiveEffects>(console).unwrap().is_empty());
+    }
+}
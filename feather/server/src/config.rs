@@ -1,12 +1,26 @@
 //! Loads an `Options` from a TOML config.
 
-use std::{fs, net::IpAddr, path::Path, str::FromStr};
+use std::{
+    fs,
+    net::{IpAddr, SocketAddr},
+    path::Path,
+    str::FromStr,
+    sync::Arc,
+    time::Duration,
+};
 
 use anyhow::Context;
-use base::Gamemode;
+use base::{anvil::level::SuperflatLayer, Gamemode};
 use serde::{Deserialize, Deserializer};
 
-use crate::{favicon::Favicon, Options};
+use crate::{
+    auth::{SessionServerAuthenticator, MOJANG_SESSION_SERVER},
+    favicon::Favicon,
+    query::QueryOptions,
+    rate_limit::{Limit, LimitAction, RateLimits},
+    rcon::RconOptions,
+    Options,
+};
 
 const DEFAULT_CONFIG: &str = include_str!("../config.toml");
 
@@ -44,6 +58,14 @@ pub struct Config {
     pub log: Log,
     pub world: World,
     pub proxy: Proxy,
+    #[serde(default)]
+    pub rate_limit: RateLimit,
+    #[serde(default)]
+    pub metrics: MetricsConfig,
+    #[serde(default)]
+    pub rcon: RconConfig,
+    #[serde(default)]
+    pub query: QueryConfig,
 }
 
 impl Config {
@@ -58,20 +80,29 @@ impl Config {
             } else {
                 self.server.online_mode
             },
+            authenticator: Arc::new(SessionServerAuthenticator::new(
+                self.server.session_server.clone(),
+                Duration::from_secs(self.server.session_server_timeout),
+            )),
             compression_threshold: if self.network.compression_threshold <= 0 {
                 None
             } else {
                 Some(self.network.compression_threshold as usize)
             },
             view_distance: self.server.view_distance,
+            chunks_per_tick: self.server.chunks_per_tick.max(1),
             max_players: self.server.max_players,
             default_gamemode: self.server.default_gamemode,
+            operators: self.server.operators.clone(),
             proxy_mode: match self.proxy.proxy_mode {
                 ProxyMode::None => None,
                 ProxyMode::Bungee => Some(crate::options::ProxyMode::Bungeecord),
                 ProxyMode::Velocity => Some(crate::options::ProxyMode::Velocity),
             },
             velocity_secret: self.proxy.velocity_secret.clone(),
+            rate_limits: self.rate_limit.to_rate_limits(),
+            rcon: self.rcon.to_rcon_options(),
+            query: self.query.to_query_options(&self.world.name),
         }
     }
 }
@@ -81,6 +112,10 @@ pub struct Network {
     pub address: IpAddr,
     pub port: u16,
     pub compression_threshold: i32,
+    /// Directory containing the mapping files of
+    /// additional protocol versions to support.
+    #[serde(default = "default_protocol_mappings")]
+    pub protocol_mappings: String,
 }
 
 #[derive(Debug, Deserialize)]
@@ -90,6 +125,34 @@ pub struct ServerConfig {
     pub max_players: u32,
     pub default_gamemode: Gamemode,
     pub view_distance: u32,
+    /// Maximum number of chunks to send to each player per tick.
+    #[serde(default = "default_chunks_per_tick")]
+    pub chunks_per_tick: usize,
+    /// Base URL of the session server authenticating players in online mode.
+    #[serde(default = "default_session_server")]
+    pub session_server: String,
+    /// Seconds to wait for the session server to respond.
+    #[serde(default = "default_session_server_timeout")]
+    pub session_server_timeout: u64,
+    /// Usernames of the players allowed to run commands.
+    #[serde(default)]
+    pub operators: Vec<String>,
+}
+
+fn default_protocol_mappings() -> String {
+    "protocol-mappings".to_owned()
+}
+
+fn default_chunks_per_tick() -> usize {
+    10
+}
+
+fn default_session_server() -> String {
+    MOJANG_SESSION_SERVER.to_owned()
+}
+
+fn default_session_server_timeout() -> u64 {
+    10
 }
 
 #[derive(Debug, Deserialize)]
@@ -102,7 +165,193 @@ pub struct Log {
 pub struct World {
     pub name: String,
     pub generator: String,
+    /// Generator of the nether; empty to disable the nether.
+    #[serde(default = "default_dimension_generator")]
+    pub nether_generator: String,
+    /// Generator of the end; empty to disable the end.
+    #[serde(default = "default_dimension_generator")]
+    pub end_generator: String,
     pub seed: String,
+    #[serde(default = "default_datapacks")]
+    pub datapacks: String,
+    #[serde(default)]
+    pub generator_settings: GeneratorSettings,
+    /// Number of threads generating chunks; 0 uses one per CPU.
+    #[serde(default)]
+    pub generation_threads: usize,
+}
+
+fn default_datapacks() -> String {
+    "datapacks".to_owned()
+}
+
+fn default_dimension_generator() -> String {
+    "void".to_owned()
+}
+
+/// Overrides of the world generator's settings.
+#[derive(Debug, Default, Deserialize)]
+pub struct GeneratorSettings {
+    pub preset: Option<String>,
+    pub sea_level: Option<i32>,
+    pub biome_size: Option<u32>,
+    pub river_size: Option<u32>,
+    pub flat_biome: Option<String>,
+    pub flat_layers: Option<Vec<SuperflatLayer>>,
+}
+
+/// The HTTP endpoint serving metrics in the Prometheus text format.
+#[derive(Debug, Deserialize)]
+pub struct MetricsConfig {
+    pub enabled: bool,
+    pub address: IpAddr,
+    pub port: u16,
+}
+
+/// Used if the config has no `metrics` section.
+impl Default for MetricsConfig {
+    fn default() -> Self {
+        Self {
+            enabled: false,
+            address: IpAddr::from([127, 0, 0, 1]),
+            port: 9225,
+        }
+    }
+}
+
+/// The RCON listener.
+#[derive(Debug, Deserialize)]
+pub struct RconConfig {
+    pub enabled: bool,
+    pub address: IpAddr,
+    pub port: u16,
+    pub password: String,
+}
+
+/// Used if the config has no `rcon` section.
+impl Default for RconConfig {
+    fn default() -> Self {
+        Self {
+            enabled: false,
+            address: IpAddr::from([127, 0, 0, 1]),
+            port: 25575,
+            password: String::new(),
+        }
+    }
+}
+
+impl RconConfig {
+    pub fn to_rcon_options(&self) -> Option<RconOptions> {
+        if !self.enabled {
+            return None;
+        }
+        if self.password.is_empty() {
+            log::warn!("RCON is enabled but has no password set, so it stays disabled");
+            return None;
+        }
+        Some(RconOptions {
+            address: SocketAddr::new(self.address, self.port),
+            password: self.password.clone(),
+        })
+    }
+}
+
+/// The UDP query listener.
+#[derive(Debug, Deserialize)]
+pub struct QueryConfig {
+    pub enabled: bool,
+    pub address: IpAddr,
+    pub port: u16,
+}
+
+/// Used if the config has no `query` section.
+impl Default for QueryConfig {
+    fn default() -> Self {
+        Self {
+            enabled: false,
+            address: IpAddr::from([0, 0, 0, 0]),
+            port: 25565,
+        }
+    }
+}
+
+impl QueryConfig {
+    /// Gets the query options, reporting `world_name` as the map.
+    pub fn to_query_options(&self, world_name: &str) -> Option<QueryOptions> {
+        if !self.enabled {
+            return None;
+        }
+        Some(QueryOptions {
+            address: SocketAddr::new(self.address, self.port),
+            map: world_name.to_owned(),
+        })
+    }
+}
+
+/// Limits on what clients may send. Limits which
+/// are left out are disabled.
+#[derive(Debug, Deserialize)]
+pub struct RateLimit {
+    /// Maximum packet length in bytes; 0 to disable.
+    pub max_packet_size: usize,
+    pub packets: Option<LimitConfig>,
+    pub bytes: Option<LimitConfig>,
+    pub chat_messages: Option<LimitConfig>,
+    pub login_attempts: Option<LimitConfig>,
+}
+
+/// Used if the config has no `rate_limit` section.
+impl Default for RateLimit {
+    fn default() -> Self {
+        let limit = |max, interval, action| {
+            Some(LimitConfig {
+                max,
+                interval,
+                action,
+            })
+        };
+        Self {
+            max_packet_size: 2_097_151,
+            packets: limit(500, 1, LimitAction::Kick),
+            bytes: limit(2_097_152, 1, LimitAction::Kick),
+            chat_messages: limit(10, 5, LimitAction::Drop),
+            login_attempts: limit(5, 60, LimitAction::Kick),
+        }
+    }
+}
+
+impl RateLimit {
+    pub fn to_rate_limits(&self) -> RateLimits {
+        RateLimits {
+            max_packet_size: if self.max_packet_size == 0 {
+                None
+            } else {
+                Some(self.max_packet_size)
+            },
+            packets: self.packets.map(LimitConfig::to_limit),
+            bytes: self.bytes.map(LimitConfig::to_limit),
+            chat_messages: self.chat_messages.map(LimitConfig::to_limit),
+            login_attempts: self.login_attempts.map(LimitConfig::to_limit),
+        }
+    }
+}
+
+#[derive(Debug, Copy, Clone, Deserialize)]
+pub struct LimitConfig {
+    pub max: u64,
+    /// Length of the interval in seconds.
+    pub interval: u64,
+    pub action: LimitAction,
+}
+
+impl LimitConfig {
+    fn to_limit(self) -> Limit {
+        Limit {
+            max: self.max,
+            interval: Duration::from_secs(self.interval),
+            action: self.action,
+        }
+    }
 }
 
 #[derive(Debug, Deserialize)]
@@ -139,4 +388,50 @@ mod tests {
     fn default_config_is_valid() {
         let _config: Config = toml::from_str(DEFAULT_CONFIG).unwrap();
     }
+
+    #[test]
+    fn parse_generator_settings() {
+        let world: World = toml::from_str(
+            r#"
+            name = "world"
+            generator = "flat"
+            seed = ""
+
+            [generator_settings]
+            sea_level = 40
+            flat_layers = [ { block = "bedrock", height = 1 }, { block = "stone", height = 60 } ]
+            "#,
+        )
+        .unwrap();
+        let settings = world.generator_settings;
+        assert_eq!(settings.sea_level, Some(40));
+        assert_eq!(settings.biome_size, None);
+        let layers = settings.flat_layers.unwrap();
+        assert_eq!(layers[1].block, "stone");
+        assert_eq!(layers[1].height, 60);
+        assert_eq!(world.datapacks, "datapacks");
+        assert_eq!(world.nether_generator, "void");
+    }
+
+    #[test]
+    fn parse_rate_limit() {
+        let rate_limit: RateLimit = toml::from_str(
+            r#"
+            max_packet_size = 0
+            chat_messages = { max = 3, interval = 2, action = "drop" }
+            "#,
+        )
+        .unwrap();
+        let limits = rate_limit.to_rate_limits();
+        assert_eq!(limits.max_packet_size, None);
+        assert_eq!(limits.packets, None);
+        assert_eq!(
+            limits.chat_messages,
+            Some(Limit {
+                max: 3,
+                interval: Duration::from_secs(2),
+                action: LimitAction::Drop,
+            })
+        );
+    }
 }
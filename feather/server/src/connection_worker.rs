@@ -1,12 +1,21 @@
-use std::{fmt::Debug, io, net::SocketAddr, sync::Arc, time::Duration};
+use std::{
+    fmt::Debug,
+    io,
+    net::{IpAddr, SocketAddr},
+    sync::Arc,
+    time::{Duration, Instant},
+};
 
+use anyhow::anyhow;
 use base::Text;
 use flume::{Receiver, Sender};
 use futures_lite::FutureExt;
 use io::ErrorKind;
 use protocol::{
-    codec::CryptKey, packets::server::Disconnect, ClientPlayPacket, MinecraftCodec, Readable,
-    ServerPlayPacket, Writeable,
+    codec::{CryptKey, PacketTooLong},
+    mapping,
+    packets::server::Disconnect,
+    ClientPlayPacket, MinecraftCodec, ProtocolVersion, Readable, ServerPlayPacket, Writeable,
 };
 use tokio::{
     io::{AsyncReadExt, AsyncWriteExt},
@@ -18,9 +27,15 @@ use tokio::{
 };
 
 use crate::{
-    initial_handler::{InitialHandling, NewPlayer},
+    chunk_packets::SharedChunk,
+    initial_handler::{
+        status::{LegacyPing, StatusRequest},
+        InitialHandling, NewPlayer,
+    },
+    metrics::TrafficStats,
     options::Options,
     player_count::PlayerCount,
+    rate_limit::{ConnectionLimiter, LimitKind, RateLimiter, Verdict},
 };
 
 /// Tokio task which handles a connection and processes
@@ -34,36 +49,56 @@ use crate::{
 pub struct Worker {
     reader: Reader,
     writer: Writer,
+    address: SocketAddr,
     options: Arc<Options>,
     player_count: PlayerCount,
-    packets_to_send_tx: Sender<ServerPlayPacket>,
+    rate_limiter: RateLimiter,
+    packets_to_send_tx: Sender<OutgoingPacket>,
     received_packets_rx: Receiver<ClientPlayPacket>,
     new_players: Sender<NewPlayer>,
+    status_requests: Sender<StatusRequest>,
 }
 
 impl Worker {
+    #[allow(clippy::too_many_arguments)]
     pub fn new(
         stream: TcpStream,
-        _addr: SocketAddr,
+        address: SocketAddr,
         options: Arc<Options>,
         player_count: PlayerCount,
+        rate_limiter: RateLimiter,
+        traffic: TrafficStats,
         new_players: Sender<NewPlayer>,
+        status_requests: Sender<StatusRequest>,
     ) -> Self {
         let (reader, writer) = stream.into_split();
 
         let (received_packets_tx, received_packets_rx) = flume::bounded(32);
         let (packets_to_send_tx, packets_to_send_rx) = flume::unbounded();
-        let reader = Reader::new(reader, received_packets_tx);
-        let writer = Writer::new(writer, packets_to_send_rx);
+        let limiter = rate_limiter.connection(address, options.rate_limits);
+        let mut reader = Reader::new(
+            reader,
+            received_packets_tx,
+            packets_to_send_tx.clone(),
+            limiter,
+            traffic.clone(),
+        );
+        reader
+            .codec
+            .set_max_packet_length(options.rate_limits.max_packet_size);
+        let writer = Writer::new(writer, packets_to_send_rx, traffic);
 
         Self {
             reader,
             writer,
+            address,
             options,
             player_count,
+            rate_limiter,
             packets_to_send_tx,
             received_packets_rx,
             new_players,
+            status_requests,
         }
     }
 
@@ -109,6 +144,38 @@ impl Worker {
         self.player_count.get()
     }
 
+    pub fn address(&self) -> SocketAddr {
+        self.address
+    }
+
+    pub fn status_requests(&self) -> &Sender<StatusRequest> {
+        &self.status_requests
+    }
+
+    /// Records a login attempt from `ip`, which is the client's
+    /// IP address forwarded by the proxy in proxy mode.
+    pub fn record_login_attempt(&self, ip: IpAddr) -> Verdict {
+        let verdict = self.rate_limiter.on_login(
+            ip,
+            self.options.rate_limits.login_attempts.as_ref(),
+            Instant::now(),
+        );
+        if let Verdict::Kick(_) = verdict {
+            self.rate_limiter.stats().record_kick();
+        }
+        verdict
+    }
+
+    /// Sets the protocol version the client uses.
+    pub fn set_version(&mut self, version: ProtocolVersion) {
+        self.reader.codec.set_version(version);
+        self.writer.codec.set_version(version);
+    }
+
+    pub fn version(&self) -> ProtocolVersion {
+        self.reader.codec.version()
+    }
+
     #[allow(unused)]
     pub fn enable_compression(&mut self, threshold: usize) {
         self.reader.codec.enable_compression(threshold);
@@ -132,6 +199,19 @@ impl Worker {
         self.writer.write(packet).await
     }
 
+    /// Checks whether the connection starts with a legacy server list ping.
+    /// Must be called before reading any packets.
+    pub async fn read_legacy_ping(&mut self) -> anyhow::Result<Option<LegacyPing>> {
+        self.reader.read_legacy_ping().await
+    }
+
+    /// Writes bytes which aren't a packet, bypassing compression and encryption.
+    pub async fn write_raw(&mut self, bytes: &[u8]) -> anyhow::Result<()> {
+        self.writer.stream.write_all(bytes).await?;
+        self.writer.traffic.record_sent(bytes.len());
+        Ok(())
+    }
+
     pub fn split(self, username: String) {
         let Self {
             reader,
@@ -152,7 +232,7 @@ impl Worker {
         });
     }
 
-    pub fn packets_to_send(&self) -> Sender<ServerPlayPacket> {
+    pub fn packets_to_send(&self) -> Sender<OutgoingPacket> {
         self.packets_to_send_tx.clone()
     }
 
@@ -166,21 +246,40 @@ struct Reader {
     codec: MinecraftCodec,
     buffer: [u8; 512],
     received_packets: Sender<ClientPlayPacket>,
+    /// Used to tell the client why it was kicked.
+    packets_to_send: Sender<OutgoingPacket>,
+    limiter: ConnectionLimiter,
+    traffic: TrafficStats,
 }
 
 impl Reader {
-    pub fn new(stream: OwnedReadHalf, received_packets: Sender<ClientPlayPacket>) -> Self {
+    pub fn new(
+        stream: OwnedReadHalf,
+        received_packets: Sender<ClientPlayPacket>,
+        packets_to_send: Sender<OutgoingPacket>,
+        limiter: ConnectionLimiter,
+        traffic: TrafficStats,
+    ) -> Self {
         Self {
             stream,
             codec: MinecraftCodec::new(),
             buffer: [0; 512],
             received_packets,
+            packets_to_send,
+            limiter,
+            traffic,
         }
     }
 
     pub async fn run(mut self) -> anyhow::Result<()> {
         loop {
             let packet = self.read::<ClientPlayPacket>().await?;
+            match self.limiter.on_packet(&packet, Instant::now()) {
+                Verdict::Accept => (),
+                Verdict::Drop => continue,
+                Verdict::Kick(kind) => return Err(self.kick(kind)),
+            }
+
             let result = self.received_packets.send_async(packet).await;
             if result.is_err() {
                 // server dropped connection
@@ -192,8 +291,15 @@ impl Reader {
     pub async fn read<P: Readable>(&mut self) -> anyhow::Result<P> {
         // Keep reading bytes and trying to get the packet.
         loop {
-            if let Some(packet) = self.codec.next_packet::<P>()? {
-                return Ok(packet);
+            match self.codec.next_packet::<P>() {
+                Ok(Some(packet)) => return Ok(packet),
+                Ok(None) => (),
+                Err(e) if e.is::<PacketTooLong>() => {
+                    self.limiter.stats().record_violation(LimitKind::PacketSize);
+                    self.kick(LimitKind::PacketSize);
+                    return Err(e);
+                }
+                Err(e) => return Err(e),
             }
 
             let duration = Duration::from_secs(10);
@@ -201,40 +307,135 @@ impl Reader {
             if read_bytes == 0 {
                 return Err(io::Error::new(ErrorKind::UnexpectedEof, "read 0 bytes").into());
             }
+            self.traffic.record_received(read_bytes);
+            if let Verdict::Kick(kind) = self.limiter.on_bytes(read_bytes, Instant::now()) {
+                return Err(self.kick(kind));
+            }
 
             let bytes = &self.buffer[..read_bytes];
             self.codec.accept(bytes);
         }
     }
+
+    /// Reads the first bytes sent by the client. Legacy server list
+    /// pings start with 0xFE, where other connections start with the
+    /// length of the handshake packet.
+    pub async fn read_legacy_ping(&mut self) -> anyhow::Result<Option<LegacyPing>> {
+        let duration = Duration::from_secs(10);
+        let read_bytes = timeout(duration, self.stream.read(&mut self.buffer)).await??;
+        if read_bytes == 0 {
+            return Err(io::Error::new(ErrorKind::UnexpectedEof, "read 0 bytes").into());
+        }
+        self.traffic.record_received(read_bytes);
+
+        let bytes = &self.buffer[..read_bytes];
+        if bytes[0] == 0xFE {
+            let ping = if bytes.get(1) == Some(&0x01) {
+                LegacyPing::V1_4
+            } else {
+                LegacyPing::Beta
+            };
+            return Ok(Some(ping));
+        }
+        self.codec.accept(bytes);
+        Ok(None)
+    }
+
+    /// Disconnects the client for exceeding a limit. Returns
+    /// the error to close the connection with.
+    fn kick(&self, kind: LimitKind) -> anyhow::Error {
+        self.limiter.stats().record_kick();
+        // Only reaches the client if it is already playing.
+        let _ =
+            self.packets_to_send
+                .try_send(OutgoingPacket::Packet(ServerPlayPacket::Disconnect(
+                    Disconnect {
+                        reason: Text::from("You are sending too much data!").to_string(),
+                    },
+                )));
+        anyhow!("exceeded the {} limit", kind.name())
+    }
+}
+
+/// A packet queued to be written to a connection.
+pub enum OutgoingPacket {
+    Packet(ServerPlayPacket),
+    /// The packets of a chunk, encoded once for all connections.
+    Chunk(Arc<SharedChunk>),
 }
 
+/// The number of buffered bytes after which the
+/// `Writer` stops batching packets and writes them.
+const MAX_BATCH_SIZE: usize = 64 * 1024;
+
 struct Writer {
     stream: OwnedWriteHalf,
     codec: MinecraftCodec,
-    packets_to_send: Receiver<ServerPlayPacket>,
+    packets_to_send: Receiver<OutgoingPacket>,
     buffer: Vec<u8>,
+    traffic: TrafficStats,
 }
 
 impl Writer {
-    pub fn new(stream: OwnedWriteHalf, packets_to_send: Receiver<ServerPlayPacket>) -> Self {
+    pub fn new(
+        stream: OwnedWriteHalf,
+        packets_to_send: Receiver<OutgoingPacket>,
+        traffic: TrafficStats,
+    ) -> Self {
         Self {
             stream,
             codec: MinecraftCodec::new(),
             packets_to_send,
             buffer: Vec::new(),
+            traffic,
         }
     }
 
     pub async fn run(mut self) -> anyhow::Result<()> {
         while let Ok(packet) = self.packets_to_send.recv_async().await {
-            self.write(packet).await?;
+            self.encode(packet)?;
+            // Write the packets queued in the meantime along with it.
+            while self.buffer.len() < MAX_BATCH_SIZE {
+                match self.packets_to_send.try_recv() {
+                    Ok(packet) => self.encode(packet)?,
+                    Err(_) => break,
+                }
+            }
+            self.flush().await?;
         }
         Ok(())
     }
 
     pub async fn write(&mut self, packet: impl Writeable + Debug) -> anyhow::Result<()> {
         self.codec.encode(&packet, &mut self.buffer)?;
+        self.flush().await
+    }
+
+    fn encode(&mut self, packet: OutgoingPacket) -> anyhow::Result<()> {
+        match packet {
+            OutgoingPacket::Packet(packet) => {
+                let version = self.codec.version();
+                if !mapping::has_clientbound_equivalent(version, packet.id()) {
+                    log::trace!(
+                        "Not sending packet {:#04x}, which doesn't exist in {}",
+                        packet.id(),
+                        version.name()
+                    );
+                    return Ok(());
+                }
+                self.codec.encode(&packet, &mut self.buffer)
+            }
+            OutgoingPacket::Chunk(chunk) => {
+                let frames = chunk.encoded(&mut self.codec)?;
+                self.codec.write_frames(&frames, &mut self.buffer);
+                Ok(())
+            }
+        }
+    }
+
+    async fn flush(&mut self) -> anyhow::Result<()> {
         self.stream.write_all(&self.buffer).await?;
+        self.traffic.record_sent(self.buffer.len());
         self.buffer.clear();
         Ok(())
     }
@@ -1,4 +1,5 @@
 use base::{EntityKind, Position};
+use common::experience::ExperienceOrbValue;
 use ecs::{EntityBuilder, EntityRef, SysResult};
 use quill_common::{components::OnGround, entity_init::EntityInit};
 use uuid::Uuid;
@@ -48,6 +49,7 @@ fn add_spawn_packet(builder: &mut EntityBuilder, init: &EntityInit) {
     // (minecarts, items, ...)
     let spawn_packet = match init {
         EntityInit::Player => spawn_player,
+        EntityInit::ExperienceOrb => spawn_experience_orb,
         _ => spawn_living_entity,
     };
     builder.add(SpawnPacketSender(spawn_packet));
@@ -62,6 +64,15 @@ fn spawn_player(entity: &EntityRef, client: &Client) -> SysResult {
     Ok(())
 }
 
+fn spawn_experience_orb(entity: &EntityRef, client: &Client) -> SysResult {
+    let network_id = *entity.get::<NetworkId>()?;
+    let pos = *entity.get::<Position>()?;
+    let value = entity.get::<ExperienceOrbValue>()?.0;
+
+    client.send_experience_orb(network_id, pos, value);
+    Ok(())
+}
+
 fn spawn_living_entity(entity: &EntityRef, client: &Client) -> SysResult {
     let network_id = *entity.get::<NetworkId>()?;
     let uuid = *entity.get::<Uuid>()?;
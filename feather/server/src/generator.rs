@@ -0,0 +1,521 @@
+//! Creates the world generator selected in the config,
+//! applying settings from data packs and the config.
+
+use std::{path::Path, str::FromStr, sync::Arc};
+
+use anyhow::{bail, Context};
+use base::{anvil::level::SuperflatGeneratorOptions, Biome, BlockId, Dimension};
+use datapacks::{
+    structure::{self, StructureTemplate, TemplateBlockNbt},
+    worldgen::{BlockState, OreHeight},
+    NamespacedId, WorldgenData,
+};
+use worldgen::{
+    default_structures, BiomeTerrain, ComposableGenerator, Direction, Jigsaw, JigsawPools,
+    NoiseSampling, NoiseSettings, OreFinisher, OreVein, PoolElement, Projection, Slide,
+    SuperflatWorldGenerator, Template, TemplateBlock, TemplatePart, TemplatePool,
+    VanillaWorldGenerator, VeinHeight, VoidWorldGenerator, WorldGenerator,
+};
+
+use crate::config::{GeneratorSettings, World};
+
+/// The noise settings preset built into the vanilla generator.
+const DEFAULT_PRESET: &str = "minecraft:overworld";
+
+/// Creates the world generator configured in `config` for `dimension`.
+///
+/// Returns `None` if the world of the dimension is disabled,
+/// which is possible for all dimensions except the overworld.
+pub fn create_generator(
+    config: &World,
+    dimension: Dimension,
+    seed: u64,
+) -> anyhow::Result<Option<Arc<dyn WorldGenerator>>> {
+    let name = match dimension {
+        Dimension::Overworld => &config.generator,
+        Dimension::TheNether => &config.nether_generator,
+        Dimension::TheEnd => &config.end_generator,
+    };
+    if name.is_empty() && dimension != Dimension::Overworld {
+        return Ok(None);
+    }
+
+    let settings = &config.generator_settings;
+    let generator: Arc<dyn WorldGenerator> = match &name[..] {
+        "flat" => Arc::new(SuperflatWorldGenerator::new(flat_options(settings))),
+        "void" => Arc::new(VoidWorldGenerator),
+        "vanilla" => {
+            let data = load_datapacks(config)?;
+            let noise_settings = noise_settings(&data, settings)?;
+            Arc::new(VanillaWorldGenerator::with_settings(
+                seed as i64,
+                noise_settings,
+            ))
+        }
+        _ => {
+            let data = load_datapacks(config)?;
+            Arc::new(
+                ComposableGenerator::default_with_ores(seed, ore_finisher(&data))
+                    .with_structures(default_structures(jigsaw_pools(&data))),
+            )
+        }
+    };
+    Ok(Some(generator))
+}
+
+fn load_datapacks(config: &World) -> anyhow::Result<WorldgenData> {
+    WorldgenData::load(Path::new(&config.datapacks)).with_context(|| {
+        format!(
+            "failed to load world generation data from {}",
+            config.datapacks
+        )
+    })
+}
+
+fn flat_options(settings: &GeneratorSettings) -> SuperflatGeneratorOptions {
+    let mut options = SuperflatGeneratorOptions::default();
+    if let Some(biome) = &settings.flat_biome {
+        options.biome = biome.clone();
+    }
+    if let Some(layers) = &settings.flat_layers {
+        options.layers = layers.clone();
+    }
+    options
+}
+
+/// Builds the settings of the vanilla generator from
+/// the configured preset, data pack biomes and overrides.
+fn noise_settings(
+    data: &WorldgenData,
+    settings: &GeneratorSettings,
+) -> anyhow::Result<NoiseSettings> {
+    let mut noise_settings = NoiseSettings::default();
+
+    let preset = settings.preset.as_deref().unwrap_or(DEFAULT_PRESET);
+    let preset_id = NamespacedId::from_str(preset)
+        .with_context(|| format!("invalid noise settings preset {}", preset))?;
+    match data.noise_settings.get(&preset_id) {
+        Some(preset) => apply_preset(&mut noise_settings, preset)
+            .with_context(|| format!("invalid noise settings preset {}", preset_id))?,
+        None if preset_id.to_string() == DEFAULT_PRESET => {}
+        None => bail!("unknown noise settings preset {}", preset_id),
+    }
+
+    for (id, biome) in &data.biomes {
+        if id.namespace() != datapacks::DEFAULT_NAMESPACE {
+            continue;
+        }
+        if let Some(known) = Biome::from_name(id.name()) {
+            noise_settings.biome_terrain.insert(
+                known,
+                BiomeTerrain {
+                    depth: biome.depth,
+                    scale: biome.scale,
+                },
+            );
+        }
+    }
+
+    if let Some(sea_level) = settings.sea_level {
+        noise_settings.sea_level = sea_level;
+    }
+    if let Some(biome_size) = settings.biome_size {
+        noise_settings.biome_size = biome_size;
+    }
+    if let Some(river_size) = settings.river_size {
+        noise_settings.river_size = river_size;
+    }
+    Ok(noise_settings)
+}
+
+fn apply_preset(
+    settings: &mut NoiseSettings,
+    preset: &datapacks::worldgen::NoiseSettings,
+) -> anyhow::Result<()> {
+    let noise = &preset.noise;
+    if noise.height != 256 || noise.size_horizontal != 1 || noise.size_vertical != 2 {
+        bail!(
+            "unsupported height {} and noise sizes {}x{}; only a height of 256 and sizes 1x2 are supported",
+            noise.height,
+            noise.size_horizontal,
+            noise.size_vertical
+        );
+    }
+
+    settings.sea_level = preset.sea_level;
+    settings.default_block = block(&preset.default_block)?;
+    settings.default_fluid = block(&preset.default_fluid)?;
+    settings.bedrock_floor_position = preset.bedrock_floor_position;
+    settings.bedrock_roof_position = preset.bedrock_roof_position;
+    settings.sampling = NoiseSampling {
+        xz_scale: noise.sampling.xz_scale,
+        y_scale: noise.sampling.y_scale,
+        xz_factor: noise.sampling.xz_factor,
+        y_factor: noise.sampling.y_factor,
+    };
+    settings.top_slide = slide(noise.top_slide);
+    settings.bottom_slide = slide(noise.bottom_slide);
+    settings.density_factor = noise.density_factor;
+    settings.density_offset = noise.density_offset;
+    settings.random_density_offset = noise.random_density_offset;
+    settings.amplified = noise.amplified;
+    Ok(())
+}
+
+fn slide(slide: datapacks::worldgen::Slide) -> Slide {
+    Slide {
+        target: f64::from(slide.target),
+        size: f64::from(slide.size),
+        offset: f64::from(slide.offset),
+    }
+}
+
+fn block(state: &BlockState) -> anyhow::Result<BlockId> {
+    BlockId::from_identifier_and_properties(&state.name.to_string(), &state.properties)
+        .with_context(|| format!("unknown block state {:?}", state))
+}
+
+/// Builds the ores of the default generator by merging the
+/// ore features and biomes of data packs over vanilla's.
+///
+/// Ore features of data packs replace the vanilla features
+/// with the same ID, or are added to them. Biomes defined by
+/// data packs get exactly the ores they list, while other biomes
+/// keep their vanilla ores. Added ores which no biome lists
+/// are placed in all biomes.
+fn ore_finisher(data: &WorldgenData) -> OreFinisher {
+    let mut features = Vec::new();
+    for (name, vein) in OreFinisher::vanilla_veins() {
+        if let Ok(id) = NamespacedId::from_str(name) {
+            let biomes = vein.biomes().map(<[Biome]>::to_vec);
+            features.push((id, vein, biomes));
+        }
+    }
+
+    let pack_biomes: Vec<(Biome, &datapacks::worldgen::Biome)> = data
+        .biomes
+        .iter()
+        .filter(|(id, _)| id.namespace() == datapacks::DEFAULT_NAMESPACE)
+        .filter_map(|(id, biome)| Some((Biome::from_name(id.name())?, biome)))
+        .collect();
+    let listing_biomes = |id: &NamespacedId| -> Vec<Biome> {
+        pack_biomes
+            .iter()
+            .filter(|(_, biome)| biome.feature_ids().any(|feature| feature == *id))
+            .map(|&(biome, _)| biome)
+            .collect()
+    };
+
+    // Sort the features so veins are generated in
+    // the same order, and the terrain is reproducible.
+    let mut ores: Vec<_> = data.ore_features().into_iter().collect();
+    ores.sort_by(|(a, _), (b, _)| a.cmp(b));
+
+    let mut loaded = 0;
+    for (id, ore) in ores {
+        let block = match block(&ore.state) {
+            Ok(block) => block,
+            Err(e) => {
+                log::warn!("Skipping ore feature {}: {:?}", id, e);
+                continue;
+            }
+        };
+        let height = match ore.height {
+            OreHeight::Range { min, max } => VeinHeight::Uniform(min, max),
+            OreHeight::DepthAverage { baseline, spread } => {
+                VeinHeight::Triangular(baseline, spread)
+            }
+        };
+        let vein =
+            OreVein::new(block, ore.size, ore.count, height).with_extra_count(ore.extra_count);
+
+        loaded += 1;
+        match features.iter_mut().find(|(feature, _, _)| *feature == id) {
+            Some((_, old, _)) => *old = vein,
+            None => {
+                let biomes = if listing_biomes(&id).is_empty() {
+                    None
+                } else {
+                    Some(Vec::new())
+                };
+                features.push((id, vein, biomes));
+            }
+        }
+    }
+    if loaded > 0 {
+        log::info!("Loaded {} ore features from data packs", loaded);
+    }
+
+    let is_overridden = |biome: Biome| pack_biomes.iter().any(|&(pack, _)| pack == biome);
+    let veins = features.into_iter().map(|(id, vein, biomes)| {
+        let listed = listing_biomes(&id);
+        match biomes {
+            Some(biomes) => {
+                let mut biomes: Vec<Biome> = biomes
+                    .into_iter()
+                    .filter(|&biome| !is_overridden(biome))
+                    .collect();
+                biomes.extend(listed);
+                vein.in_biomes(&biomes)
+            }
+            None => {
+                let excluded: Vec<Biome> = pack_biomes
+                    .iter()
+                    .map(|&(biome, _)| biome)
+                    .filter(|biome| !listed.contains(biome))
+                    .collect();
+                vein.except_biomes(&excluded)
+            }
+        }
+    });
+    OreFinisher::new(veins)
+}
+
+/// Builds the templates and pools of jigsaw structures
+/// from data packs. Returns `None` if data packs
+/// contain no village pools, so villages are skipped.
+fn jigsaw_pools(data: &WorldgenData) -> Option<Arc<JigsawPools>> {
+    if !data
+        .template_pools
+        .keys()
+        .any(|id| id.name().starts_with("village/"))
+    {
+        return None;
+    }
+
+    let mut pools = JigsawPools::new();
+    let mut templates = 0;
+    for (id, template_data) in &data.structures {
+        match template(template_data) {
+            Ok(template) => {
+                pools.add_template(id.to_string(), template);
+                templates += 1;
+            }
+            Err(e) => log::warn!("Skipping structure template {}: {:?}", id, e),
+        }
+    }
+    for (id, pool) in &data.template_pools {
+        pools.add_pool(
+            id.to_string(),
+            TemplatePool {
+                fallback: pool.fallback.to_string(),
+                elements: pool
+                    .elements
+                    .iter()
+                    .map(|element| (pool_element(&element.element), element.weight))
+                    .collect(),
+            },
+        );
+    }
+    log::info!(
+        "Loaded {} structure templates and {} template pools from data packs",
+        templates,
+        data.template_pools.len()
+    );
+    Some(Arc::new(pools))
+}
+
+fn pool_element(element: &structure::PoolElement) -> PoolElement {
+    let single = |location: &NamespacedId, legacy| TemplatePart {
+        location: location.to_string(),
+        legacy,
+    };
+    let (parts, projection) = match element {
+        structure::PoolElement::Single {
+            location,
+            projection,
+        } => (vec![single(location, false)], projection),
+        structure::PoolElement::LegacySingle {
+            location,
+            projection,
+        } => (vec![single(location, true)], projection),
+        structure::PoolElement::List {
+            elements,
+            projection,
+        } => {
+            let parts = elements
+                .iter()
+                .flat_map(|element| match pool_element(element) {
+                    PoolElement::Templates { parts, .. } => parts,
+                    PoolElement::Empty => Vec::new(),
+                })
+                .collect();
+            (parts, projection)
+        }
+        // Feature elements aren't supported. Without
+        // templates, they never fit and are skipped.
+        structure::PoolElement::Feature { projection, .. } => (Vec::new(), projection),
+        structure::PoolElement::Empty => return PoolElement::Empty,
+    };
+    let projection = match projection {
+        structure::Projection::Rigid => Projection::Rigid,
+        structure::Projection::TerrainMatching => Projection::TerrainMatching,
+    };
+    PoolElement::Templates { parts, projection }
+}
+
+fn template(data: &StructureTemplate) -> anyhow::Result<Template> {
+    let vector = |vector: &[i32]| match *vector {
+        [x, y, z] => Ok((x, y, z)),
+        _ => Err(anyhow::anyhow!("invalid vector {:?}", vector)),
+    };
+
+    let mut blocks = Vec::new();
+    let mut jigsaws = Vec::new();
+    for template_block in &data.blocks {
+        let pos = vector(&template_block.pos)?;
+        let state = data
+            .palette()
+            .get(template_block.state as usize)
+            .with_context(|| format!("unknown palette index {}", template_block.state))?;
+        if state.name.to_string() == "minecraft:jigsaw" {
+            let nbt = template_block.nbt.clone().unwrap_or_default();
+            jigsaws.push(jigsaw(pos, state, &nbt)?);
+        } else {
+            blocks.push(TemplateBlock {
+                pos,
+                block: block(state)?,
+            });
+        }
+    }
+    Ok(Template::new(vector(&data.size)?, blocks, jigsaws))
+}
+
+fn jigsaw(
+    pos: (i32, i32, i32),
+    state: &BlockState,
+    nbt: &TemplateBlockNbt,
+) -> anyhow::Result<Jigsaw> {
+    let orientation = state
+        .properties
+        .get("orientation")
+        .map_or("north_up", String::as_str);
+    let mut directions = orientation.splitn(2, '_').map(Direction::from_name);
+    let (front, top) = match (directions.next(), directions.next()) {
+        (Some(Some(front)), Some(Some(top))) => (front, top),
+        _ => bail!("invalid jigsaw orientation {}", orientation),
+    };
+    let final_state = nbt.final_state.as_deref().unwrap_or("minecraft:air");
+    let rollable = match nbt.joint.as_deref() {
+        Some(joint) => joint == "rollable",
+        None => front != Direction::Up && front != Direction::Down,
+    };
+    let empty = || "minecraft:empty".to_owned();
+    Ok(Jigsaw {
+        pos,
+        front,
+        top,
+        name: nbt.name.clone().unwrap_or_else(empty),
+        pool: nbt.pool.clone().unwrap_or_else(empty),
+        target: nbt.target.clone().unwrap_or_else(empty),
+        final_state: parse_block_state(final_state)?,
+        rollable,
+    })
+}
+
+/// Parses a block state written as `name[key=value,...]`.
+fn parse_block_state(state: &str) -> anyhow::Result<BlockId> {
+    let (name, properties) = match state.find('[') {
+        Some(index) => (&state[..index], state[index + 1..].trim_end_matches(']')),
+        None => (state, ""),
+    };
+    let properties = properties
+        .split(',')
+        .filter_map(|property| {
+            let mut parts = property.splitn(2, '=');
+            Some((parts.next()?.to_owned(), parts.next()?.to_owned()))
+        })
+        .collect();
+    block(&BlockState {
+        name: NamespacedId::from_str(name)
+            .with_context(|| format!("invalid block state {}", state))?,
+        properties,
+    })
+}
+
+#[cfg(test)]
+mod tests {
+    use base::{chunk::BiomeStore, Chunk, ChunkPosition};
+    use worldgen::{FinishingGenerator, TopBlocks};
+
+    use super::*;
+
+    fn count_ores(finisher: &OreFinisher, biome: Biome, ore: BlockId) -> usize {
+        let mut chunk = Chunk::new(ChunkPosition::new(0, 0));
+        for x in 0..16 {
+            for z in 0..16 {
+                for y in 0..128 {
+                    chunk.set_block_at(x, y, z, BlockId::stone());
+                }
+            }
+        }
+        finisher.generate_for_chunk(&mut chunk, &BiomeStore::new(biome), &TopBlocks::new(), 0);
+
+        let mut count = 0;
+        for x in 0..16 {
+            for z in 0..16 {
+                for y in 0..128 {
+                    if chunk.block_at(x, y, z) == Some(ore) {
+                        count += 1;
+                    }
+                }
+            }
+        }
+        count
+    }
+
+    #[test]
+    fn data_pack_biomes_are_merged_over_vanilla_ores() {
+        // A data pack which only overrides the plains biome
+        let plains: datapacks::worldgen::Biome = serde_json::from_str(
+            r#"{
+                "depth": 0.125,
+                "scale": 0.05,
+                "temperature": 0.8,
+                "downfall": 0.4,
+                "features": [[], [], [], [], [], [], ["minecraft:ore_coal"]]
+            }"#,
+        )
+        .unwrap();
+        let mut data = WorldgenData::default();
+        data.biomes
+            .insert(NamespacedId::from_str("plains").unwrap(), plains);
+
+        let finisher = ore_finisher(&data);
+        assert!(count_ores(&finisher, Biome::Plains, BlockId::coal_ore()) > 0);
+        assert_eq!(count_ores(&finisher, Biome::Plains, BlockId::iron_ore()), 0);
+
+        // Other biomes keep their vanilla ores
+        assert!(count_ores(&finisher, Biome::Desert, BlockId::coal_ore()) > 0);
+        assert!(count_ores(&finisher, Biome::Desert, BlockId::iron_ore()) > 0);
+        assert!(count_ores(&finisher, Biome::Mountains, BlockId::emerald_ore()) > 0);
+    }
+
+    #[test]
+    fn data_pack_ore_features_replace_only_their_vanilla_feature() {
+        let diamonds: datapacks::worldgen::ConfiguredFeature = serde_json::from_str(
+            r#"{
+                "type": "minecraft:decorated",
+                "config": {
+                    "decorator": { "type": "minecraft:count", "config": { "count": 40 } },
+                    "feature": {
+                        "type": "minecraft:ore",
+                        "config": { "state": { "Name": "minecraft:gold_block" }, "size": 8 }
+                    }
+                }
+            }"#,
+        )
+        .unwrap();
+        let mut data = WorldgenData::default();
+        data.configured_features
+            .insert(NamespacedId::from_str("ore_diamond").unwrap(), diamonds);
+
+        let finisher = ore_finisher(&data);
+        assert_eq!(
+            count_ores(&finisher, Biome::Plains, BlockId::diamond_ore()),
+            0
+        );
+        assert!(count_ores(&finisher, Biome::Plains, BlockId::gold_block()) > 0);
+        assert!(count_ores(&finisher, Biome::Plains, BlockId::iron_ore()) > 0);
+    }
+}
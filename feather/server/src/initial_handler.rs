@@ -1,6 +1,10 @@
 //! Initial handling of a connection.
 
-use crate::{connection_worker::Worker, favicon::Favicon};
+use crate::{
+    auth::AuthResponse,
+    connection_worker::{OutgoingPacket, Worker},
+    rate_limit::Verdict,
+};
 use anyhow::bail;
 use base::{ProfileProperty, Text};
 use flume::{Receiver, Sender};
@@ -9,6 +13,7 @@ use num_bigint::BigInt;
 use once_cell::sync::Lazy;
 use protocol::{
     codec::CryptKey,
+    mapping,
     packets::{
         client::{HandshakeState, Ping},
         server::{
@@ -16,13 +21,12 @@ use protocol::{
         },
     },
     ClientHandshakePacket, ClientLoginPacket, ClientPlayPacket, ClientStatusPacket,
-    ServerLoginPacket, ServerPlayPacket, ServerStatusPacket,
+    ProtocolVersion, ServerLoginPacket, ServerStatusPacket,
 };
 use rand::rngs::OsRng;
 use rsa::{PaddingScheme, PublicKeyParts, RsaPrivateKey};
-use serde::{Deserialize, Serialize};
 use sha1::Sha1;
-use std::convert::TryInto;
+use std::{convert::TryInto, net::IpAddr, sync::Arc};
 use uuid::Uuid;
 
 use self::proxy::ProxyData;
@@ -31,6 +35,7 @@ const SERVER_NAME: &str = "Feather 1.16.5";
 const PROTOCOL_VERSION: i32 = 754;
 
 mod proxy;
+pub mod status;
 
 /// Information for a newly connected player.
 #[derive(Debug)]
@@ -40,7 +45,7 @@ pub struct NewPlayer {
     pub profile: Vec<ProfileProperty>,
 
     pub received_packets: Receiver<ClientPlayPacket>,
-    pub packets_to_send: Sender<ServerPlayPacket>,
+    pub packets_to_send: Sender<OutgoingPacket>,
 }
 
 /// Result of initial handling.
@@ -55,20 +60,43 @@ pub enum InitialHandling {
 /// Handles a connection until the protocol state is switched to Play;
 /// that is, until we send Login Success. Returns the client's information.
 pub async fn handle(worker: &mut Worker) -> anyhow::Result<InitialHandling> {
+    if let Some(ping) = worker.read_legacy_ping().await? {
+        status::respond_to_legacy_ping(worker, ping).await?;
+        return Ok(InitialHandling::Disconnect);
+    }
+
     // Get the handshake packet.
     let handshake = worker.read::<ClientHandshakePacket>().await?;
 
     let ClientHandshakePacket::Handshake(handshake) = handshake;
 
+    let version = ProtocolVersion::from_number(handshake.protocol_version)
+        .filter(|&version| mapping::is_supported(version));
+    if let Some(version) = version {
+        worker.set_version(version);
+    }
+
     match handshake.next_state {
         HandshakeState::Status => handle_status(worker).await,
         HandshakeState::Login => {
-            if handshake.protocol_version < PROTOCOL_VERSION {
+            // Behind a proxy, attempts are counted once
+            // the client's address has been forwarded.
+            if worker.options().proxy_mode.is_none()
+                && !accept_login_attempt(worker, worker.address().ip()).await
+            {
+                return Ok(InitialHandling::Disconnect);
+            }
+            if version.is_none() {
+                let supported: Vec<_> = mapping::supported_versions()
+                    .into_iter()
+                    .map(ProtocolVersion::name)
+                    .collect();
                 worker
                     .write(ServerLoginPacket::DisconnectLogin(DisconnectLogin {
-                        reason: Text::from(
-                            "Invalid protocol! The server is running on version 1.16!",
-                        )
+                        reason: Text::from(format!(
+                            "Invalid protocol! The server supports versions {}.",
+                            supported.join(", ")
+                        ))
                         .to_string(),
                     }))
                     .await
@@ -86,48 +114,31 @@ pub async fn handle(worker: &mut Worker) -> anyhow::Result<InitialHandling> {
     }
 }
 
-#[derive(Debug, Serialize)]
-struct StatusResponse<'a> {
-    version: Version,
-    players: Players,
-    description: Text,
-    #[serde(skip_serializing_if = "Option::is_none")]
-    favicon: Option<&'a str>,
-}
-
-#[derive(Debug, Serialize)]
-struct Version {
-    name: &'static str,
-    protocol: i32,
-}
-
-#[derive(Debug, Serialize)]
-struct Players {
-    max: u32,
-    online: u32,
+/// Records a login attempt from `ip`. Returns `false`
+/// if the client was disconnected for exceeding the limit.
+async fn accept_login_attempt(worker: &mut Worker, ip: IpAddr) -> bool {
+    match worker.record_login_attempt(ip) {
+        Verdict::Accept => true,
+        Verdict::Drop => false,
+        Verdict::Kick(_) => {
+            worker
+                .write(ServerLoginPacket::DisconnectLogin(DisconnectLogin {
+                    reason: Text::from("Too many login attempts! Please wait a moment.")
+                        .to_string(),
+                }))
+                .await
+                .ok();
+            false
+        }
+    }
 }
 
 async fn handle_status(worker: &mut Worker) -> anyhow::Result<InitialHandling> {
     let _request = worker.read::<ClientStatusPacket>().await?;
 
-    let payload = StatusResponse {
-        version: Version {
-            name: SERVER_NAME,
-            protocol: PROTOCOL_VERSION,
-        },
-        players: Players {
-            max: worker.options().max_players,
-            online: worker.player_count(),
-        },
-        description: Text::from(worker.options().motd.clone()),
-        favicon: worker
-            .options()
-            .favicon
-            .as_ref()
-            .map(Favicon::base64_encoded),
-    };
+    let status = status::query_status(worker).await;
     let response = Response {
-        response: serde_json::to_string(&payload)?,
+        response: status::status_response(&status)?,
     };
     worker
         .write(&ServerStatusPacket::Response(response))
@@ -163,6 +174,15 @@ async fn handle_login(
         proxy_data = Some(proxy::do_velocity_ip_forwarding(worker).await?);
     }
 
+    if let Some(proxy_data) = &proxy_data {
+        let ip = proxy_data
+            .client_ip()
+            .unwrap_or_else(|| worker.address().ip());
+        if !accept_login_attempt(worker, ip).await {
+            return Ok(InitialHandling::Disconnect);
+        }
+    }
+
     if worker.options().online_mode {
         enable_encryption(worker, login_start.name).await
     } else {
@@ -219,7 +239,7 @@ async fn enable_encryption(
     let shared_secret = do_encryption_handshake(worker).await?;
     worker.enable_encryption(shared_secret);
 
-    let response = authenticate(shared_secret, username).await?;
+    let response = authenticate(worker, shared_secret, username).await?;
 
     finish_login(worker, response).await
 }
@@ -252,26 +272,17 @@ async fn do_encryption_handshake(worker: &mut Worker) -> anyhow::Result<CryptKey
     Ok((&shared_secret[..]).try_into()?)
 }
 
-#[derive(Debug, Deserialize)]
-struct AuthResponse {
-    id: Uuid,
-    name: String,
-    properties: Vec<ProfileProperty>,
-}
-
-async fn authenticate(shared_secret: CryptKey, username: String) -> anyhow::Result<AuthResponse> {
+async fn authenticate(
+    worker: &Worker,
+    shared_secret: CryptKey,
+    username: String,
+) -> anyhow::Result<AuthResponse> {
     let server_hash = compute_server_hash(shared_secret);
+    let authenticator = Arc::clone(&worker.options().authenticator);
 
-    let response: AuthResponse = tokio::task::spawn_blocking(move || {
-        let url = format!(
-            "https://sessionserver.mojang.com/session/minecraft/hasJoined?username={}&serverId={}",
-            username, server_hash
-        );
-        let response = ureq::get(&url).call()?;
-
-        Result::<AuthResponse, anyhow::Error>::Ok(response.into_json()?)
-    })
-    .await??;
+    let response =
+        tokio::task::spawn_blocking(move || authenticator.has_joined(&username, &server_hash))
+            .await??;
 
     Ok(response)
 }
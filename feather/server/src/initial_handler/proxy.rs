@@ -1,5 +1,7 @@
 //! Proxy support for BungeeCord and Velocity.
 
+use std::net::IpAddr;
+
 use base::ProfileProperty;
 use protocol::packets::client::Handshake;
 use uuid::Uuid;
@@ -22,6 +24,14 @@ pub struct ProxyData {
     pub profile: Vec<ProfileProperty>,
 }
 
+impl ProxyData {
+    /// Parses the IP address of the client. Returns `None`
+    /// if the proxy forwarded a host name, like `localhost`.
+    pub fn client_ip(&self) -> Option<IpAddr> {
+        self.client.parse().ok()
+    }
+}
+
 /// Runs proxy forwarding and returns the client's `ProxyData`.
 pub fn do_bungee_ip_forwarding(handshake: &Handshake) -> anyhow::Result<ProxyData> {
     bungeecord::extract(handshake)
@@ -30,3 +40,51 @@ pub fn do_bungee_ip_forwarding(handshake: &Handshake) -> anyhow::Result<ProxyDat
 pub async fn do_velocity_ip_forwarding(worker: &mut Worker) -> anyhow::Result<ProxyData> {
     velocity::run(worker).await
 }
+
+#[cfg(test)]
+mod tests {
+    use std::time::{Duration, Instant};
+
+    use crate::rate_limit::{Limit, LimitAction, LimitKind, RateLimiter, Verdict};
+
+    use super::*;
+
+    fn proxy_data(client: &str) -> ProxyData {
+        ProxyData {
+            host: "10.0.0.1".to_owned(),
+            client: client.to_owned(),
+            uuid: Uuid::nil(),
+            profile: Vec::new(),
+        }
+    }
+
+    #[test]
+    fn client_ip() {
+        assert_eq!(
+            proxy_data("192.168.1.67").client_ip(),
+            Some("192.168.1.67".parse().unwrap())
+        );
+        assert_eq!(proxy_data("::1").client_ip(), Some("::1".parse().unwrap()));
+        assert_eq!(proxy_data("localhost").client_ip(), None);
+    }
+
+    #[test]
+    fn clients_behind_a_proxy_have_separate_login_limits() {
+        let limiter = RateLimiter::default();
+        let limit = Limit {
+            max: 1,
+            interval: Duration::from_secs(1),
+            action: LimitAction::Kick,
+        };
+        let now = Instant::now();
+        let steve = proxy_data("192.168.1.67").client_ip().unwrap();
+        let alex = proxy_data("192.168.1.68").client_ip().unwrap();
+
+        assert_eq!(limiter.on_login(steve, Some(&limit), now), Verdict::Accept);
+        assert_eq!(limiter.on_login(alex, Some(&limit), now), Verdict::Accept);
+        assert_eq!(
+            limiter.on_login(steve, Some(&limit), now),
+            Verdict::Kick(LimitKind::LoginAttempts)
+        );
+    }
+}
@@ -0,0 +1,237 @@
+//! Responses to server list pings, including legacy (pre-1.7) pings.
+
+use std::time::Duration;
+
+use base::Text;
+use flume::Sender;
+use quill_common::events::{PlayerSample, ServerListPingEvent};
+use serde::Serialize;
+use tokio::time::timeout;
+
+use crate::connection_worker::Worker;
+
+use super::SERVER_NAME;
+
+/// Time to wait for the game thread to fill in a status.
+const STATUS_TIMEOUT: Duration = Duration::from_millis(500);
+
+/// Protocol version reported to legacy clients, which
+/// makes them show the server as incompatible.
+const LEGACY_PROTOCOL_VERSION: i32 = 127;
+
+/// A status for the game thread to fill in, sent
+/// back through `reply` once the game has done so.
+#[derive(Debug)]
+pub struct StatusRequest {
+    pub status: ServerListPingEvent,
+    pub reply: Sender<ServerListPingEvent>,
+}
+
+/// The kind of a legacy server list ping.
+#[derive(Copy, Clone, Debug, PartialEq, Eq)]
+pub enum LegacyPing {
+    /// Sent by beta 1.8 to 1.3 clients.
+    Beta,
+    /// Sent by 1.4 to 1.6 clients, which expect more information.
+    V1_4,
+}
+
+#[derive(Debug, Serialize)]
+struct StatusResponse<'a> {
+    version: Version<'a>,
+    players: Players<'a>,
+    description: Text,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    favicon: Option<&'a str>,
+}
+
+#[derive(Debug, Serialize)]
+struct Version<'a> {
+    name: &'a str,
+    protocol: i32,
+}
+
+#[derive(Debug, Serialize)]
+struct Players<'a> {
+    max: u32,
+    online: u32,
+    #[serde(skip_serializing_if = "<[_]>::is_empty")]
+    sample: &'a [PlayerSample],
+}
+
+/// Gets the status to show to the client, giving the
+/// game thread the chance to fill it in.
+pub async fn query_status(worker: &Worker) -> ServerListPingEvent {
+    let status = default_status(worker);
+    let (reply, response) = flume::bounded(1);
+    let request = StatusRequest {
+        status: status.clone(),
+        reply,
+    };
+    if worker.status_requests().try_send(request).is_err() {
+        return status;
+    }
+
+    match timeout(STATUS_TIMEOUT, response.recv_async()).await {
+        Ok(Ok(status)) => status,
+        _ => {
+            log::debug!("Game did not respond to a status request in time");
+            status
+        }
+    }
+}
+
+fn default_status(worker: &Worker) -> ServerListPingEvent {
+    let options = worker.options();
+    ServerListPingEvent {
+        address: worker.address(),
+        motd: options.motd.clone(),
+        version_name: SERVER_NAME.to_owned(),
+        // Clients using an unsupported version are
+        // shown the internal version instead.
+        protocol_version: worker.version().number(),
+        online_players: worker.player_count(),
+        max_players: options.max_players,
+        sample: Vec::new(),
+        favicon: options
+            .favicon
+            .as_ref()
+            .map(|favicon| favicon.base64_encoded().to_owned()),
+    }
+}
+
+/// Serializes the JSON status response for `status`.
+pub fn status_response(status: &ServerListPingEvent) -> anyhow::Result<String> {
+    let response = StatusResponse {
+        version: Version {
+            name: &status.version_name,
+            protocol: status.protocol_version,
+        },
+        players: Players {
+            max: status.max_players,
+            online: status.online_players,
+            sample: &status.sample,
+        },
+        description: Text::from(status.motd.clone()),
+        favicon: status.favicon.as_deref(),
+    };
+    Ok(serde_json::to_string(&response)?)
+}
+
+/// Answers a legacy server list ping. The connection
+/// must be closed afterwards.
+pub async fn respond_to_legacy_ping(worker: &mut Worker, ping: LegacyPing) -> anyhow::Result<()> {
+    let status = query_status(worker).await;
+    worker
+        .write_raw(&legacy_status_response(&status, ping))
+        .await
+}
+
+/// Encodes the kick packet legacy clients
+/// expect as the response to their ping.
+fn legacy_status_response(status: &ServerListPingEvent, ping: LegacyPing) -> Vec<u8> {
+    let message = match ping {
+        LegacyPing::Beta => format!(
+            "{}§{}§{}",
+            strip_formatting_codes(&status.motd),
+            status.online_players,
+            status.max_players
+        ),
+        LegacyPing::V1_4 => format!(
+            "§1\0{}\0{}\0{}\0{}\0{}",
+            LEGACY_PROTOCOL_VERSION,
+            status.version_name,
+            status.motd,
+            status.online_players,
+            status.max_players
+        ),
+    };
+
+    let chars: Vec<u16> = message.encode_utf16().collect();
+    let mut bytes = vec![0xFF];
+    bytes.extend_from_slice(&(chars.len() as u16).to_be_bytes());
+    for c in chars {
+        bytes.extend_from_slice(&c.to_be_bytes());
+    }
+    bytes
+}
+
+/// Removes the formatting codes (`§` followed by a character) from `text`,
+/// since beta clients split the response at every `§`.
+fn strip_formatting_codes(text: &str) -> String {
+    let mut stripped = String::with_capacity(text.len());
+    let mut chars = text.chars();
+    while let Some(c) = chars.next() {
+        if c == '§' {
+            chars.next();
+        } else {
+            stripped.push(c);
+        }
+    }
+    stripped
+}
+
+#[cfg(test)]
+mod tests {
+    use uuid::Uuid;
+
+    use super::{super::PROTOCOL_VERSION, *};
+
+    fn status() -> ServerListPingEvent {
+        ServerListPingEvent {
+            address: "127.0.0.1:50000".parse().unwrap(),
+            motd: "§aHi".to_owned(),
+            version_name: SERVER_NAME.to_owned(),
+            protocol_version: PROTOCOL_VERSION,
+            online_players: 1,
+            max_players: 20,
+            sample: vec![PlayerSample {
+                name: "Notch".to_owned(),
+                id: Uuid::nil(),
+            }],
+            favicon: None,
+        }
+    }
+
+    #[test]
+    fn status_response_lists_sample() {
+        let response: serde_json::Value =
+            serde_json::from_str(&status_response(&status()).unwrap()).unwrap();
+        assert_eq!(response["players"]["online"], 1);
+        assert_eq!(response["players"]["sample"][0]["name"], "Notch");
+        assert_eq!(
+            response["players"]["sample"][0]["id"],
+            "00000000-0000-0000-0000-000000000000"
+        );
+        assert!(response.get("favicon").is_none());
+    }
+
+    #[test]
+    fn beta_legacy_response() {
+        let bytes = legacy_status_response(&status(), LegacyPing::Beta);
+        let message = "Hi§1§20";
+        assert_eq!(bytes[0], 0xFF);
+        assert_eq!(
+            u16::from_be_bytes([bytes[1], bytes[2]]) as usize,
+            message.encode_utf16().count()
+        );
+        let chars: Vec<u16> = bytes[3..]
+            .chunks(2)
+            .map(|c| u16::from_be_bytes([c[0], c[1]]))
+            .collect();
+        assert_eq!(String::from_utf16(&chars).unwrap(), message);
+    }
+
+    #[test]
+    fn v1_4_legacy_response() {
+        let bytes = legacy_status_response(&status(), LegacyPing::V1_4);
+        let chars: Vec<u16> = bytes[3..]
+            .chunks(2)
+            .map(|c| u16::from_be_bytes([c[0], c[1]]))
+            .collect();
+        assert_eq!(
+            String::from_utf16(&chars).unwrap(),
+            format!("§1\u{0}127\u{0}{}\u{0}§aHi\u{0}1\u{0}20", SERVER_NAME)
+        );
+    }
+}
@@ -3,31 +3,45 @@
 use std::{sync::Arc, time::Instant};
 
 use base::Position;
+use chunk_packets::ChunkPackets;
 use chunk_subscriptions::ChunkSubscriptions;
-use common::Game;
+use common::{Game, WorldId};
 use ecs::SystemExecutor;
 use flume::Receiver;
-use initial_handler::NewPlayer;
+use initial_handler::{status::StatusRequest, NewPlayer};
 use listener::Listener;
+use metrics::TrafficStats;
+use parking_lot::Mutex;
+use query::QueryStatus;
+use rcon::RconCommand;
 
+pub mod auth;
+pub mod chunk_packets;
 mod chunk_subscriptions;
 pub mod client;
+mod commands;
 pub mod config;
 mod connection_worker;
 mod entities;
 pub mod favicon;
+pub mod generator;
 mod initial_handler;
 mod listener;
+pub mod metrics;
 mod network_id_registry;
 mod options;
 mod packet_handlers;
 mod player_count;
+pub mod query;
+pub mod rate_limit;
+pub mod rcon;
 mod systems;
 
 pub use client::{Client, ClientId, Clients};
 pub use network_id_registry::NetworkId;
 pub use options::Options;
 use player_count::PlayerCount;
+use rate_limit::{RateLimitStats, RateLimiter};
 use systems::view::WaitingChunks;
 
 /// A Minecraft server.
@@ -41,13 +55,19 @@ pub struct Server {
     options: Arc<Options>,
     clients: Clients,
     new_players: Receiver<NewPlayer>,
+    status_requests: Receiver<StatusRequest>,
+    rcon_commands: Receiver<RconCommand>,
+    query_status: Arc<Mutex<QueryStatus>>,
 
     waiting_chunks: WaitingChunks,
     chunk_subscriptions: ChunkSubscriptions,
+    chunk_packets: ChunkPackets,
 
     last_keepalive_time: Instant,
 
     player_count: PlayerCount,
+    rate_limiter: RateLimiter,
+    traffic: TrafficStats,
 }
 
 impl Server {
@@ -57,9 +77,36 @@ impl Server {
     pub async fn bind(options: Options) -> anyhow::Result<Self> {
         let options = Arc::new(options);
         let player_count = PlayerCount::new(options.max_players);
+        let rate_limiter = RateLimiter::default();
+        let traffic = TrafficStats::default();
 
         let (new_players_tx, new_players) = flume::bounded(4);
-        Listener::start(Arc::clone(&options), player_count.clone(), new_players_tx).await?;
+        let (status_requests_tx, status_requests) = flume::bounded(16);
+        Listener::start(
+            Arc::clone(&options),
+            player_count.clone(),
+            rate_limiter.clone(),
+            traffic.clone(),
+            new_players_tx,
+            status_requests_tx,
+        )
+        .await?;
+
+        let (rcon_commands_tx, rcon_commands) = flume::bounded(16);
+        if let Some(rcon) = &options.rcon {
+            rcon::start(rcon.clone(), rcon_commands_tx).await?;
+        }
+
+        let query_status = Arc::new(Mutex::new(QueryStatus::default()));
+        if let Some(query) = &options.query {
+            query::start(
+                &options,
+                query,
+                player_count.clone(),
+                Arc::clone(&query_status),
+            )
+            .await?;
+        }
 
         log::info!(
             "Server is listening on {}:{}",
@@ -71,10 +118,16 @@ impl Server {
             options,
             clients: Clients::new(),
             new_players,
+            status_requests,
+            rcon_commands,
+            query_status,
             waiting_chunks: WaitingChunks::default(),
             chunk_subscriptions: ChunkSubscriptions::default(),
+            chunk_packets: ChunkPackets::default(),
             last_keepalive_time: Instant::now(),
             player_count,
+            rate_limiter,
+            traffic,
         })
     }
 
@@ -89,6 +142,16 @@ impl Server {
     pub fn player_count(&self) -> u32 {
         self.player_count.get()
     }
+
+    /// Gets the counters of clients exceeding the rate limits.
+    pub fn rate_limit_stats(&self) -> &RateLimitStats {
+        self.rate_limiter.stats()
+    }
+
+    /// Gets the counters of bytes sent and received.
+    pub fn traffic_stats(&self) -> &TrafficStats {
+        &self.traffic
+    }
 }
 
 /// Low-level functions, mostly used internally.
@@ -129,11 +192,19 @@ impl Server {
     }
 
     /// Sends a packet to all clients currently subscribed
-    /// to the given position. This function should be
+    /// to the given position in `world`. This function should be
     /// used for entity updates, block updates, etc—
     /// any packets that need to be sent only to nearby players.
-    pub fn broadcast_nearby_with(&self, position: Position, mut callback: impl FnMut(&Client)) {
-        for &client_id in self.chunk_subscriptions.subscriptions_for(position.chunk()) {
+    pub fn broadcast_nearby_with(
+        &self,
+        world: WorldId,
+        position: Position,
+        mut callback: impl FnMut(&Client),
+    ) {
+        for &client_id in self
+            .chunk_subscriptions
+            .subscriptions_for(world, position.chunk())
+        {
             if let Some(client) = self.clients.get(client_id) {
                 callback(client);
             }
@@ -5,8 +5,12 @@ use flume::Sender;
 use tokio::net::{TcpListener, TcpStream};
 
 use crate::{
-    connection_worker::Worker, initial_handler::NewPlayer, options::Options,
+    connection_worker::Worker,
+    initial_handler::{status::StatusRequest, NewPlayer},
+    metrics::TrafficStats,
+    options::Options,
     player_count::PlayerCount,
+    rate_limit::RateLimiter,
 };
 
 /// Listens for and accepts incoming connections.
@@ -14,14 +18,20 @@ pub struct Listener {
     listener: TcpListener,
     options: Arc<Options>,
     player_count: PlayerCount,
+    rate_limiter: RateLimiter,
+    traffic: TrafficStats,
     new_players: Sender<NewPlayer>,
+    status_requests: Sender<StatusRequest>,
 }
 
 impl Listener {
     pub async fn start(
         options: Arc<Options>,
         player_count: PlayerCount,
+        rate_limiter: RateLimiter,
+        traffic: TrafficStats,
         new_players: Sender<NewPlayer>,
+        status_requests: Sender<StatusRequest>,
     ) -> anyhow::Result<()> {
         let listener = TcpListener::bind(format!("{}:{}", options.bind_address, options.port))
             .await
@@ -31,7 +41,10 @@ impl Listener {
             listener,
             options,
             player_count,
+            rate_limiter,
+            traffic,
             new_players,
+            status_requests,
         };
         tokio::task::spawn(async move {
             listener.run().await;
@@ -54,7 +67,10 @@ impl Listener {
             addr,
             Arc::clone(&self.options),
             self.player_count.clone(),
+            self.rate_limiter.clone(),
+            self.traffic.clone(),
             self.new_players.clone(),
+            self.status_requests.clone(),
         );
         worker.start();
     }
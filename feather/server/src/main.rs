@@ -1,12 +1,13 @@
-use std::{cell::RefCell, rc::Rc, sync::Arc};
+use std::{cell::RefCell, fs::File, io, net::SocketAddr, path::Path, rc::Rc, time::Instant};
 
 use anyhow::Context;
-use base::anvil::level::SuperflatGeneratorOptions;
-use common::{Game, TickLoop, World};
+use base::{anvil::level::LevelData, Dimension};
+use common::{Game, TickLoop, World, Worlds};
 use ecs::SystemExecutor;
-use feather_server::{config::Config, Server};
+use feather_server::{config::Config, generator, Server};
+use libcraft_core::GameRules;
 use plugin_host::PluginManager;
-use worldgen::{ComposableGenerator, SuperflatWorldGenerator, VoidWorldGenerator, WorldGenerator};
+use protocol::mapping;
 
 mod logging;
 
@@ -25,21 +26,52 @@ async fn main() -> anyhow::Result<()> {
     }
     log::info!("Loaded config");
 
+    load_protocol_mappings(&config)?;
+
     log::info!("Creating server");
     let options = config.to_options();
     let server = Server::bind(options).await?;
 
     let game = init_game(server, &config)?;
 
+    if config.metrics.enabled {
+        let address = SocketAddr::new(config.metrics.address, config.metrics.port);
+        feather_server::metrics::serve(address, game.metrics.clone()).await?;
+    }
+
     run(game);
 
     Ok(())
 }
 
+fn load_protocol_mappings(config: &Config) -> anyhow::Result<()> {
+    let directory = Path::new(&config.network.protocol_mappings);
+    if !directory.exists() {
+        return Ok(());
+    }
+
+    let mut registry = mapping::Registry::new();
+    let versions = registry
+        .load_directory(directory)
+        .context("failed to load protocol mappings")?;
+    for version in versions {
+        if registry.is_supported(version) {
+            log::info!("Loaded protocol mappings for {}", version.name());
+        } else {
+            log::warn!(
+                "Loaded protocol mappings for {}, but its clients are rejected until its packet layouts are translated",
+                version.name()
+            );
+        }
+    }
+    mapping::install(registry)
+}
+
 fn init_game(server: Server, config: &Config) -> anyhow::Result<Game> {
     let mut game = Game::new();
     init_systems(&mut game, server);
-    init_world_source(&mut game, config);
+    init_game_rules(&mut game, config)?;
+    init_world_source(&mut game, config)?;
     init_plugin_manager(&mut game)?;
     Ok(game)
 }
@@ -58,7 +90,29 @@ fn init_systems(game: &mut Game, server: Server) {
     game.system_executor = Rc::new(RefCell::new(systems));
 }
 
-fn init_world_source(game: &mut Game, config: &Config) {
+/// Loads the game rules from the world's `level.dat`,
+/// keeping the defaults if the world is new.
+fn init_game_rules(game: &mut Game, config: &Config) -> anyhow::Result<()> {
+    let path = Path::new(&config.world.name).join("level.dat");
+    let mut file = match File::open(&path) {
+        Ok(file) => file,
+        Err(e) if e.kind() == io::ErrorKind::NotFound => return Ok(()),
+        Err(e) => return Err(e).with_context(|| format!("failed to open {}", path.display())),
+    };
+    let level = LevelData::load_from_file(&mut file)
+        .with_context(|| format!("failed to load {}", path.display()))?;
+
+    let mut rules = GameRules::default();
+    for (name, value) in &level.game_rules {
+        if !rules.set(name, value) {
+            log::warn!("Ignoring unsupported game rule {} = {}", name, value);
+        }
+    }
+    game.insert_resource(rules);
+    Ok(())
+}
+
+fn init_world_source(game: &mut Game, config: &Config) -> anyhow::Result<()> {
     // Load chunks from the world save first,
     // and fall back to generating a superflat
     // world otherwise. This is a placeholder:
@@ -66,14 +120,38 @@ fn init_world_source(game: &mut Game, config: &Config) {
 
     let seed = 42; // FIXME: load from the level file
 
-    let generator: Arc<dyn WorldGenerator> = match &config.world.generator[..] {
-        "flat" => Arc::new(SuperflatWorldGenerator::new(
-            SuperflatGeneratorOptions::default(),
-        )),
-        "void" => Arc::new(VoidWorldGenerator),
-        _ => Arc::new(ComposableGenerator::default_with_seed(seed)),
+    let overworld = create_world(config, Dimension::Overworld, None, seed)?
+        .expect("the overworld can't be disabled");
+    game.worlds = Worlds::new(overworld);
+    for &(dimension, directory) in &[(Dimension::TheNether, "DIM-1"), (Dimension::TheEnd, "DIM1")] {
+        if let Some(world) = create_world(config, dimension, Some(directory), seed)? {
+            game.worlds.add(world);
+        }
+    }
+    Ok(())
+}
+
+/// Creates the world of `dimension`, stored in `directory`
+/// inside the world directory, unless the dimension is disabled.
+fn create_world(
+    config: &Config,
+    dimension: Dimension,
+    directory: Option<&str>,
+    seed: u64,
+) -> anyhow::Result<Option<World>> {
+    let generator = match generator::create_generator(&config.world, dimension, seed)
+        .with_context(|| format!("failed to create world generator for {:?}", dimension))?
+    {
+        Some(generator) => generator,
+        None => return Ok(None),
+    };
+    let path = match directory {
+        Some(directory) => format!("{}/{}", config.world.name, directory),
+        None => config.world.name.clone(),
     };
-    game.world = World::with_gen_and_path(generator, config.world.name.clone());
+    let world = World::with_gen_and_path(generator, path, config.world.generation_threads)
+        .with_dimension(dimension);
+    Ok(Some(world))
 }
 
 fn init_plugin_manager(game: &mut Game) -> anyhow::Result<()> {
@@ -98,8 +176,10 @@ fn run(game: Game) {
 
 fn create_tick_loop(mut game: Game) -> TickLoop {
     TickLoop::new(move || {
+        let start = Instant::now();
         let systems = Rc::clone(&game.system_executor);
         systems.borrow_mut().run(&mut game);
+        common::metrics::record_tick(&game, start.elapsed());
         game.tick_count += 1;
 
         false
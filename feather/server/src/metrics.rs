@@ -0,0 +1,183 @@
+//! Serves the game's [`Metrics`] over HTTP and
+//! records metrics of the server's connections.
+
+use std::{
+    net::SocketAddr,
+    sync::{
+        atomic::{AtomicU64, Ordering},
+        Arc,
+    },
+    time::Duration,
+};
+
+use anyhow::{bail, Context};
+use common::{
+    metrics::{MetricKind, UPDATE_INTERVAL},
+    Game, Metrics,
+};
+use ecs::{SysResult, SystemExecutor};
+use tokio::{
+    io::{AsyncReadExt, AsyncWriteExt},
+    net::{TcpListener, TcpStream},
+    time::timeout,
+};
+
+use crate::{rate_limit::LimitKind, Server};
+
+/// Requests longer than this are rejected.
+const MAX_REQUEST_SIZE: usize = 8192;
+
+/// Counts the bytes sent and received by all connections.
+///
+/// Cloning is cheap and yields a handle to the same counters.
+#[derive(Clone, Default)]
+pub struct TrafficStats {
+    inner: Arc<TrafficCounters>,
+}
+
+#[derive(Default)]
+struct TrafficCounters {
+    received: AtomicU64,
+    sent: AtomicU64,
+}
+
+impl TrafficStats {
+    pub fn record_received(&self, bytes: usize) {
+        self.inner
+            .received
+            .fetch_add(bytes as u64, Ordering::Relaxed);
+    }
+
+    pub fn record_sent(&self, bytes: usize) {
+        self.inner.sent.fetch_add(bytes as u64, Ordering::Relaxed);
+    }
+
+    /// Total bytes received from clients.
+    pub fn received(&self) -> u64 {
+        self.inner.received.load(Ordering::Relaxed)
+    }
+
+    /// Total bytes sent to clients.
+    pub fn sent(&self) -> u64 {
+        self.inner.sent.load(Ordering::Relaxed)
+    }
+}
+
+pub fn register(systems: &mut SystemExecutor<Game>) {
+    systems.group::<Server>().add_system(update_metrics);
+}
+
+fn update_metrics(game: &mut Game, server: &mut Server) -> SysResult {
+    if game.tick_count % UPDATE_INTERVAL != 0 {
+        return Ok(());
+    }
+    let metrics = &game.metrics;
+
+    metrics.set_gauge(
+        "feather_clients",
+        "Connected clients.",
+        server.clients.iter().count() as f64,
+    );
+    metrics.set_counter(
+        "feather_received_bytes_total",
+        "Bytes received from clients.",
+        server.traffic.received() as f64,
+    );
+    metrics.set_counter(
+        "feather_sent_bytes_total",
+        "Bytes sent to clients.",
+        server.traffic.sent() as f64,
+    );
+
+    let rate_limits = server.rate_limiter.stats();
+    metrics.set_labeled(
+        MetricKind::Counter,
+        "feather_rate_limit_violations_total",
+        "Times clients exceeded each rate limit.",
+        "limit",
+        LimitKind::ALL
+            .iter()
+            .map(|&kind| (kind.name().to_owned(), rate_limits.violations(kind) as f64)),
+    );
+    metrics.set_counter(
+        "feather_rate_limit_dropped_packets_total",
+        "Packets dropped for exceeding a rate limit.",
+        rate_limits.dropped_packets() as f64,
+    );
+    metrics.set_counter(
+        "feather_rate_limit_kicks_total",
+        "Clients kicked for exceeding a rate limit.",
+        rate_limits.kicks() as f64,
+    );
+
+    Ok(())
+}
+
+/// Starts serving `metrics` in the Prometheus text
+/// format at `http://<address>/metrics`.
+///
+/// Must be called within the context of a Tokio runtime.
+pub async fn serve(address: SocketAddr, metrics: Metrics) -> anyhow::Result<()> {
+    let listener = TcpListener::bind(address)
+        .await
+        .with_context(|| format!("failed to bind the metrics endpoint to {}", address))?;
+    log::info!("Serving metrics on http://{}/metrics", address);
+
+    tokio::task::spawn(async move {
+        loop {
+            if let Ok((stream, _)) = listener.accept().await {
+                let metrics = metrics.clone();
+                tokio::task::spawn(async move {
+                    if let Err(e) = respond(stream, &metrics).await {
+                        log::debug!("Failed to serve metrics: {:?}", e);
+                    }
+                });
+            }
+        }
+    });
+    Ok(())
+}
+
+/// Answers a single HTTP request.
+async fn respond(mut stream: TcpStream, metrics: &Metrics) -> anyhow::Result<()> {
+    // Only the request line matters; the headers are skipped.
+    let mut request = Vec::new();
+    let mut buffer = [0; 1024];
+    while !request.windows(4).any(|window| window == b"\r\n\r\n") {
+        let read_bytes = timeout(Duration::from_secs(5), stream.read(&mut buffer)).await??;
+        if read_bytes == 0 {
+            break;
+        }
+        request.extend_from_slice(&buffer[..read_bytes]);
+        if request.len() > MAX_REQUEST_SIZE {
+            bail!("request is longer than {} bytes", MAX_REQUEST_SIZE);
+        }
+    }
+
+    let request = String::from_utf8_lossy(&request);
+    let mut request_line = request.split_whitespace();
+    let method = request_line.next();
+    let path = request_line
+        .next()
+        .and_then(|target| target.split('?').next());
+    let (status, body) = match (method, path) {
+        (Some("GET"), Some("/metrics")) => ("200 OK", metrics.render()),
+        _ => (
+            "404 Not Found",
+            "Metrics are served at /metrics\n".to_owned(),
+        ),
+    };
+
+    let response = format!(
+        "HTTP/1.1 {}\r\n\
+         Content-Type: text/plain; version=0.0.4; charset=utf-8\r\n\
+         Content-Length: {}\r\n\
+         Connection: close\r\n\r\n{}",
+        status,
+        body.len(),
+        body
+    );
+    stream.write_all(response.as_bytes()).await?;
+    stream.shutdown().await?;
+    Ok(())
+}
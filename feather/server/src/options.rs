@@ -1,6 +1,11 @@
+use std::sync::Arc;
+
 use base::Gamemode;
 
-use crate::favicon::Favicon;
+use crate::{
+    auth::Authenticator, favicon::Favicon, query::QueryOptions, rate_limit::RateLimits,
+    rcon::RconOptions,
+};
 
 /// Options for building a [`Server`](crate::Server).
 #[derive(Debug, Clone)]
@@ -17,17 +22,25 @@ pub struct Options {
 
     /// Whether the server should authenticate players.
     pub online_mode: bool,
+    /// Verifies the identity of players in online mode.
+    pub authenticator: Arc<dyn Authenticator>,
 
     /// The maximum view distance, which determines
     /// how far players can see.
     pub view_distance: u32,
 
+    /// Maximum number of chunks to send to each client per tick.
+    pub chunks_per_tick: usize,
+
     /// Maximum number of players to allow on the server.
     pub max_players: u32,
 
     /// The default gamemode for new players.
     pub default_gamemode: Gamemode,
 
+    /// Usernames of the players allowed to run commands.
+    pub operators: Vec<String>,
+
     /// Proxy IP forwarding mode
     pub proxy_mode: Option<ProxyMode>,
     // HMAC key used with Velocity IP forwarding.
@@ -35,6 +48,15 @@ pub struct Options {
 
     /// Packet size threshold at which to compress data
     pub compression_threshold: Option<usize>,
+
+    /// Limits on what clients may send.
+    pub rate_limits: RateLimits,
+
+    /// Where to accept RCON connections, if anywhere.
+    pub rcon: Option<RconOptions>,
+
+    /// Where to answer UDP queries, if anywhere.
+    pub query: Option<QueryOptions>,
 }
 
 #[derive(Debug, Copy, Clone, PartialEq, Eq)]
@@ -1,5 +1,5 @@
 use base::{Position, Text};
-use common::{chat::ChatKind, Game};
+use common::{chat::ChatKind, view::ViewDistance, Game, WorldId};
 use ecs::{Entity, EntityRef, SysResult};
 use interaction::{
     handle_held_item_change, handle_interact_entity, handle_player_block_placement,
@@ -12,9 +12,9 @@ use protocol::{
     },
     ClientPlayPacket,
 };
-use quill_common::components::Name;
+use quill_common::components::{Name, Ping};
 
-use crate::{NetworkId, Server};
+use crate::{ClientId, NetworkId, Server};
 
 mod entity_action;
 mod interaction;
@@ -45,7 +45,10 @@ pub fn handle_packet(
 
         ClientPlayPacket::Animation(packet) => handle_animation(server, player, packet),
 
-        ClientPlayPacket::ChatMessage(packet) => handle_chat_message(game, player, packet),
+        ClientPlayPacket::ChatMessage(packet) => match packet.message.strip_prefix('/') {
+            Some(command) => crate::commands::handle_command(game, player_id, command),
+            None => handle_chat_message(game, player, packet),
+        },
 
         ClientPlayPacket::PlayerDigging(packet) => {
             handle_player_digging(game, server, packet, player_id)
@@ -77,6 +80,12 @@ pub fn handle_packet(
             entity_action::handle_entity_action(game, player_id, packet)
         }
 
+        ClientPlayPacket::KeepAlive(packet) => handle_keep_alive(server, player, packet),
+
+        ClientPlayPacket::ClientStatus(client::ClientStatus::PerformRespawn) => {
+            common::damage::respawn(game, player_id)
+        }
+
         ClientPlayPacket::TeleportConfirm(_)
         | ClientPlayPacket::QueryBlockNbt(_)
         | ClientPlayPacket::SetDifficulty(_)
@@ -89,7 +98,6 @@ pub fn handle_packet(
         | ClientPlayPacket::EditBook(_)
         | ClientPlayPacket::QueryEntityNbt(_)
         | ClientPlayPacket::GenerateStructure(_)
-        | ClientPlayPacket::KeepAlive(_)
         | ClientPlayPacket::LockDifficulty(_)
         | ClientPlayPacket::VehicleMove(_)
         | ClientPlayPacket::SteerBoat(_)
@@ -118,6 +126,7 @@ fn handle_animation(
     player: EntityRef,
     packet: client::Animation,
 ) -> SysResult {
+    let world = *player.get::<WorldId>()?;
     let pos = *player.get::<Position>()?;
     let network_id = *player.get::<NetworkId>()?;
 
@@ -126,12 +135,22 @@ fn handle_animation(
         Hand::Off => Animation::SwingOffhand,
     };
 
-    server.broadcast_nearby_with(pos, |client| {
+    server.broadcast_nearby_with(world, pos, |client| {
         client.send_entity_animation(network_id, animation.clone())
     });
     Ok(())
 }
 
+fn handle_keep_alive(server: &Server, player: EntityRef, packet: client::KeepAlive) -> SysResult {
+    let client_id = *player.get::<ClientId>()?;
+    if let Some(client) = server.clients.get(client_id) {
+        if let Some(rtt) = client.handle_keepalive(packet.id) {
+            player.get_mut::<Ping>()?.record(rtt);
+        }
+    }
+    Ok(())
+}
+
 fn handle_chat_message(game: &Game, player: EntityRef, packet: client::ChatMessage) -> SysResult {
     let name = player.get::<Name>()?;
     let message = Text::translate_with("chat.type.text", vec![name.to_string(), packet.message]);
@@ -139,6 +158,9 @@ fn handle_chat_message(game: &Game, player: EntityRef, packet: client::ChatMessa
     Ok(())
 }
 
+/// The lowest view distance the server allows.
+const MIN_VIEW_DISTANCE: u32 = 2;
+
 fn handle_client_settings(
     server: &mut Server,
     player: EntityRef,
@@ -148,5 +170,9 @@ fn handle_client_settings(
     server.broadcast_with(|client| {
         client.send_player_model_flags(network_id, packet.displayed_skin_parts)
     });
+
+    let max_view_distance = server.options.view_distance.max(MIN_VIEW_DISTANCE);
+    let view_distance = u32::from(packet.view_distance).clamp(MIN_VIEW_DISTANCE, max_view_distance);
+    player.get_mut::<ViewDistance>()?.0 = view_distance;
     Ok(())
 }
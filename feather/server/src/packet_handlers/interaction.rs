@@ -1,8 +1,9 @@
 use crate::{ClientId, NetworkId, Server};
 use base::inventory::{SLOT_HOTBAR_OFFSET, SLOT_OFFHAND};
 use common::entities::player::HotbarSlot;
+use common::experience;
 use common::interactable::InteractableRegistry;
-use common::{Game, Window};
+use common::{Game, Window, WorldId};
 use ecs::{Entity, EntityRef, SysResult};
 use libcraft_core::{BlockFace as LibcraftBlockFace, Hand};
 use libcraft_core::{InteractionType, Vec3f};
@@ -54,7 +55,8 @@ pub fn handle_player_block_placement(
     );
 
     let block_kind = {
-        let result = game.block(packet.position);
+        let world = *game.ecs.get::<WorldId>(player)?;
+        let result = game.block(world, packet.position);
         match result {
             Some(block) => block.kind(),
             None => {
@@ -120,7 +122,13 @@ pub fn handle_player_digging(
     log::trace!("Got player digging with status {:?}", packet.status);
     match packet.status {
         PlayerDiggingStatus::StartDigging | PlayerDiggingStatus::CancelDigging => {
-            game.break_block(packet.position);
+            let world = *game.ecs.get::<WorldId>(player)?;
+            let block = game.block(world, packet.position);
+            if let Some(block) = block {
+                if game.break_block(world, packet.position) {
+                    experience::drop_block_experience(game, player, packet.position, block.kind())?;
+                }
+            }
             Ok(())
         }
         PlayerDiggingStatus::SwapItemInHand => {
@@ -1,8 +1,11 @@
+use std::convert::TryFrom;
+
 use anyhow::bail;
-use base::Gamemode;
-use common::{window::BackingWindow, Window};
+use base::{Area, Gamemode, Item};
+use common::{experience, window::BackingWindow, Window};
 use ecs::{EntityRef, SysResult};
 use protocol::packets::client::{ClickWindow, CreativeInventoryAction};
+use quill_common::components::Experience;
 
 use crate::{ClientId, Server};
 
@@ -63,6 +66,7 @@ pub fn handle_click_window(
 
 fn _handle_click_window(player: &EntityRef, packet: &ClickWindow) -> SysResult {
     let mut window = player.get_mut::<Window>()?;
+    let furnace_output = furnace_output(&window, packet.slot);
     match packet.mode {
         0 => match packet.button {
             0 => window.left_click(packet.slot as usize)?,
@@ -80,5 +84,29 @@ fn _handle_click_window(player: &EntityRef, packet: &ClickWindow) -> SysResult {
         _ => bail!("unsupported window click mode"),
     };
 
+    if let Some((item, count)) = furnace_output {
+        let remaining = window.item(packet.slot as usize)?;
+        let left = if remaining.item_kind() == Some(item) {
+            remaining.count()
+        } else {
+            0
+        };
+        let taken = count.saturating_sub(left);
+        let points = experience::smelting_experience_points(item, taken, &mut rand::thread_rng());
+        player.get_mut::<Experience>()?.add_points(points);
+    }
+
     Ok(())
 }
+
+/// Returns the item and count in `slot` if it is
+/// the output slot of a furnace, blast furnace or smoker.
+fn furnace_output(window: &Window, slot: i16) -> Option<(Item, u32)> {
+    let index = usize::try_from(slot).ok()?;
+    let (_, area, _) = window.inner().index_to_slot(index)?;
+    if area != Area::FurnaceOutput {
+        return None;
+    }
+    let stack = window.item(index).ok()?;
+    Some((stack.item_kind()?, stack.count()))
+}
@@ -0,0 +1,383 @@
+//! A listener for the UDP query protocol (GameSpy4),
+//! polled by server lists and monitoring tools.
+//!
+//! Clients first request a challenge token, which must
+//! accompany their requests for a basic or full stat.
+//! See <https://wiki.vg/Query> for the format.
+
+use std::{
+    cell::RefCell,
+    net::SocketAddr,
+    rc::Rc,
+    sync::Arc,
+    time::{Duration, Instant},
+};
+
+use ahash::AHashMap;
+use anyhow::Context;
+use common::Game;
+use ecs::{SysResult, SystemExecutor};
+use parking_lot::Mutex;
+use plugin_host::PluginManager;
+use protocol::ProtocolVersion;
+use rand::Rng;
+use tokio::net::UdpSocket;
+
+use crate::{player_count::PlayerCount, Options, Server};
+
+const HANDSHAKE: u8 = 9;
+const STAT: u8 = 0;
+
+/// Bits of the session ID which clients may use.
+const SESSION_ID_MASK: i32 = 0x0F0F_0F0F;
+
+/// How long a challenge token stays valid.
+const CHALLENGE_LIFETIME: Duration = Duration::from_secs(30);
+
+/// Most challenge tokens to keep at a time. Handshakes
+/// are ignored once reached, until tokens expire.
+const MAX_CHALLENGES: usize = 4096;
+
+/// Number of ticks between updates of the player and plugin lists.
+const STATUS_UPDATE_INTERVAL: u64 = 20;
+
+/// Precedes the key-value section of a full stat.
+const FULL_STAT_PADDING: &[u8] = b"splitnum\0\x80\0";
+
+/// Precedes the player section of a full stat.
+const PLAYERS_PADDING: &[u8] = b"\x01player_\0\0";
+
+/// Where to listen for queries.
+#[derive(Debug, Clone)]
+pub struct QueryOptions {
+    pub address: SocketAddr,
+    /// The world name reported as the map.
+    pub map: String,
+}
+
+/// The parts of a query response the game thread fills in.
+#[derive(Debug, Default)]
+pub struct QueryStatus {
+    players: Vec<String>,
+    plugins: String,
+}
+
+/// Starts answering queries on `query.address`.
+///
+/// Must be called within the context of a Tokio runtime.
+pub async fn start(
+    options: &Options,
+    query: &QueryOptions,
+    player_count: PlayerCount,
+    status: Arc<Mutex<QueryStatus>>,
+) -> anyhow::Result<()> {
+    let socket = UdpSocket::bind(query.address)
+        .await
+        .with_context(|| format!("failed to bind query listener to {}", query.address))?;
+    log::info!("Query is listening on {}", query.address);
+
+    let mut responder = Responder {
+        motd: options.motd.clone(),
+        map: query.map.clone(),
+        max_players: options.max_players,
+        host_ip: options.bind_address.clone(),
+        host_port: options.port,
+        player_count,
+        status,
+        challenges: AHashMap::new(),
+    };
+    tokio::task::spawn(async move {
+        let mut buffer = [0; 1500];
+        loop {
+            let (length, address) = match socket.recv_from(&mut buffer).await {
+                Ok(received) => received,
+                Err(e) => {
+                    log::debug!("Failed to receive query: {:?}", e);
+                    continue;
+                }
+            };
+            if let Some(response) = responder.respond(&buffer[..length], address, Instant::now()) {
+                if let Err(e) = socket.send_to(&response, address).await {
+                    log::debug!("Failed to answer query from {}: {:?}", address, e);
+                }
+            }
+        }
+    });
+    Ok(())
+}
+
+/// A challenge token handed out to an address.
+struct Challenge {
+    token: i32,
+    created_at: Instant,
+}
+
+struct Responder {
+    motd: String,
+    map: String,
+    max_players: u32,
+    host_ip: String,
+    host_port: u16,
+    player_count: PlayerCount,
+    status: Arc<Mutex<QueryStatus>>,
+    challenges: AHashMap<SocketAddr, Challenge>,
+}
+
+impl Responder {
+    /// Gets the response to `request`, or `None` if
+    /// the request is malformed or has an invalid token.
+    fn respond(&mut self, request: &[u8], address: SocketAddr, now: Instant) -> Option<Vec<u8>> {
+        let (kind, session_id, payload) = match request {
+            [0xFE, 0xFD, kind, a, b, c, d, payload @ ..] => (
+                *kind,
+                i32::from_be_bytes([*a, *b, *c, *d]) & SESSION_ID_MASK,
+                payload,
+            ),
+            _ => return None,
+        };
+
+        let mut response = vec![kind];
+        response.extend_from_slice(&session_id.to_be_bytes());
+        match (kind, payload) {
+            (HANDSHAKE, []) => {
+                let token = self.create_challenge(address, now)?;
+                write_string(&mut response, &token.to_string());
+            }
+            (STAT, [a, b, c, d, rest @ ..]) => {
+                let token = i32::from_be_bytes([*a, *b, *c, *d]);
+                if !self.is_challenge_valid(address, token, now) {
+                    return None;
+                }
+                match rest.len() {
+                    0 => self.write_basic_stat(&mut response),
+                    4 => self.write_full_stat(&mut response),
+                    _ => return None,
+                }
+            }
+            _ => return None,
+        }
+        Some(response)
+    }
+
+    fn create_challenge(&mut self, address: SocketAddr, now: Instant) -> Option<i32> {
+        if self.challenges.len() >= MAX_CHALLENGES {
+            self.challenges
+                .retain(|_, challenge| now - challenge.created_at < CHALLENGE_LIFETIME);
+            if self.challenges.len() >= MAX_CHALLENGES {
+                return None;
+            }
+        }
+        let token = rand::thread_rng().gen_range(0..1 << 24);
+        self.challenges.insert(
+            address,
+            Challenge {
+                token,
+                created_at: now,
+            },
+        );
+        Some(token)
+    }
+
+    fn is_challenge_valid(&self, address: SocketAddr, token: i32, now: Instant) -> bool {
+        self.challenges.get(&address).map_or(false, |challenge| {
+            challenge.token == token && now - challenge.created_at < CHALLENGE_LIFETIME
+        })
+    }
+
+    fn write_basic_stat(&self, output: &mut Vec<u8>) {
+        write_string(output, &self.motd);
+        write_string(output, "SMP");
+        write_string(output, &self.map);
+        write_string(output, &self.player_count.get().to_string());
+        write_string(output, &self.max_players.to_string());
+        output.extend_from_slice(&self.host_port.to_le_bytes());
+        write_string(output, &self.host_ip);
+    }
+
+    fn write_full_stat(&self, output: &mut Vec<u8>) {
+        let status = self.status.lock();
+
+        output.extend_from_slice(FULL_STAT_PADDING);
+        let values: [(&str, &str); 10] = [
+            ("hostname", self.motd.as_str()),
+            ("gametype", "SMP"),
+            ("game_id", "MINECRAFT"),
+            ("version", ProtocolVersion::INTERNAL.name()),
+            ("plugins", &status.plugins),
+            ("map", &self.map),
+            ("numplayers", &self.player_count.get().to_string()),
+            ("maxplayers", &self.max_players.to_string()),
+            ("hostport", &self.host_port.to_string()),
+            ("hostip", &self.host_ip),
+        ];
+        for (key, value) in values.iter() {
+            write_string(output, key);
+            write_string(output, value);
+        }
+        output.push(0);
+
+        output.extend_from_slice(PLAYERS_PADDING);
+        for player in &status.players {
+            write_string(output, player);
+        }
+        output.push(0);
+    }
+}
+
+/// Writes a null-terminated string, leaving
+/// out null characters inside the string.
+fn write_string(output: &mut Vec<u8>, string: &str) {
+    output.extend(string.bytes().filter(|&byte| byte != 0));
+    output.push(0);
+}
+
+pub fn register(systems: &mut SystemExecutor<Game>) {
+    systems.group::<Server>().add_system(update_query_status);
+}
+
+fn update_query_status(game: &mut Game, server: &mut Server) -> SysResult {
+    if server.options.query.is_none() || game.tick_count % STATUS_UPDATE_INTERVAL != 0 {
+        return Ok(());
+    }
+
+    let players = server
+        .clients
+        .iter()
+        .map(|client| client.username().to_owned())
+        .collect();
+    let plugins = plugin_list(game);
+    *server.query_status.lock() = QueryStatus { players, plugins };
+    Ok(())
+}
+
+/// Lists plugins in the format Bukkit uses, like
+/// `Feather 1.16.5: WorldEdit 5.3; Essentials 2.9`.
+fn plugin_list(game: &Game) -> String {
+    let mut list = format!("Feather {}", ProtocolVersion::INTERNAL.name());
+    if let Ok(plugin_manager) = game.resources.get::<Rc<RefCell<PluginManager>>>() {
+        let plugins: Vec<String> = plugin_manager
+            .borrow()
+            .plugins()
+            .map(|plugin| format!("{} {}", plugin.metadata().name, plugin.metadata().version))
+            .collect();
+        if !plugins.is_empty() {
+            list.push_str(": ");
+            list.push_str(&plugins.join("; "));
+        }
+    }
+    list
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn responder() -> Responder {
+        let player_count = PlayerCount::new(16);
+        player_count.try_add_player().unwrap();
+        Responder {
+            motd: "A Feather server".to_owned(),
+            map: "world".to_owned(),
+            max_players: 16,
+            host_ip: "127.0.0.1".to_owned(),
+            host_port: 25565,
+            player_count,
+            status: Arc::new(Mutex::new(QueryStatus {
+                players: vec!["Steve".to_owned()],
+                plugins: "Feather 1.16.5".to_owned(),
+            })),
+            challenges: AHashMap::new(),
+        }
+    }
+
+    fn address() -> SocketAddr {
+        "127.0.0.1:50000".parse().unwrap()
+    }
+
+    fn request(kind: u8, payload: &[u8]) -> Vec<u8> {
+        let mut request = vec![0xFE, 0xFD, kind, 0x01, 0x02, 0x03, 0x04];
+        request.extend_from_slice(payload);
+        request
+    }
+
+    /// Performs a handshake and returns the challenge token.
+    fn handshake(responder: &mut Responder, now: Instant) -> i32 {
+        let response = responder
+            .respond(&request(HANDSHAKE, &[]), address(), now)
+            .unwrap();
+        assert_eq!(&response[..5], &[HANDSHAKE, 0x01, 0x02, 0x03, 0x04]);
+        let token = std::str::from_utf8(&response[5..response.len() - 1]).unwrap();
+        token.parse().unwrap()
+    }
+
+    #[test]
+    fn basic_stat() {
+        let mut responder = responder();
+        let now = Instant::now();
+        let token = handshake(&mut responder, now);
+
+        let response = responder
+            .respond(&request(STAT, &token.to_be_bytes()), address(), now)
+            .unwrap();
+        let mut expected = vec![STAT, 0x01, 0x02, 0x03, 0x04];
+        expected.extend_from_slice(b"A Feather server\0SMP\0world\x001\x0016\0");
+        expected.extend_from_slice(&25565u16.to_le_bytes());
+        expected.extend_from_slice(b"127.0.0.1\0");
+        assert_eq!(response, expected);
+    }
+
+    #[test]
+    fn full_stat() {
+        let mut responder = responder();
+        let now = Instant::now();
+        let token = handshake(&mut responder, now);
+
+        let mut payload = token.to_be_bytes().to_vec();
+        payload.extend_from_slice(&[0; 4]);
+        let response = responder
+            .respond(&request(STAT, &payload), address(), now)
+            .unwrap();
+
+        let body = &response[5..];
+        assert!(body.starts_with(FULL_STAT_PADDING));
+        let values = &body[FULL_STAT_PADDING.len()..];
+        assert!(values.starts_with(b"hostname\0A Feather server\0gametype\0SMP\0"));
+        assert!(body.ends_with(b"hostip\x00127.0.0.1\0\0\x01player_\0\0Steve\0\0"));
+    }
+
+    #[test]
+    fn invalid_challenges() {
+        let mut responder = responder();
+        let now = Instant::now();
+        assert!(responder
+            .respond(&request(STAT, &0i32.to_be_bytes()), address(), now)
+            .is_none());
+
+        let token = handshake(&mut responder, now);
+        let wrong_token = (token + 1).to_be_bytes();
+        assert!(responder
+            .respond(&request(STAT, &wrong_token), address(), now)
+            .is_none());
+        let other_address = "127.0.0.1:50001".parse().unwrap();
+        assert!(responder
+            .respond(&request(STAT, &token.to_be_bytes()), other_address, now)
+            .is_none());
+        assert!(responder
+            .respond(
+                &request(STAT, &token.to_be_bytes()),
+                address(),
+                now + CHALLENGE_LIFETIME
+            )
+            .is_none());
+    }
+
+    #[test]
+    fn session_id_is_masked() {
+        let mut responder = responder();
+        let request = [0xFE, 0xFD, HANDSHAKE, 0xFF, 0xFF, 0xFF, 0xFF];
+        let response = responder
+            .respond(&request, address(), Instant::now())
+            .unwrap();
+        assert_eq!(&response[1..5], &[0x0F, 0x0F, 0x0F, 0x0F]);
+    }
+}
@@ -0,0 +1,454 @@
+//! Limits on what clients may send, protecting the server from floods.
+
+use std::{
+    collections::HashMap,
+    net::{IpAddr, SocketAddr},
+    sync::{
+        atomic::{AtomicU64, Ordering},
+        Arc, Mutex,
+    },
+    time::{Duration, Instant},
+};
+
+use protocol::ClientPlayPacket;
+use serde::Deserialize;
+
+/// What happens when a client exceeds a limit.
+#[derive(Copy, Clone, Debug, PartialEq, Eq, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum LimitAction {
+    /// Log a warning, but process the packet anyway.
+    Log,
+    /// Ignore the packet.
+    Drop,
+    /// Disconnect the client.
+    Kick,
+}
+
+/// An amount allowed per interval.
+#[derive(Copy, Clone, Debug, PartialEq, Eq)]
+pub struct Limit {
+    pub max: u64,
+    pub interval: Duration,
+    pub action: LimitAction,
+}
+
+/// The limits applied to every connection. A limit of `None` is disabled.
+#[derive(Copy, Clone, Debug, PartialEq, Eq)]
+pub struct RateLimits {
+    /// Maximum length of a packet in bytes. Clients
+    /// sending longer packets are always kicked.
+    pub max_packet_size: Option<usize>,
+    /// Packets a client may send while playing.
+    pub packets: Option<Limit>,
+    /// Bytes a client may send.
+    pub bytes: Option<Limit>,
+    /// Chat messages and commands a client may send.
+    pub chat_messages: Option<Limit>,
+    /// Logins an IP address may attempt.
+    pub login_attempts: Option<Limit>,
+}
+
+impl RateLimits {
+    /// Limits which let clients send anything.
+    pub fn unlimited() -> Self {
+        Self {
+            max_packet_size: None,
+            packets: None,
+            bytes: None,
+            chat_messages: None,
+            login_attempts: None,
+        }
+    }
+}
+
+/// The kinds of limits.
+#[derive(Copy, Clone, Debug, PartialEq, Eq, Hash)]
+pub enum LimitKind {
+    PacketSize,
+    Packets,
+    Bytes,
+    ChatMessages,
+    LoginAttempts,
+}
+
+impl LimitKind {
+    pub const ALL: [LimitKind; 5] = [
+        LimitKind::PacketSize,
+        LimitKind::Packets,
+        LimitKind::Bytes,
+        LimitKind::ChatMessages,
+        LimitKind::LoginAttempts,
+    ];
+
+    pub fn name(self) -> &'static str {
+        match self {
+            LimitKind::PacketSize => "packet_size",
+            LimitKind::Packets => "packets",
+            LimitKind::Bytes => "bytes",
+            LimitKind::ChatMessages => "chat_messages",
+            LimitKind::LoginAttempts => "login_attempts",
+        }
+    }
+}
+
+/// Counts how often clients exceeded the limits.
+///
+/// Can be cloned to create a new handle.
+#[derive(Clone, Debug, Default)]
+pub struct RateLimitStats {
+    inner: Arc<StatsInner>,
+}
+
+#[derive(Debug, Default)]
+struct StatsInner {
+    violations: [AtomicU64; 5],
+    dropped_packets: AtomicU64,
+    kicks: AtomicU64,
+}
+
+impl RateLimitStats {
+    /// Gets the number of times a limit of the given kind was exceeded.
+    pub fn violations(&self, kind: LimitKind) -> u64 {
+        self.inner.violations[kind as usize].load(Ordering::Relaxed)
+    }
+
+    /// Gets the number of packets dropped because of a limit.
+    pub fn dropped_packets(&self) -> u64 {
+        self.inner.dropped_packets.load(Ordering::Relaxed)
+    }
+
+    /// Gets the number of clients kicked because of a limit.
+    pub fn kicks(&self) -> u64 {
+        self.inner.kicks.load(Ordering::Relaxed)
+    }
+
+    pub(crate) fn record_violation(&self, kind: LimitKind) {
+        self.inner.violations[kind as usize].fetch_add(1, Ordering::Relaxed);
+    }
+
+    pub(crate) fn record_kick(&self) {
+        self.inner.kicks.fetch_add(1, Ordering::Relaxed);
+    }
+
+    fn record_dropped_packet(&self) {
+        self.inner.dropped_packets.fetch_add(1, Ordering::Relaxed);
+    }
+}
+
+/// The outcome of checking a limit.
+#[derive(Copy, Clone, Debug, PartialEq, Eq)]
+pub enum Verdict {
+    Accept,
+    Drop,
+    Kick(LimitKind),
+}
+
+/// Counts an amount over a fixed interval.
+#[derive(Debug)]
+struct Window {
+    start: Instant,
+    count: u64,
+    warned: bool,
+}
+
+impl Window {
+    fn new(now: Instant) -> Self {
+        Self {
+            start: now,
+            count: 0,
+            warned: false,
+        }
+    }
+
+    fn is_expired(&self, limit: &Limit, now: Instant) -> bool {
+        now.saturating_duration_since(self.start) >= limit.interval
+    }
+
+    /// Adds `amount`, returning whether the limit is exceeded.
+    fn add(&mut self, limit: &Limit, amount: u64, now: Instant) -> bool {
+        if self.is_expired(limit, now) {
+            *self = Window::new(now);
+        }
+        self.count += amount;
+        self.count > limit.max
+    }
+}
+
+fn check(
+    kind: LimitKind,
+    limit: Option<&Limit>,
+    window: &mut Window,
+    amount: u64,
+    now: Instant,
+    source: &dyn std::fmt::Display,
+    stats: &RateLimitStats,
+) -> Verdict {
+    let limit = match limit {
+        Some(limit) => limit,
+        None => return Verdict::Accept,
+    };
+    if !window.add(limit, amount, now) {
+        return Verdict::Accept;
+    }
+
+    stats.record_violation(kind);
+    match limit.action {
+        LimitAction::Log => {
+            // Warn once per interval to keep floods out of the log.
+            if !window.warned {
+                window.warned = true;
+                log::warn!("{} exceeded the {} limit", source, kind.name());
+            }
+            Verdict::Accept
+        }
+        LimitAction::Drop => Verdict::Drop,
+        LimitAction::Kick => Verdict::Kick(kind),
+    }
+}
+
+/// Applies the limits to the traffic of one connection.
+#[derive(Debug)]
+pub struct ConnectionLimiter {
+    address: SocketAddr,
+    limits: RateLimits,
+    stats: RateLimitStats,
+    packets: Window,
+    bytes: Window,
+    chat_messages: Window,
+    /// Whether the bytes limit requires dropping packets.
+    dropping: bool,
+}
+
+impl ConnectionLimiter {
+    fn new(address: SocketAddr, limits: RateLimits, stats: RateLimitStats) -> Self {
+        let now = Instant::now();
+        Self {
+            address,
+            limits,
+            stats,
+            packets: Window::new(now),
+            bytes: Window::new(now),
+            chat_messages: Window::new(now),
+            dropping: false,
+        }
+    }
+
+    pub fn stats(&self) -> &RateLimitStats {
+        &self.stats
+    }
+
+    /// Records bytes received from the client.
+    pub fn on_bytes(&mut self, amount: usize, now: Instant) -> Verdict {
+        let verdict = check(
+            LimitKind::Bytes,
+            self.limits.bytes.as_ref(),
+            &mut self.bytes,
+            amount as u64,
+            now,
+            &self.address,
+            &self.stats,
+        );
+        self.dropping = verdict == Verdict::Drop;
+        verdict
+    }
+
+    /// Records a packet received from a playing client.
+    pub fn on_packet(&mut self, packet: &ClientPlayPacket, now: Instant) -> Verdict {
+        let mut verdict = check(
+            LimitKind::Packets,
+            self.limits.packets.as_ref(),
+            &mut self.packets,
+            1,
+            now,
+            &self.address,
+            &self.stats,
+        );
+        if verdict == Verdict::Accept {
+            if let ClientPlayPacket::ChatMessage(_) = packet {
+                verdict = check(
+                    LimitKind::ChatMessages,
+                    self.limits.chat_messages.as_ref(),
+                    &mut self.chat_messages,
+                    1,
+                    now,
+                    &self.address,
+                    &self.stats,
+                );
+            }
+        }
+        if verdict == Verdict::Accept && self.dropping {
+            verdict = Verdict::Drop;
+        }
+
+        if verdict == Verdict::Drop {
+            self.stats.record_dropped_packet();
+        }
+        verdict
+    }
+}
+
+/// Tracks the limits shared between connections: the
+/// statistics and the login attempts per IP address.
+///
+/// Can be cloned to create a new handle.
+#[derive(Clone, Debug)]
+pub struct RateLimiter {
+    stats: RateLimitStats,
+    logins: Arc<Mutex<LoginAttempts>>,
+}
+
+#[derive(Debug)]
+struct LoginAttempts {
+    windows: HashMap<IpAddr, Window>,
+    last_cleanup: Instant,
+}
+
+impl Default for RateLimiter {
+    fn default() -> Self {
+        Self {
+            stats: RateLimitStats::default(),
+            logins: Arc::new(Mutex::new(LoginAttempts {
+                windows: HashMap::new(),
+                last_cleanup: Instant::now(),
+            })),
+        }
+    }
+}
+
+impl RateLimiter {
+    pub fn stats(&self) -> &RateLimitStats {
+        &self.stats
+    }
+
+    /// Creates the limiter for a new connection.
+    pub fn connection(&self, address: SocketAddr, limits: RateLimits) -> ConnectionLimiter {
+        ConnectionLimiter::new(address, limits, self.stats.clone())
+    }
+
+    /// Records a login attempt from `ip`.
+    pub fn on_login(&self, ip: IpAddr, limit: Option<&Limit>, now: Instant) -> Verdict {
+        let limit = match limit {
+            Some(limit) => limit,
+            None => return Verdict::Accept,
+        };
+
+        let mut logins = self.logins.lock().unwrap();
+        if now.saturating_duration_since(logins.last_cleanup) >= limit.interval {
+            logins
+                .windows
+                .retain(|_, window| !window.is_expired(limit, now));
+            logins.last_cleanup = now;
+        }
+
+        let window = logins.windows.entry(ip).or_insert_with(|| Window::new(now));
+        check(
+            LimitKind::LoginAttempts,
+            Some(limit),
+            window,
+            1,
+            now,
+            &ip,
+            &self.stats,
+        )
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use protocol::packets::client::{ChatMessage, KeepAlive};
+
+    use super::*;
+
+    fn limit(max: u64, action: LimitAction) -> Option<Limit> {
+        Some(Limit {
+            max,
+            interval: Duration::from_secs(1),
+            action,
+        })
+    }
+
+    fn limiter(limits: RateLimits) -> ConnectionLimiter {
+        RateLimiter::default().connection("127.0.0.1:25565".parse().unwrap(), limits)
+    }
+
+    fn keep_alive() -> ClientPlayPacket {
+        ClientPlayPacket::KeepAlive(KeepAlive { id: 0 })
+    }
+
+    fn chat() -> ClientPlayPacket {
+        ClientPlayPacket::ChatMessage(ChatMessage {
+            message: "hi".to_owned(),
+        })
+    }
+
+    #[test]
+    fn packets_over_limit_are_handled_per_action() {
+        let now = Instant::now();
+        let mut limiter = limiter(RateLimits {
+            packets: limit(2, LimitAction::Drop),
+            ..RateLimits::unlimited()
+        });
+        assert_eq!(limiter.on_packet(&keep_alive(), now), Verdict::Accept);
+        assert_eq!(limiter.on_packet(&keep_alive(), now), Verdict::Accept);
+        assert_eq!(limiter.on_packet(&keep_alive(), now), Verdict::Drop);
+        assert_eq!(limiter.stats().dropped_packets(), 1);
+        assert_eq!(limiter.stats().violations(LimitKind::Packets), 1);
+
+        // A new interval starts over.
+        let later = now + Duration::from_secs(1);
+        assert_eq!(limiter.on_packet(&keep_alive(), later), Verdict::Accept);
+
+        let mut limiter = self::limiter(RateLimits {
+            packets: limit(0, LimitAction::Log),
+            ..RateLimits::unlimited()
+        });
+        assert_eq!(limiter.on_packet(&keep_alive(), now), Verdict::Accept);
+        assert_eq!(limiter.stats().violations(LimitKind::Packets), 1);
+    }
+
+    #[test]
+    fn chat_messages_have_their_own_limit() {
+        let now = Instant::now();
+        let mut limiter = limiter(RateLimits {
+            chat_messages: limit(1, LimitAction::Kick),
+            ..RateLimits::unlimited()
+        });
+        assert_eq!(limiter.on_packet(&chat(), now), Verdict::Accept);
+        assert_eq!(limiter.on_packet(&keep_alive(), now), Verdict::Accept);
+        assert_eq!(
+            limiter.on_packet(&chat(), now),
+            Verdict::Kick(LimitKind::ChatMessages)
+        );
+    }
+
+    #[test]
+    fn bytes_over_limit_drop_packets() {
+        let now = Instant::now();
+        let mut limiter = limiter(RateLimits {
+            bytes: limit(100, LimitAction::Drop),
+            ..RateLimits::unlimited()
+        });
+        assert_eq!(limiter.on_bytes(100, now), Verdict::Accept);
+        assert_eq!(limiter.on_packet(&keep_alive(), now), Verdict::Accept);
+        assert_eq!(limiter.on_bytes(1, now), Verdict::Drop);
+        assert_eq!(limiter.on_packet(&keep_alive(), now), Verdict::Drop);
+    }
+
+    #[test]
+    fn login_attempts_are_counted_per_ip() {
+        let now = Instant::now();
+        let limiter = RateLimiter::default();
+        let limit = limit(1, LimitAction::Kick);
+        let a = "10.0.0.1".parse().unwrap();
+        let b = "10.0.0.2".parse().unwrap();
+
+        assert_eq!(limiter.on_login(a, limit.as_ref(), now), Verdict::Accept);
+        assert_eq!(
+            limiter.on_login(a, limit.as_ref(), now),
+            Verdict::Kick(LimitKind::LoginAttempts)
+        );
+        assert_eq!(limiter.on_login(b, limit.as_ref(), now), Verdict::Accept);
+        assert_eq!(limiter.stats().violations(LimitKind::LoginAttempts), 1);
+    }
+}
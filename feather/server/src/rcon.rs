@@ -0,0 +1,373 @@
+//! An RCON server, which lets remote tools run commands
+//! using the [Source RCON protocol](https://developer.valvesoftware.com/wiki/Source_RCON_Protocol).
+//!
+//! Connections authenticate with a password. Commands then run
+//! on the game thread as an RCON sender entity, and their
+//! feedback is sent back as the response.
+
+use std::{fmt, io::ErrorKind, net::SocketAddr, sync::Arc};
+
+use anyhow::{bail, Context};
+use base::Text;
+use common::Game;
+use ecs::{EntityBuilder, SysResult, SystemExecutor};
+use flume::Sender;
+use serde_json::Value;
+use tokio::{
+    io::{AsyncReadExt, AsyncWriteExt},
+    net::{TcpListener, TcpStream},
+};
+
+use crate::Server;
+
+const SERVERDATA_AUTH: i32 = 3;
+const SERVERDATA_AUTH_RESPONSE: i32 = 2;
+const SERVERDATA_EXECCOMMAND: i32 = 2;
+const SERVERDATA_RESPONSE_VALUE: i32 = 0;
+
+/// The request ID of a failed authentication response.
+const AUTH_FAILED_ID: i32 = -1;
+
+/// Longest packet accepted from clients, excluding the length field.
+const MAX_REQUEST_LENGTH: usize = 4096;
+
+/// Longest body of a response packet. Longer
+/// responses are split into several packets.
+const MAX_RESPONSE_BODY: usize = 4096;
+
+/// Sent after the mirrored empty `SERVERDATA_RESPONSE_VALUE`, so clients
+/// can tell the end of a multi-packet response apart from its packets.
+const END_OF_RESPONSE_BODY: &str = "\0\u{1}\0\0";
+
+/// Where and how to accept RCON connections.
+#[derive(Clone)]
+pub struct RconOptions {
+    pub address: SocketAddr,
+    pub password: String,
+}
+
+impl fmt::Debug for RconOptions {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        f.debug_struct("RconOptions")
+            .field("address", &self.address)
+            .finish()
+    }
+}
+
+/// A command for the game thread to run, whose
+/// output is sent back through `reply`.
+#[derive(Debug)]
+pub struct RconCommand {
+    command: String,
+    reply: Sender<String>,
+}
+
+/// Starts accepting RCON connections, which send
+/// their commands to `commands`.
+///
+/// Must be called within the context of a Tokio runtime.
+pub async fn start(options: RconOptions, commands: Sender<RconCommand>) -> anyhow::Result<()> {
+    let listener = TcpListener::bind(options.address)
+        .await
+        .with_context(|| format!("failed to bind RCON to {}", options.address))?;
+    log::info!("RCON is listening on {}", options.address);
+
+    let options = Arc::new(options);
+    tokio::task::spawn(async move {
+        loop {
+            if let Ok((stream, address)) = listener.accept().await {
+                let options = Arc::clone(&options);
+                let commands = commands.clone();
+                tokio::task::spawn(async move {
+                    if let Err(e) = handle_connection(stream, &options, &commands).await {
+                        log::debug!("RCON connection from {} closed: {:?}", address, e);
+                    }
+                });
+            }
+        }
+    });
+    Ok(())
+}
+
+async fn handle_connection(
+    mut stream: TcpStream,
+    options: &RconOptions,
+    commands: &Sender<RconCommand>,
+) -> anyhow::Result<()> {
+    let mut authenticated = false;
+    while let Some(packet) = read_packet(&mut stream).await? {
+        match packet.kind {
+            SERVERDATA_AUTH => {
+                if !is_password_correct(&packet.body, &options.password) {
+                    write_packets(
+                        &mut stream,
+                        &[Packet::new(AUTH_FAILED_ID, SERVERDATA_AUTH_RESPONSE, "")],
+                    )
+                    .await?;
+                    bail!("wrong password");
+                }
+                authenticated = true;
+                write_packets(
+                    &mut stream,
+                    &[Packet::new(packet.id, SERVERDATA_AUTH_RESPONSE, "")],
+                )
+                .await?;
+            }
+            _ if !authenticated => {
+                write_packets(
+                    &mut stream,
+                    &[Packet::new(AUTH_FAILED_ID, SERVERDATA_AUTH_RESPONSE, "")],
+                )
+                .await?;
+                bail!("sent a request before authenticating");
+            }
+            SERVERDATA_EXECCOMMAND => {
+                let (reply, response) = flume::bounded(1);
+                commands
+                    .send_async(RconCommand {
+                        command: packet.body,
+                        reply,
+                    })
+                    .await?;
+                let response = response.recv_async().await?;
+                let packets: Vec<Packet> = split_response(&response)
+                    .map(|body| Packet::new(packet.id, SERVERDATA_RESPONSE_VALUE, body))
+                    .collect();
+                write_packets(&mut stream, &packets).await?;
+            }
+            SERVERDATA_RESPONSE_VALUE => {
+                write_packets(
+                    &mut stream,
+                    &[
+                        Packet::new(packet.id, SERVERDATA_RESPONSE_VALUE, ""),
+                        Packet::new(packet.id, SERVERDATA_RESPONSE_VALUE, END_OF_RESPONSE_BODY),
+                    ],
+                )
+                .await?;
+            }
+            kind => {
+                let body = format!("Unknown request {:x}", kind);
+                write_packets(
+                    &mut stream,
+                    &[Packet::new(packet.id, SERVERDATA_RESPONSE_VALUE, &body)],
+                )
+                .await?;
+            }
+        }
+    }
+    Ok(())
+}
+
+fn is_password_correct(attempt: &str, password: &str) -> bool {
+    ring::constant_time::verify_slices_are_equal(attempt.as_bytes(), password.as_bytes()).is_ok()
+}
+
+/// Reads a packet, or returns `None` if the client closed the connection.
+async fn read_packet(stream: &mut TcpStream) -> anyhow::Result<Option<Packet>> {
+    let length = match stream.read_i32_le().await {
+        Ok(length) => length,
+        Err(e) if e.kind() == ErrorKind::UnexpectedEof => return Ok(None),
+        Err(e) => return Err(e.into()),
+    };
+    if length < 0 || length as usize > MAX_REQUEST_LENGTH {
+        bail!("invalid packet length {}", length);
+    }
+    let mut buffer = vec![0; length as usize];
+    stream.read_exact(&mut buffer).await?;
+    Packet::decode(&buffer).map(Some)
+}
+
+async fn write_packets(stream: &mut TcpStream, packets: &[Packet]) -> anyhow::Result<()> {
+    let mut buffer = Vec::new();
+    for packet in packets {
+        packet.encode(&mut buffer);
+    }
+    stream.write_all(&buffer).await?;
+    Ok(())
+}
+
+/// Splits a response into packet bodies of at most
+/// [`MAX_RESPONSE_BODY`] bytes. An empty response
+/// still takes one packet.
+fn split_response(mut response: &str) -> impl Iterator<Item = &str> {
+    let mut first = true;
+    std::iter::from_fn(move || {
+        if response.is_empty() && !first {
+            return None;
+        }
+        first = false;
+        let mut end = response.len().min(MAX_RESPONSE_BODY);
+        while !response.is_char_boundary(end) {
+            end -= 1;
+        }
+        let (body, rest) = response.split_at(end);
+        response = rest;
+        Some(body)
+    })
+}
+
+/// A packet in the RCON protocol.
+#[derive(Debug, PartialEq)]
+struct Packet {
+    id: i32,
+    kind: i32,
+    body: String,
+}
+
+impl Packet {
+    fn new(id: i32, kind: i32, body: &str) -> Self {
+        Self {
+            id,
+            kind,
+            body: body.to_owned(),
+        }
+    }
+
+    /// Decodes a packet, excluding its length field.
+    fn decode(bytes: &[u8]) -> anyhow::Result<Self> {
+        // The ID, the type and the two null bytes ending the body.
+        if bytes.len() < 10 {
+            bail!("packet is only {} bytes long", bytes.len());
+        }
+        let (header, body) = bytes.split_at(8);
+        let id = i32::from_le_bytes([header[0], header[1], header[2], header[3]]);
+        let kind = i32::from_le_bytes([header[4], header[5], header[6], header[7]]);
+        let body = match body {
+            [body @ .., 0, 0] => body,
+            _ => bail!("packet body is not null-terminated"),
+        };
+        let body = String::from_utf8(body.to_vec()).context("packet body is not UTF-8")?;
+        Ok(Self { id, kind, body })
+    }
+
+    /// Encodes the packet, including its length field.
+    fn encode(&self, output: &mut Vec<u8>) {
+        let length = 10 + self.body.len() as i32;
+        output.extend_from_slice(&length.to_le_bytes());
+        output.extend_from_slice(&self.id.to_le_bytes());
+        output.extend_from_slice(&self.kind.to_le_bytes());
+        output.extend_from_slice(self.body.as_bytes());
+        output.extend_from_slice(&[0, 0]);
+    }
+}
+
+/// Marker component for the entity running RCON commands.
+struct RconSender;
+
+/// Registers the RCON sender. Only called if RCON is enabled.
+pub fn register(game: &mut Game, systems: &mut SystemExecutor<Game>) {
+    // Like the console, the sender isn't a "normal" entity.
+    // It has no chat box, so it doesn't collect broadcasts.
+    let mut sender = EntityBuilder::new();
+    sender.add(RconSender);
+    game.ecs.spawn(sender.build());
+
+    systems.group::<Server>().add_system(run_rcon_commands);
+}
+
+fn run_rcon_commands(game: &mut Game, server: &mut Server) -> SysResult {
+    let sender = match game.ecs.query::<&RconSender>().iter().next() {
+        Some((sender, _)) => sender,
+        None => return Ok(()),
+    };
+
+    for RconCommand { command, reply } in server.rcon_commands.try_iter() {
+        log::info!("Running command from RCON: {}", command);
+        let command = command.strip_prefix('/').unwrap_or(&command);
+        let feedback = crate::commands::run_command(game, sender, command);
+        // The connection may have closed in the meantime.
+        let _ = reply.send(plain_text(&feedback));
+    }
+    Ok(())
+}
+
+/// Renders `text` without formatting. We have no translations,
+/// so translatable components are rendered as their key followed
+/// by their arguments.
+fn plain_text(text: &Text) -> String {
+    let mut output = String::new();
+    if let Ok(value) = serde_json::to_value(text) {
+        write_plain_text(&value, &mut output);
+    }
+    output
+}
+
+fn write_plain_text(value: &Value, output: &mut String) {
+    match value {
+        Value::String(text) => output.push_str(text),
+        Value::Array(components) => {
+            for component in components {
+                write_plain_text(component, output);
+            }
+        }
+        Value::Object(component) => {
+            if let Some(Value::String(text)) = component.get("text") {
+                output.push_str(text);
+            } else if let Some(Value::String(key)) = component.get("translate") {
+                output.push_str(key);
+                if let Some(Value::Array(args)) = component.get("with") {
+                    for (i, arg) in args.iter().enumerate() {
+                        output.push_str(if i == 0 { " " } else { ", " });
+                        write_plain_text(arg, output);
+                    }
+                }
+            }
+            if let Some(extra) = component.get("extra") {
+                write_plain_text(extra, output);
+            }
+        }
+        _ => {}
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn packet_round_trip() {
+        let packet = Packet::new(7, SERVERDATA_EXECCOMMAND, "effect give Steve speed");
+        let mut bytes = Vec::new();
+        packet.encode(&mut bytes);
+
+        assert_eq!(&bytes[..4], &(bytes.len() as i32 - 4).to_le_bytes());
+        assert_eq!(Packet::decode(&bytes[4..]).unwrap(), packet);
+    }
+
+    #[test]
+    fn decode_invalid_packets() {
+        assert!(Packet::decode(&[0; 9]).is_err());
+        assert!(Packet::decode(&[0, 0, 0, 0, 2, 0, 0, 0, b'a', 0]).is_err());
+        assert!(Packet::decode(&[0, 0, 0, 0, 2, 0, 0, 0, 0xFF, 0, 0]).is_err());
+    }
+
+    #[test]
+    fn split_long_responses() {
+        assert_eq!(split_response("").collect::<Vec<_>>(), vec![""]);
+        assert_eq!(split_response("done").collect::<Vec<_>>(), vec!["done"]);
+
+        let response = "a".repeat(MAX_RESPONSE_BODY - 1) + "é" + "b";
+        let bodies: Vec<&str> = split_response(&response).collect();
+        assert_eq!(bodies.len(), 2);
+        assert_eq!(bodies[0].len(), MAX_RESPONSE_BODY - 1);
+        assert_eq!(bodies[1], "éb");
+    }
+
+    #[test]
+    fn render_plain_text() {
+        assert_eq!(
+            plain_text(&Text::from("Cancelled pregeneration")),
+            "Cancelled pregeneration"
+        );
+        assert_eq!(
+            plain_text(&Text::translate_with(
+                "commands.effect.give.success.single",
+                vec![
+                    Text::translate("effect.minecraft.speed"),
+                    Text::from("Steve")
+                ],
+            )),
+            "commands.effect.give.success.single effect.minecraft.speed, Steve"
+        );
+    }
+}
@@ -2,12 +2,17 @@
 
 mod block;
 mod chat;
+pub(crate) mod effects;
 mod entity;
+pub(crate) mod experience;
 mod gamemode;
+pub(crate) mod health;
+mod inventory;
 mod particle;
 mod player_join;
 mod player_leave;
 mod plugin_message;
+mod server_list_ping;
 mod tablist;
 pub mod view;
 
@@ -21,6 +26,7 @@ use crate::{client::ClientId, Server};
 
 /// Registers systems for a `Server` with a `Game`.
 pub fn register(server: Server, game: &mut Game, systems: &mut SystemExecutor<Game>) {
+    let rcon_enabled = server.options.rcon.is_some();
     game.insert_resource(server);
 
     player_join::register(systems);
@@ -28,8 +34,14 @@ pub fn register(server: Server, game: &mut Game, systems: &mut SystemExecutor<Ga
         .group::<Server>()
         .add_system(handle_packets)
         .add_system(send_keepalives);
+    crate::chunk_packets::register(systems);
     view::register(game, systems);
     crate::chunk_subscriptions::register(systems);
+    crate::metrics::register(systems);
+    if rcon_enabled {
+        crate::rcon::register(game, systems);
+    }
+    crate::query::register(systems);
     player_leave::register(systems);
     tablist::register(systems);
     block::register(systems);
@@ -38,6 +50,11 @@ pub fn register(server: Server, game: &mut Game, systems: &mut SystemExecutor<Ga
     particle::register(systems);
     plugin_message::register(systems);
     gamemode::register(systems);
+    effects::register(systems);
+    experience::register(systems);
+    health::register(systems);
+    inventory::register(systems);
+    server_list_ping::register(systems);
 
     systems.group::<Server>().add_system(tick_clients);
 }
@@ -68,12 +85,23 @@ fn handle_packets(game: &mut Game, server: &mut Server) -> SysResult {
     Ok(())
 }
 
-/// Sends out keepalive packets at an interval.
+/// Time after which clients which didn't reply to a keepalive are disconnected.
+const KEEPALIVE_TIMEOUT: Duration = Duration::from_secs(30);
+
+/// Sends out keepalive packets at an interval
+/// and disconnects clients which stopped replying.
 fn send_keepalives(_game: &mut Game, server: &mut Server) -> SysResult {
     let interval = Duration::from_secs(5);
     if server.last_keepalive_time + interval < Instant::now() {
         server.broadcast_keepalive();
     }
+
+    for client in server.clients.iter() {
+        if !client.is_disconnected() && client.is_keepalive_timed_out(KEEPALIVE_TIMEOUT) {
+            log::debug!("{} timed out", client.username());
+            client.disconnect("Timed out");
+        }
+    }
     Ok(())
 }
 
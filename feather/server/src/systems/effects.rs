@@ -0,0 +1,109 @@
+//! Sends status effect changes to clients.
+
+use base::Position;
+use common::{Game, WorldId};
+use ecs::{SysResult, SystemExecutor};
+use quill_common::components::{ActiveEffects, EffectInstance};
+
+use crate::{NetworkId, Server};
+
+pub fn register(systems: &mut SystemExecutor<Game>) {
+    systems.group::<Server>().add_system(sync_effects);
+}
+
+/// The effects of an entity as last sent to clients.
+#[derive(Debug, Default)]
+pub struct SentEffects(Vec<EffectInstance>);
+
+/// Sends `EntityEffect` and `RemoveEntityEffect` packets
+/// for entities whose effects changed since the last tick.
+fn sync_effects(game: &mut Game, server: &mut Server) -> SysResult {
+    let mut untracked = Vec::new();
+    for (entity, (_, sent)) in game
+        .ecs
+        .query::<(&ActiveEffects, Option<&SentEffects>)>()
+        .iter()
+    {
+        if sent.is_none() {
+            untracked.push(entity);
+        }
+    }
+    for entity in untracked {
+        game.ecs.insert(entity, SentEffects::default())?;
+    }
+
+    for (_, (&network_id, &world, &position, effects, sent)) in game
+        .ecs
+        .query::<(
+            &NetworkId,
+            &WorldId,
+            &Position,
+            &ActiveEffects,
+            &mut SentEffects,
+        )>()
+        .iter()
+    {
+        for instance in effects.iter() {
+            let previous = sent.0.iter().find(|e| e.effect == instance.effect);
+            if needs_update(previous, instance) {
+                server.broadcast_nearby_with(world, position, |client| {
+                    client.send_entity_effect(network_id, instance)
+                });
+            }
+        }
+
+        for previous in &sent.0 {
+            if !effects.contains(previous.effect) {
+                server.broadcast_nearby_with(world, position, |client| {
+                    client.send_remove_entity_effect(network_id, previous.effect)
+                });
+            }
+        }
+
+        sent.0 = effects.iter().copied().collect();
+    }
+
+    Ok(())
+}
+
+/// Returns whether clients need to be notified of an effect.
+///
+/// Durations count down on the client, so only effects which
+/// are new, were changed, or were extended need to be resent.
+fn needs_update(previous: Option<&EffectInstance>, current: &EffectInstance) -> bool {
+    match previous {
+        Some(previous) => {
+            previous.amplifier != current.amplifier
+                || previous.ambient != current.ambient
+                || previous.show_particles != current.show_particles
+                || previous.show_icon != current.show_icon
+                || current.duration > previous.duration
+        }
+        None => true,
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use base::StatusEffect;
+
+    #[test]
+    fn only_changed_effects_are_resent() {
+        let effect = EffectInstance::new(StatusEffect::Speed, 0, 100);
+        assert!(needs_update(None, &effect));
+
+        let ticked = EffectInstance {
+            duration: 99,
+            ..effect
+        };
+        assert!(!needs_update(Some(&effect), &ticked));
+        assert!(needs_update(Some(&ticked), &effect));
+
+        let stronger = EffectInstance {
+            amplifier: 1,
+            ..ticked
+        };
+        assert!(needs_update(Some(&effect), &stronger));
+    }
+}
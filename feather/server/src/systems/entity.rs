@@ -5,7 +5,7 @@ use base::{
     metadata::{EntityBitMask, Pose, META_INDEX_ENTITY_BITMASK, META_INDEX_POSE},
     EntityMetadata, Position,
 };
-use common::Game;
+use common::{Game, WorldId};
 use ecs::{SysResult, SystemExecutor};
 use quill_common::{
     components::{OnGround, Sprinting},
@@ -30,9 +30,10 @@ pub fn register(game: &mut Game, systems: &mut SystemExecutor<Game>) {
 
 /// Sends entity movement packets.
 fn send_entity_movement(game: &mut Game, server: &mut Server) -> SysResult {
-    for (_, (&position, prev_position, &on_ground, &network_id, prev_on_ground)) in game
+    for (_, (&world, &position, prev_position, &on_ground, &network_id, prev_on_ground)) in game
         .ecs
         .query::<(
+            &WorldId,
             &Position,
             &mut PreviousPosition,
             &OnGround,
@@ -42,7 +43,7 @@ fn send_entity_movement(game: &mut Game, server: &mut Server) -> SysResult {
         .iter()
     {
         if position != prev_position.0 {
-            server.broadcast_nearby_with(position, |client| {
+            server.broadcast_nearby_with(world, position, |client| {
                 client.update_entity_position(
                     network_id,
                     position,
@@ -62,9 +63,9 @@ fn send_entity_movement(game: &mut Game, server: &mut Server) -> SysResult {
 
 /// Sends [SendEntityMetadata](protocol::packets::server::play::SendEntityMetadata) packet for when an entity is sneaking.
 fn send_entity_sneak_metadata(game: &mut Game, server: &mut Server) -> SysResult {
-    for (_, (&position, &SneakEvent { is_sneaking }, is_sprinting, &network_id)) in game
+    for (_, (&world, &position, &SneakEvent { is_sneaking }, is_sprinting, &network_id)) in game
         .ecs
-        .query::<(&Position, &SneakEvent, &Sprinting, &NetworkId)>()
+        .query::<(&WorldId, &Position, &SneakEvent, &Sprinting, &NetworkId)>()
         .iter()
     {
         let mut metadata = EntityMetadata::entity_base();
@@ -81,7 +82,7 @@ fn send_entity_sneak_metadata(game: &mut Game, server: &mut Server) -> SysResult
             metadata.set(META_INDEX_POSE, Pose::Standing);
         }
 
-        server.broadcast_nearby_with(position, |client| {
+        server.broadcast_nearby_with(world, position, |client| {
             client.send_entity_metadata(network_id, metadata.clone());
         });
     }
@@ -90,9 +91,9 @@ fn send_entity_sneak_metadata(game: &mut Game, server: &mut Server) -> SysResult
 
 /// Sends [SendEntityMetadata](protocol::packets::server::play::SendEntityMetadata) packet for when an entity is sprinting.
 fn send_entity_sprint_metadata(game: &mut Game, server: &mut Server) -> SysResult {
-    for (_, (&position, &SprintEvent { is_sprinting }, &network_id)) in game
+    for (_, (&world, &position, &SprintEvent { is_sprinting }, &network_id)) in game
         .ecs
-        .query::<(&Position, &SprintEvent, &NetworkId)>()
+        .query::<(&WorldId, &Position, &SprintEvent, &NetworkId)>()
         .iter()
     {
         let mut metadata = EntityMetadata::entity_base();
@@ -101,7 +102,7 @@ fn send_entity_sprint_metadata(game: &mut Game, server: &mut Server) -> SysResul
         bit_mask.set(EntityBitMask::SPRINTING, is_sprinting);
         metadata.set(META_INDEX_ENTITY_BITMASK, bit_mask.bits());
 
-        server.broadcast_nearby_with(position, |client| {
+        server.broadcast_nearby_with(world, position, |client| {
             client.send_entity_metadata(network_id, metadata.clone());
         });
     }
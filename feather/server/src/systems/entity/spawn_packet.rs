@@ -3,7 +3,7 @@ use anyhow::Context;
 use base::Position;
 use common::{
     events::{ChunkCrossEvent, ViewUpdateEvent},
-    Game,
+    Game, WorldId,
 };
 use ecs::{SysResult, SystemExecutor};
 use quill_common::events::{EntityCreateEvent, EntityRemoveEvent};
@@ -29,8 +29,9 @@ pub fn update_visible_entities(game: &mut Game, server: &mut Server) -> SysResul
         };
 
         // Send newly visible entities
+        let new_world = event.new_view.world();
         for &new_chunk in &event.new_chunks {
-            for &entity_id in game.chunk_entities.entities_in_chunk(new_chunk) {
+            for &entity_id in game.chunk_entities.entities_in_chunk(new_world, new_chunk) {
                 if entity_id != player {
                     let entity_ref = game.ecs.entity(entity_id)?;
                     if let Ok(spawn_packet) = entity_ref.get::<SpawnPacketSender>() {
@@ -42,9 +43,14 @@ pub fn update_visible_entities(game: &mut Game, server: &mut Server) -> SysResul
             }
         }
 
-        // Unload entities no longer visible
+        // Unload entities no longer visible. After changing worlds,
+        // the client has already discarded all entities.
+        let old_world = event.old_view.world();
+        if old_world != new_world {
+            continue;
+        }
         for &old_chunk in &event.old_chunks {
-            for &entity_id in game.chunk_entities.entities_in_chunk(old_chunk) {
+            for &entity_id in game.chunk_entities.entities_in_chunk(old_world, old_chunk) {
                 if entity_id != player {
                     if let Ok(network_id) = game.ecs.get::<NetworkId>(entity_id) {
                         client.unload_entity(*network_id);
@@ -59,13 +65,13 @@ pub fn update_visible_entities(game: &mut Game, server: &mut Server) -> SysResul
 
 /// System to send an entity to clients when it is created.
 fn send_entities_when_created(game: &mut Game, server: &mut Server) -> SysResult {
-    for (entity, (_event, &position, spawn_packet)) in game
+    for (entity, (_event, &world, &position, spawn_packet)) in game
         .ecs
-        .query::<(&EntityCreateEvent, &Position, &SpawnPacketSender)>()
+        .query::<(&EntityCreateEvent, &WorldId, &Position, &SpawnPacketSender)>()
         .iter()
     {
         let entity_ref = game.ecs.entity(entity)?;
-        server.broadcast_nearby_with(position, |client| {
+        server.broadcast_nearby_with(world, position, |client| {
             spawn_packet
                 .send(&entity_ref, client)
                 .expect("failed to create spawn packet")
@@ -77,12 +83,12 @@ fn send_entities_when_created(game: &mut Game, server: &mut Server) -> SysResult
 
 /// System to unload an entity on clients when it is removed.
 fn unload_entities_when_removed(game: &mut Game, server: &mut Server) -> SysResult {
-    for (_, (_event, &position, &network_id)) in game
+    for (_, (_event, &world, &position, &network_id)) in game
         .ecs
-        .query::<(&EntityRemoveEvent, &Position, &NetworkId)>()
+        .query::<(&EntityRemoveEvent, &WorldId, &Position, &NetworkId)>()
         .iter()
     {
-        server.broadcast_nearby_with(position, |client| client.unload_entity(network_id));
+        server.broadcast_nearby_with(world, position, |client| client.unload_entity(network_id));
     }
 
     Ok(())
@@ -97,13 +103,13 @@ fn update_entities_on_chunk_cross(game: &mut Game, server: &mut Server) -> SysRe
     {
         let old_clients: AHashSet<_> = server
             .chunk_subscriptions
-            .subscriptions_for(event.old_chunk)
+            .subscriptions_for(event.old_world, event.old_chunk)
             .iter()
             .copied()
             .collect();
         let new_clients: AHashSet<_> = server
             .chunk_subscriptions
-            .subscriptions_for(event.new_chunk)
+            .subscriptions_for(event.new_world, event.new_chunk)
             .iter()
             .copied()
             .collect();
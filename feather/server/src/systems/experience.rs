@@ -0,0 +1,33 @@
+//! Sends experience changes to clients.
+
+use common::Game;
+use ecs::{SysResult, SystemExecutor};
+use quill_common::components::Experience;
+
+use crate::{ClientId, Server};
+
+pub fn register(systems: &mut SystemExecutor<Game>) {
+    systems.group::<Server>().add_system(sync_experience);
+}
+
+/// The experience of a player as last sent to their client.
+#[derive(Debug, Default)]
+pub struct SentExperience(Option<Experience>);
+
+/// Sends `SetExperience` to players whose experience changed.
+fn sync_experience(game: &mut Game, server: &mut Server) -> SysResult {
+    for (_, (&client_id, &experience, sent)) in game
+        .ecs
+        .query::<(&ClientId, &Experience, &mut SentExperience)>()
+        .iter()
+    {
+        if sent.0 == Some(experience) {
+            continue;
+        }
+        if let Some(client) = server.clients.get(client_id) {
+            client.send_experience(&experience);
+        }
+        sent.0 = Some(experience);
+    }
+    Ok(())
+}
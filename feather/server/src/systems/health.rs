@@ -0,0 +1,34 @@
+//! Sends health changes to clients.
+
+use common::Game;
+use ecs::{SysResult, SystemExecutor};
+use quill_common::components::Health;
+
+use crate::{ClientId, Server};
+
+pub fn register(systems: &mut SystemExecutor<Game>) {
+    systems.group::<Server>().add_system(sync_health);
+}
+
+/// The health of a player as last sent to their client.
+#[derive(Debug, Default)]
+pub struct SentHealth(Option<f32>);
+
+/// Sends `UpdateHealth` to players whose health changed.
+/// A client shows the death screen once its health reaches zero.
+fn sync_health(game: &mut Game, server: &mut Server) -> SysResult {
+    for (_, (&client_id, &health, sent)) in game
+        .ecs
+        .query::<(&ClientId, &Health, &mut SentHealth)>()
+        .iter()
+    {
+        if sent.0 == Some(health.0) {
+            continue;
+        }
+        if let Some(client) = server.clients.get(client_id) {
+            client.send_health(health);
+        }
+        sent.0 = Some(health.0);
+    }
+    Ok(())
+}
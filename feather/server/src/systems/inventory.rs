@@ -0,0 +1,23 @@
+use common::{events::InventoryUpdateEvent, Game, Window};
+use ecs::{SysResult, SystemExecutor};
+
+use crate::{ClientId, Server};
+
+pub fn register(systems: &mut SystemExecutor<Game>) {
+    systems.group::<Server>().add_system(send_inventory_updates);
+}
+
+/// Resends the window of players whose inventory
+/// was changed by the server.
+fn send_inventory_updates(game: &mut Game, server: &mut Server) -> SysResult {
+    for (_, (_event, &client_id, window)) in game
+        .ecs
+        .query::<(&InventoryUpdateEvent, &ClientId, &Window)>()
+        .iter()
+    {
+        if let Some(client) = server.clients.get(client_id) {
+            client.send_window_items(window);
+        }
+    }
+    Ok(())
+}
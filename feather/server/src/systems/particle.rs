@@ -1,6 +1,6 @@
 use crate::Server;
 use base::{Particle, Position};
-use common::Game;
+use common::{Game, WorldId};
 use ecs::{SysResult, SystemExecutor};
 
 pub fn register(systems: &mut SystemExecutor<Game>) {
@@ -10,8 +10,14 @@ pub fn register(systems: &mut SystemExecutor<Game>) {
 fn send_particle_packets(game: &mut Game, server: &mut Server) -> SysResult {
     let mut entities = Vec::new();
 
-    for (entity, (&particle, &position)) in game.ecs.query::<(&Particle, &Position)>().iter() {
-        server.broadcast_nearby_with(position, |client| {
+    for (entity, (&particle, &position, world)) in game
+        .ecs
+        .query::<(&Particle, &Position, Option<&WorldId>)>()
+        .iter()
+    {
+        // Particles spawned by plugins have no world.
+        let world = world.copied().unwrap_or(WorldId::MAIN);
+        server.broadcast_nearby_with(world, position, |client| {
             client.send_particle(&particle, &position);
         });
 
@@ -1,24 +1,28 @@
 use libcraft_items::InventorySlot;
 use log::debug;
 
-use base::anvil::player::PlayerAbilities;
-use base::{Gamemode, Inventory, ItemStack, Position, Text};
+use base::anvil::player::{ActiveEffectData, PlayerAbilities};
+use base::{Dimension, Gamemode, Inventory, ItemStack, Position, Text};
 use common::{
     chat::{ChatKind, ChatPreference},
     entities::player::HotbarSlot,
-    view::View,
+    view::{View, ViewDistance},
     window::BackingWindow,
-    ChatBox, Game, Window,
+    ChatBox, Game, Window, WorldId,
 };
 use ecs::{SysResult, SystemExecutor};
 use quill_common::components::{
-    CanBuild, CanCreativeFly, CreativeFlying, CreativeFlyingSpeed, Health, Instabreak,
-    Invulnerable, PreviousGamemode, WalkSpeed,
+    ActiveEffects, CanBuild, CanCreativeFly, CreativeFlying, CreativeFlyingSpeed, Experience,
+    Health, Instabreak, Invulnerable, Ping, PreviousGamemode, WalkSpeed,
 };
 use quill_common::events::GamemodeEvent;
 use quill_common::{components::Name, entity_init::EntityInit};
 
-use crate::{ClientId, NetworkId, Server};
+use crate::{
+    commands::Operator,
+    systems::{effects::SentEffects, experience::SentExperience, health::SentHealth},
+    ClientId, NetworkId, Server,
+};
 
 pub fn register(systems: &mut SystemExecutor<Game>) {
     systems.group::<Server>().add_system(poll_new_players);
@@ -35,20 +39,28 @@ fn poll_new_players(game: &mut Game, server: &mut Server) -> SysResult {
 
 fn accept_new_player(game: &mut Game, server: &mut Server, client_id: ClientId) -> SysResult {
     let client = server.clients.get_mut(client_id).unwrap();
-    let player_data = game.world.load_player_data(client.uuid());
-    let mut builder = game.create_entity_builder(
-        player_data
-            .as_ref()
-            .map(|data| Position {
-                x: data.animal.base.position[0],
-                y: data.animal.base.position[1],
-                z: data.animal.base.position[2],
-                yaw: data.animal.base.rotation[0],
-                pitch: data.animal.base.rotation[1],
-            })
-            .unwrap_or_default(),
-        EntityInit::Player,
-    );
+    // Player data is stored in the main world only.
+    let player_data = game.worlds.main().load_player_data(client.uuid());
+
+    // Players in a dimension that no longer has a world
+    // are moved to the spawn of the main world.
+    let world = player_data
+        .as_ref()
+        .ok()
+        .and_then(|data| game.worlds.by_dimension(data.dimension));
+    let position = match (&player_data, world) {
+        (Ok(data), Some(_)) => Position {
+            x: data.animal.base.position[0],
+            y: data.animal.base.position[1],
+            z: data.animal.base.position[2],
+            yaw: data.animal.base.rotation[0],
+            pitch: data.animal.base.rotation[1],
+        },
+        _ => Position::default(),
+    };
+    let world = world.unwrap_or(WorldId::MAIN);
+
+    let mut builder = game.create_entity_builder(position, EntityInit::Player);
     client.set_network_id(*builder.get::<NetworkId>().unwrap());
 
     if player_data.is_err() {
@@ -63,7 +75,13 @@ fn accept_new_player(game: &mut Game, server: &mut Server, client_id: ClientId)
         .map(|data| PreviousGamemode::from_id(data.previous_gamemode as i8))
         .unwrap_or(PreviousGamemode(None));
 
-    client.send_join_game(gamemode, previous_gamemode);
+    let dimensions: Vec<Dimension> = game.worlds.iter().map(|world| world.dimension()).collect();
+    client.send_join_game(
+        gamemode,
+        previous_gamemode,
+        &dimensions,
+        game.worlds[world].dimension(),
+    );
     client.send_brand();
 
     // Abilities
@@ -105,10 +123,13 @@ fn accept_new_player(game: &mut Game, server: &mut Server, client_id: ClientId)
 
     builder
         .add(client_id)
+        .add(world)
         .add(View::new(
+            world,
             Position::default().chunk(),
             server.options.view_distance,
         ))
+        .add(ViewDistance(server.options.view_distance))
         .add(gamemode)
         .add(previous_gamemode)
         .add(Name::new(client.username()))
@@ -124,13 +145,47 @@ fn accept_new_player(game: &mut Game, server: &mut Server, client_id: ClientId)
                 .map(|data| data.animal.health)
                 .unwrap_or(20.0),
         ))
+        .add(SentHealth::default())
         .add(abilities.walk_speed)
         .add(abilities.fly_speed)
         .add(abilities.is_flying)
         .add(abilities.may_fly)
         .add(abilities.may_build)
         .add(abilities.instabreak)
-        .add(abilities.invulnerable);
+        .add(abilities.invulnerable)
+        .add(
+            player_data
+                .as_ref()
+                .map(|data| {
+                    data.active_effects
+                        .iter()
+                        .filter_map(ActiveEffectData::to_effect_instance)
+                        .collect::<ActiveEffects>()
+                })
+                .unwrap_or_default(),
+        )
+        .add(SentEffects::default())
+        .add(
+            player_data
+                .as_ref()
+                .map(|data| Experience {
+                    level: data.xp_level.max(0) as u32,
+                    progress: data.xp_progress,
+                    total: data.xp_total.max(0) as u32,
+                })
+                .unwrap_or_default(),
+        )
+        .add(SentExperience::default())
+        .add(Ping::default());
+
+    if server
+        .options
+        .operators
+        .iter()
+        .any(|operator| operator.eq_ignore_ascii_case(client.username()))
+    {
+        builder.add(Operator);
+    }
 
     builder.add(GamemodeEvent(gamemode));
 
@@ -1,14 +1,14 @@
 use num_traits::cast::ToPrimitive;
 
 use base::anvil::entity::{AnimalData, BaseEntityData};
-use base::anvil::player::{InventorySlot, PlayerAbilities, PlayerData};
-use base::{Gamemode, Inventory, Position, Text};
+use base::anvil::player::{ActiveEffectData, InventorySlot, PlayerAbilities, PlayerData};
+use base::{Dimension, Gamemode, Inventory, Position, Text};
 use common::entities::player::HotbarSlot;
-use common::{chat::ChatKind, Game};
+use common::{chat::ChatKind, Game, WorldId};
 use ecs::{SysResult, SystemExecutor};
 use quill_common::components::{
-    CanBuild, CanCreativeFly, CreativeFlying, CreativeFlyingSpeed, Health, Instabreak,
-    Invulnerable, Name, PreviousGamemode, WalkSpeed,
+    ActiveEffects, CanBuild, CanCreativeFly, CreativeFlying, CreativeFlyingSpeed, Experience,
+    Health, Instabreak, Invulnerable, Name, PreviousGamemode, WalkSpeed,
 };
 
 use crate::{ClientId, Server};
@@ -65,10 +65,19 @@ fn remove_disconnected_clients(game: &mut Game, server: &mut Server) -> SysResul
         if client.is_disconnected() {
             entities_to_remove.push(player);
             broadcast_player_leave(game, name);
-            game.world
+            let active_effects = game.ecs.get::<ActiveEffects>(player).ok();
+            let experience = game
+                .ecs
+                .get::<Experience>(player)
+                .map(|experience| *experience)
+                .unwrap_or_default();
+            let dimension = game.worlds[*game.ecs.get::<WorldId>(player)?].dimension();
+            game.worlds
+                .main()
                 .save_player_data(
                     client.uuid(),
                     &create_player_data(
+                        dimension,
                         *position,
                         *gamemode,
                         *previous_gamemode,
@@ -84,6 +93,8 @@ fn remove_disconnected_clients(game: &mut Game, server: &mut Server) -> SysResul
                         },
                         *hotbar_slot,
                         inventory,
+                        active_effects.as_deref(),
+                        experience,
                     ),
                 )
                 .unwrap_or_else(|e| panic!("Couldn't save data for {}: {}", client.username(), e));
@@ -103,7 +114,9 @@ fn broadcast_player_leave(game: &Game, username: &Name) {
     game.broadcast_chat(ChatKind::System, message);
 }
 
+#[allow(clippy::too_many_arguments)]
 fn create_player_data(
+    dimension: Dimension,
     position: Position,
     gamemode: Gamemode,
     previous_gamemode: PreviousGamemode,
@@ -111,6 +124,8 @@ fn create_player_data(
     abilities: PlayerAbilities,
     hotbar_slot: HotbarSlot,
     inventory: &Inventory,
+    active_effects: Option<&ActiveEffects>,
+    experience: Experience,
 ) -> PlayerData {
     PlayerData {
         animal: AnimalData {
@@ -121,6 +136,7 @@ fn create_player_data(
             },
             health: *health,
         },
+        dimension,
         gamemode: gamemode.to_i32().unwrap(),
         previous_gamemode: previous_gamemode.id() as i32,
         inventory: inventory
@@ -149,5 +165,11 @@ fn create_player_data(
             .collect(),
         held_item: hotbar_slot.get() as i32,
         abilities,
+        active_effects: active_effects
+            .map(|effects| effects.iter().map(ActiveEffectData::from).collect())
+            .unwrap_or_default(),
+        xp_level: experience.level as i32,
+        xp_progress: experience.progress,
+        xp_total: experience.total as i32,
     }
 }
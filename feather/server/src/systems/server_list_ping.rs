@@ -0,0 +1,65 @@
+//! Lets the game and plugins fill in responses to server list pings
+//! through the `ServerListPingEvent`.
+
+use common::Game;
+use ecs::{SysResult, SystemExecutor};
+use flume::Sender;
+use quill_common::events::{PlayerSample, ServerListPingEvent};
+use rand::seq::IteratorRandom;
+
+use crate::Server;
+
+/// Maximum number of players listed in a status response.
+const MAX_SAMPLE_SIZE: usize = 12;
+
+pub fn register(systems: &mut SystemExecutor<Game>) {
+    // Responses are sent before new events are triggered
+    // so that plugins get to see each event first.
+    systems
+        .group::<Server>()
+        .add_system(send_status_responses)
+        .add_system(trigger_server_list_pings);
+}
+
+/// Where to send the response to the `ServerListPingEvent`
+/// of the same entity.
+struct StatusReply(Sender<ServerListPingEvent>);
+
+fn trigger_server_list_pings(game: &mut Game, server: &mut Server) -> SysResult {
+    for request in server.status_requests.try_iter() {
+        let mut status = request.status;
+        status.online_players = server.player_count();
+        status.sample = server
+            .clients
+            .iter()
+            .choose_multiple(&mut rand::thread_rng(), MAX_SAMPLE_SIZE)
+            .into_iter()
+            .map(|client| PlayerSample {
+                name: client.username().to_owned(),
+                id: client.uuid(),
+            })
+            .collect();
+
+        let entity = game.ecs.spawn((StatusReply(request.reply),));
+        game.ecs.insert_entity_event(entity, status)?;
+    }
+    Ok(())
+}
+
+fn send_status_responses(game: &mut Game, _server: &mut Server) -> SysResult {
+    let mut answered = Vec::new();
+    for (entity, (status, reply)) in game
+        .ecs
+        .query::<(&ServerListPingEvent, &StatusReply)>()
+        .iter()
+    {
+        // The connection may have been closed in the meantime.
+        let _ = reply.0.try_send(status.clone());
+        answered.push(entity);
+    }
+
+    for entity in answered {
+        game.ecs.despawn(entity)?;
+    }
+    Ok(())
+}
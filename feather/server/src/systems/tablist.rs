@@ -6,7 +6,10 @@ use base::{Gamemode, ProfileProperty};
 use common::Game;
 use ecs::{SysResult, SystemExecutor};
 use quill_common::events::{EntityRemoveEvent, GamemodeEvent, PlayerJoinEvent};
-use quill_common::{components::Name, entities::Player};
+use quill_common::{
+    components::{Name, Ping},
+    entities::Player,
+};
 
 use crate::{ClientId, Server};
 
@@ -15,9 +18,13 @@ pub fn register(systems: &mut SystemExecutor<Game>) {
         .group::<Server>()
         .add_system(remove_tablist_players)
         .add_system(add_tablist_players)
-        .add_system(change_tablist_player_gamemode);
+        .add_system(change_tablist_player_gamemode)
+        .add_system(update_tablist_pings);
 }
 
+/// Ticks between updates of the latencies shown in the tablist.
+const PING_UPDATE_INTERVAL: u64 = 100;
+
 fn remove_tablist_players(game: &mut Game, server: &mut Server) -> SysResult {
     for (_, (_event, _player, &uuid)) in game
         .ecs
@@ -30,7 +37,7 @@ fn remove_tablist_players(game: &mut Game, server: &mut Server) -> SysResult {
 }
 
 fn add_tablist_players(game: &mut Game, server: &mut Server) -> SysResult {
-    for (player, (_, &client_id, &uuid, name, &gamemode, profile)) in game
+    for (player, (_, &client_id, &uuid, name, &gamemode, profile, ping)) in game
         .ecs
         .query::<(
             &PlayerJoinEvent,
@@ -39,23 +46,36 @@ fn add_tablist_players(game: &mut Game, server: &mut Server) -> SysResult {
             &Name,
             &Gamemode,
             &Vec<ProfileProperty>,
+            &Ping,
         )>()
         .iter()
     {
         // Add this player to other players' tablists
         server.broadcast_with(|client| {
-            client.add_tablist_player(uuid, name.to_string(), profile, gamemode)
+            client.add_tablist_player(
+                uuid,
+                name.to_string(),
+                profile,
+                gamemode,
+                ping.latency as i32,
+            )
         });
 
         // Add other players to this player's tablist
-        for (other_player, (&uuid, name, &gamemode, profile)) in game
+        for (other_player, (&uuid, name, &gamemode, profile, ping)) in game
             .ecs
-            .query::<(&Uuid, &Name, &Gamemode, &Vec<ProfileProperty>)>()
+            .query::<(&Uuid, &Name, &Gamemode, &Vec<ProfileProperty>, &Ping)>()
             .iter()
         {
             if let Some(client) = server.clients.get(client_id) {
                 if other_player != player {
-                    client.add_tablist_player(uuid, name.to_string(), profile, gamemode);
+                    client.add_tablist_player(
+                        uuid,
+                        name.to_string(),
+                        profile,
+                        gamemode,
+                        ping.latency as i32,
+                    );
                 }
             }
         }
@@ -70,3 +90,20 @@ fn change_tablist_player_gamemode(game: &mut Game, server: &mut Server) -> SysRe
     }
     Ok(())
 }
+
+fn update_tablist_pings(game: &mut Game, server: &mut Server) -> SysResult {
+    if game.tick_count % PING_UPDATE_INTERVAL != 0 {
+        return Ok(());
+    }
+
+    let pings: Vec<(Uuid, i32)> = game
+        .ecs
+        .query::<(&Uuid, &Ping)>()
+        .iter()
+        .map(|(_, (&uuid, ping))| (uuid, ping.latency as i32))
+        .collect();
+    if !pings.is_empty() {
+        server.broadcast_with(|client| client.update_tablist_pings(pings.clone()));
+    }
+    Ok(())
+}
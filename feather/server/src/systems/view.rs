@@ -4,36 +4,58 @@
 //! determined based on the player's [`common::view::View`].
 
 use ahash::AHashMap;
-use base::{ChunkPosition, Position};
+use base::{ChunkPosition, Gamemode, Position};
 use common::{
-    events::{ChunkLoadEvent, ViewUpdateEvent},
-    Game,
+    events::{ChunkLoadEvent, ViewUpdateEvent, WorldChangeEvent},
+    Game, WorldId,
 };
 use ecs::{Entity, SysResult, SystemExecutor};
+use quill_common::components::PreviousGamemode;
 
-use crate::{Client, ClientId, Server};
+use crate::{chunk_packets::ChunkPackets, Client, ClientId, Server};
 
 pub fn register(_game: &mut Game, systems: &mut SystemExecutor<Game>) {
     systems
         .group::<Server>()
+        .add_system(send_respawn_on_world_change)
         .add_system(send_new_chunks)
         .add_system(send_loaded_chunks);
 }
 
 /// Stores the players waiting on chunks that are currently being loaded.
 #[derive(Default)]
-pub struct WaitingChunks(AHashMap<ChunkPosition, Vec<Entity>>);
+pub struct WaitingChunks(AHashMap<(WorldId, ChunkPosition), Vec<Entity>>);
 
 impl WaitingChunks {
-    pub fn drain_players_waiting_for(&mut self, chunk: ChunkPosition) -> Vec<Entity> {
-        self.0.remove(&chunk).unwrap_or_default()
+    pub fn drain_players_waiting_for(
+        &mut self,
+        world: WorldId,
+        chunk: ChunkPosition,
+    ) -> Vec<Entity> {
+        self.0.remove(&(world, chunk)).unwrap_or_default()
     }
 
-    pub fn insert(&mut self, player: Entity, chunk: ChunkPosition) {
-        self.0.entry(chunk).or_default().push(player);
+    pub fn insert(&mut self, player: Entity, world: WorldId, chunk: ChunkPosition) {
+        self.0.entry((world, chunk)).or_default().push(player);
     }
 }
 
+/// Sends `Respawn` to players who moved to another world,
+/// before the chunks of the new world are sent.
+fn send_respawn_on_world_change(game: &mut Game, server: &mut Server) -> SysResult {
+    for (_, (&client_id, event, &gamemode, &previous_gamemode)) in game
+        .ecs
+        .query::<(&ClientId, &WorldChangeEvent, &Gamemode, &PreviousGamemode)>()
+        .iter()
+    {
+        if let Some(client) = server.clients.get(client_id) {
+            let dimension = game.worlds[event.new_world].dimension();
+            client.send_respawn(dimension, gamemode, previous_gamemode);
+        }
+    }
+    Ok(())
+}
+
 fn send_new_chunks(game: &mut Game, server: &mut Server) -> SysResult {
     for (player, (&client_id, event, &position)) in game
         .ecs
@@ -44,6 +66,10 @@ fn send_new_chunks(game: &mut Game, server: &mut Server) -> SysResult {
         // happen that a client is still listed in the ecs but actually removed here so
         // we need to check if the client is actually still there.
         if let Some(client) = server.clients.get(client_id) {
+            let view_distance = event.new_view.view_distance();
+            if !event.old_view.is_empty() && event.old_view.view_distance() != view_distance {
+                client.update_view_distance(view_distance);
+            }
             client.update_own_chunk(event.new_view.center());
             update_chunks(
                 game,
@@ -52,6 +78,7 @@ fn send_new_chunks(game: &mut Game, server: &mut Server) -> SysResult {
                 event,
                 position,
                 &mut server.waiting_chunks,
+                &mut server.chunk_packets,
             )?;
         }
     }
@@ -65,19 +92,24 @@ fn update_chunks(
     event: &ViewUpdateEvent,
     position: Position,
     waiting_chunks: &mut WaitingChunks,
+    chunk_packets: &mut ChunkPackets,
 ) -> SysResult {
     // Send chunks that are in the new view but not the old view.
+    let world = event.new_view.world();
     for &pos in &event.new_chunks {
-        if let Some(chunk) = game.world.chunk_map().chunk_handle_at(pos) {
-            client.send_chunk(&chunk);
+        if let Some(chunk) = game.worlds[world].chunk_map().chunk_handle_at(pos) {
+            client.send_chunk(&chunk_packets.get(world, pos, &chunk));
         } else {
-            waiting_chunks.insert(player, pos);
+            waiting_chunks.insert(player, world, pos);
         }
     }
 
     // Unsend the chunks that are in the old view but not the new view.
-    for &pos in &event.old_chunks {
-        client.unload_chunk(pos);
+    // After changing worlds, the client has already discarded them.
+    if event.old_view.world() == world {
+        for &pos in &event.old_chunks {
+            client.unload_chunk(pos);
+        }
     }
 
     spawn_client_if_needed(client, position);
@@ -91,11 +123,22 @@ fn send_loaded_chunks(game: &mut Game, server: &mut Server) -> SysResult {
     for (_, event) in game.ecs.query::<&ChunkLoadEvent>().iter() {
         for player in server
             .waiting_chunks
-            .drain_players_waiting_for(event.position)
+            .drain_players_waiting_for(event.world, event.position)
         {
+            // Skip players who have moved to another world since.
+            if game
+                .ecs
+                .get::<WorldId>(player)
+                .map_or(true, |world| *world != event.world)
+            {
+                continue;
+            }
             if let Ok(client_id) = game.ecs.get::<ClientId>(player) {
                 if let Some(client) = server.clients.get(*client_id) {
-                    client.send_chunk(&event.chunk);
+                    let chunk = server
+                        .chunk_packets
+                        .get(event.world, event.position, &event.chunk);
+                    client.send_chunk(&chunk);
                     spawn_client_if_needed(client, *game.ecs.get::<Position>(player)?);
                 }
             }
@@ -0,0 +1,195 @@
+//! Logs in to an online-mode server which authenticates
+//! players against a local stand-in for the session server.
+
+use std::{
+    io::{BufRead, BufReader, Write},
+    net::TcpListener,
+    sync::Arc,
+    thread,
+    time::Duration,
+};
+
+use base::Gamemode;
+use feather_server::{auth::SessionServerAuthenticator, rate_limit::RateLimits, Options, Server};
+use num_bigint::BigInt;
+use protocol::{
+    packets::client::{EncryptionResponse, Handshake, HandshakeState, LoginStart},
+    ClientHandshakePacket, ClientLoginPacket, MinecraftCodec, Readable, ServerLoginPacket,
+    Writeable,
+};
+use rand::rngs::OsRng;
+use rsa::{BigUint, PaddingScheme, PublicKey, RsaPublicKey};
+use sha1::{Digest, Sha1};
+use tokio::{
+    io::{AsyncReadExt, AsyncWriteExt},
+    net::TcpStream,
+};
+use uuid::Uuid;
+
+const USERNAME: &str = "Notch";
+const UUID: &str = "069a79f444e94726a5befca90e38aaf5";
+
+/// Answers a single `hasJoined` request with the profile
+/// of `USERNAME`, returning the request line.
+fn spawn_session_server() -> (u16, thread::JoinHandle<String>) {
+    let listener = TcpListener::bind("127.0.0.1:0").unwrap();
+    let port = listener.local_addr().unwrap().port();
+    let handle = thread::spawn(move || {
+        let (stream, _) = listener.accept().unwrap();
+        let mut reader = BufReader::new(stream);
+        let mut request_line = String::new();
+        reader.read_line(&mut request_line).unwrap();
+        loop {
+            let mut header = String::new();
+            reader.read_line(&mut header).unwrap();
+            if header.trim().is_empty() {
+                break;
+            }
+        }
+
+        let body = format!(
+            r#"{{"id":"{}","name":"{}","properties":[]}}"#,
+            UUID, USERNAME
+        );
+        write!(
+            reader.into_inner(),
+            "HTTP/1.1 200 OK\r\nContent-Type: application/json\r\nContent-Length: {}\r\nConnection: close\r\n\r\n{}",
+            body.len(),
+            body
+        )
+        .unwrap();
+        request_line
+    });
+    (port, handle)
+}
+
+fn free_port() -> u16 {
+    TcpListener::bind("127.0.0.1:0")
+        .unwrap()
+        .local_addr()
+        .unwrap()
+        .port()
+}
+
+/// The client side of a connection.
+struct Connection {
+    stream: TcpStream,
+    reader: MinecraftCodec,
+    writer: MinecraftCodec,
+}
+
+impl Connection {
+    async fn write(&mut self, packet: &impl Writeable) {
+        let mut buffer = Vec::new();
+        self.writer.encode(packet, &mut buffer).unwrap();
+        self.stream.write_all(&buffer).await.unwrap();
+    }
+
+    async fn read<T: Readable>(&mut self) -> T {
+        let mut buffer = [0; 1024];
+        loop {
+            if let Some(packet) = self.reader.next_packet().unwrap() {
+                return packet;
+            }
+            let read = self.stream.read(&mut buffer).await.unwrap();
+            assert_ne!(read, 0, "server closed the connection");
+            self.reader.accept(&buffer[..read]);
+        }
+    }
+}
+
+fn server_hash(shared_secret: &[u8], public_key: &[u8]) -> String {
+    let mut hasher = Sha1::new();
+    hasher.update(shared_secret);
+    hasher.update(public_key);
+    format!("{:x}", BigInt::from_signed_bytes_be(&hasher.finalize()))
+}
+
+#[tokio::test]
+async fn online_mode_login() {
+    let (session_port, session_server) = spawn_session_server();
+    let port = free_port();
+    let options = Options {
+        port,
+        bind_address: "127.0.0.1".to_owned(),
+        favicon: None,
+        motd: String::new(),
+        online_mode: true,
+        authenticator: Arc::new(SessionServerAuthenticator::new(
+            format!("http://127.0.0.1:{}", session_port),
+            Duration::from_secs(5),
+        )),
+        view_distance: 8,
+        chunks_per_tick: 10,
+        max_players: 1,
+        default_gamemode: Gamemode::Survival,
+        operators: Vec::new(),
+        proxy_mode: None,
+        velocity_secret: String::new(),
+        compression_threshold: None,
+        rate_limits: RateLimits::unlimited(),
+        rcon: None,
+        query: None,
+    };
+    let _server = Server::bind(options).await.unwrap();
+
+    let mut connection = Connection {
+        stream: TcpStream::connect(("127.0.0.1", port)).await.unwrap(),
+        reader: MinecraftCodec::new(),
+        writer: MinecraftCodec::new(),
+    };
+    connection
+        .write(&ClientHandshakePacket::Handshake(Handshake {
+            protocol_version: 754,
+            server_address: "127.0.0.1".to_owned(),
+            server_port: port,
+            next_state: HandshakeState::Login,
+        }))
+        .await;
+    connection
+        .write(&ClientLoginPacket::LoginStart(LoginStart {
+            name: USERNAME.to_owned(),
+        }))
+        .await;
+
+    let request = match connection.read::<ServerLoginPacket>().await {
+        ServerLoginPacket::EncryptionRequest(request) => request,
+        packet => panic!("expected encryption request, got {:?}", packet),
+    };
+    let (n, e) = rsa_der::public_key_from_der(&request.public_key).unwrap();
+    let public_key =
+        RsaPublicKey::new(BigUint::from_bytes_be(&n), BigUint::from_bytes_be(&e)).unwrap();
+    let encrypt = |data: &[u8]| {
+        public_key
+            .encrypt(&mut OsRng, PaddingScheme::new_pkcs1v15_encrypt(), data)
+            .unwrap()
+    };
+    let shared_secret: [u8; 16] = rand::random();
+    connection
+        .write(&ClientLoginPacket::EncryptionResponse(EncryptionResponse {
+            shared_secret: encrypt(&shared_secret),
+            verify_token: encrypt(&request.verify_token),
+        }))
+        .await;
+    connection.reader.enable_encryption(shared_secret);
+    connection.writer.enable_encryption(shared_secret);
+
+    let success = match connection.read::<ServerLoginPacket>().await {
+        ServerLoginPacket::LoginSuccess(success) => success,
+        packet => panic!("expected login success, got {:?}", packet),
+    };
+    assert_eq!(success.uuid, Uuid::parse_str(UUID).unwrap());
+    assert_eq!(success.username, USERNAME);
+
+    let request_line = session_server.join().unwrap();
+    let expected = format!(
+        "GET /session/minecraft/hasJoined?username={}&serverId={} ",
+        USERNAME,
+        server_hash(&shared_secret, &request.public_key)
+    );
+    assert!(
+        request_line.starts_with(&expected),
+        "unexpected request {}",
+        request_line
+    );
+}
@@ -0,0 +1,158 @@
+use super::{can_reach, for_each_start_chunk, max_tunnel_length, Ellipsoid};
+use crate::CarvingGenerator;
+use base::Chunk;
+use rand::{Rng, SeedableRng};
+use rand_xorshift::XorShiftRng;
+use std::f64::consts::PI;
+
+/// Probability that caves start in a given chunk.
+const CAVE_CHANCE: f64 = 1.0 / 7.0;
+
+/// Carves winding tunnels, known as perlin worms,
+/// occasionally branching off from large rooms.
+#[derive(Debug, Default)]
+pub struct CaveCarver;
+
+impl CarvingGenerator for CaveCarver {
+    fn carve_chunk(&self, chunk: &mut Chunk, seed: u64) {
+        let target = chunk.position();
+        for_each_start_chunk(target, seed, |start, rng| {
+            if !rng.gen_bool(CAVE_CHANCE) {
+                return;
+            }
+
+            // Skewed towards few caves per chunk.
+            let count = rng.gen_range(0, rng.gen_range(0, rng.gen_range(0, 15) + 1) + 1);
+            for _ in 0..count {
+                let x = f64::from(start.x * 16 + rng.gen_range(0, 16));
+                let y = f64::from(rng.gen_range(0, rng.gen_range(0, 120) + 8));
+                let z = f64::from(start.z * 16 + rng.gen_range(0, 16));
+
+                let mut tunnels = 1;
+                if rng.gen_range(0, 4) == 0 {
+                    let room = Tunnel {
+                        x,
+                        y,
+                        z,
+                        thickness: 1.0 + rng.gen::<f64>() * 6.0,
+                        yaw: 0.0,
+                        pitch: 0.0,
+                        vertical_scale: 0.5,
+                    };
+                    room.carve_room(chunk);
+                    tunnels += rng.gen_range(0, 4);
+                }
+
+                for _ in 0..tunnels {
+                    let mut thickness = rng.gen::<f64>() * 2.0 + rng.gen::<f64>();
+                    if rng.gen_range(0, 10) == 0 {
+                        thickness *= rng.gen::<f64>() * rng.gen::<f64>() * 3.0 + 1.0;
+                    }
+                    let length = max_tunnel_length() - rng.gen_range(0, max_tunnel_length() / 4);
+                    let tunnel = Tunnel {
+                        x,
+                        y,
+                        z,
+                        thickness,
+                        yaw: rng.gen::<f64>() * PI * 2.0,
+                        pitch: (rng.gen::<f64>() - 0.5) / 4.0,
+                        vertical_scale: 1.0,
+                    };
+                    tunnel.carve(chunk, rng.gen(), 0, length);
+                }
+            }
+        });
+    }
+}
+
+/// A tunnel being carved, along with its current position and direction.
+#[derive(Copy, Clone)]
+struct Tunnel {
+    x: f64,
+    y: f64,
+    z: f64,
+    thickness: f64,
+    yaw: f64,
+    pitch: f64,
+    vertical_scale: f64,
+}
+
+impl Tunnel {
+    /// Carves a single large ellipsoid, flattened vertically.
+    fn carve_room(&self, chunk: &mut Chunk) {
+        let horizontal_radius = 1.5 + self.thickness;
+        Ellipsoid {
+            x: self.x + 1.0,
+            y: self.y,
+            z: self.z,
+            horizontal_radius,
+            vertical_radius: horizontal_radius * self.vertical_scale,
+        }
+        .carve(chunk, skip_floor);
+    }
+
+    /// Carves the steps from `start` to `length` of this tunnel,
+    /// possibly splitting it into two branches partway.
+    fn carve(mut self, chunk: &mut Chunk, seed: u64, start: i32, length: i32) {
+        let target = chunk.position();
+        let mut rng = XorShiftRng::seed_from_u64(seed);
+        let branch_at = rng.gen_range(0, (length / 2).max(1)) + length / 4;
+        let steep = rng.gen_range(0, 6) == 0;
+        let (mut yaw_change, mut pitch_change) = (0.0, 0.0);
+
+        for step in start..length {
+            let horizontal_radius =
+                1.5 + (PI * f64::from(step) / f64::from(length)).sin() * self.thickness;
+            let vertical_radius = horizontal_radius * self.vertical_scale;
+
+            let cos_pitch = self.pitch.cos();
+            self.x += self.yaw.cos() * cos_pitch;
+            self.y += self.pitch.sin();
+            self.z += self.yaw.sin() * cos_pitch;
+
+            self.pitch *= if steep { 0.92 } else { 0.7 };
+            self.pitch += pitch_change * 0.1;
+            self.yaw += yaw_change * 0.1;
+            pitch_change *= 0.9;
+            yaw_change *= 0.75;
+            pitch_change += (rng.gen::<f64>() - rng.gen::<f64>()) * rng.gen::<f64>() * 2.0;
+            yaw_change += (rng.gen::<f64>() - rng.gen::<f64>()) * rng.gen::<f64>() * 4.0;
+
+            if step == branch_at && self.thickness > 1.0 {
+                for &turn in &[-PI / 2.0, PI / 2.0] {
+                    let branch = Tunnel {
+                        thickness: rng.gen::<f64>() * 0.5 + 0.5,
+                        yaw: self.yaw + turn,
+                        pitch: self.pitch / 3.0,
+                        vertical_scale: 1.0,
+                        ..self
+                    };
+                    branch.carve(chunk, rng.gen(), step, length);
+                }
+                return;
+            }
+
+            if rng.gen_range(0, 4) == 0 {
+                continue;
+            }
+            if !can_reach(target, self.x, self.z, step, length, self.thickness) {
+                return;
+            }
+
+            Ellipsoid {
+                x: self.x,
+                y: self.y,
+                z: self.z,
+                horizontal_radius,
+                vertical_radius,
+            }
+            .carve(chunk, skip_floor);
+        }
+    }
+}
+
+/// Leaves the bottom of each ellipsoid in place,
+/// which gives caves flat floors.
+fn skip_floor(_dx: f64, dy: f64, _dz: f64, _y: i32) -> bool {
+    dy <= -0.7
+}
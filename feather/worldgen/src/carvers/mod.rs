@@ -0,0 +1,283 @@
+//! Carvers, which cut caves and ravines into the terrain.
+//!
+//! A carved feature may start up to `CARVER_RANGE` chunks
+//! away from the chunk being carved. Each carver replays the
+//! features starting in every chunk within range, seeded only by
+//! the world seed and the starting chunk, and carves the blocks
+//! which lie in the current chunk. This allows features to cross
+//! chunk borders without access to neighbouring chunks.
+
+mod caves;
+mod ravines;
+
+pub use caves::CaveCarver;
+pub use ravines::RavineCarver;
+
+use crate::util::shuffle_seed_for_chunk;
+use base::{BlockId, BlockKind, Chunk, ChunkPosition};
+use rand::SeedableRng;
+use rand_xorshift::XorShiftRng;
+
+/// Distance, in chunks, from which a carved
+/// feature may reach into a chunk.
+const CARVER_RANGE: i32 = 4;
+/// Carved blocks at or below this height are filled with lava.
+const LAVA_LEVEL: i32 = 10;
+/// Blocks above this height are never carved.
+const MAX_CARVE_HEIGHT: i32 = 247;
+
+/// Maximum number of steps of a single tunnel, chosen
+/// so that tunnels stay within `CARVER_RANGE`.
+fn max_tunnel_length() -> i32 {
+    (CARVER_RANGE * 2 - 1) * 16
+}
+
+/// Calls `start` for each chunk within `CARVER_RANGE` of `chunk`,
+/// along with a random number generator seeded from that chunk.
+fn for_each_start_chunk(
+    chunk: ChunkPosition,
+    seed: u64,
+    mut start: impl FnMut(ChunkPosition, &mut XorShiftRng),
+) {
+    for z in chunk.z - CARVER_RANGE..=chunk.z + CARVER_RANGE {
+        for x in chunk.x - CARVER_RANGE..=chunk.x + CARVER_RANGE {
+            let start_chunk = ChunkPosition::new(x, z);
+            let mut rng = XorShiftRng::seed_from_u64(shuffle_seed_for_chunk(seed, start_chunk));
+            start(start_chunk, &mut rng);
+        }
+    }
+}
+
+/// Returns whether a tunnel at the given position
+/// could still reach `chunk` in its remaining steps.
+fn can_reach(chunk: ChunkPosition, x: f64, z: f64, step: i32, length: i32, thickness: f64) -> bool {
+    let dx = x - f64::from(chunk.x * 16 + 8);
+    let dz = z - f64::from(chunk.z * 16 + 8);
+    let remaining = f64::from(length - step);
+    let max_distance = thickness + 2.0 + 16.0;
+    dx * dx + dz * dz - remaining * remaining <= max_distance * max_distance
+}
+
+/// An ellipsoid to carve out of a chunk.
+struct Ellipsoid {
+    x: f64,
+    y: f64,
+    z: f64,
+    horizontal_radius: f64,
+    vertical_radius: f64,
+}
+
+impl Ellipsoid {
+    /// Carves the blocks of `chunk` inside this ellipsoid.
+    ///
+    /// `skip` is called with the offset of each block from the center,
+    /// divided by the radii, along with the block's height, and may
+    /// return `true` to leave the block in place.
+    ///
+    /// Blocks next to or below water are left in place, which keeps
+    /// oceans and lakes from draining into caves.
+    fn carve(&self, chunk: &mut Chunk, mut skip: impl FnMut(f64, f64, f64, i32) -> bool) {
+        let pos = chunk.position();
+        let (base_x, base_z) = (f64::from(pos.x * 16), f64::from(pos.z * 16));
+
+        let bounds = |center: f64, radius: f64, base: f64, max: i32| {
+            let min = ((center - radius - base).floor() as i32 - 1).max(0);
+            let upper = ((center + radius - base).floor() as i32 + 1).min(max);
+            (min, upper)
+        };
+        let (min_x, max_x) = bounds(self.x, self.horizontal_radius, base_x, 15);
+        let (min_z, max_z) = bounds(self.z, self.horizontal_radius, base_z, 15);
+        let (min_y, max_y) = bounds(self.y, self.vertical_radius, 0.0, MAX_CARVE_HEIGHT);
+        let min_y = min_y.max(1);
+        if min_x > max_x || min_z > max_z || min_y > max_y {
+            return;
+        }
+
+        for x in min_x..=max_x {
+            let dx = (base_x + f64::from(x) + 0.5 - self.x) / self.horizontal_radius;
+            for z in min_z..=max_z {
+                let dz = (base_z + f64::from(z) + 0.5 - self.z) / self.horizontal_radius;
+                if dx * dx + dz * dz >= 1.0 {
+                    continue;
+                }
+
+                // Go from top to bottom so that grass
+                // can be moved down onto exposed dirt.
+                let mut carved_grass = false;
+                for y in (min_y..=max_y).rev() {
+                    let dy = (f64::from(y) + 0.5 - self.y) / self.vertical_radius;
+                    if dx * dx + dy * dy + dz * dz >= 1.0 || skip(dx, dy, dz, y) {
+                        continue;
+                    }
+                    let (x, y, z) = (x as usize, y as usize, z as usize);
+                    if touches_water(chunk, x, y, z) {
+                        continue;
+                    }
+                    if let Some(carved) = carve_block(chunk, x, y, z) {
+                        carved_grass |= carved.kind() == BlockKind::GrassBlock;
+                        if carved_grass && chunk.block_at(x, y - 1, z) == Some(BlockId::dirt()) {
+                            chunk.set_block_at(x, y - 1, z, BlockId::grass_block());
+                        }
+                    }
+                }
+            }
+        }
+    }
+}
+
+/// Returns whether the given block, the block above it
+/// or one of its horizontal neighbours is water.
+fn touches_water(chunk: &Chunk, x: usize, y: usize, z: usize) -> bool {
+    let neighbours = [
+        Some((x, y, z)),
+        Some((x, y + 1, z)),
+        x.checked_sub(1).map(|x| (x, y, z)),
+        Some((x + 1, y, z)).filter(|&(x, _, _)| x < 16),
+        z.checked_sub(1).map(|z| (x, y, z)),
+        Some((x, y, z + 1)).filter(|&(_, _, z)| z < 16),
+    ];
+    neighbours.iter().flatten().any(|&(x, y, z)| {
+        chunk
+            .block_at(x, y, z)
+            .map_or(false, |block| block.kind() == BlockKind::Water)
+    })
+}
+
+/// Carves the given block if it's carvable,
+/// returning the block which was carved.
+fn carve_block(chunk: &mut Chunk, x: usize, y: usize, z: usize) -> Option<BlockId> {
+    let block = chunk
+        .block_at(x, y, z)
+        .filter(|&block| is_carvable(block))?;
+    let replacement = if y as i32 <= LAVA_LEVEL {
+        BlockId::lava()
+    } else {
+        BlockId::air()
+    };
+    chunk.set_block_at(x, y, z, replacement);
+    Some(block)
+}
+
+/// Returns whether the given block may be carved.
+///
+/// Sand and gravel are left alone since they
+/// would be left floating above the carved space.
+fn is_carvable(block: BlockId) -> bool {
+    matches!(
+        block.kind(),
+        BlockKind::Stone
+            | BlockKind::Granite
+            | BlockKind::Diorite
+            | BlockKind::Andesite
+            | BlockKind::Dirt
+            | BlockKind::CoarseDirt
+            | BlockKind::Podzol
+            | BlockKind::GrassBlock
+            | BlockKind::Mycelium
+            | BlockKind::Sandstone
+            | BlockKind::RedSandstone
+            | BlockKind::Terracotta
+            | BlockKind::Snow
+            | BlockKind::SnowBlock
+    )
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::CarvingGenerator;
+
+    fn stone_chunk(pos: ChunkPosition) -> Chunk {
+        let mut chunk = Chunk::new(pos);
+        for x in 0..16 {
+            for z in 0..16 {
+                chunk.set_block_at(x, 0, z, BlockId::bedrock());
+                for y in 1..100 {
+                    chunk.set_block_at(x, y, z, BlockId::stone());
+                }
+            }
+        }
+        chunk
+    }
+
+    #[test]
+    fn ellipsoid_fills_lava_below_level() {
+        let mut chunk = stone_chunk(ChunkPosition::new(0, 0));
+        let ellipsoid = Ellipsoid {
+            x: 8.0,
+            y: 10.0,
+            z: 8.0,
+            horizontal_radius: 4.0,
+            vertical_radius: 4.0,
+        };
+        ellipsoid.carve(&mut chunk, |_, _, _, _| false);
+
+        assert_eq!(chunk.block_at(8, 10, 8), Some(BlockId::lava()));
+        assert_eq!(chunk.block_at(8, 12, 8), Some(BlockId::air()));
+        assert_eq!(chunk.block_at(8, 20, 8), Some(BlockId::stone()));
+        assert_eq!(chunk.block_at(0, 10, 0), Some(BlockId::stone()));
+    }
+
+    #[test]
+    fn ellipsoid_leaves_water_alone() {
+        let mut chunk = stone_chunk(ChunkPosition::new(0, 0));
+        chunk.set_block_at(8, 53, 8, BlockId::water());
+        let ellipsoid = Ellipsoid {
+            x: 8.0,
+            y: 50.0,
+            z: 8.0,
+            horizontal_radius: 3.0,
+            vertical_radius: 3.0,
+        };
+        ellipsoid.carve(&mut chunk, |_, _, _, _| false);
+
+        assert_eq!(chunk.block_at(8, 53, 8), Some(BlockId::water()));
+        assert_eq!(chunk.block_at(8, 52, 8), Some(BlockId::stone()));
+        assert_eq!(chunk.block_at(7, 52, 8), Some(BlockId::air()));
+        assert_eq!(chunk.block_at(8, 50, 8), Some(BlockId::air()));
+    }
+
+    #[test]
+    fn carvers_are_deterministic_across_borders() {
+        // Carving must not depend on which chunks were
+        // carved before, only on the seed and position.
+        for carver in &[
+            Box::new(CaveCarver) as Box<dyn CarvingGenerator>,
+            Box::new(RavineCarver),
+        ] {
+            for &seed in &[0, 42, 1 << 40] {
+                let pos = ChunkPosition::new(3, -2);
+                let mut first = stone_chunk(pos);
+                carver.carve_chunk(&mut first, seed);
+                carver.carve_chunk(&mut stone_chunk(ChunkPosition::new(4, -2)), seed);
+                let mut second = stone_chunk(pos);
+                carver.carve_chunk(&mut second, seed);
+
+                for x in 0..16 {
+                    for z in 0..16 {
+                        for y in 0..100 {
+                            assert_eq!(first.block_at(x, y, z), second.block_at(x, y, z));
+                        }
+                    }
+                }
+            }
+        }
+    }
+
+    #[test]
+    fn caves_are_carved() {
+        let mut carved = 0;
+        for x in 0..4 {
+            let mut chunk = stone_chunk(ChunkPosition::new(x, 0));
+            CaveCarver.carve_chunk(&mut chunk, 1234);
+            for x in 0..16 {
+                for z in 0..16 {
+                    carved += (1..100)
+                        .filter(|&y| chunk.block_at(x, y, z) != Some(BlockId::stone()))
+                        .count();
+                }
+            }
+        }
+        assert!(carved > 0);
+    }
+}
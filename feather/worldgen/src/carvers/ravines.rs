@@ -0,0 +1,105 @@
+use super::{can_reach, for_each_start_chunk, max_tunnel_length, Ellipsoid};
+use crate::CarvingGenerator;
+use base::{Chunk, CHUNK_HEIGHT};
+use rand::{Rng, SeedableRng};
+use rand_xorshift::XorShiftRng;
+use std::f64::consts::PI;
+
+/// Probability that a ravine starts in a given chunk.
+const RAVINE_CHANCE: f64 = 0.02;
+/// Height of a ravine relative to its width.
+const RAVINE_VERTICAL_SCALE: f64 = 3.0;
+
+/// Carves ravines: long, narrow and deep
+/// tunnels with rough walls.
+#[derive(Debug, Default)]
+pub struct RavineCarver;
+
+impl CarvingGenerator for RavineCarver {
+    fn carve_chunk(&self, chunk: &mut Chunk, seed: u64) {
+        for_each_start_chunk(chunk.position(), seed, |start, rng| {
+            if !rng.gen_bool(RAVINE_CHANCE) {
+                return;
+            }
+
+            let ravine = Ravine {
+                x: f64::from(start.x * 16 + rng.gen_range(0, 16)),
+                y: f64::from(rng.gen_range(0, rng.gen_range(0, 40) + 8) + 20),
+                z: f64::from(start.z * 16 + rng.gen_range(0, 16)),
+                thickness: (rng.gen::<f64>() * 2.0 + rng.gen::<f64>()) * 2.0,
+                yaw: rng.gen::<f64>() * PI * 2.0,
+                pitch: (rng.gen::<f64>() - 0.5) / 4.0,
+            };
+            let length = max_tunnel_length() - rng.gen_range(0, max_tunnel_length() / 4);
+            ravine.carve(chunk, rng.gen(), length);
+        });
+    }
+}
+
+struct Ravine {
+    x: f64,
+    y: f64,
+    z: f64,
+    thickness: f64,
+    yaw: f64,
+    pitch: f64,
+}
+
+impl Ravine {
+    fn carve(mut self, chunk: &mut Chunk, seed: u64, length: i32) {
+        let target = chunk.position();
+        let mut rng = XorShiftRng::seed_from_u64(seed);
+
+        // Squared width factors for each height, which make
+        // the walls of the ravine rough. Consecutive heights
+        // share a factor so that the walls form ledges.
+        let mut widths = [1.0; CHUNK_HEIGHT];
+        let mut width = 1.0;
+        for (y, factor) in widths.iter_mut().enumerate() {
+            if y == 0 || rng.gen_range(0, 3) == 0 {
+                width = 1.0 + rng.gen::<f64>() * rng.gen::<f64>();
+            }
+            *factor = width * width;
+        }
+
+        let (mut yaw_change, mut pitch_change) = (0.0, 0.0);
+        for step in 0..length {
+            let mut horizontal_radius =
+                1.5 + (f64::from(step) * PI / f64::from(length)).sin() * self.thickness;
+            let mut vertical_radius = horizontal_radius * RAVINE_VERTICAL_SCALE;
+            horizontal_radius *= rng.gen::<f64>() * 0.25 + 0.75;
+            vertical_radius *= rng.gen::<f64>() * 0.25 + 0.75;
+
+            let cos_pitch = self.pitch.cos();
+            self.x += self.yaw.cos() * cos_pitch;
+            self.y += self.pitch.sin();
+            self.z += self.yaw.sin() * cos_pitch;
+
+            self.pitch *= 0.7;
+            self.pitch += pitch_change * 0.05;
+            self.yaw += yaw_change * 0.05;
+            pitch_change *= 0.8;
+            yaw_change *= 0.5;
+            pitch_change += (rng.gen::<f64>() - rng.gen::<f64>()) * rng.gen::<f64>() * 2.0;
+            yaw_change += (rng.gen::<f64>() - rng.gen::<f64>()) * rng.gen::<f64>() * 4.0;
+
+            if rng.gen_range(0, 4) == 0 {
+                continue;
+            }
+            if !can_reach(target, self.x, self.z, step, length, self.thickness) {
+                return;
+            }
+
+            Ellipsoid {
+                x: self.x,
+                y: self.y,
+                z: self.z,
+                horizontal_radius,
+                vertical_radius,
+            }
+            .carve(chunk, |dx, dy, dz, y| {
+                (dx * dx + dz * dz) * widths[y as usize - 1] + dy * dy / 6.0 >= 1.0
+            });
+        }
+    }
+}
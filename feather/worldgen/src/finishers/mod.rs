@@ -1,9 +1,11 @@
-//! Various finishers for world generation, such as grass, snow, and trees.
+//! Various finishers for world generation, such as grass, snow, and ores.
 
 mod clumped;
+mod ores;
 mod single;
 mod snow;
 
 pub use clumped::ClumpedFoliageFinisher;
+pub use ores::{OreFinisher, OreVein, VeinHeight};
 pub use single::SingleFoliageFinisher;
 pub use snow::SnowFinisher;
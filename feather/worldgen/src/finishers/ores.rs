@@ -0,0 +1,398 @@
+use crate::util::shuffle_seed_for_chunk;
+use crate::{FinishingGenerator, TopBlocks};
+use base::chunk::BiomeStore;
+use base::{Biome, BlockId, BlockKind, Chunk, ChunkPosition};
+use rand::{Rng, SeedableRng};
+use rand_xorshift::XorShiftRng;
+use std::f64::consts::PI;
+
+/// Finisher which scatters veins of ores and
+/// blobs of other blocks throughout the stone.
+///
+/// Veins may start in any of the eight neighbouring chunks, so
+/// the veins of each neighbour are regenerated from its seed
+/// and the blocks which lie in the current chunk are placed.
+pub struct OreFinisher {
+    veins: Vec<OreVein>,
+}
+
+impl OreFinisher {
+    /// Creates an `OreFinisher` which generates the given veins.
+    pub fn new(veins: impl IntoIterator<Item = OreVein>) -> Self {
+        Self {
+            veins: veins.into_iter().collect(),
+        }
+    }
+}
+
+impl OreFinisher {
+    /// Returns the veins of vanilla's ore features,
+    /// along with the names of the features.
+    pub fn vanilla_veins() -> Vec<(&'static str, OreVein)> {
+        let badlands = [
+            Biome::Badlands,
+            Biome::BadlandsPlateau,
+            Biome::ErodedBadlands,
+            Biome::WoodedBadlandsPlateau,
+            Biome::ModifiedBadlandsPlateau,
+            Biome::ModifiedWoodedBadlandsPlateau,
+        ];
+        let mountains = [
+            Biome::Mountains,
+            Biome::GravellyMountains,
+            Biome::WoodedMountains,
+            Biome::ModifiedGravellyMountains,
+            Biome::MountainEdge,
+        ];
+        vec![
+            (
+                "ore_dirt",
+                OreVein::new(BlockId::dirt(), 33, 10, VeinHeight::Uniform(0, 256)),
+            ),
+            (
+                "ore_gravel",
+                OreVein::new(BlockId::gravel(), 33, 8, VeinHeight::Uniform(0, 256)),
+            ),
+            (
+                "ore_granite",
+                OreVein::new(BlockId::granite(), 33, 10, VeinHeight::Uniform(0, 80)),
+            ),
+            (
+                "ore_diorite",
+                OreVein::new(BlockId::diorite(), 33, 10, VeinHeight::Uniform(0, 80)),
+            ),
+            (
+                "ore_andesite",
+                OreVein::new(BlockId::andesite(), 33, 10, VeinHeight::Uniform(0, 80)),
+            ),
+            (
+                "ore_coal",
+                OreVein::new(BlockId::coal_ore(), 17, 20, VeinHeight::Uniform(0, 128)),
+            ),
+            (
+                "ore_iron",
+                OreVein::new(BlockId::iron_ore(), 9, 20, VeinHeight::Uniform(0, 64)),
+            ),
+            (
+                "ore_gold",
+                OreVein::new(BlockId::gold_ore(), 9, 2, VeinHeight::Uniform(0, 32)),
+            ),
+            (
+                "ore_gold_extra",
+                OreVein::new(BlockId::gold_ore(), 9, 20, VeinHeight::Uniform(32, 80))
+                    .in_biomes(&badlands),
+            ),
+            (
+                "ore_redstone",
+                OreVein::new(BlockId::redstone_ore(), 8, 8, VeinHeight::Uniform(0, 16)),
+            ),
+            (
+                "ore_diamond",
+                OreVein::new(BlockId::diamond_ore(), 8, 1, VeinHeight::Uniform(0, 16)),
+            ),
+            (
+                "ore_lapis",
+                OreVein::new(BlockId::lapis_ore(), 7, 1, VeinHeight::Triangular(16, 16)),
+            ),
+            (
+                "ore_emerald",
+                OreVein::new(BlockId::emerald_ore(), 1, 3, VeinHeight::Uniform(4, 32))
+                    .with_extra_count(5)
+                    .in_biomes(&mountains),
+            ),
+        ]
+    }
+}
+
+impl Default for OreFinisher {
+    /// Creates an `OreFinisher` with vanilla vein sizes and heights.
+    fn default() -> Self {
+        Self::new(Self::vanilla_veins().into_iter().map(|(_, vein)| vein))
+    }
+}
+
+impl FinishingGenerator for OreFinisher {
+    fn generate_for_chunk(
+        &self,
+        chunk: &mut Chunk,
+        biomes: &BiomeStore,
+        _top_blocks: &TopBlocks,
+        seed: u64,
+    ) {
+        let center = chunk.position();
+        for z in -1..=1 {
+            for x in -1..=1 {
+                let start = ChunkPosition::new(center.x + x, center.z + z);
+                let mut rng = XorShiftRng::seed_from_u64(shuffle_seed_for_chunk(seed, start));
+                for vein in &self.veins {
+                    vein.generate(chunk, biomes, start, &mut rng);
+                }
+            }
+        }
+    }
+}
+
+/// The range of heights in which a vein may start.
+#[derive(Copy, Clone, Debug, PartialEq, Eq)]
+pub enum VeinHeight {
+    /// Any height in `min..max` is equally likely.
+    Uniform(i32, i32),
+    /// Heights within `spread` of `center` are chosen,
+    /// with those close to `center` the most likely.
+    Triangular(i32, i32),
+}
+
+impl VeinHeight {
+    fn choose(self, rng: &mut impl Rng) -> i32 {
+        match self {
+            VeinHeight::Uniform(min, max) => rng.gen_range(min, max.max(min + 1)),
+            VeinHeight::Triangular(center, spread) => {
+                let spread = spread.max(1);
+                rng.gen_range(0, spread) + rng.gen_range(0, spread) + center - spread
+            }
+        }
+    }
+}
+
+/// A kind of vein generated by an [`OreFinisher`].
+#[derive(Clone, Debug)]
+pub struct OreVein {
+    /// The block placed by the vein.
+    block: BlockId,
+    /// The maximum number of blocks in a single vein.
+    size: u32,
+    /// The number of veins started in each chunk.
+    count: u32,
+    /// A random number, up to this value,
+    /// of veins are added to `count`.
+    extra_count: u32,
+    height: VeinHeight,
+    /// The biomes in which the vein is placed,
+    /// or `None` for all biomes.
+    biomes: Option<Vec<Biome>>,
+    /// Biomes in which the vein is never placed.
+    excluded_biomes: Vec<Biome>,
+}
+
+impl OreVein {
+    /// Creates a vein which starts `count` times per
+    /// chunk at the given heights and replaces stone with
+    /// up to `size` blocks of `block`.
+    pub fn new(block: BlockId, size: u32, count: u32, height: VeinHeight) -> Self {
+        Self {
+            block,
+            size,
+            count,
+            extra_count: 0,
+            height,
+            biomes: None,
+            excluded_biomes: Vec::new(),
+        }
+    }
+
+    /// Adds a random number of veins, up
+    /// to `extra_count`, to each chunk.
+    pub fn with_extra_count(mut self, extra_count: u32) -> Self {
+        self.extra_count = extra_count;
+        self
+    }
+
+    /// Restricts the vein to the given biomes.
+    pub fn in_biomes(mut self, biomes: &[Biome]) -> Self {
+        self.biomes = Some(biomes.to_vec());
+        self
+    }
+
+    /// Keeps the vein out of the given biomes.
+    pub fn except_biomes(mut self, biomes: &[Biome]) -> Self {
+        self.excluded_biomes = biomes.to_vec();
+        self
+    }
+
+    /// Returns the biomes the vein is restricted
+    /// to, or `None` if it is placed in all biomes.
+    pub fn biomes(&self) -> Option<&[Biome]> {
+        self.biomes.as_deref()
+    }
+
+    /// Generates the veins starting in the chunk `start`,
+    /// placing the blocks which lie in `chunk`.
+    fn generate(
+        &self,
+        chunk: &mut Chunk,
+        biomes: &BiomeStore,
+        start: ChunkPosition,
+        rng: &mut impl Rng,
+    ) {
+        let count = self.count + rng.gen_range(0, self.extra_count + 1);
+        for _ in 0..count {
+            let x = start.x * 16 + rng.gen_range(0, 16);
+            let y = self.height.choose(rng);
+            let z = start.z * 16 + rng.gen_range(0, 16);
+
+            if self.size <= 1 {
+                self.place(chunk, biomes, x, y, z);
+                continue;
+            }
+
+            // The vein is a series of spheres along
+            // a line through the starting position.
+            let size = f64::from(self.size);
+            let angle = rng.gen::<f64>() * PI;
+            let (dx, dz) = (angle.sin() * size / 8.0, angle.cos() * size / 8.0);
+            let (x, z) = (f64::from(x) + 0.5, f64::from(z) + 0.5);
+            let from = (x + dx, f64::from(y + rng.gen_range(0, 3) - 1), z + dz);
+            let to = (x - dx, f64::from(y + rng.gen_range(0, 3) - 1), z - dz);
+
+            for i in 0..self.size {
+                let t = f64::from(i) / size;
+                let center_x = from.0 + (to.0 - from.0) * t;
+                let center_y = from.1 + (to.1 - from.1) * t;
+                let center_z = from.2 + (to.2 - from.2) * t;
+                let radius = ((PI * t).sin() + 1.0) * rng.gen::<f64>() * size / 32.0 + 0.5;
+
+                let range = |center: f64| {
+                    (center - radius).floor() as i32..=(center + radius).floor() as i32
+                };
+                for block_x in range(center_x) {
+                    let dx = (f64::from(block_x) + 0.5 - center_x) / radius;
+                    for block_y in range(center_y) {
+                        let dy = (f64::from(block_y) + 0.5 - center_y) / radius;
+                        for block_z in range(center_z) {
+                            let dz = (f64::from(block_z) + 0.5 - center_z) / radius;
+                            if dx * dx + dy * dy + dz * dz < 1.0 {
+                                self.place(chunk, biomes, block_x, block_y, block_z);
+                            }
+                        }
+                    }
+                }
+            }
+        }
+    }
+
+    /// Places the vein's block at the given absolute position,
+    /// if it lies in `chunk` and replaces stone.
+    fn place(&self, chunk: &mut Chunk, biomes: &BiomeStore, x: i32, y: i32, z: i32) {
+        let pos = chunk.position();
+        if x.div_euclid(16) != pos.x || z.div_euclid(16) != pos.z || !(0..256).contains(&y) {
+            return;
+        }
+        let (x, y, z) = (
+            x.rem_euclid(16) as usize,
+            y as usize,
+            z.rem_euclid(16) as usize,
+        );
+
+        let biome = biomes.get_at_block(x, y, z);
+        if self.excluded_biomes.contains(&biome)
+            || matches!(&self.biomes, Some(allowed) if !allowed.contains(&biome))
+        {
+            return;
+        }
+
+        let replaceable = chunk.block_at(x, y, z).map_or(false, |block| {
+            matches!(
+                block.kind(),
+                BlockKind::Stone | BlockKind::Granite | BlockKind::Diorite | BlockKind::Andesite
+            )
+        });
+        if replaceable {
+            chunk.set_block_at(x, y, z, self.block);
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn stone_chunk(pos: ChunkPosition) -> Chunk {
+        let mut chunk = Chunk::new(pos);
+        for x in 0..16 {
+            for z in 0..16 {
+                for y in 0..128 {
+                    chunk.set_block_at(x, y, z, BlockId::stone());
+                }
+            }
+        }
+        chunk
+    }
+
+    fn count_blocks(chunk: &Chunk, block: BlockId) -> usize {
+        let mut count = 0;
+        for x in 0..16 {
+            for z in 0..16 {
+                for y in 0..256 {
+                    if chunk.block_at(x, y, z) == Some(block) {
+                        count += 1;
+                    }
+                }
+            }
+        }
+        count
+    }
+
+    #[test]
+    fn ores_are_reproducible() {
+        let finisher = OreFinisher::default();
+        let biomes = BiomeStore::new(Biome::Plains);
+        let pos = ChunkPosition::new(-3, 7);
+
+        let mut first = stone_chunk(pos);
+        finisher.generate_for_chunk(&mut first, &biomes, &TopBlocks::new(), 10);
+        let mut second = stone_chunk(pos);
+        finisher.generate_for_chunk(&mut second, &biomes, &TopBlocks::new(), 10);
+
+        for x in 0..16 {
+            for z in 0..16 {
+                for y in 0..256 {
+                    assert_eq!(first.block_at(x, y, z), second.block_at(x, y, z));
+                }
+            }
+        }
+        assert!(count_blocks(&first, BlockId::coal_ore()) > 0);
+        assert!(count_blocks(&first, BlockId::iron_ore()) > 0);
+    }
+
+    #[test]
+    fn veins_respect_biomes() {
+        let finisher = OreFinisher::default();
+        let pos = ChunkPosition::new(0, 0);
+
+        let mut plains = stone_chunk(pos);
+        finisher.generate_for_chunk(
+            &mut plains,
+            &BiomeStore::new(Biome::Plains),
+            &TopBlocks::new(),
+            0,
+        );
+        assert_eq!(count_blocks(&plains, BlockId::emerald_ore()), 0);
+
+        let mut mountains = stone_chunk(pos);
+        finisher.generate_for_chunk(
+            &mut mountains,
+            &BiomeStore::new(Biome::Mountains),
+            &TopBlocks::new(),
+            0,
+        );
+        assert!(count_blocks(&mountains, BlockId::emerald_ore()) > 0);
+    }
+
+    #[test]
+    fn veins_only_replace_stone() {
+        let finisher = OreFinisher::new(vec![OreVein::new(
+            BlockId::diamond_ore(),
+            8,
+            50,
+            VeinHeight::Uniform(0, 256),
+        )]);
+        let mut chunk = Chunk::new(ChunkPosition::new(0, 0));
+        finisher.generate_for_chunk(
+            &mut chunk,
+            &BiomeStore::new(Biome::Plains),
+            &TopBlocks::new(),
+            0,
+        );
+        assert_eq!(count_blocks(&chunk, BlockId::diamond_ore()), 0);
+    }
+}
@@ -6,28 +6,50 @@
 //! which allows configuration of a world generator pipeline.
 
 mod biomes;
+mod carvers;
 mod composition;
 mod density_map;
 mod finishers;
 pub mod noise;
+mod population;
+mod region;
+mod structures;
 mod superflat;
+mod terrain_cache;
 mod util;
+mod vanilla;
 pub mod voronoi;
 
-use base::chunk::BiomeStore;
-use base::{Biome, BlockId, Chunk, ChunkPosition};
+use base::anvil::block_entity::{BlockEntityBase, BlockEntityData, BlockEntityKind};
+use base::chunk::{BiomeStore, StructureBox};
+use base::{Biome, BlockId, BlockKind, Chunk, ChunkPosition, EntityKind};
 pub use biomes::{DistortedVoronoiBiomeGenerator, TwoLevelBiomeGenerator};
 use bitvec::vec::BitVec;
 use bitvec::{order::LocalBits, slice::BitSlice};
+pub use carvers::{CaveCarver, RavineCarver};
 pub use composition::BasicCompositionGenerator;
 pub use density_map::{DensityMapGeneratorImpl, HeightMapGenerator};
 use finishers::{ClumpedFoliageFinisher, SingleFoliageFinisher, SnowFinisher};
+pub use finishers::{OreFinisher, OreVein, VeinHeight};
 pub use noise::NoiseLerper;
 use num_traits::ToPrimitive;
+pub use population::TreePopulator;
 use rand::{Rng, SeedableRng};
 use rand_xorshift::XorShiftRng;
+pub use region::ChunkRegion;
 use smallvec::SmallVec;
+use std::collections::HashMap;
+use std::sync::Arc;
+pub use structures::{
+    default_structures, DesertTemple, Direction, Jigsaw, JigsawPools, Mineshaft, Piece,
+    PoolElement, Projection, Stronghold, Structure, StructureContext, StructurePlacement, Template,
+    TemplateBlock, TemplatePart, TemplatePool, Village,
+};
+use structures::{AssembledStart, StartCache};
 pub use superflat::SuperflatWorldGenerator;
+use terrain_cache::TerrainCache;
+use util::shuffle_seed_for_chunk;
+pub use vanilla::{BiomeTerrain, NoiseSampling, NoiseSettings, Slide, VanillaWorldGenerator};
 
 /// Sea-level height.
 pub const SEA_LEVEL: usize = 64;
@@ -35,10 +57,30 @@ pub const SEA_LEVEL: usize = 64;
 pub const SKY_LIMIT: usize = 255;
 /// Depth of an ocean.
 const OCEAN_DEPTH: usize = 30;
+/// Number of chunks whose terrain is cached for population.
+const TERRAIN_CACHE_CAPACITY: usize = 1024;
+/// Mixed into the world seed to derive the seeds of population
+/// generators, so they are independent of the terrain seeds.
+const POPULATION_SEED_SALT: u64 = 0x5DEE_CE66_D1CE_4E5B;
+/// Number of chunks whose population changes are cached.
+const POPULATION_CACHE_CAPACITY: usize = 1024;
+/// Number of structure starts whose pieces are cached.
+const START_CACHE_CAPACITY: usize = 256;
+/// Mixed into the world seed to derive the seeds of structures.
+const STRUCTURE_SEED_SALT: u64 = 0x2F6B_1C55_93A0_7E41;
 
 pub trait WorldGenerator: Send + Sync {
     /// Generates the chunk at the given position.
     fn generate_chunk(&self, position: ChunkPosition) -> Chunk;
+
+    /// Generates the chunk at the given position along
+    /// with the block entities of the blocks placed in it.
+    fn generate_chunk_with_block_entities(
+        &self,
+        position: ChunkPosition,
+    ) -> (Chunk, Vec<BlockEntityData>) {
+        (self.generate_chunk(position), Vec::new())
+    }
 }
 
 pub struct VoidWorldGenerator;
@@ -59,7 +101,17 @@ impl WorldGenerator for VoidWorldGenerator {
 /// * Biomes - generates a biome grid.
 /// * Terrain density - generates the terrain density values using Perlin noise.
 /// * Terrain composition - sets the correct block types based on the biome and terrain density.
-/// * Finishing generators - generates final elements, such as grass, snow, and trees.
+/// * Carvers - carves caves and ravines out of the terrain.
+/// * Finishing generators - generates final elements, such as grass, snow and ores.
+/// * Population generators - generates features which may cross chunk borders, such as trees.
+/// * Structures - places the pieces of structures, such as villages, which span many chunks.
+///
+/// The first five stages only affect the chunk being generated. Population
+/// requires the terrain of the eight neighbouring chunks, and each chunk is
+/// populated by itself and its neighbours, since features such as trees
+/// may extend into the chunk from any of them. Structures are laid out
+/// on the terrain around their start, and each chunk places the parts
+/// of the pieces inside it.
 ///
 /// This generator is based on [this document](http://cuberite.xoft.cz/docs/Generator.html).
 pub struct ComposableGenerator {
@@ -69,57 +121,105 @@ pub struct ComposableGenerator {
     density_map: Box<dyn DensityMapGenerator>,
     /// The composition generator.
     composition: Box<dyn CompositionGenerator>,
+    /// Carvers, run in order.
+    carvers: SmallVec<[Box<dyn CarvingGenerator>; 4]>,
     /// A vector of finishing generators used
     /// by this composable generator.
     finishers: SmallVec<[Box<dyn FinishingGenerator>; 8]>,
+    /// Population generators, run in order.
+    populators: SmallVec<[Box<dyn PopulationGenerator>; 4]>,
+    /// Structures, placed in order.
+    structures: SmallVec<[Box<dyn Structure>; 4]>,
+    /// Terrain of recently generated chunks, used
+    /// to populate their neighbours and lay out structures.
+    terrain_cache: TerrainCache,
+    /// Blocks written by the population generators of recently
+    /// populated chunks, which their neighbours also contain.
+    population_cache: TerrainCache<RegionChanges>,
+    /// Recently laid out structure starts.
+    structure_starts: StartCache,
     /// The world seed.
     seed: u64,
 }
 
 impl ComposableGenerator {
     /// Creates a new `ComposableGenerator` with the given stages.
-    pub fn new<B, D, C, F>(
+    pub fn new<B, D, C, K, F, P>(
         biome: B,
         density_map: D,
         composition: C,
+        carvers: K,
         finishers: F,
+        populators: P,
         seed: u64,
     ) -> Self
     where
         B: BiomeGenerator + 'static,
         D: DensityMapGenerator + 'static,
         C: CompositionGenerator + 'static,
+        K: IntoIterator<Item = Box<dyn CarvingGenerator>>,
         F: IntoIterator<Item = Box<dyn FinishingGenerator>>,
+        P: IntoIterator<Item = Box<dyn PopulationGenerator>>,
     {
         Self {
             biome: Box::new(biome),
             density_map: Box::new(density_map),
             composition: Box::new(composition),
+            carvers: carvers.into_iter().collect(),
             finishers: finishers.into_iter().collect(),
+            populators: populators.into_iter().collect(),
+            structures: SmallVec::new(),
+            terrain_cache: TerrainCache::new(TERRAIN_CACHE_CAPACITY),
+            population_cache: TerrainCache::new(POPULATION_CACHE_CAPACITY),
+            structure_starts: StartCache::new(START_CACHE_CAPACITY),
             seed,
         }
     }
 
+    /// Sets the structures generated by this generator.
+    pub fn with_structures(
+        mut self,
+        structures: impl IntoIterator<Item = Box<dyn Structure>>,
+    ) -> Self {
+        self.structures = structures.into_iter().collect();
+        self
+    }
+
     /// A default composable generator, used
     /// for worlds with "default" world type.
     pub fn default_with_seed(seed: u64) -> Self {
+        Self::default_with_ores(seed, OreFinisher::default())
+    }
+
+    /// A default composable generator which
+    /// places the given ores instead of vanilla's.
+    ///
+    /// Villages are not generated, since their templates
+    /// come from data packs; see `with_structures`.
+    pub fn default_with_ores(seed: u64, ores: OreFinisher) -> Self {
         let finishers: Vec<Box<dyn FinishingGenerator>> = vec![
             Box::new(SnowFinisher::default()),
             Box::new(SingleFoliageFinisher::default()),
             Box::new(ClumpedFoliageFinisher::default()),
+            Box::new(ores),
         ];
+        let carvers: Vec<Box<dyn CarvingGenerator>> =
+            vec![Box::new(CaveCarver), Box::new(RavineCarver)];
+        let populators: Vec<Box<dyn PopulationGenerator>> = vec![Box::new(TreePopulator)];
         Self::new(
             TwoLevelBiomeGenerator::default(),
             DensityMapGeneratorImpl::default(),
             BasicCompositionGenerator::default(),
+            carvers,
             finishers,
+            populators,
             seed,
         )
+        .with_structures(default_structures(None))
     }
-}
 
-impl WorldGenerator for ComposableGenerator {
-    fn generate_chunk(&self, position: ChunkPosition) -> Chunk {
+    /// Runs every stage but population for the given chunk.
+    fn generate_terrain(&self, position: ChunkPosition) -> Chunk {
         let mut seed_shuffler = XorShiftRng::seed_from_u64(self.seed);
 
         // Generate biomes for 3x3 grid of chunks around current chunk.
@@ -150,6 +250,11 @@ impl WorldGenerator for ComposableGenerator {
             seed_shuffler.gen(),
         );
 
+        // Carvers.
+        for carver in &self.carvers {
+            carver.carve_chunk(&mut chunk, seed_shuffler.gen());
+        }
+
         // Calculate top blocks in chunk.
         // TODO: perhaps this should be moved to `Chunk`?
         let mut top_blocks = TopBlocks::new();
@@ -178,6 +283,279 @@ impl WorldGenerator for ComposableGenerator {
 
         chunk
     }
+
+    /// Gets the terrain of the given chunk from the cache,
+    /// generating it if needed.
+    fn terrain(&self, position: ChunkPosition) -> Arc<Chunk> {
+        self.terrain_cache
+            .get_or_generate(position, || self.generate_terrain(position))
+    }
+
+    /// Generates the given chunk and places the features
+    /// of it and its neighbours.
+    fn populate(&self, position: ChunkPosition) -> Chunk {
+        let mut chunk = Chunk::clone(&self.terrain(position));
+        for origin in population_order(position) {
+            region::apply_region_changes(&self.population_changes(origin), &mut chunk);
+        }
+        chunk
+    }
+
+    /// Gets the blocks written by the population generators
+    /// of the given chunk from the cache, generating them if needed.
+    ///
+    /// The features of a chunk are generated on top of those of
+    /// its neighbours in earlier phases (see [`population_phase`]),
+    /// so they see those features and don't grow into them. Since
+    /// this only depends on the position of the chunk, its features
+    /// are the same in each chunk they extend into.
+    fn population_changes(&self, origin: ChunkPosition) -> Arc<RegionChanges> {
+        self.population_cache.get_or_generate(origin, || {
+            let phase = population_phase(origin);
+            let mut earlier = HashMap::new();
+            for neighbour in population_order(origin) {
+                if population_phase(neighbour) < phase {
+                    earlier.extend(self.population_changes(neighbour).iter());
+                }
+            }
+
+            let terrain: Vec<Arc<Chunk>> =
+                chunks_around(origin).map(|pos| self.terrain(pos)).collect();
+            let chunks = [0, 1, 2, 3, 4, 5, 6, 7, 8].map(|i| &*terrain[i]);
+
+            let mut seed_shuffler = XorShiftRng::seed_from_u64(self.seed ^ POPULATION_SEED_SALT);
+            let mut region = ChunkRegion::with_changes(origin, chunks, earlier.clone());
+            for populator in &self.populators {
+                let seed = seed_shuffler.gen();
+                populator.populate(&mut region, shuffle_seed_for_chunk(seed, origin));
+            }
+
+            // Keep only the blocks written by this chunk's features
+            region
+                .into_changes()
+                .into_iter()
+                .filter(|(pos, block)| earlier.get(pos) != Some(block))
+                .collect()
+        })
+    }
+
+    /// Places the pieces of structures which intersect the chunk,
+    /// and records the starts and references of those structures in it.
+    /// Returns the block entities of the placed spawners.
+    fn place_structures(&self, chunk: &mut Chunk) -> Vec<BlockEntityData> {
+        let mut block_entities = Vec::new();
+        let position = chunk.position();
+        let chunk_box = StructureBox::for_chunk(position);
+        // Pieces may carve doorways into the piece next to them,
+        // one block outside of their own box.
+        let piece_area = StructureBox {
+            min_x: chunk_box.min_x - 1,
+            min_z: chunk_box.min_z - 1,
+            max_x: chunk_box.max_x + 1,
+            max_z: chunk_box.max_z + 1,
+            ..chunk_box
+        };
+
+        let mut seed_shuffler = XorShiftRng::seed_from_u64(self.seed ^ STRUCTURE_SEED_SALT);
+        for (index, structure) in self.structures.iter().enumerate() {
+            let seed: u64 = seed_shuffler.gen();
+            let radius = structure.max_radius();
+            let starts = structure.placement().starts_in_area(
+                seed,
+                ChunkPosition::new(position.x - radius, position.z - radius),
+                ChunkPosition::new(position.x + radius, position.z + radius),
+            );
+            for start_position in starts {
+                let start = self
+                    .structure_starts
+                    .get_or_assemble(index, start_position, || {
+                        let mut rng = XorShiftRng::seed_from_u64(shuffle_seed_for_chunk(
+                            seed,
+                            start_position,
+                        ));
+                        AssembledStart::new(structure.assemble(start_position, self, &mut rng))
+                    });
+                let start = match &*start {
+                    Some(start) => start,
+                    None => continue,
+                };
+
+                let name = structure.name();
+                if start_position == position {
+                    chunk
+                        .structures_mut()
+                        .set_start(name, start.to_saved(name, position));
+                }
+                if !start.bounding_box.intersects(&piece_area) {
+                    continue;
+                }
+                if start.bounding_box.intersects(&chunk_box) {
+                    chunk.structures_mut().add_reference(name, start_position);
+                }
+
+                // Seed each piece from its start, rather than from the chunk,
+                // so that it makes the same decisions in every chunk it spans.
+                let start_seed = shuffle_seed_for_chunk(seed, start_position);
+                for (i, piece) in start.pieces.iter().enumerate() {
+                    if piece.bounding_box().intersects(&piece_area) {
+                        let mut rng = XorShiftRng::seed_from_u64(start_seed.wrapping_add(i as u64));
+                        piece.place(chunk, self, &mut rng);
+                    }
+                }
+                for (i, piece) in start.pieces.iter().enumerate() {
+                    let bounding_box = piece.bounding_box();
+                    if let Some(mob) = piece.spawner_mob() {
+                        block_entities.extend(spawner_block_entities(chunk, &bounding_box, mob));
+                    }
+                    if let Some(loot_table) = piece.chest_loot_table() {
+                        block_entities.extend(chest_block_entities(
+                            chunk,
+                            &bounding_box,
+                            loot_table,
+                            start_seed.wrapping_add(i as u64),
+                        ));
+                    }
+                }
+            }
+        }
+        block_entities
+    }
+}
+
+/// Blocks written by population generators, keyed
+/// by their absolute position.
+type RegionChanges = HashMap<(i32, usize, i32), BlockId>;
+
+/// Returns the 3x3 chunks centered on `center`,
+/// ordered as in [`NearbyBiomes`].
+fn chunks_around(center: ChunkPosition) -> impl Iterator<Item = ChunkPosition> {
+    (-1..=1)
+        .flat_map(move |z| (-1..=1).map(move |x| ChunkPosition::new(center.x + x, center.z + z)))
+}
+
+/// Returns the phase in which the features of a chunk are
+/// generated, from 0 to 3. Neighbouring chunks are in different
+/// phases, and chunks in the same phase are two chunks apart,
+/// so their features rarely meet.
+fn population_phase(position: ChunkPosition) -> i32 {
+    position.x.rem_euclid(2) + position.z.rem_euclid(2) * 2
+}
+
+/// Returns the chunks whose features may extend into
+/// the chunk at `position`, in the order their
+/// features are placed.
+fn population_order(position: ChunkPosition) -> Vec<ChunkPosition> {
+    let mut origins: Vec<_> = chunks_around(position).collect();
+    origins.sort_by_key(|&origin| (population_phase(origin), origin.z, origin.x));
+    origins
+}
+
+/// Creates block entities spawning `mob` for the
+/// spawner blocks of `chunk` inside `bounding_box`.
+fn spawner_block_entities(
+    chunk: &Chunk,
+    bounding_box: &StructureBox,
+    mob: EntityKind,
+) -> Vec<BlockEntityData> {
+    block_entities_in(chunk, bounding_box, BlockKind::Spawner, |_, _, _| {
+        BlockEntityKind::mob_spawner(format!("minecraft:{}", mob.name()))
+    })
+}
+
+/// Creates block entities filled from `loot_table` for the chest
+/// blocks of `chunk` inside `bounding_box`. The loot of each chest
+/// is seeded from `seed` and the position of the chest.
+fn chest_block_entities(
+    chunk: &Chunk,
+    bounding_box: &StructureBox,
+    loot_table: &str,
+    seed: u64,
+) -> Vec<BlockEntityData> {
+    block_entities_in(chunk, bounding_box, BlockKind::Chest, |x, y, z| {
+        let seed = shuffle_seed_for_chunk(seed.wrapping_add(y as u64), ChunkPosition::new(x, z));
+        BlockEntityKind::Chest {
+            items: Vec::new(),
+            loot_table: Some(loot_table.to_owned()),
+            loot_table_seed: Some(seed as i64),
+        }
+    })
+}
+
+/// Creates a block entity with `block_entity` for each
+/// block of `kind` in `chunk` inside `bounding_box`.
+fn block_entities_in(
+    chunk: &Chunk,
+    bounding_box: &StructureBox,
+    kind: BlockKind,
+    mut block_entity: impl FnMut(i32, i32, i32) -> BlockEntityKind,
+) -> Vec<BlockEntityData> {
+    let chunk_box = StructureBox::for_chunk(chunk.position());
+    if !bounding_box.intersects(&chunk_box) {
+        return Vec::new();
+    }
+
+    let mut block_entities = Vec::new();
+    for x in bounding_box.min_x.max(chunk_box.min_x)..=bounding_box.max_x.min(chunk_box.max_x) {
+        for z in bounding_box.min_z.max(chunk_box.min_z)..=bounding_box.max_z.min(chunk_box.max_z) {
+            for y in bounding_box.min_y.max(0)..=bounding_box.max_y.min(SKY_LIMIT as i32) {
+                let block = chunk.block_at(
+                    (x - chunk_box.min_x) as usize,
+                    y as usize,
+                    (z - chunk_box.min_z) as usize,
+                );
+                if block.map(BlockId::kind) == Some(kind) {
+                    block_entities.push(BlockEntityData {
+                        base: BlockEntityBase { x, y, z },
+                        kind: block_entity(x, y, z),
+                    });
+                }
+            }
+        }
+    }
+    block_entities
+}
+
+impl WorldGenerator for ComposableGenerator {
+    fn generate_chunk(&self, position: ChunkPosition) -> Chunk {
+        self.generate_chunk_with_block_entities(position).0
+    }
+
+    fn generate_chunk_with_block_entities(
+        &self,
+        position: ChunkPosition,
+    ) -> (Chunk, Vec<BlockEntityData>) {
+        let mut chunk = if self.populators.is_empty() {
+            self.generate_terrain(position)
+        } else {
+            self.populate(position)
+        };
+        let block_entities = if self.structures.is_empty() {
+            Vec::new()
+        } else {
+            self.place_structures(&mut chunk)
+        };
+        (chunk, block_entities)
+    }
+}
+
+impl StructureContext for ComposableGenerator {
+    fn biome_at(&self, x: i32, z: i32) -> Biome {
+        let chunk = self.terrain(ChunkPosition::new(x.div_euclid(16), z.div_euclid(16)));
+        chunk.biomes().get_at_block(
+            x.rem_euclid(16) as usize,
+            SEA_LEVEL,
+            z.rem_euclid(16) as usize,
+        )
+    }
+
+    fn surface_height(&self, x: i32, z: i32) -> i32 {
+        let chunk = self.terrain(ChunkPosition::new(x.div_euclid(16), z.div_euclid(16)));
+        chunk
+            .heightmaps()
+            .world_surface
+            .height(x.rem_euclid(16) as usize, z.rem_euclid(16) as usize)
+            .unwrap_or(0) as i32
+    }
 }
 
 /// A generator which generates the biome grid for a `ComposableGenerator`.
@@ -217,7 +595,18 @@ pub trait CompositionGenerator: Send + Sync {
     );
 }
 
-/// A generator, run after composition,
+/// A generator, run after composition, which
+/// carves underground spaces such as caves.
+pub trait CarvingGenerator: Send + Sync {
+    /// Carves the given chunk.
+    ///
+    /// Carved features may start in nearby chunks, so this
+    /// function should only depend on `seed` and the chunk
+    /// position to line up with the carving of neighbouring chunks.
+    fn carve_chunk(&self, chunk: &mut Chunk, seed: u64);
+}
+
+/// A generator, run after carvers,
 /// which can add finishing elements to chunks,
 /// such as grass, trees, and snow.
 pub trait FinishingGenerator: Send + Sync {
@@ -232,6 +621,18 @@ pub trait FinishingGenerator: Send + Sync {
     );
 }
 
+/// A generator, run after finishing generators, which adds
+/// features that may extend into neighbouring chunks,
+/// such as trees.
+pub trait PopulationGenerator: Send + Sync {
+    /// Populates the center chunk of `region`. Blocks may
+    /// be written anywhere in the region.
+    ///
+    /// This function should be deterministic and only depend
+    /// on the terrain of the region.
+    fn populate(&self, region: &mut ChunkRegion, seed: u64);
+}
+
 /// Returns an index into a one-dimensional array
 /// for the given x, y, and z values.
 pub fn block_index(x: usize, y: usize, z: usize) -> usize {
@@ -390,6 +791,78 @@ mod tests {
         }
     }
 
+    /// Places a bar of gold blocks across the west and east borders
+    /// of each chunk, above the highest block below it, so the bars
+    /// of neighbouring chunks stack on top of each other.
+    struct BorderBars;
+
+    impl PopulationGenerator for BorderBars {
+        fn populate(&self, region: &mut ChunkRegion, _seed: u64) {
+            let center = region.center();
+            let xs = center.x * 16 - 2..center.x * 16 + 18;
+            let z = center.z * 16 + 8;
+            let top = xs
+                .clone()
+                .filter_map(|x| region.top_block_at(x, z))
+                .max()
+                .unwrap_or(0);
+            for x in xs {
+                region.set_block_at(x, top + 1, z, BlockId::gold_block());
+            }
+        }
+    }
+
+    #[test]
+    fn features_match_across_chunk_borders() {
+        let carvers: Vec<Box<dyn CarvingGenerator>> = Vec::new();
+        let finishers: Vec<Box<dyn FinishingGenerator>> = Vec::new();
+        let populators: Vec<Box<dyn PopulationGenerator>> = vec![Box::new(BorderBars)];
+        let gen = ComposableGenerator::new(
+            TwoLevelBiomeGenerator::default(),
+            DensityMapGeneratorImpl::default(),
+            BasicCompositionGenerator::default(),
+            carvers,
+            finishers,
+            populators,
+            42,
+        );
+        let west = gen.generate_chunk(ChunkPosition::new(0, 0));
+        let east = gen.generate_chunk(ChunkPosition::new(1, 0));
+
+        let bars = |chunk: &Chunk, x| -> Vec<usize> {
+            (0..256)
+                .filter(|&y| chunk.block_at(x, y, 8) == Some(BlockId::gold_block()))
+                .collect()
+        };
+        // Both columns next to the border are crossed by the bars
+        // of both chunks, which have to be at the same heights
+        assert_eq!(bars(&west, 15).len(), 2);
+        assert_eq!(bars(&west, 15), bars(&east, 0));
+    }
+
+    #[test]
+    fn population_is_independent_of_generation_order() {
+        let positions = [ChunkPosition::new(0, 0), ChunkPosition::new(1, 0)];
+
+        let forwards = ComposableGenerator::default_with_seed(7);
+        let first: Vec<Chunk> = positions
+            .iter()
+            .map(|&pos| forwards.generate_chunk(pos))
+            .collect();
+
+        let backwards = ComposableGenerator::default_with_seed(7);
+        let mut second: Vec<Chunk> = positions
+            .iter()
+            .rev()
+            .map(|&pos| backwards.generate_chunk(pos))
+            .collect();
+        second.reverse();
+
+        for (a, b) in first.iter().zip(&second) {
+            test_chunks_eq(a, b);
+        }
+    }
+
     fn test_chunks_eq(a: &Chunk, b: &Chunk) {
         for x in 0..16 {
             for z in 0..16 {
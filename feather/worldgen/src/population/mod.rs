@@ -0,0 +1,6 @@
+//! Population generators, which add features such as
+//! trees that may cross chunk borders.
+
+mod trees;
+
+pub use trees::TreePopulator;
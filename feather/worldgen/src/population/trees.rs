@@ -0,0 +1,375 @@
+use crate::{ChunkRegion, PopulationGenerator};
+use base::{Biome, BlockId, BlockKind, SimplifiedBlockKind};
+use rand::{Rng, SeedableRng};
+use rand_xorshift::XorShiftRng;
+
+/// Places trees according to the biome of each column.
+#[derive(Default)]
+pub struct TreePopulator;
+
+impl PopulationGenerator for TreePopulator {
+    fn populate(&self, region: &mut ChunkRegion, seed: u64) {
+        let mut rng = XorShiftRng::seed_from_u64(seed);
+        let center = region.center();
+        let (base_x, base_z) = (center.x * 16, center.z * 16);
+
+        let trees = match region
+            .biome_at(base_x + 8, 64, base_z + 8)
+            .and_then(biome_trees)
+        {
+            Some(trees) => trees,
+            None => return,
+        };
+
+        for _ in 0..trees.count(&mut rng) {
+            let x = base_x + rng.gen_range(0, 16);
+            let z = base_z + rng.gen_range(0, 16);
+            // Each tree uses the kinds of the biome it grows in,
+            // which gives smooth transitions between biomes.
+            let kind = match region.biome_at(x, 64, z).and_then(biome_trees) {
+                Some(trees) => trees.choose_kind(&mut rng),
+                None => continue,
+            };
+            let shape = TreeShape::generate(kind, &mut rng);
+            if let Some(y) = find_ground(region, x, z) {
+                shape.place(region, x, y, z);
+            }
+        }
+    }
+}
+
+#[derive(Copy, Clone, Debug, PartialEq, Eq)]
+enum TreeKind {
+    Oak,
+    Birch,
+    Spruce,
+    Jungle,
+    Acacia,
+}
+
+impl TreeKind {
+    fn log(self) -> BlockId {
+        match self {
+            TreeKind::Oak => BlockId::oak_log(),
+            TreeKind::Birch => BlockId::birch_log(),
+            TreeKind::Spruce => BlockId::spruce_log(),
+            TreeKind::Jungle => BlockId::jungle_log(),
+            TreeKind::Acacia => BlockId::acacia_log(),
+        }
+    }
+
+    fn leaves(self) -> BlockId {
+        match self {
+            TreeKind::Oak => BlockId::oak_leaves(),
+            TreeKind::Birch => BlockId::birch_leaves(),
+            TreeKind::Spruce => BlockId::spruce_leaves(),
+            TreeKind::Jungle => BlockId::jungle_leaves(),
+            TreeKind::Acacia => BlockId::acacia_leaves(),
+        }
+    }
+}
+
+/// The trees growing in a biome.
+struct BiomeTrees {
+    /// Average number of trees per chunk.
+    per_chunk: f32,
+    /// Kinds of trees along with their weights.
+    kinds: &'static [(TreeKind, u32)],
+}
+
+impl BiomeTrees {
+    const fn new(per_chunk: f32, kinds: &'static [(TreeKind, u32)]) -> Self {
+        Self { per_chunk, kinds }
+    }
+
+    /// Returns the number of trees to place in a chunk. The
+    /// fractional part of `per_chunk` is used as a probability.
+    fn count(&self, rng: &mut impl Rng) -> u32 {
+        let extra = rng.gen::<f32>() < self.per_chunk.fract();
+        self.per_chunk as u32 + extra as u32
+    }
+
+    fn choose_kind(&self, rng: &mut impl Rng) -> TreeKind {
+        let total: u32 = self.kinds.iter().map(|&(_, weight)| weight).sum();
+        let mut choice = rng.gen_range(0, total);
+        for &(kind, weight) in self.kinds {
+            if choice < weight {
+                return kind;
+            }
+            choice -= weight;
+        }
+        unreachable!("choice is less than the total weight")
+    }
+}
+
+fn biome_trees(biome: Biome) -> Option<BiomeTrees> {
+    use TreeKind::*;
+    let trees = match biome {
+        Biome::Forest | Biome::WoodedHills | Biome::FlowerForest => {
+            BiomeTrees::new(10.0, &[(Oak, 4), (Birch, 1)])
+        }
+        Biome::BirchForest
+        | Biome::BirchForestHills
+        | Biome::TallBirchForest
+        | Biome::TallBirchHills => BiomeTrees::new(10.0, &[(Birch, 1)]),
+        Biome::DarkForest | Biome::DarkForestHills => BiomeTrees::new(16.0, &[(Oak, 1)]),
+        Biome::Taiga
+        | Biome::TaigaHills
+        | Biome::TaigaMountains
+        | Biome::SnowyTaiga
+        | Biome::SnowyTaigaHills
+        | Biome::SnowyTaigaMountains
+        | Biome::GiantTreeTaiga
+        | Biome::GiantTreeTaigaHills
+        | Biome::GiantSpruceTaiga
+        | Biome::GiantSpruceTaigaHills => BiomeTrees::new(10.0, &[(Spruce, 1)]),
+        Biome::Jungle
+        | Biome::JungleHills
+        | Biome::ModifiedJungle
+        | Biome::BambooJungle
+        | Biome::BambooJungleHills => BiomeTrees::new(20.0, &[(Jungle, 3), (Oak, 1)]),
+        Biome::JungleEdge | Biome::ModifiedJungleEdge => {
+            BiomeTrees::new(2.0, &[(Jungle, 3), (Oak, 1)])
+        }
+        Biome::Savanna
+        | Biome::SavannaPlateau
+        | Biome::ShatteredSavanna
+        | Biome::ShatteredSavannaPlateau => BiomeTrees::new(1.0, &[(Acacia, 4), (Oak, 1)]),
+        Biome::WoodedMountains | Biome::ModifiedGravellyMountains => {
+            BiomeTrees::new(3.0, &[(Spruce, 2), (Oak, 1)])
+        }
+        Biome::Mountains | Biome::GravellyMountains => {
+            BiomeTrees::new(0.2, &[(Spruce, 2), (Oak, 1)])
+        }
+        Biome::Swamp | Biome::SwampHills => BiomeTrees::new(2.0, &[(Oak, 1)]),
+        Biome::Plains | Biome::SunflowerPlains => BiomeTrees::new(0.05, &[(Oak, 1)]),
+        Biome::SnowyTundra => BiomeTrees::new(0.1, &[(Spruce, 1)]),
+        _ => return None,
+    };
+    Some(trees)
+}
+
+/// Returns the height at which a tree growing
+/// in the given column should start, if any.
+fn find_ground(region: &ChunkRegion, x: i32, z: i32) -> Option<i32> {
+    let mut y = region.top_block_at(x, z)?;
+    while can_replace(region.block_at(x, y, z)?) {
+        y -= 1;
+    }
+    let ground = region.block_at(x, y, z)?;
+    match ground.kind() {
+        BlockKind::GrassBlock | BlockKind::Dirt | BlockKind::CoarseDirt | BlockKind::Podzol => {
+            Some(y + 1)
+        }
+        _ => None,
+    }
+}
+
+/// Returns whether a tree may grow through the given block.
+fn can_replace(block: BlockId) -> bool {
+    (block.is_replaceable() && !block.is_fluid())
+        || matches!(
+            block.simplified_kind(),
+            SimplifiedBlockKind::Leaves | SimplifiedBlockKind::Flower
+        )
+}
+
+/// The blocks of a single tree, relative to the base of its trunk.
+struct TreeShape {
+    kind: TreeKind,
+    logs: Vec<(i32, i32, i32)>,
+    leaves: Vec<(i32, i32, i32)>,
+}
+
+impl TreeShape {
+    fn generate(kind: TreeKind, rng: &mut impl Rng) -> Self {
+        let mut shape = Self {
+            kind,
+            logs: Vec::new(),
+            leaves: Vec::new(),
+        };
+        match kind {
+            TreeKind::Oak => shape.blob(rng.gen_range(4, 7), rng),
+            TreeKind::Birch => shape.blob(rng.gen_range(5, 8), rng),
+            TreeKind::Spruce => shape.cone(rng.gen_range(6, 10), rng),
+            TreeKind::Jungle => shape.tall(rng.gen_range(8, 13)),
+            TreeKind::Acacia => shape.bent(rng.gen_range(5, 8), rng),
+        }
+        let logs = &shape.logs;
+        shape.leaves.retain(|leaf| !logs.contains(leaf));
+        shape
+    }
+
+    /// A straight trunk topped with a rounded canopy.
+    fn blob(&mut self, height: i32, rng: &mut impl Rng) {
+        self.trunk(height);
+        for y in height - 3..=height {
+            let radius = if y >= height - 1 { 1 } else { 2 };
+            self.layer(y, radius, |dx, dz| {
+                // Randomly trim the corners so that trees look less regular.
+                dx.abs() != radius || dz.abs() != radius || (y < height && rng.gen_bool(0.5))
+            });
+        }
+    }
+
+    /// A straight trunk with a conical canopy of
+    /// alternately wide and narrow layers.
+    fn cone(&mut self, height: i32, rng: &mut impl Rng) {
+        self.trunk(height);
+        let bottom = rng.gen_range(1, 3);
+        for y in bottom..=height {
+            let from_top = height - y;
+            let radius = if from_top == 0 {
+                0
+            } else {
+                (1 + from_top % 2 + from_top / 4).min(3)
+            };
+            self.layer(y, radius, |dx, dz| dx * dx + dz * dz <= radius * radius + 1);
+        }
+    }
+
+    /// A tall trunk with a wide canopy at its top.
+    fn tall(&mut self, height: i32) {
+        self.trunk(height);
+        for y in height - 3..=height + 1 {
+            let radius = match height + 1 - y {
+                0 => 1,
+                1 => 2,
+                _ => 3,
+            };
+            self.layer(y, radius, |dx, dz| dx * dx + dz * dz <= radius * radius + 1);
+        }
+    }
+
+    /// A trunk which leans to one side partway up,
+    /// topped with a flat canopy.
+    fn bent(&mut self, height: i32, rng: &mut impl Rng) {
+        let (step_x, step_z) = [(1, 0), (-1, 0), (0, 1), (0, -1)][rng.gen_range(0, 4)];
+        let bend = rng.gen_range(2, height - 1);
+        let (mut x, mut z) = (0, 0);
+        for y in 0..height {
+            if y >= bend {
+                x += step_x;
+                z += step_z;
+            }
+            self.logs.push((x, y, z));
+        }
+
+        let top = height - 1;
+        for &(y, radius, max_distance) in &[(top, 3, 4), (top + 1, 1, 1)] {
+            for dx in -radius..=radius {
+                for dz in -radius..=radius {
+                    if dx.abs() + dz.abs() <= max_distance {
+                        self.leaves.push((x + dx, y, z + dz));
+                    }
+                }
+            }
+        }
+    }
+
+    fn trunk(&mut self, height: i32) {
+        self.logs.extend((0..height).map(|y| (0, y, 0)));
+    }
+
+    /// Adds a square layer of leaves around the trunk, keeping
+    /// only those for which `include` returns `true`.
+    fn layer(&mut self, y: i32, radius: i32, mut include: impl FnMut(i32, i32) -> bool) {
+        for dx in -radius..=radius {
+            for dz in -radius..=radius {
+                if include(dx, dz) {
+                    self.leaves.push((dx, y, dz));
+                }
+            }
+        }
+    }
+
+    /// Returns the distance from the given leaf to the nearest log,
+    /// as stored in the `distance` property of leaves.
+    fn leaf_distance(&self, (x, y, z): (i32, i32, i32)) -> i32 {
+        self.logs
+            .iter()
+            .map(|&(lx, ly, lz)| (x - lx).abs() + (y - ly).abs() + (z - lz).abs())
+            .min()
+            .unwrap_or(7)
+            .max(1)
+            .min(7)
+    }
+
+    /// Places the tree with its trunk starting at the given position.
+    ///
+    /// Returns `false` and places nothing if the trunk is obstructed
+    /// or the tree would extend above the world.
+    fn place(&self, region: &mut ChunkRegion, x: i32, y: i32, z: i32) -> bool {
+        let trunk_clear = self.logs.iter().all(|&(dx, dy, dz)| {
+            region
+                .block_at(x + dx, y + dy, z + dz)
+                .map_or(false, can_replace)
+        });
+        let top = self
+            .leaves
+            .iter()
+            .map(|&(_, dy, _)| y + dy)
+            .max()
+            .unwrap_or(y);
+        if !trunk_clear || region.block_at(x, top, z).is_none() {
+            return false;
+        }
+
+        region.set_block_at(x, y - 1, z, BlockId::dirt());
+        for &(dx, dy, dz) in &self.logs {
+            region.set_block_at(x + dx, y + dy, z + dz, self.kind.log());
+        }
+        for &(dx, dy, dz) in &self.leaves {
+            let (leaf_x, leaf_y, leaf_z) = (x + dx, y + dy, z + dz);
+            if region
+                .block_at(leaf_x, leaf_y, leaf_z)
+                .map_or(false, can_replace)
+            {
+                let leaves = self
+                    .kind
+                    .leaves()
+                    .with_distance_1_7(self.leaf_distance((dx, dy, dz)))
+                    .with_persistent(false);
+                region.set_block_at(leaf_x, leaf_y, leaf_z, leaves);
+            }
+        }
+        true
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn leaves_are_connected_to_logs() {
+        let mut rng = XorShiftRng::seed_from_u64(10);
+        for &kind in &[
+            TreeKind::Oak,
+            TreeKind::Birch,
+            TreeKind::Spruce,
+            TreeKind::Jungle,
+            TreeKind::Acacia,
+        ] {
+            for _ in 0..20 {
+                let shape = TreeShape::generate(kind, &mut rng);
+                assert!(!shape.logs.is_empty());
+                for &leaf in &shape.leaves {
+                    assert!(!shape.logs.contains(&leaf));
+                    assert!(shape.leaf_distance(leaf) <= 6, "{:?} {:?}", kind, leaf);
+                }
+            }
+        }
+    }
+
+    #[test]
+    fn choose_kind_respects_weights() {
+        let mut rng = XorShiftRng::seed_from_u64(0);
+        let trees = biome_trees(Biome::Forest).unwrap();
+        let oaks = (0..1000)
+            .filter(|_| trees.choose_kind(&mut rng) == TreeKind::Oak)
+            .count();
+        assert!((700..900).contains(&oaks), "{}", oaks);
+        assert!(biome_trees(Biome::Desert).is_none());
+    }
+}
@@ -0,0 +1,192 @@
+use std::collections::HashMap;
+
+use base::{Biome, BlockId, Chunk, ChunkPosition, CHUNK_HEIGHT};
+
+/// A 3x3 area of generated chunks, centered on
+/// the chunk being populated.
+///
+/// Population generators read the terrain of the whole
+/// area and may write blocks anywhere in it, which allows
+/// features such as trees to cross chunk borders. The blocks
+/// written in one region can be carried over into the next
+/// with [`into_changes`](Self::into_changes) and
+/// [`with_changes`](Self::with_changes).
+///
+/// All coordinates are absolute block coordinates.
+pub struct ChunkRegion<'a> {
+    center: ChunkPosition,
+    /// Chunks in the region. The chunk for a given chunk
+    /// position relative to the center chunk can be obtained
+    /// using (x + 1) + (z + 1) * 3.
+    chunks: [&'a Chunk; 9],
+    /// Blocks written by population generators.
+    changes: HashMap<(i32, usize, i32), BlockId>,
+}
+
+impl<'a> ChunkRegion<'a> {
+    /// Creates a region from the 3x3 chunks centered on `center`,
+    /// ordered as in [`NearbyBiomes`](crate::NearbyBiomes).
+    pub fn new(center: ChunkPosition, chunks: [&'a Chunk; 9]) -> Self {
+        Self::with_changes(center, chunks, HashMap::new())
+    }
+
+    /// Creates a region like [`new`](Self::new) on top of
+    /// blocks written in previous regions.
+    pub fn with_changes(
+        center: ChunkPosition,
+        chunks: [&'a Chunk; 9],
+        changes: HashMap<(i32, usize, i32), BlockId>,
+    ) -> Self {
+        Self {
+            center,
+            chunks,
+            changes,
+        }
+    }
+
+    /// Returns the blocks written in this region
+    /// and the regions it was created on top of.
+    pub fn into_changes(self) -> HashMap<(i32, usize, i32), BlockId> {
+        self.changes
+    }
+
+    /// Returns the position of the center chunk.
+    pub fn center(&self) -> ChunkPosition {
+        self.center
+    }
+
+    /// Gets the block at the given position, including
+    /// blocks written during population.
+    ///
+    /// Returns `None` if the position is outside the region.
+    pub fn block_at(&self, x: i32, y: i32, z: i32) -> Option<BlockId> {
+        let y = valid_y(y)?;
+        let (chunk, local_x, local_z) = self.locate(x, z)?;
+        if let Some(&block) = self.changes.get(&(x, y, z)) {
+            return Some(block);
+        }
+        chunk.block_at(local_x, y, local_z)
+    }
+
+    /// Sets the block at the given position.
+    ///
+    /// Returns `false` if the position is outside the region.
+    pub fn set_block_at(&mut self, x: i32, y: i32, z: i32, block: BlockId) -> bool {
+        match (valid_y(y), self.locate(x, z)) {
+            (Some(y), Some(_)) => {
+                self.changes.insert((x, y, z), block);
+                true
+            }
+            _ => false,
+        }
+    }
+
+    /// Gets the biome at the given position.
+    pub fn biome_at(&self, x: i32, y: i32, z: i32) -> Option<Biome> {
+        let y = valid_y(y)?;
+        let (chunk, local_x, local_z) = self.locate(x, z)?;
+        Some(chunk.biomes().get_at_block(local_x, y, local_z))
+    }
+
+    /// Returns the height of the highest non-air
+    /// block in the given column.
+    pub fn top_block_at(&self, x: i32, z: i32) -> Option<i32> {
+        self.locate(x, z)?;
+        (0..CHUNK_HEIGHT as i32).rev().find(|&y| {
+            self.block_at(x, y, z)
+                .map_or(false, |block| !block.is_air())
+        })
+    }
+
+    /// Writes the blocks written during population
+    /// which lie within `chunk` into it.
+    pub fn apply_changes(&self, chunk: &mut Chunk) {
+        apply_region_changes(&self.changes, chunk);
+    }
+
+    /// Returns the chunk containing the given column,
+    /// along with the chunk-local coordinates of the column.
+    fn locate(&self, x: i32, z: i32) -> Option<(&'a Chunk, usize, usize)> {
+        let chunk_x = x.div_euclid(16) - self.center.x;
+        let chunk_z = z.div_euclid(16) - self.center.z;
+        if !(-1..=1).contains(&chunk_x) || !(-1..=1).contains(&chunk_z) {
+            return None;
+        }
+        let chunk = self.chunks[((chunk_x + 1) + (chunk_z + 1) * 3) as usize];
+        Some((chunk, x.rem_euclid(16) as usize, z.rem_euclid(16) as usize))
+    }
+}
+
+/// Writes the blocks written in regions which
+/// lie within `chunk` into it.
+pub fn apply_region_changes(changes: &HashMap<(i32, usize, i32), BlockId>, chunk: &mut Chunk) {
+    let pos = chunk.position();
+    for (&(x, y, z), &block) in changes {
+        if x.div_euclid(16) == pos.x && z.div_euclid(16) == pos.z {
+            chunk.set_block_at(
+                x.rem_euclid(16) as usize,
+                y,
+                z.rem_euclid(16) as usize,
+                block,
+            );
+        }
+    }
+}
+
+fn valid_y(y: i32) -> Option<usize> {
+    if (0..CHUNK_HEIGHT as i32).contains(&y) {
+        Some(y as usize)
+    } else {
+        None
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn writes_cross_chunk_borders() {
+        let chunks: Vec<Chunk> = (-1..=1)
+            .flat_map(|z| (-1..=1).map(move |x| Chunk::new(ChunkPosition::new(x, z))))
+            .collect();
+        let refs: Vec<&Chunk> = chunks.iter().collect();
+        let mut region = ChunkRegion::new(
+            ChunkPosition::new(0, 0),
+            [
+                refs[0], refs[1], refs[2], refs[3], refs[4], refs[5], refs[6], refs[7], refs[8],
+            ],
+        );
+
+        assert!(region.set_block_at(-1, 70, 5, BlockId::stone()));
+        assert!(region.set_block_at(20, 70, 31, BlockId::stone()));
+        assert!(!region.set_block_at(32, 70, 0, BlockId::stone()));
+        assert_eq!(region.block_at(-1, 70, 5), Some(BlockId::stone()));
+        assert_eq!(region.top_block_at(-1, 5), Some(70));
+        assert_eq!(region.block_at(-17, 70, 5), None);
+
+        let mut west = Chunk::new(ChunkPosition::new(-1, 0));
+        region.apply_changes(&mut west);
+        assert_eq!(west.block_at(15, 70, 5), Some(BlockId::stone()));
+
+        let mut center = Chunk::new(ChunkPosition::new(0, 0));
+        region.apply_changes(&mut center);
+        assert!(center.sections().iter().all(|section| section.is_none()));
+    }
+
+    #[test]
+    fn later_regions_see_earlier_changes() {
+        let chunks: Vec<Chunk> = (-1..=2)
+            .flat_map(|z| (-1..=1).map(move |x| Chunk::new(ChunkPosition::new(x, z))))
+            .collect();
+        let area = |offset: usize| [0, 1, 2, 3, 4, 5, 6, 7, 8].map(|i| &chunks[i + offset * 3]);
+
+        let mut first = ChunkRegion::new(ChunkPosition::new(0, 0), area(0));
+        assert!(first.set_block_at(3, 70, 20, BlockId::stone()));
+
+        let second =
+            ChunkRegion::with_changes(ChunkPosition::new(0, 1), area(1), first.into_changes());
+        assert_eq!(second.block_at(3, 70, 20), Some(BlockId::stone()));
+        assert_eq!(second.top_block_at(3, 20), Some(70));
+    }
+}
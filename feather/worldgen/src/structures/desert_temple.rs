@@ -0,0 +1,241 @@
+use base::{
+    chunk::{StructureBox, StructurePiece},
+    Biome, BlockId, Chunk, ChunkPosition,
+};
+use rand_xorshift::XorShiftRng;
+
+use super::{
+    piece::{oriented_box, saved_piece, Orientation, PieceWriter},
+    Piece, Structure, StructureContext, StructurePlacement,
+};
+
+/// Width and depth of a desert temple.
+const SIZE: i32 = 21;
+/// Height of a desert temple, including its hidden chamber.
+const HEIGHT: i32 = 25;
+/// Local height of the temple floor. The hidden
+/// chamber lies below it.
+const FLOOR: i32 = 14;
+
+/// A sandstone pyramid with a trapped treasure chamber
+/// beneath it, generated in deserts.
+pub struct DesertTemple {
+    placement: StructurePlacement,
+}
+
+impl Default for DesertTemple {
+    fn default() -> Self {
+        Self {
+            placement: StructurePlacement::Grid {
+                spacing: 32,
+                separation: 8,
+                salt: 14_357_617,
+                frequency: 1.0,
+            },
+        }
+    }
+}
+
+impl Structure for DesertTemple {
+    fn name(&self) -> &'static str {
+        "desert_pyramid"
+    }
+
+    fn placement(&self) -> &StructurePlacement {
+        &self.placement
+    }
+
+    fn max_radius(&self) -> i32 {
+        2
+    }
+
+    fn assemble(
+        &self,
+        start: ChunkPosition,
+        context: &dyn StructureContext,
+        rng: &mut XorShiftRng,
+    ) -> Vec<Box<dyn Piece>> {
+        let (x, z) = (start.x * 16, start.z * 16);
+        match context.biome_at(x + 8, z + 8) {
+            Biome::Desert | Biome::DesertHills => {}
+            _ => return Vec::new(),
+        }
+
+        let orientation = Orientation::random(rng);
+        let mut bounding_box = oriented_box(x, 0, z, (0, 0, 0), SIZE, HEIGHT, SIZE, orientation);
+
+        // Rest the floor on the average height of the terrain.
+        let corners = [
+            (bounding_box.min_x, bounding_box.min_z),
+            (bounding_box.min_x, bounding_box.max_z),
+            (bounding_box.max_x, bounding_box.min_z),
+            (bounding_box.max_x, bounding_box.max_z),
+            (x + SIZE / 2, z + SIZE / 2),
+        ];
+        let ground = corners
+            .iter()
+            .map(|&(x, z)| context.surface_height(x, z))
+            .sum::<i32>()
+            / corners.len() as i32;
+        bounding_box.offset(0, (ground - 1 - FLOOR).max(1), 0);
+
+        vec![Box::new(DesertTemplePiece {
+            bounding_box,
+            orientation,
+        })]
+    }
+}
+
+struct DesertTemplePiece {
+    bounding_box: StructureBox,
+    orientation: Orientation,
+}
+
+impl Piece for DesertTemplePiece {
+    fn bounding_box(&self) -> StructureBox {
+        self.bounding_box
+    }
+
+    fn place(&self, chunk: &mut Chunk, _context: &dyn StructureContext, _rng: &mut XorShiftRng) {
+        let mut writer = PieceWriter::new(chunk, self.bounding_box, self.orientation);
+        let sandstone = BlockId::sandstone();
+        let cut_sandstone = BlockId::cut_sandstone();
+        let air = BlockId::air();
+        let f = FLOOR;
+
+        // Foundation, extended down to the ground.
+        writer.fill((0, f - 4, 0), (SIZE - 1, f, SIZE - 1), sandstone);
+        for x in 0..SIZE {
+            for z in 0..SIZE {
+                writer.fill_down(x, f - 5, z, sandstone);
+            }
+        }
+
+        // Stepped, hollow body of the pyramid.
+        for i in 1..=9 {
+            writer.fill(
+                (i, f + i, i),
+                (SIZE - 1 - i, f + i, SIZE - 1 - i),
+                sandstone,
+            );
+            writer.fill(
+                (i + 1, f + i, i + 1),
+                (SIZE - 2 - i, f + i, SIZE - 2 - i),
+                air,
+            );
+        }
+
+        // Towers at the front corners.
+        for &tower_x in &[0, SIZE - 5] {
+            writer.fill_hollow((tower_x, f, 0), (tower_x + 4, f + 9, 4), sandstone, air);
+            writer.fill(
+                (tower_x, f + 10, 0),
+                (tower_x + 4, f + 10, 4),
+                cut_sandstone,
+            );
+            writer.fill((tower_x + 1, f + 1, 4), (tower_x + 3, f + 3, 4), air);
+        }
+
+        // Entrance.
+        writer.fill_hollow((8, f, 0), (12, f + 4, 4), sandstone, air);
+        writer.fill((9, f + 1, 0), (11, f + 3, 0), air);
+        writer.fill((9, f + 1, 4), (11, f + 3, 4), air);
+        writer.set(10, f + 4, 0, BlockId::chiseled_sandstone());
+
+        // Terracotta pattern above the hidden chamber.
+        let orange = BlockId::orange_terracotta();
+        for d in 1..=2 {
+            writer.set(10 - d, f, 10, orange);
+            writer.set(10 + d, f, 10, orange);
+            writer.set(10, f, 10 - d, orange);
+            writer.set(10, f, 10 + d, orange);
+        }
+        writer.set(9, f, 9, orange);
+        writer.set(11, f, 9, orange);
+        writer.set(9, f, 11, orange);
+        writer.set(11, f, 11, orange);
+        writer.set(10, f, 10, BlockId::blue_terracotta());
+
+        // The hidden chamber: a shaft leading down to four
+        // chests around a pressure plate wired to TNT.
+        writer.fill((9, f - 9, 9), (11, f - 1, 11), air);
+        writer.fill_hollow((7, f - 13, 7), (13, f - 9, 13), cut_sandstone, air);
+        writer.fill((9, f - 14, 9), (11, f - 14, 11), BlockId::tnt());
+        writer.fill((8, f - 13, 8), (12, f - 13, 12), sandstone);
+        writer.set(10, f - 12, 10, BlockId::stone_pressure_plate());
+        for &(x, z, facing_x, facing_z) in &[
+            (10, 8, 0, 1),
+            (10, 12, 0, -1),
+            (8, 10, 1, 0),
+            (12, 10, -1, 0),
+        ] {
+            let chest = BlockId::chest().with_facing_cardinal(writer.facing(facing_x, facing_z));
+            writer.set(x, f - 12, z, chest);
+        }
+    }
+
+    fn chest_loot_table(&self) -> Option<&'static str> {
+        Some("minecraft:chests/desert_pyramid")
+    }
+
+    fn to_saved(&self) -> StructurePiece {
+        saved_piece("TeDP", self.bounding_box, self.orientation, 0)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use base::{anvil::block_entity::BlockEntityKind, Biome};
+    use rand::SeedableRng;
+
+    struct Flat;
+
+    impl StructureContext for Flat {
+        fn biome_at(&self, _x: i32, _z: i32) -> Biome {
+            Biome::Desert
+        }
+
+        fn surface_height(&self, _x: i32, _z: i32) -> i32 {
+            64
+        }
+    }
+
+    #[test]
+    fn hidden_chamber_chests_have_loot() {
+        let pieces = DesertTemple::default().assemble(
+            ChunkPosition::new(0, 0),
+            &Flat,
+            &mut XorShiftRng::seed_from_u64(0),
+        );
+        let piece = &pieces[0];
+        let loot_table = piece.chest_loot_table().unwrap();
+
+        let mut block_entities = Vec::new();
+        for x in 0..=1 {
+            for z in 0..=1 {
+                let mut chunk = Chunk::new(ChunkPosition::new(x, z));
+                piece.place(&mut chunk, &Flat, &mut XorShiftRng::seed_from_u64(0));
+                block_entities.extend(crate::chest_block_entities(
+                    &chunk,
+                    &piece.bounding_box(),
+                    loot_table,
+                    0,
+                ));
+            }
+        }
+
+        assert_eq!(block_entities.len(), 4);
+        for block_entity in &block_entities {
+            match &block_entity.kind {
+                BlockEntityKind::Chest { loot_table, .. } => {
+                    assert_eq!(
+                        loot_table.as_deref(),
+                        Some("minecraft:chests/desert_pyramid")
+                    );
+                }
+                kind => panic!("expected a chest, got {:?}", kind),
+            }
+        }
+    }
+}
@@ -0,0 +1,630 @@
+//! Jigsaw structures, such as villages, which are assembled from
+//! templates connected by jigsaw blocks.
+//!
+//! Starting with a template from a start pool, each jigsaw
+//! of a placed template picks a template from its pool with
+//! a matching jigsaw, until the maximum depth is reached.
+
+use std::{
+    collections::{BTreeMap, HashMap, VecDeque},
+    sync::Arc,
+};
+
+use base::{
+    chunk::{StructureBox, StructurePiece},
+    BlockId, Chunk,
+};
+use rand::{seq::SliceRandom, Rng};
+use rand_xorshift::XorShiftRng;
+
+use super::{
+    template::{Jigsaw, Rotation, Template},
+    Piece, StructureContext,
+};
+
+/// Pieces are generated at most this many blocks
+/// away from the start, horizontally.
+pub const MAX_DISTANCE: i32 = 80;
+/// The bottom of rigid pieces is extended at
+/// most this many blocks down to the ground.
+const MAX_FOUNDATION: usize = 16;
+
+/// How a pool element is placed vertically.
+#[derive(Copy, Clone, Debug, PartialEq, Eq)]
+pub enum Projection {
+    /// The template is placed as is.
+    Rigid,
+    /// Each column of the template follows the terrain surface.
+    TerrainMatching,
+}
+
+/// A template placed by a pool element.
+#[derive(Clone, Debug)]
+pub struct TemplatePart {
+    /// The namespaced ID of the template.
+    pub location: String,
+    /// Whether the air blocks of the template are skipped,
+    /// keeping the existing blocks.
+    pub legacy: bool,
+}
+
+/// An element of a template pool.
+#[derive(Clone, Debug)]
+pub enum PoolElement {
+    /// One or more templates placed at the same position.
+    /// Only the jigsaws of the first template are used.
+    Templates {
+        parts: Vec<TemplatePart>,
+        projection: Projection,
+    },
+    /// Ends the search for a piece: nothing is attached.
+    Empty,
+}
+
+/// A pool of weighted elements attached to jigsaws.
+#[derive(Clone, Debug)]
+pub struct TemplatePool {
+    /// The pool used when no element of this pool fits.
+    pub fallback: String,
+    pub elements: Vec<(PoolElement, u32)>,
+}
+
+/// The templates and template pools jigsaw structures are built from.
+#[derive(Default)]
+pub struct JigsawPools {
+    templates: HashMap<String, Arc<Template>>,
+    pools: HashMap<String, TemplatePool>,
+}
+
+impl JigsawPools {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    pub fn add_template(&mut self, location: impl Into<String>, template: Template) {
+        self.templates.insert(location.into(), Arc::new(template));
+    }
+
+    pub fn add_pool(&mut self, id: impl Into<String>, pool: TemplatePool) {
+        self.pools.insert(id.into(), pool);
+    }
+
+    pub fn has_pool(&self, id: &str) -> bool {
+        self.pools.contains_key(id)
+    }
+
+    /// Returns the elements of a pool in a random order, where
+    /// elements with a higher weight are more likely to come first.
+    fn shuffled_elements(&self, pool: &str, rng: &mut impl Rng) -> Vec<&PoolElement> {
+        let mut remaining: Vec<_> = match self.pools.get(pool) {
+            Some(pool) => pool
+                .elements
+                .iter()
+                .filter(|(_, weight)| *weight > 0)
+                .collect(),
+            None => return Vec::new(),
+        };
+        let mut shuffled = Vec::with_capacity(remaining.len());
+        while !remaining.is_empty() {
+            let total: u32 = remaining.iter().map(|(_, weight)| weight).sum();
+            let mut roll = rng.gen_range(0, total);
+            let index = remaining
+                .iter()
+                .position(|(_, weight)| {
+                    if roll < *weight {
+                        true
+                    } else {
+                        roll -= weight;
+                        false
+                    }
+                })
+                .unwrap_or(0);
+            shuffled.push(&remaining.remove(index).0);
+        }
+        shuffled
+    }
+
+    /// Looks up the templates of an element. Returns `None`
+    /// for empty elements and elements with unknown templates.
+    fn resolve(&self, element: &PoolElement) -> Option<Element> {
+        match element {
+            PoolElement::Templates { parts, projection } => {
+                let parts = parts
+                    .iter()
+                    .map(|part| {
+                        Some((
+                            part.clone(),
+                            Arc::clone(self.templates.get(&part.location)?),
+                        ))
+                    })
+                    .collect::<Option<Vec<_>>>()?;
+                if parts.is_empty() {
+                    return None;
+                }
+                Some(Element {
+                    parts,
+                    projection: *projection,
+                })
+            }
+            PoolElement::Empty => None,
+        }
+    }
+}
+
+/// A pool element with its templates looked up.
+#[derive(Clone)]
+struct Element {
+    parts: Vec<(TemplatePart, Arc<Template>)>,
+    projection: Projection,
+}
+
+impl Element {
+    fn bounding_box(&self, origin: (i32, i32, i32), rotation: Rotation) -> StructureBox {
+        let mut bounding_box = self.parts[0].1.bounding_box(origin, rotation);
+        for (_, template) in &self.parts[1..] {
+            bounding_box.expand(&template.bounding_box(origin, rotation));
+        }
+        bounding_box
+    }
+
+    fn jigsaws(&self, rotation: Rotation) -> impl Iterator<Item = Jigsaw> + '_ {
+        self.parts[0].1.jigsaws(rotation)
+    }
+}
+
+/// Lays out a jigsaw structure, starting with an element of
+/// `start_pool` centered on the terrain near `(x, z)`.
+pub fn assemble(
+    pools: &JigsawPools,
+    start_pool: &str,
+    max_depth: i32,
+    (x, z): (i32, i32),
+    context: &dyn StructureContext,
+    rng: &mut XorShiftRng,
+) -> Vec<Box<dyn Piece>> {
+    let element = match pools
+        .shuffled_elements(start_pool, rng)
+        .into_iter()
+        .find_map(|element| pools.resolve(element))
+    {
+        Some(element) => element,
+        None => return Vec::new(),
+    };
+    let rotation = Rotation::random(rng);
+    let mut start = JigsawPiece::new(element, (x, 0, z), rotation, 0, true);
+
+    // Rest the start on the terrain at its center.
+    let bounding_box = start.bounding_box;
+    let center_x = (bounding_box.min_x + bounding_box.max_x) / 2;
+    let center_z = (bounding_box.min_z + bounding_box.max_z) / 2;
+    start.move_to_y(context.surface_height(center_x, center_z) - 1);
+
+    let bounds = StructureBox {
+        min_x: center_x - MAX_DISTANCE,
+        min_y: 1,
+        min_z: center_z - MAX_DISTANCE,
+        max_x: center_x + MAX_DISTANCE,
+        max_y: 255,
+        max_z: center_z + MAX_DISTANCE,
+    };
+
+    let mut pieces = vec![start];
+    let mut pending = VecDeque::from(vec![0]);
+    while let Some(index) = pending.pop_front() {
+        if pieces[index].depth >= max_depth {
+            continue;
+        }
+        let mut jigsaws = pieces[index].jigsaws(context);
+        jigsaws.shuffle(rng);
+        for jigsaw in jigsaws {
+            if let Some(piece) = attach(pools, &pieces, index, &jigsaw, &bounds, context, rng) {
+                pieces.push(piece);
+                pending.push_back(pieces.len() - 1);
+            }
+        }
+    }
+
+    pieces
+        .into_iter()
+        .map(|piece| Box::new(piece) as Box<dyn Piece>)
+        .collect()
+}
+
+/// Finds a piece from the pool of `jigsaw`, on the piece with
+/// index `parent`, which attaches to it and fits among `pieces`.
+fn attach(
+    pools: &JigsawPools,
+    pieces: &[JigsawPiece],
+    parent: usize,
+    jigsaw: &Jigsaw,
+    bounds: &StructureBox,
+    context: &dyn StructureContext,
+    rng: &mut XorShiftRng,
+) -> Option<JigsawPiece> {
+    let parent_box = pieces[parent].bounding_box;
+    let (offset_x, offset_y, offset_z) = jigsaw.front.offset();
+    let target = (
+        jigsaw.pos.0 + offset_x,
+        jigsaw.pos.1 + offset_y,
+        jigsaw.pos.2 + offset_z,
+    );
+    // Pieces attached inside their parent, such as decorations,
+    // must stay inside it; others may not intersect any piece.
+    let inside_parent = parent_box.contains(target.0, target.1, target.2);
+    let fits = |bounding_box: &StructureBox| {
+        if !contains_box(bounds, bounding_box) {
+            return false;
+        }
+        if inside_parent {
+            contains_box(&parent_box, bounding_box)
+                && pieces
+                    .iter()
+                    .enumerate()
+                    .all(|(i, piece)| i == parent || !piece.bounding_box.intersects(bounding_box))
+        } else {
+            !pieces
+                .iter()
+                .any(|piece| piece.bounding_box.intersects(bounding_box))
+        }
+    };
+
+    let mut candidates = pools.shuffled_elements(&jigsaw.pool, rng);
+    if let Some(pool) = pools.pools.get(&jigsaw.pool) {
+        candidates.extend(pools.shuffled_elements(&pool.fallback, rng));
+    }
+    for element in candidates {
+        if let PoolElement::Empty = element {
+            break;
+        }
+        let element = match pools.resolve(element) {
+            Some(element) => element,
+            None => continue,
+        };
+        for &rotation in &Rotation::shuffled(rng) {
+            let mut connections: Vec<_> = element
+                .jigsaws(rotation)
+                .filter(|other| can_attach(jigsaw, other))
+                .collect();
+            connections.shuffle(rng);
+            for connection in connections {
+                let y = match element.projection {
+                    Projection::Rigid => target.1 - connection.pos.1,
+                    // The bottom of terrain matching pieces
+                    // follows the surface, just below it.
+                    Projection::TerrainMatching => context.surface_height(target.0, target.2) - 1,
+                };
+                let origin = (target.0 - connection.pos.0, y, target.2 - connection.pos.2);
+                if fits(&element.bounding_box(origin, rotation)) {
+                    let depth = pieces[parent].depth + 1;
+                    return Some(JigsawPiece::new(
+                        element,
+                        origin,
+                        rotation,
+                        depth,
+                        !inside_parent,
+                    ));
+                }
+            }
+        }
+    }
+    None
+}
+
+/// Whether a piece with the jigsaw `other` may be attached to `jigsaw`.
+fn can_attach(jigsaw: &Jigsaw, other: &Jigsaw) -> bool {
+    jigsaw.front == other.front.opposite()
+        && (jigsaw.rollable || jigsaw.top == other.top)
+        && jigsaw.target == other.name
+}
+
+fn contains_box(outer: &StructureBox, inner: &StructureBox) -> bool {
+    outer.min_x <= inner.min_x
+        && outer.min_y <= inner.min_y
+        && outer.min_z <= inner.min_z
+        && outer.max_x >= inner.max_x
+        && outer.max_y >= inner.max_y
+        && outer.max_z >= inner.max_z
+}
+
+/// A template of a jigsaw structure, placed at `origin`.
+struct JigsawPiece {
+    element: Element,
+    origin: (i32, i32, i32),
+    rotation: Rotation,
+    bounding_box: StructureBox,
+    depth: i32,
+    /// Whether terrain above the floor of a rigid piece is
+    /// cleared before placing it. Pieces inside their parent
+    /// keep the blocks of the parent.
+    clear_terrain: bool,
+}
+
+impl JigsawPiece {
+    fn new(
+        element: Element,
+        origin: (i32, i32, i32),
+        rotation: Rotation,
+        depth: i32,
+        clear_terrain: bool,
+    ) -> Self {
+        let bounding_box = element.bounding_box(origin, rotation);
+        Self {
+            element,
+            origin,
+            rotation,
+            bounding_box,
+            depth,
+            clear_terrain,
+        }
+    }
+
+    /// Moves the piece vertically so its origin lies at `y`.
+    fn move_to_y(&mut self, y: i32) {
+        let offset = y - self.origin.1;
+        self.origin.1 = y;
+        self.bounding_box.offset(0, offset, 0);
+    }
+
+    /// Gets the world height of a position relative to the origin.
+    fn world_y(&self, x: i32, y: i32, z: i32, context: &dyn StructureContext) -> i32 {
+        match self.element.projection {
+            Projection::Rigid => self.origin.1 + y,
+            Projection::TerrainMatching => context.surface_height(x, z) - 1 + y,
+        }
+    }
+
+    /// Returns the jigsaws of the piece in world coordinates.
+    fn jigsaws(&self, context: &dyn StructureContext) -> Vec<Jigsaw> {
+        self.element
+            .jigsaws(self.rotation)
+            .map(|mut jigsaw| {
+                let (x, y, z) = jigsaw.pos;
+                let (x, z) = (self.origin.0 + x, self.origin.2 + z);
+                jigsaw.pos = (x, self.world_y(x, y, z, context), z);
+                jigsaw
+            })
+            .collect()
+    }
+
+    fn pool_element_nbt(&self) -> nbt::Value {
+        let projection = match self.element.projection {
+            Projection::Rigid => "rigid",
+            Projection::TerrainMatching => "terrain_matching",
+        };
+        let single = |part: &TemplatePart| {
+            let element_type = if part.legacy {
+                "minecraft:legacy_single_pool_element"
+            } else {
+                "minecraft:single_pool_element"
+            };
+            compound(vec![
+                ("element_type", nbt::Value::String(element_type.to_owned())),
+                ("location", nbt::Value::String(part.location.clone())),
+                (
+                    "processors",
+                    compound(vec![("processors", nbt::Value::List(Vec::new()))]),
+                ),
+                ("projection", nbt::Value::String(projection.to_owned())),
+            ])
+        };
+        match &self.element.parts[..] {
+            [(part, _)] => single(part),
+            parts => compound(vec![
+                (
+                    "element_type",
+                    nbt::Value::String("minecraft:list_pool_element".to_owned()),
+                ),
+                (
+                    "elements",
+                    nbt::Value::List(parts.iter().map(|(part, _)| single(part)).collect()),
+                ),
+                ("projection", nbt::Value::String(projection.to_owned())),
+            ]),
+        }
+    }
+}
+
+fn compound(entries: Vec<(&str, nbt::Value)>) -> nbt::Value {
+    nbt::Value::Compound(
+        entries
+            .into_iter()
+            .map(|(key, value)| (key.to_owned(), value))
+            .collect(),
+    )
+}
+
+impl Piece for JigsawPiece {
+    fn bounding_box(&self) -> StructureBox {
+        self.bounding_box
+    }
+
+    fn place(&self, chunk: &mut Chunk, context: &dyn StructureContext, _rng: &mut XorShiftRng) {
+        let chunk_box = StructureBox::for_chunk(chunk.position());
+        let locate = |x: i32, y: i32, z: i32| {
+            if chunk_box.contains(x, y, z) {
+                Some((
+                    (x - chunk_box.min_x) as usize,
+                    y as usize,
+                    (z - chunk_box.min_z) as usize,
+                ))
+            } else {
+                None
+            }
+        };
+        let structure_void = BlockId::structure_void();
+        let rigid = self.element.projection == Projection::Rigid;
+
+        if rigid && self.clear_terrain {
+            let bounding_box = &self.bounding_box;
+            for x in
+                bounding_box.min_x.max(chunk_box.min_x)..=bounding_box.max_x.min(chunk_box.max_x)
+            {
+                for z in bounding_box.min_z.max(chunk_box.min_z)
+                    ..=bounding_box.max_z.min(chunk_box.max_z)
+                {
+                    for y in self.origin.1 + 1..=bounding_box.max_y {
+                        if let Some((x, y, z)) = locate(x, y, z) {
+                            chunk.set_block_at(x, y, z, BlockId::air());
+                        }
+                    }
+                }
+            }
+        }
+
+        for (part, template) in &self.element.parts {
+            let jigsaws = template
+                .jigsaws(self.rotation)
+                .map(|jigsaw| (jigsaw.pos, jigsaw.final_state));
+            for ((x, y, z), block) in template.blocks(self.rotation).chain(jigsaws) {
+                if block == structure_void || (part.legacy && block.is_air()) {
+                    continue;
+                }
+                let (x, z) = (self.origin.0 + x, self.origin.2 + z);
+                let world_y = self.world_y(x, y, z, context);
+                let (x, world_y, z) = match locate(x, world_y, z) {
+                    Some(pos) => pos,
+                    None => continue,
+                };
+                chunk.set_block_at(x, world_y, z, block);
+
+                // Extend the bottom layer of rigid pieces
+                // down to the ground, so they don't float.
+                if rigid && y == 0 && block.is_solid() {
+                    for below in (world_y.saturating_sub(MAX_FOUNDATION).max(1)..world_y).rev() {
+                        match chunk.block_at(x, below, z) {
+                            Some(existing) if !existing.is_solid() => {
+                                chunk.set_block_at(x, below, z, block)
+                            }
+                            _ => break,
+                        };
+                    }
+                }
+            }
+        }
+    }
+
+    fn to_saved(&self) -> StructurePiece {
+        let mut data = BTreeMap::new();
+        data.insert("pos_x".to_owned(), nbt::Value::Int(self.origin.0));
+        data.insert("pos_y".to_owned(), nbt::Value::Int(self.origin.1));
+        data.insert("pos_z".to_owned(), nbt::Value::Int(self.origin.2));
+        data.insert("ground_level_delta".to_owned(), nbt::Value::Int(1));
+        data.insert(
+            "rotation".to_owned(),
+            nbt::Value::String(self.rotation.name().to_owned()),
+        );
+        data.insert("pool_element".to_owned(), self.pool_element_nbt());
+        data.insert("junctions".to_owned(), nbt::Value::List(Vec::new()));
+        StructurePiece {
+            id: "minecraft:jigsaw".to_owned(),
+            bounding_box: self.bounding_box,
+            data,
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::structures::template::{Direction, TemplateBlock};
+    use base::Biome;
+    use rand::SeedableRng;
+
+    struct Flat;
+
+    impl StructureContext for Flat {
+        fn biome_at(&self, _x: i32, _z: i32) -> Biome {
+            Biome::Plains
+        }
+
+        fn surface_height(&self, _x: i32, _z: i32) -> i32 {
+            64
+        }
+    }
+
+    fn template(
+        jigsaw_pos: (i32, i32, i32),
+        front: Direction,
+        name: &str,
+        pool: &str,
+        target: &str,
+    ) -> Template {
+        let mut blocks = Vec::new();
+        for x in 0..3 {
+            for z in 0..3 {
+                blocks.push(TemplateBlock {
+                    pos: (x, 0, z),
+                    block: BlockId::cobblestone(),
+                });
+            }
+        }
+        let jigsaw = Jigsaw {
+            pos: jigsaw_pos,
+            front,
+            top: Direction::Up,
+            name: name.to_owned(),
+            pool: pool.to_owned(),
+            target: target.to_owned(),
+            final_state: BlockId::cobblestone(),
+            rollable: true,
+        };
+        Template::new((3, 1, 3), blocks, vec![jigsaw])
+    }
+
+    fn pool(location: &str) -> TemplatePool {
+        let element = PoolElement::Templates {
+            parts: vec![TemplatePart {
+                location: location.to_owned(),
+                legacy: false,
+            }],
+            projection: Projection::Rigid,
+        };
+        TemplatePool {
+            fallback: "minecraft:empty".to_owned(),
+            elements: vec![(element, 1)],
+        }
+    }
+
+    #[test]
+    fn attach_pieces_to_jigsaws() {
+        let mut pools = JigsawPools::new();
+        pools.add_template(
+            "test:center",
+            template(
+                (2, 0, 1),
+                Direction::East,
+                "test:center",
+                "test:houses",
+                "test:house",
+            ),
+        );
+        pools.add_template(
+            "test:house",
+            template(
+                (0, 0, 1),
+                Direction::West,
+                "test:house",
+                "minecraft:empty",
+                "test:center",
+            ),
+        );
+        pools.add_pool("test:centers", pool("test:center"));
+        pools.add_pool("test:houses", pool("test:house"));
+
+        let mut rng = XorShiftRng::seed_from_u64(42);
+        let pieces = assemble(&pools, "test:centers", 6, (0, 0), &Flat, &mut rng);
+        assert_eq!(pieces.len(), 2);
+
+        // Both pieces rest on the terrain, next to each other.
+        let center = pieces[0].bounding_box();
+        let house = pieces[1].bounding_box();
+        assert_eq!((center.min_y, house.min_y), (63, 63));
+        assert!(!center.intersects(&house));
+        let mut touching = center;
+        touching.min_x -= 1;
+        touching.min_z -= 1;
+        touching.max_x += 1;
+        touching.max_z += 1;
+        assert!(touching.intersects(&house));
+    }
+}
@@ -0,0 +1,435 @@
+use std::collections::VecDeque;
+
+use base::{
+    chunk::{StructureBox, StructurePiece},
+    BlockId, Chunk, ChunkPosition, EntityKind, FacingCardinal, RailShape,
+};
+use rand::Rng;
+use rand_xorshift::XorShiftRng;
+
+use super::{
+    piece::{
+        offset_below_sea_level, oriented_box, saved_piece, to_world, world_direction, Orientation,
+        PieceWriter,
+    },
+    Piece, Structure, StructureContext, StructurePlacement,
+};
+use crate::SEA_LEVEL;
+
+/// Pieces further than this many steps from the
+/// central room are not generated.
+const MAX_DEPTH: i32 = 8;
+/// Pieces are generated at most this many blocks
+/// away from the start, horizontally.
+const MAX_DISTANCE: i32 = 80;
+
+/// Abandoned mineshafts: a dirt room from which
+/// corridors, crossings and stairs branch out underground.
+pub struct Mineshaft {
+    placement: StructurePlacement,
+}
+
+impl Default for Mineshaft {
+    fn default() -> Self {
+        Self {
+            placement: StructurePlacement::Grid {
+                spacing: 1,
+                separation: 0,
+                salt: 0x4D53_4846,
+                frequency: 0.004,
+            },
+        }
+    }
+}
+
+impl Structure for Mineshaft {
+    fn name(&self) -> &'static str {
+        "mineshaft"
+    }
+
+    fn placement(&self) -> &StructurePlacement {
+        &self.placement
+    }
+
+    fn max_radius(&self) -> i32 {
+        MAX_DISTANCE / 16 + 1
+    }
+
+    fn assemble(
+        &self,
+        start: ChunkPosition,
+        _context: &dyn StructureContext,
+        rng: &mut XorShiftRng,
+    ) -> Vec<Box<dyn Piece>> {
+        let (x, z) = (start.x * 16 + 2, start.z * 16 + 2);
+        let room = MineshaftPiece {
+            kind: Kind::Room,
+            bounding_box: StructureBox {
+                min_x: x,
+                min_y: 50,
+                min_z: z,
+                max_x: x + 7 + rng.gen_range(0, 6),
+                max_y: 54 + rng.gen_range(0, 6),
+                max_z: z + 7 + rng.gen_range(0, 6),
+            },
+            orientation: Orientation::South,
+            depth: 0,
+        };
+
+        let mut pieces = vec![room];
+        let mut pending: VecDeque<_> = pieces[0].exits(rng).into();
+        while let Some(exit) = pending.pop_front() {
+            if exit.depth > MAX_DEPTH
+                || (exit.x - x).abs() > MAX_DISTANCE
+                || (exit.z - z).abs() > MAX_DISTANCE
+            {
+                continue;
+            }
+            if let Some(piece) = random_piece(&exit, &pieces, rng) {
+                pending.extend(piece.exits(rng));
+                pieces.push(piece);
+            }
+        }
+
+        let mut bounding_box = pieces[0].bounding_box;
+        for piece in &pieces {
+            bounding_box.expand(&piece.bounding_box);
+        }
+        let offset = offset_below_sea_level(&bounding_box, SEA_LEVEL as i32 - 1, rng);
+        pieces
+            .into_iter()
+            .map(|mut piece| {
+                piece.bounding_box.offset(0, offset, 0);
+                Box::new(piece) as Box<dyn Piece>
+            })
+            .collect()
+    }
+}
+
+/// A position where a new piece may be attached.
+struct Exit {
+    x: i32,
+    y: i32,
+    z: i32,
+    orientation: Orientation,
+    depth: i32,
+}
+
+#[derive(Copy, Clone, Debug, PartialEq)]
+enum Kind {
+    Room,
+    Corridor {
+        sections: i32,
+        rails: bool,
+        /// Whether the corridor holds a cave spider spawner.
+        spiders: bool,
+    },
+    Crossing {
+        two_floors: bool,
+    },
+    /// Stairs descending five blocks.
+    Stairs,
+}
+
+struct MineshaftPiece {
+    kind: Kind,
+    bounding_box: StructureBox,
+    orientation: Orientation,
+    depth: i32,
+}
+
+/// Picks a random piece which fits at `exit`
+/// without intersecting existing pieces.
+fn random_piece(
+    exit: &Exit,
+    pieces: &[MineshaftPiece],
+    rng: &mut XorShiftRng,
+) -> Option<MineshaftPiece> {
+    let fits = |bounding_box: &StructureBox| {
+        bounding_box.min_y > 0
+            && !pieces
+                .iter()
+                .any(|piece| piece.bounding_box.intersects(bounding_box))
+    };
+    let piece = |kind, bounding_box| MineshaftPiece {
+        kind,
+        bounding_box,
+        orientation: exit.orientation,
+        depth: exit.depth,
+    };
+    let oriented = |offset, width, height, depth| {
+        oriented_box(
+            exit.x,
+            exit.y,
+            exit.z,
+            offset,
+            width,
+            height,
+            depth,
+            exit.orientation,
+        )
+    };
+
+    let roll = rng.gen_range(0, 100);
+    if roll >= 80 {
+        let two_floors = rng.gen_range(0, 4) == 0;
+        let bounding_box = oriented((-2, 0, 0), 5, if two_floors { 7 } else { 3 }, 5);
+        if fits(&bounding_box) {
+            return Some(piece(Kind::Crossing { two_floors }, bounding_box));
+        }
+    } else if roll >= 70 {
+        let bounding_box = oriented((-1, -5, 0), 3, 8, 9);
+        if fits(&bounding_box) {
+            return Some(piece(Kind::Stairs, bounding_box));
+        }
+    } else {
+        let rails = rng.gen_range(0, 3) == 0;
+        let spiders = !rails && rng.gen_range(0, 23) == 0;
+        for sections in (1..=rng.gen_range(2, 5)).rev() {
+            let bounding_box = oriented((-1, 0, 0), 3, 3, sections * 5);
+            if fits(&bounding_box) {
+                let kind = Kind::Corridor {
+                    sections,
+                    rails,
+                    spiders,
+                };
+                return Some(piece(kind, bounding_box));
+            }
+        }
+    }
+    None
+}
+
+impl MineshaftPiece {
+    /// Returns the positions new pieces may be attached to.
+    fn exits(&self, rng: &mut XorShiftRng) -> Vec<Exit> {
+        let depth = self.depth + 1;
+        let bounding_box = &self.bounding_box;
+        let exit = |x, y, z, direction_x, direction_z| {
+            let (x, y, z) = to_world(bounding_box, self.orientation, x, y, z);
+            Exit {
+                x,
+                y,
+                z,
+                orientation: world_direction(
+                    bounding_box,
+                    self.orientation,
+                    direction_x,
+                    direction_z,
+                ),
+                depth,
+            }
+        };
+
+        match self.kind {
+            Kind::Room => {
+                // Corridors leave the room at random
+                // positions along each of its walls.
+                let width = bounding_box.max_x - bounding_box.min_x + 1;
+                let length = bounding_box.max_z - bounding_box.min_z + 1;
+                let height = bounding_box.max_y - bounding_box.min_y + 1;
+                let mut exits = Vec::new();
+                for &(wall_length, side) in &[(width, 0), (width, 1), (length, 2), (length, 3)] {
+                    let mut offset = 0;
+                    loop {
+                        offset += rng.gen_range(0, wall_length);
+                        if offset + 3 > wall_length {
+                            break;
+                        }
+                        let y = rng.gen_range(0, (height - 3).max(1)) + 1;
+                        exits.push(match side {
+                            0 => exit(offset + 1, y, -1, 0, -1),
+                            1 => exit(offset + 1, y, length, 0, 1),
+                            2 => exit(-1, y, offset + 1, -1, 0),
+                            _ => exit(width, y, offset + 1, 1, 0),
+                        });
+                        offset += 4;
+                    }
+                }
+                exits
+            }
+            Kind::Corridor { sections, .. } => {
+                let length = sections * 5;
+                let y = rng.gen_range(0, 3) - 1;
+                let mut exits = vec![match rng.gen_range(0, 4) {
+                    0 => exit(-1, y, length - 2, -1, 0),
+                    1 => exit(3, y, length - 2, 1, 0),
+                    _ => exit(1, y, length, 0, 1),
+                }];
+                if depth <= MAX_DEPTH {
+                    let mut z = 3;
+                    while z + 3 <= length {
+                        match rng.gen_range(0, 5) {
+                            0 => exits.push(exit(-1, 0, z, -1, 0)),
+                            1 => exits.push(exit(3, 0, z, 1, 0)),
+                            _ => {}
+                        }
+                        z += 5;
+                    }
+                }
+                exits
+            }
+            Kind::Crossing { two_floors } => {
+                let mut exits = vec![
+                    exit(2, 0, 5, 0, 1),
+                    exit(-1, 0, 2, -1, 0),
+                    exit(5, 0, 2, 1, 0),
+                ];
+                if two_floors && rng.gen() {
+                    exits.push(exit(2, 4, 5, 0, 1));
+                    exits.push(exit(-1, 4, 2, -1, 0));
+                    exits.push(exit(5, 4, 2, 1, 0));
+                }
+                exits
+            }
+            Kind::Stairs => vec![exit(1, 0, 9, 0, 1)],
+        }
+    }
+}
+
+impl Piece for MineshaftPiece {
+    fn bounding_box(&self) -> StructureBox {
+        self.bounding_box
+    }
+
+    fn place(&self, chunk: &mut Chunk, _context: &dyn StructureContext, rng: &mut XorShiftRng) {
+        let mut writer = PieceWriter::new(chunk, self.bounding_box, self.orientation);
+        let air = BlockId::air();
+        let planks = BlockId::oak_planks();
+        let width = self.bounding_box.max_x - self.bounding_box.min_x;
+        let height = self.bounding_box.max_y - self.bounding_box.min_y;
+        let length = self.bounding_box.max_z - self.bounding_box.min_z;
+
+        match self.kind {
+            Kind::Room => {
+                writer.fill((0, 0, 0), (width, 0, length), BlockId::dirt());
+                writer.fill((0, 1, 0), (width, height, length), air);
+            }
+            Kind::Corridor {
+                sections,
+                rails,
+                spiders,
+            } => {
+                let length = sections * 5 - 1;
+                writer.fill((0, 0, 0), (2, 2, length), air);
+                for z in 0..=length {
+                    // Bridge gaps in the floor, such as caves.
+                    for x in 0..3 {
+                        if writer.get(x, -1, z).map_or(false, |block| block.is_air()) {
+                            writer.set(x, -1, z, planks);
+                        }
+                    }
+                    if rails && rng.gen::<f64>() < 0.7 {
+                        let shape = match writer.facing(0, 1) {
+                            FacingCardinal::North | FacingCardinal::South => RailShape::NorthSouth,
+                            _ => RailShape::EastWest,
+                        };
+                        writer.set(1, 0, z, BlockId::rail().with_rail_shape(shape));
+                    }
+                }
+                for section in 0..sections {
+                    let z = section * 5 + 2;
+                    writer.fill((0, 0, z), (0, 1, z), BlockId::oak_fence());
+                    writer.fill((2, 0, z), (2, 1, z), BlockId::oak_fence());
+                    writer.fill((0, 2, z), (2, 2, z), planks);
+                }
+                writer.fill_randomly((0, 2, 0), (2, 2, length), 0.05, rng, BlockId::cobweb());
+                if spiders {
+                    // A cave spider spawner in a nest of cobwebs.
+                    writer.fill_randomly((0, 0, 0), (2, 1, length), 0.6, rng, BlockId::cobweb());
+                    let z = rng.gen_range(0, sections) * 5 + 2;
+                    writer.set(1, 0, z, BlockId::spawner());
+                }
+            }
+            Kind::Crossing { two_floors } => {
+                writer.fill((0, 0, 0), (4, height, 4), air);
+                for &(x, z) in &[(1, 1), (3, 1), (1, 3), (3, 3)] {
+                    writer.fill((x, 0, z), (x, height, z), planks);
+                }
+                if two_floors {
+                    writer.fill((1, 3, 1), (3, 3, 3), planks);
+                    writer.fill((2, 3, 0), (2, 3, 4), planks);
+                    writer.fill((0, 3, 2), (4, 3, 2), planks);
+                }
+            }
+            Kind::Stairs => {
+                for z in 0..9 {
+                    let floor = (6 - z).max(0).min(5);
+                    writer.fill((0, floor, z), (2, floor + 2, z), air);
+                }
+            }
+        }
+    }
+
+    fn spawner_mob(&self) -> Option<EntityKind> {
+        match self.kind {
+            Kind::Corridor { spiders: true, .. } => Some(EntityKind::CaveSpider),
+            _ => None,
+        }
+    }
+
+    fn to_saved(&self) -> StructurePiece {
+        let id = match self.kind {
+            Kind::Room => "MSRoom",
+            Kind::Corridor { .. } => "MSCorridor",
+            Kind::Crossing { .. } => "MSCrossing",
+            Kind::Stairs => "MSStairs",
+        };
+        saved_piece(id, self.bounding_box, self.orientation, self.depth)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use base::{anvil::block_entity::BlockEntityKind, Biome};
+    use rand::SeedableRng;
+
+    struct Flat;
+
+    impl StructureContext for Flat {
+        fn biome_at(&self, _x: i32, _z: i32) -> Biome {
+            Biome::Plains
+        }
+
+        fn surface_height(&self, _x: i32, _z: i32) -> i32 {
+            64
+        }
+    }
+
+    #[test]
+    fn spider_corridor_has_one_spawner_across_chunks() {
+        let orientation = Orientation::South;
+        let piece = MineshaftPiece {
+            kind: Kind::Corridor {
+                sections: 4,
+                rails: false,
+                spiders: true,
+            },
+            bounding_box: oriented_box(1, 20, 8, (-1, 0, 0), 3, 3, 20, orientation),
+            orientation,
+            depth: 1,
+        };
+        assert_eq!(piece.spawner_mob(), Some(EntityKind::CaveSpider));
+
+        // The generator seeds the piece the same way in every chunk.
+        let mut block_entities = Vec::new();
+        for z in 0..=1 {
+            let mut chunk = Chunk::new(ChunkPosition::new(0, z));
+            piece.place(&mut chunk, &Flat, &mut XorShiftRng::seed_from_u64(7));
+            block_entities.extend(crate::spawner_block_entities(
+                &chunk,
+                &piece.bounding_box,
+                EntityKind::CaveSpider,
+            ));
+        }
+
+        assert_eq!(block_entities.len(), 1);
+        match &block_entities[0].kind {
+            BlockEntityKind::MobSpawner { spawn_data, .. } => {
+                assert_eq!(spawn_data.as_ref().unwrap().id, "minecraft:cave_spider");
+            }
+            kind => panic!("expected a mob spawner, got {:?}", kind),
+        }
+    }
+}
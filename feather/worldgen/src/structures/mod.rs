@@ -0,0 +1,220 @@
+//! Structures, such as villages and strongholds, which are
+//! made up of pieces that may span many chunks.
+//!
+//! Each structure picks the chunks it starts in from the
+//! world seed. When a chunk is generated, the starts of nearby
+//! structures are laid out again, deterministically, and the
+//! parts of their pieces inside the chunk are placed.
+
+mod desert_temple;
+mod jigsaw;
+mod mineshaft;
+mod piece;
+mod placement;
+mod stronghold;
+mod template;
+mod village;
+
+pub use desert_temple::DesertTemple;
+pub use jigsaw::{JigsawPools, PoolElement, Projection, TemplatePart, TemplatePool};
+pub use mineshaft::Mineshaft;
+pub use placement::StructurePlacement;
+pub use stronghold::Stronghold;
+pub use template::{Direction, Jigsaw, Template, TemplateBlock};
+pub use village::Village;
+
+use std::{
+    collections::{HashMap, VecDeque},
+    sync::{Arc, Mutex},
+};
+
+use base::{
+    chunk::{StructureBox, StructurePiece, StructureStart},
+    Biome, Chunk, ChunkPosition, EntityKind,
+};
+use rand_xorshift::XorShiftRng;
+
+/// Terrain information available to structures while
+/// they are laid out and placed.
+pub trait StructureContext {
+    /// Gets the biome at the given block column.
+    fn biome_at(&self, x: i32, z: i32) -> Biome;
+
+    /// Gets the height of the terrain at the given block column,
+    /// i.e. the y coordinate above its highest non-air block.
+    fn surface_height(&self, x: i32, z: i32) -> i32;
+}
+
+/// A structure generated by a `ComposableGenerator`.
+pub trait Structure: Send + Sync {
+    /// The name under which starts and references of
+    /// this structure are saved, e.g. `village`.
+    fn name(&self) -> &'static str;
+
+    /// Determines the chunks this structure may start in.
+    fn placement(&self) -> &StructurePlacement;
+
+    /// The maximum distance, in chunks, pieces
+    /// may extend from the start chunk.
+    fn max_radius(&self) -> i32;
+
+    /// Lays out the pieces of the structure starting in the
+    /// given chunk. Returns no pieces if the structure
+    /// cannot start there, e.g. because of the biome.
+    ///
+    /// This function should be deterministic.
+    fn assemble(
+        &self,
+        start: ChunkPosition,
+        context: &dyn StructureContext,
+        rng: &mut XorShiftRng,
+    ) -> Vec<Box<dyn Piece>>;
+}
+
+/// A piece of a structure.
+pub trait Piece: Send + Sync {
+    /// Gets the box enclosing all blocks of the piece.
+    fn bounding_box(&self) -> StructureBox;
+
+    /// Places the blocks of the piece inside `chunk`.
+    fn place(&self, chunk: &mut Chunk, context: &dyn StructureContext, rng: &mut XorShiftRng);
+
+    /// Returns the mob spawned by the spawner blocks this piece places.
+    /// The generator gives each of them a block entity spawning this mob.
+    fn spawner_mob(&self) -> Option<EntityKind> {
+        None
+    }
+
+    /// Returns the loot table which fills the chests this piece places.
+    /// The generator gives each of them a block entity using this table.
+    fn chest_loot_table(&self) -> Option<&'static str> {
+        None
+    }
+
+    /// Converts the piece to the form saved in chunks.
+    fn to_saved(&self) -> StructurePiece;
+}
+
+/// The laid out pieces of a structure start.
+pub struct AssembledStart {
+    pub pieces: Vec<Box<dyn Piece>>,
+    pub bounding_box: StructureBox,
+}
+
+impl AssembledStart {
+    /// Creates a start from its pieces. Returns `None` if there are none.
+    pub fn new(pieces: Vec<Box<dyn Piece>>) -> Option<Self> {
+        let mut bounding_box = pieces.first()?.bounding_box();
+        for piece in &pieces[1..] {
+            bounding_box.expand(&piece.bounding_box());
+        }
+        Some(Self {
+            pieces,
+            bounding_box,
+        })
+    }
+
+    /// Converts the start to the form saved in its start chunk.
+    pub fn to_saved(&self, name: &str, chunk: ChunkPosition) -> StructureStart {
+        let min = ChunkPosition::new(
+            self.bounding_box.min_x.div_euclid(16),
+            self.bounding_box.min_z.div_euclid(16),
+        );
+        let max = ChunkPosition::new(
+            self.bounding_box.max_x.div_euclid(16),
+            self.bounding_box.max_z.div_euclid(16),
+        );
+        let mut references = 0;
+        for z in min.z..=max.z {
+            for x in min.x..=max.x {
+                let chunk_box = StructureBox::for_chunk(ChunkPosition::new(x, z));
+                if self
+                    .pieces
+                    .iter()
+                    .any(|piece| piece.bounding_box().intersects(&chunk_box))
+                {
+                    references += 1;
+                }
+            }
+        }
+
+        StructureStart {
+            id: format!("minecraft:{}", name),
+            chunk,
+            bounding_box: self.bounding_box,
+            pieces: self.pieces.iter().map(|piece| piece.to_saved()).collect(),
+            references,
+        }
+    }
+}
+
+/// Caches recently laid out structure starts, since each
+/// start is needed by every chunk its pieces cover.
+pub struct StartCache {
+    capacity: usize,
+    inner: Mutex<CacheInner>,
+}
+
+type CachedStart = Arc<Option<AssembledStart>>;
+
+#[derive(Default)]
+struct CacheInner {
+    starts: HashMap<(usize, ChunkPosition), CachedStart>,
+    /// Cached starts, oldest first.
+    order: VecDeque<(usize, ChunkPosition)>,
+}
+
+impl StartCache {
+    pub fn new(capacity: usize) -> Self {
+        Self {
+            capacity,
+            inner: Mutex::new(CacheInner::default()),
+        }
+    }
+
+    /// Gets the start of the structure with the given index in
+    /// the given chunk, laying it out with `assemble` if it's not cached.
+    pub fn get_or_assemble(
+        &self,
+        structure: usize,
+        pos: ChunkPosition,
+        assemble: impl FnOnce() -> Option<AssembledStart>,
+    ) -> CachedStart {
+        let key = (structure, pos);
+        if let Some(start) = self.inner.lock().unwrap().starts.get(&key) {
+            return Arc::clone(start);
+        }
+
+        // Lay out without holding the lock, since
+        // it may generate the terrain of many chunks.
+        let start = Arc::new(assemble());
+
+        let mut inner = self.inner.lock().unwrap();
+        if let Some(existing) = inner.starts.get(&key) {
+            return Arc::clone(existing);
+        }
+        inner.starts.insert(key, Arc::clone(&start));
+        inner.order.push_back(key);
+        while inner.order.len() > self.capacity {
+            if let Some(oldest) = inner.order.pop_front() {
+                inner.starts.remove(&oldest);
+            }
+        }
+        start
+    }
+}
+
+/// Returns the structures generated by default: villages,
+/// desert temples, mineshafts and strongholds.
+///
+/// Villages are only generated if `pools` contains their templates.
+pub fn default_structures(pools: Option<Arc<JigsawPools>>) -> Vec<Box<dyn Structure>> {
+    let mut structures: Vec<Box<dyn Structure>> = Vec::new();
+    if let Some(pools) = pools {
+        structures.push(Box::new(Village::new(pools)));
+    }
+    structures.push(Box::new(DesertTemple::default()));
+    structures.push(Box::new(Mineshaft::default()));
+    structures.push(Box::new(Stronghold::default()));
+    structures
+}
@@ -0,0 +1,314 @@
+//! Helpers for pieces built in code, which are laid out in
+//! local coordinates relative to their bounding box and orientation.
+
+use std::collections::BTreeMap;
+
+use base::{
+    chunk::{StructureBox, StructurePiece},
+    BlockId, Chunk, FacingCardinal,
+};
+use rand::Rng;
+
+/// The horizontal direction a piece extends in.
+#[derive(Copy, Clone, Debug, PartialEq, Eq)]
+pub enum Orientation {
+    North,
+    East,
+    South,
+    West,
+}
+
+impl Orientation {
+    pub const ALL: [Orientation; 4] = [
+        Orientation::North,
+        Orientation::East,
+        Orientation::South,
+        Orientation::West,
+    ];
+
+    pub fn random(rng: &mut impl Rng) -> Self {
+        Self::ALL[rng.gen_range(0, 4)]
+    }
+
+    /// The index vanilla saves as the `O` tag of pieces.
+    pub fn index(self) -> i32 {
+        match self {
+            Orientation::South => 0,
+            Orientation::West => 1,
+            Orientation::North => 2,
+            Orientation::East => 3,
+        }
+    }
+
+    /// Gets the direction of a world offset along one axis.
+    fn from_offset(x: i32, z: i32) -> Self {
+        match (x.signum(), z.signum()) {
+            (1, _) => Orientation::East,
+            (-1, _) => Orientation::West,
+            (_, 1) => Orientation::South,
+            _ => Orientation::North,
+        }
+    }
+
+    pub fn facing(self) -> FacingCardinal {
+        match self {
+            Orientation::North => FacingCardinal::North,
+            Orientation::East => FacingCardinal::East,
+            Orientation::South => FacingCardinal::South,
+            Orientation::West => FacingCardinal::West,
+        }
+    }
+}
+
+/// Creates the bounding box of a piece which starts at `(x, y, z)`
+/// and extends `depth` blocks in the direction of `orientation`.
+/// `offset` is applied in local coordinates, so an x offset of
+/// `-(width / 2)` centers the piece on the start position.
+#[allow(clippy::too_many_arguments)]
+pub fn oriented_box(
+    x: i32,
+    y: i32,
+    z: i32,
+    offset: (i32, i32, i32),
+    width: i32,
+    height: i32,
+    depth: i32,
+    orientation: Orientation,
+) -> StructureBox {
+    let (off_x, off_y, off_z) = offset;
+    let (min_x, min_z, max_x, max_z) = match orientation {
+        Orientation::North => (
+            x + off_x,
+            z - depth + 1 + off_z,
+            x + width - 1 + off_x,
+            z + off_z,
+        ),
+        Orientation::South => (
+            x + off_x,
+            z + off_z,
+            x + width - 1 + off_x,
+            z + depth - 1 + off_z,
+        ),
+        Orientation::West => (
+            x - depth + 1 + off_z,
+            z + off_x,
+            x + off_z,
+            z + width - 1 + off_x,
+        ),
+        Orientation::East => (
+            x + off_z,
+            z + off_x,
+            x + depth - 1 + off_z,
+            z + width - 1 + off_x,
+        ),
+    };
+    StructureBox {
+        min_x,
+        min_y: y + off_y,
+        min_z,
+        max_x,
+        max_y: y + height - 1 + off_y,
+        max_z,
+    }
+}
+
+/// Converts a position local to a piece into world coordinates.
+///
+/// Local z increases in the direction of `orientation`, and
+/// local y is relative to the bottom of the box.
+pub fn to_world(
+    bounding_box: &StructureBox,
+    orientation: Orientation,
+    x: i32,
+    y: i32,
+    z: i32,
+) -> (i32, i32, i32) {
+    let y = bounding_box.min_y + y;
+    match orientation {
+        Orientation::North => (bounding_box.min_x + x, y, bounding_box.max_z - z),
+        Orientation::South => (bounding_box.min_x + x, y, bounding_box.min_z + z),
+        Orientation::West => (bounding_box.max_x - z, y, bounding_box.min_z + x),
+        Orientation::East => (bounding_box.min_x + z, y, bounding_box.min_z + x),
+    }
+}
+
+/// Gets the world direction of the local offset `(x, z)`.
+pub fn world_direction(
+    bounding_box: &StructureBox,
+    orientation: Orientation,
+    x: i32,
+    z: i32,
+) -> Orientation {
+    let (origin_x, _, origin_z) = to_world(bounding_box, orientation, 0, 0, 0);
+    let (world_x, _, world_z) = to_world(bounding_box, orientation, x, 0, z);
+    Orientation::from_offset(world_x - origin_x, world_z - origin_z)
+}
+
+/// Returns the vertical offset which moves an underground structure,
+/// enclosed by `bounding_box`, at least 10 blocks below sea level.
+pub fn offset_below_sea_level(
+    bounding_box: &StructureBox,
+    sea_level: i32,
+    rng: &mut impl Rng,
+) -> i32 {
+    let limit = sea_level - 10;
+    let mut top = bounding_box.max_y - bounding_box.min_y + 2;
+    if top < limit {
+        top += rng.gen_range(0, limit - top);
+    }
+    top - bounding_box.max_y
+}
+
+/// Creates the saved form of a piece built in code,
+/// with its orientation and depth in the structure.
+pub fn saved_piece(
+    id: &str,
+    bounding_box: StructureBox,
+    orientation: Orientation,
+    depth: i32,
+) -> StructurePiece {
+    let mut data = BTreeMap::new();
+    data.insert("O".to_owned(), nbt::Value::Int(orientation.index()));
+    data.insert("GD".to_owned(), nbt::Value::Int(depth));
+    StructurePiece {
+        id: id.to_owned(),
+        bounding_box,
+        data,
+    }
+}
+
+/// Writes the blocks of a piece into a chunk, using
+/// local coordinates. Blocks outside the chunk are skipped.
+pub struct PieceWriter<'a> {
+    chunk: &'a mut Chunk,
+    chunk_box: StructureBox,
+    bounding_box: StructureBox,
+    orientation: Orientation,
+}
+
+impl<'a> PieceWriter<'a> {
+    pub fn new(chunk: &'a mut Chunk, bounding_box: StructureBox, orientation: Orientation) -> Self {
+        Self {
+            chunk_box: StructureBox::for_chunk(chunk.position()),
+            chunk,
+            bounding_box,
+            orientation,
+        }
+    }
+
+    fn locate(&self, x: i32, y: i32, z: i32) -> Option<(usize, usize, usize)> {
+        let (x, y, z) = to_world(&self.bounding_box, self.orientation, x, y, z);
+        if !self.chunk_box.contains(x, y, z) {
+            return None;
+        }
+        Some((
+            (x - self.chunk_box.min_x) as usize,
+            y as usize,
+            (z - self.chunk_box.min_z) as usize,
+        ))
+    }
+
+    /// Gets the block at the given local position,
+    /// or `None` if it lies outside the chunk.
+    pub fn get(&self, x: i32, y: i32, z: i32) -> Option<BlockId> {
+        let (x, y, z) = self.locate(x, y, z)?;
+        self.chunk.block_at(x, y, z)
+    }
+
+    pub fn set(&mut self, x: i32, y: i32, z: i32, block: BlockId) {
+        if let Some((x, y, z)) = self.locate(x, y, z) {
+            self.chunk.set_block_at(x, y, z, block);
+        }
+    }
+
+    /// Gets the world facing of the local offset `(x, z)`,
+    /// for blocks such as stairs and chests.
+    pub fn facing(&self, x: i32, z: i32) -> FacingCardinal {
+        world_direction(&self.bounding_box, self.orientation, x, z).facing()
+    }
+
+    /// Fills the box between the given local corners.
+    pub fn fill(&mut self, min: (i32, i32, i32), max: (i32, i32, i32), block: BlockId) {
+        self.fill_hollow(min, max, block, block);
+    }
+
+    /// Fills the faces of the box between the given local
+    /// corners with `edge` and its inside with `inside`.
+    pub fn fill_hollow(
+        &mut self,
+        min: (i32, i32, i32),
+        max: (i32, i32, i32),
+        edge: BlockId,
+        inside: BlockId,
+    ) {
+        for y in min.1..=max.1 {
+            for x in min.0..=max.0 {
+                for z in min.2..=max.2 {
+                    let is_edge = y == min.1
+                        || y == max.1
+                        || x == min.0
+                        || x == max.0
+                        || z == min.2
+                        || z == max.2;
+                    self.set(x, y, z, if is_edge { edge } else { inside });
+                }
+            }
+        }
+    }
+
+    /// Sets each block in the box with the given chance.
+    pub fn fill_randomly(
+        &mut self,
+        min: (i32, i32, i32),
+        max: (i32, i32, i32),
+        chance: f64,
+        rng: &mut impl Rng,
+        block: BlockId,
+    ) {
+        for y in min.1..=max.1 {
+            for x in min.0..=max.0 {
+                for z in min.2..=max.2 {
+                    if rng.gen::<f64>() < chance {
+                        self.set(x, y, z, block);
+                    }
+                }
+            }
+        }
+    }
+
+    /// Fills downwards from the given local position
+    /// until reaching a solid block, so the piece
+    /// does not float above caves or water.
+    pub fn fill_down(&mut self, x: i32, y: i32, z: i32, block: BlockId) {
+        let mut y = y;
+        while let Some(existing) = self.get(x, y, z) {
+            if existing.is_solid() || self.bounding_box.min_y + y <= 0 {
+                break;
+            }
+            self.set(x, y, z, block);
+            y -= 1;
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn local_coordinates_follow_orientation() {
+        for &orientation in &Orientation::ALL {
+            let bounding_box = oriented_box(100, 10, 200, (-1, 0, 0), 3, 3, 5, orientation);
+            // The piece starts at the given position...
+            let (x, _, z) = to_world(&bounding_box, orientation, 1, 0, 0);
+            assert_eq!((x, z), (100, 200), "{:?}", orientation);
+            // ...and extends away from it.
+            assert_eq!(
+                world_direction(&bounding_box, orientation, 0, 1),
+                orientation
+            );
+            let (x, _, z) = to_world(&bounding_box, orientation, 1, 0, 4);
+            assert!(bounding_box.contains(x, 10, z));
+        }
+    }
+}
@@ -0,0 +1,164 @@
+use std::f64::consts::PI;
+
+use base::ChunkPosition;
+use rand::{Rng, SeedableRng};
+use rand_xorshift::XorShiftRng;
+
+use crate::util::shuffle_seed_for_chunk;
+
+/// Determines the chunks a structure starts in.
+#[derive(Clone, Debug, PartialEq)]
+pub enum StructurePlacement {
+    /// The world is divided into cells of `spacing` by `spacing`
+    /// chunks, and the structure may start in one chunk of each cell.
+    /// The chunk is picked at random, leaving at least `separation`
+    /// chunks between starts of neighbouring cells.
+    Grid {
+        spacing: i32,
+        separation: i32,
+        /// Mixed into the world seed so structures
+        /// with the same spacing don't line up.
+        salt: u64,
+        /// The chance that a cell contains a start.
+        frequency: f64,
+    },
+    /// A fixed number of starts placed on rings around the origin.
+    Rings {
+        /// Distance between rings, in chunks.
+        distance: i32,
+        /// Number of starts on the first ring.
+        spread: i32,
+        count: i32,
+    },
+}
+
+impl StructurePlacement {
+    /// Returns the chunks in which the structure starts
+    /// between `min` and `max` inclusive.
+    pub fn starts_in_area(
+        &self,
+        seed: u64,
+        min: ChunkPosition,
+        max: ChunkPosition,
+    ) -> Vec<ChunkPosition> {
+        let in_area = |pos: &ChunkPosition| {
+            (min.x..=max.x).contains(&pos.x) && (min.z..=max.z).contains(&pos.z)
+        };
+        match *self {
+            StructurePlacement::Grid {
+                spacing,
+                separation,
+                salt,
+                frequency,
+            } => {
+                let mut starts = Vec::new();
+                for cell_z in min.z.div_euclid(spacing)..=max.z.div_euclid(spacing) {
+                    for cell_x in min.x.div_euclid(spacing)..=max.x.div_euclid(spacing) {
+                        let cell = ChunkPosition::new(cell_x, cell_z);
+                        let mut rng =
+                            XorShiftRng::seed_from_u64(shuffle_seed_for_chunk(seed ^ salt, cell));
+                        let range = (spacing - separation).max(1);
+                        let start = ChunkPosition::new(
+                            cell_x * spacing + rng.gen_range(0, range),
+                            cell_z * spacing + rng.gen_range(0, range),
+                        );
+                        if frequency < 1.0 && rng.gen::<f64>() >= frequency {
+                            continue;
+                        }
+                        if in_area(&start) {
+                            starts.push(start);
+                        }
+                    }
+                }
+                starts
+            }
+            StructurePlacement::Rings { .. } => {
+                self.ring_starts(seed).into_iter().filter(in_area).collect()
+            }
+        }
+    }
+
+    /// Computes the starts of ring placement: each ring holds
+    /// more starts than the previous, at random angles.
+    fn ring_starts(&self, seed: u64) -> Vec<ChunkPosition> {
+        let (distance, spread, count) = match *self {
+            StructurePlacement::Rings {
+                distance,
+                spread,
+                count,
+            } => (distance, spread, count),
+            StructurePlacement::Grid { .. } => return Vec::new(),
+        };
+
+        let mut rng = XorShiftRng::seed_from_u64(seed);
+        let mut starts = Vec::with_capacity(count.max(0) as usize);
+        let mut angle = rng.gen::<f64>() * PI * 2.0;
+        let mut ring = 0;
+        let mut in_ring = 0;
+        let mut per_ring = spread;
+        for i in 0..count {
+            let radius = f64::from(4 * distance + distance * ring * 6)
+                + (rng.gen::<f64>() - 0.5) * f64::from(distance) * 2.5;
+            starts.push(ChunkPosition::new(
+                (angle.cos() * radius).round() as i32,
+                (angle.sin() * radius).round() as i32,
+            ));
+
+            angle += PI * 2.0 / f64::from(per_ring);
+            in_ring += 1;
+            if in_ring == per_ring {
+                ring += 1;
+                in_ring = 0;
+                per_ring += 2 * per_ring / (ring + 1);
+                per_ring = per_ring.min(count - i - 1).max(1);
+                angle += rng.gen::<f64>() * PI * 2.0;
+            }
+        }
+        starts
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn one_start_per_grid_cell() {
+        let placement = StructurePlacement::Grid {
+            spacing: 32,
+            separation: 8,
+            salt: 10_387_312,
+            frequency: 1.0,
+        };
+        let starts =
+            placement.starts_in_area(42, ChunkPosition::new(-64, -64), ChunkPosition::new(63, 63));
+        assert_eq!(starts.len(), 16);
+        for start in &starts {
+            assert!(start.x.rem_euclid(32) < 24);
+            assert!(start.z.rem_euclid(32) < 24);
+        }
+
+        // A smaller area sees the same starts.
+        let nearby =
+            placement.starts_in_area(42, ChunkPosition::new(0, 0), ChunkPosition::new(31, 31));
+        assert_eq!(nearby.len(), 1);
+        assert!(starts.contains(&nearby[0]));
+    }
+
+    #[test]
+    fn ring_starts() {
+        let placement = StructurePlacement::Rings {
+            distance: 32,
+            spread: 3,
+            count: 128,
+        };
+        let starts = placement.ring_starts(42);
+        assert_eq!(starts.len(), 128);
+
+        // The first ring lies between 88 and 168 chunks from the origin.
+        for start in &starts[..3] {
+            let distance = f64::from(start.x * start.x + start.z * start.z).sqrt();
+            assert!((87.0..=169.0).contains(&distance), "{}", distance);
+        }
+    }
+}
@@ -0,0 +1,520 @@
+use std::collections::VecDeque;
+
+use base::{
+    chunk::{StructureBox, StructurePiece},
+    BlockId, Chunk, ChunkPosition, EntityKind, FacingCardinal,
+};
+use rand::Rng;
+use rand_xorshift::XorShiftRng;
+
+use super::{
+    piece::{
+        offset_below_sea_level, oriented_box, saved_piece, to_world, world_direction, Orientation,
+        PieceWriter,
+    },
+    Piece, Structure, StructureContext, StructurePlacement,
+};
+use crate::SEA_LEVEL;
+
+/// Pieces further than this many steps from the
+/// start are not generated.
+const MAX_DEPTH: i32 = 50;
+/// Pieces are generated at most this many blocks
+/// away from the start, horizontally.
+const MAX_DISTANCE: i32 = 112;
+/// Number of times a stronghold is laid out again
+/// if it ends up without a portal room.
+const MAX_ATTEMPTS: usize = 16;
+
+/// Strongholds: mazes of stone brick corridors and rooms,
+/// one of which holds the portal to the End.
+pub struct Stronghold {
+    placement: StructurePlacement,
+}
+
+impl Default for Stronghold {
+    fn default() -> Self {
+        Self {
+            placement: StructurePlacement::Rings {
+                distance: 32,
+                spread: 3,
+                count: 128,
+            },
+        }
+    }
+}
+
+impl Structure for Stronghold {
+    fn name(&self) -> &'static str {
+        "stronghold"
+    }
+
+    fn placement(&self) -> &StructurePlacement {
+        &self.placement
+    }
+
+    fn max_radius(&self) -> i32 {
+        MAX_DISTANCE / 16 + 1
+    }
+
+    fn assemble(
+        &self,
+        start: ChunkPosition,
+        _context: &dyn StructureContext,
+        rng: &mut XorShiftRng,
+    ) -> Vec<Box<dyn Piece>> {
+        let (x, z) = (start.x * 16 + 2, start.z * 16 + 2);
+        let mut pieces = Vec::new();
+        for _ in 0..MAX_ATTEMPTS {
+            pieces = lay_out(x, z, rng);
+            if pieces.iter().any(|piece| piece.kind == Kind::PortalRoom) {
+                break;
+            }
+        }
+
+        let mut bounding_box = pieces[0].bounding_box;
+        for piece in &pieces {
+            bounding_box.expand(&piece.bounding_box);
+        }
+        let offset = offset_below_sea_level(&bounding_box, SEA_LEVEL as i32 - 1, rng);
+        pieces
+            .into_iter()
+            .map(|mut piece| {
+                piece.bounding_box.offset(0, offset, 0);
+                Box::new(piece) as Box<dyn Piece>
+            })
+            .collect()
+    }
+}
+
+/// Lays out the pieces of a stronghold starting at the given position.
+fn lay_out(x: i32, z: i32, rng: &mut XorShiftRng) -> Vec<StrongholdPiece> {
+    let orientation = Orientation::random(rng);
+    let start = StrongholdPiece {
+        kind: Kind::Start,
+        bounding_box: Kind::Start.bounding_box(x, 64, z, orientation),
+        orientation,
+        depth: 0,
+    };
+
+    let mut counts = [0; Kind::ALL.len()];
+    let mut pieces = vec![start];
+    let mut pending: VecDeque<_> = pieces[0].exits().into();
+    while let Some(exit) = pending.pop_front() {
+        if exit.depth > MAX_DEPTH
+            || (exit.x - x).abs() > MAX_DISTANCE
+            || (exit.z - z).abs() > MAX_DISTANCE
+        {
+            continue;
+        }
+        if let Some(piece) = random_piece(&exit, &pieces, &counts, rng) {
+            counts[piece.kind.index()] += 1;
+            pending.extend(piece.exits());
+            pieces.push(piece);
+        }
+    }
+    pieces
+}
+
+/// The center of the bottom of a doorway where
+/// a new piece may be attached.
+struct Exit {
+    x: i32,
+    y: i32,
+    z: i32,
+    orientation: Orientation,
+    depth: i32,
+}
+
+#[derive(Copy, Clone, Debug, PartialEq, Eq)]
+enum Kind {
+    /// A spiral staircase leading down from the start.
+    Start,
+    Straight,
+    LeftTurn,
+    RightTurn,
+    StairsDown,
+    RoomCrossing,
+    PrisonHall,
+    Library,
+    PortalRoom,
+}
+
+impl Kind {
+    const ALL: [Kind; 9] = [
+        Kind::Start,
+        Kind::Straight,
+        Kind::LeftTurn,
+        Kind::RightTurn,
+        Kind::StairsDown,
+        Kind::RoomCrossing,
+        Kind::PrisonHall,
+        Kind::Library,
+        Kind::PortalRoom,
+    ];
+
+    fn index(self) -> usize {
+        Self::ALL.iter().position(|&kind| kind == self).unwrap()
+    }
+
+    /// Returns the weight, maximum count and minimum depth
+    /// of randomly picked pieces of this kind.
+    fn frequency(self) -> (u32, u32, i32) {
+        match self {
+            Kind::Start => (0, 0, 0),
+            Kind::Straight => (40, u32::MAX, 0),
+            Kind::LeftTurn | Kind::RightTurn => (20, u32::MAX, 0),
+            Kind::StairsDown => (5, 5, 0),
+            Kind::RoomCrossing => (10, 6, 0),
+            Kind::PrisonHall => (5, 5, 0),
+            Kind::Library => (10, 2, 5),
+            Kind::PortalRoom => (20, 1, 6),
+        }
+    }
+
+    fn id(self) -> &'static str {
+        match self {
+            Kind::Start => "SHStart",
+            Kind::Straight => "SHS",
+            Kind::LeftTurn => "SHLT",
+            Kind::RightTurn => "SHRT",
+            Kind::StairsDown => "SHSSD",
+            Kind::RoomCrossing => "SHRC",
+            Kind::PrisonHall => "SHPH",
+            Kind::Library => "SHLi",
+            Kind::PortalRoom => "SHPR",
+        }
+    }
+
+    /// Returns the width, height and depth of the piece, and the
+    /// local position of its entrance relative to the box corner.
+    fn shape(self) -> ((i32, i32, i32), (i32, i32)) {
+        match self {
+            Kind::Start => ((5, 11, 5), (2, 7)),
+            Kind::Straight => ((5, 5, 7), (2, 1)),
+            Kind::LeftTurn | Kind::RightTurn => ((5, 5, 5), (2, 1)),
+            Kind::StairsDown => ((5, 11, 8), (2, 7)),
+            Kind::RoomCrossing => ((11, 7, 11), (5, 1)),
+            Kind::PrisonHall => ((9, 5, 11), (2, 1)),
+            Kind::Library => ((14, 6, 15), (4, 1)),
+            Kind::PortalRoom => ((11, 8, 16), (5, 1)),
+        }
+    }
+
+    /// Creates the bounding box of a piece whose
+    /// entrance is at the given position.
+    fn bounding_box(self, x: i32, y: i32, z: i32, orientation: Orientation) -> StructureBox {
+        let ((width, height, depth), (entrance_x, entrance_y)) = self.shape();
+        oriented_box(
+            x,
+            y,
+            z,
+            (-entrance_x, -entrance_y, 0),
+            width,
+            height,
+            depth,
+            orientation,
+        )
+    }
+}
+
+/// Picks a random piece which fits at `exit`
+/// without intersecting existing pieces.
+fn random_piece(
+    exit: &Exit,
+    pieces: &[StrongholdPiece],
+    counts: &[u32],
+    rng: &mut XorShiftRng,
+) -> Option<StrongholdPiece> {
+    let available: Vec<Kind> = Kind::ALL
+        .iter()
+        .copied()
+        .filter(|kind| {
+            let (weight, limit, min_depth) = kind.frequency();
+            weight > 0 && counts[kind.index()] < limit && exit.depth >= min_depth
+        })
+        .collect();
+    let total_weight: u32 = available.iter().map(|kind| kind.frequency().0).sum();
+
+    for _ in 0..5 {
+        let mut roll = rng.gen_range(0, total_weight);
+        let kind = available
+            .iter()
+            .copied()
+            .find(|kind| {
+                let weight = kind.frequency().0;
+                if roll < weight {
+                    true
+                } else {
+                    roll -= weight;
+                    false
+                }
+            })
+            .unwrap_or(Kind::Straight);
+
+        let bounding_box = kind.bounding_box(exit.x, exit.y, exit.z, exit.orientation);
+        if bounding_box.min_y > 0
+            && !pieces
+                .iter()
+                .any(|piece| piece.bounding_box.intersects(&bounding_box))
+        {
+            return Some(StrongholdPiece {
+                kind,
+                bounding_box,
+                orientation: exit.orientation,
+                depth: exit.depth,
+            });
+        }
+    }
+    None
+}
+
+struct StrongholdPiece {
+    kind: Kind,
+    bounding_box: StructureBox,
+    orientation: Orientation,
+    depth: i32,
+}
+
+impl StrongholdPiece {
+    /// Returns the doorways new pieces may be attached to.
+    fn exits(&self) -> Vec<Exit> {
+        let ((width, _, depth), (entrance_x, _)) = self.kind.shape();
+        let exit = |x, y, z, direction_x, direction_z| {
+            let (x, y, z) = to_world(&self.bounding_box, self.orientation, x, y, z);
+            Exit {
+                x,
+                y,
+                z,
+                orientation: world_direction(
+                    &self.bounding_box,
+                    self.orientation,
+                    direction_x,
+                    direction_z,
+                ),
+                depth: self.depth + 1,
+            }
+        };
+        let forward = exit(entrance_x, 1, depth, 0, 1);
+        let left = exit(-1, 1, depth / 2, -1, 0);
+        let right = exit(width, 1, depth / 2, 1, 0);
+        match self.kind {
+            Kind::Start | Kind::StairsDown | Kind::PrisonHall => vec![forward],
+            Kind::Straight | Kind::RoomCrossing => vec![forward, left, right],
+            Kind::LeftTurn => vec![left],
+            Kind::RightTurn => vec![right],
+            Kind::Library | Kind::PortalRoom => Vec::new(),
+        }
+    }
+}
+
+/// Picks one of the stone brick variants making up stronghold walls.
+fn random_bricks(rng: &mut XorShiftRng) -> BlockId {
+    let roll = rng.gen::<f64>();
+    if roll < 0.2 {
+        BlockId::cracked_stone_bricks()
+    } else if roll < 0.5 {
+        BlockId::mossy_stone_bricks()
+    } else {
+        BlockId::stone_bricks()
+    }
+}
+
+impl Piece for StrongholdPiece {
+    fn bounding_box(&self) -> StructureBox {
+        self.bounding_box
+    }
+
+    fn place(&self, chunk: &mut Chunk, _context: &dyn StructureContext, rng: &mut XorShiftRng) {
+        let mut writer = PieceWriter::new(chunk, self.bounding_box, self.orientation);
+        let ((width, height, depth), (entrance_x, entrance_y)) = self.kind.shape();
+        let (max_x, max_y, max_z) = (width - 1, height - 1, depth - 1);
+        let air = BlockId::air();
+
+        // Walls of randomly weathered bricks around an empty inside.
+        for y in 0..=max_y {
+            for x in 0..=max_x {
+                for z in 0..=max_z {
+                    let is_edge =
+                        y == 0 || y == max_y || x == 0 || x == max_x || z == 0 || z == max_z;
+                    let block = if is_edge { random_bricks(rng) } else { air };
+                    writer.set(x, y, z, block);
+                }
+            }
+        }
+
+        // The doorway into this piece, which also
+        // opens the wall of the previous piece.
+        if self.kind != Kind::Start {
+            writer.fill(
+                (entrance_x - 1, entrance_y, -1),
+                (entrance_x + 1, entrance_y + 2, 0),
+                air,
+            );
+        }
+
+        match self.kind {
+            Kind::Start | Kind::StairsDown => {
+                let steps: Vec<(i32, i32)> = if self.kind == Kind::Start {
+                    vec![(1, 1), (2, 1), (3, 1), (3, 2), (3, 3), (2, 3), (1, 3)]
+                } else {
+                    (1..=6).map(|z| (2, z)).collect()
+                };
+                writer.fill(
+                    (1, 1, 1),
+                    (max_x - 1, 6, max_z - 1),
+                    BlockId::stone_bricks(),
+                );
+                for (i, &(x, z)) in steps.iter().enumerate() {
+                    let floor = 6 - i as i32;
+                    writer.fill((x, floor + 1, z), (x, floor + 3, z), air);
+                    if self.kind == Kind::StairsDown {
+                        writer.fill((1, floor + 1, z), (3, floor + 3, z), air);
+                    }
+                }
+                writer.fill((1, 7, 1), (max_x - 1, max_y - 1, max_z - 1), air);
+                if self.kind == Kind::Start {
+                    writer.fill((2, 7, 2), (2, max_y - 1, 2), BlockId::stone_bricks());
+                }
+                writer.fill((1, 1, max_z - 1), (3, 3, max_z - 1), air);
+            }
+            Kind::Straight | Kind::LeftTurn | Kind::RightTurn => {
+                if rng.gen_range(0, 4) == 0 {
+                    writer.set(1, 2, max_z / 2, BlockId::torch());
+                }
+            }
+            Kind::RoomCrossing => {
+                writer.fill((5, 1, 5), (5, max_y - 1, 5), BlockId::stone_bricks());
+                for &(x, z) in &[(4, 5), (6, 5), (5, 4), (5, 6)] {
+                    writer.set(x, 3, z, BlockId::torch());
+                }
+            }
+            Kind::PrisonHall => {
+                writer.fill((4, 1, 1), (4, 3, max_z - 1), BlockId::iron_bars());
+                for z in (4..max_z).step_by(4) {
+                    writer.fill((5, 1, z), (max_x - 1, 3, z), BlockId::stone_bricks());
+                }
+            }
+            Kind::Library => {
+                for z in 1..max_z {
+                    for y in 1..max_y {
+                        writer.set(1, y, z, BlockId::bookshelf());
+                        writer.set(max_x - 1, y, z, BlockId::bookshelf());
+                    }
+                }
+                for x in (3..max_x - 2).step_by(3) {
+                    writer.fill((x, 1, 3), (x, 3, max_z - 3), BlockId::bookshelf());
+                }
+                writer.fill((3, 1, 1), (max_x - 3, 3, 1), air);
+            }
+            Kind::PortalRoom => {
+                // Stairs up to a platform holding the portal
+                // frame, with a pool of lava beneath it.
+                let stairs =
+                    BlockId::stone_brick_stairs().with_facing_cardinal(writer.facing(0, 1));
+                for step in 0..3 {
+                    writer.fill((4, 1 + step, 4 + step), (6, 1 + step, 4 + step), stairs);
+                    writer.fill(
+                        (4, 1, 4 + step),
+                        (6, step, 4 + step),
+                        BlockId::stone_bricks(),
+                    );
+                }
+                writer.fill((1, 1, 7), (9, 2, 13), BlockId::stone_bricks());
+                writer.fill((4, 2, 9), (6, 2, 11), BlockId::lava());
+
+                let frames: [(i32, i32, FacingCardinal); 12] = [
+                    (4, 8, writer.facing(0, 1)),
+                    (5, 8, writer.facing(0, 1)),
+                    (6, 8, writer.facing(0, 1)),
+                    (4, 12, writer.facing(0, -1)),
+                    (5, 12, writer.facing(0, -1)),
+                    (6, 12, writer.facing(0, -1)),
+                    (3, 9, writer.facing(1, 0)),
+                    (3, 10, writer.facing(1, 0)),
+                    (3, 11, writer.facing(1, 0)),
+                    (7, 9, writer.facing(-1, 0)),
+                    (7, 10, writer.facing(-1, 0)),
+                    (7, 11, writer.facing(-1, 0)),
+                ];
+                for &(x, z, facing) in &frames {
+                    let frame = BlockId::end_portal_frame()
+                        .with_facing_cardinal(facing)
+                        .with_eye(rng.gen::<f64>() < 0.1);
+                    writer.set(x, 3, z, frame);
+                }
+                // A silverfish spawner behind the portal.
+                writer.set(5, 3, 13, BlockId::spawner());
+            }
+        }
+    }
+
+    fn spawner_mob(&self) -> Option<EntityKind> {
+        if self.kind == Kind::PortalRoom {
+            Some(EntityKind::Silverfish)
+        } else {
+            None
+        }
+    }
+
+    fn to_saved(&self) -> StructurePiece {
+        saved_piece(
+            self.kind.id(),
+            self.bounding_box,
+            self.orientation,
+            self.depth,
+        )
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use base::{anvil::block_entity::BlockEntityKind, Biome};
+    use rand::SeedableRng;
+
+    struct Flat;
+
+    impl StructureContext for Flat {
+        fn biome_at(&self, _x: i32, _z: i32) -> Biome {
+            Biome::Plains
+        }
+
+        fn surface_height(&self, _x: i32, _z: i32) -> i32 {
+            64
+        }
+    }
+
+    #[test]
+    fn portal_room_has_silverfish_spawner() {
+        let orientation = Orientation::South;
+        let piece = StrongholdPiece {
+            kind: Kind::PortalRoom,
+            bounding_box: Kind::PortalRoom.bounding_box(5, 20, 5, orientation),
+            orientation,
+            depth: 0,
+        };
+        assert_eq!(piece.spawner_mob(), Some(EntityKind::Silverfish));
+
+        let mut block_entities = Vec::new();
+        for x in -1..=1 {
+            for z in -1..=1 {
+                let mut chunk = Chunk::new(ChunkPosition::new(x, z));
+                piece.place(&mut chunk, &Flat, &mut XorShiftRng::seed_from_u64(0));
+                block_entities.extend(crate::spawner_block_entities(
+                    &chunk,
+                    &piece.bounding_box,
+                    EntityKind::Silverfish,
+                ));
+            }
+        }
+
+        assert_eq!(block_entities.len(), 1);
+        match &block_entities[0].kind {
+            BlockEntityKind::MobSpawner { spawn_data, .. } => {
+                assert_eq!(spawn_data.as_ref().unwrap().id, "minecraft:silverfish");
+            }
+            kind => panic!("expected a mob spawner, got {:?}", kind),
+        }
+    }
+}
@@ -0,0 +1,345 @@
+//! Structure templates, as saved by structure blocks,
+//! and their rotation.
+
+use std::collections::BTreeMap;
+
+use base::{chunk::StructureBox, BlockId};
+use rand::{seq::SliceRandom, Rng};
+
+/// A rotation of a template around its origin,
+/// clockwise when seen from above.
+#[derive(Copy, Clone, Debug, PartialEq, Eq)]
+pub enum Rotation {
+    None,
+    Clockwise90,
+    Clockwise180,
+    CounterClockwise90,
+}
+
+impl Rotation {
+    pub const ALL: [Rotation; 4] = [
+        Rotation::None,
+        Rotation::Clockwise90,
+        Rotation::Clockwise180,
+        Rotation::CounterClockwise90,
+    ];
+
+    pub fn random(rng: &mut impl Rng) -> Self {
+        Self::ALL[rng.gen_range(0, 4)]
+    }
+
+    /// Returns all rotations in a random order.
+    pub fn shuffled(rng: &mut impl Rng) -> [Rotation; 4] {
+        let mut rotations = Self::ALL;
+        rotations.shuffle(rng);
+        rotations
+    }
+
+    /// The name vanilla saves as the `rotation` tag of pieces.
+    pub fn name(self) -> &'static str {
+        match self {
+            Rotation::None => "NONE",
+            Rotation::Clockwise90 => "CLOCKWISE_90",
+            Rotation::Clockwise180 => "CLOCKWISE_180",
+            Rotation::CounterClockwise90 => "COUNTERCLOCKWISE_90",
+        }
+    }
+
+    /// The number of clockwise quarter turns.
+    fn turns(self) -> usize {
+        match self {
+            Rotation::None => 0,
+            Rotation::Clockwise90 => 1,
+            Rotation::Clockwise180 => 2,
+            Rotation::CounterClockwise90 => 3,
+        }
+    }
+
+    /// Rotates the horizontal position `(x, z)` around the origin.
+    pub fn rotate(self, x: i32, z: i32) -> (i32, i32) {
+        match self {
+            Rotation::None => (x, z),
+            Rotation::Clockwise90 => (-z, x),
+            Rotation::Clockwise180 => (-x, -z),
+            Rotation::CounterClockwise90 => (z, -x),
+        }
+    }
+}
+
+/// One of the six directions a jigsaw block may face.
+#[derive(Copy, Clone, Debug, PartialEq, Eq)]
+pub enum Direction {
+    Down,
+    Up,
+    North,
+    East,
+    South,
+    West,
+}
+
+impl Direction {
+    /// Parses a lowercase direction name, e.g. `north`.
+    pub fn from_name(name: &str) -> Option<Self> {
+        Some(match name {
+            "down" => Direction::Down,
+            "up" => Direction::Up,
+            "north" => Direction::North,
+            "east" => Direction::East,
+            "south" => Direction::South,
+            "west" => Direction::West,
+            _ => return None,
+        })
+    }
+
+    pub fn opposite(self) -> Self {
+        match self {
+            Direction::Down => Direction::Up,
+            Direction::Up => Direction::Down,
+            Direction::North => Direction::South,
+            Direction::East => Direction::West,
+            Direction::South => Direction::North,
+            Direction::West => Direction::East,
+        }
+    }
+
+    /// Gets the offset of the neighbouring block in this direction.
+    pub fn offset(self) -> (i32, i32, i32) {
+        match self {
+            Direction::Down => (0, -1, 0),
+            Direction::Up => (0, 1, 0),
+            Direction::North => (0, 0, -1),
+            Direction::East => (1, 0, 0),
+            Direction::South => (0, 0, 1),
+            Direction::West => (-1, 0, 0),
+        }
+    }
+
+    /// Rotates horizontal directions; vertical ones are unchanged.
+    pub fn rotate(self, rotation: Rotation) -> Self {
+        let mut direction = self;
+        for _ in 0..rotation.turns() {
+            direction = match direction {
+                Direction::North => Direction::East,
+                Direction::East => Direction::South,
+                Direction::South => Direction::West,
+                Direction::West => Direction::North,
+                vertical => vertical,
+            };
+        }
+        direction
+    }
+}
+
+/// A jigsaw block, which connects a template to
+/// the templates of another pool.
+#[derive(Clone, Debug)]
+pub struct Jigsaw {
+    /// The position relative to the template origin.
+    pub pos: (i32, i32, i32),
+    /// The direction pieces are attached in.
+    pub front: Direction,
+    /// The direction the top of the jigsaw faces.
+    pub top: Direction,
+    /// The name other jigsaws target to attach to this one.
+    pub name: String,
+    /// The pool of pieces attached to this jigsaw.
+    pub pool: String,
+    /// The name of the jigsaw an attached piece connects with.
+    pub target: String,
+    /// The block which replaces the jigsaw once placed.
+    pub final_state: BlockId,
+    /// Whether attached pieces may be rolled around the
+    /// front direction, rather than aligned with `top`.
+    pub rollable: bool,
+}
+
+/// A block of a template.
+#[derive(Copy, Clone, Debug)]
+pub struct TemplateBlock {
+    /// The position relative to the template origin.
+    pub pos: (i32, i32, i32),
+    pub block: BlockId,
+}
+
+/// A structure template, made up of blocks and
+/// the jigsaws which connect it to other templates.
+pub struct Template {
+    size: (i32, i32, i32),
+    blocks: Vec<TemplateBlock>,
+    /// The states of `blocks`, rotated by each rotation.
+    rotated_states: [Vec<BlockId>; 4],
+    jigsaws: Vec<Jigsaw>,
+}
+
+impl Template {
+    /// Creates a template of the given size. Jigsaws
+    /// should not be included in `blocks`.
+    pub fn new(size: (i32, i32, i32), blocks: Vec<TemplateBlock>, jigsaws: Vec<Jigsaw>) -> Self {
+        let rotated = |rotation| {
+            blocks
+                .iter()
+                .map(|block| rotate_block(block.block, rotation))
+                .collect::<Vec<_>>()
+        };
+        let rotated_states = [
+            rotated(Rotation::None),
+            rotated(Rotation::Clockwise90),
+            rotated(Rotation::Clockwise180),
+            rotated(Rotation::CounterClockwise90),
+        ];
+        Self {
+            size,
+            blocks,
+            rotated_states,
+            jigsaws,
+        }
+    }
+
+    /// Gets the box enclosing the template placed
+    /// at `origin` with the given rotation.
+    pub fn bounding_box(&self, origin: (i32, i32, i32), rotation: Rotation) -> StructureBox {
+        let (size_x, size_y, size_z) = self.size;
+        let (corner_x, corner_z) = rotation.rotate(size_x - 1, size_z - 1);
+        StructureBox {
+            min_x: origin.0 + corner_x.min(0),
+            min_y: origin.1,
+            min_z: origin.2 + corner_z.min(0),
+            max_x: origin.0 + corner_x.max(0),
+            max_y: origin.1 + size_y - 1,
+            max_z: origin.2 + corner_z.max(0),
+        }
+    }
+
+    /// Returns the blocks of the template with the given
+    /// rotation, at positions relative to the origin.
+    pub fn blocks(
+        &self,
+        rotation: Rotation,
+    ) -> impl Iterator<Item = ((i32, i32, i32), BlockId)> + '_ {
+        self.blocks
+            .iter()
+            .zip(&self.rotated_states[rotation.turns()])
+            .map(move |(block, &state)| (rotate_pos(block.pos, rotation), state))
+    }
+
+    /// Returns the jigsaws of the template with the given
+    /// rotation, at positions relative to the origin.
+    pub fn jigsaws(&self, rotation: Rotation) -> impl Iterator<Item = Jigsaw> + '_ {
+        self.jigsaws.iter().map(move |jigsaw| Jigsaw {
+            pos: rotate_pos(jigsaw.pos, rotation),
+            front: jigsaw.front.rotate(rotation),
+            top: jigsaw.top.rotate(rotation),
+            final_state: rotate_block(jigsaw.final_state, rotation),
+            ..jigsaw.clone()
+        })
+    }
+}
+
+fn rotate_pos((x, y, z): (i32, i32, i32), rotation: Rotation) -> (i32, i32, i32) {
+    let (x, z) = rotation.rotate(x, z);
+    (x, y, z)
+}
+
+/// Rotates the name of a horizontal direction.
+fn rotate_direction_name(name: &str, rotation: Rotation) -> Option<&'static str> {
+    let direction = match Direction::from_name(name)? {
+        Direction::Up | Direction::Down => return None,
+        horizontal => horizontal.rotate(rotation),
+    };
+    Some(match direction {
+        Direction::North => "north",
+        Direction::East => "east",
+        Direction::South => "south",
+        _ => "west",
+    })
+}
+
+/// Rotates rail shapes such as `north_east` and `ascending_west`.
+fn rotate_shape(shape: &str, rotation: Rotation) -> Option<String> {
+    let mut parts: Vec<&str> = Vec::new();
+    for part in shape.split('_') {
+        match part {
+            "ascending" => parts.push(part),
+            _ => parts.push(rotate_direction_name(part, rotation)?),
+        }
+    }
+    // Vanilla names corners north or south first,
+    // and straight rails north_south or east_west.
+    if let [a, b] = parts[..] {
+        if a != "ascending" && (b == "north" || b == "south" || (a == "west" && b == "east")) {
+            parts.swap(0, 1);
+        }
+    }
+    Some(parts.join("_"))
+}
+
+/// Rotates the properties of a block state,
+/// such as `facing`, `axis` and fence connections.
+pub fn rotate_block(block: BlockId, rotation: Rotation) -> BlockId {
+    if rotation == Rotation::None {
+        return block;
+    }
+    let properties = block.to_properties_map();
+    if properties.is_empty() {
+        return block;
+    }
+
+    let mut rotated = BTreeMap::new();
+    for (key, value) in properties {
+        let value = match key {
+            "facing" => rotate_direction_name(value, rotation)
+                .unwrap_or(value)
+                .to_owned(),
+            "axis" if rotation.turns() % 2 == 1 => match value {
+                "x" => "z".to_owned(),
+                "z" => "x".to_owned(),
+                other => other.to_owned(),
+            },
+            "rotation" => match value.parse::<usize>() {
+                Ok(angle) => ((angle + 4 * rotation.turns()) % 16).to_string(),
+                Err(_) => value.to_owned(),
+            },
+            "shape" => rotate_shape(value, rotation).unwrap_or_else(|| value.to_owned()),
+            _ => value.to_owned(),
+        };
+        let key = rotate_direction_name(key, rotation).unwrap_or(key);
+        rotated.insert(key.to_owned(), value);
+    }
+    BlockId::from_identifier_and_properties(block.identifier(), &rotated).unwrap_or(block)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use base::{FacingCardinal, RailShape};
+
+    #[test]
+    fn rotate_blocks() {
+        let stairs = BlockId::oak_stairs().with_facing_cardinal(FacingCardinal::North);
+        assert_eq!(
+            rotate_block(stairs, Rotation::Clockwise90),
+            stairs.with_facing_cardinal(FacingCardinal::East)
+        );
+
+        let rail = BlockId::rail().with_rail_shape(RailShape::NorthEast);
+        assert_eq!(
+            rotate_block(rail, Rotation::Clockwise90),
+            rail.with_rail_shape(RailShape::SouthEast)
+        );
+        assert_eq!(
+            rotate_block(rail, Rotation::CounterClockwise90),
+            rail.with_rail_shape(RailShape::NorthWest)
+        );
+    }
+
+    #[test]
+    fn rotated_bounding_box() {
+        let template = Template::new((3, 2, 5), Vec::new(), Vec::new());
+        for &rotation in &Rotation::ALL {
+            let bounding_box = template.bounding_box((10, 0, 10), rotation);
+            let (x, z) = rotation.rotate(2, 4);
+            assert!(bounding_box.contains(10, 0, 10));
+            assert!(bounding_box.contains(10 + x, 1, 10 + z));
+        }
+    }
+}
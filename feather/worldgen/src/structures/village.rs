@@ -0,0 +1,66 @@
+use std::sync::Arc;
+
+use base::{Biome, ChunkPosition};
+use rand_xorshift::XorShiftRng;
+
+use super::{
+    jigsaw::{self, JigsawPools, MAX_DISTANCE},
+    Piece, Structure, StructureContext, StructurePlacement,
+};
+
+/// The number of pools a village grows through from its town center.
+const MAX_DEPTH: i32 = 6;
+
+/// Villages, assembled from the jigsaw templates of
+/// the `village` pools in data packs.
+pub struct Village {
+    pools: Arc<JigsawPools>,
+    placement: StructurePlacement,
+}
+
+impl Village {
+    pub fn new(pools: Arc<JigsawPools>) -> Self {
+        Self {
+            pools,
+            placement: StructurePlacement::Grid {
+                spacing: 32,
+                separation: 8,
+                salt: 10_387_312,
+                frequency: 1.0,
+            },
+        }
+    }
+}
+
+impl Structure for Village {
+    fn name(&self) -> &'static str {
+        "village"
+    }
+
+    fn placement(&self) -> &StructurePlacement {
+        &self.placement
+    }
+
+    fn max_radius(&self) -> i32 {
+        MAX_DISTANCE / 16 + 1
+    }
+
+    fn assemble(
+        &self,
+        start: ChunkPosition,
+        context: &dyn StructureContext,
+        rng: &mut XorShiftRng,
+    ) -> Vec<Box<dyn Piece>> {
+        let (x, z) = (start.x * 16, start.z * 16);
+        let kind = match context.biome_at(x + 8, z + 8) {
+            Biome::Plains => "plains",
+            Biome::Desert => "desert",
+            Biome::Savanna => "savanna",
+            Biome::Taiga => "taiga",
+            Biome::SnowyTundra => "snowy",
+            _ => return Vec::new(),
+        };
+        let start_pool = format!("minecraft:village/{}/town_centers", kind);
+        jigsaw::assemble(&self.pools, &start_pool, MAX_DEPTH, (x, z), context, rng)
+    }
+}
@@ -0,0 +1,80 @@
+use std::{
+    collections::{HashMap, VecDeque},
+    sync::{Arc, Mutex},
+};
+
+use base::{Chunk, ChunkPosition};
+
+/// Caches the terrain of recently generated chunks,
+/// or other data generated for each chunk.
+///
+/// Populating a chunk requires the terrain of the surrounding
+/// chunks, so without this cache the terrain of each chunk
+/// would be generated many times over.
+pub struct TerrainCache<T = Chunk> {
+    capacity: usize,
+    inner: Mutex<Inner<T>>,
+}
+
+struct Inner<T> {
+    chunks: HashMap<ChunkPosition, Arc<T>>,
+    /// Cached chunks, oldest first.
+    order: VecDeque<ChunkPosition>,
+}
+
+impl<T> TerrainCache<T> {
+    pub fn new(capacity: usize) -> Self {
+        Self {
+            capacity,
+            inner: Mutex::new(Inner {
+                chunks: HashMap::new(),
+                order: VecDeque::new(),
+            }),
+        }
+    }
+
+    /// Gets the data of the given chunk, generating
+    /// it with `generate` if it's not cached.
+    pub fn get_or_generate(&self, pos: ChunkPosition, generate: impl FnOnce() -> T) -> Arc<T> {
+        if let Some(chunk) = self.inner.lock().unwrap().chunks.get(&pos) {
+            return Arc::clone(chunk);
+        }
+
+        // Generate without holding the lock so other
+        // threads can generate chunks in parallel.
+        let chunk = Arc::new(generate());
+
+        let mut inner = self.inner.lock().unwrap();
+        if let Some(existing) = inner.chunks.get(&pos) {
+            return Arc::clone(existing);
+        }
+        inner.chunks.insert(pos, Arc::clone(&chunk));
+        inner.order.push_back(pos);
+        while inner.order.len() > self.capacity {
+            if let Some(oldest) = inner.order.pop_front() {
+                inner.chunks.remove(&oldest);
+            }
+        }
+        chunk
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn evicts_oldest_chunks() {
+        let cache = TerrainCache::new(2);
+        let mut generated = 0;
+        for &x in &[0, 1, 0, 2, 0, 1] {
+            cache.get_or_generate(ChunkPosition::new(x, 0), || {
+                generated += 1;
+                Chunk::new(ChunkPosition::new(x, 0))
+            });
+        }
+        // 0 and 1 are generated, 0 is cached, 2 evicts 0,
+        // then 0 evicts 1 and 1 evicts 2.
+        assert_eq!(generated, 5);
+    }
+}
@@ -0,0 +1,255 @@
+//! Numeric biome IDs and the biome properties
+//! used by the vanilla layer stack and terrain noise.
+
+use base::Biome;
+
+pub const OCEAN: i32 = 0;
+pub const PLAINS: i32 = 1;
+pub const DESERT: i32 = 2;
+pub const MOUNTAINS: i32 = 3;
+pub const FOREST: i32 = 4;
+pub const TAIGA: i32 = 5;
+pub const SWAMP: i32 = 6;
+pub const RIVER: i32 = 7;
+pub const FROZEN_OCEAN: i32 = 10;
+pub const FROZEN_RIVER: i32 = 11;
+pub const SNOWY_TUNDRA: i32 = 12;
+pub const SNOWY_MOUNTAINS: i32 = 13;
+pub const MUSHROOM_FIELDS: i32 = 14;
+pub const MUSHROOM_FIELD_SHORE: i32 = 15;
+pub const BEACH: i32 = 16;
+pub const DESERT_HILLS: i32 = 17;
+pub const WOODED_HILLS: i32 = 18;
+pub const TAIGA_HILLS: i32 = 19;
+pub const MOUNTAIN_EDGE: i32 = 20;
+pub const JUNGLE: i32 = 21;
+pub const JUNGLE_HILLS: i32 = 22;
+pub const JUNGLE_EDGE: i32 = 23;
+pub const DEEP_OCEAN: i32 = 24;
+pub const STONE_SHORE: i32 = 25;
+pub const SNOWY_BEACH: i32 = 26;
+pub const BIRCH_FOREST: i32 = 27;
+pub const BIRCH_FOREST_HILLS: i32 = 28;
+pub const DARK_FOREST: i32 = 29;
+pub const SNOWY_TAIGA: i32 = 30;
+pub const SNOWY_TAIGA_HILLS: i32 = 31;
+pub const GIANT_TREE_TAIGA: i32 = 32;
+pub const GIANT_TREE_TAIGA_HILLS: i32 = 33;
+pub const WOODED_MOUNTAINS: i32 = 34;
+pub const SAVANNA: i32 = 35;
+pub const SAVANNA_PLATEAU: i32 = 36;
+pub const BADLANDS: i32 = 37;
+pub const WOODED_BADLANDS_PLATEAU: i32 = 38;
+pub const BADLANDS_PLATEAU: i32 = 39;
+pub const WARM_OCEAN: i32 = 44;
+pub const LUKEWARM_OCEAN: i32 = 45;
+pub const COLD_OCEAN: i32 = 46;
+pub const DEEP_WARM_OCEAN: i32 = 47;
+pub const DEEP_LUKEWARM_OCEAN: i32 = 48;
+pub const DEEP_COLD_OCEAN: i32 = 49;
+pub const DEEP_FROZEN_OCEAN: i32 = 50;
+pub const SUNFLOWER_PLAINS: i32 = 129;
+pub const ICE_SPIKES: i32 = 140;
+pub const SNOWY_TAIGA_MOUNTAINS: i32 = 158;
+pub const ERODED_BADLANDS: i32 = 165;
+pub const MODIFIED_WOODED_BADLANDS_PLATEAU: i32 = 166;
+pub const MODIFIED_BADLANDS_PLATEAU: i32 = 167;
+pub const BAMBOO_JUNGLE: i32 = 168;
+pub const BAMBOO_JUNGLE_HILLS: i32 = 169;
+
+/// Vanilla's biome categories, used to decide
+/// whether two biomes are similar.
+#[derive(Copy, Clone, Debug, PartialEq, Eq)]
+pub enum Category {
+    None,
+    Taiga,
+    ExtremeHills,
+    Jungle,
+    Mesa,
+    Plains,
+    Savanna,
+    Icy,
+    Beach,
+    Forest,
+    Ocean,
+    Desert,
+    River,
+    Swamp,
+    Mushroom,
+}
+
+pub fn category(id: i32) -> Option<Category> {
+    use Category::*;
+    let category = match id {
+        OCEAN | FROZEN_OCEAN | DEEP_OCEAN | WARM_OCEAN..=DEEP_FROZEN_OCEAN => Ocean,
+        PLAINS | SUNFLOWER_PLAINS => Plains,
+        DESERT | DESERT_HILLS | 130 => Desert,
+        MOUNTAINS | MOUNTAIN_EDGE | WOODED_MOUNTAINS | 131 | 162 => ExtremeHills,
+        FOREST | WOODED_HILLS | BIRCH_FOREST | BIRCH_FOREST_HILLS | DARK_FOREST | 132 | 155
+        | 156 | 157 => Forest,
+        TAIGA
+        | TAIGA_HILLS
+        | SNOWY_TAIGA
+        | SNOWY_TAIGA_HILLS
+        | GIANT_TREE_TAIGA
+        | GIANT_TREE_TAIGA_HILLS
+        | 133
+        | SNOWY_TAIGA_MOUNTAINS
+        | 160
+        | 161 => Taiga,
+        SWAMP | 134 => Swamp,
+        RIVER | FROZEN_RIVER => River,
+        SNOWY_TUNDRA | SNOWY_MOUNTAINS | ICE_SPIKES => Icy,
+        MUSHROOM_FIELDS | MUSHROOM_FIELD_SHORE => Mushroom,
+        BEACH | SNOWY_BEACH => Beach,
+        JUNGLE | JUNGLE_HILLS | JUNGLE_EDGE | 149 | 151 | BAMBOO_JUNGLE | BAMBOO_JUNGLE_HILLS => {
+            Jungle
+        }
+        STONE_SHORE => None,
+        SAVANNA | SAVANNA_PLATEAU | 163 | 164 => Savanna,
+        BADLANDS
+        | WOODED_BADLANDS_PLATEAU
+        | BADLANDS_PLATEAU
+        | ERODED_BADLANDS
+        | MODIFIED_WOODED_BADLANDS_PLATEAU
+        | MODIFIED_BADLANDS_PLATEAU => Mesa,
+        _ => return Option::None,
+    };
+    Some(category)
+}
+
+pub fn is_shallow_ocean(id: i32) -> bool {
+    matches!(
+        id,
+        WARM_OCEAN | LUKEWARM_OCEAN | OCEAN | COLD_OCEAN | FROZEN_OCEAN
+    )
+}
+
+pub fn is_ocean(id: i32) -> bool {
+    is_shallow_ocean(id)
+        || matches!(
+            id,
+            DEEP_WARM_OCEAN
+                | DEEP_LUKEWARM_OCEAN
+                | DEEP_OCEAN
+                | DEEP_COLD_OCEAN
+                | DEEP_FROZEN_OCEAN
+        )
+}
+
+pub fn is_mesa(id: i32) -> bool {
+    matches!(
+        id,
+        BADLANDS
+            | WOODED_BADLANDS_PLATEAU
+            | BADLANDS_PLATEAU
+            | ERODED_BADLANDS
+            | MODIFIED_WOODED_BADLANDS_PLATEAU
+            | MODIFIED_BADLANDS_PLATEAU
+    )
+}
+
+/// Returns whether snow falls in the given biome.
+pub fn is_snowy(id: i32) -> bool {
+    matches!(
+        id,
+        SNOWY_BEACH
+            | FROZEN_RIVER
+            | SNOWY_TUNDRA
+            | SNOWY_MOUNTAINS
+            | ICE_SPIKES
+            | SNOWY_TAIGA
+            | SNOWY_TAIGA_HILLS
+            | SNOWY_TAIGA_MOUNTAINS
+            | FROZEN_OCEAN
+    )
+}
+
+pub fn are_similar(a: i32, b: i32) -> bool {
+    if a == b {
+        return true;
+    }
+    if a == WOODED_BADLANDS_PLATEAU || a == BADLANDS_PLATEAU {
+        return b == WOODED_BADLANDS_PLATEAU || b == BADLANDS_PLATEAU;
+    }
+    match (category(a), category(b)) {
+        (Some(a), Some(b)) => a != Category::None && a == b,
+        _ => false,
+    }
+}
+
+/// Returns the mutated ("M") variant of a biome, if it has one.
+pub fn mutation(id: i32) -> Option<i32> {
+    match id {
+        PLAINS
+        | DESERT
+        | MOUNTAINS
+        | FOREST
+        | TAIGA
+        | SWAMP
+        | JUNGLE
+        | JUNGLE_EDGE
+        | BIRCH_FOREST
+        | BIRCH_FOREST_HILLS
+        | DARK_FOREST
+        | SNOWY_TAIGA
+        | GIANT_TREE_TAIGA
+        | GIANT_TREE_TAIGA_HILLS
+        | WOODED_MOUNTAINS
+        | SAVANNA
+        | SAVANNA_PLATEAU
+        | BADLANDS
+        | WOODED_BADLANDS_PLATEAU
+        | BADLANDS_PLATEAU => Some(id + 128),
+        SNOWY_TUNDRA => Some(ICE_SPIKES),
+        _ => None,
+    }
+}
+
+/// Returns the depth and scale of a biome, which
+/// determine the height and roughness of its terrain.
+pub fn depth_and_scale(id: i32) -> (f32, f32) {
+    match id {
+        OCEAN | FROZEN_OCEAN | WARM_OCEAN | LUKEWARM_OCEAN | COLD_OCEAN => (-1.0, 0.1),
+        DEEP_OCEAN | DEEP_WARM_OCEAN | DEEP_LUKEWARM_OCEAN | DEEP_COLD_OCEAN
+        | DEEP_FROZEN_OCEAN => (-1.8, 0.1),
+        PLAINS | DESERT | SNOWY_TUNDRA | SAVANNA | SUNFLOWER_PLAINS => (0.125, 0.05),
+        MOUNTAINS | WOODED_MOUNTAINS | 131 | 162 => (1.0, 0.5),
+        FOREST | JUNGLE | JUNGLE_EDGE | BIRCH_FOREST | DARK_FOREST | BADLANDS | ERODED_BADLANDS
+        | BAMBOO_JUNGLE => (0.1, 0.2),
+        TAIGA | SNOWY_TAIGA | GIANT_TREE_TAIGA | 160 | 161 => (0.2, 0.2),
+        SWAMP => (-0.2, 0.1),
+        RIVER | FROZEN_RIVER => (-0.5, 0.0),
+        SNOWY_MOUNTAINS
+        | DESERT_HILLS
+        | WOODED_HILLS
+        | TAIGA_HILLS
+        | JUNGLE_HILLS
+        | BIRCH_FOREST_HILLS
+        | SNOWY_TAIGA_HILLS
+        | GIANT_TREE_TAIGA_HILLS
+        | MODIFIED_WOODED_BADLANDS_PLATEAU
+        | MODIFIED_BADLANDS_PLATEAU
+        | BAMBOO_JUNGLE_HILLS => (0.45, 0.3),
+        MUSHROOM_FIELDS => (0.2, 0.3),
+        MUSHROOM_FIELD_SHORE | BEACH | SNOWY_BEACH => (0.0, 0.025),
+        MOUNTAIN_EDGE => (0.8, 0.3),
+        STONE_SHORE => (0.1, 0.8),
+        SAVANNA_PLATEAU | WOODED_BADLANDS_PLATEAU | BADLANDS_PLATEAU => (1.5, 0.025),
+        130 => (0.225, 0.25),
+        132 => (0.1, 0.4),
+        133 | SNOWY_TAIGA_MOUNTAINS => (0.3, 0.4),
+        134 => (-0.1, 0.3),
+        ICE_SPIKES => (0.425, 0.45),
+        149 | 151 | 155 | 157 => (0.2, 0.4),
+        156 => (0.55, 0.5),
+        163 => (0.3625, 1.225),
+        164 => (1.05, 1.2125),
+        _ => (0.1, 0.2),
+    }
+}
+
+/// Converts a numeric biome ID into a `Biome`.
+pub fn to_biome(id: i32) -> Biome {
+    Biome::from_id(id as u32).unwrap_or(Biome::Plains)
+}
@@ -0,0 +1,865 @@
+//! Vanilla's biome layer stack.
+//!
+//! Each layer transforms the output of its parent layers, usually
+//! by zooming in or by looking at the neighbours of each cell. The
+//! final layer has a resolution of one cell per 4x4 blocks.
+
+use super::biomes::*;
+use super::noise::PerlinNoise;
+use super::random::{JavaRandom, LayerRandom};
+use std::sync::Arc;
+
+/// A rectangular area of biome IDs.
+#[derive(Debug, Clone)]
+pub struct Area {
+    x: i32,
+    z: i32,
+    width: i32,
+    height: i32,
+    values: Vec<i32>,
+}
+
+impl Area {
+    fn from_fn(
+        x: i32,
+        z: i32,
+        width: i32,
+        height: i32,
+        mut f: impl FnMut(i32, i32) -> i32,
+    ) -> Self {
+        let mut values = Vec::with_capacity((width * height) as usize);
+        for value_z in z..z + height {
+            for value_x in x..x + width {
+                values.push(f(value_x, value_z));
+            }
+        }
+        Self {
+            x,
+            z,
+            width,
+            height,
+            values,
+        }
+    }
+
+    /// Gets the value at the given absolute coordinates.
+    ///
+    /// # Panics
+    /// Panics if the coordinates are outside the area.
+    pub fn get(&self, x: i32, z: i32) -> i32 {
+        assert!(
+            (self.x..self.x + self.width).contains(&x)
+                && (self.z..self.z + self.height).contains(&z),
+            "({}, {}) is outside the area",
+            x,
+            z
+        );
+        self.values[((x - self.x) + (z - self.z) * self.width) as usize]
+    }
+}
+
+/// The values around a cell, in the cardinal directions.
+#[derive(Copy, Clone)]
+struct Cross {
+    north: i32,
+    east: i32,
+    south: i32,
+    west: i32,
+    center: i32,
+}
+
+impl Cross {
+    fn sides(&self) -> [i32; 4] {
+        [self.north, self.east, self.south, self.west]
+    }
+}
+
+/// The values around a cell, in the diagonal directions.
+#[derive(Copy, Clone)]
+struct Diagonals {
+    south_west: i32,
+    south_east: i32,
+    north_east: i32,
+    north_west: i32,
+    center: i32,
+}
+
+#[derive(Copy, Clone, Debug)]
+enum LayerKind {
+    Continent,
+    Zoom,
+    FuzzyZoom,
+    AddIsland,
+    RemoveTooMuchOcean,
+    AddSnow,
+    CoolWarm,
+    HeatIce,
+    Special,
+    AddMushroomIsland,
+    DeepOcean,
+    Biome,
+    AddBamboo,
+    BiomeEdge,
+    StartRiver,
+    Hills,
+    RareBiome,
+    Shore,
+    Smooth,
+    River,
+    MixRiver,
+    OceanTemperature,
+    MixOceans,
+}
+
+struct Layer {
+    kind: LayerKind,
+    random: LayerRandom,
+    parent: Option<Arc<Layer>>,
+    second_parent: Option<Arc<Layer>>,
+    /// Noise used by the ocean temperature layer.
+    noise: Option<PerlinNoise>,
+}
+
+/// Builds layers for a single world seed.
+struct LayerBuilder {
+    seed: i64,
+}
+
+impl LayerBuilder {
+    fn layer(&self, kind: LayerKind, salt: i64, parent: Option<&Arc<Layer>>) -> Arc<Layer> {
+        Arc::new(Layer {
+            kind,
+            random: LayerRandom::new(self.seed, salt),
+            parent: parent.cloned(),
+            second_parent: None,
+            noise: None,
+        })
+    }
+
+    fn then(&self, kind: LayerKind, salt: i64, parent: &Arc<Layer>) -> Arc<Layer> {
+        self.layer(kind, salt, Some(parent))
+    }
+
+    fn merge(
+        &self,
+        kind: LayerKind,
+        salt: i64,
+        first: &Arc<Layer>,
+        second: &Arc<Layer>,
+    ) -> Arc<Layer> {
+        Arc::new(Layer {
+            kind,
+            random: LayerRandom::new(self.seed, salt),
+            parent: Some(Arc::clone(first)),
+            second_parent: Some(Arc::clone(second)),
+            noise: None,
+        })
+    }
+
+    /// Zooms in `count` times, with salts counting up from `salt`.
+    fn zoom(&self, salt: i64, parent: &Arc<Layer>, count: i64) -> Arc<Layer> {
+        let mut layer = Arc::clone(parent);
+        for i in 0..count {
+            layer = self.then(LayerKind::Zoom, salt + i, &layer);
+        }
+        layer
+    }
+}
+
+/// The layer stack which generates overworld biomes.
+pub struct BiomeLayers {
+    root: Arc<Layer>,
+}
+
+impl BiomeLayers {
+    /// Creates the layer stack. Biomes are zoomed in `biome_size`
+    /// times and rivers `river_size` times; vanilla uses 4 for
+    /// both, and a biome size of 6 for large biomes.
+    pub fn new(seed: i64, biome_size: u32, river_size: u32) -> Self {
+        use LayerKind::*;
+        let b = LayerBuilder { seed };
+
+        let mut land = b.layer(Continent, 1, None);
+        land = b.then(FuzzyZoom, 2000, &land);
+        land = b.then(AddIsland, 1, &land);
+        land = b.then(Zoom, 2001, &land);
+        land = b.then(AddIsland, 2, &land);
+        land = b.then(AddIsland, 50, &land);
+        land = b.then(AddIsland, 70, &land);
+        land = b.then(RemoveTooMuchOcean, 2, &land);
+
+        let ocean_temperature = Arc::new(Layer {
+            kind: OceanTemperature,
+            random: LayerRandom::new(seed, 2),
+            parent: None,
+            second_parent: None,
+            noise: Some(PerlinNoise::new(&mut JavaRandom::new(seed))),
+        });
+        let ocean_temperature = b.zoom(2001, &ocean_temperature, 6);
+
+        land = b.then(AddSnow, 2, &land);
+        land = b.then(AddIsland, 3, &land);
+        land = b.then(CoolWarm, 2, &land);
+        land = b.then(HeatIce, 2, &land);
+        land = b.then(Special, 3, &land);
+        land = b.then(Zoom, 2002, &land);
+        land = b.then(Zoom, 2003, &land);
+        land = b.then(AddIsland, 4, &land);
+        land = b.then(AddMushroomIsland, 5, &land);
+        land = b.then(DeepOcean, 4, &land);
+
+        let river_noise = b.then(StartRiver, 100, &land);
+
+        let mut biomes = b.then(Biome, 200, &land);
+        biomes = b.then(AddBamboo, 1001, &biomes);
+        biomes = b.zoom(1000, &biomes, 2);
+        biomes = b.then(BiomeEdge, 1000, &biomes);
+        let hills_noise = b.zoom(1000, &river_noise, 2);
+        biomes = b.merge(Hills, 1000, &biomes, &hills_noise);
+
+        let mut rivers = b.zoom(1000, &river_noise, 2);
+        rivers = b.zoom(1000, &rivers, i64::from(river_size));
+        rivers = b.then(River, 1, &rivers);
+        rivers = b.then(Smooth, 1000, &rivers);
+
+        biomes = b.then(RareBiome, 1001, &biomes);
+        for i in 0..i64::from(biome_size) {
+            biomes = b.then(Zoom, 1000 + i, &biomes);
+            if i == 0 {
+                biomes = b.then(AddIsland, 3, &biomes);
+            }
+            if i == 1 || biome_size == 1 {
+                biomes = b.then(Shore, 1000, &biomes);
+            }
+        }
+        biomes = b.then(Smooth, 1000, &biomes);
+        biomes = b.merge(MixRiver, 100, &biomes, &rivers);
+        biomes = b.merge(MixOceans, 100, &biomes, &ocean_temperature);
+
+        Self { root: biomes }
+    }
+
+    /// Generates the biome IDs of the given area, in
+    /// units of 4 blocks.
+    pub fn generate(&self, x: i32, z: i32, width: i32, height: i32) -> Area {
+        self.root.generate(x, z, width, height)
+    }
+}
+
+impl Layer {
+    fn parent(&self) -> &Layer {
+        self.parent.as_ref().expect("layer has no parent")
+    }
+
+    fn second_parent(&self) -> &Layer {
+        self.second_parent
+            .as_ref()
+            .expect("layer has no second parent")
+    }
+
+    fn generate(&self, x: i32, z: i32, width: i32, height: i32) -> Area {
+        use LayerKind::*;
+        match self.kind {
+            Continent => {
+                let mut random = self.random.clone();
+                Area::from_fn(x, z, width, height, |x, z| {
+                    random.set_position(x, z);
+                    if x == 0 && z == 0 {
+                        1
+                    } else {
+                        (random.next_int(10) == 0) as i32
+                    }
+                })
+            }
+            OceanTemperature => {
+                let noise = self.noise.as_ref().expect("missing ocean noise");
+                Area::from_fn(x, z, width, height, |x, z| {
+                    let value = noise.sample(f64::from(x) / 8.0, f64::from(z) / 8.0, 0.0, 0.0, 0.0);
+                    if value > 0.4 {
+                        WARM_OCEAN
+                    } else if value > 0.2 {
+                        LUKEWARM_OCEAN
+                    } else if value < -0.4 {
+                        FROZEN_OCEAN
+                    } else if value < -0.2 {
+                        COLD_OCEAN
+                    } else {
+                        OCEAN
+                    }
+                })
+            }
+            Zoom => self.zoom(x, z, width, height, false),
+            FuzzyZoom => self.zoom(x, z, width, height, true),
+            AddIsland => self.map_diagonals(x, z, width, height, add_island),
+            AddMushroomIsland => self.map_diagonals(x, z, width, height, add_mushroom_island),
+            RemoveTooMuchOcean => self.map_cross(x, z, width, height, remove_too_much_ocean),
+            CoolWarm => self.map_cross(x, z, width, height, cool_warm),
+            HeatIce => self.map_cross(x, z, width, height, heat_ice),
+            DeepOcean => self.map_cross(x, z, width, height, deep_ocean),
+            BiomeEdge => self.map_cross(x, z, width, height, biome_edge),
+            Shore => self.map_cross(x, z, width, height, shore),
+            Smooth => self.map_cross(x, z, width, height, smooth),
+            River => self.map_cross(x, z, width, height, river),
+            AddSnow => self.map_single(x, z, width, height, add_snow),
+            Special => self.map_single(x, z, width, height, special),
+            Biome => self.map_single(x, z, width, height, biome),
+            AddBamboo => self.map_single(x, z, width, height, add_bamboo),
+            StartRiver => self.map_single(x, z, width, height, start_river),
+            RareBiome => self.map_single(x, z, width, height, rare_biome),
+            Hills => self.hills(x, z, width, height),
+            MixRiver => self.mix_river(x, z, width, height),
+            MixOceans => self.mix_oceans(x, z, width, height),
+        }
+    }
+
+    fn zoom(&self, x: i32, z: i32, width: i32, height: i32, fuzzy: bool) -> Area {
+        let parent_x = x >> 1;
+        let parent_z = z >> 1;
+        let parent = self.parent().generate(
+            parent_x,
+            parent_z,
+            ((x + width) >> 1) - parent_x + 1,
+            ((z + height) >> 1) - parent_z + 1,
+        );
+
+        let mut random = self.random.clone();
+        Area::from_fn(x, z, width, height, |x, z| {
+            let (cell_x, cell_z) = (x >> 1, z >> 1);
+            random.set_position(cell_x << 1, cell_z << 1);
+            let north_west = parent.get(cell_x, cell_z);
+            let (odd_x, odd_z) = (x & 1 == 1, z & 1 == 1);
+            if !odd_x && !odd_z {
+                return north_west;
+            }
+
+            let south_west = parent.get(cell_x, (z + 1) >> 1);
+            let picked = pick(&mut random, north_west, south_west);
+            if !odd_x {
+                return picked;
+            }
+
+            let north_east = parent.get((x + 1) >> 1, cell_z);
+            let picked = pick(&mut random, north_west, north_east);
+            if !odd_z {
+                return picked;
+            }
+
+            let south_east = parent.get((x + 1) >> 1, (z + 1) >> 1);
+            if fuzzy {
+                random.choose(&[north_west, north_east, south_west, south_east])
+            } else {
+                mode_or_random(&mut random, north_west, north_east, south_west, south_east)
+            }
+        })
+    }
+
+    fn map_single(
+        &self,
+        x: i32,
+        z: i32,
+        width: i32,
+        height: i32,
+        f: fn(&mut LayerRandom, i32) -> i32,
+    ) -> Area {
+        let parent = self.parent().generate(x, z, width, height);
+        let mut random = self.random.clone();
+        Area::from_fn(x, z, width, height, |x, z| {
+            random.set_position(x, z);
+            f(&mut random, parent.get(x, z))
+        })
+    }
+
+    fn map_cross(
+        &self,
+        x: i32,
+        z: i32,
+        width: i32,
+        height: i32,
+        f: fn(&mut LayerRandom, Cross) -> i32,
+    ) -> Area {
+        let parent = self.parent().generate(x - 1, z - 1, width + 2, height + 2);
+        let mut random = self.random.clone();
+        Area::from_fn(x, z, width, height, |x, z| {
+            random.set_position(x, z);
+            f(&mut random, cross(&parent, x, z))
+        })
+    }
+
+    fn map_diagonals(
+        &self,
+        x: i32,
+        z: i32,
+        width: i32,
+        height: i32,
+        f: fn(&mut LayerRandom, Diagonals) -> i32,
+    ) -> Area {
+        let parent = self.parent().generate(x - 1, z - 1, width + 2, height + 2);
+        let mut random = self.random.clone();
+        Area::from_fn(x, z, width, height, |x, z| {
+            random.set_position(x, z);
+            let diagonals = Diagonals {
+                south_west: parent.get(x - 1, z + 1),
+                south_east: parent.get(x + 1, z + 1),
+                north_east: parent.get(x + 1, z - 1),
+                north_west: parent.get(x - 1, z - 1),
+                center: parent.get(x, z),
+            };
+            f(&mut random, diagonals)
+        })
+    }
+
+    /// Adds hills and mutated biomes, using the
+    /// river noise to decide where they go.
+    fn hills(&self, x: i32, z: i32, width: i32, height: i32) -> Area {
+        let biomes = self.parent().generate(x - 1, z - 1, width + 2, height + 2);
+        let noise = self.second_parent().generate(x, z, width, height);
+        let mut random = self.random.clone();
+        Area::from_fn(x, z, width, height, |x, z| {
+            random.set_position(x, z);
+            let biome = biomes.get(x, z);
+            let river = noise.get(x, z);
+            let variant = (river - 2) % 29;
+
+            if !is_shallow_ocean(biome) && river >= 2 && variant == 1 {
+                return mutation(biome).unwrap_or(biome);
+            }
+
+            if random.next_int(3) == 0 || variant == 0 {
+                let mut hill = match biome {
+                    DESERT => DESERT_HILLS,
+                    FOREST => WOODED_HILLS,
+                    BIRCH_FOREST => BIRCH_FOREST_HILLS,
+                    DARK_FOREST => PLAINS,
+                    TAIGA => TAIGA_HILLS,
+                    GIANT_TREE_TAIGA => GIANT_TREE_TAIGA_HILLS,
+                    SNOWY_TAIGA => SNOWY_TAIGA_HILLS,
+                    PLAINS => {
+                        if random.next_int(3) == 0 {
+                            WOODED_HILLS
+                        } else {
+                            FOREST
+                        }
+                    }
+                    SNOWY_TUNDRA => SNOWY_MOUNTAINS,
+                    JUNGLE => JUNGLE_HILLS,
+                    BAMBOO_JUNGLE => BAMBOO_JUNGLE_HILLS,
+                    OCEAN => DEEP_OCEAN,
+                    LUKEWARM_OCEAN => DEEP_LUKEWARM_OCEAN,
+                    COLD_OCEAN => DEEP_COLD_OCEAN,
+                    FROZEN_OCEAN => DEEP_FROZEN_OCEAN,
+                    MOUNTAINS => WOODED_MOUNTAINS,
+                    SAVANNA => SAVANNA_PLATEAU,
+                    _ if are_similar(biome, WOODED_BADLANDS_PLATEAU) => BADLANDS,
+                    DEEP_OCEAN | DEEP_LUKEWARM_OCEAN | DEEP_COLD_OCEAN | DEEP_FROZEN_OCEAN
+                        if random.next_int(3) == 0 =>
+                    {
+                        if random.next_int(2) == 0 {
+                            PLAINS
+                        } else {
+                            FOREST
+                        }
+                    }
+                    _ => biome,
+                };
+
+                if variant == 0 && hill != biome {
+                    hill = mutation(hill).unwrap_or(biome);
+                }
+
+                if hill != biome {
+                    let similar = cross(&biomes, x, z)
+                        .sides()
+                        .iter()
+                        .filter(|&&side| are_similar(side, biome))
+                        .count();
+                    if similar >= 3 {
+                        return hill;
+                    }
+                }
+            }
+
+            biome
+        })
+    }
+
+    fn mix_river(&self, x: i32, z: i32, width: i32, height: i32) -> Area {
+        let biomes = self.parent().generate(x, z, width, height);
+        let rivers = self.second_parent().generate(x, z, width, height);
+        Area::from_fn(x, z, width, height, |x, z| {
+            let biome = biomes.get(x, z);
+            let river = rivers.get(x, z);
+            if is_ocean(biome) || river != RIVER {
+                biome
+            } else if biome == SNOWY_TUNDRA {
+                FROZEN_RIVER
+            } else if biome == MUSHROOM_FIELDS || biome == MUSHROOM_FIELD_SHORE {
+                MUSHROOM_FIELD_SHORE
+            } else {
+                river & 255
+            }
+        })
+    }
+
+    fn mix_oceans(&self, x: i32, z: i32, width: i32, height: i32) -> Area {
+        let biomes = self
+            .parent()
+            .generate(x - 8, z - 8, width + 16, height + 16);
+        let temperatures = self.second_parent().generate(x, z, width, height);
+        Area::from_fn(x, z, width, height, |x, z| {
+            let biome = biomes.get(x, z);
+            if !is_ocean(biome) {
+                return biome;
+            }
+
+            let temperature = temperatures.get(x, z);
+            for offset_x in (-8..=8).step_by(4) {
+                for offset_z in (-8..=8).step_by(4) {
+                    if !is_ocean(biomes.get(x + offset_x, z + offset_z)) {
+                        if temperature == WARM_OCEAN {
+                            return LUKEWARM_OCEAN;
+                        }
+                        if temperature == FROZEN_OCEAN {
+                            return COLD_OCEAN;
+                        }
+                    }
+                }
+            }
+
+            if biome == DEEP_OCEAN {
+                match temperature {
+                    LUKEWARM_OCEAN => return DEEP_LUKEWARM_OCEAN,
+                    OCEAN => return DEEP_OCEAN,
+                    COLD_OCEAN => return DEEP_COLD_OCEAN,
+                    FROZEN_OCEAN => return DEEP_FROZEN_OCEAN,
+                    _ => {}
+                }
+            }
+            temperature
+        })
+    }
+}
+
+fn cross(area: &Area, x: i32, z: i32) -> Cross {
+    Cross {
+        north: area.get(x, z - 1),
+        east: area.get(x + 1, z),
+        south: area.get(x, z + 1),
+        west: area.get(x - 1, z),
+        center: area.get(x, z),
+    }
+}
+
+fn pick(random: &mut LayerRandom, a: i32, b: i32) -> i32 {
+    if random.next_int(2) == 0 {
+        a
+    } else {
+        b
+    }
+}
+
+/// Returns the most common of the four values,
+/// or a random one if there is a tie.
+fn mode_or_random(random: &mut LayerRandom, a: i32, b: i32, c: i32, d: i32) -> i32 {
+    if b == c && c == d {
+        b
+    } else if (a == b && a == c)
+        || (a == b && a == d)
+        || (a == c && a == d)
+        || (a == b && c != d)
+        || (a == c && b != d)
+        || (a == d && b != c)
+    {
+        a
+    } else if (b == c && a != d) || (b == d && a != c) {
+        b
+    } else if c == d && a != b {
+        c
+    } else {
+        random.choose(&[a, b, c, d])
+    }
+}
+
+fn add_island(random: &mut LayerRandom, d: Diagonals) -> i32 {
+    let corners = [d.north_west, d.south_west, d.north_east, d.south_east];
+    if !is_shallow_ocean(d.center) || corners.iter().all(|&corner| is_shallow_ocean(corner)) {
+        if !is_shallow_ocean(d.center)
+            && corners.iter().any(|&corner| is_shallow_ocean(corner))
+            && random.next_int(5) == 0
+        {
+            if let Some(&ocean) = corners.iter().find(|&&corner| is_shallow_ocean(corner)) {
+                return if d.center == FOREST { FOREST } else { ocean };
+            }
+        }
+        d.center
+    } else {
+        let mut chance = 1;
+        let mut land = 1;
+        for &corner in &[d.north_west, d.north_east, d.south_west, d.south_east] {
+            if !is_shallow_ocean(corner) {
+                if random.next_int(chance) == 0 {
+                    land = corner;
+                }
+                chance += 1;
+            }
+        }
+        if random.next_int(3) == 0 {
+            land
+        } else if land == FOREST {
+            FOREST
+        } else {
+            OCEAN
+        }
+    }
+}
+
+fn add_mushroom_island(random: &mut LayerRandom, d: Diagonals) -> i32 {
+    let all_ocean = [
+        d.center,
+        d.north_west,
+        d.south_west,
+        d.north_east,
+        d.south_east,
+    ]
+    .iter()
+    .all(|&value| is_shallow_ocean(value));
+    if all_ocean && random.next_int(100) == 0 {
+        MUSHROOM_FIELDS
+    } else {
+        d.center
+    }
+}
+
+fn remove_too_much_ocean(random: &mut LayerRandom, c: Cross) -> i32 {
+    let all_ocean =
+        is_shallow_ocean(c.center) && c.sides().iter().all(|&side| is_shallow_ocean(side));
+    if all_ocean && random.next_int(2) == 0 {
+        1
+    } else {
+        c.center
+    }
+}
+
+// Before the biome layer, land is represented by its
+// climate: 1 is warm, 2 is temperate, 3 is cool and 4 is icy.
+
+fn add_snow(random: &mut LayerRandom, value: i32) -> i32 {
+    if is_shallow_ocean(value) {
+        return value;
+    }
+    match random.next_int(6) {
+        0 => 4,
+        1 => 3,
+        _ => 1,
+    }
+}
+
+fn cool_warm(_random: &mut LayerRandom, c: Cross) -> i32 {
+    if c.center == 1 && c.sides().iter().any(|&side| side == 3 || side == 4) {
+        2
+    } else {
+        c.center
+    }
+}
+
+fn heat_ice(_random: &mut LayerRandom, c: Cross) -> i32 {
+    if c.center == 4 && c.sides().iter().any(|&side| side == 1 || side == 2) {
+        3
+    } else {
+        c.center
+    }
+}
+
+/// Marks some land as special, which leads to rare
+/// biomes such as jungles and badlands plateaus.
+fn special(random: &mut LayerRandom, value: i32) -> i32 {
+    if !is_shallow_ocean(value) && random.next_int(13) == 0 {
+        value | (((1 + random.next_int(15)) << 8) & 0xF00)
+    } else {
+        value
+    }
+}
+
+fn deep_ocean(_random: &mut LayerRandom, c: Cross) -> i32 {
+    if !is_shallow_ocean(c.center) {
+        return c.center;
+    }
+    let oceans = c
+        .sides()
+        .iter()
+        .filter(|&&side| is_shallow_ocean(side))
+        .count();
+    if oceans <= 3 {
+        return c.center;
+    }
+    match c.center {
+        WARM_OCEAN => DEEP_WARM_OCEAN,
+        LUKEWARM_OCEAN => DEEP_LUKEWARM_OCEAN,
+        COLD_OCEAN => DEEP_COLD_OCEAN,
+        FROZEN_OCEAN => DEEP_FROZEN_OCEAN,
+        _ => DEEP_OCEAN,
+    }
+}
+
+fn biome(random: &mut LayerRandom, value: i32) -> i32 {
+    const WARM: [i32; 6] = [DESERT, DESERT, DESERT, SAVANNA, SAVANNA, PLAINS];
+    const TEMPERATE: [i32; 6] = [FOREST, DARK_FOREST, MOUNTAINS, PLAINS, BIRCH_FOREST, SWAMP];
+    const COOL: [i32; 4] = [FOREST, MOUNTAINS, TAIGA, PLAINS];
+    const ICY: [i32; 4] = [SNOWY_TUNDRA, SNOWY_TUNDRA, SNOWY_TUNDRA, SNOWY_TAIGA];
+
+    let special = (value & 0xF00) >> 8;
+    let value = value & !0xF00;
+    if is_ocean(value) || value == MUSHROOM_FIELDS {
+        return value;
+    }
+    match value {
+        1 if special > 0 => {
+            if random.next_int(3) == 0 {
+                BADLANDS_PLATEAU
+            } else {
+                WOODED_BADLANDS_PLATEAU
+            }
+        }
+        1 => random.choose(&WARM),
+        2 if special > 0 => JUNGLE,
+        2 => random.choose(&TEMPERATE),
+        3 if special > 0 => GIANT_TREE_TAIGA,
+        3 => random.choose(&COOL),
+        4 => random.choose(&ICY),
+        _ => MUSHROOM_FIELDS,
+    }
+}
+
+fn add_bamboo(random: &mut LayerRandom, value: i32) -> i32 {
+    if random.next_int(10) == 0 && value == JUNGLE {
+        BAMBOO_JUNGLE
+    } else {
+        value
+    }
+}
+
+fn biome_edge(_random: &mut LayerRandom, c: Cross) -> i32 {
+    if are_similar(c.center, MOUNTAINS) {
+        return c.center;
+    }
+    for &(biome, edge) in &[
+        (WOODED_BADLANDS_PLATEAU, BADLANDS),
+        (BADLANDS_PLATEAU, BADLANDS),
+        (GIANT_TREE_TAIGA, TAIGA),
+    ] {
+        if c.center == biome {
+            let surrounded = c.sides().iter().all(|&side| are_similar(side, biome));
+            return if surrounded { c.center } else { edge };
+        }
+    }
+
+    let sides = c.sides();
+    let borders = |biomes: &[i32]| sides.iter().any(|side| biomes.contains(side));
+    if c.center == DESERT && borders(&[SNOWY_TUNDRA]) {
+        WOODED_MOUNTAINS
+    } else if c.center == SWAMP && borders(&[DESERT, SNOWY_TAIGA, SNOWY_TUNDRA]) {
+        PLAINS
+    } else if c.center == SWAMP && borders(&[JUNGLE, BAMBOO_JUNGLE]) {
+        JUNGLE_EDGE
+    } else {
+        c.center
+    }
+}
+
+fn start_river(random: &mut LayerRandom, value: i32) -> i32 {
+    if is_shallow_ocean(value) {
+        value
+    } else {
+        random.next_int(299_999) + 2
+    }
+}
+
+fn rare_biome(random: &mut LayerRandom, value: i32) -> i32 {
+    if random.next_int(57) == 0 && value == PLAINS {
+        SUNFLOWER_PLAINS
+    } else {
+        value
+    }
+}
+
+fn shore(_random: &mut LayerRandom, c: Cross) -> i32 {
+    let sides = c.sides();
+    let borders_ocean = sides.iter().any(|&side| is_ocean(side));
+    let center = c.center;
+
+    if center == MUSHROOM_FIELDS {
+        if sides.iter().any(|&side| is_shallow_ocean(side)) {
+            return MUSHROOM_FIELD_SHORE;
+        }
+    } else if category(center) == Some(Category::Jungle) {
+        if !sides.iter().all(|&side| is_jungle_compatible(side)) {
+            return JUNGLE_EDGE;
+        }
+        if borders_ocean {
+            return BEACH;
+        }
+    } else if center != MOUNTAINS && center != WOODED_MOUNTAINS && center != MOUNTAIN_EDGE {
+        if is_snowy(center) {
+            if !is_ocean(center) && borders_ocean {
+                return SNOWY_BEACH;
+            }
+        } else if center != BADLANDS && center != WOODED_BADLANDS_PLATEAU {
+            if !is_ocean(center) && center != RIVER && center != SWAMP && borders_ocean {
+                return BEACH;
+            }
+        } else if !borders_ocean && !sides.iter().all(|&side| is_mesa(side)) {
+            return DESERT;
+        }
+    } else if !is_ocean(center) && borders_ocean {
+        return STONE_SHORE;
+    }
+    center
+}
+
+fn is_jungle_compatible(id: i32) -> bool {
+    category(id) == Some(Category::Jungle)
+        || matches!(id, JUNGLE_EDGE | JUNGLE | JUNGLE_HILLS | FOREST | TAIGA)
+        || is_ocean(id)
+}
+
+fn smooth(random: &mut LayerRandom, c: Cross) -> i32 {
+    let x_equal = c.east == c.west;
+    let z_equal = c.north == c.south;
+    if x_equal == z_equal {
+        if x_equal {
+            pick(random, c.west, c.north)
+        } else {
+            c.center
+        }
+    } else if x_equal {
+        c.west
+    } else {
+        c.north
+    }
+}
+
+fn river(_random: &mut LayerRandom, c: Cross) -> i32 {
+    let filter = |value: i32| if value >= 2 { 2 + (value & 1) } else { value };
+    let center = filter(c.center);
+    if c.sides().iter().all(|&side| filter(side) == center) {
+        -1
+    } else {
+        RIVER
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn generation_is_independent_of_area() {
+        let layers = BiomeLayers::new(1234, 4, 4);
+        let large = layers.generate(-20, -20, 40, 40);
+        let small = layers.generate(3, -7, 5, 5);
+        for x in 3..8 {
+            for z in -7..-2 {
+                assert_eq!(large.get(x, z), small.get(x, z));
+            }
+        }
+    }
+}
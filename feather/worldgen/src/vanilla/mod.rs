@@ -0,0 +1,415 @@
+//! A world generator ported from the terrain
+//! and biome generation of vanilla 1.16.
+//!
+//! Biomes come from a port of vanilla's biome layer stack, and
+//! the shape of the terrain from a port of its noise chunk generator.
+//! Surface blocks are placed by ports of vanilla's surface builders,
+//! including the terracotta bands of badlands, using vanilla's
+//! fuzzed per-block biomes. The output has not yet been compared
+//! against chunks generated by the vanilla game, so it may differ
+//! from vanilla for the same seed. Caves, structures and
+//! decorations are not generated.
+
+mod biomes;
+mod layers;
+mod noise;
+mod random;
+mod settings;
+mod surface;
+mod zoom;
+
+use crate::WorldGenerator;
+use base::{Chunk, ChunkPosition};
+use layers::{Area, BiomeLayers};
+use noise::{clamped_lerp, lerp, wrap, OctavePerlinNoise, OctaveSimplexNoise};
+use once_cell::sync::Lazy;
+use random::JavaRandom;
+use surface::SurfaceBuilder;
+
+pub use settings::{BiomeTerrain, NoiseSampling, NoiseSettings, Slide};
+
+/// Width of a noise cell, in blocks.
+const CELL_WIDTH: usize = 4;
+/// Height of a noise cell, in blocks.
+const CELL_HEIGHT: usize = 8;
+/// Number of noise cells along each horizontal axis of a chunk.
+const CELLS_PER_CHUNK: usize = 16 / CELL_WIDTH;
+/// Number of noise cells stacked in a chunk.
+const CELL_COUNT_Y: usize = 256 / CELL_HEIGHT;
+
+/// Base scale of the terrain noise, multiplied
+/// by the scales in [`NoiseSampling`].
+const NOISE_SCALE: f64 = 684.412;
+
+/// Weights of the biomes surrounding a noise column,
+/// indexed by `(x + 2) + (z + 2) * 5`.
+static BIOME_WEIGHTS: Lazy<[f32; 25]> = Lazy::new(|| {
+    let mut weights = [0.0; 25];
+    for x in -2i32..=2 {
+        for z in -2i32..=2 {
+            weights[(x + 2 + (z + 2) * 5) as usize] = 10.0 / ((x * x + z * z) as f32 + 0.2).sqrt();
+        }
+    }
+    weights
+});
+
+/// Generates chunks with a port of the terrain
+/// shape and biomes of vanilla 1.16.
+pub struct VanillaWorldGenerator {
+    settings: NoiseSettings,
+    layers: BiomeLayers,
+    min_limit_noise: OctavePerlinNoise,
+    max_limit_noise: OctavePerlinNoise,
+    main_noise: OctavePerlinNoise,
+    surface: SurfaceBuilder,
+    depth_noise: OctavePerlinNoise,
+}
+
+impl VanillaWorldGenerator {
+    pub fn new(seed: i64) -> Self {
+        Self::with_settings(seed, NoiseSettings::default())
+    }
+
+    /// Creates a generator with custom noise settings.
+    pub fn with_settings(seed: i64, settings: NoiseSettings) -> Self {
+        // Noise generators must be created in this
+        // order to consume the same random numbers as vanilla.
+        let mut random = JavaRandom::new(seed);
+        let min_limit_noise = OctavePerlinNoise::new(&mut random, 16);
+        let max_limit_noise = OctavePerlinNoise::new(&mut random, 16);
+        let main_noise = OctavePerlinNoise::new(&mut random, 8);
+        let surface_noise = OctaveSimplexNoise::new(&mut random, 4);
+        random.skip(2620);
+        let depth_noise = OctavePerlinNoise::new(&mut random, 16);
+
+        Self {
+            layers: BiomeLayers::new(seed, settings.biome_size, settings.river_size),
+            settings,
+            min_limit_noise,
+            max_limit_noise,
+            main_noise,
+            surface: SurfaceBuilder::new(seed, surface_noise),
+            depth_noise,
+        }
+    }
+
+    /// Fills the noise values of the column at the given noise
+    /// cell coordinates. `biomes` must contain the biomes
+    /// within two cells of the column.
+    fn noise_column(&self, x: i32, z: i32, biomes: &Area) -> [f64; CELL_COUNT_Y + 1] {
+        let (mut scale_sum, mut depth_sum, mut weight_sum) = (0.0f32, 0.0f32, 0.0f32);
+        let (center_depth, _) = self.depth_and_scale(biomes.get(x, z));
+        for offset_x in -2..=2 {
+            for offset_z in -2..=2 {
+                let (raw_depth, raw_scale) =
+                    self.depth_and_scale(biomes.get(x + offset_x, z + offset_z));
+                let (depth, scale) = if self.settings.amplified && raw_depth > 0.0 {
+                    (1.0 + raw_depth * 2.0, 1.0 + raw_scale * 4.0)
+                } else {
+                    (raw_depth, raw_scale)
+                };
+                let factor = if raw_depth > center_depth { 0.5 } else { 1.0 };
+                let weight = factor * BIOME_WEIGHTS[(offset_x + 2 + (offset_z + 2) * 5) as usize]
+                    / (depth + 2.0);
+                scale_sum += scale * weight;
+                depth_sum += depth * weight;
+                weight_sum += weight;
+            }
+        }
+        let depth = f64::from(depth_sum / weight_sum * 0.5 - 0.125) * 0.265_625;
+        let scale = 96.0 / f64::from(scale_sum / weight_sum * 0.9 + 0.1);
+
+        let settings = &self.settings;
+        let random_density = if settings.random_density_offset {
+            self.random_density(x, z)
+        } else {
+            0.0
+        };
+        let (top_slide, bottom_slide) = (settings.top_slide, settings.bottom_slide);
+        let mut column = [0.0; CELL_COUNT_Y + 1];
+        for (y, value) in column.iter_mut().enumerate() {
+            let mut noise = self.sample_noise(x, y as i32, z);
+            let falloff = 1.0 - y as f64 * 2.0 / CELL_COUNT_Y as f64 + random_density;
+            let density =
+                ((falloff * settings.density_factor + settings.density_offset) + depth) * scale;
+            noise += if density > 0.0 {
+                density * 4.0
+            } else {
+                density
+            };
+
+            if top_slide.size > 0.0 {
+                let slide = (CELL_COUNT_Y as f64 - y as f64 - top_slide.offset) / top_slide.size;
+                noise = clamped_lerp(top_slide.target, noise, slide);
+            }
+            if bottom_slide.size > 0.0 {
+                let slide = (y as f64 - bottom_slide.offset) / bottom_slide.size;
+                noise = clamped_lerp(bottom_slide.target, noise, slide);
+            }
+            *value = noise;
+        }
+        column
+    }
+
+    /// Returns the depth and scale of the biome with the given ID.
+    fn depth_and_scale(&self, id: i32) -> (f32, f32) {
+        match self.settings.biome_terrain.get(&biomes::to_biome(id)) {
+            Some(terrain) => (terrain.depth, terrain.scale),
+            None => biomes::depth_and_scale(id),
+        }
+    }
+
+    fn random_density(&self, x: i32, z: i32) -> f64 {
+        let noise =
+            self.depth_noise
+                .sample(f64::from(x * 200), 10.0, f64::from(z * 200), 1.0, 0.0, true);
+        let noise = if noise < 0.0 { -noise * 0.3 } else { noise };
+        let density = noise * 24.575_625 - 2.0;
+        if density < 0.0 {
+            density * 0.009_486_607_142_857_142
+        } else {
+            density.min(1.0) * 0.006_640_625
+        }
+    }
+
+    fn sample_noise(&self, x: i32, y: i32, z: i32) -> f64 {
+        let (x, y, z) = (f64::from(x), f64::from(y), f64::from(z));
+        let sampling = self.settings.sampling;
+        let xz_scale = NOISE_SCALE * sampling.xz_scale;
+        let y_scale = NOISE_SCALE * sampling.y_scale;
+        let xz_factor = xz_scale / sampling.xz_factor;
+        let y_factor = y_scale / sampling.y_factor;
+
+        let (mut min_limit, mut max_limit, mut main) = (0.0, 0.0, 0.0);
+        let mut frequency = 1.0;
+        for i in 0..16 {
+            let sample_x = wrap(x * xz_scale * frequency);
+            let sample_y = wrap(y * y_scale * frequency);
+            let sample_z = wrap(z * xz_scale * frequency);
+            let octave_y_scale = y_scale * frequency;
+
+            if let Some(octave) = self.min_limit_noise.octave(i) {
+                min_limit += octave.sample(
+                    sample_x,
+                    sample_y,
+                    sample_z,
+                    octave_y_scale,
+                    y * octave_y_scale,
+                ) / frequency;
+            }
+            if let Some(octave) = self.max_limit_noise.octave(i) {
+                max_limit += octave.sample(
+                    sample_x,
+                    sample_y,
+                    sample_z,
+                    octave_y_scale,
+                    y * octave_y_scale,
+                ) / frequency;
+            }
+            if i < 8 {
+                if let Some(octave) = self.main_noise.octave(i) {
+                    main += octave.sample(
+                        wrap(x * xz_factor * frequency),
+                        wrap(y * y_factor * frequency),
+                        wrap(z * xz_factor * frequency),
+                        y_factor * frequency,
+                        y * y_factor * frequency,
+                    ) / frequency;
+                }
+            }
+            frequency /= 2.0;
+        }
+
+        clamped_lerp(
+            min_limit / 512.0,
+            max_limit / 512.0,
+            (main / 10.0 + 1.0) / 2.0,
+        )
+    }
+
+    /// Fills the chunk with the default block and fluid
+    /// according to the terrain noise.
+    fn fill_terrain(&self, chunk: &mut Chunk, biomes: &Area) {
+        let pos = chunk.position();
+        let (base_x, base_z) = (
+            pos.x * CELLS_PER_CHUNK as i32,
+            pos.z * CELLS_PER_CHUNK as i32,
+        );
+
+        let mut columns = Vec::with_capacity((CELLS_PER_CHUNK + 1) * (CELLS_PER_CHUNK + 1));
+        for cell_x in 0..=CELLS_PER_CHUNK as i32 {
+            for cell_z in 0..=CELLS_PER_CHUNK as i32 {
+                columns.push(self.noise_column(base_x + cell_x, base_z + cell_z, biomes));
+            }
+        }
+        let column = |x: usize, z: usize| &columns[x * (CELLS_PER_CHUNK + 1) + z];
+
+        for cell_x in 0..CELLS_PER_CHUNK {
+            for cell_z in 0..CELLS_PER_CHUNK {
+                let (west, east) = (column(cell_x, cell_z), column(cell_x + 1, cell_z));
+                let (south_west, south_east) =
+                    (column(cell_x, cell_z + 1), column(cell_x + 1, cell_z + 1));
+                for cell_y in (0..CELL_COUNT_Y).rev() {
+                    for local_y in (0..CELL_HEIGHT).rev() {
+                        let y = cell_y * CELL_HEIGHT + local_y;
+                        let t_y = local_y as f64 / CELL_HEIGHT as f64;
+                        let d0 = lerp(t_y, west[cell_y], west[cell_y + 1]);
+                        let d1 = lerp(t_y, east[cell_y], east[cell_y + 1]);
+                        let d2 = lerp(t_y, south_west[cell_y], south_west[cell_y + 1]);
+                        let d3 = lerp(t_y, south_east[cell_y], south_east[cell_y + 1]);
+
+                        for local_x in 0..CELL_WIDTH {
+                            let t_x = local_x as f64 / CELL_WIDTH as f64;
+                            let north = lerp(t_x, d0, d1);
+                            let south = lerp(t_x, d2, d3);
+                            for local_z in 0..CELL_WIDTH {
+                                let t_z = local_z as f64 / CELL_WIDTH as f64;
+                                let density = lerp(t_z, north, south);
+
+                                let block = if density > 0.0 {
+                                    self.settings.default_block
+                                } else if (y as i32) < self.settings.sea_level {
+                                    self.settings.default_fluid
+                                } else {
+                                    continue;
+                                };
+                                chunk.set_block_at(
+                                    cell_x * CELL_WIDTH + local_x,
+                                    y,
+                                    cell_z * CELL_WIDTH + local_z,
+                                    block,
+                                );
+                            }
+                        }
+                    }
+                }
+            }
+        }
+    }
+
+    /// Sets the biomes of the chunk from the layer stack.
+    fn fill_biomes(&self, chunk: &mut Chunk, biomes: &Area) {
+        let pos = chunk.position();
+        for x in 0..4 {
+            for z in 0..4 {
+                let id = biomes.get(pos.x * 4 + x as i32, pos.z * 4 + z as i32);
+                let biome = biomes::to_biome(id);
+                for y in 0..64 {
+                    chunk.biomes_mut().set(x, y, z, biome);
+                }
+            }
+        }
+    }
+}
+
+impl WorldGenerator for VanillaWorldGenerator {
+    fn generate_chunk(&self, position: ChunkPosition) -> Chunk {
+        // Biomes for the chunk and two noise cells around it.
+        let biomes = self
+            .layers
+            .generate(position.x * 4 - 2, position.z * 4 - 2, 9, 9);
+
+        let mut chunk = Chunk::new(position);
+        self.fill_biomes(&mut chunk, &biomes);
+        self.fill_terrain(&mut chunk, &biomes);
+        self.surface.build(&mut chunk, &biomes, &self.settings);
+        chunk.recalculate_heightmaps();
+        chunk
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use base::BlockId;
+
+    /// Heightmaps and biomes of a few chunks generated by this
+    /// generator with seed 42. This is a regression snapshot of our
+    /// own output, not data from vanilla, so it only catches
+    /// unintended changes. Each line holds the chunk coordinates,
+    /// followed by the world surface and ocean floor heightmaps
+    /// and the biome IDs at y=0, all in z-major order.
+    const SNAPSHOT_CHUNKS: &str = include_str!("snapshot_chunks_seed_42.txt");
+
+    fn parse(values: &str) -> Vec<usize> {
+        values
+            .split_whitespace()
+            .map(|value| value.parse().unwrap())
+            .collect()
+    }
+
+    #[test]
+    fn matches_snapshot_chunks() {
+        let generator = VanillaWorldGenerator::new(42);
+        for line in SNAPSHOT_CHUNKS.lines() {
+            let parts: Vec<&str> = line.split('|').collect();
+            let position: Vec<i32> = parts[0]
+                .split_whitespace()
+                .map(|value| value.parse().unwrap())
+                .collect();
+            let position = ChunkPosition::new(position[0], position[1]);
+            let chunk = generator.generate_chunk(position);
+
+            let heightmaps = chunk.heightmaps();
+            let (surface, ocean_floor, biome_ids) =
+                (parse(parts[1]), parse(parts[2]), parse(parts[3]));
+            for z in 0..16 {
+                for x in 0..16 {
+                    assert_eq!(
+                        heightmaps.world_surface.height(x, z),
+                        Some(surface[z * 16 + x]),
+                        "surface height at {}, {} in {:?}",
+                        x,
+                        z,
+                        position
+                    );
+                    assert_eq!(
+                        heightmaps.ocean_floor.height(x, z),
+                        Some(ocean_floor[z * 16 + x]),
+                        "ocean floor height at {}, {} in {:?}",
+                        x,
+                        z,
+                        position
+                    );
+                }
+            }
+            for z in 0..4 {
+                for x in 0..4 {
+                    assert_eq!(
+                        chunk.biomes().get(x, 0, z).id() as usize,
+                        biome_ids[z * 4 + x],
+                        "biome at {}, {} in {:?}",
+                        x,
+                        z,
+                        position
+                    );
+                }
+            }
+        }
+    }
+
+    #[test]
+    fn custom_sea_level() {
+        let settings = NoiseSettings {
+            sea_level: 120,
+            ..Default::default()
+        };
+        let chunk = VanillaWorldGenerator::with_settings(42, settings)
+            .generate_chunk(ChunkPosition::new(0, 0));
+        for x in 0..16 {
+            for z in 0..16 {
+                assert!(chunk.heightmaps().world_surface.height(x, z).unwrap() >= 120);
+            }
+        }
+    }
+
+    #[test]
+    fn bedrock_floor() {
+        let chunk = VanillaWorldGenerator::new(0).generate_chunk(ChunkPosition::new(3, -2));
+        for x in 0..16 {
+            for z in 0..16 {
+                assert_eq!(chunk.block_at(x, 0, z), Some(BlockId::bedrock()));
+            }
+        }
+    }
+}
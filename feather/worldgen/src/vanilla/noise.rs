@@ -0,0 +1,330 @@
+//! Ports of the noise generators used by vanilla world generation.
+
+use super::random::JavaRandom;
+
+/// Gradients shared by the Perlin and simplex noise generators.
+const GRADIENTS: [[f64; 3]; 16] = [
+    [1.0, 1.0, 0.0],
+    [-1.0, 1.0, 0.0],
+    [1.0, -1.0, 0.0],
+    [-1.0, -1.0, 0.0],
+    [1.0, 0.0, 1.0],
+    [-1.0, 0.0, 1.0],
+    [1.0, 0.0, -1.0],
+    [-1.0, 0.0, -1.0],
+    [0.0, 1.0, 1.0],
+    [0.0, -1.0, 1.0],
+    [0.0, 1.0, -1.0],
+    [0.0, -1.0, -1.0],
+    [1.0, 1.0, 0.0],
+    [0.0, -1.0, 1.0],
+    [-1.0, 1.0, 0.0],
+    [0.0, -1.0, -1.0],
+];
+
+fn dot(gradient: [f64; 3], x: f64, y: f64, z: f64) -> f64 {
+    gradient[0] * x + gradient[1] * y + gradient[2] * z
+}
+
+/// Generates the random offset and permutation table
+/// shared by the Perlin and simplex noise generators.
+fn offsets_and_permutations(random: &mut JavaRandom) -> ([f64; 3], [u8; 256]) {
+    let offsets = [
+        random.next_double() * 256.0,
+        random.next_double() * 256.0,
+        random.next_double() * 256.0,
+    ];
+
+    let mut permutations = [0; 256];
+    for (i, value) in permutations.iter_mut().enumerate() {
+        *value = i as u8;
+    }
+    for i in 0..256 {
+        let j = random.next_int_bounded(256 - i as i32) as usize;
+        permutations.swap(i, i + j);
+    }
+    (offsets, permutations)
+}
+
+/// Three-dimensional Perlin noise ("improved noise").
+#[derive(Debug, Clone)]
+pub struct PerlinNoise {
+    offset: [f64; 3],
+    permutations: [u8; 256],
+}
+
+impl PerlinNoise {
+    pub fn new(random: &mut JavaRandom) -> Self {
+        let (offset, permutations) = offsets_and_permutations(random);
+        Self {
+            offset,
+            permutations,
+        }
+    }
+
+    pub fn y_offset(&self) -> f64 {
+        self.offset[1]
+    }
+
+    /// Samples the noise at the given position.
+    ///
+    /// If `y_scale` is not zero, the fractional part of the y coordinate
+    /// is quantized to multiples of `y_scale`, which stretches the
+    /// noise vertically. `y_max` limits the quantized value.
+    pub fn sample(&self, x: f64, y: f64, z: f64, y_scale: f64, y_max: f64) -> f64 {
+        let x = x + self.offset[0];
+        let y = y + self.offset[1];
+        let z = z + self.offset[2];
+        let (cell_x, cell_y, cell_z) = (x.floor(), y.floor(), z.floor());
+        let (local_x, local_y, local_z) = (x - cell_x, y - cell_y, z - cell_z);
+
+        let y_quantization = if y_scale != 0.0 {
+            let limited = if y_max >= 0.0 && y_max < local_y {
+                y_max
+            } else {
+                local_y
+            };
+            (limited / y_scale + f64::from(1.0e-7f32)).floor() * y_scale
+        } else {
+            0.0
+        };
+
+        self.sample_cell(
+            cell_x as i32,
+            cell_y as i32,
+            cell_z as i32,
+            local_x,
+            local_y - y_quantization,
+            local_z,
+            local_y,
+        )
+    }
+
+    fn permutation(&self, i: i32) -> i32 {
+        i32::from(self.permutations[(i & 255) as usize])
+    }
+
+    fn gradient(&self, hash: i32, x: f64, y: f64, z: f64) -> f64 {
+        dot(GRADIENTS[(self.permutation(hash) & 15) as usize], x, y, z)
+    }
+
+    #[allow(clippy::too_many_arguments)]
+    fn sample_cell(
+        &self,
+        cell_x: i32,
+        cell_y: i32,
+        cell_z: i32,
+        x: f64,
+        y: f64,
+        z: f64,
+        fade_y: f64,
+    ) -> f64 {
+        let i = self.permutation(cell_x) + cell_y;
+        let j = self.permutation(i) + cell_z;
+        let k = self.permutation(i + 1) + cell_z;
+        let l = self.permutation(cell_x + 1) + cell_y;
+        let m = self.permutation(l) + cell_z;
+        let n = self.permutation(l + 1) + cell_z;
+
+        let d000 = self.gradient(j, x, y, z);
+        let d100 = self.gradient(m, x - 1.0, y, z);
+        let d010 = self.gradient(k, x, y - 1.0, z);
+        let d110 = self.gradient(n, x - 1.0, y - 1.0, z);
+        let d001 = self.gradient(j + 1, x, y, z - 1.0);
+        let d101 = self.gradient(m + 1, x - 1.0, y, z - 1.0);
+        let d011 = self.gradient(k + 1, x, y - 1.0, z - 1.0);
+        let d111 = self.gradient(n + 1, x - 1.0, y - 1.0, z - 1.0);
+
+        let (fx, fy, fz) = (fade(x), fade(fade_y), fade(z));
+        lerp(
+            fz,
+            lerp(fy, lerp(fx, d000, d100), lerp(fx, d010, d110)),
+            lerp(fy, lerp(fx, d001, d101), lerp(fx, d011, d111)),
+        )
+    }
+}
+
+/// Several octaves of Perlin noise, from the lowest
+/// frequency to the highest.
+#[derive(Debug, Clone)]
+pub struct OctavePerlinNoise {
+    /// Octaves, indexed from the lowest frequency.
+    octaves: Vec<PerlinNoise>,
+    /// Frequency of the lowest octave.
+    lowest_frequency: f64,
+    /// Amplitude of the lowest octave.
+    lowest_amplitude: f64,
+}
+
+impl OctavePerlinNoise {
+    /// Creates noise with octaves of frequencies
+    /// `2^-(count - 1)` through `1`.
+    pub fn new(random: &mut JavaRandom, count: usize) -> Self {
+        // The highest frequency octave is created first.
+        let mut octaves: Vec<PerlinNoise> = (0..count).map(|_| PerlinNoise::new(random)).collect();
+        octaves.reverse();
+        Self {
+            octaves,
+            lowest_frequency: 2f64.powi(-(count as i32 - 1)),
+            lowest_amplitude: 2f64.powi(count as i32 - 1) / (2f64.powi(count as i32) - 1.0),
+        }
+    }
+
+    /// Returns the octave with frequency `2^-i`.
+    pub fn octave(&self, i: usize) -> Option<&PerlinNoise> {
+        self.octaves
+            .len()
+            .checked_sub(i + 1)
+            .map(|i| &self.octaves[i])
+    }
+
+    /// Samples the sum of all octaves. If `fixed_y` is set, each octave
+    /// is sampled at a y coordinate of zero, ignoring `y`.
+    pub fn sample(&self, x: f64, y: f64, z: f64, y_scale: f64, y_max: f64, fixed_y: bool) -> f64 {
+        let mut value = 0.0;
+        let mut frequency = self.lowest_frequency;
+        let mut amplitude = self.lowest_amplitude;
+        for octave in &self.octaves {
+            let octave_y = if fixed_y {
+                -octave.y_offset()
+            } else {
+                wrap(y * frequency)
+            };
+            value += octave.sample(
+                wrap(x * frequency),
+                octave_y,
+                wrap(z * frequency),
+                y_scale * frequency,
+                y_max * frequency,
+            ) * amplitude;
+            frequency *= 2.0;
+            amplitude /= 2.0;
+        }
+        value
+    }
+}
+
+/// Two-dimensional simplex noise.
+#[derive(Debug, Clone)]
+pub struct SimplexNoise {
+    offset: [f64; 3],
+    permutations: [u8; 256],
+}
+
+impl SimplexNoise {
+    pub fn new(random: &mut JavaRandom) -> Self {
+        let (offset, permutations) = offsets_and_permutations(random);
+        Self {
+            offset,
+            permutations,
+        }
+    }
+
+    fn permutation(&self, i: i32) -> i32 {
+        i32::from(self.permutations[(i & 255) as usize])
+    }
+
+    pub fn sample(&self, x: f64, y: f64) -> f64 {
+        let sqrt_3 = 3f64.sqrt();
+        let skew = 0.5 * (sqrt_3 - 1.0);
+        let unskew = (3.0 - sqrt_3) / 6.0;
+
+        let s = (x + y) * skew;
+        let i = (x + s).floor() as i32;
+        let j = (y + s).floor() as i32;
+        let t = f64::from(i + j) * unskew;
+        let x0 = x - (f64::from(i) - t);
+        let y0 = y - (f64::from(j) - t);
+
+        let (i1, j1) = if x0 > y0 { (1, 0) } else { (0, 1) };
+        let x1 = x0 - f64::from(i1) + unskew;
+        let y1 = y0 - f64::from(j1) + unskew;
+        let x2 = x0 - 1.0 + 2.0 * unskew;
+        let y2 = y0 - 1.0 + 2.0 * unskew;
+
+        let (ii, jj) = (i & 255, j & 255);
+        let g0 = self.permutation(ii + self.permutation(jj)) % 12;
+        let g1 = self.permutation(ii + i1 + self.permutation(jj + j1)) % 12;
+        let g2 = self.permutation(ii + 1 + self.permutation(jj + 1)) % 12;
+
+        let contribution = |gradient: i32, x: f64, y: f64| {
+            let t = 0.5 - x * x - y * y;
+            if t < 0.0 {
+                0.0
+            } else {
+                let t = t * t;
+                t * t * dot(GRADIENTS[gradient as usize], x, y, 0.0)
+            }
+        };
+        70.0 * (contribution(g0, x0, y0) + contribution(g1, x1, y1) + contribution(g2, x2, y2))
+    }
+}
+
+/// Several octaves of simplex noise, from the
+/// highest frequency to the lowest.
+#[derive(Debug, Clone)]
+pub struct OctaveSimplexNoise {
+    octaves: Vec<SimplexNoise>,
+    amplitude: f64,
+}
+
+impl OctaveSimplexNoise {
+    /// Creates noise with octaves of frequencies
+    /// `1` through `2^-(count - 1)`.
+    pub fn new(random: &mut JavaRandom, count: usize) -> Self {
+        Self {
+            octaves: (0..count).map(|_| SimplexNoise::new(random)).collect(),
+            amplitude: 1.0 / (2f64.powi(count as i32) - 1.0),
+        }
+    }
+
+    /// Samples the noise as used for surface depth.
+    pub fn sample_surface(&self, x: f64, z: f64) -> f64 {
+        self.sample(x, z, true) * 0.55
+    }
+
+    /// Samples the sum of the octaves, optionally
+    /// shifting each octave by its random offset.
+    pub fn sample(&self, x: f64, z: f64, use_offsets: bool) -> f64 {
+        let mut value = 0.0;
+        let mut frequency = 1.0;
+        let mut amplitude = self.amplitude;
+        for octave in &self.octaves {
+            let (offset_x, offset_z) = if use_offsets {
+                (octave.offset[0], octave.offset[1])
+            } else {
+                (0.0, 0.0)
+            };
+            value += octave.sample(x * frequency + offset_x, z * frequency + offset_z) * amplitude;
+            frequency /= 2.0;
+            amplitude *= 2.0;
+        }
+        value
+    }
+}
+
+/// Wraps a coordinate to avoid losing precision far from the origin.
+pub fn wrap(value: f64) -> f64 {
+    const PERIOD: f64 = 33_554_432.0;
+    value - (value / PERIOD + 0.5).floor() * PERIOD
+}
+
+fn fade(t: f64) -> f64 {
+    t * t * t * (t * (t * 6.0 - 15.0) + 10.0)
+}
+
+pub fn lerp(t: f64, a: f64, b: f64) -> f64 {
+    a + t * (b - a)
+}
+
+/// Linearly interpolates between `a` and `b`,
+/// clamping `t` to `0.0..=1.0`.
+pub fn clamped_lerp(a: f64, b: f64, t: f64) -> f64 {
+    if t < 0.0 {
+        a
+    } else if t > 1.0 {
+        b
+    } else {
+        lerp(t, a, b)
+    }
+}
@@ -0,0 +1,156 @@
+//! Random number generators used by vanilla world generation.
+
+/// A port of Java's `java.util.Random`, a 48-bit linear
+/// congruential generator.
+#[derive(Debug, Clone)]
+pub struct JavaRandom {
+    seed: i64,
+}
+
+const MULTIPLIER: i64 = 0x5_DEEC_E66D;
+const ADDEND: i64 = 0xB;
+const MASK: i64 = (1 << 48) - 1;
+
+impl JavaRandom {
+    pub fn new(seed: i64) -> Self {
+        let mut random = Self { seed: 0 };
+        random.set_seed(seed);
+        random
+    }
+
+    /// Creates a generator seeded for the given chunk,
+    /// as used when building the surface of a chunk.
+    pub fn for_chunk(chunk_x: i32, chunk_z: i32) -> Self {
+        Self::new(
+            i64::from(chunk_x)
+                .wrapping_mul(341_873_128_712)
+                .wrapping_add(i64::from(chunk_z).wrapping_mul(132_897_987_541)),
+        )
+    }
+
+    pub fn set_seed(&mut self, seed: i64) {
+        self.seed = (seed ^ MULTIPLIER) & MASK;
+    }
+
+    fn next(&mut self, bits: u32) -> i32 {
+        self.seed = self.seed.wrapping_mul(MULTIPLIER).wrapping_add(ADDEND) & MASK;
+        (self.seed >> (48 - bits)) as i32
+    }
+
+    pub fn next_int(&mut self) -> i32 {
+        self.next(32)
+    }
+
+    /// Returns a random integer in `0..bound`.
+    ///
+    /// # Panics
+    /// Panics if `bound` is not positive.
+    pub fn next_int_bounded(&mut self, bound: i32) -> i32 {
+        assert!(bound > 0, "bound must be positive");
+        if bound & -bound == bound {
+            return ((i64::from(bound) * i64::from(self.next(31))) >> 31) as i32;
+        }
+        loop {
+            let bits = self.next(31);
+            let value = bits % bound;
+            // Reject values from the last, incomplete
+            // range, which would otherwise be overrepresented.
+            if bits.wrapping_sub(value).wrapping_add(bound - 1) >= 0 {
+                return value;
+            }
+        }
+    }
+
+    pub fn next_long(&mut self) -> i64 {
+        (i64::from(self.next(32)) << 32).wrapping_add(i64::from(self.next(32)))
+    }
+
+    pub fn next_bool(&mut self) -> bool {
+        self.next(1) != 0
+    }
+
+    pub fn next_double(&mut self) -> f64 {
+        let high = i64::from(self.next(26)) << 27;
+        let low = i64::from(self.next(27));
+        (high + low) as f64 * (1.0 / (1u64 << 53) as f64)
+    }
+
+    /// Advances the generator by `count` steps.
+    pub fn skip(&mut self, count: usize) {
+        for _ in 0..count {
+            self.next(1);
+        }
+    }
+}
+
+/// The generator used by biome layers, which derives a
+/// random sequence from the world seed, the layer's salt and
+/// the position being sampled.
+#[derive(Debug, Clone)]
+pub struct LayerRandom {
+    seed: i64,
+    position_seed: i64,
+}
+
+impl LayerRandom {
+    pub fn new(world_seed: i64, salt: i64) -> Self {
+        let mut salt_seed = mix(salt, salt);
+        salt_seed = mix(salt_seed, salt);
+        salt_seed = mix(salt_seed, salt);
+
+        let mut seed = mix(world_seed, salt_seed);
+        seed = mix(seed, salt_seed);
+        seed = mix(seed, salt_seed);
+        Self {
+            seed,
+            position_seed: 0,
+        }
+    }
+
+    pub fn set_position(&mut self, x: i32, z: i32) {
+        let (x, z) = (i64::from(x), i64::from(z));
+        let mut seed = mix(self.seed, x);
+        seed = mix(seed, z);
+        seed = mix(seed, x);
+        seed = mix(seed, z);
+        self.position_seed = seed;
+    }
+
+    /// Returns a random integer in `0..bound`.
+    pub fn next_int(&mut self, bound: i32) -> i32 {
+        let value = (self.position_seed >> 24).rem_euclid(i64::from(bound)) as i32;
+        self.position_seed = mix(self.position_seed, self.seed);
+        value
+    }
+
+    /// Returns one of the given values at random.
+    pub fn choose(&mut self, values: &[i32]) -> i32 {
+        values[self.next_int(values.len() as i32) as usize]
+    }
+}
+
+fn mix(seed: i64, salt: i64) -> i64 {
+    seed.wrapping_mul(
+        seed.wrapping_mul(6_364_136_223_846_793_005)
+            .wrapping_add(1_442_695_040_888_963_407),
+    )
+    .wrapping_add(salt)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn matches_java_random() {
+        let mut random = JavaRandom::new(0);
+        assert_eq!(random.next_int(), -1_155_484_576);
+        assert_eq!(random.next_int(), -723_955_400);
+
+        let mut random = JavaRandom::new(0);
+        assert_eq!(random.next_long(), -4_962_768_465_676_381_896);
+
+        let mut random = JavaRandom::new(0);
+        assert!((random.next_double() - 0.730_967_787_376_657).abs() < 1e-15);
+    }
+}
@@ -0,0 +1,110 @@
+//! Settings of the vanilla generator, mirroring
+//! the noise settings of vanilla data packs.
+
+use base::{Biome, BlockId};
+use std::collections::HashMap;
+
+/// Configures the terrain generated by a [`VanillaWorldGenerator`](super::VanillaWorldGenerator).
+///
+/// The defaults match the `minecraft:overworld` noise settings.
+#[derive(Clone, Debug)]
+pub struct NoiseSettings {
+    /// Height below which empty space is filled with `default_fluid`.
+    pub sea_level: i32,
+    /// Block making up the terrain.
+    pub default_block: BlockId,
+    /// Block filling oceans and other areas below sea level.
+    pub default_fluid: BlockId,
+    /// Height of the lowest layer of the bedrock floor.
+    /// The floor is not generated if it lies outside the world.
+    pub bedrock_floor_position: i32,
+    /// Distance of the bedrock roof below the top of the world.
+    /// The roof is not generated if it lies outside the world.
+    pub bedrock_roof_position: i32,
+    /// Number of times biomes are zoomed in; each
+    /// additional zoom doubles the size of biomes.
+    pub biome_size: u32,
+    /// Number of times rivers are zoomed in; each
+    /// additional zoom doubles the spacing of rivers.
+    pub river_size: u32,
+    pub sampling: NoiseSampling,
+    pub top_slide: Slide,
+    pub bottom_slide: Slide,
+    /// Multiplier of the falloff of density with height.
+    pub density_factor: f64,
+    pub density_offset: f64,
+    /// Whether to vary the density of each column with a noise.
+    pub random_density_offset: bool,
+    /// Whether to exaggerate the depth and scale of land biomes.
+    pub amplified: bool,
+    /// Depth and scale used instead of the vanilla
+    /// values for the given biomes.
+    pub biome_terrain: HashMap<Biome, BiomeTerrain>,
+}
+
+impl Default for NoiseSettings {
+    fn default() -> Self {
+        Self {
+            sea_level: 63,
+            default_block: BlockId::stone(),
+            default_fluid: BlockId::water(),
+            bedrock_floor_position: 0,
+            bedrock_roof_position: -10,
+            biome_size: 4,
+            river_size: 4,
+            sampling: NoiseSampling::default(),
+            top_slide: Slide {
+                target: -10.0,
+                size: 3.0,
+                offset: 0.0,
+            },
+            bottom_slide: Slide {
+                target: -30.0,
+                size: 0.0,
+                offset: 0.0,
+            },
+            density_factor: 1.0,
+            density_offset: -0.468_75,
+            random_density_offset: true,
+            amplified: false,
+            biome_terrain: HashMap::new(),
+        }
+    }
+}
+
+/// Scales of the noise which shapes the terrain.
+#[derive(Copy, Clone, Debug, PartialEq)]
+pub struct NoiseSampling {
+    pub xz_scale: f64,
+    pub y_scale: f64,
+    pub xz_factor: f64,
+    pub y_factor: f64,
+}
+
+impl Default for NoiseSampling {
+    fn default() -> Self {
+        Self {
+            xz_scale: 0.999_999_981_450_774_5,
+            y_scale: 0.999_999_981_450_774_5,
+            xz_factor: 80.0,
+            y_factor: 160.0,
+        }
+    }
+}
+
+/// Blends the noise towards `target` over `size` noise
+/// cells at the top or bottom of the world, after
+/// skipping `offset` cells.
+#[derive(Copy, Clone, Debug, PartialEq)]
+pub struct Slide {
+    pub target: f64,
+    pub size: f64,
+    pub offset: f64,
+}
+
+/// The height and roughness of a biome's terrain.
+#[derive(Copy, Clone, Debug, PartialEq)]
+pub struct BiomeTerrain {
+    pub depth: f32,
+    pub scale: f32,
+}
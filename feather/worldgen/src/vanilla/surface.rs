@@ -0,0 +1,504 @@
+//! Replaces the top layers of stone with
+//! biome-specific blocks and places the bedrock floor.
+
+use super::biomes::{self, to_biome};
+use super::layers::Area;
+use super::noise::OctaveSimplexNoise;
+use super::random::JavaRandom;
+use super::settings::NoiseSettings;
+use super::zoom;
+use base::{BlockId, Chunk, SimplifiedBlockKind, CHUNK_HEIGHT};
+use std::f64::consts::PI;
+
+/// Blocks used by a surface builder.
+#[derive(Copy, Clone)]
+struct SurfaceConfig {
+    top: BlockId,
+    under: BlockId,
+    underwater: BlockId,
+}
+
+impl SurfaceConfig {
+    fn new(top: BlockId, under: BlockId, underwater: BlockId) -> Self {
+        Self {
+            top,
+            under,
+            underwater,
+        }
+    }
+
+    fn grass() -> Self {
+        Self::new(BlockId::grass_block(), BlockId::dirt(), BlockId::gravel())
+    }
+
+    fn sand() -> Self {
+        Self::new(BlockId::sand(), BlockId::sand(), BlockId::gravel())
+    }
+
+    fn stone() -> Self {
+        Self::new(BlockId::stone(), BlockId::stone(), BlockId::gravel())
+    }
+
+    fn gravel() -> Self {
+        Self::new(BlockId::gravel(), BlockId::gravel(), BlockId::gravel())
+    }
+}
+
+/// Returns the surface blocks of a biome,
+/// given the surface noise at the column.
+fn config_for(biome: i32, noise: f64) -> SurfaceConfig {
+    match biome {
+        biomes::DESERT | biomes::DESERT_HILLS | 130 | biomes::BEACH | biomes::SNOWY_BEACH => {
+            SurfaceConfig::sand()
+        }
+        biomes::WARM_OCEAN | biomes::DEEP_WARM_OCEAN => {
+            SurfaceConfig::new(BlockId::sand(), BlockId::sand(), BlockId::sand())
+        }
+        biomes::STONE_SHORE => SurfaceConfig::stone(),
+        biomes::MUSHROOM_FIELDS | biomes::MUSHROOM_FIELD_SHORE => {
+            SurfaceConfig::new(BlockId::mycelium(), BlockId::dirt(), BlockId::gravel())
+        }
+        biomes::ICE_SPIKES => {
+            SurfaceConfig::new(BlockId::snow_block(), BlockId::dirt(), BlockId::gravel())
+        }
+        id if biomes::is_mesa(id) => SurfaceConfig::new(
+            BlockId::red_sand(),
+            BlockId::white_terracotta(),
+            BlockId::gravel(),
+        ),
+        biomes::MOUNTAINS | biomes::WOODED_MOUNTAINS if noise > 1.0 => SurfaceConfig::stone(),
+        131 | 162 if noise < -1.0 || noise > 2.0 => SurfaceConfig::gravel(),
+        131 | 162 if noise > 1.0 => SurfaceConfig::stone(),
+        163 | 164 if noise > 1.75 => SurfaceConfig::stone(),
+        163 | 164 if noise > -0.5 => {
+            SurfaceConfig::new(BlockId::coarse_dirt(), BlockId::dirt(), BlockId::gravel())
+        }
+        biomes::GIANT_TREE_TAIGA | biomes::GIANT_TREE_TAIGA_HILLS | 160 | 161 if noise > 1.75 => {
+            SurfaceConfig::new(BlockId::coarse_dirt(), BlockId::dirt(), BlockId::gravel())
+        }
+        biomes::GIANT_TREE_TAIGA | biomes::GIANT_TREE_TAIGA_HILLS | 160 | 161 if noise > -0.95 => {
+            SurfaceConfig::new(BlockId::podzol(), BlockId::dirt(), BlockId::gravel())
+        }
+        _ => SurfaceConfig::grass(),
+    }
+}
+
+/// Builds the surface and bedrock of chunks
+/// whose terrain has been filled with the default block and fluid.
+pub struct SurfaceBuilder {
+    noise: OctaveSimplexNoise,
+    badlands: Badlands,
+    /// Seed used to pick the biome of each column.
+    biome_seed: i64,
+}
+
+impl SurfaceBuilder {
+    pub fn new(seed: i64, noise: OctaveSimplexNoise) -> Self {
+        Self {
+            noise,
+            badlands: Badlands::new(seed),
+            biome_seed: zoom::obfuscate_seed(seed),
+        }
+    }
+
+    /// Builds the surface of a chunk. `biomes` must contain
+    /// the biomes within one quarter of the chunk.
+    pub fn build(&self, chunk: &mut Chunk, biomes: &Area, settings: &NoiseSettings) {
+        let pos = chunk.position();
+        let mut random = JavaRandom::for_chunk(pos.x, pos.z);
+
+        for x in 0..16 {
+            for z in 0..16 {
+                let (block_x, block_z) = (pos.x * 16 + x as i32, pos.z * 16 + z as i32);
+                let start_height = (0..256)
+                    .rev()
+                    .find(|&y| {
+                        chunk
+                            .block_at(x, y, z)
+                            .map_or(false, |block| !block.is_air())
+                    })
+                    .map_or(0, |y| y as i32 + 1);
+                let surface_noise = self
+                    .noise
+                    .sample_surface(f64::from(block_x) * 0.0625, f64::from(block_z) * 0.0625)
+                    * 15.0;
+                let biome = zoom::biome_at(self.biome_seed, block_x, block_z, biomes);
+
+                let column = Column {
+                    x,
+                    z,
+                    start_height,
+                    noise: surface_noise,
+                    config: config_for(biome, surface_noise),
+                    temperature: to_biome(biome).temperature(),
+                };
+                match biome {
+                    biomes::BADLANDS
+                    | biomes::BADLANDS_PLATEAU
+                    | biomes::MODIFIED_BADLANDS_PLATEAU => self.badlands.build_column(
+                        chunk,
+                        &mut random,
+                        &column,
+                        settings,
+                        BadlandsStyle::Plain,
+                    ),
+                    biomes::WOODED_BADLANDS_PLATEAU | biomes::MODIFIED_WOODED_BADLANDS_PLATEAU => {
+                        self.badlands.build_column(
+                            chunk,
+                            &mut random,
+                            &column,
+                            settings,
+                            BadlandsStyle::Wooded,
+                        )
+                    }
+                    biomes::ERODED_BADLANDS => self.badlands.build_column(
+                        chunk,
+                        &mut random,
+                        &column,
+                        settings,
+                        BadlandsStyle::Eroded,
+                    ),
+                    _ => build_column(chunk, &mut random, &column, settings),
+                }
+            }
+        }
+
+        build_bedrock(chunk, &mut random, settings);
+    }
+}
+
+/// A column of blocks to build the surface of.
+struct Column {
+    x: usize,
+    z: usize,
+    start_height: i32,
+    noise: f64,
+    config: SurfaceConfig,
+    temperature: f32,
+}
+
+/// Port of vanilla's default surface builder.
+fn build_column(
+    chunk: &mut Chunk,
+    random: &mut JavaRandom,
+    column: &Column,
+    settings: &NoiseSettings,
+) {
+    let Column {
+        x,
+        z,
+        start_height,
+        noise,
+        config,
+        temperature,
+    } = *column;
+    let sea_level = settings.sea_level;
+    let depth = (noise / 3.0 + 3.0 + random.next_double() * 0.25) as i32;
+    let mut top = config.top;
+    let mut under = config.under;
+    let mut remaining = -1;
+
+    for y in (0..=start_height.min(255)).rev() {
+        let block = match chunk.block_at(x, y as usize, z) {
+            Some(block) => block,
+            None => continue,
+        };
+        if block.is_air() {
+            remaining = -1;
+            continue;
+        }
+        if block != settings.default_block {
+            continue;
+        }
+
+        if remaining == -1 {
+            if depth <= 0 {
+                top = BlockId::air();
+                under = settings.default_block;
+            } else if (sea_level - 4..=sea_level + 1).contains(&y) {
+                top = config.top;
+                under = config.under;
+            }
+
+            if y < sea_level && top.is_air() {
+                top = if temperature < 0.15 {
+                    BlockId::ice()
+                } else {
+                    settings.default_fluid
+                };
+            }
+
+            remaining = depth;
+            let block = if y >= sea_level - 1 {
+                top
+            } else if y < sea_level - 7 - depth {
+                top = BlockId::air();
+                under = settings.default_block;
+                config.underwater
+            } else {
+                under
+            };
+            chunk.set_block_at(x, y as usize, z, block);
+        } else if remaining > 0 {
+            remaining -= 1;
+            chunk.set_block_at(x, y as usize, z, under);
+        }
+    }
+}
+
+/// The variants of vanilla's badlands surface builder.
+#[derive(Copy, Clone, PartialEq, Eq)]
+enum BadlandsStyle {
+    Plain,
+    /// Covers high ground with coarse dirt and grass.
+    Wooded,
+    /// Raises pillars of terracotta out of the ground.
+    Eroded,
+}
+
+/// Port of vanilla's badlands surface builders, which
+/// layer the ground with bands of colored terracotta.
+struct Badlands {
+    /// The terracotta at each height, repeating every 64 blocks.
+    bands: [BlockId; 64],
+    band_offset_noise: OctaveSimplexNoise,
+    pillar_noise: OctaveSimplexNoise,
+    pillar_roof_noise: OctaveSimplexNoise,
+}
+
+impl Badlands {
+    fn new(seed: i64) -> Self {
+        let mut random = JavaRandom::new(seed);
+        let band_offset_noise = OctaveSimplexNoise::new(&mut random, 1);
+        let bands = generate_bands(&mut random);
+
+        let mut random = JavaRandom::new(seed);
+        let pillar_noise = OctaveSimplexNoise::new(&mut random, 4);
+        let pillar_roof_noise = OctaveSimplexNoise::new(&mut random, 1);
+
+        Self {
+            bands,
+            band_offset_noise,
+            pillar_noise,
+            pillar_roof_noise,
+        }
+    }
+
+    /// Returns the terracotta band at the given position.
+    fn band(&self, x: i32, y: i32, z: i32) -> BlockId {
+        let offset =
+            self.band_offset_noise
+                .sample(f64::from(x) / 512.0, f64::from(z) / 512.0, false)
+                * 2.0;
+        // Java's `Math.round` rounds halves up.
+        let offset = (offset + 0.5).floor() as i32;
+        self.bands[(y + offset).rem_euclid(64) as usize]
+    }
+
+    /// Returns the height up to which an eroded badlands
+    /// pillar fills the column, or zero if there is none.
+    fn pillar_height(&self, x: i32, z: i32, noise: f64) -> f64 {
+        let (x, z) = (f64::from(x), f64::from(z));
+        let height = noise
+            .abs()
+            .min(self.pillar_noise.sample(x * 0.25, z * 0.25, false) * 15.0);
+        if height <= 0.0 {
+            return 0.0;
+        }
+        let roof = self
+            .pillar_roof_noise
+            .sample(x * 0.001_953_125, z * 0.001_953_125, false)
+            .abs();
+        (height * height * 2.5).min((roof * 50.0).ceil() + 14.0) + 64.0
+    }
+
+    fn build_column(
+        &self,
+        chunk: &mut Chunk,
+        random: &mut JavaRandom,
+        column: &Column,
+        settings: &NoiseSettings,
+        style: BadlandsStyle,
+    ) {
+        let Column {
+            x,
+            z,
+            start_height,
+            noise,
+            config,
+            ..
+        } = *column;
+        let pos = chunk.position();
+        let (block_x, block_z) = (pos.x * 16 + x as i32, pos.z * 16 + z as i32);
+        let sea_level = settings.sea_level;
+        let pillar_height = if style == BadlandsStyle::Eroded {
+            self.pillar_height(block_x, block_z, noise) as i32
+        } else {
+            0
+        };
+        let depth = (noise / 3.0 + 3.0 + random.next_double() * 0.25) as i32;
+        let plain_terracotta = (noise / 3.0 * PI).cos() > 0.0;
+        let start_height = if style == BadlandsStyle::Eroded {
+            start_height.max(pillar_height + 1)
+        } else {
+            start_height
+        };
+
+        let mut under = config.under;
+        let mut remaining = -1;
+        let mut above_sand = false;
+        let mut replaced = 0;
+        for y in (0..=start_height.min(255)).rev() {
+            // Only the eroded builder continues past
+            // the first 15 blocks of the default block.
+            if style != BadlandsStyle::Eroded && replaced >= 15 {
+                break;
+            }
+            let mut block = match chunk.block_at(x, y as usize, z) {
+                Some(block) => block,
+                None => continue,
+            };
+            if block.is_air() && y < pillar_height {
+                block = settings.default_block;
+                chunk.set_block_at(x, y as usize, z, block);
+            }
+            if block.is_air() {
+                remaining = -1;
+                continue;
+            }
+            if block != settings.default_block {
+                continue;
+            }
+
+            if remaining == -1 {
+                above_sand = false;
+                if depth <= 0 {
+                    under = settings.default_block;
+                } else if (sea_level - 4..=sea_level + 1).contains(&y) {
+                    under = config.under;
+                }
+
+                remaining = depth + (y - sea_level).max(0);
+                let block = if y < sea_level - 1 {
+                    if under.simplified_kind() == SimplifiedBlockKind::Teracotta {
+                        BlockId::orange_terracotta()
+                    } else {
+                        under
+                    }
+                } else if style == BadlandsStyle::Wooded && y > 86 + depth * 2 {
+                    if plain_terracotta {
+                        BlockId::coarse_dirt()
+                    } else {
+                        BlockId::grass_block()
+                    }
+                } else if y > sea_level + 3 + depth {
+                    if (64..=127).contains(&y) {
+                        if plain_terracotta {
+                            BlockId::terracotta()
+                        } else {
+                            self.band(block_x, y, block_z)
+                        }
+                    } else {
+                        BlockId::orange_terracotta()
+                    }
+                } else {
+                    above_sand = true;
+                    config.top
+                };
+                chunk.set_block_at(x, y as usize, z, block);
+            } else if remaining > 0 {
+                remaining -= 1;
+                let block = if above_sand {
+                    BlockId::orange_terracotta()
+                } else {
+                    self.band(block_x, y, block_z)
+                };
+                chunk.set_block_at(x, y as usize, z, block);
+            }
+            replaced += 1;
+        }
+    }
+}
+
+/// Generates the terracotta bands of badlands, consuming
+/// random numbers in the same order as vanilla.
+fn generate_bands(random: &mut JavaRandom) -> [BlockId; 64] {
+    let mut bands = [BlockId::terracotta(); 64];
+
+    let mut y = 0;
+    while y < 64 {
+        y += random.next_int_bounded(5) as usize + 1;
+        if y < 64 {
+            bands[y] = BlockId::orange_terracotta();
+        }
+        y += 1;
+    }
+
+    for &(block, min_length) in &[
+        (BlockId::yellow_terracotta(), 1),
+        (BlockId::brown_terracotta(), 2),
+        (BlockId::red_terracotta(), 1),
+    ] {
+        let count = random.next_int_bounded(4) + 2;
+        for _ in 0..count {
+            let length = random.next_int_bounded(3) as usize + min_length;
+            let start = random.next_int_bounded(64) as usize;
+            for band in bands.iter_mut().skip(start).take(length) {
+                *band = block;
+            }
+        }
+    }
+
+    let count = random.next_int_bounded(3) + 3;
+    let mut y = 0;
+    for _ in 0..count {
+        y += random.next_int_bounded(16) as usize + 4;
+        if y < 64 {
+            bands[y] = BlockId::white_terracotta();
+            if y > 1 && random.next_bool() {
+                bands[y - 1] = BlockId::light_gray_terracotta();
+            }
+            if y < 63 && random.next_bool() {
+                bands[y + 1] = BlockId::light_gray_terracotta();
+            }
+        }
+    }
+    bands
+}
+
+/// Places the bedrock floor and roof, each five layers
+/// thick and becoming sparser away from the world's edge.
+fn build_bedrock(chunk: &mut Chunk, random: &mut JavaRandom, settings: &NoiseSettings) {
+    let height = CHUNK_HEIGHT as i32;
+    let floor = settings.bedrock_floor_position;
+    let roof = height - 1 - settings.bedrock_roof_position;
+    let has_floor = floor + 4 >= 0 && floor < height;
+    let has_roof = roof + 4 >= 0 && roof < height;
+    if !has_floor && !has_roof {
+        return;
+    }
+
+    let mut set_bedrock = |x: usize, y: i32, z: usize| {
+        if y >= 0 {
+            chunk.set_block_at(x, y as usize, z, BlockId::bedrock());
+        }
+    };
+    for z in 0..16 {
+        for x in 0..16 {
+            if has_roof {
+                for offset in 0..5 {
+                    if offset <= random.next_int_bounded(5) {
+                        set_bedrock(x, roof - offset, z);
+                    }
+                }
+            }
+            if has_floor {
+                for offset in (0..=4).rev() {
+                    if offset <= random.next_int_bounded(5) {
+                        set_bedrock(x, floor + offset, z);
+                    }
+                }
+            }
+        }
+    }
+}
@@ -0,0 +1,83 @@
+//! Port of vanilla's fuzzed biome zoom, which picks the biome
+//! of each block column from the biomes at quarter resolution.
+
+use super::layers::Area;
+use sha2::{Digest, Sha256};
+
+/// Derives the seed used to fuzz biome positions
+/// from the first eight bytes of a SHA-256 hash of the world seed.
+pub fn obfuscate_seed(seed: i64) -> i64 {
+    let hash = Sha256::digest(&seed.to_le_bytes());
+    let mut bytes = [0; 8];
+    bytes.copy_from_slice(&hash[..8]);
+    i64::from_le_bytes(bytes)
+}
+
+/// Returns the biome of the block column at the given coordinates.
+/// `biomes` must contain the biomes from one quarter below
+/// to one quarter above the column's quarter.
+pub fn biome_at(seed: i64, x: i32, z: i32, biomes: &Area) -> i32 {
+    // Vanilla zooms in three dimensions, but the overworld
+    // always samples at y = 0.
+    let (x, y, z) = (x - 2, -2, z - 2);
+    let (quart_x, quart_y, quart_z) = (x >> 2, y >> 2, z >> 2);
+    let (frac_x, frac_y, frac_z) = (
+        f64::from(x & 3) / 4.0,
+        f64::from(y & 3) / 4.0,
+        f64::from(z & 3) / 4.0,
+    );
+
+    let corner_offsets = |corner: i32| ((corner >> 2) & 1, (corner >> 1) & 1, corner & 1);
+    let mut closest = 0;
+    let mut closest_distance = f64::INFINITY;
+    for corner in 0..8 {
+        let (offset_x, offset_y, offset_z) = corner_offsets(corner);
+        let distance = fiddled_distance(
+            seed,
+            (quart_x + offset_x, quart_y + offset_y, quart_z + offset_z),
+            (
+                frac_x - f64::from(offset_x),
+                frac_y - f64::from(offset_y),
+                frac_z - f64::from(offset_z),
+            ),
+        );
+        if distance < closest_distance {
+            closest = corner;
+            closest_distance = distance;
+        }
+    }
+
+    let (offset_x, _, offset_z) = corner_offsets(closest);
+    biomes.get(quart_x + offset_x, quart_z + offset_z)
+}
+
+/// Squared distance to a quarter's corner, jittered
+/// by a hash of the corner's position.
+fn fiddled_distance(seed: i64, corner: (i32, i32, i32), delta: (f64, f64, f64)) -> f64 {
+    let (x, y, z) = corner;
+    let mut hash = seed;
+    for &value in &[x, y, z, x, y, z] {
+        hash = next_hash(hash, i64::from(value));
+    }
+    let fiddle_x = fiddle(hash);
+    hash = next_hash(hash, seed);
+    let fiddle_y = fiddle(hash);
+    hash = next_hash(hash, seed);
+    let fiddle_z = fiddle(hash);
+
+    let square = |value: f64| value * value;
+    square(delta.2 + fiddle_z) + square(delta.1 + fiddle_y) + square(delta.0 + fiddle_x)
+}
+
+fn next_hash(hash: i64, value: i64) -> i64 {
+    hash.wrapping_mul(
+        hash.wrapping_mul(6_364_136_223_846_793_005)
+            .wrapping_add(1_442_695_040_888_963_407),
+    )
+    .wrapping_add(value)
+}
+
+fn fiddle(hash: i64) -> f64 {
+    let value = (hash >> 24).rem_euclid(1024) as f64 / 1024.0;
+    (value - 0.5) * 0.9
+}
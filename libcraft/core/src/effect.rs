@@ -0,0 +1,166 @@
+//! Data sourced from: <https://minecraft.fandom.com/wiki/Effect>
+
+use serde::{Deserialize, Serialize};
+
+/// A status effect which can be applied to living entities.
+#[derive(Copy, Clone, Debug, PartialEq, Eq, Hash, PartialOrd, Ord, Serialize, Deserialize)]
+pub enum StatusEffect {
+    Speed,
+    Slowness,
+    Haste,
+    MiningFatigue,
+    Strength,
+    InstantHealth,
+    InstantDamage,
+    JumpBoost,
+    Nausea,
+    Regeneration,
+    Resistance,
+    FireResistance,
+    WaterBreathing,
+    Invisibility,
+    Blindness,
+    NightVision,
+    Hunger,
+    Weakness,
+    Poison,
+    Wither,
+    HealthBoost,
+    Absorption,
+    Saturation,
+    Glowing,
+    Levitation,
+    Luck,
+    BadLuck,
+    SlowFalling,
+    ConduitPower,
+    DolphinsGrace,
+    BadOmen,
+    HeroOfTheVillage,
+}
+
+impl StatusEffect {
+    /// All status effects, ordered by ID.
+    pub const ALL: [StatusEffect; 32] = [
+        StatusEffect::Speed,
+        StatusEffect::Slowness,
+        StatusEffect::Haste,
+        StatusEffect::MiningFatigue,
+        StatusEffect::Strength,
+        StatusEffect::InstantHealth,
+        StatusEffect::InstantDamage,
+        StatusEffect::JumpBoost,
+        StatusEffect::Nausea,
+        StatusEffect::Regeneration,
+        StatusEffect::Resistance,
+        StatusEffect::FireResistance,
+        StatusEffect::WaterBreathing,
+        StatusEffect::Invisibility,
+        StatusEffect::Blindness,
+        StatusEffect::NightVision,
+        StatusEffect::Hunger,
+        StatusEffect::Weakness,
+        StatusEffect::Poison,
+        StatusEffect::Wither,
+        StatusEffect::HealthBoost,
+        StatusEffect::Absorption,
+        StatusEffect::Saturation,
+        StatusEffect::Glowing,
+        StatusEffect::Levitation,
+        StatusEffect::Luck,
+        StatusEffect::BadLuck,
+        StatusEffect::SlowFalling,
+        StatusEffect::ConduitPower,
+        StatusEffect::DolphinsGrace,
+        StatusEffect::BadOmen,
+        StatusEffect::HeroOfTheVillage,
+    ];
+
+    /// Returns the protocol and NBT ID of this effect.
+    pub fn id(self) -> u8 {
+        self as u8 + 1
+    }
+
+    /// Gets an effect by its ID.
+    pub fn from_id(id: u8) -> Option<Self> {
+        Self::ALL.get((id as usize).checked_sub(1)?).copied()
+    }
+
+    /// Returns the name of this effect, as used in commands,
+    /// without the `minecraft:` namespace.
+    pub fn name(self) -> &'static str {
+        match self {
+            StatusEffect::Speed => "speed",
+            StatusEffect::Slowness => "slowness",
+            StatusEffect::Haste => "haste",
+            StatusEffect::MiningFatigue => "mining_fatigue",
+            StatusEffect::Strength => "strength",
+            StatusEffect::InstantHealth => "instant_health",
+            StatusEffect::InstantDamage => "instant_damage",
+            StatusEffect::JumpBoost => "jump_boost",
+            StatusEffect::Nausea => "nausea",
+            StatusEffect::Regeneration => "regeneration",
+            StatusEffect::Resistance => "resistance",
+            StatusEffect::FireResistance => "fire_resistance",
+            StatusEffect::WaterBreathing => "water_breathing",
+            StatusEffect::Invisibility => "invisibility",
+            StatusEffect::Blindness => "blindness",
+            StatusEffect::NightVision => "night_vision",
+            StatusEffect::Hunger => "hunger",
+            StatusEffect::Weakness => "weakness",
+            StatusEffect::Poison => "poison",
+            StatusEffect::Wither => "wither",
+            StatusEffect::HealthBoost => "health_boost",
+            StatusEffect::Absorption => "absorption",
+            StatusEffect::Saturation => "saturation",
+            StatusEffect::Glowing => "glowing",
+            StatusEffect::Levitation => "levitation",
+            StatusEffect::Luck => "luck",
+            StatusEffect::BadLuck => "unluck",
+            StatusEffect::SlowFalling => "slow_falling",
+            StatusEffect::ConduitPower => "conduit_power",
+            StatusEffect::DolphinsGrace => "dolphins_grace",
+            StatusEffect::BadOmen => "bad_omen",
+            StatusEffect::HeroOfTheVillage => "hero_of_the_village",
+        }
+    }
+
+    /// Gets an effect by its name. The `minecraft:` namespace is optional.
+    pub fn from_name(name: &str) -> Option<Self> {
+        let name = name.strip_prefix("minecraft:").unwrap_or(name);
+        Self::ALL
+            .iter()
+            .copied()
+            .find(|effect| effect.name() == name)
+    }
+
+    /// Returns whether this effect is applied once
+    /// rather than over a duration.
+    pub fn is_instant(self) -> bool {
+        matches!(
+            self,
+            StatusEffect::InstantHealth | StatusEffect::InstantDamage
+        )
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn ids_roundtrip() {
+        for &effect in &StatusEffect::ALL {
+            assert_eq!(StatusEffect::from_id(effect.id()), Some(effect));
+            assert_eq!(StatusEffect::from_name(effect.name()), Some(effect));
+        }
+        assert_eq!(StatusEffect::Speed.id(), 1);
+        assert_eq!(StatusEffect::HeroOfTheVillage.id(), 32);
+        assert_eq!(StatusEffect::from_id(0), None);
+        assert_eq!(StatusEffect::from_id(33), None);
+        assert_eq!(
+            StatusEffect::from_name("minecraft:night_vision"),
+            Some(StatusEffect::NightVision)
+        );
+    }
+}
@@ -0,0 +1,369 @@
+//! Natural spawning data: mob categories, mob caps,
+//! and the mobs which spawn in each biome.
+//!
+//! Data sourced from: <https://minecraft.fandom.com/wiki/Spawn>
+
+use crate::{Biome, EntityKind};
+
+/// A category of naturally spawning mobs.
+///
+/// Each category has its own mob cap.
+#[derive(Copy, Clone, Debug, PartialEq, Eq, Hash)]
+pub enum MobCategory {
+    /// Hostile mobs, which spawn in darkness.
+    Monster,
+    /// Passive land animals, which spawn on grass in light.
+    Creature,
+    /// Bats, which spawn in dark caves.
+    Ambient,
+    /// Squid and fish, which spawn in water.
+    Water,
+}
+
+impl MobCategory {
+    /// All mob categories.
+    pub const ALL: [MobCategory; 4] = [
+        MobCategory::Monster,
+        MobCategory::Creature,
+        MobCategory::Ambient,
+        MobCategory::Water,
+    ];
+
+    /// Returns the maximum number of mobs of this category
+    /// around a single player. The total mob cap scales with the number
+    /// of chunks in which mobs can spawn.
+    pub fn mob_cap(self) -> u32 {
+        match self {
+            MobCategory::Monster => 70,
+            MobCategory::Creature => 10,
+            MobCategory::Ambient => 15,
+            MobCategory::Water => 5,
+        }
+    }
+
+    /// Returns the category of the given entity, or `None` if
+    /// the entity does not spawn naturally.
+    pub fn of(kind: EntityKind) -> Option<Self> {
+        use EntityKind::*;
+        match kind {
+            Blaze | CaveSpider | Creeper | Drowned | ElderGuardian | Enderman | Endermite
+            | Evoker | Ghast | Giant | Guardian | Hoglin | Husk | Illusioner | MagmaCube
+            | Phantom | Piglin | PiglinBrute | Pillager | Ravager | Shulker | Silverfish
+            | Skeleton | Slime | Spider | Stray | Vex | Vindicator | Witch | WitherSkeleton
+            | Zoglin | Zombie | ZombieVillager | ZombifiedPiglin => Some(MobCategory::Monster),
+            Bee | Cat | Chicken | Cow | Donkey | Fox | Horse | Llama | Mooshroom | Mule
+            | Ocelot | Panda | Parrot | Pig | PolarBear | Rabbit | Sheep | SkeletonHorse
+            | Strider | Turtle | Wolf | ZombieHorse => Some(MobCategory::Creature),
+            Bat => Some(MobCategory::Ambient),
+            Cod | Dolphin | Pufferfish | Salmon | Squid | TropicalFish => Some(MobCategory::Water),
+            _ => None,
+        }
+    }
+}
+
+/// A mob which can spawn naturally in a biome.
+#[derive(Copy, Clone, Debug, PartialEq, Eq)]
+pub struct SpawnEntry {
+    pub kind: EntityKind,
+    /// Relative chance of this entry being picked.
+    pub weight: u32,
+    /// Minimum number of mobs spawned in one group.
+    pub min_group: u32,
+    /// Maximum number of mobs spawned in one group.
+    pub max_group: u32,
+}
+
+impl SpawnEntry {
+    const fn new(kind: EntityKind, weight: u32, min_group: u32, max_group: u32) -> Self {
+        Self {
+            kind,
+            weight,
+            min_group,
+            max_group,
+        }
+    }
+}
+
+const OVERWORLD_MONSTERS: &[SpawnEntry] = &[
+    SpawnEntry::new(EntityKind::Spider, 100, 4, 4),
+    SpawnEntry::new(EntityKind::Zombie, 95, 4, 4),
+    SpawnEntry::new(EntityKind::ZombieVillager, 5, 1, 1),
+    SpawnEntry::new(EntityKind::Skeleton, 100, 4, 4),
+    SpawnEntry::new(EntityKind::Creeper, 100, 4, 4),
+    SpawnEntry::new(EntityKind::Slime, 100, 4, 4),
+    SpawnEntry::new(EntityKind::Enderman, 10, 1, 4),
+    SpawnEntry::new(EntityKind::Witch, 5, 1, 1),
+];
+
+const DESERT_MONSTERS: &[SpawnEntry] = &[
+    SpawnEntry::new(EntityKind::Spider, 100, 4, 4),
+    SpawnEntry::new(EntityKind::Zombie, 19, 4, 4),
+    SpawnEntry::new(EntityKind::ZombieVillager, 1, 1, 1),
+    SpawnEntry::new(EntityKind::Husk, 80, 4, 4),
+    SpawnEntry::new(EntityKind::Skeleton, 100, 4, 4),
+    SpawnEntry::new(EntityKind::Creeper, 100, 4, 4),
+    SpawnEntry::new(EntityKind::Slime, 100, 4, 4),
+    SpawnEntry::new(EntityKind::Enderman, 10, 1, 4),
+    SpawnEntry::new(EntityKind::Witch, 5, 1, 1),
+];
+
+const SNOWY_MONSTERS: &[SpawnEntry] = &[
+    SpawnEntry::new(EntityKind::Spider, 100, 4, 4),
+    SpawnEntry::new(EntityKind::Zombie, 95, 4, 4),
+    SpawnEntry::new(EntityKind::ZombieVillager, 5, 1, 1),
+    SpawnEntry::new(EntityKind::Skeleton, 20, 4, 4),
+    SpawnEntry::new(EntityKind::Stray, 80, 4, 4),
+    SpawnEntry::new(EntityKind::Creeper, 100, 4, 4),
+    SpawnEntry::new(EntityKind::Slime, 100, 4, 4),
+    SpawnEntry::new(EntityKind::Enderman, 10, 1, 4),
+    SpawnEntry::new(EntityKind::Witch, 5, 1, 1),
+];
+
+const OCEAN_MONSTERS: &[SpawnEntry] = &[
+    SpawnEntry::new(EntityKind::Spider, 100, 4, 4),
+    SpawnEntry::new(EntityKind::Zombie, 95, 4, 4),
+    SpawnEntry::new(EntityKind::Drowned, 5, 1, 1),
+    SpawnEntry::new(EntityKind::ZombieVillager, 5, 1, 1),
+    SpawnEntry::new(EntityKind::Skeleton, 100, 4, 4),
+    SpawnEntry::new(EntityKind::Creeper, 100, 4, 4),
+    SpawnEntry::new(EntityKind::Slime, 100, 4, 4),
+    SpawnEntry::new(EntityKind::Enderman, 10, 1, 4),
+    SpawnEntry::new(EntityKind::Witch, 5, 1, 1),
+];
+
+const NETHER_MONSTERS: &[SpawnEntry] = &[
+    SpawnEntry::new(EntityKind::Ghast, 50, 4, 4),
+    SpawnEntry::new(EntityKind::ZombifiedPiglin, 100, 4, 4),
+    SpawnEntry::new(EntityKind::MagmaCube, 2, 4, 4),
+    SpawnEntry::new(EntityKind::Enderman, 1, 4, 4),
+    SpawnEntry::new(EntityKind::Piglin, 15, 4, 4),
+];
+
+const CRIMSON_FOREST_MONSTERS: &[SpawnEntry] = &[
+    SpawnEntry::new(EntityKind::ZombifiedPiglin, 1, 2, 4),
+    SpawnEntry::new(EntityKind::Hoglin, 9, 3, 4),
+    SpawnEntry::new(EntityKind::Piglin, 5, 3, 4),
+];
+
+const WARPED_FOREST_MONSTERS: &[SpawnEntry] = &[SpawnEntry::new(EntityKind::Enderman, 1, 4, 4)];
+
+const SOUL_SAND_VALLEY_MONSTERS: &[SpawnEntry] = &[
+    SpawnEntry::new(EntityKind::Skeleton, 20, 5, 5),
+    SpawnEntry::new(EntityKind::Ghast, 50, 4, 4),
+    SpawnEntry::new(EntityKind::Enderman, 1, 4, 4),
+];
+
+const BASALT_DELTAS_MONSTERS: &[SpawnEntry] = &[
+    SpawnEntry::new(EntityKind::Ghast, 40, 1, 1),
+    SpawnEntry::new(EntityKind::MagmaCube, 100, 2, 5),
+];
+
+const END_MONSTERS: &[SpawnEntry] = &[SpawnEntry::new(EntityKind::Enderman, 10, 4, 4)];
+
+const FARM_ANIMALS: &[SpawnEntry] = &[
+    SpawnEntry::new(EntityKind::Sheep, 12, 4, 4),
+    SpawnEntry::new(EntityKind::Pig, 10, 4, 4),
+    SpawnEntry::new(EntityKind::Chicken, 10, 4, 4),
+    SpawnEntry::new(EntityKind::Cow, 8, 4, 4),
+];
+
+const PLAINS_CREATURES: &[SpawnEntry] = &[
+    SpawnEntry::new(EntityKind::Sheep, 12, 4, 4),
+    SpawnEntry::new(EntityKind::Pig, 10, 4, 4),
+    SpawnEntry::new(EntityKind::Chicken, 10, 4, 4),
+    SpawnEntry::new(EntityKind::Cow, 8, 4, 4),
+    SpawnEntry::new(EntityKind::Horse, 5, 2, 6),
+    SpawnEntry::new(EntityKind::Donkey, 1, 1, 3),
+];
+
+const FOREST_CREATURES: &[SpawnEntry] = &[
+    SpawnEntry::new(EntityKind::Sheep, 12, 4, 4),
+    SpawnEntry::new(EntityKind::Pig, 10, 4, 4),
+    SpawnEntry::new(EntityKind::Chicken, 10, 4, 4),
+    SpawnEntry::new(EntityKind::Cow, 8, 4, 4),
+    SpawnEntry::new(EntityKind::Wolf, 5, 4, 4),
+];
+
+const TAIGA_CREATURES: &[SpawnEntry] = &[
+    SpawnEntry::new(EntityKind::Sheep, 12, 4, 4),
+    SpawnEntry::new(EntityKind::Pig, 10, 4, 4),
+    SpawnEntry::new(EntityKind::Chicken, 10, 4, 4),
+    SpawnEntry::new(EntityKind::Cow, 8, 4, 4),
+    SpawnEntry::new(EntityKind::Wolf, 8, 4, 4),
+    SpawnEntry::new(EntityKind::Rabbit, 4, 2, 3),
+    SpawnEntry::new(EntityKind::Fox, 8, 2, 4),
+];
+
+const SAVANNA_CREATURES: &[SpawnEntry] = &[
+    SpawnEntry::new(EntityKind::Sheep, 12, 4, 4),
+    SpawnEntry::new(EntityKind::Pig, 10, 4, 4),
+    SpawnEntry::new(EntityKind::Chicken, 10, 4, 4),
+    SpawnEntry::new(EntityKind::Cow, 8, 4, 4),
+    SpawnEntry::new(EntityKind::Horse, 1, 2, 6),
+    SpawnEntry::new(EntityKind::Donkey, 1, 1, 1),
+    SpawnEntry::new(EntityKind::Llama, 8, 4, 4),
+];
+
+const MOUNTAINS_CREATURES: &[SpawnEntry] = &[
+    SpawnEntry::new(EntityKind::Sheep, 12, 4, 4),
+    SpawnEntry::new(EntityKind::Pig, 10, 4, 4),
+    SpawnEntry::new(EntityKind::Chicken, 10, 4, 4),
+    SpawnEntry::new(EntityKind::Cow, 8, 4, 4),
+    SpawnEntry::new(EntityKind::Llama, 5, 4, 6),
+];
+
+const JUNGLE_CREATURES: &[SpawnEntry] = &[
+    SpawnEntry::new(EntityKind::Sheep, 12, 4, 4),
+    SpawnEntry::new(EntityKind::Pig, 10, 4, 4),
+    SpawnEntry::new(EntityKind::Chicken, 10, 4, 4),
+    SpawnEntry::new(EntityKind::Cow, 8, 4, 4),
+    SpawnEntry::new(EntityKind::Parrot, 40, 1, 2),
+    SpawnEntry::new(EntityKind::Panda, 1, 1, 2),
+    SpawnEntry::new(EntityKind::Ocelot, 2, 1, 3),
+];
+
+const SNOWY_CREATURES: &[SpawnEntry] = &[
+    SpawnEntry::new(EntityKind::Rabbit, 10, 2, 3),
+    SpawnEntry::new(EntityKind::PolarBear, 1, 1, 2),
+];
+
+const DESERT_CREATURES: &[SpawnEntry] = &[SpawnEntry::new(EntityKind::Rabbit, 4, 2, 3)];
+
+const MUSHROOM_CREATURES: &[SpawnEntry] = &[SpawnEntry::new(EntityKind::Mooshroom, 8, 4, 8)];
+
+const BEACH_CREATURES: &[SpawnEntry] = &[SpawnEntry::new(EntityKind::Turtle, 5, 2, 5)];
+
+const BATS: &[SpawnEntry] = &[SpawnEntry::new(EntityKind::Bat, 10, 8, 8)];
+
+const OCEAN_WATER: &[SpawnEntry] = &[
+    SpawnEntry::new(EntityKind::Squid, 1, 1, 4),
+    SpawnEntry::new(EntityKind::Cod, 10, 3, 6),
+    SpawnEntry::new(EntityKind::Dolphin, 1, 1, 2),
+];
+
+const WARM_OCEAN_WATER: &[SpawnEntry] = &[
+    SpawnEntry::new(EntityKind::Squid, 10, 4, 4),
+    SpawnEntry::new(EntityKind::Pufferfish, 15, 1, 3),
+    SpawnEntry::new(EntityKind::TropicalFish, 25, 8, 8),
+    SpawnEntry::new(EntityKind::Dolphin, 2, 1, 2),
+];
+
+const COLD_OCEAN_WATER: &[SpawnEntry] = &[
+    SpawnEntry::new(EntityKind::Squid, 3, 1, 4),
+    SpawnEntry::new(EntityKind::Cod, 15, 3, 6),
+    SpawnEntry::new(EntityKind::Salmon, 15, 1, 5),
+];
+
+const FROZEN_OCEAN_WATER: &[SpawnEntry] = &[
+    SpawnEntry::new(EntityKind::Squid, 1, 1, 4),
+    SpawnEntry::new(EntityKind::Salmon, 15, 1, 5),
+];
+
+const RIVER_WATER: &[SpawnEntry] = &[
+    SpawnEntry::new(EntityKind::Squid, 2, 1, 4),
+    SpawnEntry::new(EntityKind::Salmon, 5, 1, 5),
+];
+
+impl Biome {
+    /// Returns the mobs of the given category which can
+    /// spawn naturally in this biome.
+    pub fn spawn_entries(self, category: MobCategory) -> &'static [SpawnEntry] {
+        use Biome::*;
+        match category {
+            MobCategory::Monster => match self {
+                MushroomFields | MushroomFieldShore | TheVoid => &[],
+                Desert | DesertHills | DesertLakes => DESERT_MONSTERS,
+                SnowyTundra | SnowyMountains | IceSpikes => SNOWY_MONSTERS,
+                Ocean | DeepOcean | WarmOcean | LukewarmOcean | ColdOcean | DeepWarmOcean
+                | DeepLukewarmOcean | DeepColdOcean | DeepFrozenOcean | FrozenOcean | River
+                | FrozenRiver => OCEAN_MONSTERS,
+                NetherWastes => NETHER_MONSTERS,
+                CrimsonForest => CRIMSON_FOREST_MONSTERS,
+                WarpedForest => WARPED_FOREST_MONSTERS,
+                SoulSandValley => SOUL_SAND_VALLEY_MONSTERS,
+                BasaltDeltas => BASALT_DELTAS_MONSTERS,
+                TheEnd | SmallEndIslands | EndMidlands | EndHighlands | EndBarrens => END_MONSTERS,
+                _ => OVERWORLD_MONSTERS,
+            },
+            MobCategory::Creature => match self {
+                Plains | SunflowerPlains => PLAINS_CREATURES,
+                Forest | FlowerForest | WoodedHills | BirchForest | BirchForestHills
+                | TallBirchForest | TallBirchHills | DarkForest | DarkForestHills => {
+                    FOREST_CREATURES
+                }
+                Taiga
+                | TaigaHills
+                | TaigaMountains
+                | GiantTreeTaiga
+                | GiantTreeTaigaHills
+                | GiantSpruceTaiga
+                | GiantSpruceTaigaHills
+                | SnowyTaiga
+                | SnowyTaigaHills
+                | SnowyTaigaMountains => TAIGA_CREATURES,
+                Savanna | SavannaPlateau | ShatteredSavanna | ShatteredSavannaPlateau => {
+                    SAVANNA_CREATURES
+                }
+                Mountains
+                | MountainEdge
+                | WoodedMountains
+                | GravellyMountains
+                | ModifiedGravellyMountains => MOUNTAINS_CREATURES,
+                Jungle | JungleHills | JungleEdge | ModifiedJungle | ModifiedJungleEdge
+                | BambooJungle | BambooJungleHills => JUNGLE_CREATURES,
+                SnowyTundra | SnowyMountains | IceSpikes => SNOWY_CREATURES,
+                Desert | DesertHills | DesertLakes => DESERT_CREATURES,
+                MushroomFields | MushroomFieldShore => MUSHROOM_CREATURES,
+                Beach => BEACH_CREATURES,
+                Swamp
+                | SwampHills
+                | StoneShore
+                | SnowyBeach
+                | Badlands
+                | WoodedBadlandsPlateau
+                | BadlandsPlateau
+                | ErodedBadlands
+                | ModifiedWoodedBadlandsPlateau
+                | ModifiedBadlandsPlateau => FARM_ANIMALS,
+                _ => &[],
+            },
+            MobCategory::Ambient => match self {
+                NetherWastes | CrimsonForest | WarpedForest | SoulSandValley | BasaltDeltas
+                | TheEnd | SmallEndIslands | EndMidlands | EndHighlands | EndBarrens | TheVoid
+                | MushroomFields | MushroomFieldShore => &[],
+                _ => BATS,
+            },
+            MobCategory::Water => match self {
+                Ocean | DeepOcean | LukewarmOcean | DeepLukewarmOcean => OCEAN_WATER,
+                WarmOcean | DeepWarmOcean => WARM_OCEAN_WATER,
+                ColdOcean | DeepColdOcean => COLD_OCEAN_WATER,
+                FrozenOcean | DeepFrozenOcean => FROZEN_OCEAN_WATER,
+                River | FrozenRiver => RIVER_WATER,
+                _ => &[],
+            },
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn spawn_entries_match_categories() {
+        for id in 0.. {
+            let biome = match Biome::from_id(id) {
+                Some(biome) => biome,
+                None if id < 200 => continue,
+                None => break,
+            };
+            for &category in &MobCategory::ALL {
+                for entry in biome.spawn_entries(category) {
+                    assert_eq!(MobCategory::of(entry.kind), Some(category));
+                    assert!(entry.min_group <= entry.max_group);
+                    assert!(entry.weight > 0);
+                }
+            }
+        }
+    }
+}
@@ -0,0 +1,123 @@
+"""
+Generates the mapping files which let the server accept clients of versions
+other than the one its packets are modeled after.
+
+Packets, block states, items and entity types are matched by name, so that
+e.g. a block state of 1.16.5 maps to the state of the same block with the
+same property values in the mapped version.
+"""
+
+from itertools import product
+from json import dump
+
+from common import load_minecraft_json, LIBCRAFT_ROOT
+
+# The version feather's packets are modeled after
+INTERNAL_VERSION = "1.16.2"
+
+# Mapped versions and their protocol numbers
+VERSIONS = {
+    "1.17.1": 756,
+    "1.18.2": 758,
+    "1.19": 759,
+}
+
+OUTPUT_DIRECTORY = LIBCRAFT_ROOT / ".." / "feather" / "server" / "protocol-mappings"
+
+
+def packet_ids(version: str, direction: str) -> dict:
+    """Returns a dict mapping play packet names to their IDs."""
+    protocol = load_minecraft_json("protocol.json", version)
+    packet = protocol["play"][direction]["types"]["packet"]
+    mappings = packet[1][0]["type"][1]["mappings"]
+    return {name: int(id_, 16) for id_, name in mappings.items()}
+
+
+def property_values(state: dict) -> list:
+    values = state.get("values")
+    if values is None and state["type"] == "bool":
+        values = ["true", "false"]
+    return [str(value) for value in values]
+
+
+def block_states(version: str) -> dict:
+    """Returns a dict mapping block names to dicts mapping property values to state IDs."""
+    blocks = {}
+    for block in load_minecraft_json("blocks.json", version):
+        properties = [state["name"] for state in block["states"]]
+        values = [property_values(state) for state in block["states"]]
+
+        states = {}
+        # The last property varies fastest, as in the vanilla state ordering
+        for offset, combination in enumerate(product(*values)):
+            states[frozenset(zip(properties, combination))] = block["minStateId"] + offset
+        blocks[block["name"]] = (block["defaultState"], states)
+    return blocks
+
+
+def map_block_states(source: dict, target: dict) -> list:
+    """
+    Returns a list indexed by the state IDs of `source` containing the matching
+    state IDs of `target`. Properties which don't exist in `target` are ignored;
+    states of blocks which don't exist in `target` map to air.
+    """
+    result = [0] * (max(max(states.values()) for _, states in source.values()) + 1)
+    for name, (_, states) in source.items():
+        if name not in target:
+            continue
+        default_state, target_states = target[name]
+        target_properties = {property for key in target_states for property, _ in key}
+        for key, state in states.items():
+            wanted = {(property, value) for property, value in key if property in target_properties}
+            result[state] = next(
+                (target_state for target_key, target_state in target_states.items() if wanted <= target_key),
+                default_state,
+            )
+    return result
+
+
+def item_ids(version: str) -> dict:
+    return {item["name"]: item["id"] for item in load_minecraft_json("items.json", version)}
+
+
+def entity_ids(version: str) -> dict:
+    return {entity["name"]: entity["id"] for entity in load_minecraft_json("entities.json", version)}
+
+
+def map_ids(source: dict, target: dict, missing=0) -> list:
+    """Returns a list indexed by the IDs of `source` containing the IDs with the same name in `target`."""
+    result = [missing] * (max(source.values()) + 1)
+    for name, id_ in source.items():
+        result[id_] = target.get(name, missing)
+    return result
+
+
+internal_blocks = block_states(INTERNAL_VERSION)
+internal_items = item_ids(INTERNAL_VERSION)
+internal_entities = entity_ids(INTERNAL_VERSION)
+internal_clientbound = packet_ids(INTERNAL_VERSION, "toClient")
+internal_serverbound = packet_ids(INTERNAL_VERSION, "toServer")
+
+OUTPUT_DIRECTORY.mkdir(exist_ok=True)
+for version, protocol in VERSIONS.items():
+    blocks = block_states(version)
+    items = item_ids(version)
+    entities = entity_ids(version)
+
+    mappings = {
+        "protocol": protocol,
+        "clientbound_play": map_ids(internal_clientbound, packet_ids(version, "toClient"), None),
+        "serverbound_play": map_ids(packet_ids(version, "toServer"), internal_serverbound, None),
+        "block_states_to_mapped": map_block_states(internal_blocks, blocks),
+        "block_states_to_internal": map_block_states(blocks, internal_blocks),
+        "items_to_mapped": map_ids(internal_items, items),
+        "items_to_internal": map_ids(items, internal_items),
+        # Entity types which don't exist in the other version become pigs
+        "entities_to_mapped": map_ids(internal_entities, entities, entities["pig"]),
+        "entities_to_internal": map_ids(entities, internal_entities, internal_entities["pig"]),
+    }
+
+    path = OUTPUT_DIRECTORY / f"{version}.json"
+    with open(path, "w") as f:
+        dump(mappings, f)
+    print(f"Generated {path.name}")
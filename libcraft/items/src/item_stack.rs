@@ -332,6 +332,26 @@ impl ItemStack {
         self.meta.as_ref().map_or(Some(0), |meta| meta.damage)
     }
 
+    /// Repairs up to `amount` points of damage.
+    /// Returns the number of points actually repaired.
+    pub fn repair(&mut self, amount: i32) -> i32 {
+        match self.meta.as_mut().and_then(|meta| meta.damage.as_mut()) {
+            Some(damage) => {
+                let repaired = amount.min(*damage).max(0);
+                *damage -= repaired;
+                repaired
+            }
+            None => 0,
+        }
+    }
+
+    /// Returns the level of the given enchantment on this item,
+    /// or `None` if the item doesn't have the enchantment.
+    #[must_use]
+    pub fn enchantment_level(&self, ench: EnchantmentKind) -> Option<u32> {
+        self.meta.as_ref()?.get_enchantment_level(ench)
+    }
+
     /// Returns true is the contents of other could be merged with the contents
     /// of self. This does not look at the item count, just the kind.
     /// Items can be merged when they have the same kind, damage, and enchantment.
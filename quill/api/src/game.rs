@@ -229,6 +229,24 @@ impl Game {
             quill_sys::add_event(host_component, bytes.as_ptr().into(), bytes.len() as u32);
         }
     }
+
+    /// Sets the value of a gauge exported by the server's
+    /// metrics endpoint, registering the gauge if needed.
+    ///
+    /// `name` must be a valid Prometheus metric name, like
+    /// `myplugin_active_games`. Names starting with `feather_`
+    /// are reserved for the server.
+    pub fn set_gauge(&self, name: &str, help: &str, value: f64) {
+        unsafe {
+            quill_sys::metrics_set_gauge(
+                name.as_ptr().into(),
+                name.len() as u32,
+                help.as_ptr().into(),
+                help.len() as u32,
+                value,
+            )
+        }
+    }
 }
 
 fn check_y_bound(pos: BlockPosition) -> Result<(), BlockAccessError> {
@@ -206,6 +206,15 @@ host_component_enum! {
         FlyingAbilityEvent = 1028,
         BuildingAbilityEvent = 1029,
         InvulnerabilityEvent = 1030,
+        MobGoals = 1031,
+        ActiveEffects = 1032,
+        Experience = 1033,
+        PortalEnterEvent = 1034,
+        ServerListPingEvent = 1035,
+        Ping = 1036,
+        EntityDamageEvent = 1037,
+        EntityDeathEvent = 1038,
+        Persistent = 1039,
     }
 }
 
@@ -377,3 +386,7 @@ bincode_component_impl!(InstabreakEvent);
 bincode_component_impl!(FlyingAbilityEvent);
 bincode_component_impl!(BuildingAbilityEvent);
 bincode_component_impl!(InvulnerabilityEvent);
+bincode_component_impl!(PortalEnterEvent);
+bincode_component_impl!(ServerListPingEvent);
+bincode_component_impl!(EntityDamageEvent);
+bincode_component_impl!(EntityDeathEvent);
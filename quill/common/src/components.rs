@@ -3,12 +3,13 @@
 //! See the [entities module](crate::entities) for entity-specific
 //! components.
 
-use std::fmt::Display;
+use std::{fmt::Display, time::Duration};
 
+use bytemuck::{Pod, Zeroable};
 use serde::{Deserialize, Serialize};
 use smartstring::{LazyCompact, SmartString};
 
-use libcraft_core::Gamemode;
+use libcraft_core::{Gamemode, StatusEffect};
 
 /// Whether an entity is touching the ground.
 #[derive(
@@ -87,6 +88,16 @@ impl Display for CustomName {
     }
 }
 
+/// Marker component for entities which are never despawned
+/// automatically. Entities spawned by plugins get it.
+///
+/// Mobs with a [`CustomName`] are never despawned either.
+#[derive(Debug, Copy, Clone, Zeroable, Pod)]
+#[repr(C)]
+pub struct Persistent;
+
+pod_component_impl!(Persistent);
+
 /// A player's walk speed
 #[derive(
     Copy, Clone, Debug, PartialEq, Serialize, Deserialize, derive_more::Deref, derive_more::DerefMut,
@@ -284,3 +295,353 @@ impl Sprinting {
     }
 }
 bincode_component_impl!(Sprinting);
+
+/// A behavior that a mob can pursue, used in [`MobGoals`].
+///
+/// Distances are measured in blocks and speeds
+/// in blocks per tick.
+#[derive(Copy, Clone, Debug, PartialEq, Serialize, Deserialize)]
+pub enum Goal {
+    /// Walk to random nearby positions.
+    Wander { speed: f64 },
+    /// Turn to face the nearest player within `range`.
+    LookAtPlayer { range: f64 },
+    /// Walk towards the nearest player within `range`.
+    FollowPlayer { speed: f64, range: f64 },
+    /// Run away from the nearest player within `range`.
+    FleePlayer { speed: f64, range: f64 },
+    /// Chase the nearest player within `range` and hit
+    /// them when they are within `reach`.
+    MeleeAttack {
+        speed: f64,
+        range: f64,
+        reach: f64,
+        damage: f32,
+        cooldown_ticks: u32,
+    },
+    /// Attack the nearest player within `range` from a distance.
+    RangedAttack {
+        range: f64,
+        damage: f32,
+        cooldown_ticks: u32,
+    },
+}
+
+/// The AI goals of a mob.
+///
+/// Each tick, the mob pursues the goal with the highest
+/// priority (the lowest number) that is able to run.
+/// Goals with the same priority are tried in insertion order.
+///
+/// # Example
+/// Make a spawned cow follow players around:
+/// ```no_run
+/// use quill::{components::{Goal, MobGoals}, EntityInit, Game, Position};
+/// # let game: Game = unreachable!();
+/// # let position: Position = unreachable!();
+/// let goals = MobGoals::new()
+///     .with(0, Goal::FollowPlayer { speed: 0.15, range: 16.0 })
+///     .with(1, Goal::Wander { speed: 0.1 });
+/// game.create_entity_builder(position, EntityInit::Cow)
+///     .with(goals)
+///     .finish();
+/// ```
+#[derive(Clone, Debug, Default, PartialEq, Serialize, Deserialize)]
+pub struct MobGoals(Vec<(u8, Goal)>);
+
+bincode_component_impl!(MobGoals);
+
+impl MobGoals {
+    /// Creates an empty set of goals. A mob without
+    /// goals stands still.
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Adds a goal with the given priority.
+    pub fn add(&mut self, priority: u8, goal: Goal) -> &mut Self {
+        let index = self
+            .0
+            .iter()
+            .position(|(p, _)| *p > priority)
+            .unwrap_or(self.0.len());
+        self.0.insert(index, (priority, goal));
+        self
+    }
+
+    /// Adds a goal with the given priority and returns `self`
+    /// for method chaining.
+    pub fn with(mut self, priority: u8, goal: Goal) -> Self {
+        self.add(priority, goal);
+        self
+    }
+
+    /// Removes all goals.
+    pub fn clear(&mut self) {
+        self.0.clear();
+    }
+
+    /// Returns the goals ordered from highest to lowest priority.
+    pub fn iter(&self) -> impl Iterator<Item = Goal> + '_ {
+        self.0.iter().map(|(_, goal)| *goal)
+    }
+
+    /// Returns the goal at `index` in priority order.
+    pub fn get(&self, index: usize) -> Option<Goal> {
+        self.0.get(index).map(|(_, goal)| *goal)
+    }
+}
+
+/// A status effect applied to an entity, as
+/// stored in [`ActiveEffects`].
+#[derive(Copy, Clone, Debug, PartialEq, Eq, Serialize, Deserialize)]
+pub struct EffectInstance {
+    pub effect: StatusEffect,
+    /// The level of the effect minus one.
+    pub amplifier: u8,
+    /// Remaining duration in ticks.
+    pub duration: u32,
+    /// Whether the effect originates from a beacon or conduit.
+    pub ambient: bool,
+    pub show_particles: bool,
+    pub show_icon: bool,
+}
+
+impl EffectInstance {
+    /// Creates an effect with visible particles and icon.
+    pub fn new(effect: StatusEffect, amplifier: u8, duration: u32) -> Self {
+        Self {
+            effect,
+            amplifier,
+            duration,
+            ambient: false,
+            show_particles: true,
+            show_icon: true,
+        }
+    }
+}
+
+/// The status effects currently applied to an entity.
+///
+/// Effects are removed once their duration runs out. Adding
+/// this component to a player or mob applies its effects.
+///
+/// # Example
+/// Give a player ten seconds of speed II:
+/// ```no_run
+/// use quill::{components::{ActiveEffects, EffectInstance}, Entity, StatusEffect};
+/// # let player: Entity = unreachable!();
+/// let mut effects = player.get::<ActiveEffects>().unwrap_or_default();
+/// effects.add(EffectInstance::new(StatusEffect::Speed, 1, 200));
+/// player.insert(effects);
+/// ```
+#[derive(Clone, Debug, Default, PartialEq, Serialize, Deserialize)]
+pub struct ActiveEffects(Vec<EffectInstance>);
+
+bincode_component_impl!(ActiveEffects);
+
+impl ActiveEffects {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Applies an effect.
+    ///
+    /// If the entity already has the effect, it is only replaced if
+    /// the new effect has a higher amplifier, or the same amplifier
+    /// and a longer duration. Returns whether the effect was applied.
+    pub fn add(&mut self, instance: EffectInstance) -> bool {
+        match self.0.iter_mut().find(|e| e.effect == instance.effect) {
+            Some(existing) => {
+                let stronger = instance.amplifier > existing.amplifier;
+                let longer = instance.amplifier == existing.amplifier
+                    && instance.duration > existing.duration;
+                if stronger || longer {
+                    *existing = instance;
+                    true
+                } else {
+                    false
+                }
+            }
+            None => {
+                self.0.push(instance);
+                true
+            }
+        }
+    }
+
+    /// Removes an effect, returning it if it was applied.
+    pub fn remove(&mut self, effect: StatusEffect) -> Option<EffectInstance> {
+        let index = self.0.iter().position(|e| e.effect == effect)?;
+        Some(self.0.remove(index))
+    }
+
+    /// Removes all effects.
+    pub fn clear(&mut self) {
+        self.0.clear();
+    }
+
+    pub fn get(&self, effect: StatusEffect) -> Option<&EffectInstance> {
+        self.0.iter().find(|e| e.effect == effect)
+    }
+
+    pub fn get_mut(&mut self, effect: StatusEffect) -> Option<&mut EffectInstance> {
+        self.0.iter_mut().find(|e| e.effect == effect)
+    }
+
+    pub fn contains(&self, effect: StatusEffect) -> bool {
+        self.get(effect).is_some()
+    }
+
+    /// Returns the level (amplifier plus one) of the given effect,
+    /// or zero if it is not applied.
+    pub fn level(&self, effect: StatusEffect) -> u32 {
+        self.get(effect).map_or(0, |e| e.amplifier as u32 + 1)
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.0.is_empty()
+    }
+
+    pub fn iter(&self) -> impl Iterator<Item = &EffectInstance> + '_ {
+        self.0.iter()
+    }
+
+    pub fn iter_mut(&mut self) -> impl Iterator<Item = &mut EffectInstance> + '_ {
+        self.0.iter_mut()
+    }
+
+    /// Removes all effects for which `f` returns `false`.
+    pub fn retain(&mut self, f: impl FnMut(&EffectInstance) -> bool) {
+        self.0.retain(f);
+    }
+}
+
+impl std::iter::FromIterator<EffectInstance> for ActiveEffects {
+    fn from_iter<I: IntoIterator<Item = EffectInstance>>(iter: I) -> Self {
+        let mut effects = Self::new();
+        for instance in iter {
+            effects.add(instance);
+        }
+        effects
+    }
+}
+
+/// A player's experience.
+///
+/// `level` and `progress` are what the client shows on the
+/// experience bar. `total` counts every point collected and is
+/// used to compute the score shown on the death screen.
+#[derive(Copy, Clone, Debug, Default, PartialEq, Serialize, Deserialize)]
+pub struct Experience {
+    pub level: u32,
+    /// Progress towards the next level, from 0 to 1.
+    pub progress: f32,
+    pub total: u32,
+}
+
+bincode_component_impl!(Experience);
+
+impl Experience {
+    /// Returns the number of points needed to advance
+    /// from `level` to the next level.
+    pub fn points_to_next_level(level: u32) -> u32 {
+        match level {
+            0..=15 => 2 * level + 7,
+            16..=30 => 5 * level - 38,
+            _ => 9 * level - 158,
+        }
+    }
+
+    /// Returns the total number of points needed
+    /// to reach `level` from level 0.
+    pub fn points_for_level(level: u32) -> u32 {
+        let level = level as f64;
+        let points = if level <= 16.0 {
+            level * level + 6.0 * level
+        } else if level <= 31.0 {
+            2.5 * level * level - 40.5 * level + 360.0
+        } else {
+            4.5 * level * level - 162.5 * level + 2220.0
+        };
+        points as u32
+    }
+
+    /// Returns the number of points collected towards the next level.
+    pub fn points_in_level(&self) -> u32 {
+        (self.progress * Self::points_to_next_level(self.level) as f32).round() as u32
+    }
+
+    /// Adds experience points, advancing levels as needed.
+    pub fn add_points(&mut self, points: u32) {
+        self.total = self.total.saturating_add(points);
+
+        let mut points = self.points_in_level() as u64 + points as u64;
+        loop {
+            let needed = Self::points_to_next_level(self.level) as u64;
+            if points < needed {
+                break;
+            }
+            points -= needed;
+            self.level += 1;
+        }
+        self.progress = points as f32 / Self::points_to_next_level(self.level) as f32;
+    }
+
+    /// Removes `levels` levels, keeping the progress
+    /// towards the next level.
+    ///
+    /// Returns `false` without changing anything if
+    /// there are not enough levels.
+    pub fn remove_levels(&mut self, levels: u32) -> bool {
+        match self.level.checked_sub(levels) {
+            Some(level) => {
+                self.level = level;
+                true
+            }
+            None => false,
+        }
+    }
+
+    /// Takes `cost` levels for an enchanting table or anvil operation
+    /// which requires at least `required` levels.
+    ///
+    /// Players in creative mode are never charged. Returns
+    /// `false` if the operation can't be afforded.
+    pub fn charge_levels(&mut self, gamemode: Gamemode, required: u32, cost: u32) -> bool {
+        if gamemode == Gamemode::Creative {
+            return true;
+        }
+        if self.level < required.max(cost) {
+            return false;
+        }
+        self.remove_levels(cost)
+    }
+}
+
+/// A player's connection latency, measured with keepalives.
+///
+/// The latency is smoothed like in vanilla, so that
+/// a single slow keepalive doesn't cause a spike.
+#[derive(Copy, Clone, Debug, Default, PartialEq, Eq, Serialize, Deserialize)]
+pub struct Ping {
+    /// Smoothed round-trip time in milliseconds.
+    pub latency: u32,
+    /// Number of keepalives measured so far.
+    pub samples: u32,
+}
+
+bincode_component_impl!(Ping);
+
+impl Ping {
+    /// Adds the round-trip time of a keepalive.
+    pub fn record(&mut self, rtt: Duration) {
+        let rtt = rtt.as_millis().min(u32::MAX as u128) as u32;
+        self.latency = if self.samples == 0 {
+            rtt
+        } else {
+            ((self.latency as u64 * 3 + rtt as u64) / 4) as u32
+        };
+        self.samples = self.samples.saturating_add(1);
+    }
+}
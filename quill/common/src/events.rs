@@ -3,10 +3,16 @@ pub use change::{
     BuildingAbilityEvent, CreativeFlyingEvent, FlyingAbilityEvent, GamemodeEvent, InstabreakEvent,
     InvulnerabilityEvent, SneakEvent, SprintEvent,
 };
-pub use entity::{EntityCreateEvent, EntityRemoveEvent, PlayerJoinEvent};
+pub use entity::{
+    EntityCreateEvent, EntityDamageEvent, EntityDeathEvent, EntityRemoveEvent, PlayerJoinEvent,
+};
 pub use interact_entity::InteractEntityEvent;
+pub use portal::{PortalEnterEvent, PortalKind};
+pub use status::{PlayerSample, ServerListPingEvent};
 
 mod block_interact;
 mod change;
 mod entity;
 mod interact_entity;
+mod portal;
+mod status;
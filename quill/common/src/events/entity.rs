@@ -14,3 +14,30 @@ pub struct EntityRemoveEvent;
 /// Triggered when an entity is added into the world.
 #[derive(Debug, Serialize, Deserialize, Clone)]
 pub struct EntityCreateEvent;
+
+/// Triggered when an entity is hurt.
+///
+/// The damage is subtracted from the entity's `Health` on the
+/// next tick. To change or cancel it, insert the event again
+/// with a different `damage` or with `cancelled` set.
+#[derive(Debug, Serialize, Deserialize, Clone)]
+pub struct EntityDamageEvent {
+    pub damage: f32,
+    pub cancelled: bool,
+}
+
+impl EntityDamageEvent {
+    pub fn new(damage: f32) -> Self {
+        Self {
+            damage,
+            cancelled: false,
+        }
+    }
+}
+
+/// Triggered when an entity's `Health` drops to zero.
+///
+/// Mobs are removed on the next tick, while players
+/// stay dead until they respawn.
+#[derive(Debug, Serialize, Deserialize, Clone)]
+pub struct EntityDeathEvent;
@@ -0,0 +1,21 @@
+use serde::{Deserialize, Serialize};
+
+/// The kind of a portal.
+#[derive(Copy, Clone, Debug, PartialEq, Eq, Hash, Serialize, Deserialize)]
+pub enum PortalKind {
+    /// A nether portal, linking the overworld and the nether.
+    Nether,
+    /// An end portal, leading to the end and back.
+    End,
+}
+
+/// Triggered when an entity has stood in a portal long
+/// enough to be teleported through it.
+///
+/// The entity is teleported on the next tick. To cancel the
+/// teleport, insert the event again with `cancelled` set.
+#[derive(Debug, Serialize, Deserialize, Clone)]
+pub struct PortalEnterEvent {
+    pub portal: PortalKind,
+    pub cancelled: bool,
+}
@@ -0,0 +1,36 @@
+use std::net::SocketAddr;
+
+use serde::{Deserialize, Serialize};
+use uuid::Uuid;
+
+/// Triggered when a client pings the server from its server list.
+///
+/// The event is triggered on an entity of its own and the response
+/// is sent on the next tick. To change the response, insert
+/// the event again with the fields changed.
+#[derive(Debug, Serialize, Deserialize, Clone)]
+pub struct ServerListPingEvent {
+    /// Address of the pinging client.
+    pub address: SocketAddr,
+    /// Message of the day, shown below the server name.
+    pub motd: String,
+    /// Name of the server version, shown to clients with
+    /// a different protocol version.
+    pub version_name: String,
+    /// Protocol version of the server.
+    pub protocol_version: i32,
+    pub online_players: u32,
+    pub max_players: u32,
+    /// Players listed when hovering over the player count.
+    pub sample: Vec<PlayerSample>,
+    /// The server icon as a `data:image/png;base64,` URL
+    /// of a 64x64 PNG image.
+    pub favicon: Option<String>,
+}
+
+/// A player listed in a [`ServerListPingEvent`].
+#[derive(Debug, Serialize, Deserialize, Clone, PartialEq, Eq)]
+pub struct PlayerSample {
+    pub name: String,
+    pub id: Uuid,
+}
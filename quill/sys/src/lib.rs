@@ -193,4 +193,17 @@ extern "C" {
         data_ptr: Pointer<u8>,
         data_len: u32,
     );
+
+    /// Sets the value of a gauge exported by the server's
+    /// metrics endpoint, registering the gauge if needed.
+    ///
+    /// Does nothing if the name is not a valid
+    /// metric name or starts with `feather_`.
+    pub fn metrics_set_gauge(
+        name_ptr: Pointer<u8>,
+        name_len: u32,
+        help_ptr: Pointer<u8>,
+        help_len: u32,
+        value: f64,
+    );
 }